@@ -36,9 +36,15 @@ async fn main() {
 
     info!("Starting EVM ABI fetcher");
 
-    let db = EVMDatabase::new(config.db_url, config.redis_url.clone(), ETHEREUM)
-        .await
-        .expect("Unable to start DB connection.");
+    let db = EVMDatabase::new(
+        config.db_url,
+        Vec::new(),
+        config.db_schema.clone(),
+        config.redis_url.clone(),
+        ETHEREUM,
+    )
+    .await
+    .expect("Unable to start DB connection.");
 
     loop {
         let contracts = db.get_contracts().await.unwrap();
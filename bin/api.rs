@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use dotenv::dotenv;
+use evm_indexer::{
+    api::{
+        auth::ApiKeys,
+        grpc::{IndexerGrpcService, IndexerServer},
+        lists::ListsService,
+        pagination::PageLimits,
+        websocket::start_websocket_server,
+    },
+    chains::chains::ETHEREUM,
+    configs::api_config::EVMApiConfig,
+    db::db::EVMDatabase,
+};
+use log::*;
+use simple_logger::SimpleLogger;
+use tonic::transport::Server;
+
+#[tokio::main()]
+async fn main() {
+    dotenv().ok();
+
+    let log = SimpleLogger::new().with_level(LevelFilter::Info);
+
+    let config = EVMApiConfig::new();
+
+    if config.debug {
+        log.with_level(LevelFilter::Debug).init().unwrap();
+    } else {
+        log.init().unwrap();
+    }
+
+    info!("Starting EVM API.");
+
+    let db = EVMDatabase::new(
+        config.db_url.clone(),
+        config.db_replica_urls.clone(),
+        config.db_schema.clone(),
+        config.redis_url.clone(),
+        ETHEREUM,
+    )
+    .await
+    .expect("Unable to start DB connection.");
+
+    let api_keys = match config.auth {
+        true => {
+            let api_keys =
+                ApiKeys::new(db.clone(), &config.api_keys).expect("Unable to load API keys.");
+
+            api_keys.start_usage_sync();
+
+            Some(api_keys)
+        }
+        false => None,
+    };
+
+    let lists = ListsService::new(
+        db.clone(),
+        PageLimits {
+            default_page_size: config.page_size,
+            max_page_size: config.max_page_size,
+        },
+    );
+
+    let _ws_handle =
+        start_websocket_server(db.redis.clone(), lists, api_keys.clone(), config.ws_port)
+            .await
+            .expect("Unable to start WebSocket server.");
+
+    let grpc_service = IndexerGrpcService::new(
+        db,
+        config.batch_size,
+        Duration::from_secs(config.poll_interval),
+        config.ens_names,
+    );
+
+    let address = format!("0.0.0.0:{}", config.grpc_port)
+        .parse()
+        .expect("Unable to parse gRPC address.");
+
+    info!("Serving gRPC API on {}.", address);
+
+    let router = match api_keys {
+        Some(api_keys) => {
+            Server::builder().add_service(IndexerServer::with_interceptor(grpc_service, api_keys))
+        }
+        None => Server::builder().add_service(IndexerServer::new(grpc_service)),
+    };
+
+    router
+        .serve(address)
+        .await
+        .expect("Unable to serve gRPC API.");
+}
@@ -0,0 +1,66 @@
+use dotenv::dotenv;
+use evm_indexer::{
+    configs::graph_export_config::EVMGraphExportConfig,
+    db::db::EVMDatabase,
+    exports::graph::TransferGraphExporter,
+    rpc::dates::{parse_date, parse_end_date},
+};
+use log::*;
+use simple_logger::SimpleLogger;
+
+#[tokio::main()]
+async fn main() {
+    dotenv().ok();
+
+    let log = SimpleLogger::new().with_level(LevelFilter::Info);
+
+    let mut config = EVMGraphExportConfig::new();
+
+    if config.debug {
+        log.with_level(LevelFilter::Debug).init().unwrap();
+    } else {
+        log.init().unwrap();
+    }
+
+    let db = EVMDatabase::new(
+        config.db_url.clone(),
+        config.db_replica_urls.clone(),
+        config.db_schema.clone(),
+        config.redis_url.clone(),
+        config.chain.clone(),
+    )
+    .await
+    .expect("Unable to start DB connection.");
+
+    // The exported transfers are indexed, so the dates are searched in the indexed blocks.
+    if let Some(date) = &config.from_date {
+        let timestamp = parse_date(date).expect("Unable to parse the from date.");
+
+        config.from_block = db
+            .get_first_block_at(timestamp)
+            .await
+            .expect("Unable to find the first block of the from date.");
+    }
+
+    if let Some(date) = &config.to_date {
+        let timestamp = parse_end_date(date).expect("Unable to parse the to date.");
+
+        config.to_block = db
+            .get_first_block_at(timestamp)
+            .await
+            .expect("Unable to find the last block of the to date.")
+            - 1;
+    }
+
+    info!(
+        "Exporting the transfer graph of chain {} from block {} to block {}.",
+        config.chain.name, config.from_block, config.to_block
+    );
+
+    let exporter = TransferGraphExporter::new(db, config);
+
+    exporter
+        .export()
+        .await
+        .expect("Unable to export the transfer graph.");
+}
@@ -1,39 +1,551 @@
-use std::{thread::sleep, time::Duration};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use dotenv::dotenv;
+#[cfg(feature = "api")]
+use evm_indexer::admin::server::AdminServer;
+#[cfg(feature = "traces")]
+use evm_indexer::traces::{
+    call_frames::CallTreeIndexer, native_transfers::NativeTransferIndexer,
+    revert_reasons::RevertReasonIndexer, state_diffs::StateDiffIndexer,
+};
 use evm_indexer::{
+    admin::control::SyncControl,
+    alerts::{engine::AlertsEngine, rules::load_alert_rules},
+    api::events::{notify_events, publish_events, IndexedEvent},
+    archive::{archive::BlockArchive, redecode::ArchiveRedecoder},
+    audit::{audit::ChainAuditor, balances::BalanceAuditor},
+    calls::sampler::{load_view_calls, CallSampler},
     chains::chains::Chain,
-    configs::indexer_config::EVMIndexerConfig,
+    clustering::{
+        clustering::AddressClusterer, deposits::ExchangeDepositsDetector,
+        labels::import_address_labels,
+    },
+    configs::{
+        indexer_config::{EVMIndexerCommand, EVMIndexerConfig},
+        reload::FileWatcher,
+    },
+    dashboard::dashboard::SyncDashboard,
     db::{
+        backfill::{
+            enqueue_backfill_job, fail_backfill_job, get_next_backfill_job,
+            update_backfill_job_status, DONE, RUNNING,
+        },
+        buffer::IndexedDataBuffer,
         db::EVMDatabase,
+        embedded::{is_embedded_url, open_embedded_database, EmbeddedDatabase},
+        lock::{ChainLock, LOCK_TTL},
         models::models::{
             DatabaseChainIndexedState, DatabaseEVMBlock, DatabaseEVMContract,
-            DatabaseEVMTransaction, DatabaseEVMTransactionLog, DatabaseEVMTransactionReceipt,
+            DatabaseEVMOutboxEvent, DatabaseEVMParseFailure, DatabaseEVMTransaction,
+            DatabaseEVMTransactionLog, DatabaseEVMTransactionReceipt,
+        },
+        reset::ChainReset,
+    },
+    exports::{
+        holders::{read_holders, write_holders, HolderSnapshotter},
+        merkle::get_airdrop_tree,
+    },
+    lake::writer::LakeWriter,
+    metrics::{
+        block_times::{get_timestamp_ms, store_block_time, DatabaseEVMBlockTime},
+        fee_history::FeeHistoryWorker,
+        rpc_usage::store_rpc_usage,
+        sync_lag::SyncLagMonitor,
+        table_stats::sample_table_stats,
+        telemetry::init_telemetry,
+    },
+    parsers::erc20_transfers_parser::{
+        emit_transfer_events, get_transfers, DatabaseEVMErc20Transfer,
+        PARSER_NAME as ERC20_TRANSFERS_PARSER,
+    },
+    query::query::run_query,
+    rpc::{
+        autoscale::BatchSizeController,
+        dates::{get_block_range, parse_date},
+        rpc::EVMRpc,
+        usage::log_usage_summary,
+    },
+    screening::screening::AddressScreener,
+    signatures::importer::import_signatures,
+    stablecoins::monitor::{load_stablecoins, StablecoinMonitor},
+    storage::{
+        proofs::{load_proof_accounts, ProofArchiver},
+        watcher::{load_storage_slots, StorageWatcher},
+    },
+    tokens::{
+        lists::import_token_lists,
+        nonstandard::{
+            get_audited_tokens, load_nonstandard_tokens, load_watched_holders,
+            store_nonstandard_tokens, BalanceSampler, AUDIT_SOURCE, LIST_SOURCE,
         },
     },
-    rpc::rpc::EVMRpc,
 };
-use futures::{future::join_all, StreamExt};
+use futures::{future::join_all, stream, StreamExt};
 use log::*;
 use simple_logger::SimpleLogger;
+use tokio::time::sleep;
 use web3::{transports::WebSocket, Web3};
 
 #[tokio::main()]
 async fn main() {
     dotenv().ok();
 
-    let log = SimpleLogger::new().with_level(LevelFilter::Info);
-
     let mut config = EVMIndexerConfig::new();
 
-    if config.debug {
-        log.with_level(LevelFilter::Debug).init().unwrap();
-    } else {
-        log.init().unwrap();
+    // The logger lets everything through and the level is set with the max level, so the admin
+    // endpoints can change it at runtime in both directions.
+    SimpleLogger::new()
+        .with_level(LevelFilter::Trace)
+        .init()
+        .unwrap();
+
+    // Subcommands print their output, so only warnings are logged.
+    log::set_max_level(match (&config.command, config.tui, config.debug) {
+        (Some(_), _, _) => LevelFilter::Warn,
+        (None, true, _) => LevelFilter::Off,
+        (None, false, true) => LevelFilter::Debug,
+        (None, false, false) => LevelFilter::Info,
+    });
+
+    match &config.command {
+        Some(EVMIndexerCommand::Query { query, format }) => {
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            match run_query(&db, query, *format) {
+                Ok(_) => std::process::exit(0),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::Audit {
+            from,
+            to,
+            from_date,
+            to_date,
+            repair,
+        }) => {
+            let (from, to) = get_command_range(&config, from, to, from_date, to_date).await;
+
+            if config.rpcs.iter().all(|rpc| rpc.is_empty()) {
+                eprintln!("The audit requires the rpcs to fetch the blocks from");
+                std::process::exit(1)
+            }
+
+            let rpc = EVMRpc::new(&config)
+                .await
+                .expect("Unable to start RPC client.");
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.")
+            .with_payload_compression(config.compress_payloads)
+            .with_upsert_policies(config.upsert_policies.clone());
+
+            let auditor = ChainAuditor::new(rpc, db, config.batch_size);
+
+            match auditor.audit(from, to, *repair).await {
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&report).expect("Unable to print report.")
+                    );
+
+                    // Unrepaired discrepancies fail the command, so audits can run as checks.
+                    if report.discrepancies.len() > 0 && !*repair {
+                        std::process::exit(1)
+                    }
+
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::AuditBalances {
+            block,
+            date,
+            tokens,
+            samples,
+            window,
+            mark,
+        }) => {
+            let (_, block) = get_command_range(&config, block, block, date, date).await;
+
+            if config.rpcs.iter().all(|rpc| rpc.is_empty()) {
+                eprintln!("The balances audit requires the rpcs to call balanceOf");
+                std::process::exit(1)
+            }
+
+            let rpc = EVMRpc::new(&config)
+                .await
+                .expect("Unable to start RPC client.");
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            let tokens = tokens.as_ref().map(|tokens| {
+                tokens
+                    .split(",")
+                    .map(|token| token.trim().to_string())
+                    .filter(|token| !token.is_empty())
+                    .collect()
+            });
+
+            let auditor = BalanceAuditor::new(rpc, db.clone());
+
+            match auditor.audit(block, tokens, *samples, *window).await {
+                Ok(report) => {
+                    if *mark {
+                        let tokens = get_audited_tokens(&report);
+
+                        match store_nonstandard_tokens(&db, &tokens, AUDIT_SOURCE) {
+                            Ok(marked) => info!("Marked {} nonstandard tokens.", marked),
+                            Err(err) => warn!("Unable to mark the nonstandard tokens: {}", err),
+                        }
+                    }
+
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&report).expect("Unable to print report.")
+                    );
+
+                    if report.discrepancies.len() > 0 {
+                        std::process::exit(1)
+                    }
+
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::Backfill {
+            from,
+            to,
+            from_date,
+            to_date,
+            priority,
+        }) => {
+            let (from, to) = get_command_range(&config, from, to, from_date, to_date).await;
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            match enqueue_backfill_job(&db, from, to, *priority) {
+                Ok(job) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&job).expect("Unable to print job.")
+                    );
+
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("Unable to queue the backfill: {}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::Redecode {
+            from,
+            to,
+            from_date,
+            to_date,
+            replace,
+        }) => {
+            let (from, to) = get_command_range(&config, from, to, from_date, to_date).await;
+
+            let url = match &config.archive_url {
+                Some(url) => url,
+                None => {
+                    eprintln!("The redecode requires the archive url to read the blocks from");
+                    std::process::exit(1)
+                }
+            };
+
+            let archive = BlockArchive::new(url, config.chain.name, config.archive_range)
+                .expect("Unable to open raw blocks archive.");
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.")
+            .with_payload_compression(config.compress_payloads)
+            .with_upsert_policies(config.upsert_policies.clone());
+
+            let redecoder = ArchiveRedecoder::new(archive, db);
+
+            match redecoder.redecode(from, to, *replace).await {
+                Ok(summary) => {
+                    println!(
+                        "Decoded {} blocks from {} archived objects.",
+                        summary.blocks, summary.objects
+                    );
+
+                    if summary.invalid_blocks.len() > 0 {
+                        println!("Unable to decode blocks {:?}.", summary.invalid_blocks);
+                        std::process::exit(1)
+                    }
+
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::SampleCalls {
+            from,
+            to,
+            from_date,
+            to_date,
+        }) => {
+            let (from, to) = get_command_range(&config, from, to, from_date, to_date).await;
+
+            let calls = match &config.view_calls {
+                Some(path) => load_view_calls(path),
+                None => {
+                    eprintln!("The sampling requires the view calls to make");
+                    std::process::exit(1)
+                }
+            };
+
+            if config.rpcs.iter().all(|rpc| rpc.is_empty()) {
+                eprintln!("The sampling requires the rpcs to make the calls with");
+                std::process::exit(1)
+            }
+
+            let rpc = EVMRpc::new(&config)
+                .await
+                .expect("Unable to start RPC client.");
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            let sampler = CallSampler::new(config.chain.name, calls);
+
+            match sampler
+                .backfill(&rpc, &db, from, to, config.batch_size)
+                .await
+            {
+                Ok(samples) => {
+                    println!("Stored {} view call samples.", samples);
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::Cluster {
+            from,
+            to,
+            from_date,
+            to_date,
+        }) => {
+            let (from, to) = get_command_range(&config, from, to, from_date, to_date).await;
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            let clusterer = AddressClusterer::new(db, config.batch_size);
+
+            match clusterer.cluster(from, to) {
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&report).expect("Unable to print report.")
+                    );
+
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::ExchangeDeposits {
+            from,
+            to,
+            from_date,
+            to_date,
+            labels,
+        }) => {
+            let (from, to) = get_command_range(&config, from, to, from_date, to_date).await;
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            match labels {
+                Some(path) => match import_address_labels(&db, path) {
+                    Ok(_) => (),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(1)
+                    }
+                },
+                None => (),
+            }
+
+            let detector = ExchangeDepositsDetector::new(db, config.batch_size);
+
+            match detector.detect(from, to) {
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&report).expect("Unable to print report.")
+                    );
+
+                    std::process::exit(0)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::HolderSnapshot {
+            token,
+            standard,
+            block,
+            date,
+            format,
+            output,
+        }) => {
+            // The snapshot of a date is taken at its last block.
+            let (_, block) = get_command_range(&config, block, block, date, date).await;
+
+            let db = EVMDatabase::new(
+                config.db_url.clone(),
+                config.db_replica_urls.clone(),
+                config.db_schema.clone(),
+                config.redis_url.clone(),
+                config.chain.clone(),
+            )
+            .await
+            .expect("Unable to start DB connection.");
+
+            let snapshotter = HolderSnapshotter::new(db);
+
+            match snapshotter.snapshot(token, *standard, block) {
+                Ok(holders) => match write_holders(&holders, *format, output) {
+                    Ok(_) => std::process::exit(0),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(1)
+                    }
+                },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(EVMIndexerCommand::MerkleAirdrop { snapshot, output }) => {
+            let tree = match read_holders(snapshot).and_then(|holders| get_airdrop_tree(&holders)) {
+                Ok(tree) => tree,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            };
+
+            let tree =
+                serde_json::to_string_pretty(&tree).expect("Unable to print the airdrop tree.");
+
+            match output {
+                Some(path) => match std::fs::write(path, tree) {
+                    Ok(_) => std::process::exit(0),
+                    Err(err) => {
+                        eprintln!("Unable to write the airdrop tree: {}", err);
+                        std::process::exit(1)
+                    }
+                },
+                None => {
+                    println!("{}", tree);
+                    std::process::exit(0)
+                }
+            }
+        }
+        None => (),
     }
 
     info!("Starting EVM Indexer.");
 
+    match &config.otlp_endpoint {
+        Some(endpoint) => {
+            init_telemetry("evm-indexer", endpoint).expect("Unable to start telemetry.")
+        }
+        None => (),
+    }
+
     if !config.reset {
         info!("Syncing chain {}.", config.chain.name.clone());
     }
@@ -42,45 +554,540 @@ async fn main() {
         .await
         .expect("Unable to start RPC client.");
 
+    if let Some(date) = &config.from_date {
+        let timestamp = parse_date(date).expect("Unable to parse the from date.");
+
+        config.start_block = rpc
+            .get_first_block_at(timestamp)
+            .await
+            .expect("Unable to find the first block of the from date.");
+
+        info!(
+            "Syncing from block {} for date {}.",
+            config.start_block, date
+        );
+    }
+
+    let lake = match &config.lake_url {
+        Some(url) => Some(
+            LakeWriter::new(url, config.chain.name, config.lake_range)
+                .expect("Unable to open the lake tables."),
+        ),
+        None => None,
+    };
+
+    tokio::spawn({
+        let rpc = rpc.clone();
+        let lake = lake.clone();
+
+        async move {
+            wait_for_shutdown().await;
+
+            flush_writers(&rpc, &lake).await;
+
+            log_usage_summary(&rpc.stats.get_usage());
+
+            std::process::exit(0)
+        }
+    });
+
+    if is_embedded_url(&config.db_url) {
+        let db = open_embedded_database(&config.db_url, config.chain.clone())
+            .expect("Unable to open the embedded database.");
+
+        sync_embedded(&rpc, &config, db.as_ref()).await;
+        return;
+    }
+
     let db = EVMDatabase::new(
         config.db_url.clone(),
+        config.db_replica_urls.clone(),
+        config.db_schema.clone(),
         config.redis_url.clone(),
         config.chain.clone(),
     )
     .await
-    .expect("Unable to start DB connection.");
+    .expect("Unable to start DB connection.")
+    .with_payload_compression(config.compress_payloads)
+    .with_upsert_policies(config.upsert_policies.clone())
+    .with_new_addresses(config.new_addresses, config.outbox);
+
+    let lock = match config.distributed {
+        true => None,
+        false => Some(ChainLock::new(db.clone())),
+    };
+
+    if let Some(lock) = lock.clone() {
+        match lock.acquire().await {
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!(
+                    "{}, use --distributed to let several indexers write it",
+                    err
+                );
+                std::process::exit(1)
+            }
+        }
+
+        tokio::spawn(async move {
+            loop {
+                sleep(Duration::from_secs(LOCK_TTL / 3)).await;
+
+                match lock.renew().await {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        error!(
+                            "Lost the lock of chain {}, stopping to avoid concurrent writes.",
+                            lock.db.chain.name
+                        );
+                        std::process::exit(1)
+                    }
+                    Err(err) => warn!("Unable to renew the chain lock: {}", err),
+                }
+            }
+        });
+    }
+
+    if config.reset_range.is_some() || config.reset_table.is_some() || config.reset_parser.is_some()
+    {
+        let reset = ChainReset::new(db.clone(), config.reset_range);
+
+        let result = match (&config.reset_table, &config.reset_parser) {
+            (Some(table), _) => reset.reset_table(table).await,
+            (None, Some(parser)) => reset.reset_parser(parser).await,
+            (None, None) => reset.reset_blocks().await,
+        };
+
+        match result {
+            Ok(summary) => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&summary).expect("Unable to print summary.")
+                );
+
+                std::process::exit(0)
+            }
+            Err(err) => {
+                eprintln!("Unable to reset chain {}: {}", config.chain.name, err);
+                std::process::exit(1)
+            }
+        }
+    }
+
+    if config.signatures.len() > 0 {
+        match import_signatures(&db, &config.signatures) {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to import the known signatures: {}", err),
+        }
+    }
+
+    if config.token_lists.len() > 0 {
+        match import_token_lists(&db, &config.token_lists).await {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to import the token lists: {}", err),
+        }
+    }
+
+    match store_nonstandard_tokens(
+        &db,
+        &load_nonstandard_tokens(&config.nonstandard_tokens),
+        LIST_SOURCE,
+    ) {
+        Ok(_) => (),
+        Err(err) => warn!("Unable to mark the nonstandard tokens: {}", err),
+    }
+
+    let alerts = match &config.alert_rules {
+        Some(path) => Some(AlertsEngine::new(load_alert_rules(path))),
+        None => None,
+    };
+
+    let screener = match &config.screening_list {
+        Some(path) => Some(AddressScreener::load(
+            path,
+            config.screening_webhook.clone(),
+        )),
+        None => None,
+    };
+
+    let storage = match &config.storage_slots {
+        Some(path) => Some(StorageWatcher::new(
+            config.chain.name,
+            load_storage_slots(path),
+        )),
+        None => None,
+    };
+
+    let proofs = match &config.proof_accounts {
+        Some(path) => Some(ProofArchiver::new(
+            config.chain.name,
+            load_proof_accounts(path),
+        )),
+        None => None,
+    };
+
+    let calls = match &config.view_calls {
+        Some(path) => Some(CallSampler::new(config.chain.name, load_view_calls(path))),
+        None => None,
+    };
+
+    let balances = match &config.watched_holders {
+        Some(path) => Some(BalanceSampler::new(
+            load_watched_holders(path),
+            config.balance_sampling_interval,
+        )),
+        None => None,
+    };
+
+    #[cfg(feature = "traces")]
+    let state_diffs = match config.state_diffs {
+        true => Some(StateDiffIndexer {}),
+        false => None,
+    };
+
+    #[cfg(feature = "traces")]
+    let call_trees = match config.trace_contracts.len() > 0 || config.trace_flagged {
+        true => Some(CallTreeIndexer::new(
+            &config.trace_contracts,
+            config.trace_flagged,
+        )),
+        false => None,
+    };
+
+    #[cfg(feature = "traces")]
+    let native_transfers = match config.native_transfers {
+        true => Some(NativeTransferIndexer {}),
+        false => None,
+    };
+
+    #[cfg(feature = "traces")]
+    let revert_reasons = match config.revert_reasons {
+        true => Some(RevertReasonIndexer {}),
+        false => None,
+    };
+
+    #[cfg(not(feature = "traces"))]
+    if config.state_diffs
+        || config.trace_contracts.len() > 0
+        || config.trace_flagged
+        || config.native_transfers
+        || config.revert_reasons
+    {
+        eprintln!("The indexer must be built with the traces feature to index traces");
+        std::process::exit(1)
+    }
+
+    let stablecoins = match config.stablecoins {
+        true => Some(StablecoinMonitor::new(
+            config.chain.name,
+            load_stablecoins(&config.stablecoin_list),
+        )),
+        false => None,
+    };
+
+    let hooks: SharedHooks = Arc::new(RwLock::new(IndexedDataHooks {
+        screener,
+        alerts,
+        stablecoins,
+        storage,
+        proofs,
+        balances,
+        calls,
+        #[cfg(feature = "traces")]
+        state_diffs,
+        #[cfg(feature = "traces")]
+        call_trees,
+        #[cfg(feature = "traces")]
+        native_transfers,
+        #[cfg(feature = "traces")]
+        revert_reasons,
+        lake,
+    }));
 
     if !config.reset {
-        let mut finished_initial_sync = false;
+        let control = SyncControl::default();
 
-        loop {
-            sync_chain(&rpc, &db, &mut config).await;
+        match config.admin_port {
+            #[cfg(feature = "api")]
+            Some(port) => {
+                let token = config
+                    .admin_token
+                    .clone()
+                    .expect("ADMIN_TOKEN must be set to serve the admin endpoints.");
 
-            if !finished_initial_sync {
                 tokio::spawn({
-                    let db = db.clone();
-                    let rpc = rpc.clone();
-                    let chain = config.chain.clone();
-                    let config = config.clone();
+                    let server = AdminServer::new(token, control.clone(), db.clone());
 
                     async move {
-                        loop {
-                            subscribe_heads(chain, &db, &rpc, &config).await;
-                            sleep(Duration::from_secs(10))
+                        match server.start(port).await {
+                            Ok(_) => (),
+                            Err(err) => warn!("Unable to serve the admin endpoints: {}", err),
                         }
                     }
                 });
             }
-            finished_initial_sync = true;
+            #[cfg(not(feature = "api"))]
+            Some(_) => {
+                eprintln!(
+                    "The indexer must be built with the api feature to serve the admin endpoints"
+                );
+                std::process::exit(1)
+            }
+            None => (),
+        }
+
+        let reloaded_files = get_reloaded_files(&config);
+
+        if config.reload_interval > 0 && reloaded_files.len() > 0 {
+            tokio::spawn({
+                let config = config.clone();
+                let hooks = hooks.clone();
+                let mut watcher = FileWatcher::new(reloaded_files);
+
+                async move {
+                    loop {
+                        sleep(Duration::from_secs(config.reload_interval)).await;
+
+                        let changed = watcher.get_changed_files();
+
+                        if changed.len() > 0 {
+                            reload_hooks(&config, &hooks, changed).await;
+                        }
+                    }
+                }
+            });
+        }
+
+        tokio::spawn({
+            let db = db.clone();
+            let rpc = rpc.clone();
+
+            async move {
+                loop {
+                    sleep(Duration::from_secs(60)).await;
+
+                    match store_rpc_usage(&rpc, &db) {
+                        Ok(_) => (),
+                        Err(err) => warn!("Unable to store RPC usage: {}", err),
+                    }
+                }
+            }
+        });
 
-            sleep(Duration::from_secs(5))
+        if config.table_stats_interval > 0 {
+            tokio::spawn({
+                let db = db.clone();
+                let interval = config.table_stats_interval;
+
+                async move {
+                    loop {
+                        match sample_table_stats(&db) {
+                            Ok(_) => (),
+                            Err(err) => warn!("Unable to sample table stats: {}", err),
+                        }
+
+                        sleep(Duration::from_secs(interval)).await;
+                    }
+                }
+            });
         }
+
+        // Returns the lag that stops the indexer with --exit-on-lag.
+        let mut lag_exit = tokio::spawn({
+            let db = db.clone();
+            let rpc = rpc.clone();
+            let mut monitor = SyncLagMonitor::new(&config);
+
+            async move {
+                loop {
+                    match monitor.check(&rpc, &db).await {
+                        Ok(sync_lag) => {
+                            if monitor.must_exit(sync_lag) {
+                                return sync_lag;
+                            }
+                        }
+                        Err(err) => warn!("Unable to check sync lag: {}", err),
+                    }
+
+                    sleep(Duration::from_secs(30)).await;
+                }
+            }
+        });
+
+        if config.fee_history {
+            tokio::spawn({
+                let db = db.clone();
+                let rpc = rpc.clone();
+                let worker = FeeHistoryWorker::new(&config);
+
+                async move {
+                    loop {
+                        match worker.poll(&rpc, &db).await {
+                            Ok(_) => (),
+                            Err(err) => warn!("Unable to store fee history: {}", err),
+                        }
+
+                        sleep(Duration::from_secs(15)).await;
+                    }
+                }
+            });
+        }
+
+        if let Some(logs_start_block) = config.logs_start_block {
+            tokio::spawn({
+                let db = db.clone();
+                let rpc = rpc.clone();
+                let config = config.clone();
+
+                async move { sync_logs(&rpc, &db, &config, logs_start_block).await }
+            });
+        }
+
+        if config.tui {
+            tokio::spawn({
+                let dashboard = SyncDashboard::new(rpc.clone(), db.clone(), config.start_block);
+
+                async move {
+                    match dashboard.run().await {
+                        Ok(_) => std::process::exit(0),
+                        Err(err) => panic!("Unable to run dashboard: {}", err),
+                    }
+                }
+            });
+        }
+
+        let indexing = async {
+            let mut finished_initial_sync = false;
+
+            loop {
+                apply_reindexes(&db, &control).await;
+
+                if control.is_paused() {
+                    sleep(Duration::from_secs(5)).await;
+
+                    continue;
+                }
+
+                sync_chain(&rpc, &db, &mut config, &hooks).await;
+
+                if !finished_initial_sync {
+                    tokio::spawn({
+                        let db = db.clone();
+                        let rpc = rpc.clone();
+                        let chain = config.chain.clone();
+                        let config = config.clone();
+                        let hooks = hooks.clone();
+                        let control = control.clone();
+
+                        async move {
+                            loop {
+                                subscribe_heads(chain, &db, &rpc, &config, &hooks, &control).await;
+                                sleep(Duration::from_secs(10)).await
+                            }
+                        }
+                    });
+                }
+                finished_initial_sync = true;
+
+                sleep(Duration::from_secs(5)).await
+            }
+        };
+
+        // A panicked monitor disables its branch and the indexing goes on.
+        let sync_lag = tokio::select! {
+            _ = indexing => return,
+            Ok(sync_lag) = &mut lag_exit => sync_lag,
+        };
+
+        error!(
+            "Exiting because the sync lag of {} blocks exceeds the threshold.",
+            sync_lag
+        );
+
+        flush_writers(&rpc, &hooks.lake).await;
+
+        if let Some(lock) = &lock {
+            match lock.release().await {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to release the chain lock: {}", err),
+            }
+        }
+
+        std::process::exit(2)
     } else {
         db.delete_indexed_blocks().await.unwrap();
     }
 }
 
-async fn sync_chain(rpc: &EVMRpc, db: &EVMDatabase, config: &EVMIndexerConfig) {
+/// Block range of a command, searching the blocks of its dates through the RPC.
+async fn get_command_range(
+    config: &EVMIndexerConfig,
+    from: &Option<i64>,
+    to: &Option<i64>,
+    from_date: &Option<String>,
+    to_date: &Option<String>,
+) -> (i64, i64) {
+    if from_date.is_none() && to_date.is_none() {
+        return (from.unwrap(), to.unwrap());
+    }
+
+    if config.rpcs.iter().all(|rpc| rpc.is_empty()) {
+        eprintln!("The dates require the rpcs to search the blocks with");
+        std::process::exit(1)
+    }
+
+    let rpc = EVMRpc::new(config)
+        .await
+        .expect("Unable to start RPC client.");
+
+    match get_block_range(&rpc, *from, *to, from_date, to_date).await {
+        Ok(range) => range,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Writes the buffered raw blocks and lake rows before the indexer stops.
+async fn flush_writers(rpc: &EVMRpc, lake: &Option<LakeWriter>) {
+    rpc.flush_archive().await;
+
+    match lake {
+        Some(lake) => match lake.flush().await {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to write the lake tables: {}", err),
+        },
+        None => (),
+    }
+}
+
+/// Waits for Ctrl-C or, on Unix, the SIGTERM sent by Docker and Kubernetes.
+async fn wait_for_shutdown() {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Unable to listen to SIGTERM.");
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = terminate.recv() => (),
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Unable to listen to Ctrl-C.");
+}
+
+async fn sync_chain(
+    rpc: &EVMRpc,
+    db: &EVMDatabase,
+    config: &EVMIndexerConfig,
+    hooks: &SharedHooks,
+) {
     let last_block = rpc.get_last_block().await.unwrap();
 
     let full_block_range = config.start_block..last_block;
@@ -94,6 +1101,8 @@ async fn sync_chain(rpc: &EVMRpc, db: &EVMDatabase, config: &EVMIndexerConfig) {
 
     db.update_indexed_blocks_number(&db_state).await.unwrap();
 
+    sync_backfill_jobs(rpc, db, config, hooks, last_block, &mut indexed_blocks).await;
+
     let missing_blocks: Vec<i64> = full_block_range
         .into_iter()
         .filter(|block| !indexed_blocks.contains(block))
@@ -103,147 +1112,733 @@ async fn sync_chain(rpc: &EVMRpc, db: &EVMDatabase, config: &EVMIndexerConfig) {
 
     info!("Syncing {} blocks.", total_missing_blocks);
 
-    let missing_blocks_chunks = missing_blocks.chunks(config.batch_size);
+    sync_blocks(
+        rpc,
+        db,
+        config,
+        hooks,
+        &missing_blocks,
+        &mut indexed_blocks,
+        true,
+    )
+    .await;
+}
 
-    for missing_blocks_chunk in missing_blocks_chunks {
-        let mut work = vec![];
+/// Runs the queued backfill jobs by priority before the linear sync. Blocks that fail to be
+/// fetched are left for the linear sync when they are after the start block.
+async fn sync_backfill_jobs(
+    rpc: &EVMRpc,
+    db: &EVMDatabase,
+    config: &EVMIndexerConfig,
+    hooks: &SharedHooks,
+    last_block: i64,
+    indexed_blocks: &mut HashSet<i64>,
+) {
+    loop {
+        let job = match get_next_backfill_job(db) {
+            Ok(Some(job)) => job,
+            Ok(None) => return,
+            Err(err) => {
+                warn!("Unable to get the backfill jobs: {}", err);
+                return;
+            }
+        };
 
-        for block_number in missing_blocks_chunk {
-            work.push(fetch_block(&rpc, &block_number, &config.chain))
+        match update_backfill_job_status(db, job.id, RUNNING) {
+            Ok(_) => (),
+            Err(err) => {
+                warn!("Unable to start backfill job {}: {}", job.id, err);
+                return;
+            }
         }
 
-        let results = join_all(work).await;
+        let missing_blocks: Vec<i64> = (job.from_block.max(0)..=job.to_block.min(last_block))
+            .filter(|block| !indexed_blocks.contains(block))
+            .collect();
 
-        let mut db_blocks: Vec<DatabaseEVMBlock> = Vec::new();
-        let mut db_transactions: Vec<DatabaseEVMTransaction> = Vec::new();
-        let mut db_receipts: Vec<DatabaseEVMTransactionReceipt> = Vec::new();
-        let mut db_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
-        let mut db_contracts: Vec<DatabaseEVMContract> = Vec::new();
+        info!(
+            "Running backfill job {} with priority {}, syncing {} blocks from {} to {}.",
+            job.id,
+            job.priority,
+            missing_blocks.len(),
+            job.from_block,
+            job.to_block
+        );
 
-        for result in results {
-            match result {
-                Some((block, mut transactions, mut receipts, mut logs, mut contracts)) => {
-                    db_blocks.push(block);
-                    db_transactions.append(&mut transactions);
-                    db_receipts.append(&mut receipts);
-                    db_logs.append(&mut logs);
-                    db_contracts.append(&mut contracts);
+        sync_blocks(
+            rpc,
+            db,
+            config,
+            hooks,
+            &missing_blocks,
+            indexed_blocks,
+            false,
+        )
+        .await;
+
+        // Blocks that failed to fetch or store are not in the indexed blocks.
+        let failed_blocks: Vec<i64> = missing_blocks
+            .into_iter()
+            .filter(|block| !indexed_blocks.contains(block))
+            .collect();
+
+        if failed_blocks.len() > 0 {
+            warn!(
+                "Backfill job {} failed to index {} blocks.",
+                job.id,
+                failed_blocks.len()
+            );
+
+            match fail_backfill_job(db, job.id, &failed_blocks) {
+                Ok(_) => continue,
+                Err(err) => {
+                    warn!("Unable to fail backfill job {}: {}", job.id, err);
+                    return;
+                }
+            }
+        }
+
+        match update_backfill_job_status(db, job.id, DONE) {
+            Ok(_) => info!("Finished backfill job {}.", job.id),
+            Err(err) => {
+                warn!("Unable to finish backfill job {}: {}", job.id, err);
+                return;
+            }
+        }
+    }
+}
+
+/// Fetches and stores the blocks by batches. The linear sync is `preemptible`, it stops between
+/// batches when a backfill job is queued so the next pass runs the job first.
+async fn sync_blocks(
+    rpc: &EVMRpc,
+    db: &EVMDatabase,
+    config: &EVMIndexerConfig,
+    hooks: &SharedHooks,
+    blocks: &Vec<i64>,
+    indexed_blocks: &mut HashSet<i64>,
+    preemptible: bool,
+) {
+    let mut controller = BatchSizeController::new(
+        config.batch_size,
+        config.min_batch_size,
+        config.max_batch_size,
+    );
+
+    let mut remaining_blocks = blocks.as_slice();
+
+    while remaining_blocks.len() > 0 {
+        if preemptible {
+            match get_next_backfill_job(db) {
+                Ok(Some(job)) => {
+                    info!("Pausing the sync to run backfill job {}.", job.id);
+                    return;
                 }
+                _ => (),
+            }
+        }
+
+        let batch_size = match config.autoscale {
+            true => controller.adjust(remaining_blocks.len(), &rpc.stats),
+            false => config.batch_size.max(1),
+        };
+
+        let (missing_blocks_chunk, rest) =
+            remaining_blocks.split_at(batch_size.min(remaining_blocks.len()));
+
+        remaining_blocks = rest;
+
+        // Blocks are stored as they arrive, the buffer is flushed early when it exceeds the
+        // memory budget so a chunk of large blocks doesn't have to be held at once.
+        let mut results = stream::iter(missing_blocks_chunk)
+            .map(|block_number| rpc.fetch_block(block_number))
+            .buffer_unordered(missing_blocks_chunk.len());
+
+        let mut buffer = IndexedDataBuffer::default();
+
+        while let Some(result) = results.next().await {
+            match result {
+                Some(data) => buffer.push(data),
                 None => continue,
             }
+
+            if buffer.is_full(config.memory_budget) {
+                info!(
+                    "Flushing {} blocks early, buffer of {} MB exceeds the memory budget.",
+                    buffer.blocks.len(),
+                    buffer.size / 1_000_000
+                );
+
+                let hooks = get_hooks(hooks);
+
+                store_buffer(rpc, db, config, &hooks, buffer.take(), indexed_blocks).await;
+            }
         }
 
-        db.store_data(
-            &db_blocks,
-            &db_transactions,
-            &db_receipts,
-            &db_logs,
-            &db_contracts,
-        )
-        .await;
+        if !buffer.is_empty() {
+            let hooks = get_hooks(hooks);
 
-        for block in db_blocks.into_iter() {
-            indexed_blocks.insert(block.number);
+            store_buffer(rpc, db, config, &hooks, buffer, indexed_blocks).await;
         }
+    }
+}
+
+/// Deletes the ranges queued by the admin endpoints and removes them from the indexed blocks, so
+/// the next pass fetches them again.
+async fn apply_reindexes(db: &EVMDatabase, control: &SyncControl) {
+    for range in control.take_reindex_ranges() {
+        let reset = ChainReset::new(db.clone(), Some(range));
 
-        db.store_indexed_blocks(&indexed_blocks).await.unwrap();
+        match reset.reset_blocks().await {
+            Ok(summary) => info!(
+                "Reindexing {} blocks from {} to {}.",
+                summary.blocks, summary.from_block, summary.to_block
+            ),
+            Err(err) => warn!("Unable to reset blocks {} to {}: {}", range.0, range.1, err),
+        }
     }
 }
 
-async fn fetch_block(
+/// Backfills the logs of the blocks from `logs_start_block` up to the start block with
+/// `eth_getLogs`, which is much cheaper than fetching the full blocks. The range of a request is
+/// halved when it fails, since providers cap the logs returned at once.
+async fn sync_logs(
     rpc: &EVMRpc,
-    block_number: &i64,
-    chain: &Chain,
-) -> Option<(
-    DatabaseEVMBlock,
-    Vec<DatabaseEVMTransaction>,
-    Vec<DatabaseEVMTransactionReceipt>,
-    Vec<DatabaseEVMTransactionLog>,
-    Vec<DatabaseEVMContract>,
-)> {
-    let block_data = rpc.get_block(block_number).await.unwrap();
-
-    match block_data {
-        Some((db_block, mut db_transactions)) => {
-            let total_block_transactions = db_transactions.len();
-
-            // Make sure all the transactions are correctly formatted.
-            if db_block.transactions != total_block_transactions as i64 {
+    db: &EVMDatabase,
+    config: &EVMIndexerConfig,
+    logs_start_block: i64,
+) {
+    let mut cursor = match db.get_logs_cursor().await.unwrap() {
+        Some(cursor) => cursor.max(logs_start_block),
+        None => logs_start_block,
+    };
+
+    info!(
+        "Backfilling the logs of {} blocks.",
+        (config.start_block - cursor).max(0)
+    );
+
+    let mut range = config.logs_range;
+
+    while cursor < config.start_block {
+        let to_block = (cursor + range).min(config.start_block) - 1;
+
+        let (mut db_logs, db_log_transactions) = match rpc.get_logs(cursor, to_block).await {
+            Ok(Some(logs)) => logs,
+            _ => {
                 warn!(
-                    "Missing {} transactions for block {}.",
-                    db_block.transactions - total_block_transactions as i64,
-                    db_block.number
+                    "Unable to fetch the logs of blocks {} to {}, retrying with a smaller range.",
+                    cursor, to_block
                 );
-                return None;
+
+                range = (range / 2).max(1);
+
+                sleep(Duration::from_secs(1)).await;
+
+                continue;
+            }
+        };
+
+        let (db_erc20_transfers, db_parse_failures) =
+            parse_inline(&config.inline_parsers, &mut db_logs);
+
+        db.store_logs_backfill(&db_logs, &db_log_transactions, &db_erc20_transfers)
+            .await;
+
+        store_inline_results(
+            db,
+            config.publish_events,
+            config.notify,
+            &db_erc20_transfers,
+            &db_parse_failures,
+        )
+        .await;
+
+        cursor = to_block + 1;
+
+        db.store_logs_cursor(cursor).await.unwrap();
+
+        range = config.logs_range;
+    }
+
+    info!(
+        "Finished the logs backfill up to block {}.",
+        config.start_block
+    );
+}
+
+async fn store_buffer(
+    rpc: &EVMRpc,
+    db: &EVMDatabase,
+    config: &EVMIndexerConfig,
+    hooks: &IndexedDataHooks,
+    buffer: IndexedDataBuffer,
+    indexed_blocks: &mut HashSet<i64>,
+) {
+    let IndexedDataBuffer {
+        blocks: db_blocks,
+        transactions: db_transactions,
+        receipts: db_receipts,
+        logs: mut db_logs,
+        contracts: db_contracts,
+        ..
+    } = buffer;
+
+    let (db_erc20_transfers, db_parse_failures) =
+        parse_inline(&config.inline_parsers, &mut db_logs);
+
+    let db_outbox = match config.outbox {
+        true => get_outbox_events(&config.chain, &db_blocks, &db_logs),
+        false => Vec::new(),
+    };
+
+    db.store_data(
+        &db_blocks,
+        &db_transactions,
+        &db_receipts,
+        &db_logs,
+        &db_contracts,
+        &db_outbox,
+        &db_erc20_transfers,
+    )
+    .await;
+
+    store_inline_results(
+        db,
+        config.publish_events,
+        config.notify,
+        &db_erc20_transfers,
+        &db_parse_failures,
+    )
+    .await;
+
+    rpc.archive_blocks(&db_blocks).await;
+
+    write_lake(hooks, &db_blocks, &db_transactions, &db_receipts, &db_logs).await;
+
+    if config.publish_events {
+        publish_indexed_events(db, &db_blocks, &db_logs);
+    }
+
+    if config.notify {
+        notify_indexed_blocks(db, &db_blocks);
+    }
+
+    process_indexed_data(hooks, db, &db_transactions, &db_logs, &db_contracts, false).await;
+
+    process_traces(hooks, rpc, db, &db_blocks, &db_transactions, &db_receipts).await;
+
+    for block in db_blocks.into_iter() {
+        indexed_blocks.insert(block.number);
+    }
+
+    db.store_indexed_blocks(indexed_blocks).await.unwrap();
+}
+
+/// Indexes the chain into an embedded database. Only the blocks, transactions, receipts, logs
+/// and contracts are stored, the hooks, outbox and archive need the Postgres database.
+async fn sync_embedded(rpc: &EVMRpc, config: &EVMIndexerConfig, db: &dyn EmbeddedDatabase) {
+    loop {
+        let last_block = rpc.get_last_block().await.unwrap();
+
+        let indexed_blocks = db.get_indexed_blocks().unwrap();
+
+        let missing_blocks: Vec<i64> = (config.start_block..last_block)
+            .filter(|block| !indexed_blocks.contains(block))
+            .collect();
+
+        info!("Syncing {} blocks.", missing_blocks.len());
+
+        for missing_blocks_chunk in missing_blocks.chunks(config.batch_size) {
+            let mut work = vec![];
+
+            for block_number in missing_blocks_chunk {
+                work.push(rpc.fetch_block(&block_number))
             }
 
+            let results = join_all(work).await;
+
+            let mut db_blocks: Vec<DatabaseEVMBlock> = Vec::new();
+            let mut db_transactions: Vec<DatabaseEVMTransaction> = Vec::new();
             let mut db_receipts: Vec<DatabaseEVMTransactionReceipt> = Vec::new();
             let mut db_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
             let mut db_contracts: Vec<DatabaseEVMContract> = Vec::new();
 
-            if chain.supports_blocks_receipts {
-                let receipts_data = rpc.get_block_receipts(block_number).await.unwrap();
-                match receipts_data {
-                    Some((mut receipts, mut logs, mut contracts)) => {
+            for result in results {
+                match result {
+                    Some((block, mut transactions, mut receipts, mut logs, mut contracts)) => {
+                        db_blocks.push(block);
+                        db_transactions.append(&mut transactions);
                         db_receipts.append(&mut receipts);
                         db_logs.append(&mut logs);
                         db_contracts.append(&mut contracts);
                     }
-                    None => return None,
-                }
-            } else {
-                for transaction in db_transactions.iter_mut() {
-                    let receipt_data = rpc
-                        .get_transaction_receipt(transaction.hash.clone())
-                        .await
-                        .unwrap();
-
-                    match receipt_data {
-                        Some((receipt, mut logs, contract)) => {
-                            db_receipts.push(receipt);
-                            db_logs.append(&mut logs);
-                            match contract {
-                                Some(contract) => db_contracts.push(contract),
-                                None => continue,
-                            }
-                        }
-                        None => continue,
-                    }
+                    None => continue,
                 }
             }
 
-            if total_block_transactions != db_receipts.len() {
-                warn!(
-                    "Missing receipts for block {}. Transactions {} receipts {}",
-                    db_block.number,
-                    total_block_transactions,
-                    db_receipts.len()
-                );
-                return None;
+            db.store_data(
+                &db_blocks,
+                &db_transactions,
+                &db_receipts,
+                &db_logs,
+                &db_contracts,
+            )
+            .expect("Unable to store data into the embedded database");
+        }
+
+        sleep(Duration::from_secs(5)).await
+    }
+}
+
+/// Whether the block fetched by number is the one of the head notification.
+fn is_canonical_head(block: &DatabaseEVMBlock, header_hash: &Option<String>) -> bool {
+    match header_hash {
+        Some(header_hash) => &block.block_hash == header_hash,
+        None => true,
+    }
+}
+
+/// Runs the inline parsers on the fetched logs. The logs are stored marked as parsed, so the
+/// parser service skips them, and the results are stored in the same transaction.
+fn parse_inline(
+    inline_parsers: &Vec<String>,
+    logs: &mut Vec<DatabaseEVMTransactionLog>,
+) -> (Vec<DatabaseEVMErc20Transfer>, Vec<DatabaseEVMParseFailure>) {
+    if !inline_parsers
+        .iter()
+        .any(|parser| parser == ERC20_TRANSFERS_PARSER)
+    {
+        return (Vec::new(), Vec::new());
+    }
+
+    let (transfers, failures) = get_transfers(logs);
+
+    for log in logs.iter_mut() {
+        log.erc20_transfers_parsed = Some(true);
+    }
+
+    (transfers, failures)
+}
+
+async fn store_inline_results(
+    db: &EVMDatabase,
+    publish: bool,
+    notify: bool,
+    erc20_transfers: &Vec<DatabaseEVMErc20Transfer>,
+    parse_failures: &Vec<DatabaseEVMParseFailure>,
+) {
+    match db.store_parse_failures(parse_failures).await {
+        Ok(_) => (),
+        Err(err) => warn!("Unable to store the inline parse failures: {}", err),
+    }
+
+    emit_transfer_events(db, erc20_transfers, publish, notify);
+}
+
+fn get_outbox_events(
+    chain: &Chain,
+    blocks: &Vec<DatabaseEVMBlock>,
+    logs: &Vec<DatabaseEVMTransactionLog>,
+) -> Vec<DatabaseEVMOutboxEvent> {
+    let mut events: Vec<DatabaseEVMOutboxEvent> = blocks
+        .iter()
+        .map(|block| IndexedEvent::from_block(block).to_outbox_event(chain.name))
+        .collect();
+
+    for log in logs {
+        events.push(IndexedEvent::from_log(log, chain.name).to_outbox_event(chain.name));
+    }
+
+    events
+}
+
+fn publish_indexed_events(
+    db: &EVMDatabase,
+    blocks: &Vec<DatabaseEVMBlock>,
+    logs: &Vec<DatabaseEVMTransactionLog>,
+) {
+    let mut events: Vec<IndexedEvent> = blocks
+        .iter()
+        .map(|block| IndexedEvent::from_block(block))
+        .collect();
+
+    for log in logs {
+        events.push(IndexedEvent::from_log(log, db.chain.name));
+    }
+
+    match publish_events(db, &events) {
+        Ok(_) => (),
+        Err(err) => warn!("Unable to publish indexed events: {}", err),
+    }
+}
+
+fn notify_indexed_blocks(db: &EVMDatabase, blocks: &Vec<DatabaseEVMBlock>) {
+    let events: Vec<IndexedEvent> = blocks
+        .iter()
+        .map(|block| IndexedEvent::from_block(block))
+        .collect();
+
+    match notify_events(db, &events) {
+        Ok(_) => (),
+        Err(err) => warn!("Unable to notify indexed blocks: {}", err),
+    }
+}
+
+/// Optional processing of the indexed data after it is stored. Storage slots, proofs and view
+/// calls are only read for the new blocks, since older state needs an archive node.
+#[derive(Debug, Clone)]
+struct IndexedDataHooks {
+    screener: Option<AddressScreener>,
+    alerts: Option<AlertsEngine>,
+    stablecoins: Option<StablecoinMonitor>,
+    storage: Option<StorageWatcher>,
+    proofs: Option<ProofArchiver>,
+    balances: Option<BalanceSampler>,
+    calls: Option<CallSampler>,
+    #[cfg(feature = "traces")]
+    state_diffs: Option<StateDiffIndexer>,
+    #[cfg(feature = "traces")]
+    call_trees: Option<CallTreeIndexer>,
+    #[cfg(feature = "traces")]
+    native_transfers: Option<NativeTransferIndexer>,
+    #[cfg(feature = "traces")]
+    revert_reasons: Option<RevertReasonIndexer>,
+    lake: Option<LakeWriter>,
+}
+
+/// Hooks shared with the reload task, each batch and new block uses a copy of the current ones.
+type SharedHooks = Arc<RwLock<IndexedDataHooks>>;
+
+fn get_hooks(hooks: &SharedHooks) -> IndexedDataHooks {
+    hooks.read().expect("Unable to read the hooks").clone()
+}
+
+/// Files of the hooks that are rebuilt when they change.
+fn get_reloaded_files(config: &EVMIndexerConfig) -> Vec<String> {
+    [
+        &config.screening_list,
+        &config.alert_rules,
+        &config.storage_slots,
+        &config.proof_accounts,
+        &config.view_calls,
+        &config.watched_holders,
+    ]
+    .into_iter()
+    .filter_map(|path| path.clone())
+    .collect()
+}
+
+/// Rebuilds the hooks of the changed files. The loaders panic on invalid files, so each file is
+/// loaded on its own task and a failed one keeps the previous hook instead of stopping the
+/// indexer.
+async fn reload_hooks(config: &EVMIndexerConfig, hooks: &SharedHooks, changed: Vec<String>) {
+    for path in changed {
+        let result = tokio::task::spawn_blocking({
+            let config = config.clone();
+            let current = get_hooks(hooks);
+            let path = path.clone();
+
+            move || rebuild_hooks(&config, current, &path)
+        })
+        .await;
+
+        match result {
+            Ok(reloaded) => {
+                *hooks.write().expect("Unable to update the hooks") = reloaded;
+
+                info!("Reloaded {}.", path);
             }
+            Err(_) => warn!(
+                "Unable to reload {}, keeping the previous configuration.",
+                path
+            ),
+        }
+    }
+}
 
-            info!(
-                "Found transactions {} receipts {} logs {} and contracts {} for block {}.",
-                total_block_transactions,
-                db_receipts.len(),
-                db_logs.len(),
-                db_contracts.len(),
-                block_number
-            );
+fn rebuild_hooks(
+    config: &EVMIndexerConfig,
+    mut hooks: IndexedDataHooks,
+    path: &String,
+) -> IndexedDataHooks {
+    let path = Some(path.clone());
+
+    if config.screening_list == path {
+        hooks.screener = path
+            .as_ref()
+            .map(|path| AddressScreener::load(path, config.screening_webhook.clone()));
+    }
+
+    if config.alert_rules == path {
+        hooks.alerts = path
+            .as_ref()
+            .map(|path| AlertsEngine::new(load_alert_rules(path)));
+    }
+
+    if config.storage_slots == path {
+        hooks.storage = path
+            .as_ref()
+            .map(|path| StorageWatcher::new(config.chain.name, load_storage_slots(path)));
+    }
+
+    if config.proof_accounts == path {
+        hooks.proofs = path
+            .as_ref()
+            .map(|path| ProofArchiver::new(config.chain.name, load_proof_accounts(path)));
+    }
+
+    if config.view_calls == path {
+        hooks.calls = path
+            .as_ref()
+            .map(|path| CallSampler::new(config.chain.name, load_view_calls(path)));
+    }
+
+    if config.watched_holders == path {
+        hooks.balances = path.as_ref().map(|path| {
+            BalanceSampler::new(load_watched_holders(path), config.balance_sampling_interval)
+        });
+    }
+
+    hooks
+}
+
+async fn write_lake(
+    hooks: &IndexedDataHooks,
+    blocks: &Vec<DatabaseEVMBlock>,
+    transactions: &Vec<DatabaseEVMTransaction>,
+    receipts: &Vec<DatabaseEVMTransactionReceipt>,
+    logs: &Vec<DatabaseEVMTransactionLog>,
+) {
+    match &hooks.lake {
+        Some(lake) => match lake.write(blocks, transactions, receipts, logs).await {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to write the lake tables: {}", err),
+        },
+        None => (),
+    }
+}
+
+#[cfg(feature = "traces")]
+async fn process_traces(
+    hooks: &IndexedDataHooks,
+    rpc: &EVMRpc,
+    db: &EVMDatabase,
+    blocks: &Vec<DatabaseEVMBlock>,
+    transactions: &Vec<DatabaseEVMTransaction>,
+    receipts: &Vec<DatabaseEVMTransactionReceipt>,
+) {
+    match &hooks.state_diffs {
+        Some(state_diffs) => match state_diffs.process(rpc, db, blocks, transactions).await {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to store state diffs: {}", err),
+        },
+        None => (),
+    }
+
+    match &hooks.native_transfers {
+        Some(native_transfers) => {
+            match native_transfers
+                .process(rpc, db, blocks, transactions)
+                .await
+            {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to store native transfers: {}", err),
+            }
+        }
+        None => (),
+    }
+
+    match &hooks.revert_reasons {
+        Some(revert_reasons) => {
+            match revert_reasons
+                .process(rpc, db, transactions, receipts)
+                .await
+            {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to store revert reasons: {}", err),
+            }
+        }
+        None => (),
+    }
+
+    // Runs after the screening so the flagged transactions are already stored.
+    match &hooks.call_trees {
+        Some(call_trees) => match call_trees.process(rpc, db, transactions).await {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to store call trees: {}", err),
+        },
+        None => (),
+    }
+}
+
+#[cfg(not(feature = "traces"))]
+async fn process_traces(
+    _hooks: &IndexedDataHooks,
+    _rpc: &EVMRpc,
+    _db: &EVMDatabase,
+    _blocks: &Vec<DatabaseEVMBlock>,
+    _transactions: &Vec<DatabaseEVMTransaction>,
+    _receipts: &Vec<DatabaseEVMTransactionReceipt>,
+) {
+}
+
+/// Alerts are only sent for the new heads, so syncing or backfilling old blocks doesn't send
+/// alerts about past activity.
+async fn process_indexed_data(
+    hooks: &IndexedDataHooks,
+    db: &EVMDatabase,
+    transactions: &Vec<DatabaseEVMTransaction>,
+    logs: &Vec<DatabaseEVMTransactionLog>,
+    contracts: &Vec<DatabaseEVMContract>,
+    head: bool,
+) {
+    let alerts = match head {
+        true => hooks.alerts.as_ref(),
+        false => None,
+    };
+
+    match &hooks.screener {
+        Some(screener) => match screener.process(db, transactions, logs).await {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to screen indexed data: {}", err),
+        },
+        None => (),
+    }
 
-            return Some((
-                db_block,
-                db_transactions,
-                db_receipts,
-                db_logs,
-                db_contracts,
-            ));
+    match alerts {
+        Some(alerts) => {
+            alerts
+                .process(db.chain.name, transactions, logs, contracts)
+                .await
         }
-        None => return None,
+        None => (),
+    }
+
+    match &hooks.stablecoins {
+        Some(stablecoins) => match stablecoins.process(db, transactions, logs).await {
+            Ok(events) => match alerts {
+                Some(alerts) => {
+                    alerts
+                        .process_stablecoin_events(db.chain.name, &events)
+                        .await
+                }
+                None => (),
+            },
+            Err(err) => warn!("Unable to store stablecoin events: {}", err),
+        },
+        None => (),
     }
 }
 
-async fn subscribe_heads(chain: Chain, db: &EVMDatabase, rpc: &EVMRpc, config: &EVMIndexerConfig) {
+async fn subscribe_heads(
+    chain: Chain,
+    db: &EVMDatabase,
+    rpc: &EVMRpc,
+    config: &EVMIndexerConfig,
+    hooks: &SharedHooks,
+    control: &SyncControl,
+) {
     let wss = match WebSocket::new(&config.websocket.clone()).await {
         Ok(ws) => Some(Web3::new(ws)),
         Err(_) => None,
@@ -255,41 +1850,235 @@ async fn subscribe_heads(chain: Chain, db: &EVMDatabase, rpc: &EVMRpc, config: &
         Some(wss) => {
             let mut sub = wss.eth_subscribe().subscribe_new_heads().await.unwrap();
 
+            // Idle websockets are closed by some providers and proxies without notice, the pings
+            // keep them open and detect the dead ones to reconnect.
+            let ping_interval = Duration::from_secs(config.ws_ping_interval.max(1));
+
+            let mut ping = tokio::time::interval(ping_interval);
+
+            ping.tick().await;
+
             loop {
-                let new_block = sub.next().await;
+                let new_block = tokio::select! {
+                    new_block = sub.next() => new_block,
+                    _ = ping.tick(), if config.ws_ping_interval > 0 => {
+                        match tokio::time::timeout(ping_interval, wss.eth().block_number()).await {
+                            Ok(Ok(_)) => continue,
+                            _ => {
+                                warn!(
+                                    "Websocket for chain {} didn't answer the ping, reconnecting.",
+                                    chain.name
+                                );
+
+                                return;
+                            }
+                        }
+                    }
+                };
                 match new_block {
                     Some(block_header) => match block_header {
                         Ok(block_header) => {
                             let block_number = block_header.number.unwrap().as_u64() as i64;
+
+                            // Same format as the stored hashes.
+                            let header_hash = block_header.hash.map(|hash| format!("{:?}", hash));
+
+                            let received_at = get_timestamp_ms();
+
                             info!(
                                 "New block with height {:?} for chain {}",
                                 block_number, chain.name
                             );
 
+                            if config.block_times {
+                                match &header_hash {
+                                    Some(hash) => {
+                                        let block_time = DatabaseEVMBlockTime::new(
+                                            chain.name,
+                                            block_number,
+                                            hash.clone(),
+                                            format!("{:?}", block_header.parent_hash),
+                                            block_header.timestamp.as_u64() as i64,
+                                            received_at,
+                                        );
+
+                                        match store_block_time(db, &block_time) {
+                                            Ok(_) => (),
+                                            Err(err) => {
+                                                warn!("Unable to store block time: {}", err)
+                                            }
+                                        }
+                                    }
+                                    None => (),
+                                }
+                            }
+
+                            // Missed heads are fetched by the sync once it is resumed.
+                            if control.is_paused() {
+                                continue;
+                            }
+
                             tokio::spawn({
                                 let rpc = rpc.clone();
                                 let db = db.clone();
+                                let publish = config.publish_events;
+                                let notify = config.notify;
+                                let outbox = config.outbox;
+                                let inline_parsers = config.inline_parsers.clone();
+                                let hooks = get_hooks(hooks);
 
                                 async move {
-                                    let block_data = fetch_block(&rpc, &block_number, &chain).await;
+                                    let block_data = rpc.fetch_block(&block_number).await;
 
                                     match block_data {
                                         Some((
                                             db_block,
                                             db_transactions,
                                             db_receipts,
-                                            db_logs,
+                                            mut db_logs,
                                             db_contracts,
                                         )) => {
-                                            db.store_data(
-                                                &vec![db_block],
+                                            // The block can be reorged out between the
+                                            // notification and the fetch, it is indexed later
+                                            // from the canonical chain.
+                                            if !is_canonical_head(&db_block, &header_hash) {
+                                                warn!(
+                                                    "Skipping block {} for chain {}, fetched hash {} doesn't match the head {:?}.",
+                                                    block_number,
+                                                    chain.name,
+                                                    db_block.block_hash,
+                                                    header_hash
+                                                );
+
+                                                return;
+                                            }
+
+                                            let db_blocks = vec![db_block];
+
+                                            let (db_erc20_transfers, db_parse_failures) =
+                                                parse_inline(&inline_parsers, &mut db_logs);
+
+                                            let db_outbox = match outbox {
+                                                true => {
+                                                    get_outbox_events(&chain, &db_blocks, &db_logs)
+                                                }
+                                                false => Vec::new(),
+                                            };
+
+                                            db.store_head_data(
+                                                &db_blocks,
+                                                &db_transactions,
+                                                &db_receipts,
+                                                &db_logs,
+                                                &db_contracts,
+                                                &db_outbox,
+                                                &db_erc20_transfers,
+                                            )
+                                            .await;
+
+                                            store_inline_results(
+                                                &db,
+                                                publish,
+                                                notify,
+                                                &db_erc20_transfers,
+                                                &db_parse_failures,
+                                            )
+                                            .await;
+
+                                            rpc.archive_blocks(&db_blocks).await;
+
+                                            write_lake(
+                                                &hooks,
+                                                &db_blocks,
                                                 &db_transactions,
                                                 &db_receipts,
                                                 &db_logs,
+                                            )
+                                            .await;
+
+                                            if publish {
+                                                publish_indexed_events(&db, &db_blocks, &db_logs);
+                                            }
+
+                                            if notify {
+                                                notify_indexed_blocks(&db, &db_blocks);
+                                            }
+
+                                            process_indexed_data(
+                                                &hooks,
+                                                &db,
+                                                &db_transactions,
+                                                &db_logs,
                                                 &db_contracts,
+                                                true,
+                                            )
+                                            .await;
+
+                                            process_traces(
+                                                &hooks,
+                                                &rpc,
+                                                &db,
+                                                &db_blocks,
+                                                &db_transactions,
+                                                &db_receipts,
                                             )
                                             .await;
 
+                                            match &hooks.storage {
+                                                Some(storage) => match storage
+                                                    .process(&rpc, &db, block_number)
+                                                    .await
+                                                {
+                                                    Ok(_) => (),
+                                                    Err(err) => warn!(
+                                                        "Unable to read storage slots: {}",
+                                                        err
+                                                    ),
+                                                },
+                                                None => (),
+                                            }
+
+                                            match &hooks.proofs {
+                                                Some(proofs) => match proofs
+                                                    .process(&rpc, &db, block_number)
+                                                    .await
+                                                {
+                                                    Ok(_) => (),
+                                                    Err(err) => warn!(
+                                                        "Unable to archive state proofs: {}",
+                                                        err
+                                                    ),
+                                                },
+                                                None => (),
+                                            }
+
+                                            match &hooks.balances {
+                                                Some(balances) => match balances
+                                                    .process(&rpc, &db, block_number)
+                                                    .await
+                                                {
+                                                    Ok(_) => (),
+                                                    Err(err) => {
+                                                        warn!("Unable to sample balances: {}", err)
+                                                    }
+                                                },
+                                                None => (),
+                                            }
+
+                                            match &hooks.calls {
+                                                Some(calls) => match calls
+                                                    .sample(&rpc, &db, block_number)
+                                                    .await
+                                                {
+                                                    Ok(_) => (),
+                                                    Err(err) => warn!(
+                                                        "Unable to sample view calls: {}",
+                                                        err
+                                                    ),
+                                                },
+                                                None => (),
+                                            }
+
                                             let mut indexed_blocks =
                                                 db.get_indexed_blocks().await.unwrap();
 
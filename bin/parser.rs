@@ -1,17 +1,42 @@
-use std::{thread::sleep, time::Duration};
+use std::{sync::Arc, time::Duration};
 
 use dotenv::dotenv;
 use evm_indexer::{
     chains::chains::ETHEREUM,
     configs::parser_config::EVMParserConfig,
     db::db::EVMDatabase,
+    metrics::{telemetry::init_telemetry, views::ViewRefresher},
     parsers::{
-        erc20_tokens_parser::ERC20TokensParser, erc20_transfers_parser::ERC20TransfersParser,
+        bridge_parser::{load_bridge_deployments, BridgeParser, BRIDGE_PROTOCOLS},
+        decoded_logs_parser::DecodedLogsParser,
+        dedup::{load_dedup_rules, DuplicateFilter},
+        deployments::{
+            load_protocol_deployments, register_deployments, store_protocol_deployments,
+        },
+        dex_swaps_parser::DexSwapsParser,
+        ens_parser::ENSParser,
+        erc20_tokens_parser::ERC20TokensParser,
+        erc20_transfers_parser::ERC20TransfersParser,
+        flash_loans_parser::FlashLoansParser,
+        governance_parser::GovernanceParser,
+        lending_parser::{load_lending_deployments, LendingParser, LENDING_PROTOCOLS},
+        liquidity_parser::{
+            get_default_liquidity_deployments, LiquidityParser, LIQUIDITY_PROTOCOLS,
+        },
         llamafolio_adapters::LlamafolioParser,
+        mev_parser::MevParser,
+        parallel::parse_in_chunks,
+        permits_parser::PermitsParser,
+        spam_tokens_parser::SpamTokensParser,
+        staking_parser::{load_staking_deployments, StakingParser, STAKING_PROTOCOLS},
+        token_prices_parser::TokenPricesParser,
     },
+    state::reducer::{get_reducers, ReducerRunner},
+    tokens::prices::ExternalPricesFetcher,
 };
 use log::*;
 use simple_logger::SimpleLogger;
+use tokio::time::sleep;
 
 #[tokio::main()]
 async fn main() {
@@ -29,9 +54,51 @@ async fn main() {
 
     info!("Starting EVM Parser.");
 
-    let db = EVMDatabase::new(config.db_url, config.redis_url.clone(), ETHEREUM)
-        .await
-        .expect("Unable to start DB connection.");
+    match &config.otlp_endpoint {
+        Some(endpoint) => {
+            init_telemetry("evm-parser", endpoint).expect("Unable to start telemetry.")
+        }
+        None => (),
+    }
+
+    let db = EVMDatabase::new(
+        config.db_url,
+        config.db_replica_urls.clone(),
+        config.db_schema.clone(),
+        config.redis_url.clone(),
+        ETHEREUM,
+    )
+    .await
+    .expect("Unable to start DB connection.");
+
+    let dedup = DuplicateFilter::new(load_dedup_rules(&config.dedup_policies));
+
+    match &config.protocol_deployments {
+        Some(path) => {
+            let stored = store_protocol_deployments(&db, &load_protocol_deployments(path))
+                .expect("Unable to store the protocol deployments.");
+
+            info!("Registered {} protocol deployments.", stored);
+        }
+        None => (),
+    }
+
+    match &config.redrive_failures {
+        Some(parser) => {
+            let parser = match parser.as_str() {
+                "all" => None,
+                parser => Some(parser),
+            };
+
+            let released = db
+                .redrive_parse_failures(parser)
+                .await
+                .expect("Unable to redrive the parse failures.");
+
+            info!("Released {} quarantined logs to parse again.", released);
+        }
+        None => (),
+    }
 
     if config.llamafolio_adapter {
         info!("Starting the LlamaFolio adapters fetcher.");
@@ -48,7 +115,7 @@ async fn main() {
 
                     llamafolio_adapters.parse(&db, &adapters).await.unwrap();
 
-                    sleep(Duration::from_secs(1800))
+                    sleep(Duration::from_secs(1800)).await
                 }
             }
         });
@@ -69,7 +136,441 @@ async fn main() {
 
                     erc20_tokens_parser.parse(&db, &transfers).await.unwrap();
 
-                    sleep(Duration::from_secs(2))
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.lending_parser {
+        info!("Starting the lending events parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            let deployments = register_deployments(
+                &db,
+                load_lending_deployments(&config.lending_deployments),
+                &LENDING_PROTOCOLS,
+            )
+            .expect("Unable to register the lending deployments.");
+            let dedup = dedup.clone();
+            async move {
+                let lending_parser = Arc::new(LendingParser::new(deployments, dedup));
+
+                loop {
+                    let logs = lending_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} lending logs to parse.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let lending_parser = lending_parser.clone();
+                        let db = db.clone();
+                        async move { lending_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.dex_swaps_parser {
+        info!("Starting the dex swaps parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            async move {
+                let dex_swaps_parser = Arc::new(DexSwapsParser::new());
+
+                loop {
+                    let logs = dex_swaps_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} dex swap logs to parse.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let dex_swaps_parser = dex_swaps_parser.clone();
+                        let db = db.clone();
+                        async move { dex_swaps_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.liquidity_parser {
+        info!("Starting the liquidity events parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let deployments = register_deployments(
+                &db,
+                get_default_liquidity_deployments(),
+                &LIQUIDITY_PROTOCOLS,
+            )
+            .expect("Unable to register the liquidity deployments.");
+            async move {
+                let liquidity_parser = LiquidityParser::new(deployments);
+
+                loop {
+                    let logs = liquidity_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} liquidity logs to parse.", logs.len());
+
+                    liquidity_parser.parse(&db, &logs).await.unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.token_prices_parser {
+        info!("Starting the token prices parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                loop {
+                    let token_prices_parser = TokenPricesParser {};
+
+                    let swaps = token_prices_parser.fetch(&db).unwrap();
+
+                    info!("Fetched {} dex swaps to price.", swaps.len());
+
+                    token_prices_parser.parse(&db, &swaps).await.unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.mev_parser {
+        info!("Starting the mev parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                loop {
+                    let mev_parser = MevParser {};
+
+                    let swaps = mev_parser.fetch(&db).unwrap();
+
+                    info!("Fetched {} dex swaps to analyze.", swaps.len());
+
+                    mev_parser.parse(&db, &swaps).await.unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.flash_loans_parser {
+        info!("Starting the flash loans parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            async move {
+                let flash_loans_parser = Arc::new(FlashLoansParser::new());
+
+                loop {
+                    let logs = flash_loans_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} flash loan logs to parse.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let flash_loans_parser = flash_loans_parser.clone();
+                        let db = db.clone();
+                        async move { flash_loans_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.bridge_parser {
+        info!("Starting the bridge transfers parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            let deployments = register_deployments(
+                &db,
+                load_bridge_deployments(&config.bridge_deployments),
+                &BRIDGE_PROTOCOLS,
+            )
+            .expect("Unable to register the bridge deployments.");
+            let dedup = dedup.clone();
+            async move {
+                let bridge_parser = Arc::new(BridgeParser::new(deployments, dedup));
+
+                loop {
+                    let logs = bridge_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} bridge logs to parse.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let bridge_parser = bridge_parser.clone();
+                        let db = db.clone();
+                        async move { bridge_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.governance_parser {
+        info!("Starting the governance parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                let governance_parser = GovernanceParser::new();
+
+                loop {
+                    let logs = governance_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} governance logs to parse.", logs.len());
+
+                    governance_parser.parse(&db, &logs).await.unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.staking_parser {
+        info!("Starting the staking parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            let deployments = register_deployments(
+                &db,
+                load_staking_deployments(&config.staking_deployments),
+                &STAKING_PROTOCOLS,
+            )
+            .expect("Unable to register the staking deployments.");
+            let dedup = dedup.clone();
+            async move {
+                let staking_parser = Arc::new(StakingParser::new(deployments, dedup));
+
+                loop {
+                    let logs = staking_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} staking logs to parse.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let staking_parser = staking_parser.clone();
+                        let db = db.clone();
+                        async move { staking_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.ens_parser {
+        info!("Starting the ENS parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                let ens_parser = ENSParser::new();
+
+                loop {
+                    let logs = ens_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} ENS logs to parse.", logs.len());
+
+                    ens_parser.parse(&db, &logs).await.unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.permits_parser {
+        info!("Starting the permits parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            async move {
+                let permits_parser = Arc::new(PermitsParser::new());
+
+                loop {
+                    let logs = permits_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} approval logs to parse.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let permits_parser = permits_parser.clone();
+                        let db = db.clone();
+                        async move { permits_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.spam_tokens_parser {
+        info!("Starting the spam tokens parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                let spam_tokens_parser = SpamTokensParser {};
+
+                loop {
+                    let tokens = spam_tokens_parser.fetch(&db).unwrap();
+
+                    info!("Fetched {} tokens to score.", tokens.len());
+
+                    spam_tokens_parser.parse(&db, &tokens).await.unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    if config.decoded_logs_parser {
+        info!("Starting the decoded logs parser.");
+
+        tokio::spawn({
+            let db = db.clone();
+            let workers = config.parser_workers;
+            async move {
+                let decoded_logs_parser = Arc::new(DecodedLogsParser {});
+
+                loop {
+                    let logs = decoded_logs_parser.fetch(&db).await.unwrap();
+
+                    info!("Fetched {} logs with known ABIs to decode.", logs.len());
+
+                    parse_in_chunks(logs, workers, |chunk| {
+                        let decoded_logs_parser = decoded_logs_parser.clone();
+                        let db = db.clone();
+                        async move { decoded_logs_parser.parse(&db, &chunk).await }
+                    })
+                    .await
+                    .unwrap();
+
+                    sleep(Duration::from_secs(2)).await
+                }
+            }
+        });
+    }
+
+    match config.external_prices {
+        Some(provider) => {
+            info!("Starting the {} prices fetcher.", provider.name());
+
+            tokio::spawn({
+                let db = db.clone();
+                let api_key = config.external_prices_api_key.clone();
+                let history_days = config.external_prices_days;
+                let interval = config.external_prices_interval;
+                async move {
+                    let mut fetcher = ExternalPricesFetcher::new(provider, api_key, history_days);
+
+                    loop {
+                        match fetcher.run(&db).await {
+                            Ok(_) => (),
+                            Err(err) => warn!("Unable to fetch the external prices: {}", err),
+                        }
+
+                        sleep(Duration::from_secs(interval)).await
+                    }
+                }
+            });
+        }
+        None => (),
+    }
+
+    if config.refresh_views {
+        let refresher = ViewRefresher::new(config.views.clone(), config.views_interval)
+            .expect("Unable to start the views refresher.");
+
+        info!(
+            "Starting the refresher of the materialized views {}.",
+            refresher.views.join(", ")
+        );
+
+        tokio::spawn({
+            let db = db.clone();
+            async move {
+                loop {
+                    match refresher.refresh_due(&db) {
+                        Ok(_) => (),
+                        Err(err) => warn!("Unable to refresh the materialized views: {}", err),
+                    }
+
+                    sleep(Duration::from_secs(30)).await
+                }
+            }
+        });
+    }
+
+    let reducers = get_reducers(&config.reducers).expect("Unable to start the reducers.");
+
+    for reducer in reducers {
+        info!("Starting the {} reducer.", reducer.name());
+
+        tokio::spawn({
+            let runner = ReducerRunner::new(db.clone(), reducer);
+            async move {
+                let mut last_block = 0;
+
+                loop {
+                    match runner.run() {
+                        Ok(block) => {
+                            // Reduced up to the head or rolled back, wait for new blocks.
+                            if block <= last_block {
+                                sleep(Duration::from_secs(5)).await
+                            }
+
+                            last_block = block;
+                        }
+                        Err(err) => {
+                            warn!(
+                                "Unable to run the {} reducer: {}",
+                                runner.reducer.name(),
+                                err
+                            );
+
+                            sleep(Duration::from_secs(5)).await
+                        }
+                    }
                 }
             }
         });
@@ -77,15 +578,25 @@ async fn main() {
 
     info!("Starting the ERC20 Transfers parser.");
 
-    loop {
-        let erc20_transfers_parser = ERC20TransfersParser {};
+    let erc20_transfers_parser = Arc::new(ERC20TransfersParser {
+        publish_events: config.publish_events,
+        notify: config.notify,
+        dedup,
+    });
 
+    loop {
         let logs = erc20_transfers_parser.fetch(&db).unwrap();
 
         info!("Fetched {} logs to parse.", logs.len());
 
-        erc20_transfers_parser.parse(&db, &logs).await.unwrap();
+        parse_in_chunks(logs, config.parser_workers, |chunk| {
+            let erc20_transfers_parser = erc20_transfers_parser.clone();
+            let db = db.clone();
+            async move { erc20_transfers_parser.parse(&db, &chunk).await }
+        })
+        .await
+        .unwrap();
 
-        sleep(Duration::from_secs(2))
+        sleep(Duration::from_secs(2)).await
     }
 }
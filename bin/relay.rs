@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use dotenv::dotenv;
+use evm_indexer::{
+    chains::chains::ETHEREUM,
+    configs::relay_config::{EVMRelayConfig, EVMRelaySink},
+    db::db::EVMDatabase,
+    sinks::{nats::NatsSink, outbox::OutboxRelay, sink::Sink, webhook::WebhookSink},
+};
+
+#[cfg(feature = "bigquery")]
+use evm_indexer::sinks::bigquery::BigQuerySink;
+use log::*;
+use simple_logger::SimpleLogger;
+use tokio::time::sleep;
+
+#[tokio::main()]
+async fn main() {
+    dotenv().ok();
+
+    let log = SimpleLogger::new().with_level(LevelFilter::Info);
+
+    let config = EVMRelayConfig::new();
+
+    if config.debug {
+        log.with_level(LevelFilter::Debug).init().unwrap();
+    } else {
+        log.init().unwrap();
+    }
+
+    info!("Starting EVM Relay.");
+
+    let db = EVMDatabase::new(
+        config.db_url.clone(),
+        Vec::new(),
+        config.db_schema.clone(),
+        config.redis_url.clone(),
+        ETHEREUM,
+    )
+    .await
+    .expect("Unable to start DB connection.");
+
+    let sink: Box<dyn Sink> = match &config.sink {
+        EVMRelaySink::Webhook(url) => {
+            Box::new(WebhookSink::new(config.sink_name.clone(), url.clone()))
+        }
+        #[cfg(feature = "bigquery")]
+        EVMRelaySink::BigQuery {
+            project,
+            dataset,
+            table_prefix,
+        } => Box::new(
+            BigQuerySink::new(
+                config.sink_name.clone(),
+                project.clone(),
+                dataset.clone(),
+                table_prefix.clone(),
+            )
+            .expect("Unable to start BigQuery sink."),
+        ),
+        #[cfg(not(feature = "bigquery"))]
+        EVMRelaySink::BigQuery { .. } => {
+            eprintln!("The relay must be built with the bigquery feature to use a BigQuery sink");
+            std::process::exit(1)
+        }
+        EVMRelaySink::Nats {
+            url,
+            subject_prefix,
+            subjects,
+        } => Box::new(
+            NatsSink::new(
+                config.sink_name.clone(),
+                url.clone(),
+                subject_prefix.clone(),
+                subjects.clone(),
+            )
+            .await
+            .expect("Unable to start NATS sink."),
+        ),
+    };
+
+    let relay = OutboxRelay::new(db, sink, config.batch_size);
+
+    match config.replay_from {
+        Some(id) => relay.replay_from(id).await.unwrap(),
+        None => (),
+    }
+
+    loop {
+        match relay.relay().await {
+            Ok(delivered) => {
+                if delivered > 0 {
+                    info!("Delivered {} outbox events.", delivered);
+                    continue;
+                }
+            }
+            Err(err) => warn!("Unable to relay outbox events: {}", err),
+        }
+
+        sleep(Duration::from_secs(2)).await;
+    }
+}
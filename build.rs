@@ -0,0 +1,15 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The gRPC service is only compiled with the API server.
+    if std::env::var("CARGO_FEATURE_API").is_ok() {
+        tonic_build::compile_protos("proto/indexer.proto")?;
+    }
+
+    // The BigQuery sink appends rows through the Storage Write API client.
+    if std::env::var("CARGO_FEATURE_BIGQUERY").is_ok() {
+        tonic_build::configure()
+            .build_server(false)
+            .compile(&["proto/bigquery_storage.proto"], &["proto"])?;
+    }
+
+    Ok(())
+}
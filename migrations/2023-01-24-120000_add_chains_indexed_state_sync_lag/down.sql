@@ -0,0 +1 @@
+ALTER TABLE chains_indexed_state DROP sync_lag;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE chains_indexed_state ADD COLUMN sync_lag BIGINT;
\ No newline at end of file
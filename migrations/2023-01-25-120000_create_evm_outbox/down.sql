@@ -0,0 +1,3 @@
+DROP TABLE evm_outbox;
+
+DROP TABLE evm_outbox_offsets;
\ No newline at end of file
@@ -0,0 +1,11 @@
+CREATE TABLE evm_outbox (
+  id BIGSERIAL PRIMARY KEY,
+  chain TEXT NOT NULL,
+  topic TEXT NOT NULL,
+  payload TEXT NOT NULL
+);
+
+CREATE TABLE evm_outbox_offsets (
+  sink TEXT PRIMARY KEY NOT NULL,
+  last_id BIGINT NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE evm_erc20_supply_changes;
+
+DROP TABLE evm_erc20_supply;
\ No newline at end of file
@@ -0,0 +1,16 @@
+CREATE TABLE evm_erc20_supply_changes (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  token TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  amount TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_erc20_supply_changes_by_token
+ON evm_erc20_supply_changes (token);
+
+CREATE TABLE evm_erc20_supply (
+  token TEXT PRIMARY KEY NOT NULL,
+  supply NUMERIC NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,5 @@
+DROP TABLE evm_parsed_logs;
+
+DROP INDEX evm_transactions_logs_by_address;
+
+DROP TABLE evm_lending_events;
\ No newline at end of file
@@ -0,0 +1,29 @@
+CREATE TABLE evm_parsed_logs (
+  parser TEXT NOT NULL,
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  PRIMARY KEY (parser, hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_transactions_logs_by_address
+ON evm_transactions_logs (address);
+
+CREATE TABLE evm_lending_events (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  market TEXT NOT NULL,
+  action TEXT NOT NULL,
+  user_address TEXT NOT NULL,
+  amount TEXT NOT NULL,
+  liquidator TEXT,
+  collateral TEXT,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_lending_events_by_user
+ON evm_lending_events (user_address);
+
+CREATE INDEX IF NOT EXISTS evm_lending_events_by_market
+ON evm_lending_events (market);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_dex_swaps;
\ No newline at end of file
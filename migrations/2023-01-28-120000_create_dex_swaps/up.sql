@@ -0,0 +1,22 @@
+CREATE TABLE evm_dex_swaps (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  dex TEXT NOT NULL,
+  pool TEXT NOT NULL,
+  sender TEXT NOT NULL,
+  recipient TEXT,
+  token_in TEXT,
+  token_out TEXT,
+  token_in_index BIGINT,
+  token_out_index BIGINT,
+  amount_in TEXT NOT NULL,
+  amount_out TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_dex_swaps_by_pool
+ON evm_dex_swaps (pool);
+
+CREATE INDEX IF NOT EXISTS evm_dex_swaps_by_dex
+ON evm_dex_swaps (dex);
\ No newline at end of file
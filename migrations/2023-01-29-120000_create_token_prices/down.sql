@@ -0,0 +1,3 @@
+DROP TABLE evm_token_prices;
+
+DROP TABLE evm_dex_pools;
\ No newline at end of file
@@ -0,0 +1,16 @@
+CREATE TABLE evm_dex_pools (
+  pool TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  tokens TEXT[] NOT NULL,
+  PRIMARY KEY (pool, chain)
+);
+
+CREATE TABLE evm_token_prices (
+  chain TEXT NOT NULL,
+  token TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  price_native DOUBLE PRECISION,
+  price_usd DOUBLE PRECISION,
+  swaps BIGINT NOT NULL,
+  PRIMARY KEY (chain, token, block_number)
+);
\ No newline at end of file
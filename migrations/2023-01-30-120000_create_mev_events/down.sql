@@ -0,0 +1 @@
+DROP TABLE evm_mev_events;
\ No newline at end of file
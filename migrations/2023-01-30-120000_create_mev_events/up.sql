@@ -0,0 +1,14 @@
+CREATE TABLE evm_mev_events (
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  kind TEXT NOT NULL,
+  attacker TEXT NOT NULL,
+  victim TEXT,
+  pools TEXT[] NOT NULL,
+  transactions TEXT[] NOT NULL,
+  profit TEXT,
+  PRIMARY KEY (chain, block_number, kind, transactions)
+);
+
+CREATE INDEX IF NOT EXISTS evm_mev_events_by_attacker
+ON evm_mev_events (attacker);
\ No newline at end of file
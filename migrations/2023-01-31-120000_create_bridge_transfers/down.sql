@@ -0,0 +1 @@
+DROP TABLE evm_bridge_transfers;
\ No newline at end of file
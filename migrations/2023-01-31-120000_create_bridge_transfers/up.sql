@@ -0,0 +1,20 @@
+CREATE TABLE evm_bridge_transfers (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  direction TEXT NOT NULL,
+  status TEXT NOT NULL,
+  user_address TEXT NOT NULL,
+  recipient TEXT,
+  token TEXT,
+  amount TEXT NOT NULL,
+  message_id TEXT,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_bridge_transfers_by_user
+ON evm_bridge_transfers (user_address);
+
+CREATE INDEX IF NOT EXISTS evm_bridge_transfers_by_message
+ON evm_bridge_transfers (protocol, message_id);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE evm_governance_votes;
+
+DROP TABLE evm_governance_proposals;
\ No newline at end of file
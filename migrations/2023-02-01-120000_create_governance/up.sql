@@ -0,0 +1,36 @@
+CREATE TABLE evm_governance_proposals (
+  chain TEXT NOT NULL,
+  governor TEXT NOT NULL,
+  proposal_id TEXT NOT NULL,
+  hash TEXT NOT NULL,
+  proposer TEXT NOT NULL,
+  targets TEXT[] NOT NULL,
+  call_values TEXT[] NOT NULL,
+  signatures TEXT[] NOT NULL,
+  calldatas TEXT[] NOT NULL,
+  decoded_calls TEXT,
+  vote_start TEXT NOT NULL,
+  vote_end TEXT NOT NULL,
+  description TEXT NOT NULL,
+  status TEXT NOT NULL,
+  PRIMARY KEY (chain, governor, proposal_id)
+);
+
+CREATE TABLE evm_governance_votes (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  governor TEXT NOT NULL,
+  proposal_id TEXT NOT NULL,
+  voter TEXT NOT NULL,
+  support BIGINT NOT NULL,
+  weight TEXT NOT NULL,
+  reason TEXT,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_governance_votes_by_proposal
+ON evm_governance_votes (chain, governor, proposal_id);
+
+CREATE INDEX IF NOT EXISTS evm_governance_votes_by_voter
+ON evm_governance_votes (voter);
\ No newline at end of file
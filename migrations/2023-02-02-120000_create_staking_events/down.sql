@@ -0,0 +1,3 @@
+DROP TABLE evm_staking_rewards;
+
+DROP TABLE evm_staking_events;
\ No newline at end of file
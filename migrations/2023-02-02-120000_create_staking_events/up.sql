@@ -0,0 +1,27 @@
+CREATE TABLE evm_staking_events (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  action TEXT NOT NULL,
+  user_address TEXT NOT NULL,
+  amount TEXT NOT NULL,
+  shares TEXT,
+  request_id TEXT,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_staking_events_by_user
+ON evm_staking_events (user_address);
+
+CREATE TABLE evm_staking_rewards (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  total_pooled TEXT NOT NULL,
+  total_shares TEXT NOT NULL,
+  rewards TEXT,
+  report_timestamp TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
\ No newline at end of file
@@ -0,0 +1,5 @@
+DROP TABLE evm_ens_reverse_nodes;
+
+DROP TABLE evm_ens_records;
+
+DROP TABLE evm_ens_registrations;
\ No newline at end of file
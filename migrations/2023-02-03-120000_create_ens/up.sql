@@ -0,0 +1,38 @@
+CREATE TABLE evm_ens_registrations (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  block_number BIGINT NOT NULL,
+  action TEXT NOT NULL,
+  name TEXT NOT NULL,
+  label TEXT NOT NULL,
+  node TEXT NOT NULL,
+  owner TEXT,
+  expires TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_ens_registrations_by_node
+ON evm_ens_registrations (node);
+
+CREATE TABLE evm_ens_records (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  block_number BIGINT NOT NULL,
+  resolver TEXT NOT NULL,
+  node TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_ens_records_by_node
+ON evm_ens_records (node, kind, block_number);
+
+CREATE TABLE evm_ens_reverse_nodes (
+  node TEXT NOT NULL,
+  address TEXT NOT NULL,
+  PRIMARY KEY (node)
+);
+
+CREATE INDEX IF NOT EXISTS evm_ens_reverse_nodes_by_address
+ON evm_ens_reverse_nodes (address);
\ No newline at end of file
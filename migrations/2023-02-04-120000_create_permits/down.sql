@@ -0,0 +1 @@
+DROP TABLE evm_permits;
\ No newline at end of file
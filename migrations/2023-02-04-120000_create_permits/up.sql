@@ -0,0 +1,16 @@
+CREATE TABLE evm_permits (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  token TEXT NOT NULL,
+  owner TEXT NOT NULL,
+  spender TEXT NOT NULL,
+  value TEXT NOT NULL,
+  deadline TEXT,
+  kind TEXT NOT NULL,
+  nested BOOLEAN NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_permits_by_token
+ON evm_permits (token);
\ No newline at end of file
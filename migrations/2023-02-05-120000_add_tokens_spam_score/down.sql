@@ -0,0 +1,3 @@
+ALTER TABLE evm_erc20_tokens DROP COLUMN spam_reasons;
+
+ALTER TABLE evm_erc20_tokens DROP COLUMN spam_score;
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE evm_erc20_tokens ADD COLUMN spam_score BIGINT;
+
+ALTER TABLE evm_erc20_tokens ADD COLUMN spam_reasons TEXT[];
\ No newline at end of file
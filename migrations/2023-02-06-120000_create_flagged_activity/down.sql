@@ -0,0 +1 @@
+DROP TABLE evm_flagged_activity;
\ No newline at end of file
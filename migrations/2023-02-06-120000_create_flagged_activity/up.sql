@@ -0,0 +1,14 @@
+CREATE TABLE evm_flagged_activity (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  kind TEXT NOT NULL,
+  address TEXT NOT NULL,
+  counterparty TEXT NOT NULL,
+  list TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index, address)
+);
+
+CREATE INDEX IF NOT EXISTS evm_flagged_activity_by_address
+ON evm_flagged_activity (address);
\ No newline at end of file
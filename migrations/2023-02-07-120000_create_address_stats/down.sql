@@ -0,0 +1 @@
+DROP TABLE evm_address_stats;
\ No newline at end of file
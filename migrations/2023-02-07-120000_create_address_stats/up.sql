@@ -0,0 +1,11 @@
+CREATE TABLE evm_address_stats (
+  address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  first_seen_block BIGINT NOT NULL,
+  last_active_block BIGINT NOT NULL,
+  transactions_sent BIGINT NOT NULL,
+  transactions_received BIGINT NOT NULL,
+  gas_spent NUMERIC NOT NULL,
+  contracts_deployed BIGINT NOT NULL,
+  PRIMARY KEY (address, chain)
+);
\ No newline at end of file
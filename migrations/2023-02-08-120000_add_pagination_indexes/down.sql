@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS evm_transactions_by_sender_position;
+
+DROP INDEX IF EXISTS evm_transactions_by_receiver_position;
\ No newline at end of file
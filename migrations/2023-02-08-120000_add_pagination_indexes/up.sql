@@ -0,0 +1,5 @@
+CREATE INDEX IF NOT EXISTS evm_transactions_by_sender_position
+ON evm_transactions (from_address, block_number DESC, transaction_index DESC);
+
+CREATE INDEX IF NOT EXISTS evm_transactions_by_receiver_position
+ON evm_transactions (to_address, block_number DESC, transaction_index DESC);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_api_keys;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE evm_api_keys (
+  key TEXT NOT NULL,
+  name TEXT NOT NULL,
+  rate_limit BIGINT,
+  requests BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (key)
+);
\ No newline at end of file
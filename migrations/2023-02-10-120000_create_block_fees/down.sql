@@ -0,0 +1,3 @@
+DROP VIEW evm_supply_changes;
+
+DROP TABLE evm_block_fees;
\ No newline at end of file
@@ -0,0 +1,25 @@
+CREATE TABLE evm_block_fees (
+  chain TEXT NOT NULL,
+  number BIGINT NOT NULL,
+  block_hash TEXT NOT NULL,
+  base_fee_per_gas NUMERIC NOT NULL,
+  gas_used NUMERIC NOT NULL,
+  burned NUMERIC NOT NULL,
+  priority_fees NUMERIC NOT NULL,
+  block_reward NUMERIC NOT NULL,
+  PRIMARY KEY (chain, number)
+);
+
+CREATE VIEW evm_supply_changes AS
+SELECT
+  chain,
+  number,
+  burned,
+  priority_fees,
+  block_reward,
+  block_reward - burned AS supply_change,
+  SUM(burned) OVER (PARTITION BY chain ORDER BY number) AS total_burned,
+  SUM(priority_fees) OVER (PARTITION BY chain ORDER BY number) AS total_priority_fees,
+  SUM(block_reward) OVER (PARTITION BY chain ORDER BY number) AS total_block_rewards,
+  SUM(block_reward - burned) OVER (PARTITION BY chain ORDER BY number) AS total_supply_change
+FROM evm_block_fees;
\ No newline at end of file
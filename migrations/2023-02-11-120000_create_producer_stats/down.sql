@@ -0,0 +1 @@
+DROP TABLE evm_producer_stats;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE evm_producer_stats (
+  miner TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  first_block BIGINT NOT NULL,
+  last_block BIGINT NOT NULL,
+  blocks_produced BIGINT NOT NULL,
+  empty_blocks BIGINT NOT NULL,
+  fees_earned NUMERIC NOT NULL,
+  rewards_earned NUMERIC NOT NULL,
+  gas_used NUMERIC NOT NULL,
+  average_gas_used NUMERIC NOT NULL,
+  PRIMARY KEY (miner, chain)
+);
\ No newline at end of file
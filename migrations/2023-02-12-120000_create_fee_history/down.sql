@@ -0,0 +1 @@
+DROP TABLE evm_fee_history;
\ No newline at end of file
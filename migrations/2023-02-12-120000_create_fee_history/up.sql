@@ -0,0 +1,9 @@
+CREATE TABLE evm_fee_history (
+  chain TEXT NOT NULL,
+  number BIGINT NOT NULL,
+  base_fee_per_gas NUMERIC NOT NULL,
+  gas_used_ratio DOUBLE PRECISION NOT NULL,
+  reward_percentiles DOUBLE PRECISION[] NOT NULL,
+  rewards NUMERIC[] NOT NULL,
+  PRIMARY KEY (chain, number)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_storage_values;
\ No newline at end of file
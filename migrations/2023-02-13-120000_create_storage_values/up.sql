@@ -0,0 +1,9 @@
+CREATE TABLE evm_storage_values (
+  chain TEXT NOT NULL,
+  contract TEXT NOT NULL,
+  slot TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  name TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY (chain, contract, slot, block_number)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_call_samples;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE evm_call_samples (
+  chain TEXT NOT NULL,
+  name TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  contract TEXT NOT NULL,
+  function TEXT NOT NULL,
+  result TEXT NOT NULL,
+  PRIMARY KEY (chain, name, block_number)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_state_diffs;
\ No newline at end of file
@@ -0,0 +1,14 @@
+CREATE TABLE evm_state_diffs (
+  hash TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  transaction_index BIGINT NOT NULL,
+  address TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  slot TEXT NOT NULL,
+  before TEXT,
+  after TEXT,
+  PRIMARY KEY (hash, address, kind, slot)
+);
+
+CREATE INDEX evm_state_diffs_by_address ON evm_state_diffs (chain, address, block_number, transaction_index);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_call_frames;
\ No newline at end of file
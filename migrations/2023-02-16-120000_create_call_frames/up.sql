@@ -0,0 +1,18 @@
+CREATE TABLE evm_call_frames (
+  hash TEXT NOT NULL,
+  trace_address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  depth BIGINT NOT NULL,
+  call_type TEXT NOT NULL,
+  from_address TEXT NOT NULL,
+  to_address TEXT NOT NULL,
+  value TEXT NOT NULL,
+  selector TEXT NOT NULL,
+  gas TEXT NOT NULL,
+  gas_used TEXT NOT NULL,
+  error TEXT,
+  PRIMARY KEY (hash, trace_address)
+);
+
+CREATE INDEX evm_call_frames_by_to_address ON evm_call_frames (chain, to_address);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_native_transfers;
\ No newline at end of file
@@ -0,0 +1,16 @@
+CREATE TABLE evm_native_transfers (
+  hash TEXT NOT NULL,
+  trace_address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  transaction_index BIGINT NOT NULL,
+  kind TEXT NOT NULL,
+  from_address TEXT NOT NULL,
+  to_address TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY (chain, hash, trace_address)
+);
+
+CREATE INDEX evm_native_transfers_by_from_address ON evm_native_transfers (chain, from_address);
+
+CREATE INDEX evm_native_transfers_by_to_address ON evm_native_transfers (chain, to_address);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE evm_transactions_receipts DROP COLUMN revert_reason;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE evm_transactions_receipts ADD COLUMN revert_reason TEXT;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_liquidity_events;
\ No newline at end of file
@@ -0,0 +1,24 @@
+CREATE TABLE evm_liquidity_events (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  dex TEXT NOT NULL,
+  contract TEXT NOT NULL,
+  pool TEXT,
+  event TEXT NOT NULL,
+  position_id TEXT,
+  owner TEXT,
+  recipient TEXT,
+  tick_lower BIGINT,
+  tick_upper BIGINT,
+  liquidity TEXT,
+  amount0 TEXT NOT NULL,
+  amount1 TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_liquidity_events_by_pool
+ON evm_liquidity_events (pool);
+
+CREATE INDEX IF NOT EXISTS evm_liquidity_events_by_position
+ON evm_liquidity_events (contract, position_id);
\ No newline at end of file
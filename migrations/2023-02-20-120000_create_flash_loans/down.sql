@@ -0,0 +1 @@
+DROP TABLE evm_flash_loans;
\ No newline at end of file
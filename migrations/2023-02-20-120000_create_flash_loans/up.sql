@@ -0,0 +1,20 @@
+CREATE TABLE evm_flash_loans (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  token_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  lender TEXT NOT NULL,
+  initiator TEXT,
+  receiver TEXT,
+  token TEXT,
+  amount TEXT NOT NULL,
+  fee TEXT,
+  PRIMARY KEY (hash, log_index, token_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_flash_loans_by_receiver
+ON evm_flash_loans (receiver);
+
+CREATE INDEX IF NOT EXISTS evm_flash_loans_by_token
+ON evm_flash_loans (token);
\ No newline at end of file
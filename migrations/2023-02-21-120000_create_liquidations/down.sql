@@ -0,0 +1 @@
+DROP TABLE evm_liquidations;
\ No newline at end of file
@@ -0,0 +1,26 @@
+CREATE TABLE evm_liquidations (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  protocol TEXT NOT NULL,
+  liquidator TEXT,
+  borrower TEXT NOT NULL,
+  collateral_token TEXT,
+  collateral_amount TEXT,
+  debt_token TEXT NOT NULL,
+  debt_amount TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_liquidations_by_borrower
+ON evm_liquidations (borrower);
+
+CREATE INDEX IF NOT EXISTS evm_liquidations_by_protocol
+ON evm_liquidations (protocol, block_number);
+
+INSERT INTO evm_liquidations
+SELECT l.hash, l.log_index, l.chain, t.block_number, l.protocol, l.liquidator,
+l.user_address, l.collateral, NULL, l.market, l.amount
+FROM evm_lending_events l JOIN evm_transactions t ON t.hash = l.hash
+WHERE l.action = 'liquidation';
\ No newline at end of file
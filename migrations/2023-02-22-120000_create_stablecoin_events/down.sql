@@ -0,0 +1,3 @@
+DROP TABLE evm_stablecoin_blacklist;
+
+DROP TABLE evm_stablecoin_events;
\ No newline at end of file
@@ -0,0 +1,28 @@
+CREATE TABLE evm_stablecoin_events (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  token TEXT NOT NULL,
+  symbol TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  account TEXT NOT NULL,
+  counterparty TEXT,
+  amount TEXT,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_stablecoin_events_by_token
+ON evm_stablecoin_events (token, kind, block_number);
+
+CREATE INDEX IF NOT EXISTS evm_stablecoin_events_by_account
+ON evm_stablecoin_events (account);
+
+CREATE TABLE evm_stablecoin_blacklist (
+  chain TEXT NOT NULL,
+  token TEXT NOT NULL,
+  account TEXT NOT NULL,
+  blacklisted BOOLEAN NOT NULL,
+  block_number BIGINT NOT NULL,
+  PRIMARY KEY (chain, token, account)
+);
\ No newline at end of file
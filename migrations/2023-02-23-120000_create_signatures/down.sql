@@ -0,0 +1 @@
+DROP TABLE evm_signatures;
\ No newline at end of file
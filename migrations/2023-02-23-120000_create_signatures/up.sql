@@ -0,0 +1,7 @@
+CREATE TABLE evm_signatures (
+  hash TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  signature TEXT NOT NULL,
+  name TEXT NOT NULL,
+  PRIMARY KEY (hash, signature)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_rpc_usage;
\ No newline at end of file
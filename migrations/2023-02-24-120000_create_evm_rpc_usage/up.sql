@@ -0,0 +1,10 @@
+CREATE TABLE evm_rpc_usage (
+  chain TEXT NOT NULL,
+  provider TEXT NOT NULL,
+  method TEXT NOT NULL,
+  requests BIGINT NOT NULL,
+  errors BIGINT NOT NULL,
+  compute_units BIGINT NOT NULL,
+  credits BIGINT NOT NULL,
+  PRIMARY KEY (chain, provider, method)
+);
\ No newline at end of file
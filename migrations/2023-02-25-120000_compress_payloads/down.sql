@@ -0,0 +1,5 @@
+DROP TABLE evm_compression_dictionaries;
+
+ALTER TABLE evm_transactions_logs DROP COLUMN data_zstd;
+
+ALTER TABLE evm_transactions DROP COLUMN input_zstd;
\ No newline at end of file
@@ -0,0 +1,10 @@
+ALTER TABLE evm_transactions ADD COLUMN input_zstd BYTEA;
+
+ALTER TABLE evm_transactions_logs ADD COLUMN data_zstd BYTEA;
+
+CREATE TABLE evm_compression_dictionaries (
+  chain TEXT NOT NULL,
+  payload TEXT NOT NULL,
+  dictionary BYTEA NOT NULL,
+  PRIMARY KEY (chain, payload)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_parse_failures;
\ No newline at end of file
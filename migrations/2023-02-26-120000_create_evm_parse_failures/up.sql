@@ -0,0 +1,7 @@
+CREATE TABLE evm_parse_failures (
+  parser TEXT NOT NULL,
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  reason TEXT NOT NULL,
+  PRIMARY KEY (parser, hash, log_index)
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP INDEX evm_transactions_logs_by_decoded;
+
+ALTER TABLE evm_transactions_logs DROP COLUMN decoded;
\ No newline at end of file
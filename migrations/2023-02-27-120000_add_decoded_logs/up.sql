@@ -0,0 +1,4 @@
+ALTER TABLE evm_transactions_logs ADD COLUMN decoded JSONB;
+
+CREATE INDEX IF NOT EXISTS evm_transactions_logs_by_decoded
+ON evm_transactions_logs USING GIN (decoded jsonb_path_ops);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE evm_erc20_transfers DROP COLUMN value_decimal;
\ No newline at end of file
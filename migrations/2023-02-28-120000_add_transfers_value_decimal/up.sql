@@ -0,0 +1,7 @@
+ALTER TABLE evm_erc20_transfers ADD COLUMN value_decimal NUMERIC;
+
+UPDATE evm_erc20_transfers tr
+SET value_decimal = (tr.value || 'e-' || k.decimals)::NUMERIC
+FROM evm_transactions t, evm_erc20_tokens k
+WHERE t.hash = tr.hash AND k.chain = t.chain AND k.address = tr.token
+AND k.decimals BETWEEN 0 AND 255;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE evm_erc20_tokens DROP COLUMN logo;
\ No newline at end of file
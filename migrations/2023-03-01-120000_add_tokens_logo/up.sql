@@ -0,0 +1 @@
+ALTER TABLE evm_erc20_tokens ADD COLUMN logo TEXT;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_token_prices_external;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE evm_token_prices_external (
+  chain TEXT NOT NULL,
+  token TEXT NOT NULL,
+  source TEXT NOT NULL,
+  timestamp BIGINT NOT NULL,
+  price_usd DOUBLE PRECISION NOT NULL,
+  PRIMARY KEY (chain, token, source, timestamp)
+);
\ No newline at end of file
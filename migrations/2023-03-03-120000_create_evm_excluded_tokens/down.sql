@@ -0,0 +1 @@
+DROP TABLE evm_excluded_tokens;
\ No newline at end of file
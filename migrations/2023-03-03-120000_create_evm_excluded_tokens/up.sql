@@ -0,0 +1,11 @@
+CREATE TABLE evm_excluded_tokens (
+  address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  reason TEXT NOT NULL,
+  attempts BIGINT NOT NULL,
+  retry_after BIGINT,
+  PRIMARY KEY (address, chain)
+);
+
+CREATE INDEX IF NOT EXISTS evm_excluded_tokens_by_retry_after
+ON evm_excluded_tokens (retry_after);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_new_addresses;
\ No newline at end of file
@@ -0,0 +1,11 @@
+CREATE TABLE evm_new_addresses (
+  address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  hash TEXT NOT NULL,
+  role TEXT NOT NULL,
+  PRIMARY KEY (address, chain)
+);
+
+CREATE INDEX IF NOT EXISTS evm_new_addresses_by_block
+ON evm_new_addresses (chain, block_number);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_address_clusters;
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE evm_address_clusters (
+  address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  cluster TEXT NOT NULL,
+  heuristics TEXT[] NOT NULL,
+  PRIMARY KEY (address, chain)
+);
+
+CREATE INDEX IF NOT EXISTS evm_address_clusters_by_cluster
+ON evm_address_clusters (chain, cluster);
\ No newline at end of file
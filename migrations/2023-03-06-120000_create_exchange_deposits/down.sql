@@ -0,0 +1,3 @@
+DROP TABLE evm_exchange_deposits;
+
+DROP TABLE evm_address_labels;
\ No newline at end of file
@@ -0,0 +1,22 @@
+CREATE TABLE evm_address_labels (
+  address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  label TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  PRIMARY KEY (address, chain)
+);
+
+CREATE TABLE evm_exchange_deposits (
+  address TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  exchange TEXT NOT NULL,
+  hot_wallet TEXT NOT NULL,
+  forwards BIGINT NOT NULL,
+  transfers_out BIGINT NOT NULL,
+  senders BIGINT NOT NULL,
+  confidence DOUBLE PRECISION NOT NULL,
+  PRIMARY KEY (address, chain)
+);
+
+CREATE INDEX IF NOT EXISTS evm_exchange_deposits_by_exchange
+ON evm_exchange_deposits (chain, exchange);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_log_transactions;
\ No newline at end of file
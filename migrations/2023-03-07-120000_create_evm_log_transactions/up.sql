@@ -0,0 +1,10 @@
+CREATE TABLE evm_log_transactions (
+  hash TEXT PRIMARY KEY,
+  chain TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  block_hash TEXT NOT NULL,
+  transaction_index BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS evm_log_transactions_by_block
+ON evm_log_transactions (chain, block_number);
\ No newline at end of file
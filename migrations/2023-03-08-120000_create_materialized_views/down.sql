@@ -0,0 +1,7 @@
+DROP MATERIALIZED VIEW evm_gas_leaders;
+
+DROP MATERIALIZED VIEW evm_busiest_contracts;
+
+DROP MATERIALIZED VIEW evm_top_tokens_by_volume;
+
+DROP TABLE evm_view_refreshes;
\ No newline at end of file
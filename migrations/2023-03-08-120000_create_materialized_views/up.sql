@@ -0,0 +1,56 @@
+CREATE TABLE evm_view_refreshes (
+  name TEXT PRIMARY KEY,
+  refreshed_at BIGINT,
+  duration_ms BIGINT,
+  row_count BIGINT,
+  refreshes BIGINT NOT NULL DEFAULT 0,
+  failures BIGINT NOT NULL DEFAULT 0,
+  error TEXT
+);
+
+CREATE MATERIALIZED VIEW evm_top_tokens_by_volume AS
+SELECT chain, token, symbol, transfers, senders, volume FROM (
+  SELECT t.chain, tr.token, k.symbol, COUNT(*) AS transfers,
+  COUNT(DISTINCT tr.from_address) AS senders, SUM(tr.value_decimal) AS volume,
+  ROW_NUMBER() OVER (PARTITION BY t.chain ORDER BY SUM(tr.value_decimal) DESC NULLS LAST) AS rank
+  FROM evm_erc20_transfers tr
+  JOIN evm_transactions t ON t.hash = tr.hash
+  LEFT JOIN evm_erc20_tokens k ON k.chain = t.chain AND k.address = tr.token
+  WHERE t.timestamp::BIGINT >= EXTRACT(EPOCH FROM NOW())::BIGINT - 86400
+  GROUP BY t.chain, tr.token, k.symbol
+) tokens
+WHERE rank <= 100;
+
+CREATE UNIQUE INDEX IF NOT EXISTS evm_top_tokens_by_volume_by_token
+ON evm_top_tokens_by_volume (chain, token);
+
+CREATE MATERIALIZED VIEW evm_busiest_contracts AS
+SELECT chain, contract, transactions, callers FROM (
+  SELECT t.chain, t.to_address AS contract, COUNT(*) AS transactions,
+  COUNT(DISTINCT t.from_address) AS callers,
+  ROW_NUMBER() OVER (PARTITION BY t.chain ORDER BY COUNT(*) DESC) AS rank
+  FROM evm_transactions t
+  WHERE t.timestamp::BIGINT >= EXTRACT(EPOCH FROM NOW())::BIGINT - 86400
+  AND EXISTS (SELECT 1 FROM evm_contracts c WHERE c.chain = t.chain AND c.contract = t.to_address)
+  GROUP BY t.chain, t.to_address
+) contracts
+WHERE rank <= 100;
+
+CREATE UNIQUE INDEX IF NOT EXISTS evm_busiest_contracts_by_contract
+ON evm_busiest_contracts (chain, contract);
+
+CREATE MATERIALIZED VIEW evm_gas_leaders AS
+SELECT chain, address, transactions, gas_used, fees FROM (
+  SELECT t.chain, t.to_address AS address, COUNT(*) AS transactions,
+  SUM(r.gas_used::NUMERIC) AS gas_used,
+  SUM(r.gas_used::NUMERIC * r.effective_gas_price::NUMERIC) AS fees,
+  ROW_NUMBER() OVER (PARTITION BY t.chain ORDER BY SUM(r.gas_used::NUMERIC) DESC) AS rank
+  FROM evm_transactions t
+  JOIN evm_transactions_receipts r ON r.hash = t.hash
+  WHERE t.timestamp::BIGINT >= EXTRACT(EPOCH FROM NOW())::BIGINT - 86400
+  GROUP BY t.chain, t.to_address
+) leaders
+WHERE rank <= 100;
+
+CREATE UNIQUE INDEX IF NOT EXISTS evm_gas_leaders_by_address
+ON evm_gas_leaders (chain, address);
\ No newline at end of file
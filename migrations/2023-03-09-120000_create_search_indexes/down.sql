@@ -0,0 +1,5 @@
+DROP INDEX evm_address_labels_by_label_trgm;
+
+DROP INDEX evm_erc20_tokens_by_symbol_trgm;
+
+DROP INDEX evm_erc20_tokens_by_name_trgm;
\ No newline at end of file
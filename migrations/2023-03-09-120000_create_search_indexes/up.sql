@@ -0,0 +1,10 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS evm_erc20_tokens_by_name_trgm
+ON evm_erc20_tokens USING GIN (LOWER(name) gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS evm_erc20_tokens_by_symbol_trgm
+ON evm_erc20_tokens USING GIN (LOWER(symbol) gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS evm_address_labels_by_label_trgm
+ON evm_address_labels USING GIN (LOWER(label) gin_trgm_ops);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_state_proofs;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE evm_state_proofs (
+  chain TEXT NOT NULL,
+  account TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  name TEXT NOT NULL,
+  balance TEXT NOT NULL,
+  nonce BIGINT NOT NULL,
+  code_hash TEXT NOT NULL,
+  storage_hash TEXT NOT NULL,
+  account_proof TEXT[] NOT NULL,
+  storage_proofs JSONB NOT NULL,
+  PRIMARY KEY (chain, account, block_number)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_block_times;
\ No newline at end of file
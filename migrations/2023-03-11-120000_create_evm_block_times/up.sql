@@ -0,0 +1,13 @@
+CREATE TABLE evm_block_times (
+  chain TEXT NOT NULL,
+  number BIGINT NOT NULL,
+  block_hash TEXT NOT NULL,
+  parent_hash TEXT NOT NULL,
+  timestamp BIGINT NOT NULL,
+  received_at BIGINT NOT NULL,
+  delay_ms BIGINT NOT NULL,
+  PRIMARY KEY (chain, block_hash)
+);
+
+CREATE INDEX IF NOT EXISTS evm_block_times_by_number
+ON evm_block_times (chain, number);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_duplicate_logs;
\ No newline at end of file
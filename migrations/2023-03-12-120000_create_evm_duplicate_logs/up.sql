@@ -0,0 +1,9 @@
+CREATE TABLE evm_duplicate_logs (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  parser TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  policy TEXT NOT NULL,
+  duplicate_of BIGINT NOT NULL,
+  PRIMARY KEY (hash, log_index, parser)
+);
\ No newline at end of file
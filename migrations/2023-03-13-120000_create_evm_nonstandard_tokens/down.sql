@@ -0,0 +1,3 @@
+DROP TABLE evm_sampled_balances;
+
+DROP TABLE evm_nonstandard_tokens;
\ No newline at end of file
@@ -0,0 +1,17 @@
+CREATE TABLE evm_nonstandard_tokens (
+  chain TEXT NOT NULL,
+  address TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  source TEXT NOT NULL,
+  marked_at BIGINT NOT NULL,
+  PRIMARY KEY (chain, address)
+);
+
+CREATE TABLE evm_sampled_balances (
+  chain TEXT NOT NULL,
+  token TEXT NOT NULL,
+  holder TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  balance TEXT NOT NULL,
+  PRIMARY KEY (chain, token, holder, block_number)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_backfill_jobs;
\ No newline at end of file
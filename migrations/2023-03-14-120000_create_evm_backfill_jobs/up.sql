@@ -0,0 +1,13 @@
+CREATE TABLE evm_backfill_jobs (
+  id BIGSERIAL PRIMARY KEY,
+  chain TEXT NOT NULL,
+  from_block BIGINT NOT NULL,
+  to_block BIGINT NOT NULL,
+  priority BIGINT NOT NULL,
+  status TEXT NOT NULL,
+  created_at BIGINT NOT NULL,
+  finished_at BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS evm_backfill_jobs_status_index
+ON evm_backfill_jobs (chain, status, priority);
\ No newline at end of file
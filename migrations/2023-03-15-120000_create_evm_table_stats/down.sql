@@ -0,0 +1 @@
+DROP TABLE evm_table_stats;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE evm_table_stats (
+  sampled_at BIGINT NOT NULL,
+  table_name TEXT NOT NULL,
+  inserted_rows BIGINT NOT NULL,
+  live_rows BIGINT NOT NULL,
+  table_bytes BIGINT NOT NULL,
+  index_bytes BIGINT NOT NULL,
+  PRIMARY KEY (table_name, sampled_at)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP INDEX evm_transactions_receipts_failed;
+ALTER TABLE evm_transactions DROP COLUMN gas_efficiency;
\ No newline at end of file
@@ -0,0 +1,8 @@
+ALTER TABLE evm_transactions ADD COLUMN gas_efficiency DOUBLE PRECISION;
+
+UPDATE evm_transactions t
+SET gas_efficiency = r.gas_used::NUMERIC / t.gas::NUMERIC
+FROM evm_transactions_receipts r
+WHERE r.hash = t.hash AND t.gas <> '0';
+
+CREATE INDEX evm_transactions_receipts_failed ON evm_transactions_receipts (hash) WHERE status = '0';
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP TABLE evm_contract_callers;
+DROP TABLE evm_contract_activity;
\ No newline at end of file
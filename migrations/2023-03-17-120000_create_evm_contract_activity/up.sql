@@ -0,0 +1,18 @@
+CREATE TABLE evm_contract_activity (
+  chain TEXT NOT NULL,
+  contract TEXT NOT NULL,
+  day BIGINT NOT NULL,
+  transactions BIGINT NOT NULL,
+  unique_callers BIGINT NOT NULL,
+  PRIMARY KEY (chain, contract, day)
+);
+
+CREATE INDEX evm_contract_activity_day ON evm_contract_activity (chain, day);
+
+CREATE TABLE evm_contract_callers (
+  chain TEXT NOT NULL,
+  contract TEXT NOT NULL,
+  day BIGINT NOT NULL,
+  caller TEXT NOT NULL,
+  PRIMARY KEY (chain, contract, day, caller)
+);
\ No newline at end of file
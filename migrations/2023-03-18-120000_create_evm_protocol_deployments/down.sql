@@ -0,0 +1 @@
+DROP TABLE evm_protocol_deployments;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE evm_protocol_deployments (
+  chain TEXT NOT NULL,
+  protocol TEXT NOT NULL,
+  address TEXT NOT NULL,
+  contract TEXT,
+  PRIMARY KEY (chain, address)
+);
+
+CREATE INDEX evm_protocol_deployments_protocol ON evm_protocol_deployments (protocol);
\ No newline at end of file
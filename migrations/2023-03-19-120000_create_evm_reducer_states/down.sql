@@ -0,0 +1,2 @@
+DROP TABLE evm_reducer_checkpoints;
+DROP TABLE evm_reducer_states;
\ No newline at end of file
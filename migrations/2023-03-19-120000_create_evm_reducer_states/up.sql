@@ -0,0 +1,19 @@
+CREATE TABLE evm_reducer_states (
+  chain TEXT NOT NULL,
+  reducer TEXT NOT NULL,
+  key TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  value JSONB,
+  PRIMARY KEY (chain, reducer, key, block_number)
+);
+
+CREATE INDEX evm_reducer_states_block_number ON evm_reducer_states (chain, reducer, block_number);
+
+CREATE TABLE evm_reducer_checkpoints (
+  chain TEXT NOT NULL,
+  reducer TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  block_hash TEXT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  PRIMARY KEY (chain, reducer)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_aggregated_transactions;
\ No newline at end of file
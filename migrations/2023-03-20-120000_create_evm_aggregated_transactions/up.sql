@@ -0,0 +1,5 @@
+CREATE TABLE evm_aggregated_transactions (
+  hash TEXT PRIMARY KEY
+);
+
+INSERT INTO evm_aggregated_transactions (hash) SELECT hash FROM evm_transactions;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_aggregated_blocks;
\ No newline at end of file
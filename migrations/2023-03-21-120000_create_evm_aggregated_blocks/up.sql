@@ -0,0 +1,5 @@
+CREATE TABLE evm_aggregated_blocks (
+  block_hash TEXT PRIMARY KEY
+);
+
+INSERT INTO evm_aggregated_blocks (block_hash) SELECT block_hash FROM evm_blocks;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE evm_ens_resolvers;
\ No newline at end of file
@@ -0,0 +1,11 @@
+CREATE TABLE evm_ens_resolvers (
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  block_number BIGINT NOT NULL,
+  node TEXT NOT NULL,
+  resolver TEXT NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS evm_ens_resolvers_by_node
+ON evm_ens_resolvers (node, block_number);
\ No newline at end of file
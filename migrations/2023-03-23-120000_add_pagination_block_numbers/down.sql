@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS evm_transactions_logs_by_address_position;
+
+DROP INDEX IF EXISTS evm_erc20_transfers_by_receiver_position;
+
+DROP INDEX IF EXISTS evm_erc20_transfers_by_sender_position;
+
+ALTER TABLE evm_transactions_logs DROP COLUMN block_number;
+
+ALTER TABLE evm_erc20_transfers DROP COLUMN block_number;
\ No newline at end of file
@@ -0,0 +1,32 @@
+ALTER TABLE evm_erc20_transfers ADD COLUMN block_number BIGINT;
+
+ALTER TABLE evm_transactions_logs ADD COLUMN block_number BIGINT;
+
+UPDATE evm_erc20_transfers e
+SET block_number = t.block_number
+FROM evm_transactions t
+WHERE t.hash = e.hash;
+
+UPDATE evm_erc20_transfers e
+SET block_number = t.block_number
+FROM evm_log_transactions t
+WHERE t.hash = e.hash AND e.block_number IS NULL;
+
+UPDATE evm_transactions_logs l
+SET block_number = t.block_number
+FROM evm_transactions t
+WHERE t.hash = l.hash;
+
+UPDATE evm_transactions_logs l
+SET block_number = t.block_number
+FROM evm_log_transactions t
+WHERE t.hash = l.hash AND l.block_number IS NULL;
+
+CREATE INDEX IF NOT EXISTS evm_erc20_transfers_by_sender_position
+ON evm_erc20_transfers (from_address, block_number DESC, log_index DESC);
+
+CREATE INDEX IF NOT EXISTS evm_erc20_transfers_by_receiver_position
+ON evm_erc20_transfers (to_address, block_number DESC, log_index DESC);
+
+CREATE INDEX IF NOT EXISTS evm_transactions_logs_by_address_position
+ON evm_transactions_logs (address, block_number DESC, log_index DESC);
\ No newline at end of file
@@ -0,0 +1,19 @@
+ALTER TABLE evm_aggregated_transactions DROP CONSTRAINT evm_aggregated_transactions_pkey;
+
+ALTER TABLE evm_aggregated_blocks DROP CONSTRAINT evm_aggregated_blocks_pkey;
+
+DELETE FROM evm_aggregated_transactions a
+USING evm_aggregated_transactions b
+WHERE a.hash = b.hash AND a.chain > b.chain;
+
+DELETE FROM evm_aggregated_blocks a
+USING evm_aggregated_blocks b
+WHERE a.block_hash = b.block_hash AND a.chain > b.chain;
+
+ALTER TABLE evm_aggregated_transactions DROP COLUMN chain;
+
+ALTER TABLE evm_aggregated_blocks DROP COLUMN chain;
+
+ALTER TABLE evm_aggregated_transactions ADD PRIMARY KEY (hash);
+
+ALTER TABLE evm_aggregated_blocks ADD PRIMARY KEY (block_hash);
\ No newline at end of file
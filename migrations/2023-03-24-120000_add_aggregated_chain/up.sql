@@ -0,0 +1,30 @@
+ALTER TABLE evm_aggregated_transactions ADD COLUMN chain TEXT;
+
+ALTER TABLE evm_aggregated_blocks ADD COLUMN chain TEXT;
+
+UPDATE evm_aggregated_transactions a
+SET chain = t.chain
+FROM evm_transactions t
+WHERE t.hash = a.hash;
+
+UPDATE evm_aggregated_blocks a
+SET chain = b.chain
+FROM evm_blocks b
+WHERE b.block_hash = a.block_hash;
+
+-- The chain of the counted rows deleted since can't be known.
+DELETE FROM evm_aggregated_transactions WHERE chain IS NULL;
+
+DELETE FROM evm_aggregated_blocks WHERE chain IS NULL;
+
+ALTER TABLE evm_aggregated_transactions ALTER COLUMN chain SET NOT NULL;
+
+ALTER TABLE evm_aggregated_blocks ALTER COLUMN chain SET NOT NULL;
+
+ALTER TABLE evm_aggregated_transactions DROP CONSTRAINT evm_aggregated_transactions_pkey;
+
+ALTER TABLE evm_aggregated_blocks DROP CONSTRAINT evm_aggregated_blocks_pkey;
+
+ALTER TABLE evm_aggregated_transactions ADD PRIMARY KEY (chain, hash);
+
+ALTER TABLE evm_aggregated_blocks ADD PRIMARY KEY (chain, block_hash);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE evm_backfill_jobs DROP COLUMN missing_blocks;
\ No newline at end of file
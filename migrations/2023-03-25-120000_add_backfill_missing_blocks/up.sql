@@ -0,0 +1 @@
+ALTER TABLE evm_backfill_jobs ADD COLUMN missing_blocks BIGINT[];
\ No newline at end of file
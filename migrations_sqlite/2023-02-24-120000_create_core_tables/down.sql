@@ -0,0 +1,9 @@
+DROP TABLE evm_contracts;
+
+DROP TABLE evm_transactions_logs;
+
+DROP TABLE evm_transactions_receipts;
+
+DROP TABLE evm_transactions;
+
+DROP TABLE evm_blocks;
\ No newline at end of file
@@ -0,0 +1,79 @@
+CREATE TABLE evm_blocks (
+  base_fee_per_gas TEXT NOT NULL,
+  chain TEXT NOT NULL,
+  difficulty TEXT NOT NULL,
+  extra_data TEXT NOT NULL,
+  gas_limit TEXT NOT NULL,
+  gas_used TEXT NOT NULL,
+  block_hash TEXT PRIMARY KEY NOT NULL,
+  logs_bloom TEXT NOT NULL,
+  miner TEXT NOT NULL,
+  mix_hash TEXT NOT NULL,
+  nonce TEXT NOT NULL,
+  number BIGINT NOT NULL,
+  parent_hash TEXT NOT NULL,
+  receipts_root TEXT NOT NULL,
+  sha3_uncles TEXT NOT NULL,
+  size BIGINT NOT NULL,
+  state_root TEXT NOT NULL,
+  timestamp TEXT NOT NULL,
+  total_difficulty TEXT NOT NULL,
+  transactions BIGINT NOT NULL,
+  uncles TEXT NOT NULL
+);
+
+CREATE INDEX evm_blocks_chain_number ON evm_blocks (chain, number);
+
+CREATE TABLE evm_transactions (
+  block_hash TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  from_address TEXT NOT NULL,
+  gas TEXT NOT NULL,
+  gas_price TEXT NOT NULL,
+  max_priority_fee_per_gas TEXT NOT NULL,
+  max_fee_per_gas TEXT NOT NULL,
+  hash TEXT PRIMARY KEY NOT NULL,
+  input TEXT NOT NULL,
+  method TEXT NOT NULL,
+  nonce TEXT NOT NULL,
+  timestamp TEXT NOT NULL,
+  to_address TEXT NOT NULL,
+  transaction_index BIGINT NOT NULL,
+  transaction_type BIGINT NOT NULL,
+  value TEXT NOT NULL
+);
+
+CREATE INDEX evm_transactions_block_number ON evm_transactions (block_number);
+
+CREATE TABLE evm_transactions_receipts (
+  contract_address TEXT,
+  cumulative_gas_used TEXT NOT NULL,
+  effective_gas_price TEXT NOT NULL,
+  gas_used TEXT NOT NULL,
+  hash TEXT PRIMARY KEY NOT NULL,
+  status TEXT NOT NULL,
+  revert_reason TEXT
+);
+
+CREATE TABLE evm_transactions_logs (
+  address TEXT NOT NULL,
+  topics TEXT NOT NULL,
+  data TEXT NOT NULL,
+  hash TEXT NOT NULL,
+  log_index BIGINT NOT NULL,
+  removed BOOLEAN NOT NULL,
+  PRIMARY KEY (hash, log_index)
+);
+
+CREATE INDEX evm_transactions_logs_address ON evm_transactions_logs (address);
+
+CREATE TABLE evm_contracts (
+  block BIGINT NOT NULL,
+  chain TEXT NOT NULL,
+  contract TEXT NOT NULL,
+  creator TEXT NOT NULL,
+  hash TEXT PRIMARY KEY NOT NULL,
+  parsed BOOLEAN NOT NULL,
+  verified BOOLEAN NOT NULL
+);
\ No newline at end of file
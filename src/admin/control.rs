@@ -0,0 +1,52 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use log::*;
+
+/// Runtime state of the sync shared with the admin endpoints. Reindexed ranges are queued and
+/// applied by the sync loop between passes, since it holds the indexed blocks while syncing.
+#[derive(Debug, Clone, Default)]
+pub struct SyncControl {
+    pub paused: Arc<AtomicBool>,
+    pub reindex_ranges: Arc<Mutex<Vec<(i64, i64)>>>,
+}
+
+impl SyncControl {
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+
+        info!("Paused syncing.");
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+
+        info!("Resumed syncing.");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn queue_reindex(&self, from_block: i64, to_block: i64) {
+        self.reindex_ranges
+            .lock()
+            .unwrap()
+            .push((from_block, to_block));
+
+        info!(
+            "Queued the reindex of blocks {} to {}.",
+            from_block, to_block
+        );
+    }
+
+    pub fn get_queued_reindexes(&self) -> Vec<(i64, i64)> {
+        self.reindex_ranges.lock().unwrap().clone()
+    }
+
+    pub fn take_reindex_ranges(&self) -> Vec<(i64, i64)> {
+        std::mem::take(&mut *self.reindex_ranges.lock().unwrap())
+    }
+}
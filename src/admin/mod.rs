@@ -0,0 +1,3 @@
+pub mod control;
+#[cfg(feature = "api")]
+pub mod server;
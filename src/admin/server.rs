@@ -0,0 +1,226 @@
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, str::FromStr};
+
+use anyhow::Result;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::*;
+use serde_json::{json, Value};
+
+use crate::{
+    db::{
+        backfill::{enqueue_backfill_job, get_backfill_jobs},
+        db::EVMDatabase,
+    },
+    metrics::table_stats::get_table_stats,
+};
+
+use super::control::SyncControl;
+
+/// Endpoints to control a running indexer, authenticated with the `Authorization: Bearer` token:
+/// - `GET /status`
+/// - `POST /pause` and `POST /resume` to stop and restart fetching blocks and heads.
+/// - `POST /reindex?from=<block>&to=<block>` to delete a range and fetch it again.
+/// - `GET /backfill` and `POST /backfill?from=<block>&to=<block>[&priority=<priority>]` to list
+///   and queue backfill jobs, which are synced before the rest of the history.
+/// - `POST /parsers/flush?parser=<name>[&from=<block>&to=<block>]` so a parser parses its logs
+///   again.
+/// - `PUT /log-level?level=<level>`
+/// - `GET /metrics[?hours=<hours>]` with the rows, sizes and growth of the tables.
+#[derive(Debug, Clone)]
+pub struct AdminServer {
+    pub token: String,
+    pub control: SyncControl,
+    pub db: EVMDatabase,
+}
+
+impl AdminServer {
+    pub fn new(token: String, control: SyncControl, db: EVMDatabase) -> Self {
+        Self { token, control, db }
+    }
+
+    pub async fn start(self, port: u16) -> Result<()> {
+        let address: SocketAddr = format!("0.0.0.0:{}", port).parse()?;
+
+        let service = make_service_fn(move |_| {
+            let admin = self.clone();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let admin = admin.clone();
+
+                    async move { Ok::<_, Infallible>(admin.handle(request).await) }
+                }))
+            }
+        });
+
+        info!("Serving the admin endpoints on {}.", address);
+
+        Server::bind(&address).serve(service).await?;
+
+        Ok(())
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let authorized = match request.headers().get("authorization") {
+            Some(header) => is_token_valid(header.as_bytes(), &self.token),
+            None => false,
+        };
+
+        if !authorized {
+            return respond(StatusCode::UNAUTHORIZED, json!({ "error": "unauthorized" }));
+        }
+
+        let params: HashMap<String, String> = match request.uri().query() {
+            Some(query) => query
+                .split("&")
+                .filter_map(|pair| pair.split_once("="))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/status") => respond(
+                StatusCode::OK,
+                json!({
+                    "chain": self.db.chain.name,
+                    "paused": self.control.is_paused(),
+                    "log_level": log::max_level().to_string(),
+                    "queued_reindexes": self.control.get_queued_reindexes(),
+                }),
+            ),
+            (&Method::POST, "/pause") => {
+                self.control.pause();
+
+                respond(StatusCode::OK, json!({ "paused": true }))
+            }
+            (&Method::POST, "/resume") => {
+                self.control.resume();
+
+                respond(StatusCode::OK, json!({ "paused": false }))
+            }
+            (&Method::POST, "/reindex") => {
+                match (get_number(&params, "from"), get_number(&params, "to")) {
+                    (Some(from_block), Some(to_block)) if from_block <= to_block => {
+                        self.control.queue_reindex(from_block, to_block);
+
+                        respond(
+                            StatusCode::ACCEPTED,
+                            json!({ "from": from_block, "to": to_block }),
+                        )
+                    }
+                    _ => bad_request("from and to blocks are required"),
+                }
+            }
+            (&Method::GET, "/backfill") => match get_backfill_jobs(&self.db, false) {
+                Ok(jobs) => respond(StatusCode::OK, json!(jobs)),
+                Err(err) => internal_error(err),
+            },
+            (&Method::POST, "/backfill") => {
+                let (from_block, to_block) =
+                    match (get_number(&params, "from"), get_number(&params, "to")) {
+                        (Some(from_block), Some(to_block)) => (from_block, to_block),
+                        _ => return bad_request("from and to blocks are required"),
+                    };
+
+                let priority = get_number(&params, "priority").unwrap_or(0);
+
+                match enqueue_backfill_job(&self.db, from_block, to_block, priority) {
+                    Ok(job) => respond(StatusCode::ACCEPTED, json!(job)),
+                    Err(err) => bad_request(&err.to_string()),
+                }
+            }
+            (&Method::GET, "/metrics") => {
+                let hours = get_number(&params, "hours").unwrap_or(24);
+
+                match get_table_stats(&self.db, hours) {
+                    Ok(stats) => respond(StatusCode::OK, json!({ "tables": stats })),
+                    Err(err) => internal_error(err),
+                }
+            }
+            (&Method::POST, "/parsers/flush") => {
+                let parser = match params.get("parser") {
+                    Some(parser) => parser,
+                    None => return bad_request("parser is required"),
+                };
+
+                let from_block = get_number(&params, "from").unwrap_or(0);
+
+                let to_block = get_number(&params, "to").unwrap_or(i64::MAX);
+
+                match self
+                    .db
+                    .flush_parser_cursors(parser, from_block, to_block)
+                    .await
+                {
+                    Ok(flushed) => respond(
+                        StatusCode::OK,
+                        json!({ "parser": parser, "flushed": flushed }),
+                    ),
+                    Err(err) => internal_error(err),
+                }
+            }
+            (&Method::PUT, "/log-level") => {
+                let level = match params
+                    .get("level")
+                    .map(|level| LevelFilter::from_str(level))
+                {
+                    Some(Ok(level)) => level,
+                    _ => {
+                        return bad_request("level must be off, error, warn, info, debug or trace")
+                    }
+                };
+
+                log::set_max_level(level);
+
+                info!("Changed the log level to {}.", level);
+
+                respond(StatusCode::OK, json!({ "log_level": level.to_string() }))
+            }
+            _ => respond(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+        }
+    }
+}
+
+/// Compares the bearer token in constant time, so the time of a rejected request doesn't tell
+/// how many bytes of the token were right.
+fn is_token_valid(header: &[u8], token: &str) -> bool {
+    let expected = format!("Bearer {}", token);
+
+    let expected = expected.as_bytes();
+
+    if header.len() != expected.len() {
+        return false;
+    }
+
+    header
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn get_number(params: &HashMap<String, String>, name: &str) -> Option<i64> {
+    params.get(name).and_then(|value| value.parse::<i64>().ok())
+}
+
+fn internal_error(err: anyhow::Error) -> Response<Body> {
+    respond(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        json!({ "error": err.to_string() }),
+    )
+}
+
+fn bad_request(error: &str) -> Response<Body> {
+    respond(StatusCode::BAD_REQUEST, json!({ "error": error }))
+}
+
+fn respond(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("Unable to build the admin response")
+}
@@ -0,0 +1,278 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use log::*;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    db::models::models::{DatabaseEVMContract, DatabaseEVMTransaction, DatabaseEVMTransactionLog},
+    parsers::decoder::EventDecoder,
+    stablecoins::monitor::DatabaseEVMStablecoinEvent,
+};
+
+use super::{
+    notifiers::RateLimiter,
+    rules::{matches_filter, matches_min_value, AlertCondition, AlertRule},
+};
+
+/// Timeout of the requests to the notifiers, so a hanging webhook doesn't pile up tasks.
+pub const ALERT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertMatch {
+    pub rule: String,
+    pub chain: String,
+    pub kind: String,
+    pub row: serde_json::Value,
+}
+
+/// Evaluates the alert rules on the indexed data and sends the matched rows to the notifiers of
+/// each rule. ERC-20 transfers are decoded from the logs, so rules don't wait for the parser.
+#[derive(Debug, Clone)]
+pub struct AlertsEngine {
+    pub rules: Vec<AlertRule>,
+    pub transfer: EventDecoder,
+    pub client: Client,
+    /// Rate limiter of each notifier, by rule and notifier position.
+    pub limiters: HashMap<(usize, usize), Arc<RateLimiter>>,
+}
+
+impl AlertsEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        info!("Loaded {} alert rules.", rules.len());
+
+        let mut limiters = HashMap::new();
+
+        for (rule_index, rule) in rules.iter().enumerate() {
+            for (notifier_index, notifier) in rule.notify.iter().enumerate() {
+                limiters.insert(
+                    (rule_index, notifier_index),
+                    Arc::new(RateLimiter::new(notifier.rate_limit())),
+                );
+            }
+        }
+
+        Self {
+            rules,
+            limiters,
+            transfer: EventDecoder::new(&[
+                "event Transfer(address indexed from, address indexed to, uint256 value)",
+            ]),
+            client: Client::builder()
+                .timeout(ALERT_REQUEST_TIMEOUT)
+                .build()
+                .expect("Unable to build alerts HTTP client"),
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        chain: &str,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) -> Vec<(usize, AlertMatch)> {
+        let mut matches = Vec::new();
+
+        let blocks: HashMap<&String, i64> = transactions
+            .iter()
+            .map(|transaction| (&transaction.hash, transaction.block_number))
+            .collect();
+
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            match &rule.chain {
+                Some(rule_chain) if rule_chain != chain => continue,
+                _ => (),
+            }
+
+            let rows: Vec<(&str, serde_json::Value)> = match &rule.condition {
+                AlertCondition::Transaction {
+                    from_address,
+                    to_address,
+                    method,
+                    min_value,
+                } => transactions
+                    .iter()
+                    .filter(|transaction| {
+                        matches_filter(from_address, &transaction.from_address)
+                            && matches_filter(to_address, &transaction.to_address)
+                            && matches_filter(method, &transaction.method)
+                            && matches_min_value(min_value, &transaction.value)
+                    })
+                    .map(|transaction| ("transaction", json!(transaction)))
+                    .collect(),
+                AlertCondition::Transfer {
+                    token,
+                    from_address,
+                    to_address,
+                    min_value,
+                } => logs
+                    .iter()
+                    .filter(|log| matches_filter(token, &log.address))
+                    .filter_map(|log| {
+                        let transfer = self.transfer.decode(log)?;
+
+                        let from = transfer.address("from")?;
+                        let to = transfer.address("to")?;
+                        let value = transfer.uint("value")?;
+
+                        if !matches_filter(from_address, &from)
+                            || !matches_filter(to_address, &to)
+                            || !matches_min_value(min_value, &value)
+                        {
+                            return None;
+                        }
+
+                        Some((
+                            "transfer",
+                            json!({
+                                "hash": log.hash,
+                                "log_index": log.log_index,
+                                "block_number": blocks.get(&log.hash),
+                                "token": log.address,
+                                "from_address": from,
+                                "to_address": to,
+                                "value": value,
+                            }),
+                        ))
+                    })
+                    .collect(),
+                AlertCondition::ContractDeployment { creator } => contracts
+                    .iter()
+                    .filter(|contract| matches_filter(creator, &contract.creator))
+                    .map(|contract| ("contract_deployment", json!(contract)))
+                    .collect(),
+                // Matched on the stored events by `evaluate_stablecoin_events`.
+                AlertCondition::Stablecoin { .. } => Vec::new(),
+            };
+
+            for (kind, row) in rows {
+                matches.push((
+                    rule_index,
+                    AlertMatch {
+                        rule: rule.name.clone(),
+                        chain: chain.to_string(),
+                        kind: kind.to_string(),
+                        row,
+                    },
+                ));
+            }
+        }
+
+        matches
+    }
+
+    pub fn evaluate_stablecoin_events(
+        &self,
+        chain: &str,
+        events: &Vec<DatabaseEVMStablecoinEvent>,
+    ) -> Vec<(usize, AlertMatch)> {
+        let mut matches = Vec::new();
+
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            match &rule.chain {
+                Some(rule_chain) if rule_chain != chain => continue,
+                _ => (),
+            }
+
+            let (token, event, min_value) = match &rule.condition {
+                AlertCondition::Stablecoin {
+                    token,
+                    event,
+                    min_value,
+                } => (token, event, min_value),
+                _ => continue,
+            };
+
+            for stablecoin_event in events {
+                let amount = stablecoin_event.amount.clone().unwrap_or_default();
+
+                if !matches_filter(token, &stablecoin_event.token)
+                    || !matches_filter(event, &stablecoin_event.kind)
+                    || !matches_min_value(min_value, &amount)
+                {
+                    continue;
+                }
+
+                matches.push((
+                    rule_index,
+                    AlertMatch {
+                        rule: rule.name.clone(),
+                        chain: chain.to_string(),
+                        kind: "stablecoin".to_string(),
+                        row: json!(stablecoin_event),
+                    },
+                ));
+            }
+        }
+
+        matches
+    }
+
+    pub async fn process(
+        &self,
+        chain: &str,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) {
+        let matches = self.evaluate(chain, transactions, logs, contracts);
+
+        self.notify(chain, matches).await
+    }
+
+    pub async fn process_stablecoin_events(
+        &self,
+        chain: &str,
+        events: &Vec<DatabaseEVMStablecoinEvent>,
+    ) {
+        let matches = self.evaluate_stablecoin_events(chain, events);
+
+        self.notify(chain, matches).await
+    }
+
+    /// Notifications are sent in the background and are best effort, a slow or failing notifier
+    /// doesn't stop the indexer.
+    pub async fn notify(&self, chain: &str, matches: Vec<(usize, AlertMatch)>) {
+        if matches.len() == 0 {
+            return;
+        }
+
+        info!("Matched {} alerts for chain {}.", matches.len(), chain);
+
+        let mut rules_alerts: HashMap<usize, Vec<AlertMatch>> = HashMap::new();
+
+        for (rule_index, alert) in matches {
+            rules_alerts.entry(rule_index).or_default().push(alert);
+        }
+
+        for (rule_index, alerts) in rules_alerts {
+            let rule = &self.rules[rule_index];
+
+            for (notifier_index, notifier) in rule.notify.iter().enumerate() {
+                let limiter = Arc::clone(&self.limiters[&(rule_index, notifier_index)]);
+
+                let batches: Vec<Vec<AlertMatch>> = alerts
+                    .chunks(notifier.batch_size())
+                    .map(|batch| batch.to_vec())
+                    .collect();
+
+                tokio::spawn({
+                    let notifier = notifier.clone();
+                    let client = self.client.clone();
+                    let rule = rule.name.clone();
+
+                    async move {
+                        for batch in batches {
+                            match limiter.send(&notifier, &client, &batch).await {
+                                Ok(_) => (),
+                                Err(err) => warn!("Unable to send alert {}: {}", rule, err),
+                            }
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
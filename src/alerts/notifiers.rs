@@ -0,0 +1,304 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use super::engine::AlertMatch;
+
+/// Discord rejects messages longer than 2000 characters.
+pub const DISCORD_MAX_LENGTH: usize = 2000;
+
+/// Telegram rejects messages longer than 4096 characters.
+pub const TELEGRAM_MAX_LENGTH: usize = 4096;
+
+/// Options of the chat notifiers. The template supports the `{rule}`, `{chain}` and `{kind}`
+/// placeholders and `{row.<field>}` for the fields of the matched row. Up to `batch_size`
+/// alerts are joined in a message, split in several when longer than the chat allows, and at
+/// most `rate_limit` messages are sent per minute.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatOptions {
+    pub template: Option<String>,
+    pub batch_size: Option<usize>,
+    pub rate_limit: Option<u64>,
+}
+
+/// Destination of the alerts of a rule, e.g.
+/// `{ "type": "telegram", "bot_token": "...", "chat_id": "...", "batch_size": 10 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertNotifier {
+    Webhook {
+        url: String,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+        #[serde(flatten)]
+        options: ChatOptions,
+    },
+    Discord {
+        webhook: String,
+        #[serde(flatten)]
+        options: ChatOptions,
+    },
+    Slack {
+        webhook: String,
+    },
+    Nats {
+        url: String,
+        subject: String,
+    },
+}
+
+impl AlertNotifier {
+    pub fn batch_size(&self) -> usize {
+        match self {
+            AlertNotifier::Telegram { options, .. } | AlertNotifier::Discord { options, .. } => {
+                options.batch_size.unwrap_or(1).max(1)
+            }
+            _ => 1,
+        }
+    }
+
+    pub fn rate_limit(&self) -> Option<u64> {
+        match self {
+            AlertNotifier::Telegram { options, .. } | AlertNotifier::Discord { options, .. } => {
+                options.rate_limit
+            }
+            _ => None,
+        }
+    }
+
+    /// Messages of a batch for the chat notifiers, split to fit their length limit. Webhooks and
+    /// NATS receive the batch as a JSON array instead.
+    pub fn get_messages(&self, alerts: &[AlertMatch]) -> Option<Vec<String>> {
+        match self {
+            AlertNotifier::Telegram { options, .. } => Some(get_batch_messages(
+                alerts,
+                &options.template,
+                TELEGRAM_MAX_LENGTH,
+            )),
+            AlertNotifier::Discord { options, .. } => Some(get_batch_messages(
+                alerts,
+                &options.template,
+                DISCORD_MAX_LENGTH,
+            )),
+            AlertNotifier::Slack { .. } => Some(get_batch_messages(alerts, &None, usize::MAX)),
+            AlertNotifier::Webhook { .. } | AlertNotifier::Nats { .. } => None,
+        }
+    }
+
+    /// Sends a batch of alerts to the webhooks and NATS.
+    pub async fn send(&self, client: &Client, alerts: &[AlertMatch]) -> Result<()> {
+        match self {
+            AlertNotifier::Nats { url, subject } => send_nats(url, subject, alerts).await,
+            AlertNotifier::Webhook { url } => send_request(client.post(url).json(alerts)).await,
+            _ => bail!("Chat notifiers send the alerts as messages"),
+        }
+    }
+
+    /// Sends a message to the chat notifiers.
+    pub async fn send_message(&self, client: &Client, message: &String) -> Result<()> {
+        let request = match self {
+            AlertNotifier::Telegram {
+                bot_token, chat_id, ..
+            } => client
+                .post(format!(
+                    "https://api.telegram.org/bot{}/sendMessage",
+                    bot_token
+                ))
+                .json(&json!({
+                    "chat_id": chat_id,
+                    "text": message,
+                    "disable_web_page_preview": true,
+                })),
+            AlertNotifier::Discord { webhook, .. } => {
+                client.post(webhook).json(&json!({ "content": message }))
+            }
+            AlertNotifier::Slack { webhook } => {
+                client.post(webhook).json(&json!({ "text": message }))
+            }
+            _ => bail!("Only chat notifiers send messages"),
+        };
+
+        send_request(request).await
+    }
+}
+
+async fn send_request(request: RequestBuilder) -> Result<()> {
+    let response = request.send().await?;
+
+    if !response.status().is_success() {
+        bail!("Alert notifier responded with status {}", response.status());
+    }
+
+    Ok(())
+}
+
+/// Publishes the alerts to JetStream and waits for the acknowledgement. Alerts are rare, so a
+/// connection is opened for each batch.
+async fn send_nats(url: &String, subject: &String, alerts: &[AlertMatch]) -> Result<()> {
+    let client = match async_nats::connect(url.as_str()).await {
+        Ok(client) => client,
+        Err(err) => bail!("Unable to connect to NATS: {}", err),
+    };
+
+    let context = async_nats::jetstream::new(client);
+
+    let payload = Bytes::from(serde_json::to_vec(alerts)?);
+
+    match context.publish(subject.clone(), payload).await {
+        Ok(ack) => match ack.await {
+            Ok(_) => Ok(()),
+            Err(err) => bail!("JetStream didn't acknowledge the alerts: {}", err),
+        },
+        Err(err) => bail!("Unable to publish the alerts to NATS: {}", err),
+    }
+}
+
+/// Spaces the messages of a notifier to respect its rate limit, a batch split in several
+/// messages counts each of them. The lock is held while waiting, so the messages are also sent
+/// in order.
+#[derive(Debug)]
+pub struct RateLimiter {
+    pub interval: Option<Duration>,
+    pub last_sent: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate_limit: Option<u64>) -> Self {
+        Self {
+            interval: rate_limit
+                .filter(|rate_limit| *rate_limit > 0)
+                .map(|rate_limit| Duration::from_millis(60_000 / rate_limit)),
+            last_sent: Mutex::new(None),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        notifier: &AlertNotifier,
+        client: &Client,
+        alerts: &[AlertMatch],
+    ) -> Result<()> {
+        let mut last_sent = self.last_sent.lock().await;
+
+        match notifier.get_messages(alerts) {
+            Some(messages) => {
+                for message in messages {
+                    self.wait(&mut last_sent).await;
+
+                    notifier.send_message(client, &message).await?;
+                }
+
+                Ok(())
+            }
+            None => {
+                self.wait(&mut last_sent).await;
+
+                notifier.send(client, alerts).await
+            }
+        }
+    }
+
+    async fn wait(&self, last_sent: &mut Option<Instant>) {
+        match (self.interval, *last_sent) {
+            (Some(interval), Some(last)) => {
+                let elapsed = last.elapsed();
+
+                if elapsed < interval {
+                    tokio::time::sleep(interval - elapsed).await;
+                }
+            }
+            _ => (),
+        }
+
+        *last_sent = Some(Instant::now());
+    }
+}
+
+/// Plain text message for chat notifiers, with the matched row as pretty JSON.
+pub fn get_alert_text(alert: &AlertMatch) -> String {
+    format!(
+        "Alert {} matched a {} on {}:\n{}",
+        alert.rule,
+        alert.kind,
+        alert.chain,
+        serde_json::to_string_pretty(&alert.row).unwrap_or_default()
+    )
+}
+
+pub fn render_template(template: &String, alert: &AlertMatch) -> String {
+    let mut text = template
+        .replace("{rule}", &alert.rule)
+        .replace("{chain}", &alert.chain)
+        .replace("{kind}", &alert.kind);
+
+    match alert.row.as_object() {
+        Some(row) => {
+            for (field, value) in row {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+
+                text = text.replace(&format!("{{row.{}}}", field), &value);
+            }
+        }
+        None => (),
+    }
+
+    text
+}
+
+/// Joins the alerts of a batch in messages up to the notifier length limit. Alerts are not split
+/// across messages unless a single alert is longer than the limit.
+pub fn get_batch_messages(
+    alerts: &[AlertMatch],
+    template: &Option<String>,
+    max_length: usize,
+) -> Vec<String> {
+    let mut messages = Vec::new();
+
+    let mut message = String::new();
+
+    for alert in alerts {
+        let text = match template {
+            Some(template) => render_template(template, alert),
+            None => get_alert_text(alert),
+        };
+
+        for part in split_text(&text, max_length) {
+            let length = message.chars().count();
+
+            if length > 0 && length + 2 + part.chars().count() > max_length {
+                messages.push(std::mem::take(&mut message));
+            }
+
+            if !message.is_empty() {
+                message.push_str("\n\n");
+            }
+
+            message.push_str(&part);
+        }
+    }
+
+    if !message.is_empty() {
+        messages.push(message);
+    }
+
+    messages
+}
+
+fn split_text(text: &String, max_length: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+
+    chars
+        .chunks(max_length.max(1))
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
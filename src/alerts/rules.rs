@@ -0,0 +1,65 @@
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+
+use super::notifiers::AlertNotifier;
+
+/// Condition of an alert rule. Addresses are compared lowercase and amounts are raw token
+/// units, e.g.
+/// `{ "kind": "transfer", "token": "0xa0b8...", "min_value": "1000000000000" }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    Transfer {
+        token: Option<String>,
+        from_address: Option<String>,
+        to_address: Option<String>,
+        min_value: Option<String>,
+    },
+    Transaction {
+        from_address: Option<String>,
+        to_address: Option<String>,
+        method: Option<String>,
+        min_value: Option<String>,
+    },
+    ContractDeployment {
+        creator: Option<String>,
+    },
+    /// Events of the stablecoin monitor, e.g. `mint`, `burn`, `blacklist` or `large_transfer`.
+    Stablecoin {
+        token: Option<String>,
+        event: Option<String>,
+        min_value: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub chain: Option<String>,
+    pub condition: AlertCondition,
+    pub notify: Vec<AlertNotifier>,
+}
+
+pub fn load_alert_rules(path: &String) -> Vec<AlertRule> {
+    let file = std::fs::read_to_string(path).expect("Unable to read alert rules");
+
+    serde_json::from_str(&file).expect("Unable to parse alert rules")
+}
+
+/// Filters are compared lowercase, an unset filter matches any value.
+pub fn matches_filter(filter: &Option<String>, value: &String) -> bool {
+    match filter {
+        Some(filter) => filter.to_lowercase() == value.to_lowercase(),
+        None => true,
+    }
+}
+
+pub fn matches_min_value(min_value: &Option<String>, value: &String) -> bool {
+    match min_value {
+        Some(min_value) => match (U256::from_dec_str(min_value), U256::from_dec_str(value)) {
+            (Ok(min_value), Ok(value)) => value >= min_value,
+            _ => false,
+        },
+        None => true,
+    }
+}
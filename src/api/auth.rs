@@ -0,0 +1,402 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use diesel::{prelude::*, upsert::excluded};
+use hyper::{Body, Request, Response, StatusCode};
+use jsonrpsee::server::logger::{HttpRequest, Logger, MethodKind, Params, TransportProtocol};
+use log::*;
+use serde::Deserialize;
+use tonic::{service::Interceptor, Status};
+use tower::{Layer, Service};
+
+use crate::db::{db::EVMDatabase, schema::evm_api_keys};
+
+/// Header with the API key, WebSocket clients that can't set headers use the `api_key` query
+/// parameter instead.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+pub const API_KEY_QUERY: &str = "api_key";
+
+/// Rate limits are requests per window.
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// WebSocket connections open at the same time with a key. A connection counts as a single
+/// request, so subscriptions are capped instead of metered.
+pub const MAX_CONNECTIONS_PER_KEY: usize = 5;
+
+pub const MAX_SUBSCRIPTIONS_PER_CONNECTION: u32 = 10;
+
+/// Interval to store the usage counters and reload the keys added to the database.
+pub const USAGE_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Selectable, Queryable, Insertable, Deserialize, Debug, Clone)]
+#[diesel(table_name = evm_api_keys)]
+pub struct DatabaseEVMApiKey {
+    pub key: String,
+    pub name: String,
+    /// Requests per minute, unset for unlimited.
+    pub rate_limit: Option<i64>,
+    #[serde(default)]
+    pub requests: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthError {
+    MissingKey,
+    InvalidKey,
+    RateLimited,
+    TooManyConnections,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::MissingKey => write!(f, "Missing API key"),
+            AuthError::InvalidKey => write!(f, "Invalid API key"),
+            AuthError::RateLimited => write!(f, "API key rate limit exceeded"),
+            AuthError::TooManyConnections => write!(f, "API key connections limit exceeded"),
+        }
+    }
+}
+
+impl From<AuthError> for Status {
+    fn from(err: AuthError) -> Self {
+        match err {
+            AuthError::RateLimited | AuthError::TooManyConnections => {
+                Status::resource_exhausted(err.to_string())
+            }
+            _ => Status::unauthenticated(err.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyUsage {
+    window_start: Option<Instant>,
+    window_requests: i64,
+    /// Requests not yet added to the counter in the database.
+    pending_requests: i64,
+}
+
+/// API keys allowed to use the servers, with a per key rate limit and usage counter. Keys are
+/// stored in `evm_api_keys`, the keys of the config file are added to it on start.
+#[derive(Debug, Clone)]
+pub struct ApiKeys {
+    pub db: EVMDatabase,
+    keys: Arc<Mutex<HashMap<String, DatabaseEVMApiKey>>>,
+    usage: Arc<Mutex<HashMap<String, KeyUsage>>>,
+    /// Key of each open WebSocket connection by the address of the client.
+    connections: Arc<Mutex<HashMap<SocketAddr, String>>>,
+}
+
+impl ApiKeys {
+    pub fn new(db: EVMDatabase, path: &Option<String>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let file = std::fs::read_to_string(path).expect("Unable to read API keys");
+
+                let keys: Vec<DatabaseEVMApiKey> =
+                    serde_json::from_str(&file).expect("Unable to parse API keys");
+
+                let mut connection = db.establish_connection();
+
+                diesel::insert_into(evm_api_keys::dsl::evm_api_keys)
+                    .values(&keys)
+                    .on_conflict(evm_api_keys::key)
+                    .do_update()
+                    .set((
+                        evm_api_keys::name.eq(excluded(evm_api_keys::name)),
+                        evm_api_keys::rate_limit.eq(excluded(evm_api_keys::rate_limit)),
+                    ))
+                    .execute(&mut connection)?;
+            }
+            None => (),
+        }
+
+        let api_keys = Self {
+            db,
+            keys: Arc::new(Mutex::new(HashMap::new())),
+            usage: Arc::new(Mutex::new(HashMap::new())),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        api_keys.reload()?;
+
+        Ok(api_keys)
+    }
+
+    pub fn reload(&self) -> Result<()> {
+        let mut connection = self.db.establish_connection();
+
+        let keys: Vec<DatabaseEVMApiKey> = evm_api_keys::table
+            .select(DatabaseEVMApiKey::as_select())
+            .load::<DatabaseEVMApiKey>(&mut connection)?;
+
+        debug!("Loaded {} API keys.", keys.len());
+
+        *self.keys.lock().unwrap() = keys.into_iter().map(|key| (key.key.clone(), key)).collect();
+
+        Ok(())
+    }
+
+    /// Checks the key and counts the request in its current rate limit window.
+    pub fn authorize(&self, key: Option<&str>) -> Result<(), AuthError> {
+        let key = match key {
+            Some(key) => key,
+            None => return Err(AuthError::MissingKey),
+        };
+
+        let rate_limit = match self.keys.lock().unwrap().get(key) {
+            Some(api_key) => api_key.rate_limit,
+            None => return Err(AuthError::InvalidKey),
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+
+        let usage = usage.entry(key.to_string()).or_default();
+
+        let now = Instant::now();
+
+        match usage.window_start {
+            Some(start) if now.duration_since(start) < RATE_LIMIT_WINDOW => (),
+            _ => {
+                usage.window_start = Some(now);
+                usage.window_requests = 0;
+            }
+        }
+
+        match rate_limit {
+            Some(rate_limit) if usage.window_requests >= rate_limit => {
+                return Err(AuthError::RateLimited)
+            }
+            _ => (),
+        }
+
+        usage.window_requests += 1;
+        usage.pending_requests += 1;
+
+        Ok(())
+    }
+
+    /// Checks the key can open another WebSocket connection. Connections opened at the same time
+    /// are only counted once they are established, so the limit can be briefly exceeded.
+    pub fn authorize_connection(&self, key: Option<&str>) -> Result<(), AuthError> {
+        let key = match key {
+            Some(key) => key,
+            None => return Err(AuthError::MissingKey),
+        };
+
+        let connections = self
+            .connections
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|connection_key| connection_key.as_str() == key)
+            .count();
+
+        if connections >= MAX_CONNECTIONS_PER_KEY {
+            return Err(AuthError::TooManyConnections);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the requests made since the last call to the usage counters in the database.
+    pub fn store_usage(&self) -> Result<()> {
+        let pending: Vec<(String, i64)> = self
+            .usage
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .filter(|(_, usage)| usage.pending_requests > 0)
+            .map(|(key, usage)| (key.clone(), std::mem::take(&mut usage.pending_requests)))
+            .collect();
+
+        let mut connection = self.db.establish_connection();
+
+        for (key, requests) in pending {
+            diesel::update(evm_api_keys::table.find(&key))
+                .set(evm_api_keys::requests.eq(evm_api_keys::requests + requests))
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+
+    /// Periodically stores the usage counters and picks up the keys added or removed from the
+    /// database without restarting the API.
+    pub fn start_usage_sync(&self) {
+        let api_keys = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(USAGE_SYNC_INTERVAL).await;
+
+                match api_keys.store_usage() {
+                    Ok(_) => (),
+                    Err(err) => warn!("Unable to store API keys usage: {}", err),
+                }
+
+                match api_keys.reload() {
+                    Ok(_) => (),
+                    Err(err) => warn!("Unable to reload API keys: {}", err),
+                }
+            }
+        });
+    }
+}
+
+/// Checks the API key of the gRPC calls, a stream counts as a single request.
+impl Interceptor for ApiKeys {
+    fn call(&mut self, request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        let key = request
+            .metadata()
+            .get(API_KEY_HEADER)
+            .and_then(|key| key.to_str().ok());
+
+        match self.authorize(key) {
+            Ok(_) => Ok(request),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+fn is_websocket_upgrade(request: &Request<Body>) -> bool {
+    match request.headers().get(hyper::header::UPGRADE) {
+        Some(upgrade) => upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"),
+        None => false,
+    }
+}
+
+fn get_request_key(request: &Request<Body>) -> Option<&str> {
+    match request.headers().get(API_KEY_HEADER) {
+        Some(key) => key.to_str().ok(),
+        None => request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|param| match param.split_once('=') {
+                    Some((API_KEY_QUERY, key)) => Some(key),
+                    _ => None,
+                })
+        }),
+    }
+}
+
+/// Middleware of the JSON-RPC server. It sees the HTTP requests and the WebSocket upgrades, so
+/// a WebSocket connection counts as a single request. Requests pass through without keys.
+#[derive(Debug, Clone)]
+pub struct ApiKeysLayer {
+    pub api_keys: Option<ApiKeys>,
+}
+
+impl<S> Layer<S> for ApiKeysLayer {
+    type Service = ApiKeysService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeysService {
+            inner,
+            api_keys: self.api_keys.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKeysService<S> {
+    inner: S,
+    api_keys: Option<ApiKeys>,
+}
+
+impl<S> Service<Request<Body>> for ApiKeysService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let authorized = match &self.api_keys {
+            Some(api_keys) => match is_websocket_upgrade(&request) {
+                true => api_keys
+                    .authorize_connection(get_request_key(&request))
+                    .and_then(|_| api_keys.authorize(get_request_key(&request))),
+                false => api_keys.authorize(get_request_key(&request)),
+            },
+            None => Ok(()),
+        };
+
+        match authorized {
+            Ok(_) => Box::pin(self.inner.call(request)),
+            Err(err) => {
+                let status = match err {
+                    AuthError::RateLimited | AuthError::TooManyConnections => {
+                        StatusCode::TOO_MANY_REQUESTS
+                    }
+                    _ => StatusCode::UNAUTHORIZED,
+                };
+
+                let response = Response::builder()
+                    .status(status)
+                    .body(Body::from(err.to_string()))
+                    .expect("Unable to build auth response");
+
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+/// Keeps track of the WebSocket connections of each key, the server only tells the address of
+/// the client when it disconnects.
+#[derive(Debug, Clone)]
+pub struct ApiKeysLogger {
+    pub api_keys: Option<ApiKeys>,
+}
+
+impl Logger for ApiKeysLogger {
+    type Instant = ();
+
+    fn on_connect(&self, remote_addr: SocketAddr, request: &HttpRequest, t: TransportProtocol) {
+        match (&self.api_keys, t, get_request_key(request)) {
+            (Some(api_keys), TransportProtocol::WebSocket, Some(key)) => {
+                api_keys
+                    .connections
+                    .lock()
+                    .unwrap()
+                    .insert(remote_addr, key.to_string());
+            }
+            _ => (),
+        }
+    }
+
+    fn on_request(&self, _: TransportProtocol) -> Self::Instant {}
+
+    fn on_call(&self, _: &str, _: Params, _: MethodKind, _: TransportProtocol) {}
+
+    fn on_result(&self, _: &str, _: bool, _: Self::Instant, _: TransportProtocol) {}
+
+    fn on_response(&self, _: &str, _: Self::Instant, _: TransportProtocol) {}
+
+    fn on_disconnect(&self, remote_addr: SocketAddr, t: TransportProtocol) {
+        match (&self.api_keys, t) {
+            (Some(api_keys), TransportProtocol::WebSocket) => {
+                api_keys.connections.lock().unwrap().remove(&remote_addr);
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, Text},
+};
+use redis::Commands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    db::{
+        db::EVMDatabase,
+        models::models::{DatabaseEVMBlock, DatabaseEVMOutboxEvent, DatabaseEVMTransactionLog},
+    },
+    parsers::erc20_transfers_parser::DatabaseEVMErc20Transfer,
+};
+
+pub const EVENTS_CHANNEL: &str = "evm-indexer-events";
+
+/// Postgres channel of the `pg_notify` events, e.g. `LISTEN evm_indexer_events`.
+pub const NOTIFY_CHANNEL: &str = "evm_indexer_events";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IndexedEvent {
+    Block {
+        chain: String,
+        number: i64,
+        block_hash: String,
+        timestamp: String,
+        transactions: i64,
+    },
+    Log {
+        chain: String,
+        hash: String,
+        log_index: i64,
+        address: String,
+        topics: Vec<Option<String>>,
+        data: String,
+    },
+    Erc20Transfer {
+        chain: String,
+        hash: String,
+        log_index: i64,
+        token: String,
+        from_address: String,
+        to_address: String,
+        value: String,
+    },
+    NewAddress {
+        chain: String,
+        address: String,
+        block_number: i64,
+        hash: String,
+        role: String,
+    },
+}
+
+impl IndexedEvent {
+    pub fn from_block(block: &DatabaseEVMBlock) -> Self {
+        IndexedEvent::Block {
+            chain: block.chain.clone(),
+            number: block.number,
+            block_hash: block.block_hash.clone(),
+            timestamp: block.timestamp.clone(),
+            transactions: block.transactions,
+        }
+    }
+
+    pub fn from_log(log: &DatabaseEVMTransactionLog, chain: &str) -> Self {
+        IndexedEvent::Log {
+            chain: chain.to_string(),
+            hash: log.hash.clone(),
+            log_index: log.log_index,
+            address: log.address.clone(),
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+        }
+    }
+
+    pub fn from_erc20_transfer(transfer: &DatabaseEVMErc20Transfer, chain: &str) -> Self {
+        IndexedEvent::Erc20Transfer {
+            chain: chain.to_string(),
+            hash: transfer.hash.clone(),
+            log_index: transfer.log_index,
+            token: transfer.token.clone(),
+            from_address: transfer.from_address.clone(),
+            to_address: transfer.to_address.clone(),
+            value: transfer.value.clone(),
+        }
+    }
+
+    pub fn topic(&self) -> &'static str {
+        match self {
+            IndexedEvent::Block { .. } => "block",
+            IndexedEvent::Log { .. } => "log",
+            IndexedEvent::Erc20Transfer { .. } => "erc20_transfer",
+            IndexedEvent::NewAddress { .. } => "new_address",
+        }
+    }
+
+    /// Compact payload of the `pg_notify` events, which are limited to 8000 bytes. Logs are not
+    /// notified, their data can be larger.
+    pub fn to_notify_payload(&self) -> Option<String> {
+        let payload = match self {
+            IndexedEvent::Block {
+                chain,
+                number,
+                block_hash,
+                ..
+            } => json!({ "type": "block", "chain": chain, "number": number, "hash": block_hash }),
+            IndexedEvent::Log { .. } => return None,
+            IndexedEvent::Erc20Transfer {
+                chain,
+                hash,
+                log_index,
+                token,
+                from_address,
+                to_address,
+                value,
+            } => json!({
+                "type": "erc20_transfer",
+                "chain": chain,
+                "hash": hash,
+                "log_index": log_index,
+                "token": token,
+                "from": from_address,
+                "to": to_address,
+                "value": value,
+            }),
+            IndexedEvent::NewAddress {
+                chain,
+                address,
+                block_number,
+                role,
+                ..
+            } => json!({
+                "type": "new_address",
+                "chain": chain,
+                "address": address,
+                "number": block_number,
+                "role": role,
+            }),
+        };
+
+        Some(payload.to_string())
+    }
+
+    pub fn to_outbox_event(&self, chain: &str) -> DatabaseEVMOutboxEvent {
+        DatabaseEVMOutboxEvent {
+            chain: chain.to_string(),
+            topic: self.topic().to_string(),
+            payload: serde_json::to_string(self).unwrap(),
+        }
+    }
+}
+
+/// Subscription filter, every field that is set must match the event.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventsFilter {
+    pub chain: Option<String>,
+    pub address: Option<String>,
+    pub topic0: Option<String>,
+    pub token: Option<String>,
+}
+
+impl EventsFilter {
+    pub fn matches(&self, event: &IndexedEvent) -> bool {
+        match event {
+            IndexedEvent::Block { chain, .. } => {
+                self.address.is_none()
+                    && self.topic0.is_none()
+                    && self.token.is_none()
+                    && matches_field(&self.chain, chain)
+            }
+            IndexedEvent::Log {
+                chain,
+                address,
+                topics,
+                ..
+            } => {
+                let topic0 = match topics.first() {
+                    Some(Some(topic0)) => topic0.clone(),
+                    _ => String::new(),
+                };
+
+                matches_field(&self.chain, chain)
+                    && matches_field(&self.address, address)
+                    && matches_field(&self.token, address)
+                    && matches_field(&self.topic0, &topic0)
+            }
+            IndexedEvent::Erc20Transfer {
+                chain,
+                token,
+                from_address,
+                to_address,
+                ..
+            } => {
+                let address_matches = matches_field(&self.address, from_address)
+                    || matches_field(&self.address, to_address);
+
+                self.topic0.is_none()
+                    && address_matches
+                    && matches_field(&self.chain, chain)
+                    && matches_field(&self.token, token)
+            }
+            IndexedEvent::NewAddress { chain, address, .. } => {
+                self.topic0.is_none()
+                    && self.token.is_none()
+                    && matches_field(&self.chain, chain)
+                    && matches_field(&self.address, address)
+            }
+        }
+    }
+}
+
+/// Notifies the events on `NOTIFY_CHANNEL`, listeners only receive them once the data is
+/// committed, so this must be called after storing it.
+pub fn notify_events(db: &EVMDatabase, events: &Vec<IndexedEvent>) -> Result<()> {
+    let payloads: Vec<String> = events
+        .iter()
+        .filter_map(|event| event.to_notify_payload())
+        .collect();
+
+    if payloads.len() == 0 {
+        return Ok(());
+    }
+
+    let mut connection = db.establish_connection();
+
+    sql_query("SELECT pg_notify($1, payload) FROM UNNEST($2::text[]) AS payload")
+        .bind::<Text, _>(NOTIFY_CHANNEL)
+        .bind::<Array<Text>, _>(payloads)
+        .execute(&mut connection)?;
+
+    Ok(())
+}
+
+fn matches_field(filter: &Option<String>, value: &String) -> bool {
+    match filter {
+        Some(filter) => filter.to_lowercase() == value.to_lowercase(),
+        None => true,
+    }
+}
+
+pub fn publish_events(db: &EVMDatabase, events: &Vec<IndexedEvent>) -> Result<()> {
+    if events.len() == 0 {
+        return Ok(());
+    }
+
+    let mut connection = db.redis.get_connection()?;
+
+    let serialized = serde_json::to_string(events)?;
+
+    let _: () = connection.publish(EVENTS_CHANNEL, serialized)?;
+
+    Ok(())
+}
@@ -0,0 +1,1171 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    time::Duration,
+};
+
+use diesel::{
+    dsl::min,
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Bytea, Nullable, Text},
+};
+use log::*;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::chains::chains::get_chains;
+use crate::db::{
+    compression::{DATA_PAYLOAD, INPUT_PAYLOAD},
+    db::EVMDatabase,
+    schema::{
+        evm_blocks, evm_erc20_tokens, evm_erc20_transfers, evm_transactions, evm_transactions_logs,
+    },
+};
+use crate::parsers::{
+    ens_parser::{get_ens_name, ENS_CHAIN},
+    erc20_transfers_parser::DatabaseEVMErc20Transfer,
+};
+
+pub mod proto {
+    tonic::include_proto!("evm_indexer");
+}
+
+use proto::{
+    indexer_server::Indexer, log_stream_message, BlockMessage, Cursor, Erc20TransferMessage,
+    InvalidateMessage, LogMessage, LogStreamMessage, StreamRequest, TransactionMessage,
+};
+
+pub use proto::indexer_server::IndexerServer;
+
+#[derive(QueryableByName, Debug)]
+struct StreamedLog {
+    #[diesel(sql_type = BigInt)]
+    block_number: i64,
+    #[diesel(sql_type = Text)]
+    block_hash: String,
+    #[diesel(sql_type = Text)]
+    hash: String,
+    #[diesel(sql_type = BigInt)]
+    transaction_index: i64,
+    #[diesel(sql_type = BigInt)]
+    log_index: i64,
+    #[diesel(sql_type = Text)]
+    address: String,
+    #[diesel(sql_type = Array<Nullable<Text>>)]
+    topics: Vec<Option<String>>,
+    #[diesel(sql_type = Text)]
+    data: String,
+    #[diesel(sql_type = Nullable<Bytea>)]
+    data_zstd: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexerGrpcService {
+    pub db: EVMDatabase,
+    pub batch_size: i64,
+    pub poll_interval: Duration,
+    pub ens_names: bool,
+}
+
+impl IndexerGrpcService {
+    pub fn new(db: EVMDatabase, batch_size: i64, poll_interval: Duration, ens_names: bool) -> Self {
+        Self {
+            db,
+            batch_size,
+            poll_interval,
+            ens_names,
+        }
+    }
+
+    /// ENS names are resolved once per address and block for each loaded batch.
+    fn get_ens_name(
+        &self,
+        connection: &mut PgConnection,
+        cache: &mut HashMap<(String, i64), String>,
+        address: &String,
+        block_number: i64,
+    ) -> String {
+        cache
+            .entry((address.clone(), block_number))
+            .or_insert_with(|| get_ens_name(connection, address, block_number).unwrap_or_default())
+            .clone()
+    }
+
+    /// Contiguous canonical blocks after the cursor. The blocks and transactions streams wait
+    /// at a missing block until it's indexed, so blocks stored by a backfill after the stream
+    /// reached them are not skipped.
+    fn get_contiguous_blocks(
+        &self,
+        chain: &String,
+        cursor: &Cursor,
+    ) -> Result<Vec<(i64, String)>, diesel::result::Error> {
+        let from_block = match cursor.block_number < 0 {
+            true => self.get_first_block(chain)?,
+            false => cursor.block_number + 1,
+        };
+
+        self.get_block_hashes(chain, from_block, from_block + self.batch_size - 1)
+    }
+
+    fn get_blocks_after(
+        &self,
+        chain: &String,
+        cursor: &Cursor,
+    ) -> Result<Vec<BlockMessage>, diesel::result::Error> {
+        let blocks = self.get_contiguous_blocks(chain, cursor)?;
+
+        if blocks.len() == 0 {
+            return Ok(Vec::new());
+        }
+
+        let hashes: Vec<String> = blocks
+            .into_iter()
+            .map(|(_, block_hash)| block_hash)
+            .collect();
+
+        let mut connection = self.db.establish_read_connection();
+
+        let blocks = evm_blocks::table
+            .select((
+                evm_blocks::chain,
+                evm_blocks::number,
+                evm_blocks::block_hash,
+                evm_blocks::parent_hash,
+                evm_blocks::timestamp,
+                evm_blocks::miner,
+                evm_blocks::gas_used,
+                evm_blocks::gas_limit,
+                evm_blocks::base_fee_per_gas,
+                evm_blocks::transactions,
+            ))
+            .filter(evm_blocks::chain.eq(chain))
+            .filter(evm_blocks::block_hash.eq_any(hashes))
+            .order(evm_blocks::number.asc())
+            .limit(self.batch_size)
+            .load::<(
+                String,
+                i64,
+                String,
+                String,
+                String,
+                String,
+                String,
+                String,
+                String,
+                i64,
+            )>(&mut connection)?;
+
+        Ok(blocks
+            .into_iter()
+            .map(
+                |(
+                    chain,
+                    number,
+                    block_hash,
+                    parent_hash,
+                    timestamp,
+                    miner,
+                    gas_used,
+                    gas_limit,
+                    base_fee_per_gas,
+                    transactions,
+                )| BlockMessage {
+                    cursor: Some(Cursor {
+                        block_number: number,
+                        index: 0,
+                        block_hash: String::new(),
+                    }),
+                    chain,
+                    number,
+                    block_hash,
+                    parent_hash,
+                    timestamp,
+                    miner,
+                    gas_used,
+                    gas_limit,
+                    base_fee_per_gas,
+                    transactions,
+                },
+            )
+            .collect())
+    }
+
+    /// Input of a transaction, decompressed when it was stored compressed.
+    fn get_input(&self, input: String, input_zstd: Option<Vec<u8>>) -> String {
+        match self.db.decompress_payload(INPUT_PAYLOAD, input, input_zstd) {
+            Ok(input) => input,
+            Err(err) => {
+                warn!("Unable to decompress a transaction input: {}", err);
+
+                String::new()
+            }
+        }
+    }
+
+    fn get_transactions_after(
+        &self,
+        chain: &String,
+        cursor: &Cursor,
+    ) -> Result<Vec<TransactionMessage>, diesel::result::Error> {
+        let blocks = self.get_contiguous_blocks(chain, cursor)?;
+
+        let last_block = match blocks.last() {
+            Some((number, _)) => *number,
+            None => cursor.block_number,
+        };
+
+        let hashes: Vec<String> = blocks
+            .into_iter()
+            .map(|(_, block_hash)| block_hash)
+            .collect();
+
+        let mut connection = self.db.establish_read_connection();
+
+        let transactions = evm_transactions::table
+            .select((
+                evm_transactions::chain,
+                evm_transactions::hash,
+                evm_transactions::block_number,
+                evm_transactions::transaction_index,
+                evm_transactions::from_address,
+                evm_transactions::to_address,
+                evm_transactions::value,
+                evm_transactions::method,
+                evm_transactions::input,
+                evm_transactions::input_zstd,
+                evm_transactions::timestamp,
+            ))
+            .filter(evm_transactions::chain.eq(chain))
+            .filter(
+                evm_transactions::block_number.gt(cursor.block_number).or(
+                    evm_transactions::block_number
+                        .eq(cursor.block_number)
+                        .and(evm_transactions::transaction_index.gt(cursor.index)),
+                ),
+            )
+            .filter(evm_transactions::block_number.le(last_block))
+            // The rest of the cursor block was checked when its first transactions were served.
+            .filter(
+                evm_transactions::block_hash
+                    .eq_any(hashes)
+                    .or(evm_transactions::block_number.eq(cursor.block_number)),
+            )
+            .order((
+                evm_transactions::block_number.asc(),
+                evm_transactions::transaction_index.asc(),
+            ))
+            .limit(self.batch_size)
+            .load::<(
+                String,
+                String,
+                i64,
+                i64,
+                String,
+                String,
+                String,
+                String,
+                String,
+                Option<Vec<u8>>,
+                String,
+            )>(&mut connection)?;
+
+        let mut messages: Vec<TransactionMessage> = transactions
+            .into_iter()
+            .map(
+                |(
+                    chain,
+                    hash,
+                    block_number,
+                    transaction_index,
+                    from_address,
+                    to_address,
+                    value,
+                    method,
+                    input,
+                    input_zstd,
+                    timestamp,
+                )| TransactionMessage {
+                    cursor: Some(Cursor {
+                        block_number,
+                        index: transaction_index,
+                        block_hash: String::new(),
+                    }),
+                    chain,
+                    hash,
+                    block_number,
+                    transaction_index,
+                    from_address,
+                    to_address,
+                    value,
+                    method,
+                    input: self.get_input(input, input_zstd),
+                    timestamp,
+                    from_ens: String::new(),
+                    to_ens: String::new(),
+                },
+            )
+            .collect();
+
+        if self.ens_names && chain == ENS_CHAIN {
+            let mut cache = HashMap::new();
+
+            for message in messages.iter_mut() {
+                message.from_ens = self.get_ens_name(
+                    &mut connection,
+                    &mut cache,
+                    &message.from_address,
+                    message.block_number,
+                );
+
+                message.to_ens = self.get_ens_name(
+                    &mut connection,
+                    &mut cache,
+                    &message.to_address,
+                    message.block_number,
+                );
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Transfers don't store the chain or block, so they are resolved through the transactions
+    /// of a block range. Returns `None` until every block of the range is indexed and their
+    /// logs are parsed. Transfers of tokens scored above `max_spam_score` are skipped, unscored
+    /// tokens are always kept.
+    fn get_transfers_in_range(
+        &self,
+        chain: &String,
+        from_block: i64,
+        to_block: i64,
+        max_spam_score: Option<i64>,
+    ) -> Result<Option<Vec<(i64, DatabaseEVMErc20Transfer)>>, diesel::result::Error> {
+        let blocks = self.get_block_hashes(chain, from_block, to_block)?;
+
+        match blocks.last() {
+            Some((last_block, _)) if *last_block == to_block => (),
+            _ => return Ok(None),
+        }
+
+        let block_hashes: Vec<String> = blocks
+            .into_iter()
+            .map(|(_, block_hash)| block_hash)
+            .collect();
+
+        let mut connection = self.db.establish_read_connection();
+
+        let transactions: HashMap<String, i64> = evm_transactions::table
+            .select((evm_transactions::hash, evm_transactions::block_number))
+            .filter(evm_transactions::chain.eq(chain))
+            .filter(evm_transactions::block_hash.eq_any(block_hashes))
+            .load::<(String, i64)>(&mut connection)?
+            .into_iter()
+            .collect();
+
+        let hashes: Vec<String> = transactions.keys().cloned().collect();
+
+        let unparsed_logs: i64 = evm_transactions_logs::table
+            .filter(evm_transactions_logs::hash.eq_any(&hashes))
+            .filter(
+                evm_transactions_logs::erc20_transfers_parsed
+                    .is_null()
+                    .or(evm_transactions_logs::erc20_transfers_parsed.eq(false)),
+            )
+            .count()
+            .get_result(&mut connection)?;
+
+        if unparsed_logs > 0 {
+            return Ok(None);
+        }
+
+        let mut transfers: Vec<(i64, DatabaseEVMErc20Transfer)> = evm_erc20_transfers::table
+            .select(DatabaseEVMErc20Transfer::as_select())
+            .filter(evm_erc20_transfers::hash.eq_any(hashes))
+            .load::<DatabaseEVMErc20Transfer>(&mut connection)?
+            .into_iter()
+            .map(|transfer| (transactions[&transfer.hash], transfer))
+            .collect();
+
+        match max_spam_score {
+            Some(max_spam_score) => {
+                let tokens: Vec<String> = transfers
+                    .iter()
+                    .map(|(_, transfer)| transfer.token.clone())
+                    .collect();
+
+                let spam_tokens: HashSet<String> = evm_erc20_tokens::table
+                    .select(evm_erc20_tokens::address)
+                    .filter(evm_erc20_tokens::chain.eq(chain))
+                    .filter(evm_erc20_tokens::address.eq_any(tokens))
+                    .filter(evm_erc20_tokens::spam_score.gt(max_spam_score))
+                    .load::<String>(&mut connection)?
+                    .into_iter()
+                    .collect();
+
+                transfers.retain(|(_, transfer)| !spam_tokens.contains(&transfer.token));
+            }
+            None => (),
+        }
+
+        transfers.sort_by_key(|(block_number, transfer)| (*block_number, transfer.log_index));
+
+        Ok(Some(transfers))
+    }
+
+    /// First stored block of the chain, where streams without a cursor start.
+    fn get_first_block(&self, chain: &String) -> Result<i64, diesel::result::Error> {
+        let mut connection = self.db.establish_read_connection();
+
+        let first_block: Option<i64> = evm_blocks::table
+            .select(min(evm_blocks::number))
+            .filter(evm_blocks::chain.eq(chain))
+            .first(&mut connection)?;
+
+        Ok(first_block.unwrap_or_default())
+    }
+
+    /// Hashes of the canonical blocks of a range, up to the first missing block so the logs of
+    /// a range are only served once every block before them is indexed.
+    fn get_block_hashes(
+        &self,
+        chain: &String,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<Vec<(i64, String)>, diesel::result::Error> {
+        let mut connection = self.db.establish_read_connection();
+
+        let blocks = evm_blocks::table
+            .select((
+                evm_blocks::number,
+                evm_blocks::block_hash,
+                evm_blocks::parent_hash,
+            ))
+            .filter(evm_blocks::chain.eq(chain))
+            .filter(evm_blocks::number.ge(from_block))
+            .filter(evm_blocks::number.le(to_block))
+            .order((evm_blocks::number.asc(), evm_blocks::block_hash.asc()))
+            .load::<(i64, String, String)>(&mut connection)?;
+
+        Ok(get_canonical_blocks(from_block, blocks))
+    }
+
+    /// Logs of the canonical blocks of a range, backfilled logs are located through the log
+    /// transactions like the transactions of the indexed blocks.
+    fn get_logs_in_range(
+        &self,
+        request: &StreamRequest,
+        blocks: &Vec<(i64, String)>,
+    ) -> Result<Vec<LogMessage>, diesel::result::Error> {
+        let (from_block, to_block) = match (blocks.first(), blocks.last()) {
+            (Some((from_block, _)), Some((to_block, _))) => (*from_block, *to_block),
+            _ => return Ok(Vec::new()),
+        };
+
+        let hashes: Vec<String> = blocks
+            .iter()
+            .map(|(_, block_hash)| block_hash.clone())
+            .collect();
+
+        let mut connection = self.db.establish_read_connection();
+
+        let logs = sql_query(
+            "SELECT bk.number AS block_number, bk.block_hash, l.hash, \
+            COALESCE(t.transaction_index, b.transaction_index) AS transaction_index, \
+            l.log_index, l.address, l.topics, l.data, l.data_zstd \
+            FROM evm_transactions_logs l \
+            LEFT JOIN evm_transactions t ON t.hash = l.hash \
+            LEFT JOIN evm_log_transactions b ON b.hash = l.hash \
+            INNER JOIN evm_blocks bk ON bk.chain = $1 \
+            AND bk.number = COALESCE(t.block_number, b.block_number) \
+            AND bk.block_hash = COALESCE(t.block_hash, b.block_hash) \
+            AND bk.block_hash = ANY($6) \
+            WHERE COALESCE(t.chain, b.chain) = $1 \
+            AND COALESCE(t.block_number, b.block_number) BETWEEN $2 AND $3 \
+            AND NOT l.removed \
+            AND ($4::TEXT IS NULL OR l.address = $4) \
+            AND ($5::TEXT IS NULL OR l.topics[1] = $5) \
+            ORDER BY bk.number, l.log_index",
+        )
+        .bind::<Text, _>(&request.chain)
+        .bind::<BigInt, _>(from_block)
+        .bind::<BigInt, _>(to_block)
+        .bind::<Nullable<Text>, _>(
+            request
+                .address
+                .as_ref()
+                .map(|address| address.to_lowercase()),
+        )
+        .bind::<Nullable<Text>, _>(request.topic0.as_ref().map(|topic0| topic0.to_lowercase()))
+        .bind::<Array<Text>, _>(&hashes)
+        .load::<StreamedLog>(&mut connection)?;
+
+        Ok(logs
+            .into_iter()
+            .map(|log| LogMessage {
+                cursor: Some(Cursor {
+                    block_number: log.block_number,
+                    index: log.log_index,
+                    block_hash: log.block_hash.clone(),
+                }),
+                chain: request.chain.clone(),
+                block_number: log.block_number,
+                block_hash: log.block_hash,
+                hash: log.hash,
+                transaction_index: log.transaction_index,
+                log_index: log.log_index,
+                address: log.address,
+                topics: log.topics.into_iter().flatten().collect(),
+                data: match self
+                    .db
+                    .decompress_payload(DATA_PAYLOAD, log.data, log.data_zstd)
+                {
+                    Ok(data) => data,
+                    Err(err) => {
+                        warn!("Unable to decompress the data of a log: {}", err);
+
+                        String::new()
+                    }
+                },
+            })
+            .collect())
+    }
+
+    /// First of the served blocks that is no longer the canonical block of its number, if any.
+    fn get_reorged_block(
+        &self,
+        chain: &String,
+        served: &VecDeque<(i64, String)>,
+    ) -> Result<Option<i64>, diesel::result::Error> {
+        let (from_block, to_block) = match (served.front(), served.back()) {
+            (Some((from_block, _)), Some((to_block, _))) => (*from_block, *to_block),
+            _ => return Ok(None),
+        };
+
+        let canonical = self.get_block_hashes(chain, from_block, to_block)?;
+
+        Ok(get_first_reorged_block(served, &canonical))
+    }
+}
+
+/// Canonical blocks of the stored `(number, hash, parent hash)` rows of a range, up to the first
+/// missing block. A reorg can leave the replaced block stored next to the new one until it's
+/// deleted, so each block must be the child of the previous one, and when several are stored
+/// at a number the one with a stored child wins. Without one, the range stops before them
+/// until the stale block is deleted, so a stream never flips between the two.
+fn get_canonical_blocks(from_block: i64, blocks: Vec<(i64, String, String)>) -> Vec<(i64, String)> {
+    let mut numbers: BTreeMap<i64, Vec<(String, String)>> = BTreeMap::new();
+
+    for (number, block_hash, parent_hash) in blocks {
+        numbers
+            .entry(number)
+            .or_default()
+            .push((block_hash, parent_hash));
+    }
+
+    let mut canonical: Vec<(i64, String)> = Vec::new();
+
+    let mut number = from_block;
+
+    while let Some(stored) = numbers.get(&number) {
+        let linked: Vec<&String> = stored
+            .iter()
+            .filter(|(_, parent_hash)| {
+                canonical
+                    .last()
+                    .map_or(true, |(_, block_hash)| parent_hash == block_hash)
+            })
+            .map(|(block_hash, _)| block_hash)
+            .collect();
+
+        let block_hash = match linked.len() {
+            1 => linked[0],
+            _ => {
+                let children = numbers
+                    .get(&(number + 1))
+                    .map_or(&[][..], |stored| stored.as_slice());
+
+                let parents: Vec<&String> = linked
+                    .into_iter()
+                    .filter(|block_hash| {
+                        children
+                            .iter()
+                            .any(|(_, parent_hash)| parent_hash == *block_hash)
+                    })
+                    .collect();
+
+                match parents.len() {
+                    1 => parents[0],
+                    _ => break,
+                }
+            }
+        };
+
+        canonical.push((number, block_hash.clone()));
+
+        number += 1;
+    }
+
+    canonical
+}
+
+/// First of the served blocks replaced by another canonical block. Served blocks after the
+/// canonical range are not known yet, so they are checked once the range reaches them.
+fn get_first_reorged_block(
+    served: &VecDeque<(i64, String)>,
+    canonical: &Vec<(i64, String)>,
+) -> Option<i64> {
+    let canonical: HashMap<i64, &String> = canonical
+        .iter()
+        .map(|(number, block_hash)| (*number, block_hash))
+        .collect();
+
+    served
+        .iter()
+        .find(|(number, block_hash)| {
+            canonical
+                .get(number)
+                .map_or(false, |canonical_hash| *canonical_hash != block_hash)
+        })
+        .map(|(number, _)| *number)
+}
+
+fn get_cursor(request: &StreamRequest) -> Cursor {
+    match &request.cursor {
+        Some(cursor) => cursor.clone(),
+        None => Cursor {
+            block_number: -1,
+            index: -1,
+            block_hash: String::new(),
+        },
+    }
+}
+
+/// Whether the item at `index` of the block was not served before the cursor.
+fn is_after_cursor(cursor: &Cursor, block_number: i64, index: i64) -> bool {
+    (block_number, index) > (cursor.block_number, cursor.index)
+}
+
+/// First block invalidated by a reorg. A reorged resumed block invalidates the finality depth
+/// before it, since the blocks served before the resume are unknown.
+fn get_invalid_block(reorged_block: i64, resumed_block: Option<i64>, finality_depth: i64) -> i64 {
+    match resumed_block == Some(reorged_block) {
+        true => (reorged_block - finality_depth).max(0),
+        false => reorged_block,
+    }
+}
+
+#[tonic::async_trait]
+impl Indexer for IndexerGrpcService {
+    type StreamBlocksStream = ReceiverStream<Result<BlockMessage, Status>>;
+
+    type StreamTransactionsStream = ReceiverStream<Result<TransactionMessage, Status>>;
+
+    type StreamErc20TransfersStream = ReceiverStream<Result<Erc20TransferMessage, Status>>;
+
+    type StreamLogsStream = ReceiverStream<Result<LogStreamMessage, Status>>;
+
+    async fn stream_blocks(
+        &self,
+        request: Request<StreamRequest>,
+    ) -> Result<Response<Self::StreamBlocksStream>, Status> {
+        let request = request.into_inner();
+
+        let (tx, rx) = mpsc::channel(self.batch_size as usize);
+
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut cursor = get_cursor(&request);
+
+            info!(
+                "Streaming blocks for chain {} after block {}",
+                request.chain, cursor.block_number
+            );
+
+            loop {
+                let blocks = match service.get_blocks_after(&request.chain, &cursor) {
+                    Ok(blocks) => blocks,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+
+                if blocks.len() == 0 {
+                    tokio::time::sleep(service.poll_interval).await;
+                    continue;
+                }
+
+                for message in blocks {
+                    cursor = message.cursor.clone().unwrap();
+
+                    if tx.send(Ok(message)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn stream_transactions(
+        &self,
+        request: Request<StreamRequest>,
+    ) -> Result<Response<Self::StreamTransactionsStream>, Status> {
+        let request = request.into_inner();
+
+        let (tx, rx) = mpsc::channel(self.batch_size as usize);
+
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut cursor = get_cursor(&request);
+
+            info!(
+                "Streaming transactions for chain {} after block {}",
+                request.chain, cursor.block_number
+            );
+
+            loop {
+                let transactions = match service.get_transactions_after(&request.chain, &cursor) {
+                    Ok(transactions) => transactions,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+
+                if transactions.len() == 0 {
+                    tokio::time::sleep(service.poll_interval).await;
+                    continue;
+                }
+
+                for message in transactions {
+                    cursor = message.cursor.clone().unwrap();
+
+                    if tx.send(Ok(message)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn stream_erc20_transfers(
+        &self,
+        request: Request<StreamRequest>,
+    ) -> Result<Response<Self::StreamErc20TransfersStream>, Status> {
+        let request = request.into_inner();
+
+        let (tx, rx) = mpsc::channel(self.batch_size as usize);
+
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut cursor = get_cursor(&request);
+
+            let mut from_block = match cursor.block_number < 0 {
+                true => match service.get_first_block(&request.chain) {
+                    Ok(first_block) => first_block,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                },
+                false => cursor.block_number,
+            };
+
+            info!(
+                "Streaming erc20 transfers for chain {} after block {}",
+                request.chain, cursor.block_number
+            );
+
+            loop {
+                let to_block = from_block + service.batch_size - 1;
+
+                let transfers = match service.get_transfers_in_range(
+                    &request.chain,
+                    from_block,
+                    to_block,
+                    request.max_spam_score,
+                ) {
+                    Ok(Some(transfers)) => transfers,
+                    Ok(None) => {
+                        tokio::time::sleep(service.poll_interval).await;
+                        continue;
+                    }
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+
+                let mut ens_connection = if service.ens_names && request.chain == ENS_CHAIN {
+                    Some(service.db.establish_read_connection())
+                } else {
+                    None
+                };
+
+                let mut ens_cache = HashMap::new();
+
+                for (block_number, transfer) in transfers {
+                    if !is_after_cursor(&cursor, block_number, transfer.log_index) {
+                        continue;
+                    }
+
+                    let (from_ens, to_ens) = match ens_connection.as_mut() {
+                        Some(connection) => (
+                            service.get_ens_name(
+                                connection,
+                                &mut ens_cache,
+                                &transfer.from_address,
+                                block_number,
+                            ),
+                            service.get_ens_name(
+                                connection,
+                                &mut ens_cache,
+                                &transfer.to_address,
+                                block_number,
+                            ),
+                        ),
+                        None => (String::new(), String::new()),
+                    };
+
+                    cursor = Cursor {
+                        block_number,
+                        index: transfer.log_index,
+                        block_hash: String::new(),
+                    };
+
+                    let message = Erc20TransferMessage {
+                        cursor: Some(cursor.clone()),
+                        chain: request.chain.clone(),
+                        hash: transfer.hash,
+                        block_number,
+                        log_index: transfer.log_index,
+                        token: transfer.token,
+                        from_address: transfer.from_address,
+                        to_address: transfer.to_address,
+                        value: transfer.value,
+                        from_ens,
+                        to_ens,
+                    };
+
+                    if tx.send(Ok(message)).await.is_err() {
+                        return;
+                    }
+                }
+
+                from_block = to_block + 1;
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    /// Serves the logs of the stored blocks in order, keeping the hashes of the blocks served
+    /// within the finality depth of the chain. When one of them is replaced by a reorg, an
+    /// invalidation from that block is sent and the stream restarts there. A resumed cursor is
+    /// checked against its block hash, and when it was reorged out the invalidation covers the
+    /// finality depth before it, since the blocks served before the resume are unknown.
+    async fn stream_logs(
+        &self,
+        request: Request<StreamRequest>,
+    ) -> Result<Response<Self::StreamLogsStream>, Status> {
+        let request = request.into_inner();
+
+        let finality_depth = match get_chains().get(&request.chain) {
+            Some(chain) => chain.finality_depth,
+            None => {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown chain {}",
+                    request.chain
+                )))
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(self.batch_size as usize);
+
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut cursor = get_cursor(&request);
+
+            let mut from_block = match cursor.block_number < 0 {
+                true => match service.get_first_block(&request.chain) {
+                    Ok(first_block) => first_block,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                },
+                false => cursor.block_number,
+            };
+
+            let mut served: VecDeque<(i64, String)> = VecDeque::new();
+
+            let mut resumed_block = None;
+
+            if cursor.block_hash.len() > 0 {
+                served.push_back((cursor.block_number, cursor.block_hash.clone()));
+
+                resumed_block = Some(cursor.block_number);
+            }
+
+            info!(
+                "Streaming logs for chain {} after block {}",
+                request.chain, cursor.block_number
+            );
+
+            loop {
+                let reorged_block = match service.get_reorged_block(&request.chain, &served) {
+                    Ok(reorged_block) => reorged_block,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+
+                match reorged_block {
+                    Some(reorged_block) => {
+                        let invalid_block =
+                            get_invalid_block(reorged_block, resumed_block, finality_depth);
+
+                        warn!(
+                            "Invalidating the logs of chain {} from block {}",
+                            request.chain, invalid_block
+                        );
+
+                        served.retain(|(number, _)| *number < invalid_block);
+
+                        resumed_block = None;
+
+                        from_block = invalid_block;
+
+                        cursor = Cursor {
+                            block_number: invalid_block,
+                            index: -1,
+                            block_hash: String::new(),
+                        };
+
+                        let message = LogStreamMessage {
+                            message: Some(log_stream_message::Message::Invalidate(
+                                InvalidateMessage {
+                                    cursor: Some(cursor.clone()),
+                                    chain: request.chain.clone(),
+                                    from_block: invalid_block,
+                                },
+                            )),
+                        };
+
+                        if tx.send(Ok(message)).await.is_err() {
+                            return;
+                        }
+                    }
+                    None => (),
+                }
+
+                let to_block = from_block + service.batch_size - 1;
+
+                let blocks = match service.get_block_hashes(&request.chain, from_block, to_block) {
+                    Ok(blocks) => blocks,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+
+                let last_block = match blocks.last() {
+                    Some((last_block, _)) => *last_block,
+                    None => {
+                        tokio::time::sleep(service.poll_interval).await;
+                        continue;
+                    }
+                };
+
+                let logs = match service.get_logs_in_range(&request, &blocks) {
+                    Ok(logs) => logs,
+                    Err(err) => {
+                        let _ = tx.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+
+                for log in logs {
+                    if !is_after_cursor(&cursor, log.block_number, log.log_index) {
+                        continue;
+                    }
+
+                    cursor = log.cursor.clone().unwrap();
+
+                    let message = LogStreamMessage {
+                        message: Some(log_stream_message::Message::Log(log)),
+                    };
+
+                    if tx.send(Ok(message)).await.is_err() {
+                        return;
+                    }
+                }
+
+                for (number, block_hash) in blocks {
+                    if served.back().map_or(true, |(last, _)| *last < number) {
+                        served.push_back((number, block_hash));
+                    }
+                }
+
+                while served.len() > finality_depth as usize {
+                    served.pop_front();
+                }
+
+                resumed_block = None;
+
+                from_block = last_block + 1;
+
+                // Partial ranges reached the last indexed block.
+                if last_block < to_block {
+                    tokio::time::sleep(service.poll_interval).await;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: i64, block_hash: &str, parent_hash: &str) -> (i64, String, String) {
+        (number, block_hash.to_string(), parent_hash.to_string())
+    }
+
+    #[test]
+    fn stops_at_the_first_missing_block() {
+        let blocks = vec![
+            block(10, "0x0a", "0x09"),
+            block(11, "0x0b", "0x0a"),
+            block(13, "0x0d", "0x0c"),
+        ];
+
+        assert_eq!(
+            get_canonical_blocks(10, blocks.clone()),
+            vec![(10, "0x0a".to_string()), (11, "0x0b".to_string())]
+        );
+
+        assert_eq!(get_canonical_blocks(9, blocks), Vec::new());
+    }
+
+    #[test]
+    fn settles_on_the_canonical_block_of_a_number() {
+        // The reorged 0x0b is still stored next to 0x1b, whose child was indexed.
+        let blocks = vec![
+            block(10, "0x0a", "0x09"),
+            block(11, "0x0b", "0x0a"),
+            block(11, "0x1b", "0x0a"),
+            block(12, "0x1c", "0x1b"),
+        ];
+
+        let canonical = vec![
+            (10, "0x0a".to_string()),
+            (11, "0x1b".to_string()),
+            (12, "0x1c".to_string()),
+        ];
+
+        assert_eq!(get_canonical_blocks(10, blocks.clone()), canonical);
+
+        // The stale block is first in the range.
+        assert_eq!(get_canonical_blocks(11, blocks), canonical[1..].to_vec());
+
+        // Without a child neither is canonical yet.
+        let blocks = vec![
+            block(10, "0x0a", "0x09"),
+            block(11, "0x0b", "0x0a"),
+            block(11, "0x1b", "0x0a"),
+        ];
+
+        assert_eq!(
+            get_canonical_blocks(10, blocks),
+            vec![(10, "0x0a".to_string())]
+        );
+
+        // A block that isn't the child of the previous one is from another fork.
+        let blocks = vec![block(10, "0x0a", "0x09"), block(11, "0x1b", "0x1a")];
+
+        assert_eq!(
+            get_canonical_blocks(10, blocks),
+            vec![(10, "0x0a".to_string())]
+        );
+    }
+
+    #[test]
+    fn finds_the_first_reorged_block() {
+        let served = VecDeque::from([
+            (10, "0x0a".to_string()),
+            (11, "0x0b".to_string()),
+            (12, "0x0c".to_string()),
+        ]);
+
+        let canonical = vec![
+            (10, "0x0a".to_string()),
+            (11, "0x1b".to_string()),
+            (12, "0x1c".to_string()),
+        ];
+
+        assert_eq!(get_first_reorged_block(&served, &canonical), Some(11));
+
+        assert_eq!(
+            get_first_reorged_block(&served, &canonical[..1].to_vec()),
+            None
+        );
+
+        // Both hashes of block 11 are stored, the stream keeps the canonical one.
+        let blocks = vec![
+            block(10, "0x0a", "0x09"),
+            block(11, "0x0b", "0x0a"),
+            block(11, "0x1b", "0x0a"),
+            block(12, "0x1c", "0x1b"),
+        ];
+
+        for _ in 0..2 {
+            assert_eq!(
+                get_first_reorged_block(&served, &get_canonical_blocks(10, blocks.clone())),
+                Some(11)
+            );
+        }
+
+        let served = VecDeque::from([(10, "0x0a".to_string()), (11, "0x1b".to_string())]);
+
+        assert_eq!(
+            get_first_reorged_block(&served, &get_canonical_blocks(10, blocks)),
+            None
+        );
+    }
+
+    #[test]
+    fn resumes_after_the_cursor() {
+        let cursor = get_cursor(&StreamRequest::default());
+
+        assert!(is_after_cursor(&cursor, 0, 0));
+
+        let cursor = Cursor {
+            block_number: 10,
+            index: 3,
+            block_hash: "0x0a".to_string(),
+        };
+
+        assert!(!is_after_cursor(&cursor, 9, 5));
+        assert!(!is_after_cursor(&cursor, 10, 3));
+        assert!(is_after_cursor(&cursor, 10, 4));
+        assert!(is_after_cursor(&cursor, 11, -1));
+
+        assert_eq!(get_invalid_block(100, None, 64), 100);
+        assert_eq!(get_invalid_block(100, Some(100), 64), 36);
+        assert_eq!(get_invalid_block(10, Some(10), 64), 0);
+        assert_eq!(get_invalid_block(100, Some(90), 64), 100);
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Bytea, Nullable, Text},
+};
+use jsonrpsee::{core::Error, RpcModule};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{compression::DATA_PAYLOAD, db::EVMDatabase},
+    parsers::deployments::{get_protocol_deployments, DeploymentsParams},
+};
+
+use super::{
+    pagination::{get_page, Page, PageCursor, PageLimits, PageRequest},
+    search::{search, SearchParams},
+};
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct TransactionItem {
+    #[diesel(sql_type = Text)]
+    pub hash: String,
+    #[diesel(sql_type = BigInt)]
+    pub block_number: i64,
+    #[diesel(sql_type = BigInt)]
+    pub transaction_index: i64,
+    #[diesel(sql_type = Text)]
+    pub timestamp: String,
+    #[diesel(sql_type = Text)]
+    pub from_address: String,
+    #[diesel(sql_type = Text)]
+    pub to_address: String,
+    #[diesel(sql_type = Text)]
+    pub method: String,
+    #[diesel(sql_type = Text)]
+    pub value: String,
+}
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct TransferItem {
+    #[diesel(sql_type = Text)]
+    pub hash: String,
+    #[diesel(sql_type = BigInt)]
+    pub block_number: i64,
+    #[diesel(sql_type = BigInt)]
+    pub log_index: i64,
+    #[diesel(sql_type = Text)]
+    pub token: String,
+    #[diesel(sql_type = Text)]
+    pub from_address: String,
+    #[diesel(sql_type = Text)]
+    pub to_address: String,
+    #[diesel(sql_type = Text)]
+    pub value: String,
+}
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct LogItem {
+    #[diesel(sql_type = Text)]
+    pub hash: String,
+    #[diesel(sql_type = BigInt)]
+    pub block_number: i64,
+    #[diesel(sql_type = BigInt)]
+    pub log_index: i64,
+    #[diesel(sql_type = Text)]
+    pub address: String,
+    #[diesel(sql_type = Array<Nullable<Text>>)]
+    pub topics: Vec<Option<String>>,
+    #[diesel(sql_type = Text)]
+    pub data: String,
+    #[diesel(sql_type = Nullable<Bytea>)]
+    #[serde(skip)]
+    pub data_zstd: Option<Vec<u8>>,
+}
+
+/// Parameters of the list methods, e.g.
+/// `{ "chain": "ethereum", "address": "0x...", "cursor": "16500000-12", "page_size": 100 }`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListParams {
+    pub chain: String,
+    pub address: String,
+    pub topic: Option<String>,
+    #[serde(flatten)]
+    pub page: PageRequest,
+}
+
+/// Lists of indexed data paginated with keyset cursors instead of offsets, so the cost of a page
+/// doesn't grow with the length of the address history.
+#[derive(Debug, Clone)]
+pub struct ListsService {
+    pub db: EVMDatabase,
+    pub limits: PageLimits,
+}
+
+impl ListsService {
+    pub fn new(db: EVMDatabase, limits: PageLimits) -> Self {
+        Self { db, limits }
+    }
+
+    /// Without a cursor the list starts at the newest item.
+    fn get_cursor(&self, params: &ListParams) -> Result<PageCursor> {
+        match &params.page.cursor {
+            Some(cursor) => PageCursor::decode(cursor),
+            None => Ok(PageCursor {
+                block_number: i64::MAX,
+                index: i64::MAX,
+            }),
+        }
+    }
+
+    /// Each side of the union walks its own index, so a page only reads up to two pages of rows.
+    pub fn get_transactions(&self, params: &ListParams) -> Result<Page<TransactionItem>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let cursor = self.get_cursor(params)?;
+
+        let page_size = self.limits.get_page_size(&params.page);
+
+        let columns = "hash, block_number, transaction_index, timestamp, from_address, \
+            to_address, method, value";
+
+        let query = format!(
+            "SELECT * FROM ( \
+            (SELECT {columns} FROM evm_transactions WHERE chain = $1 AND from_address = $2 \
+            AND (block_number, transaction_index) < ($3, $4) \
+            ORDER BY block_number DESC, transaction_index DESC LIMIT $5) \
+            UNION \
+            (SELECT {columns} FROM evm_transactions WHERE chain = $1 AND to_address = $2 \
+            AND (block_number, transaction_index) < ($3, $4) \
+            ORDER BY block_number DESC, transaction_index DESC LIMIT $5) \
+            ) t ORDER BY block_number DESC, transaction_index DESC LIMIT $5",
+            columns = columns
+        );
+
+        let transactions = sql_query(query)
+            .bind::<Text, _>(&params.chain)
+            .bind::<Text, _>(params.address.to_lowercase())
+            .bind::<BigInt, _>(cursor.block_number)
+            .bind::<BigInt, _>(cursor.index)
+            .bind::<BigInt, _>(page_size)
+            .load::<TransactionItem>(&mut connection)?;
+
+        Ok(get_page(transactions, page_size, |transaction| {
+            PageCursor {
+                block_number: transaction.block_number,
+                index: transaction.transaction_index,
+            }
+        }))
+    }
+
+    /// Like the transactions, each side of the union walks its own index.
+    pub fn get_transfers(&self, params: &ListParams) -> Result<Page<TransferItem>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let cursor = self.get_cursor(params)?;
+
+        let page_size = self.limits.get_page_size(&params.page);
+
+        let columns = "e.hash, e.block_number, e.log_index, e.token, e.from_address, \
+            e.to_address, e.value";
+
+        let query = format!(
+            "SELECT * FROM ( \
+            (SELECT {columns} FROM evm_erc20_transfers e JOIN evm_transactions t ON t.hash = e.hash \
+            WHERE t.chain = $1 AND e.from_address = $2 \
+            AND (e.block_number, e.log_index) < ($3, $4) \
+            ORDER BY e.block_number DESC, e.log_index DESC LIMIT $5) \
+            UNION \
+            (SELECT {columns} FROM evm_erc20_transfers e JOIN evm_transactions t ON t.hash = e.hash \
+            WHERE t.chain = $1 AND e.to_address = $2 \
+            AND (e.block_number, e.log_index) < ($3, $4) \
+            ORDER BY e.block_number DESC, e.log_index DESC LIMIT $5) \
+            ) e ORDER BY block_number DESC, log_index DESC LIMIT $5",
+            columns = columns
+        );
+
+        let transfers = sql_query(query)
+            .bind::<Text, _>(&params.chain)
+            .bind::<Text, _>(params.address.to_lowercase())
+            .bind::<BigInt, _>(cursor.block_number)
+            .bind::<BigInt, _>(cursor.index)
+            .bind::<BigInt, _>(page_size)
+            .load::<TransferItem>(&mut connection)?;
+
+        Ok(get_page(transfers, page_size, |transfer| PageCursor {
+            block_number: transfer.block_number,
+            index: transfer.log_index,
+        }))
+    }
+
+    /// Logs emitted by an address, optionally only the ones with the given first topic.
+    pub fn get_logs(&self, params: &ListParams) -> Result<Page<LogItem>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let cursor = self.get_cursor(params)?;
+
+        let page_size = self.limits.get_page_size(&params.page);
+
+        let mut logs = sql_query(
+            "SELECT l.hash, l.block_number, l.log_index, l.address, l.topics, l.data, l.data_zstd \
+            FROM evm_transactions_logs l JOIN evm_transactions t ON t.hash = l.hash \
+            WHERE t.chain = $1 AND l.address = $2 \
+            AND ($3::TEXT IS NULL OR l.topics[1] = $3) \
+            AND (l.block_number, l.log_index) < ($4, $5) \
+            ORDER BY l.block_number DESC, l.log_index DESC LIMIT $6",
+        )
+        .bind::<Text, _>(&params.chain)
+        .bind::<Text, _>(params.address.to_lowercase())
+        .bind::<Nullable<Text>, _>(params.topic.as_ref().map(|topic| topic.to_lowercase()))
+        .bind::<BigInt, _>(cursor.block_number)
+        .bind::<BigInt, _>(cursor.index)
+        .bind::<BigInt, _>(page_size)
+        .load::<LogItem>(&mut connection)?;
+
+        for log in logs.iter_mut() {
+            log.data =
+                self.db
+                    .decompress_payload(DATA_PAYLOAD, log.data.clone(), log.data_zstd.take())?;
+        }
+
+        Ok(get_page(logs, page_size, |log| PageCursor {
+            block_number: log.block_number,
+            index: log.log_index,
+        }))
+    }
+
+    /// Registers `get_transactions`, `get_transfers`, `get_logs`, `search` and
+    /// `get_protocol_deployments`. Queries are blocking, so they run outside of the server
+    /// workers.
+    pub fn into_rpc(self) -> Result<RpcModule<Self>> {
+        let mut module = RpcModule::new(self);
+
+        module.register_blocking_method("get_transactions", |params, service| {
+            let params: ListParams = params.one()?;
+
+            service
+                .get_transactions(&params)
+                .map_err(|err| Error::Custom(err.to_string()))
+        })?;
+
+        module.register_blocking_method("get_transfers", |params, service| {
+            let params: ListParams = params.one()?;
+
+            service
+                .get_transfers(&params)
+                .map_err(|err| Error::Custom(err.to_string()))
+        })?;
+
+        module.register_blocking_method("get_logs", |params, service| {
+            let params: ListParams = params.one()?;
+
+            service
+                .get_logs(&params)
+                .map_err(|err| Error::Custom(err.to_string()))
+        })?;
+
+        // Search results aren't paginated, the page size only limits them.
+        module.register_blocking_method("search", |params, service| {
+            let params: SearchParams = params.one()?;
+
+            search(
+                &service.db,
+                &params.text,
+                params.chain.as_ref(),
+                service.limits.get_page_size(&params.page),
+            )
+            .map_err(|err| Error::Custom(err.to_string()))
+        })?;
+
+        // The registry is small, so the deployments are listed whole.
+        module.register_blocking_method("get_protocol_deployments", |params, service| {
+            let params: DeploymentsParams = params.one()?;
+
+            get_protocol_deployments(&service.db, &params)
+                .map_err(|err| Error::Custom(err.to_string()))
+        })?;
+
+        Ok(module)
+    }
+}
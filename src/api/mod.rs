@@ -0,0 +1,11 @@
+#[cfg(feature = "api")]
+pub mod auth;
+pub mod events;
+#[cfg(feature = "api")]
+pub mod grpc;
+#[cfg(feature = "api")]
+pub mod lists;
+pub mod pagination;
+pub mod search;
+#[cfg(feature = "api")]
+pub mod websocket;
@@ -0,0 +1,71 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Position of the last item of a page. Lists are ordered newest first by block number and
+/// position inside the block, so the next page starts right after the cursor no matter how many
+/// items are indexed in between.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageCursor {
+    pub block_number: i64,
+    pub index: i64,
+}
+
+impl PageCursor {
+    /// Cursors are opaque to the clients and encoded as `<block_number>-<index>`.
+    pub fn encode(&self) -> String {
+        format!("{}-{}", self.block_number, self.index)
+    }
+
+    pub fn decode(cursor: &String) -> Result<Self> {
+        let (block_number, index) = match cursor.split_once('-') {
+            Some(parts) => parts,
+            None => bail!("Invalid page cursor {}", cursor),
+        };
+
+        match (block_number.parse::<i64>(), index.parse::<i64>()) {
+            (Ok(block_number), Ok(index)) => Ok(Self {
+                block_number,
+                index,
+            }),
+            _ => bail!("Invalid page cursor {}", cursor),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageRequest {
+    pub cursor: Option<String>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the following page, unset once the list is exhausted.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PageLimits {
+    pub default_page_size: i64,
+    pub max_page_size: i64,
+}
+
+impl PageLimits {
+    pub fn get_page_size(&self, request: &PageRequest) -> i64 {
+        request
+            .page_size
+            .unwrap_or(self.default_page_size)
+            .clamp(1, self.max_page_size)
+    }
+}
+
+/// Builds the page from the loaded items, a full page means there may be more items after it.
+pub fn get_page<T>(items: Vec<T>, page_size: i64, cursor: impl Fn(&T) -> PageCursor) -> Page<T> {
+    let next_cursor = match items.last() {
+        Some(last) if items.len() as i64 >= page_size => Some(cursor(last).encode()),
+        _ => None,
+    };
+
+    Page { items, next_cursor }
+}
@@ -0,0 +1,97 @@
+use anyhow::{bail, Result};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Double, Nullable, Text},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::db::db::EVMDatabase;
+
+use super::pagination::PageRequest;
+
+/// Shortest text searched, trigrams of shorter texts match almost anything.
+const MIN_SEARCH_LENGTH: usize = 2;
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct SearchResult {
+    /// `token` for the ERC-20 tokens, `label` for the labeled addresses.
+    #[diesel(sql_type = Text)]
+    pub kind: String,
+    #[diesel(sql_type = Text)]
+    pub chain: String,
+    #[diesel(sql_type = Text)]
+    pub address: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub name: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub symbol: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub label: Option<String>,
+    /// Trigram similarity to the searched text, from 0 to 1.
+    #[diesel(sql_type = Double)]
+    pub score: f64,
+}
+
+/// Parameters of the search method, e.g. `{ "text": "usdc", "chain": "ethereum", "page_size": 20 }`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchParams {
+    pub text: String,
+    pub chain: Option<String>,
+    #[serde(flatten)]
+    pub page: PageRequest,
+}
+
+/// Tokens by name or symbol and addresses by label, ranked by their trigram similarity to the
+/// text so typos still match. Exact symbols rank first, and spam tokens after the others.
+pub fn search(
+    db: &EVMDatabase,
+    text: &str,
+    chain: Option<&String>,
+    limit: i64,
+) -> Result<Vec<SearchResult>> {
+    let text = text.trim().to_lowercase();
+
+    if text.chars().count() < MIN_SEARCH_LENGTH {
+        bail!(
+            "The search text must have at least {} characters",
+            MIN_SEARCH_LENGTH
+        );
+    }
+
+    let pattern = format!(
+        "%{}%",
+        text.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+
+    let mut connection = db.establish_read_connection();
+
+    let results = sql_query(
+        "SELECT kind, chain, address, name, symbol, label, score FROM ( \
+            SELECT 'token' AS kind, chain, address, name, symbol, NULL::TEXT AS label, \
+            GREATEST(similarity(LOWER(symbol), $1), similarity(LOWER(name), $1))::FLOAT8 AS score, \
+            COALESCE(spam_score, 0) AS spam_score \
+            FROM evm_erc20_tokens \
+            WHERE ($2::TEXT IS NULL OR chain = $2) \
+            AND (LOWER(symbol) % $1 OR LOWER(name) % $1 \
+            OR LOWER(symbol) LIKE $3 OR LOWER(name) LIKE $3) \
+            UNION ALL \
+            SELECT 'label' AS kind, chain, address, NULL::TEXT AS name, NULL::TEXT AS symbol, \
+            label, similarity(LOWER(label), $1)::FLOAT8 AS score, 0 AS spam_score \
+            FROM evm_address_labels \
+            WHERE ($2::TEXT IS NULL OR chain = $2) \
+            AND (LOWER(label) % $1 OR LOWER(label) LIKE $3) \
+        ) results \
+        ORDER BY spam_score > 0, score DESC, chain, address \
+        LIMIT $4",
+    )
+    .bind::<Text, _>(&text)
+    .bind::<Nullable<Text>, _>(chain)
+    .bind::<Text, _>(&pattern)
+    .bind::<BigInt, _>(limit.max(1))
+    .load::<SearchResult>(&mut connection)?;
+
+    Ok(results)
+}
@@ -0,0 +1,130 @@
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use futures::StreamExt;
+use jsonrpsee::{
+    server::{ServerBuilder, ServerHandle},
+    RpcModule,
+};
+use log::*;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tower::ServiceBuilder;
+
+use super::{
+    auth::{ApiKeys, ApiKeysLayer, ApiKeysLogger, MAX_SUBSCRIPTIONS_PER_CONNECTION},
+    events::{EventsFilter, IndexedEvent, EVENTS_CHANNEL},
+    lists::ListsService,
+};
+
+/// Relays the events published by the indexer and parsers into a broadcast channel.
+fn subscribe_indexed_events(redis: redis::Client, sender: broadcast::Sender<IndexedEvent>) {
+    tokio::task::spawn_blocking(move || loop {
+        let mut connection = match redis.get_connection() {
+            Ok(connection) => connection,
+            Err(err) => {
+                warn!("Unable to connect to Redis for events: {}", err);
+                std::thread::sleep(std::time::Duration::from_secs(5));
+                continue;
+            }
+        };
+
+        let mut pubsub = connection.as_pubsub();
+
+        if let Err(err) = pubsub.subscribe(EVENTS_CHANNEL) {
+            warn!("Unable to subscribe to events: {}", err);
+            continue;
+        }
+
+        loop {
+            let message = match pubsub.get_message() {
+                Ok(message) => message,
+                Err(_) => break,
+            };
+
+            let payload: String = match message.get_payload() {
+                Ok(payload) => payload,
+                Err(_) => continue,
+            };
+
+            let events: Vec<IndexedEvent> = match serde_json::from_str(&payload) {
+                Ok(events) => events,
+                Err(_) => continue,
+            };
+
+            for event in events {
+                // Sending only fails when there are no subscribers.
+                let _ = sender.send(event);
+            }
+        }
+    });
+}
+
+/// Serves the events subscription and the paginated list methods, over WebSocket and HTTP.
+/// When API keys are given, every request must include a valid key, and the WebSocket
+/// connections and subscriptions of each key are capped.
+pub async fn start_websocket_server(
+    redis: redis::Client,
+    lists: ListsService,
+    api_keys: Option<ApiKeys>,
+    port: u16,
+) -> Result<ServerHandle> {
+    let (sender, _) = broadcast::channel::<IndexedEvent>(10000);
+
+    subscribe_indexed_events(redis, sender.clone());
+
+    let mut module = RpcModule::new(sender);
+
+    module.register_subscription(
+        "subscribe_events",
+        "event",
+        "unsubscribe_events",
+        |params, mut sink, sender| {
+            let filter: EventsFilter = match params.one() {
+                Ok(filter) => filter,
+                Err(_) => EventsFilter::default(),
+            };
+
+            debug!("New events subscription with filter {:?}", filter);
+
+            // The sink needs an `Unpin` stream and the async filter is not.
+            let stream = Box::pin(BroadcastStream::new(sender.subscribe()).filter_map(
+                move |event| {
+                    let event = match event {
+                        Ok(event) if filter.matches(&event) => Some(event),
+                        _ => None,
+                    };
+
+                    async move { event }
+                },
+            ));
+
+            tokio::spawn(async move {
+                sink.pipe_from_stream(stream).await;
+            });
+
+            Ok(())
+        },
+    )?;
+
+    module.merge(lists.into_rpc()?)?;
+
+    let address: SocketAddr = format!("0.0.0.0:{}", port).parse()?;
+
+    let logger = ApiKeysLogger {
+        api_keys: api_keys.clone(),
+    };
+
+    let middleware = ServiceBuilder::new().layer(ApiKeysLayer { api_keys });
+
+    let server = ServerBuilder::default()
+        .max_subscriptions_per_connection(MAX_SUBSCRIPTIONS_PER_CONNECTION)
+        .set_logger(logger)
+        .set_middleware(middleware)
+        .build(address)
+        .await?;
+
+    info!("Serving WebSocket events and lists on {}.", address);
+
+    Ok(server.start(module)?)
+}
@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use futures::TryStreamExt;
+use log::*;
+use object_store::{
+    aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, local::LocalFileSystem, path::Path,
+    ObjectStore,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Compression level of the archived objects.
+pub const ARCHIVE_COMPRESSION_LEVEL: i32 = 3;
+
+/// Raw JSON-RPC responses of a block, the block with its transactions and the receipts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RawBlock {
+    pub number: i64,
+    pub block: Value,
+    pub receipts: Vec<Value>,
+}
+
+/// Opens the object store of an archive url, `s3://bucket/prefix`, `gs://bucket/prefix` or a
+/// local directory. S3 and GCS credentials are read from the standard environment variables.
+pub fn get_object_store(url: &String) -> Result<(Arc<dyn ObjectStore>, String)> {
+    let (scheme, location) = match url.split_once("://") {
+        Some((scheme, location)) => (scheme, location),
+        None => ("file", url.as_str()),
+    };
+
+    let (bucket, prefix) = match location.split_once('/') {
+        Some((bucket, prefix)) => (bucket, prefix.trim_end_matches('/').to_string()),
+        None => (location, String::new()),
+    };
+
+    let store: Arc<dyn ObjectStore> = match scheme {
+        "s3" => Arc::new(
+            AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()?,
+        ),
+        "gs" => Arc::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_bucket_name(bucket)
+                .build()?,
+        ),
+        "file" => {
+            std::fs::create_dir_all(location)?;
+
+            return Ok((
+                Arc::new(LocalFileSystem::new_with_prefix(location)?),
+                String::new(),
+            ));
+        }
+        scheme => bail!("Unsupported archive scheme {}", scheme),
+    };
+
+    Ok((store, prefix))
+}
+
+/// Archives the raw responses of the indexed blocks, so the database can be rebuilt or decoded
+/// again without fetching the blocks from the RPC. Responses are recorded when fetched and
+/// written once the blocks are stored, as zstd compressed JSON objects of `range_size` blocks.
+/// Blocks that don't fill a range yet are kept in memory and written with `flush` when the
+/// indexer stops.
+#[derive(Debug, Clone)]
+pub struct BlockArchive {
+    pub store: Arc<dyn ObjectStore>,
+    pub prefix: String,
+    pub chain: &'static str,
+    pub range_size: usize,
+    fetched: Arc<Mutex<HashMap<i64, RawBlock>>>,
+    pending: Arc<tokio::sync::Mutex<Vec<RawBlock>>>,
+}
+
+impl BlockArchive {
+    pub fn new(url: &String, chain: &'static str, range_size: usize) -> Result<Self> {
+        let (store, prefix) = get_object_store(url)?;
+
+        info!("Using the raw blocks archive at {}.", url);
+
+        Ok(Self {
+            store,
+            prefix,
+            chain,
+            range_size: range_size.max(1),
+            fetched: Arc::new(Mutex::new(HashMap::new())),
+            pending: Arc::new(tokio::sync::Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Fetching a block again replaces its previous responses.
+    pub fn record_block(&self, number: i64, block: &Value) {
+        self.fetched.lock().unwrap().insert(
+            number,
+            RawBlock {
+                number,
+                block: block.clone(),
+                receipts: Vec::new(),
+            },
+        );
+    }
+
+    /// Records a receipt, or the array of receipts of `eth_getBlockReceipts`.
+    pub fn record_receipts(&self, number: i64, receipts: &Value) {
+        let mut fetched = self.fetched.lock().unwrap();
+
+        let raw_block = fetched.entry(number).or_insert_with(|| RawBlock {
+            number,
+            ..Default::default()
+        });
+
+        match receipts {
+            Value::Array(receipts) => raw_block.receipts.extend(receipts.iter().cloned()),
+            receipt => raw_block.receipts.push(receipt.clone()),
+        }
+    }
+
+    pub async fn archive(&self, blocks: &Vec<i64>) -> Result<()> {
+        let stored: Vec<RawBlock> = {
+            let mut fetched = self.fetched.lock().unwrap();
+
+            blocks
+                .iter()
+                .filter_map(|number| fetched.remove(number))
+                .collect()
+        };
+
+        let mut pending = self.pending.lock().await;
+
+        pending.extend(stored);
+
+        if pending.len() < self.range_size {
+            return Ok(());
+        }
+
+        self.write_pending(&mut pending).await
+    }
+
+    /// Writes the blocks that don't fill a range yet, so they aren't lost when the indexer stops.
+    pub async fn flush(&self) -> Result<()> {
+        let mut pending = self.pending.lock().await;
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        self.write_pending(&mut pending).await
+    }
+
+    /// Responses of blocks fetched again, or fetched and never stored, are dropped once a range
+    /// with their numbers is written.
+    async fn write_pending(&self, pending: &mut Vec<RawBlock>) -> Result<()> {
+        let mut range: Vec<RawBlock> = pending.drain(..).collect();
+
+        range.sort_by_key(|raw_block| raw_block.number);
+
+        let written = self.write(&range).await;
+
+        match written {
+            Ok(_) => {
+                let from_block = range[0].number;
+                let to_block = range[range.len() - 1].number;
+
+                self.fetched
+                    .lock()
+                    .unwrap()
+                    .retain(|number, _| *number < from_block || *number > to_block);
+            }
+            // Keep the blocks to retry with the next range.
+            Err(_) => pending.extend(range),
+        }
+
+        written
+    }
+
+    pub fn get_object_path(&self, from_block: i64, to_block: i64) -> Path {
+        let name = format!(
+            "{}/{:012}-{:012}.json.zst",
+            self.chain, from_block, to_block
+        );
+
+        match self.prefix.is_empty() {
+            true => Path::from(name),
+            false => Path::from(format!("{}/{}", self.prefix, name)),
+        }
+    }
+
+    /// Archived objects with blocks of the range, ordered by their first block.
+    pub async fn get_objects(&self, from_block: i64, to_block: i64) -> Result<Vec<Path>> {
+        let prefix = match self.prefix.is_empty() {
+            true => Path::from(self.chain),
+            false => Path::from(format!("{}/{}", self.prefix, self.chain)),
+        };
+
+        let objects: Vec<_> = self.store.list(Some(&prefix)).await?.try_collect().await?;
+
+        let mut ranges: Vec<(i64, i64, Path)> = Vec::new();
+
+        for object in objects {
+            let name = match object.location.filename() {
+                Some(name) => name.trim_end_matches(".json.zst").to_string(),
+                None => continue,
+            };
+
+            let range = match name.split_once('-') {
+                Some((first, last)) => (first.parse::<i64>(), last.parse::<i64>()),
+                None => continue,
+            };
+
+            match range {
+                (Ok(first), Ok(last)) if first <= to_block && last >= from_block => {
+                    ranges.push((first, last, object.location))
+                }
+                _ => continue,
+            }
+        }
+
+        ranges.sort_by_key(|(first, ..)| *first);
+
+        Ok(ranges.into_iter().map(|(.., path)| path).collect())
+    }
+
+    pub async fn read(&self, path: &Path) -> Result<Vec<RawBlock>> {
+        let compressed = self.store.get(path).await?.bytes().await?;
+
+        let serialized = zstd::decode_all(compressed.as_ref())?;
+
+        Ok(serde_json::from_slice(&serialized)?)
+    }
+
+    async fn write(&self, range: &Vec<RawBlock>) -> Result<()> {
+        let from_block = range[0].number;
+        let to_block = range[range.len() - 1].number;
+
+        let serialized = serde_json::to_vec(range)?;
+
+        let compressed = zstd::encode_all(serialized.as_slice(), ARCHIVE_COMPRESSION_LEVEL)?;
+
+        let path = self.get_object_path(from_block, to_block);
+
+        self.store.put(&path, Bytes::from(compressed)).await?;
+
+        info!(
+            "Archived {} raw blocks from {} to {} to {}.",
+            range.len(),
+            from_block,
+            to_block,
+            path
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use ethers::types::{Block, Transaction, TransactionReceipt};
+use log::*;
+
+use crate::{
+    db::{
+        db::EVMDatabase,
+        models::models::{
+            DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction,
+            DatabaseEVMTransactionLog, DatabaseEVMTransactionReceipt,
+        },
+    },
+    rpc::rpc::{get_block_data, get_receipt_data, set_gas_efficiencies},
+};
+
+use super::archive::{BlockArchive, RawBlock};
+
+#[derive(Debug, Clone, Default)]
+pub struct RedecodeSummary {
+    pub objects: usize,
+    pub blocks: usize,
+    pub invalid_blocks: Vec<i64>,
+}
+
+/// Replays the archived raw responses of a block range through the same decoding as the indexer
+/// and stores the result, so new columns and tables can be backfilled without the RPC.
+#[derive(Debug, Clone)]
+pub struct ArchiveRedecoder {
+    pub archive: BlockArchive,
+    pub db: EVMDatabase,
+}
+
+impl ArchiveRedecoder {
+    pub fn new(archive: BlockArchive, db: EVMDatabase) -> Self {
+        Self { archive, db }
+    }
+
+    /// Stored rows are kept unless `replace` is set, in which case the rows of each decoded
+    /// block are deleted and stored again with the current columns in a single transaction.
+    /// Blocks that are missing from the archive or fail to decode keep their rows. Replaced
+    /// transactions are counted again in the address stats.
+    pub async fn redecode(
+        &self,
+        from_block: i64,
+        to_block: i64,
+        replace: bool,
+    ) -> Result<RedecodeSummary> {
+        let mut summary = RedecodeSummary::default();
+
+        let objects = self.archive.get_objects(from_block, to_block).await?;
+
+        info!(
+            "Found {} archived objects for blocks {} to {}.",
+            objects.len(),
+            from_block,
+            to_block
+        );
+
+        for path in objects {
+            let raw_blocks: Vec<RawBlock> = self
+                .archive
+                .read(&path)
+                .await?
+                .into_iter()
+                .filter(|raw_block| raw_block.number >= from_block && raw_block.number <= to_block)
+                .collect();
+
+            let mut db_blocks: Vec<DatabaseEVMBlock> = Vec::new();
+            let mut db_transactions: Vec<DatabaseEVMTransaction> = Vec::new();
+            let mut db_receipts: Vec<DatabaseEVMTransactionReceipt> = Vec::new();
+            let mut db_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
+            let mut db_contracts: Vec<DatabaseEVMContract> = Vec::new();
+
+            for raw_block in raw_blocks {
+                let block: Block<Transaction> = match serde_json::from_value(raw_block.block) {
+                    Ok(block) => block,
+                    Err(err) => {
+                        warn!(
+                            "Unable to decode archived block {}: {}",
+                            raw_block.number, err
+                        );
+                        summary.invalid_blocks.push(raw_block.number);
+                        continue;
+                    }
+                };
+
+                let receipts: Result<Vec<TransactionReceipt>, serde_json::Error> = raw_block
+                    .receipts
+                    .into_iter()
+                    .map(serde_json::from_value)
+                    .collect();
+
+                let receipts = match receipts {
+                    Ok(receipts) if receipts.len() == block.transactions.len() => receipts,
+                    _ => {
+                        warn!(
+                            "Unable to decode archived receipts of block {}.",
+                            raw_block.number
+                        );
+                        summary.invalid_blocks.push(raw_block.number);
+                        continue;
+                    }
+                };
+
+                let (db_block, mut transactions) = get_block_data(block, self.db.chain.name);
+
+                db_blocks.push(db_block);
+                db_transactions.append(&mut transactions);
+
+                for receipt in receipts.iter() {
+                    let (db_receipt, mut logs, contract) =
+                        get_receipt_data(receipt, self.db.chain.name);
+
+                    db_receipts.push(db_receipt);
+                    db_logs.append(&mut logs);
+
+                    match contract {
+                        Some(contract) => db_contracts.push(contract),
+                        None => (),
+                    }
+                }
+            }
+
+            set_gas_efficiencies(&mut db_transactions, &db_receipts);
+
+            match replace {
+                true => {
+                    self.db
+                        .replace_data(
+                            &db_blocks,
+                            &db_transactions,
+                            &db_receipts,
+                            &db_logs,
+                            &db_contracts,
+                        )
+                        .await
+                }
+                false => {
+                    self.db
+                        .store_data(
+                            &db_blocks,
+                            &db_transactions,
+                            &db_receipts,
+                            &db_logs,
+                            &db_contracts,
+                            &Vec::new(),
+                            &Vec::new(),
+                        )
+                        .await
+                }
+            }
+
+            let mut indexed_blocks: HashSet<i64> = self.db.get_indexed_blocks().await?;
+
+            indexed_blocks.extend(db_blocks.iter().map(|block| block.number));
+
+            self.db.store_indexed_blocks(&indexed_blocks).await?;
+
+            info!("Decoded {} archived blocks from {}.", db_blocks.len(), path);
+
+            summary.objects += 1;
+            summary.blocks += db_blocks.len();
+        }
+
+        Ok(summary)
+    }
+}
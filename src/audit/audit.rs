@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Text},
+};
+use futures::future::join_all;
+use log::*;
+use serde::Serialize;
+
+use crate::{
+    db::{
+        db::EVMDatabase,
+        models::models::{
+            DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction,
+            DatabaseEVMTransactionLog, DatabaseEVMTransactionReceipt,
+        },
+    },
+    rpc::rpc::EVMRpc,
+};
+
+#[derive(QueryableByName, Debug, Clone)]
+struct StoredBlock {
+    #[diesel(sql_type = BigInt)]
+    number: i64,
+    #[diesel(sql_type = Text)]
+    block_hash: String,
+    #[diesel(sql_type = BigInt)]
+    transactions: i64,
+    #[diesel(sql_type = BigInt)]
+    receipts: i64,
+    #[diesel(sql_type = BigInt)]
+    logs: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockDiscrepancy {
+    pub block_number: i64,
+    pub kind: String,
+    pub expected: String,
+    pub stored: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    pub chain: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub blocks_checked: i64,
+    /// Blocks the RPC didn't return, so they couldn't be checked.
+    pub blocks_skipped: Vec<i64>,
+    pub discrepancies: Vec<BlockDiscrepancy>,
+    pub blocks_repaired: Vec<i64>,
+}
+
+type BlockData = (
+    DatabaseEVMBlock,
+    Vec<DatabaseEVMTransaction>,
+    Vec<DatabaseEVMTransactionReceipt>,
+    Vec<DatabaseEVMTransactionLog>,
+    Vec<DatabaseEVMContract>,
+);
+
+/// Re-fetches a range of blocks and compares the stored hashes and amount of transactions,
+/// receipts and logs against the RPC, optionally storing the blocks again when they differ.
+#[derive(Clone)]
+pub struct ChainAuditor {
+    pub rpc: EVMRpc,
+    pub db: EVMDatabase,
+    pub batch_size: usize,
+}
+
+impl ChainAuditor {
+    pub fn new(rpc: EVMRpc, db: EVMDatabase, batch_size: usize) -> Self {
+        Self {
+            rpc,
+            db,
+            batch_size,
+        }
+    }
+
+    pub async fn audit(&self, from_block: i64, to_block: i64, repair: bool) -> Result<AuditReport> {
+        let mut report = AuditReport {
+            chain: self.db.chain.name.to_string(),
+            from_block,
+            to_block,
+            ..Default::default()
+        };
+
+        let blocks: Vec<i64> = (from_block..=to_block).collect();
+
+        for chunk in blocks.chunks(self.batch_size.max(1)) {
+            let start = chunk[0];
+            let end = chunk[chunk.len() - 1];
+
+            let stored = self.get_stored_blocks(start, end)?;
+
+            let results = join_all(chunk.iter().map(|block| self.rpc.fetch_block(block))).await;
+
+            let mut repairs: Vec<BlockData> = Vec::new();
+
+            for (block_number, result) in chunk.iter().zip(results) {
+                let data = match result {
+                    Some(data) => data,
+                    None => {
+                        warn!("Unable to fetch block {} to audit it.", block_number);
+                        report.blocks_skipped.push(*block_number);
+                        continue;
+                    }
+                };
+
+                report.blocks_checked += 1;
+
+                let discrepancies = get_discrepancies(&data, stored.get(block_number));
+
+                if discrepancies.len() > 0 && repair {
+                    repairs.push(data);
+                }
+
+                report.discrepancies.extend(discrepancies);
+            }
+
+            if repairs.len() > 0 {
+                report.blocks_repaired.extend(self.repair(repairs).await?);
+            }
+
+            info!(
+                "Audited blocks {} to {} with {} discrepancies.",
+                start,
+                end,
+                report.discrepancies.len()
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// Stored blocks of the range by number, with the amount of rows stored for each of them.
+    fn get_stored_blocks(&self, start: i64, end: i64) -> Result<HashMap<i64, Vec<StoredBlock>>> {
+        let mut connection = self.db.establish_connection();
+
+        let rows = sql_query(
+            "SELECT b.number, b.block_hash, \
+            (SELECT COUNT(*) FROM evm_transactions t \
+            WHERE t.chain = b.chain AND t.block_number = b.number) AS transactions, \
+            (SELECT COUNT(*) FROM evm_transactions_receipts r JOIN evm_transactions t \
+            ON t.hash = r.hash WHERE t.chain = b.chain AND t.block_number = b.number) AS receipts, \
+            (SELECT COUNT(*) FROM evm_transactions_logs l JOIN evm_transactions t \
+            ON t.hash = l.hash WHERE t.chain = b.chain AND t.block_number = b.number) AS logs \
+            FROM evm_blocks b WHERE b.chain = $1 AND b.number BETWEEN $2 AND $3",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<BigInt, _>(start)
+        .bind::<BigInt, _>(end)
+        .load::<StoredBlock>(&mut connection)?;
+
+        let mut blocks: HashMap<i64, Vec<StoredBlock>> = HashMap::new();
+
+        for row in rows {
+            blocks.entry(row.number).or_default().push(row);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Rows of stale blocks are removed first, then the blocks are stored again. Existing rows
+    /// are kept, so only the missing ones are inserted.
+    async fn repair(&self, repairs: Vec<BlockData>) -> Result<Vec<i64>> {
+        let canonical: Vec<(i64, String)> = repairs
+            .iter()
+            .map(|(block, ..)| (block.number, block.block_hash.clone()))
+            .collect();
+
+        self.db.delete_stale_blocks(&canonical).await?;
+
+        let mut db_blocks: Vec<DatabaseEVMBlock> = Vec::new();
+        let mut db_transactions: Vec<DatabaseEVMTransaction> = Vec::new();
+        let mut db_receipts: Vec<DatabaseEVMTransactionReceipt> = Vec::new();
+        let mut db_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
+        let mut db_contracts: Vec<DatabaseEVMContract> = Vec::new();
+
+        for (block, mut transactions, mut receipts, mut logs, mut contracts) in repairs {
+            db_blocks.push(block);
+            db_transactions.append(&mut transactions);
+            db_receipts.append(&mut receipts);
+            db_logs.append(&mut logs);
+            db_contracts.append(&mut contracts);
+        }
+
+        self.db
+            .store_data(
+                &db_blocks,
+                &db_transactions,
+                &db_receipts,
+                &db_logs,
+                &db_contracts,
+                &Vec::new(),
+                &Vec::new(),
+            )
+            .await;
+
+        let mut indexed_blocks: HashSet<i64> = self.db.get_indexed_blocks().await?;
+
+        let repaired: Vec<i64> = db_blocks.iter().map(|block| block.number).collect();
+
+        indexed_blocks.extend(repaired.iter());
+
+        self.db.store_indexed_blocks(&indexed_blocks).await?;
+
+        info!("Repaired {} blocks.", repaired.len());
+
+        Ok(repaired)
+    }
+}
+
+fn get_discrepancies(data: &BlockData, stored: Option<&Vec<StoredBlock>>) -> Vec<BlockDiscrepancy> {
+    let (block, transactions, receipts, logs, _) = data;
+
+    let discrepancy = |kind: &str, expected: String, stored: String| BlockDiscrepancy {
+        block_number: block.number,
+        kind: kind.to_string(),
+        expected,
+        stored,
+    };
+
+    let stored = match stored {
+        Some(stored) => stored,
+        None => {
+            return vec![discrepancy(
+                "missing_block",
+                block.block_hash.clone(),
+                String::new(),
+            )]
+        }
+    };
+
+    let mut discrepancies = Vec::new();
+
+    for stored_block in stored {
+        if stored_block.block_hash != block.block_hash {
+            discrepancies.push(discrepancy(
+                "block_hash",
+                block.block_hash.clone(),
+                stored_block.block_hash.clone(),
+            ));
+        }
+    }
+
+    // The amounts are counted by block number, so they are the same for every stored hash.
+    let counts = [
+        (
+            "transactions",
+            transactions.len() as i64,
+            stored[0].transactions,
+        ),
+        ("receipts", receipts.len() as i64, stored[0].receipts),
+        ("logs", logs.len() as i64, stored[0].logs),
+    ];
+
+    for (kind, expected, found) in counts {
+        if expected != found {
+            discrepancies.push(discrepancy(kind, expected.to_string(), found.to_string()));
+        }
+    }
+
+    discrepancies
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Nullable, Text},
+};
+use ethers::types::{H160, U256};
+use futures::future::join_all;
+use log::*;
+use serde::Serialize;
+
+use crate::{db::db::EVMDatabase, rpc::rpc::EVMRpc};
+
+#[derive(QueryableByName, Debug, Clone)]
+struct HolderPair {
+    #[diesel(sql_type = Text)]
+    token: String,
+    #[diesel(sql_type = Text)]
+    holder: String,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+struct IndexedBalance {
+    #[diesel(sql_type = Text)]
+    balance: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceDiscrepancy {
+    pub token: String,
+    pub holder: String,
+    pub indexed: String,
+    pub on_chain: String,
+    /// On chain balance minus the indexed one. Positive differences on every holder of a token
+    /// suggest a rebasing token, negative ones a fee on transfer token.
+    pub difference: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BalanceAuditReport {
+    pub chain: String,
+    pub block: i64,
+    pub pairs_checked: i64,
+    /// Pairs checked of each token.
+    pub tokens_checked: BTreeMap<String, i64>,
+    /// Pairs whose `balanceOf` call reverted or didn't return a balance.
+    pub pairs_skipped: Vec<(String, String)>,
+    pub discrepancies: Vec<BalanceDiscrepancy>,
+}
+
+/// Compares the ERC-20 balances rebuilt from the indexed transfers against `balanceOf` at a
+/// block, for a random sample of the holders that received transfers in the blocks before it.
+/// Discrepancies point to missed transfers, parser bugs or tokens whose balances don't follow
+/// their transfers. The balances are only complete when the transfers of the sampled tokens are
+/// indexed from their deployment.
+#[derive(Clone)]
+pub struct BalanceAuditor {
+    pub rpc: EVMRpc,
+    pub db: EVMDatabase,
+}
+
+impl BalanceAuditor {
+    pub fn new(rpc: EVMRpc, db: EVMDatabase) -> Self {
+        Self { rpc, db }
+    }
+
+    pub async fn audit(
+        &self,
+        block: i64,
+        tokens: Option<Vec<String>>,
+        samples: i64,
+        window: i64,
+    ) -> Result<BalanceAuditReport> {
+        let mut report = BalanceAuditReport {
+            chain: self.db.chain.name.to_string(),
+            block,
+            ..Default::default()
+        };
+
+        let tokens = tokens.map(|tokens| {
+            tokens
+                .iter()
+                .map(|token| token.to_lowercase())
+                .collect::<Vec<String>>()
+        });
+
+        let pairs = self.get_sample(block, tokens, samples, window)?;
+
+        info!(
+            "Auditing the balances of {} holders at block {} of chain {}.",
+            pairs.len(),
+            block,
+            self.db.chain.name
+        );
+
+        let results = join_all(
+            pairs
+                .iter()
+                .map(|pair| self.rpc.get_erc20_balance(&pair.token, &pair.holder, block)),
+        )
+        .await;
+
+        for (pair, result) in pairs.into_iter().zip(results) {
+            let on_chain = match result? {
+                Some(on_chain) => on_chain,
+                None => {
+                    warn!(
+                        "Unable to call balanceOf of {} for holder {}.",
+                        pair.token, pair.holder
+                    );
+
+                    report.pairs_skipped.push((pair.token, pair.holder));
+
+                    continue;
+                }
+            };
+
+            let (negative, indexed) = self.get_indexed_balance(&pair.token, &pair.holder, block)?;
+
+            report.pairs_checked += 1;
+
+            *report.tokens_checked.entry(pair.token.clone()).or_default() += 1;
+
+            if !negative && indexed == on_chain {
+                continue;
+            }
+
+            let difference = match (negative, on_chain >= indexed) {
+                (true, _) => on_chain.saturating_add(indexed).to_string(),
+                (false, true) => (on_chain - indexed).to_string(),
+                (false, false) => format!("-{}", indexed - on_chain),
+            };
+
+            report.discrepancies.push(BalanceDiscrepancy {
+                token: pair.token,
+                holder: pair.holder,
+                indexed: match negative {
+                    true => format!("-{}", indexed),
+                    false => indexed.to_string(),
+                },
+                on_chain: on_chain.to_string(),
+                difference,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Random recipients of the transfers of the `window` blocks up to `block`.
+    fn get_sample(
+        &self,
+        block: i64,
+        tokens: Option<Vec<String>>,
+        samples: i64,
+        window: i64,
+    ) -> Result<Vec<HolderPair>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let pairs = sql_query(
+            "SELECT token, holder FROM ( \
+                SELECT DISTINCT tr.token, tr.to_address AS holder \
+                FROM evm_erc20_transfers tr \
+                LEFT JOIN evm_transactions t ON t.hash = tr.hash \
+                LEFT JOIN evm_log_transactions b ON b.hash = tr.hash \
+                WHERE COALESCE(t.chain, b.chain) = $1 \
+                AND COALESCE(t.block_number, b.block_number) BETWEEN $2 - $3 AND $2 \
+                AND tr.to_address <> $4 \
+                AND ($5::TEXT[] IS NULL OR tr.token = ANY($5)) \
+            ) pairs \
+            ORDER BY random() LIMIT $6",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<BigInt, _>(block)
+        .bind::<BigInt, _>(window.max(0))
+        .bind::<Text, _>(format!("{:?}", H160::zero()))
+        .bind::<Nullable<Array<Text>>, _>(tokens)
+        .bind::<BigInt, _>(samples.max(1))
+        .load::<HolderPair>(&mut connection)?;
+
+        Ok(pairs)
+    }
+
+    /// Received minus sent transfers of the holder up to the block, as a sign and an amount
+    /// since it is negative when transfers are missing. Backfilled transfers are located
+    /// through the log transactions.
+    fn get_indexed_balance(
+        &self,
+        token: &String,
+        holder: &String,
+        block: i64,
+    ) -> Result<(bool, U256)> {
+        let mut connection = self.db.establish_read_connection();
+
+        let balance = sql_query(
+            "SELECT COALESCE(SUM(CASE WHEN tr.to_address = $3 THEN tr.value::NUMERIC ELSE 0 END) \
+            - SUM(CASE WHEN tr.from_address = $3 THEN tr.value::NUMERIC ELSE 0 END), 0)::TEXT AS balance \
+            FROM evm_erc20_transfers tr \
+            LEFT JOIN evm_transactions t ON t.hash = tr.hash \
+            LEFT JOIN evm_log_transactions b ON b.hash = tr.hash \
+            WHERE tr.token = $2 AND (tr.from_address = $3 OR tr.to_address = $3) \
+            AND COALESCE(t.chain, b.chain) = $1 \
+            AND COALESCE(t.block_number, b.block_number) <= $4",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(token)
+        .bind::<Text, _>(holder)
+        .bind::<BigInt, _>(block)
+        .get_result::<IndexedBalance>(&mut connection)?;
+
+        let (negative, amount) = match balance.balance.strip_prefix('-') {
+            Some(amount) => (true, amount),
+            None => (false, balance.balance.as_str()),
+        };
+
+        match U256::from_dec_str(amount) {
+            Ok(amount) => Ok((negative, amount)),
+            Err(err) => bail!("Invalid indexed balance {}: {:?}", balance.balance, err),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::{bail, Result};
+use diesel::prelude::*;
+use ethers::{
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Function, HumanReadableParser, Token,
+    },
+    types::Bytes,
+};
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        schema::evm_call_samples,
+    },
+    rpc::rpc::EVMRpc,
+};
+
+/// View call to sample from a contract, with the arguments in their plain text form, e.g.
+/// `{ "name": "weth_usdc_reserves", "contract": "0xb4e1...",
+/// "function": "function getReserves() view returns (uint112 reserve0, uint112 reserve1, uint32 timestamp)",
+/// "args": [], "interval": 100 }`. The call is made every `interval` blocks, or at each block
+/// when unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewCall {
+    pub name: String,
+    pub chain: Option<String>,
+    pub contract: String,
+    pub function: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub interval: Option<i64>,
+}
+
+pub fn load_view_calls(path: &String) -> Vec<ViewCall> {
+    let file = std::fs::read_to_string(path).expect("Unable to read view calls");
+
+    serde_json::from_str(&file).expect("Unable to parse view calls")
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_call_samples)]
+pub struct DatabaseEVMCallSample {
+    pub chain: String,
+    pub name: String,
+    pub block_number: i64,
+    pub contract: String,
+    pub function: String,
+    pub result: String,
+}
+
+/// View call with its function parsed and calldata encoded once.
+#[derive(Debug, Clone)]
+pub struct SampledCall {
+    pub call: ViewCall,
+    pub function: Function,
+    pub data: Bytes,
+}
+
+/// Executes the configured view calls with `eth_call` at new blocks, or over a range of
+/// historical blocks, and stores the decoded outputs as JSON in `evm_call_samples`. Historical
+/// blocks older than the node's state retention need an archive node.
+#[derive(Debug, Clone)]
+pub struct CallSampler {
+    pub calls: Vec<SampledCall>,
+}
+
+impl CallSampler {
+    pub fn new(chain: &str, calls: Vec<ViewCall>) -> Self {
+        let calls: Vec<SampledCall> = calls
+            .into_iter()
+            .filter(|call| match &call.chain {
+                Some(call_chain) => call_chain == chain,
+                None => true,
+            })
+            .map(|call| match get_sampled_call(&call) {
+                Ok(sampled) => sampled,
+                Err(err) => panic!("Unable to parse view call {}: {}", call.name, err),
+            })
+            .collect();
+
+        info!("Sampling {} view calls for chain {}.", calls.len(), chain);
+
+        Self { calls }
+    }
+
+    pub fn get_due_calls(&self, block_number: i64) -> Vec<&SampledCall> {
+        self.calls
+            .iter()
+            .filter(|sampled| match sampled.call.interval {
+                Some(interval) if interval > 1 => block_number % interval == 0,
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Samples the due calls at a block, returns the amount of samples stored.
+    pub async fn sample(&self, rpc: &EVMRpc, db: &EVMDatabase, block_number: i64) -> Result<usize> {
+        let samples = self.get_samples(rpc, db.chain.name, block_number).await?;
+
+        self.store_samples(db, &samples)?;
+
+        Ok(samples.len())
+    }
+
+    /// Samples the due calls of every block of the range, `batch_size` blocks at a time.
+    pub async fn backfill(
+        &self,
+        rpc: &EVMRpc,
+        db: &EVMDatabase,
+        from_block: i64,
+        to_block: i64,
+        batch_size: usize,
+    ) -> Result<usize> {
+        let blocks: Vec<i64> = (from_block..=to_block)
+            .filter(|block_number| self.get_due_calls(*block_number).len() > 0)
+            .collect();
+
+        let mut stored = 0;
+
+        for blocks_chunk in blocks.chunks(batch_size.max(1)) {
+            let mut work = vec![];
+
+            for block_number in blocks_chunk {
+                work.push(self.get_samples(rpc, db.chain.name, *block_number))
+            }
+
+            let mut samples = Vec::new();
+
+            for result in join_all(work).await {
+                samples.append(&mut result?);
+            }
+
+            self.store_samples(db, &samples)?;
+
+            stored += samples.len();
+
+            info!(
+                "Sampled {} view calls up to block {}.",
+                stored,
+                blocks_chunk[blocks_chunk.len() - 1]
+            );
+        }
+
+        Ok(stored)
+    }
+
+    async fn get_samples(
+        &self,
+        rpc: &EVMRpc,
+        chain: &str,
+        block_number: i64,
+    ) -> Result<Vec<DatabaseEVMCallSample>> {
+        let calls = self.get_due_calls(block_number);
+
+        let mut work = vec![];
+
+        for sampled in calls.iter() {
+            work.push(rpc.call(&sampled.call.contract, &sampled.data, block_number))
+        }
+
+        let results = join_all(work).await;
+
+        let mut samples = Vec::new();
+
+        for (sampled, result) in calls.into_iter().zip(results) {
+            let output = match result? {
+                Some(output) => output,
+                None => {
+                    warn!(
+                        "View call {} reverted at block {}.",
+                        sampled.call.name, block_number
+                    );
+                    continue;
+                }
+            };
+
+            let tokens = match sampled.function.decode_output(&output) {
+                Ok(tokens) => tokens,
+                Err(err) => {
+                    warn!(
+                        "Unable to decode view call {} at block {}: {}",
+                        sampled.call.name, block_number, err
+                    );
+                    continue;
+                }
+            };
+
+            samples.push(DatabaseEVMCallSample {
+                chain: chain.to_string(),
+                name: sampled.call.name.clone(),
+                block_number,
+                contract: sampled.call.contract.clone(),
+                function: sampled.function.signature(),
+                result: get_outputs_json(&sampled.function, tokens).to_string(),
+            });
+        }
+
+        Ok(samples)
+    }
+
+    fn store_samples(&self, db: &EVMDatabase, samples: &Vec<DatabaseEVMCallSample>) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(samples.len(), DatabaseEVMCallSample::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_call_samples::dsl::evm_call_samples)
+                .values(&samples[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn get_sampled_call(call: &ViewCall) -> Result<SampledCall> {
+    let function = HumanReadableParser::parse_function(&call.function)?;
+
+    if function.inputs.len() != call.args.len() {
+        bail!(
+            "{} expects {} arguments",
+            function.signature(),
+            function.inputs.len()
+        );
+    }
+
+    let mut args = Vec::new();
+
+    for (param, arg) in function.inputs.iter().zip(call.args.iter()) {
+        args.push(LenientTokenizer::tokenize(&param.kind, arg)?);
+    }
+
+    let data = function.encode_input(&args)?;
+
+    Ok(SampledCall {
+        call: ViewCall {
+            contract: call.contract.to_lowercase(),
+            ..call.clone()
+        },
+        function,
+        data: Bytes::from(data),
+    })
+}
+
+/// Outputs keyed by their name, or by their position when unnamed.
+pub fn get_outputs_json(function: &Function, tokens: Vec<Token>) -> Value {
+    let mut outputs = Map::new();
+
+    for (index, (param, token)) in function.outputs.iter().zip(tokens).enumerate() {
+        let name = match param.name.is_empty() {
+            true => index.to_string(),
+            false => param.name.clone(),
+        };
+
+        outputs.insert(name, get_token_json(token));
+    }
+
+    Value::Object(outputs)
+}
+
+/// Numbers are kept as strings, since most don't fit in a JSON number.
+pub fn get_token_json(token: Token) -> Value {
+    match token {
+        Token::Address(address) => json!(format!("{:?}", address)),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => {
+            json!(format!("0x{}", hex::encode(bytes)))
+        }
+        Token::Int(value) => json!(ethers::types::I256::from_raw(value).to_string()),
+        Token::Uint(value) => json!(value.to_string()),
+        Token::Bool(value) => json!(value),
+        Token::String(value) => json!(value),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.into_iter().map(get_token_json).collect())
+        }
+    }
+}
@@ -9,6 +9,13 @@ pub struct Chain {
     pub abi_source_require_auth: bool,
     pub supports_blocks_receipts: bool,
     pub public_rpc: &'static str,
+    pub wrapped_native_token: &'static str,
+    pub usd_quote_tokens: &'static [&'static str],
+    /// Recommended amount of blocks to fetch at the same time, lower on chains with large blocks.
+    pub batch_size: usize,
+    /// Blocks behind the head after which reorgs are not expected.
+    pub finality_depth: i64,
+    pub multicall3: &'static str,
 }
 
 impl Chain {
@@ -21,10 +28,18 @@ impl Chain {
             abi_source_require_auth: chain.abi_source_require_auth,
             supports_blocks_receipts: chain.supports_blocks_receipts,
             public_rpc: chain.public_rpc,
+            wrapped_native_token: chain.wrapped_native_token,
+            usd_quote_tokens: chain.usd_quote_tokens,
+            batch_size: chain.batch_size,
+            finality_depth: chain.finality_depth,
+            multicall3: chain.multicall3,
         }
     }
 }
 
+/// Multicall3 is deployed at the same address on every supported chain.
+pub const MULTICALL3: &str = "0xca11bde05977b3631167028862be2a173976ca11";
+
 pub const ETHEREUM: Chain = Chain {
     id: 1,
     name: "ethereum",
@@ -33,6 +48,15 @@ pub const ETHEREUM: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: true,
     public_rpc: "https://eth.llamarpc.com",
+    wrapped_native_token: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+    usd_quote_tokens: &[
+        "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+        "0xdac17f958d2ee523a2206206994597c13d831ec7",
+        "0x6b175474e89094c44da98b954eedeac495271d0f",
+    ],
+    batch_size: 100,
+    finality_depth: 64,
+    multicall3: MULTICALL3,
 };
 
 pub const POLYGON: Chain = Chain {
@@ -43,6 +67,15 @@ pub const POLYGON: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: true,
     public_rpc: "https://polygon.llamarpc.com",
+    wrapped_native_token: "0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270",
+    usd_quote_tokens: &[
+        "0x2791bca1f2de4661ed88a30c99a7a9449aa84174",
+        "0xc2132d05d31c914a87c6611c10748aeb04b58e8f",
+        "0x8f3cf7ad23cd3cadbd9735aff958023239c6a063",
+    ],
+    batch_size: 200,
+    finality_depth: 256,
+    multicall3: MULTICALL3,
 };
 
 pub const FANTOM: Chain = Chain {
@@ -53,6 +86,15 @@ pub const FANTOM: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ftm.tools",
+    wrapped_native_token: "0x21be370d5312f44cb42ce377bc9b8a0cef1a4c83",
+    usd_quote_tokens: &[
+        "0x04068da6c83afcfa0e13ba15a6696662335d5b75",
+        "0x049d68029688eabf473097a2fc38ef61633a3c7a",
+        "0x8d11ec38a3eb5e956b052f67da8bdc9bef8abf3e",
+    ],
+    batch_size: 200,
+    finality_depth: 5,
+    multicall3: MULTICALL3,
 };
 
 pub const BSC: Chain = Chain {
@@ -63,6 +105,15 @@ pub const BSC: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: true,
     public_rpc: "https://bscrpc.com",
+    wrapped_native_token: "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c",
+    usd_quote_tokens: &[
+        "0xe9e7cea3dedca5984780bafc599bd69add087d56",
+        "0x55d398326f99059ff775485246999027b3197955",
+        "0x8ac76a51cc950d9822d68b83fe1ad97b32cd580d",
+    ],
+    batch_size: 100,
+    finality_depth: 15,
+    multicall3: MULTICALL3,
 };
 
 pub const GNOSIS: Chain = Chain {
@@ -73,6 +124,14 @@ pub const GNOSIS: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ankr.com/gnosis",
+    wrapped_native_token: "0xe91d153e0b41518a2ce8dd3d7944fa863463a97d",
+    usd_quote_tokens: &[
+        "0xddafbb505ad214d7b80b1f830fccc89b60fb7a83",
+        "0x4ecaba5870353805a9f068101a40e0f32ed605c6",
+    ],
+    batch_size: 500,
+    finality_depth: 20,
+    multicall3: MULTICALL3,
 };
 
 pub const OPTIMISM: Chain = Chain {
@@ -83,6 +142,15 @@ pub const OPTIMISM: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ankr.com/optimism",
+    wrapped_native_token: "0x4200000000000000000000000000000000000006",
+    usd_quote_tokens: &[
+        "0x7f5c764cbc14f9669b88837ca1490cca17c31607",
+        "0x94b008aa00579c1307b0ef2c499ad98a8ce58e58",
+        "0xda10009cbd5d07dd0cecc66161fc93d7c9000da1",
+    ],
+    batch_size: 500,
+    finality_depth: 10,
+    multicall3: MULTICALL3,
 };
 
 pub const ARBITRUM_ONE: Chain = Chain {
@@ -93,6 +161,15 @@ pub const ARBITRUM_ONE: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ankr.com/arbitrum",
+    wrapped_native_token: "0x82af49447d8a07e3bd95bd0d56f35241523fbab1",
+    usd_quote_tokens: &[
+        "0xff970a61a04b1ca14834a43f5de4533ebddb5cc8",
+        "0xfd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9",
+        "0xda10009cbd5d07dd0cecc66161fc93d7c9000da1",
+    ],
+    batch_size: 500,
+    finality_depth: 20,
+    multicall3: MULTICALL3,
 };
 
 pub const ARBITRUM_NOVA: Chain = Chain {
@@ -103,6 +180,11 @@ pub const ARBITRUM_NOVA: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://nova.arbitrum.io/rpc",
+    wrapped_native_token: "0x722e8bdd2ce80a4422e880164f2079488e115365",
+    usd_quote_tokens: &["0x750ba8b76187092b0d1e87e28daaf484d1b5273b"],
+    batch_size: 500,
+    finality_depth: 20,
+    multicall3: MULTICALL3,
 };
 
 pub const MOONBEAM: Chain = Chain {
@@ -113,6 +195,11 @@ pub const MOONBEAM: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ankr.com/moonbeam",
+    wrapped_native_token: "0xacc15dc74880c9944775448304b263d191c6077f",
+    usd_quote_tokens: &["0x818ec0a7fe18ff94269904fced6ae3dae6d6dc0b"],
+    batch_size: 500,
+    finality_depth: 5,
+    multicall3: MULTICALL3,
 };
 
 pub const AVALANCHE: Chain = Chain {
@@ -123,6 +210,14 @@ pub const AVALANCHE: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ankr.com/avalanche",
+    wrapped_native_token: "0xb31f66aa3c1e785363f0875a1b74e27b85fd66c7",
+    usd_quote_tokens: &[
+        "0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e",
+        "0x9702230a8ea53601f5cd2dc00fdbc13d4df4a8c7",
+    ],
+    batch_size: 200,
+    finality_depth: 5,
+    multicall3: MULTICALL3,
 };
 
 pub const BITTORRENT: Chain = Chain {
@@ -133,6 +228,11 @@ pub const BITTORRENT: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.bittorrentchain.io",
+    wrapped_native_token: "0x23181f21dea5936e24163ffaba2fa4e0bc5b2b5c",
+    usd_quote_tokens: &[],
+    batch_size: 500,
+    finality_depth: 128,
+    multicall3: MULTICALL3,
 };
 
 pub const CELO: Chain = Chain {
@@ -143,6 +243,11 @@ pub const CELO: Chain = Chain {
     abi_source_require_auth: true,
     supports_blocks_receipts: false,
     public_rpc: "https://rpc.ankr.com/celo",
+    wrapped_native_token: "0x471ece3750da237f93b8e339c536989b8978a438",
+    usd_quote_tokens: &["0x765de816845861e75a25fca122bb6898b8b1282a"],
+    batch_size: 500,
+    finality_depth: 5,
+    multicall3: MULTICALL3,
 };
 
 pub static CHAINS: [Chain; 12] = [
@@ -177,3 +282,7 @@ pub fn get_chain(chain: String) -> Chain {
 
     return Chain::new_from_borrowed(selected_chain);
 }
+
+pub fn get_chain_by_id(id: i64) -> Option<Chain> {
+    CHAINS.into_iter().find(|chain| chain.id == id)
+}
@@ -0,0 +1,410 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::{prelude::*, upsert::excluded};
+use ethers::types::H160;
+use field_count::FieldCount;
+use log::*;
+use serde::Serialize;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    schema::{evm_address_clusters, evm_contracts, evm_transactions},
+};
+
+/// Funders of more addresses than this are services, like exchanges or faucets, not owners.
+const MAX_FUNDED_ADDRESSES: usize = 20;
+
+/// Blocks within which funds sent back to their sender count as churn.
+const CHURN_WINDOW: i64 = 100;
+
+/// Addresses looked up at once in the contracts and clusters tables.
+const LOOKUP_SIZE: usize = 10000;
+
+pub const COMMON_FUNDING: &str = "common_funding";
+pub const SELF_CHURN: &str = "self_churn";
+pub const DEPLOYER: &str = "deployer";
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_address_clusters)]
+pub struct DatabaseEVMAddressCluster {
+    pub address: String,
+    pub chain: String,
+    /// Smallest address of the cluster.
+    pub cluster: String,
+    /// Heuristics that linked the address to the cluster.
+    pub heuristics: Vec<Option<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClusteringReport {
+    pub chain: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub transactions: i64,
+    /// Links found by each heuristic.
+    pub links: HashMap<String, i64>,
+    pub clusters: i64,
+    pub addresses: i64,
+}
+
+#[derive(Debug, Clone)]
+struct Link {
+    from: String,
+    to: String,
+    heuristic: &'static str,
+}
+
+/// Disjoint sets of addresses with the heuristics that linked each one.
+#[derive(Default)]
+struct Clusters {
+    parents: HashMap<String, String>,
+    heuristics: HashMap<String, BTreeSet<String>>,
+}
+
+impl Clusters {
+    fn find(&mut self, address: &String) -> String {
+        let parent = match self.parents.get(address) {
+            Some(parent) => parent.clone(),
+            None => {
+                self.parents.insert(address.clone(), address.clone());
+
+                return address.clone();
+            }
+        };
+
+        if &parent == address {
+            return parent;
+        }
+
+        let root = self.find(&parent);
+
+        self.parents.insert(address.clone(), root.clone());
+
+        root
+    }
+
+    fn add_heuristics(&mut self, address: &String, heuristics: Vec<String>) {
+        self.heuristics
+            .entry(address.clone())
+            .or_default()
+            .extend(heuristics);
+    }
+
+    fn union(&mut self, from: &String, to: &String) {
+        let from_root = self.find(from);
+        let to_root = self.find(to);
+
+        if from_root != to_root {
+            self.parents.insert(from_root, to_root);
+        }
+    }
+
+    /// Members of each cluster with at least two addresses.
+    fn get_groups(&mut self) -> Vec<Vec<String>> {
+        let addresses: Vec<String> = self.parents.keys().cloned().collect();
+
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+
+        for address in addresses {
+            let root = self.find(&address);
+
+            groups.entry(root).or_default().push(address);
+        }
+
+        groups
+            .into_values()
+            .filter(|members| members.len() > 1)
+            .collect()
+    }
+}
+
+/// Groups the addresses of a range that are likely controlled by the same owner, for
+/// investigations. Addresses are linked by three heuristics:
+///
+/// - Common funding: an address first funded with native currency by another one, unless the
+///   funder funded many addresses, like an exchange hot wallet.
+/// - Self churn: two addresses sending native currency back and forth within a few blocks.
+/// - Deployer: a contract and the address that deployed it.
+///
+/// Contracts are only linked to their deployer, so routers and pools don't join their users.
+/// Clusters stored by previous runs are merged with the new links, and each cluster is named
+/// after its smallest address.
+pub struct AddressClusterer {
+    pub db: EVMDatabase,
+    pub batch_size: i64,
+}
+
+impl AddressClusterer {
+    pub fn new(db: EVMDatabase, batch_size: usize) -> Self {
+        Self {
+            db,
+            batch_size: batch_size.max(1) as i64,
+        }
+    }
+
+    pub fn cluster(&self, from_block: i64, to_block: i64) -> Result<ClusteringReport> {
+        let mut report = ClusteringReport {
+            chain: self.db.chain.name.to_string(),
+            from_block,
+            to_block,
+            ..Default::default()
+        };
+
+        let zero_address = format!("{:?}", H160::zero());
+
+        let mut links: Vec<Link> = Vec::new();
+
+        let mut senders: HashSet<String> = HashSet::new();
+        let mut funders: HashMap<String, String> = HashMap::new();
+        let mut transfers: HashMap<(String, String), i64> = HashMap::new();
+
+        let mut connection = self.db.establish_read_connection();
+
+        let mut start = from_block;
+
+        while start <= to_block {
+            let end = (start + self.batch_size - 1).min(to_block);
+
+            let transactions = evm_transactions::table
+                .select((
+                    evm_transactions::block_number,
+                    evm_transactions::from_address,
+                    evm_transactions::to_address,
+                    evm_transactions::value,
+                ))
+                .filter(evm_transactions::chain.eq(self.db.chain.name))
+                .filter(evm_transactions::block_number.ge(start))
+                .filter(evm_transactions::block_number.le(end))
+                .order((
+                    evm_transactions::block_number.asc(),
+                    evm_transactions::transaction_index.asc(),
+                ))
+                .load::<(i64, String, String, String)>(&mut connection)?;
+
+            report.transactions += transactions.len() as i64;
+
+            for (block_number, from_address, to_address, value) in transactions {
+                // Funds received before sending anything are the first funding of an address.
+                if value != "0"
+                    && to_address != zero_address
+                    && to_address != from_address
+                    && !senders.contains(&to_address)
+                {
+                    funders
+                        .entry(to_address.clone())
+                        .or_insert(from_address.clone());
+                }
+
+                senders.insert(from_address.clone());
+
+                if value == "0" || to_address == zero_address || from_address == to_address {
+                    continue;
+                }
+
+                match transfers.get(&(to_address.clone(), from_address.clone())) {
+                    Some(sent_block) if block_number - sent_block <= CHURN_WINDOW => {
+                        links.push(Link {
+                            from: from_address.clone(),
+                            to: to_address.clone(),
+                            heuristic: SELF_CHURN,
+                        })
+                    }
+                    _ => (),
+                }
+
+                transfers.insert((from_address, to_address), block_number);
+            }
+
+            let contracts = evm_contracts::table
+                .select((evm_contracts::creator, evm_contracts::contract))
+                .filter(evm_contracts::chain.eq(self.db.chain.name))
+                .filter(evm_contracts::block.ge(start))
+                .filter(evm_contracts::block.le(end))
+                .load::<(String, String)>(&mut connection)?;
+
+            for (creator, contract) in contracts {
+                links.push(Link {
+                    from: creator,
+                    to: contract,
+                    heuristic: DEPLOYER,
+                });
+            }
+
+            info!(
+                "Read the transactions of blocks {} to {} of chain {} to cluster.",
+                start, end, self.db.chain.name
+            );
+
+            start = end + 1;
+        }
+
+        let mut funded: HashMap<String, usize> = HashMap::new();
+
+        for funder in funders.values() {
+            *funded.entry(funder.clone()).or_default() += 1;
+        }
+
+        for (address, funder) in funders {
+            if funded[&funder] <= MAX_FUNDED_ADDRESSES {
+                links.push(Link {
+                    from: address,
+                    to: funder,
+                    heuristic: COMMON_FUNDING,
+                });
+            }
+        }
+
+        let contracts = self.get_contracts(&links)?;
+
+        let links: Vec<Link> = links
+            .into_iter()
+            .filter(|link| {
+                link.heuristic == DEPLOYER
+                    || !(contracts.contains(&link.from) || contracts.contains(&link.to))
+            })
+            .collect();
+
+        let mut clusters = Clusters::default();
+
+        for link in links.iter() {
+            *report.links.entry(link.heuristic.to_string()).or_default() += 1;
+
+            clusters.union(&link.from, &link.to);
+
+            clusters.add_heuristics(&link.from, vec![link.heuristic.to_string()]);
+            clusters.add_heuristics(&link.to, vec![link.heuristic.to_string()]);
+        }
+
+        self.merge_stored_clusters(&mut clusters)?;
+
+        let db_clusters = self.get_db_clusters(&mut clusters, &mut report);
+
+        self.store(&db_clusters)?;
+
+        Ok(report)
+    }
+
+    /// Addresses of the links that are contracts.
+    fn get_contracts(&self, links: &Vec<Link>) -> Result<HashSet<String>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let addresses: Vec<String> = links
+            .iter()
+            .flat_map(|link| [link.from.clone(), link.to.clone()])
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        let mut contracts = HashSet::new();
+
+        for chunk in addresses.chunks(LOOKUP_SIZE) {
+            let found = evm_contracts::table
+                .select(evm_contracts::contract)
+                .filter(evm_contracts::chain.eq(self.db.chain.name))
+                .filter(evm_contracts::contract.eq_any(chunk))
+                .load::<String>(&mut connection)?;
+
+            contracts.extend(found);
+        }
+
+        Ok(contracts)
+    }
+
+    /// Links the linked addresses to the other members of their stored clusters.
+    fn merge_stored_clusters(&self, clusters: &mut Clusters) -> Result<()> {
+        let mut connection = self.db.establish_read_connection();
+
+        let addresses: Vec<String> = clusters.parents.keys().cloned().collect();
+
+        let mut cluster_ids: HashSet<String> = HashSet::new();
+
+        for chunk in addresses.chunks(LOOKUP_SIZE) {
+            let stored = evm_address_clusters::table
+                .select(evm_address_clusters::cluster)
+                .filter(evm_address_clusters::chain.eq(self.db.chain.name))
+                .filter(evm_address_clusters::address.eq_any(chunk))
+                .load::<String>(&mut connection)?;
+
+            cluster_ids.extend(stored);
+        }
+
+        let cluster_ids: Vec<String> = cluster_ids.into_iter().collect();
+
+        for chunk in cluster_ids.chunks(LOOKUP_SIZE) {
+            let members = evm_address_clusters::table
+                .select(DatabaseEVMAddressCluster::as_select())
+                .filter(evm_address_clusters::chain.eq(self.db.chain.name))
+                .filter(evm_address_clusters::cluster.eq_any(chunk))
+                .load::<DatabaseEVMAddressCluster>(&mut connection)?;
+
+            for member in members {
+                clusters.union(&member.address, &member.cluster);
+
+                clusters.add_heuristics(
+                    &member.address,
+                    member.heuristics.into_iter().flatten().collect(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_db_clusters(
+        &self,
+        clusters: &mut Clusters,
+        report: &mut ClusteringReport,
+    ) -> Vec<DatabaseEVMAddressCluster> {
+        let mut db_clusters = Vec::new();
+
+        for members in clusters.get_groups() {
+            let cluster = members.iter().min().unwrap().clone();
+
+            report.clusters += 1;
+            report.addresses += members.len() as i64;
+
+            for address in members {
+                let heuristics = match clusters.heuristics.get(&address) {
+                    Some(heuristics) => heuristics.iter().cloned().map(Some).collect(),
+                    None => Vec::new(),
+                };
+
+                db_clusters.push(DatabaseEVMAddressCluster {
+                    address,
+                    chain: self.db.chain.name.to_string(),
+                    cluster: cluster.clone(),
+                    heuristics,
+                });
+            }
+        }
+
+        db_clusters
+    }
+
+    fn store(&self, db_clusters: &Vec<DatabaseEVMAddressCluster>) -> Result<()> {
+        let mut connection = self.db.establish_connection();
+
+        let chunks = get_chunks(db_clusters.len(), DatabaseEVMAddressCluster::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_address_clusters::dsl::evm_address_clusters)
+                .values(&db_clusters[start..end])
+                .on_conflict((evm_address_clusters::address, evm_address_clusters::chain))
+                .do_update()
+                .set((
+                    evm_address_clusters::cluster.eq(excluded(evm_address_clusters::cluster)),
+                    evm_address_clusters::heuristics.eq(excluded(evm_address_clusters::heuristics)),
+                ))
+                .execute(&mut connection)?;
+        }
+
+        info!(
+            "Inserted {} clustered addresses of chain {}.",
+            db_clusters.len(),
+            self.db.chain.name
+        );
+
+        Ok(())
+    }
+}
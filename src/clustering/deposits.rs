@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Text},
+    upsert::excluded,
+};
+use field_count::FieldCount;
+use log::*;
+use serde::Serialize;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    schema::evm_exchange_deposits,
+};
+
+use super::labels::EXCHANGE;
+
+/// Share of the outgoing transfers of an address that must go to one exchange to flag it.
+const MIN_FORWARD_SHARE: f64 = 0.8;
+
+/// Distinct senders after which an address is fully trusted to collect user funds.
+const FULL_SENDERS: usize = 5;
+
+/// Forwards after which an address is fully trusted to sweep into the exchange.
+const FULL_FORWARDS: i64 = 3;
+
+#[derive(Selectable, Queryable, Insertable, Serialize, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_exchange_deposits)]
+pub struct DatabaseEVMExchangeDeposit {
+    pub address: String,
+    pub chain: String,
+    pub exchange: String,
+    /// Hot wallet the address forwarded to, the smallest address when there are several.
+    pub hot_wallet: String,
+    pub forwards: i64,
+    pub transfers_out: i64,
+    pub senders: i64,
+    /// From 0 to 1, how likely the address is a deposit address of the exchange.
+    pub confidence: f64,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+struct DepositCandidate {
+    #[diesel(sql_type = Text)]
+    address: String,
+    #[diesel(sql_type = Text)]
+    exchange: String,
+    #[diesel(sql_type = Text)]
+    hot_wallet: String,
+    #[diesel(sql_type = BigInt)]
+    forwards: i64,
+    #[diesel(sql_type = BigInt)]
+    transfers_out: i64,
+    #[diesel(sql_type = Array<Text>)]
+    senders: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DepositStats {
+    hot_wallets: HashMap<String, String>,
+    forwards: HashMap<String, i64>,
+    transfers_out: i64,
+    senders: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DepositsReport {
+    pub chain: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub candidates: i64,
+    pub deposits: i64,
+}
+
+/// Flags the probable exchange deposit addresses of a range: addresses not labeled themselves
+/// that forward almost all their native and ERC-20 transfers to the hot wallets of a single
+/// exchange in `evm_address_labels`. The confidence grows with the share of transfers forwarded,
+/// the amount of forwards and the distinct senders the address collected funds from.
+///
+/// Each run replaces the stored stats of the flagged addresses with the ones of its range.
+pub struct ExchangeDepositsDetector {
+    pub db: EVMDatabase,
+    pub batch_size: i64,
+}
+
+impl ExchangeDepositsDetector {
+    pub fn new(db: EVMDatabase, batch_size: usize) -> Self {
+        Self {
+            db,
+            batch_size: batch_size.max(1) as i64,
+        }
+    }
+
+    pub fn detect(&self, from_block: i64, to_block: i64) -> Result<DepositsReport> {
+        let mut report = DepositsReport {
+            chain: self.db.chain.name.to_string(),
+            from_block,
+            to_block,
+            ..Default::default()
+        };
+
+        let mut stats: HashMap<String, DepositStats> = HashMap::new();
+
+        let mut start = from_block;
+
+        while start <= to_block {
+            let end = (start + self.batch_size - 1).min(to_block);
+
+            let candidates = self.get_candidates(start, end)?;
+
+            // The outgoing transfers of a batch are repeated on each exchange row of the address.
+            let mut counted: HashSet<String> = HashSet::new();
+
+            for candidate in candidates {
+                let address_stats = stats.entry(candidate.address.clone()).or_default();
+
+                if counted.insert(candidate.address.clone()) {
+                    address_stats.transfers_out += candidate.transfers_out;
+                }
+
+                address_stats
+                    .hot_wallets
+                    .entry(candidate.exchange.clone())
+                    .or_insert(candidate.hot_wallet);
+
+                *address_stats
+                    .forwards
+                    .entry(candidate.exchange)
+                    .or_default() += candidate.forwards;
+
+                address_stats.senders.extend(candidate.senders);
+            }
+
+            info!(
+                "Read the transfers of blocks {} to {} of chain {} to detect deposits.",
+                start, end, self.db.chain.name
+            );
+
+            start = end + 1;
+        }
+
+        report.candidates = stats.len() as i64;
+
+        let deposits: Vec<DatabaseEVMExchangeDeposit> = stats
+            .into_iter()
+            .filter_map(|(address, stats)| self.get_deposit(address, stats))
+            .collect();
+
+        report.deposits = deposits.len() as i64;
+
+        self.store(&deposits)?;
+
+        Ok(report)
+    }
+
+    /// Unlabeled addresses that sent transfers to exchange hot wallets in the range, with their
+    /// forwards to each exchange, all their outgoing transfers and their senders.
+    fn get_candidates(&self, from_block: i64, to_block: i64) -> Result<Vec<DepositCandidate>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let candidates = sql_query(
+            "WITH edges AS ( \
+                SELECT t.from_address, t.to_address FROM evm_transactions t \
+                WHERE t.chain = $1 AND t.block_number BETWEEN $2 AND $3 AND t.value <> '0' \
+                UNION ALL \
+                SELECT tr.from_address, tr.to_address FROM evm_erc20_transfers tr \
+                JOIN evm_transactions t ON t.hash = tr.hash \
+                WHERE t.chain = $1 AND t.block_number BETWEEN $2 AND $3 \
+            ), forwards AS ( \
+                SELECT e.from_address AS address, l.label AS exchange, \
+                MIN(e.to_address) AS hot_wallet, COUNT(*) AS forwards \
+                FROM edges e JOIN evm_address_labels l \
+                ON l.chain = $1 AND l.address = e.to_address AND l.kind = $4 \
+                WHERE NOT EXISTS (SELECT 1 FROM evm_address_labels o \
+                WHERE o.chain = $1 AND o.address = e.from_address) \
+                GROUP BY e.from_address, l.label \
+            ) \
+            SELECT f.address, f.exchange, f.hot_wallet, f.forwards, \
+            (SELECT COUNT(*) FROM edges o WHERE o.from_address = f.address) AS transfers_out, \
+            ARRAY(SELECT DISTINCT i.from_address FROM edges i WHERE i.to_address = f.address) AS senders \
+            FROM forwards f",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<BigInt, _>(from_block)
+        .bind::<BigInt, _>(to_block)
+        .bind::<Text, _>(EXCHANGE)
+        .load::<DepositCandidate>(&mut connection)?;
+
+        Ok(candidates)
+    }
+
+    /// Deposit of the exchange the address forwarded the most to, when it's single-purpose.
+    fn get_deposit(
+        &self,
+        address: String,
+        stats: DepositStats,
+    ) -> Option<DatabaseEVMExchangeDeposit> {
+        let (exchange, forwards) = stats
+            .forwards
+            .iter()
+            .max_by_key(|(_, forwards)| **forwards)?;
+
+        let share = *forwards as f64 / stats.transfers_out.max(*forwards) as f64;
+
+        if share < MIN_FORWARD_SHARE {
+            return None;
+        }
+
+        let senders = stats.senders.len();
+
+        let senders_factor = senders.min(FULL_SENDERS) as f64 / FULL_SENDERS as f64;
+
+        let forwards_factor = (*forwards).min(FULL_FORWARDS) as f64 / FULL_FORWARDS as f64;
+
+        let confidence = share * (0.5 + 0.25 * senders_factor + 0.25 * forwards_factor);
+
+        Some(DatabaseEVMExchangeDeposit {
+            address,
+            chain: self.db.chain.name.to_string(),
+            exchange: exchange.clone(),
+            hot_wallet: stats.hot_wallets[exchange].clone(),
+            forwards: *forwards,
+            transfers_out: stats.transfers_out,
+            senders: senders as i64,
+            confidence,
+        })
+    }
+
+    fn store(&self, deposits: &Vec<DatabaseEVMExchangeDeposit>) -> Result<()> {
+        let mut connection = self.db.establish_connection();
+
+        let chunks = get_chunks(deposits.len(), DatabaseEVMExchangeDeposit::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_exchange_deposits::dsl::evm_exchange_deposits)
+                .values(&deposits[start..end])
+                .on_conflict((evm_exchange_deposits::address, evm_exchange_deposits::chain))
+                .do_update()
+                .set((
+                    evm_exchange_deposits::exchange.eq(excluded(evm_exchange_deposits::exchange)),
+                    evm_exchange_deposits::hot_wallet
+                        .eq(excluded(evm_exchange_deposits::hot_wallet)),
+                    evm_exchange_deposits::forwards.eq(excluded(evm_exchange_deposits::forwards)),
+                    evm_exchange_deposits::transfers_out
+                        .eq(excluded(evm_exchange_deposits::transfers_out)),
+                    evm_exchange_deposits::senders.eq(excluded(evm_exchange_deposits::senders)),
+                    evm_exchange_deposits::confidence
+                        .eq(excluded(evm_exchange_deposits::confidence)),
+                ))
+                .execute(&mut connection)?;
+        }
+
+        info!(
+            "Inserted {} exchange deposit addresses of chain {}.",
+            deposits.len(),
+            self.db.chain.name
+        );
+
+        Ok(())
+    }
+}
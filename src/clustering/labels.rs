@@ -0,0 +1,64 @@
+use anyhow::{bail, Result};
+use diesel::{prelude::*, upsert::excluded};
+use field_count::FieldCount;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    schema::evm_address_labels,
+};
+
+/// Kind of the labels of the exchange hot wallets.
+pub const EXCHANGE: &str = "exchange";
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount, Serialize, Deserialize)]
+#[diesel(table_name = evm_address_labels)]
+pub struct DatabaseEVMAddressLabel {
+    pub address: String,
+    pub chain: String,
+    /// Owner of the address, e.g. `binance`.
+    pub label: String,
+    /// Kind of owner, e.g. `exchange`.
+    pub kind: String,
+}
+
+/// Loads a JSON array of labels, e.g. `[{"address": "0x28c6...", "chain": "ethereum",
+/// "label": "binance", "kind": "exchange"}]`, into `evm_address_labels`. Labels already stored
+/// for an address are replaced.
+pub fn import_address_labels(db: &EVMDatabase, path: &String) -> Result<usize> {
+    let file = std::fs::read_to_string(path)?;
+
+    let mut labels: Vec<DatabaseEVMAddressLabel> = match serde_json::from_str(&file) {
+        Ok(labels) => labels,
+        Err(err) => bail!("Unable to parse the address labels: {}", err),
+    };
+
+    for label in labels.iter_mut() {
+        label.address = label.address.to_lowercase();
+    }
+
+    // An address can only be upserted once per statement, the first label of the file wins.
+    labels.sort_by(|a, b| (&a.chain, &a.address).cmp(&(&b.chain, &b.address)));
+    labels.dedup_by(|a, b| a.chain == b.chain && a.address == b.address);
+
+    let mut connection = db.establish_connection();
+
+    let chunks = get_chunks(labels.len(), DatabaseEVMAddressLabel::field_count());
+
+    for (start, end) in chunks {
+        diesel::insert_into(evm_address_labels::dsl::evm_address_labels)
+            .values(&labels[start..end])
+            .on_conflict((evm_address_labels::address, evm_address_labels::chain))
+            .do_update()
+            .set((
+                evm_address_labels::label.eq(excluded(evm_address_labels::label)),
+                evm_address_labels::kind.eq(excluded(evm_address_labels::kind)),
+            ))
+            .execute(&mut connection)?;
+    }
+
+    info!("Imported {} address labels.", labels.len());
+
+    Ok(labels.len())
+}
@@ -0,0 +1,3 @@
+pub mod clustering;
+pub mod deposits;
+pub mod labels;
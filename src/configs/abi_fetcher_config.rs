@@ -4,6 +4,8 @@ use clap::Parser;
 
 use crate::chains::chains::get_chains;
 
+use super::secrets::get_secret;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "EVM ABI Fetcher",
@@ -17,6 +19,7 @@ pub struct EVMAbiFetcherArgs {
 #[derive(Debug, Clone)]
 pub struct EVMAbiFetcherConfig {
     pub db_url: String,
+    pub db_schema: Option<String>,
     pub redis_url: String,
     pub debug: bool,
     pub api_source_tokens: HashMap<String, String>,
@@ -38,8 +41,9 @@ impl EVMAbiFetcherConfig {
         }
 
         Self {
-            db_url: std::env::var("DATABASE_URL").expect("DATABASE_URL must be set."),
-            redis_url: std::env::var("REDIS_URL").expect("REDIS_URL must be set."),
+            db_url: get_secret("DATABASE_URL").expect("DATABASE_URL must be set."),
+            db_schema: get_secret("DATABASE_SCHEMA"),
+            redis_url: get_secret("REDIS_URL").expect("REDIS_URL must be set."),
             debug: args.debug,
             api_source_tokens,
         }
@@ -48,99 +52,51 @@ impl EVMAbiFetcherConfig {
 
 pub fn get_abi_token_for_chain(chain: String) -> Option<String> {
     if chain == "ethereum" {
-        let token = std::env::var("ETHERSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("ETHERSCAN_TOKEN");
     }
 
     if chain == "polygon" {
-        let token = std::env::var("POLYGONSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("POLYGONSCAN_TOKEN");
     }
 
     if chain == "bsc" {
-        let token = std::env::var("BSCSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("BSCSCAN_TOKEN");
     }
 
     if chain == "fantom" {
-        let token = std::env::var("FTMSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("FTMSCAN_TOKEN");
     }
 
     if chain == "gnosis" {
-        let token = std::env::var("GNOSISSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("GNOSISSCAN_TOKEN");
     }
 
     if chain == "optimism" {
-        let token = std::env::var("OPTIMISMSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("OPTIMISMSCAN_TOKEN");
     }
 
     if chain == "arbitrum" {
-        let token = std::env::var("ARBISCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("ARBISCAN_TOKEN");
     }
 
     if chain == "arbitrum-nova" {
-        let token = std::env::var("ARBISCAN_NOVA_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("ARBISCAN_NOVA_TOKEN");
     }
 
     if chain == "moonbeam" {
-        let token = std::env::var("MOONSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("MOONSCAN_TOKEN");
     }
 
     if chain == "avalanche" {
-        let token = std::env::var("SNOWTRACE_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("SNOWTRACE_TOKEN");
     }
 
     if chain == "bittorrent" {
-        let token = std::env::var("BITTORRENTSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("BITTORRENTSCAN_TOKEN");
     }
 
     if chain == "celo" {
-        let token = std::env::var("CELOSCAN_TOKEN");
-        match token {
-            Ok(token) => return Some(token),
-            Err(_) => return None,
-        }
+        return get_secret("CELOSCAN_TOKEN");
     }
 
     return None;
@@ -0,0 +1,117 @@
+use clap::Parser;
+
+use super::secrets::{get_replica_urls, get_secret};
+
+#[derive(Parser, Debug)]
+#[command(name = "EVM API", about = "Streaming API for the EVM indexed data.")]
+pub struct EVMApiArgs {
+    #[arg(short, long, help = "Start log with debug", default_value_t = false)]
+    pub debug: bool,
+
+    #[arg(long, help = "Port to serve the gRPC API", default_value_t = 50051)]
+    pub grpc_port: u16,
+
+    #[arg(
+        long,
+        help = "Port to serve the WebSocket events",
+        default_value_t = 8080
+    )]
+    pub ws_port: u16,
+
+    #[arg(
+        long,
+        help = "Amount of entities to load from the database at the same time",
+        default_value_t = 500
+    )]
+    pub batch_size: i64,
+
+    #[arg(
+        long,
+        help = "Seconds to wait for new data once a stream reaches the head",
+        default_value_t = 2
+    )]
+    pub poll_interval: u64,
+
+    #[arg(
+        long,
+        help = "Include the ENS names of the addresses in the streamed messages",
+        default_value_t = false
+    )]
+    pub ens_names: bool,
+
+    #[arg(
+        long,
+        help = "Amount of items returned by the list methods when no page size is requested",
+        default_value_t = 100
+    )]
+    pub page_size: i64,
+
+    #[arg(
+        long,
+        help = "Maximum amount of items returned by the list methods in a single page",
+        default_value_t = 1000
+    )]
+    pub max_page_size: i64,
+
+    #[arg(
+        long,
+        help = "Require an API key on every request to the servers",
+        default_value_t = false
+    )]
+    pub auth: bool,
+
+    #[arg(
+        long,
+        help = "Path to a JSON file with API keys to add, each with a key, name and rate limit"
+    )]
+    pub api_keys: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EVMApiConfig {
+    pub db_url: String,
+    pub db_replica_urls: Vec<String>,
+    pub db_schema: Option<String>,
+    pub redis_url: String,
+    pub debug: bool,
+    pub grpc_port: u16,
+    pub ws_port: u16,
+    pub batch_size: i64,
+    pub poll_interval: u64,
+    pub ens_names: bool,
+    pub page_size: i64,
+    pub max_page_size: i64,
+    pub auth: bool,
+    pub api_keys: Option<String>,
+}
+
+impl EVMApiConfig {
+    pub fn new() -> Self {
+        let args = EVMApiArgs::parse();
+
+        if args.batch_size < 1 {
+            panic!("--batch-size must be at least 1.");
+        }
+
+        if args.max_page_size < 1 {
+            panic!("--max-page-size must be at least 1.");
+        }
+
+        Self {
+            db_url: get_secret("DATABASE_URL").expect("DATABASE_URL must be set."),
+            db_replica_urls: get_replica_urls(),
+            db_schema: get_secret("DATABASE_SCHEMA"),
+            redis_url: get_secret("REDIS_URL").expect("REDIS_URL must be set."),
+            debug: args.debug,
+            grpc_port: args.grpc_port,
+            ws_port: args.ws_port,
+            batch_size: args.batch_size,
+            poll_interval: args.poll_interval,
+            ens_names: args.ens_names,
+            page_size: args.page_size,
+            max_page_size: args.max_page_size,
+            auth: args.auth,
+            api_keys: args.api_keys,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use clap::Parser;
+
+use crate::chains::chains::{get_chain, Chain};
+
+use super::secrets::{get_replica_urls, get_secret};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "EVM Graph Export",
+    about = "Export the indexed token transfers as a graph of addresses."
+)]
+pub struct EVMGraphExportArgs {
+    #[arg(short, long, help = "Start log with debug", default_value_t = false)]
+    pub debug: bool,
+
+    #[arg(short, long, help = "Chain name to export", default_value_t = String::from("mainnet"))]
+    pub chain: String,
+
+    #[arg(
+        long,
+        help = "First block of the range to export",
+        required_unless_present = "from_date"
+    )]
+    pub from_block: Option<i64>,
+
+    #[arg(
+        long,
+        help = "Last block of the range to export",
+        required_unless_present = "to_date"
+    )]
+    pub to_block: Option<i64>,
+
+    #[arg(
+        long,
+        help = "UTC date to export from instead of the first block, e.g. 2023-03-01"
+    )]
+    pub from_date: Option<String>,
+
+    #[arg(
+        long,
+        help = "UTC date to export to instead of the last block, its whole day is included"
+    )]
+    pub to_date: Option<String>,
+
+    #[arg(
+        long,
+        help = "Amount of blocks to export at the same time",
+        default_value_t = 1000
+    )]
+    pub batch_size: i64,
+
+    #[arg(
+        long,
+        help = "Directory to write the nodes and edges files",
+        default_value_t = String::from("graph")
+    )]
+    pub output: String,
+
+    #[arg(
+        long,
+        help = "Include the native currency transfers",
+        default_value_t = false
+    )]
+    pub native: bool,
+
+    #[arg(
+        long,
+        help = "Neo4j HTTP transaction endpoint to ingest the graph instead of writing files"
+    )]
+    pub neo4j_url: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EVMGraphExportConfig {
+    pub db_url: String,
+    pub db_replica_urls: Vec<String>,
+    pub db_schema: Option<String>,
+    pub redis_url: String,
+    pub debug: bool,
+    pub chain: Chain,
+    pub from_block: i64,
+    pub to_block: i64,
+    /// Resolved to the block range from the indexed blocks.
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub batch_size: i64,
+    pub output: String,
+    pub native: bool,
+    pub neo4j_url: Option<String>,
+    pub neo4j_user: Option<String>,
+    pub neo4j_password: Option<String>,
+}
+
+impl EVMGraphExportConfig {
+    pub fn new() -> Self {
+        let args = EVMGraphExportArgs::parse();
+
+        let mut chainname = args.chain;
+
+        if chainname == "mainnet" {
+            chainname = "ethereum".to_string();
+        }
+
+        Self {
+            db_url: get_secret("DATABASE_URL").expect("DATABASE_URL must be set."),
+            db_replica_urls: get_replica_urls(),
+            db_schema: get_secret("DATABASE_SCHEMA"),
+            redis_url: get_secret("REDIS_URL").expect("REDIS_URL must be set."),
+            debug: args.debug,
+            chain: get_chain(chainname),
+            from_block: args.from_block.unwrap_or_default(),
+            to_block: args.to_block.unwrap_or_default(),
+            from_date: args.from_date,
+            to_date: args.to_date,
+            batch_size: args.batch_size,
+            output: args.output,
+            native: args.native,
+            neo4j_url: args.neo4j_url,
+            neo4j_user: get_secret("NEO4J_USER"),
+            neo4j_password: get_secret("NEO4J_PASSWORD"),
+        }
+    }
+}
@@ -1,28 +1,402 @@
-use crate::chains::chains::{get_chain, Chain};
-use clap::Parser;
+use crate::{
+    chains::chains::{get_chain, get_chain_by_id, Chain},
+    db::{
+        embedded::is_embedded_url,
+        reset::parse_block_range,
+        upsert::{get_upsert_policies, UpsertPolicies},
+    },
+    exports::holders::{SnapshotFormat, TokenStandard},
+    parsers::erc20_transfers_parser::PARSER_NAME as ERC20_TRANSFERS_PARSER,
+    query::query::{QueryCommand, QueryFormat},
+};
+use clap::{Parser, Subcommand};
+
+use super::secrets::{get_replica_urls, get_secret};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum EVMIndexerCommand {
+    /// Print indexed data from the database.
+    Query {
+        #[command(subcommand)]
+        query: QueryCommand,
+
+        #[arg(long, help = "Output format.", value_enum, default_value_t = QueryFormat::Json)]
+        format: QueryFormat,
+    },
+
+    /// Compare stored blocks against the RPC and report the discrepancies.
+    Audit {
+        #[arg(
+            long,
+            help = "First block to audit.",
+            required_unless_present = "from_date"
+        )]
+        from: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Last block to audit.",
+            required_unless_present = "to_date"
+        )]
+        to: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to start from instead of the first block, e.g. 2023-03-01."
+        )]
+        from_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "UTC date to end at instead of the last block, its whole day is included."
+        )]
+        to_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "Store again the blocks with discrepancies.",
+            default_value_t = false
+        )]
+        repair: bool,
+    },
+
+    /// Compare the ERC-20 balances computed from the indexed transfers against balanceOf.
+    AuditBalances {
+        #[arg(
+            long,
+            help = "Block to compare the balances at.",
+            required_unless_present = "date"
+        )]
+        block: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to compare the balances at instead of the block, at the end of the day."
+        )]
+        date: Option<String>,
+
+        #[arg(
+            long,
+            help = "Comma separated tokens to audit, defaults to every token with recent transfers."
+        )]
+        tokens: Option<String>,
+
+        #[arg(long, help = "Amount of holders to sample.", default_value_t = 100)]
+        samples: i64,
+
+        #[arg(
+            long,
+            help = "Blocks before the audited one to sample the recipients of transfers from.",
+            default_value_t = 10000
+        )]
+        window: i64,
+
+        #[arg(
+            long,
+            help = "Mark the tokens whose every sampled holder differs in the same direction as rebasing or fee on transfer."
+        )]
+        mark: bool,
+    },
+
+    /// Decode and store again the archived raw blocks of a range.
+    Redecode {
+        #[arg(
+            long,
+            help = "First block to decode.",
+            required_unless_present = "from_date"
+        )]
+        from: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Last block to decode.",
+            required_unless_present = "to_date"
+        )]
+        to: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to start from instead of the first block, e.g. 2023-03-01."
+        )]
+        from_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "UTC date to end at instead of the last block, its whole day is included."
+        )]
+        to_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "Delete the stored rows of the range before storing them again.",
+            default_value_t = false
+        )]
+        replace: bool,
+    },
+
+    /// Queue a range of blocks for the running indexer to sync before the rest of the history.
+    Backfill {
+        #[arg(
+            long,
+            help = "First block to sync.",
+            required_unless_present = "from_date"
+        )]
+        from: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Last block to sync.",
+            required_unless_present = "to_date"
+        )]
+        to: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to start from instead of the first block, e.g. 2023-03-01."
+        )]
+        from_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "UTC date to end at instead of the last block, its whole day is included."
+        )]
+        to_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "Jobs with a higher priority are synced first.",
+            default_value_t = 0
+        )]
+        priority: i64,
+    },
+
+    /// Sample the configured view calls over a range of blocks.
+    SampleCalls {
+        #[arg(
+            long,
+            help = "First block to sample.",
+            required_unless_present = "from_date"
+        )]
+        from: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Last block to sample.",
+            required_unless_present = "to_date"
+        )]
+        to: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to start from instead of the first block, e.g. 2023-03-01."
+        )]
+        from_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "UTC date to end at instead of the last block, its whole day is included."
+        )]
+        to_date: Option<String>,
+    },
+
+    /// Cluster the addresses of a range of blocks likely controlled by the same owner.
+    Cluster {
+        #[arg(
+            long,
+            help = "First block to read the transactions from.",
+            required_unless_present = "from_date"
+        )]
+        from: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Last block to read the transactions from.",
+            required_unless_present = "to_date"
+        )]
+        to: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to start from instead of the first block, e.g. 2023-03-01."
+        )]
+        from_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "UTC date to end at instead of the last block, its whole day is included."
+        )]
+        to_date: Option<String>,
+    },
+
+    /// Flag the probable exchange deposit addresses of a range of blocks.
+    ExchangeDeposits {
+        #[arg(
+            long,
+            help = "First block to read the transfers from.",
+            required_unless_present = "from_date"
+        )]
+        from: Option<i64>,
+
+        #[arg(
+            long,
+            help = "Last block to read the transfers from.",
+            required_unless_present = "to_date"
+        )]
+        to: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date to start from instead of the first block, e.g. 2023-03-01."
+        )]
+        from_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "UTC date to end at instead of the last block, its whole day is included."
+        )]
+        to_date: Option<String>,
+
+        #[arg(
+            long,
+            help = "JSON file with the address labels to import first, the exchange hot wallets have the exchange kind."
+        )]
+        labels: Option<String>,
+    },
+
+    /// Export the balances of the holders of a token as of a block, e.g. for an airdrop.
+    HolderSnapshot {
+        #[arg(long, help = "Address of the token.")]
+        token: String,
+
+        #[arg(long, help = "Standard of the token.", value_enum, default_value_t = TokenStandard::Erc20)]
+        standard: TokenStandard,
+
+        #[arg(
+            long,
+            help = "Block of the snapshot.",
+            required_unless_present = "date"
+        )]
+        block: Option<i64>,
+
+        #[arg(
+            long,
+            help = "UTC date of the snapshot instead of the block, taken at the end of the day."
+        )]
+        date: Option<String>,
+
+        #[arg(long, help = "Output format.", value_enum, default_value_t = SnapshotFormat::Csv)]
+        format: SnapshotFormat,
+
+        #[arg(
+            long,
+            help = "File to write the holders to, defaults to the standard output."
+        )]
+        output: Option<String>,
+    },
+
+    /// Build the Merkle tree and proofs of an airdrop to the holders of a snapshot.
+    MerkleAirdrop {
+        #[arg(long, help = "JSON holder snapshot, the holders claim their balance.")]
+        snapshot: String,
+
+        #[arg(
+            long,
+            help = "File to write the root and proofs to, defaults to the standard output."
+        )]
+        output: Option<String>,
+    },
+}
 
 #[derive(Parser, Debug)]
 #[command(
     name = "EVM Indexer",
-    about = "A scalable SQL indexer for EVM compatible blockchains."
+    about = "A scalable SQL indexer for EVM compatible blockchains.",
+    subcommand_negates_reqs = true
 )]
 pub struct EVMIndexerArgs {
+    #[command(subcommand)]
+    pub command: Option<EVMIndexerCommand>,
+
     #[arg(short, long, help = "Start log with debug.", default_value_t = false)]
     pub debug: bool,
 
-    #[arg(short, long, help = "Chain name to sync.", default_value_t = String::from("mainnet"))]
+    #[arg(short, long, help = "Chain name or id to sync.", default_value_t = String::from("mainnet"))]
     pub chain: String,
 
     #[arg(short, long, help = "Block to start syncing.", default_value_t = 0)]
     pub start_block: i64,
 
+    #[arg(
+        long,
+        help = "UTC date to start syncing from instead of the start block, e.g. 2023-03-01."
+    )]
+    pub from_date: Option<String>,
+
+    #[arg(
+        long,
+        help = "Block to start backfilling the logs with eth_getLogs, the blocks below the start block only get their logs."
+    )]
+    pub logs_start_block: Option<i64>,
+
+    #[arg(
+        long,
+        help = "Amount of blocks to request logs for at once during the logs backfill.",
+        default_value_t = 1000
+    )]
+    pub logs_range: i64,
+
     #[arg(
         short,
         long,
-        help = "Amount of blocks to fetch at the same time.",
-        default_value_t = 200
+        help = "Amount of blocks to fetch at the same time, defaults to the chain preset."
     )]
-    pub batch_size: usize,
+    pub batch_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Adjust the batch size to the sync lag and the RPC error rate.",
+        default_value_t = false
+    )]
+    pub autoscale: bool,
+
+    #[arg(
+        long,
+        help = "Smallest batch size when autoscaling.",
+        default_value_t = 10
+    )]
+    pub min_batch_size: usize,
+
+    #[arg(
+        long,
+        help = "Largest batch size when autoscaling.",
+        default_value_t = 1000
+    )]
+    pub max_batch_size: usize,
+
+    #[arg(
+        long,
+        help = "Megabytes of fetched data to buffer before storing it, large blocks are flushed early."
+    )]
+    pub memory_budget: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Store the transactions input and logs data compressed with zstd.",
+        default_value_t = false
+    )]
+    pub compress_payloads: bool,
+
+    #[arg(
+        long,
+        help = "Upsert policy of the tables already stored, e.g. blocks=ignore,receipts=merge. Policies are ignore, replace and merge."
+    )]
+    pub upsert_policies: Option<String>,
+
+    #[arg(
+        long,
+        help = "Parsers to run on the logs of each block before storing it, e.g. erc20_transfers. Only the erc20 transfers parser can run inline."
+    )]
+    pub inline_parsers: Option<String>,
 
     #[arg(
         short,
@@ -32,28 +406,424 @@ pub struct EVMIndexerArgs {
     )]
     pub reset: bool,
 
-    #[arg(short, long, help = "Websocket to fetch blocks from.")]
-    pub websocket: String,
+    #[arg(
+        long,
+        help = "Blocks to reset as <from>..<to>, alone their data is deleted to fetch them again, with a table or parser it limits their reset to the range."
+    )]
+    pub reset_range: Option<String>,
+
+    #[arg(
+        long,
+        help = "Parser table to delete and parse again, e.g. erc20_transfers."
+    )]
+    pub reset_table: Option<String>,
+
+    #[arg(
+        long,
+        help = "Parser whose tables are deleted and parsed again, e.g. lending."
+    )]
+    pub reset_parser: Option<String>,
+
+    #[arg(
+        long,
+        help = "Let several indexers write the same chain instead of locking it for a single one.",
+        default_value_t = false
+    )]
+    pub distributed: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Websocket to fetch blocks from, defaults to WEBSOCKET_URL."
+    )]
+    pub websocket: Option<String>,
 
     #[arg(
         short,
         long,
-        help = "Comma separated list of rpcs to use to fetch blocks."
+        help = "Comma separated list of rpcs to use to fetch blocks, defaults to RPC_URL."
+    )]
+    pub rpcs: Option<String>,
+
+    #[arg(
+        long,
+        help = "Cache finalized blocks and receipts in Redis.",
+        default_value_t = false
+    )]
+    pub rpc_cache: bool,
+
+    #[arg(
+        long,
+        help = "Seconds to wait for a RPC response.",
+        default_value_t = 60
     )]
-    pub rpcs: String,
+    pub rpc_timeout: u64,
+
+    #[arg(
+        long,
+        help = "Seconds to wait for a RPC trace response.",
+        default_value_t = 300
+    )]
+    pub rpc_trace_timeout: u64,
+
+    #[arg(
+        long,
+        help = "Seconds to wait for a RPC endpoint to answer at startup before skipping it.",
+        default_value_t = 10
+    )]
+    pub rpc_connect_timeout: u64,
+
+    #[arg(
+        long,
+        help = "Seconds between TCP keep-alive probes of the RPC connections, 0 disables them.",
+        default_value_t = 60
+    )]
+    pub rpc_keepalive: u64,
+
+    #[arg(
+        long,
+        help = "Seconds between pings of the websocket, 0 disables them.",
+        default_value_t = 30
+    )]
+    pub ws_ping_interval: u64,
+
+    #[arg(
+        long,
+        help = "Amount of blocks behind the head considered final, defaults to the chain preset."
+    )]
+    pub finality_depth: Option<i64>,
+
+    #[arg(
+        long,
+        help = "Fetch the receipts of a block in a single request, defaults to the chain preset."
+    )]
+    pub block_receipts: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Verify the transactions and receipts roots of the blocks before storing them.",
+        default_value_t = false
+    )]
+    pub verify_roots: bool,
+
+    #[arg(
+        long,
+        help = "Archive the raw block and receipts responses to s3://, gs:// or a local directory."
+    )]
+    pub archive_url: Option<String>,
+
+    #[arg(
+        long,
+        help = "Amount of blocks to archive in each object.",
+        default_value_t = 1000
+    )]
+    pub archive_range: usize,
+
+    #[arg(
+        long,
+        help = "Write the indexed data as Delta Lake tables to s3://, gs:// or a local directory."
+    )]
+    pub lake_url: Option<String>,
+
+    #[arg(
+        long,
+        help = "Amount of blocks to write to the lake tables at once.",
+        default_value_t = 1000
+    )]
+    pub lake_range: usize,
+
+    #[arg(long, help = "Amount of blocks behind the head before warning.")]
+    pub lag_threshold: Option<i64>,
+
+    #[arg(
+        long,
+        help = "Webhook to notify when the sync lag exceeds the threshold."
+    )]
+    pub lag_webhook: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exit the indexer when the sync lag exceeds the threshold.",
+        default_value_t = false
+    )]
+    pub exit_on_lag: bool,
+
+    #[arg(long, help = "OTLP endpoint to export tracing spans to.")]
+    pub otlp_endpoint: Option<String>,
+
+    #[arg(
+        long,
+        help = "Show a terminal dashboard instead of logs.",
+        default_value_t = false
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Publish committed blocks and logs for the WebSocket server.",
+        default_value_t = false
+    )]
+    pub publish_events: bool,
+
+    #[arg(
+        long,
+        help = "Send a pg_notify event of each committed block.",
+        default_value_t = false
+    )]
+    pub notify: bool,
+
+    #[arg(
+        long,
+        help = "Write committed blocks and logs to the outbox for the relay.",
+        default_value_t = false
+    )]
+    pub outbox: bool,
+
+    #[arg(
+        long,
+        help = "Track the first appearance of each address in evm_new_addresses, also written to the outbox with --outbox.",
+        default_value_t = false
+    )]
+    pub new_addresses: bool,
+
+    #[arg(
+        long,
+        help = "File with the addresses to flag transactions and transfers from or to."
+    )]
+    pub screening_list: Option<String>,
+
+    #[arg(long, help = "Webhook to notify when an address of the list is found.")]
+    pub screening_webhook: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with the alert rules to evaluate on the indexed data."
+    )]
+    pub alert_rules: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with the contract storage slots to read at each new block."
+    )]
+    pub storage_slots: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with the accounts and storage slots to archive the proofs of at each new block."
+    )]
+    pub proof_accounts: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with the holders to sample the balanceOf of the rebasing and fee on transfer tokens for."
+    )]
+    pub watched_holders: Option<String>,
+
+    #[arg(
+        long,
+        help = "Blocks between the balance samples of the watched holders.",
+        default_value_t = 100
+    )]
+    pub balance_sampling_interval: i64,
+
+    #[arg(
+        long,
+        help = "JSON file with the rebasing and fee on transfer tokens, defaults to a known list."
+    )]
+    pub nonstandard_tokens: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with the contract view calls to sample at each new block."
+    )]
+    pub view_calls: Option<String>,
+
+    #[arg(
+        long,
+        help = "Store the base fee and priority fee percentiles of eth_feeHistory.",
+        default_value_t = false
+    )]
+    pub fee_history: bool,
+
+    #[arg(
+        long,
+        help = "Store the interval and propagation delay of the new blocks received by the subscription.",
+        default_value_t = false
+    )]
+    pub block_times: bool,
+
+    #[arg(
+        long,
+        help = "Comma separated reward percentiles to request from eth_feeHistory.",
+        default_value_t = String::from("10,25,50,75,90")
+    )]
+    pub fee_history_percentiles: String,
+
+    #[arg(
+        long,
+        help = "Store the state changes of the transactions traced with the prestate tracer.",
+        default_value_t = false
+    )]
+    pub state_diffs: bool,
+
+    #[arg(
+        long,
+        help = "Comma separated contracts to store the call tree of the transactions sent to."
+    )]
+    pub trace_contracts: Option<String>,
+
+    #[arg(
+        long,
+        help = "Store the call tree of the transactions flagged by the screening.",
+        default_value_t = false
+    )]
+    pub trace_flagged: bool,
+
+    #[arg(
+        long,
+        help = "Store the native token transfers of the internal calls traced with the call tracer.",
+        default_value_t = false
+    )]
+    pub native_transfers: bool,
+
+    #[arg(
+        long,
+        help = "Store the decoded revert reason of the failed transactions.",
+        default_value_t = false
+    )]
+    pub revert_reasons: bool,
+
+    #[arg(
+        long,
+        help = "Store the mints, burns, blacklistings and large transfers of the stablecoins.",
+        default_value_t = false
+    )]
+    pub stablecoins: bool,
+
+    #[arg(
+        long,
+        help = "JSON file with the stablecoins to monitor, defaults to USDC, USDT and DAI."
+    )]
+    pub stablecoin_list: Option<String>,
+
+    #[arg(
+        long,
+        help = "Seconds between checks of the screening list, alert rules and watched contracts files for changes to reload them, 0 disables it.",
+        default_value_t = 30
+    )]
+    pub reload_interval: u64,
+
+    #[arg(
+        long,
+        help = "Seconds between samples of the inserted rows and sizes of the tables, 0 disables it.",
+        default_value_t = 300
+    )]
+    pub table_stats_interval: u64,
+
+    #[arg(
+        long,
+        help = "Port to serve the admin endpoints on, authenticated with the ADMIN_TOKEN secret."
+    )]
+    pub admin_port: Option<u16>,
+
+    #[arg(
+        long,
+        help = "Comma separated files or directories of function and event signatures to import at startup."
+    )]
+    pub signatures: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma separated URLs, files or directories of Uniswap token lists or CoinGecko coins exports to import at startup."
+    )]
+    pub token_lists: Option<String>,
+
+    #[arg(
+        long,
+        help = "Store the chain tables in a database schema named after the chain.",
+        default_value_t = false
+    )]
+    pub schema_per_chain: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct EVMIndexerConfig {
+    pub command: Option<EVMIndexerCommand>,
     pub start_block: i64,
+    /// Resolved to the start block once the RPC is available.
+    pub from_date: Option<String>,
+    /// Blocks from this one up to the start block only get their logs, fetched by range.
+    pub logs_start_block: Option<i64>,
+    pub logs_range: i64,
     pub db_url: String,
+    pub db_replica_urls: Vec<String>,
+    pub db_schema: Option<String>,
     pub redis_url: String,
     pub debug: bool,
     pub chain: Chain,
     pub batch_size: usize,
+    pub autoscale: bool,
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    /// Bytes of fetched data to buffer before storing it.
+    pub memory_budget: Option<usize>,
+    pub compress_payloads: bool,
+    pub upsert_policies: UpsertPolicies,
+    /// Parsers run on the fetched logs, their results are stored in the same transaction.
+    pub inline_parsers: Vec<String>,
     pub reset: bool,
+    pub reset_range: Option<(i64, i64)>,
+    pub reset_table: Option<String>,
+    pub reset_parser: Option<String>,
+    pub distributed: bool,
     pub websocket: String,
     pub rpcs: Vec<String>,
+    pub rpc_cache: bool,
+    pub rpc_timeout: u64,
+    pub rpc_trace_timeout: u64,
+    pub rpc_connect_timeout: u64,
+    pub rpc_keepalive: u64,
+    pub ws_ping_interval: u64,
+    pub finality_depth: i64,
+    pub verify_roots: bool,
+    pub archive_url: Option<String>,
+    pub archive_range: usize,
+    pub lake_url: Option<String>,
+    pub lake_range: usize,
+    pub lag_threshold: Option<i64>,
+    pub lag_webhook: Option<String>,
+    pub exit_on_lag: bool,
+    pub otlp_endpoint: Option<String>,
+    pub tui: bool,
+    pub publish_events: bool,
+    pub notify: bool,
+    pub outbox: bool,
+    pub new_addresses: bool,
+    pub screening_list: Option<String>,
+    pub screening_webhook: Option<String>,
+    pub alert_rules: Option<String>,
+    pub storage_slots: Option<String>,
+    pub proof_accounts: Option<String>,
+    pub watched_holders: Option<String>,
+    pub balance_sampling_interval: i64,
+    pub nonstandard_tokens: Option<String>,
+    pub view_calls: Option<String>,
+    pub fee_history: bool,
+    pub fee_history_percentiles: Vec<f64>,
+    pub block_times: bool,
+    pub state_diffs: bool,
+    pub trace_contracts: Vec<String>,
+    pub trace_flagged: bool,
+    pub native_transfers: bool,
+    pub revert_reasons: bool,
+    pub stablecoins: bool,
+    pub stablecoin_list: Option<String>,
+    pub reload_interval: u64,
+    pub table_stats_interval: u64,
+    pub admin_port: Option<u16>,
+    pub admin_token: Option<String>,
+    pub signatures: Vec<String>,
+    pub token_lists: Vec<String>,
 }
 
 impl EVMIndexerConfig {
@@ -66,20 +836,177 @@ impl EVMIndexerConfig {
             chainname = "ethereum".to_string();
         }
 
-        let chain = get_chain(chainname.clone());
+        // Chains can also be selected by id.
+        let mut chain = match chainname.parse::<i64>() {
+            Ok(id) => get_chain_by_id(id).expect("Chain not found"),
+            Err(_) => get_chain(chainname.clone()),
+        };
+
+        match args.block_receipts {
+            Some(block_receipts) => chain.supports_blocks_receipts = block_receipts,
+            None => (),
+        }
+
+        // The websocket and rpcs can hold credentials, so they can also be read as secrets and
+        // are only optional for the subcommands.
+        let websocket = match args.websocket.or_else(|| get_secret("WEBSOCKET_URL")) {
+            Some(websocket) => websocket,
+            None if args.command.is_some() => String::new(),
+            None => panic!("--websocket or WEBSOCKET_URL must be set."),
+        };
+
+        let rpcs: Vec<String> = match args.rpcs.or_else(|| get_secret("RPC_URL")) {
+            Some(rpcs) => rpcs,
+            None if args.command.is_some() => String::new(),
+            None => panic!("--rpcs or RPC_URL must be set."),
+        }
+        .split(",")
+        .map(|rpc| rpc.to_string())
+        .collect();
+
+        let fee_history_percentiles: Vec<f64> = args
+            .fee_history_percentiles
+            .split(",")
+            .map(|percentile| {
+                percentile
+                    .trim()
+                    .parse::<f64>()
+                    .expect("Unable to parse the fee history percentiles.")
+            })
+            .collect();
+
+        let trace_contracts: Vec<String> = match args.trace_contracts {
+            Some(contracts) => contracts
+                .split(",")
+                .map(|contract| contract.trim().to_lowercase())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // Each chain gets its own schema to index several chains into the same database.
+        let db_schema = match args.schema_per_chain {
+            true => Some(chain.name.to_string()),
+            false => get_secret("DATABASE_SCHEMA"),
+        };
+
+        let db_url = get_secret("DATABASE_URL").expect("DATABASE_URL must be set.");
 
-        let rpcs: Vec<String> = args.rpcs.split(",").map(|rpc| rpc.to_string()).collect();
+        // Embedded databases track the indexed blocks themselves, without Redis.
+        let redis_url = match get_secret("REDIS_URL") {
+            Some(redis_url) => redis_url,
+            None if is_embedded_url(&db_url) => String::new(),
+            None => panic!("REDIS_URL must be set."),
+        };
+
+        let signatures: Vec<String> = match args.signatures {
+            Some(paths) => paths
+                .split(",")
+                .map(|path| path.trim().to_string())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let token_lists: Vec<String> = match args.token_lists {
+            Some(sources) => sources
+                .split(",")
+                .map(|source| source.trim().to_string())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let inline_parsers: Vec<String> = match args.inline_parsers {
+            Some(parsers) => parsers
+                .split(",")
+                .map(|parser| parser.trim().to_string())
+                .filter(|parser| !parser.is_empty())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for parser in inline_parsers.iter() {
+            if parser != ERC20_TRANSFERS_PARSER {
+                panic!("Unknown inline parser {}.", parser);
+            }
+        }
 
         Self {
+            command: args.command,
             start_block: args.start_block,
-            db_url: std::env::var("DATABASE_URL").expect("DATABASE_URL must be set."),
-            redis_url: std::env::var("REDIS_URL").expect("REDIS_URL must be set."),
+            from_date: args.from_date,
+            logs_start_block: args.logs_start_block,
+            logs_range: args.logs_range.max(1),
+            db_url,
+            db_replica_urls: get_replica_urls(),
+            db_schema,
+            redis_url,
             debug: args.debug,
             chain,
-            batch_size: args.batch_size,
+            batch_size: args.batch_size.unwrap_or(chain.batch_size),
+            autoscale: args.autoscale,
+            min_batch_size: args.min_batch_size,
+            max_batch_size: args.max_batch_size,
+            memory_budget: args
+                .memory_budget
+                .map(|megabytes| megabytes.max(1) * 1_000_000),
+            compress_payloads: args.compress_payloads,
+            upsert_policies: get_upsert_policies(&args.upsert_policies)
+                .expect("Unable to parse the upsert policies."),
+            inline_parsers,
             reset: args.reset,
-            websocket: args.websocket,
+            reset_range: args
+                .reset_range
+                .map(|range| parse_block_range(&range).expect("Unable to parse the reset range.")),
+            reset_table: args.reset_table,
+            reset_parser: args.reset_parser,
+            distributed: args.distributed,
+            websocket,
             rpcs,
+            rpc_cache: args.rpc_cache,
+            rpc_timeout: args.rpc_timeout,
+            rpc_trace_timeout: args.rpc_trace_timeout,
+            rpc_connect_timeout: args.rpc_connect_timeout,
+            rpc_keepalive: args.rpc_keepalive,
+            ws_ping_interval: args.ws_ping_interval,
+            finality_depth: args.finality_depth.unwrap_or(chain.finality_depth),
+            verify_roots: args.verify_roots,
+            archive_url: args.archive_url,
+            archive_range: args.archive_range,
+            lake_url: args.lake_url,
+            lake_range: args.lake_range,
+            lag_threshold: args.lag_threshold,
+            lag_webhook: args.lag_webhook,
+            exit_on_lag: args.exit_on_lag,
+            otlp_endpoint: args.otlp_endpoint,
+            tui: args.tui,
+            publish_events: args.publish_events,
+            notify: args.notify,
+            outbox: args.outbox,
+            new_addresses: args.new_addresses,
+            screening_list: args.screening_list,
+            screening_webhook: args.screening_webhook,
+            alert_rules: args.alert_rules,
+            storage_slots: args.storage_slots,
+            proof_accounts: args.proof_accounts,
+            watched_holders: args.watched_holders,
+            balance_sampling_interval: args.balance_sampling_interval,
+            nonstandard_tokens: args.nonstandard_tokens,
+            view_calls: args.view_calls,
+            fee_history: args.fee_history,
+            fee_history_percentiles,
+            block_times: args.block_times,
+            state_diffs: args.state_diffs,
+            trace_contracts,
+            trace_flagged: args.trace_flagged,
+            native_transfers: args.native_transfers,
+            revert_reasons: args.revert_reasons,
+            stablecoins: args.stablecoins,
+            stablecoin_list: args.stablecoin_list,
+            reload_interval: args.reload_interval,
+            table_stats_interval: args.table_stats_interval,
+            admin_port: args.admin_port,
+            admin_token: get_secret("ADMIN_TOKEN"),
+            signatures,
+            token_lists,
         }
     }
 }
@@ -1,3 +1,8 @@
 pub mod abi_fetcher_config;
+pub mod api_config;
+pub mod graph_export_config;
 pub mod indexer_config;
 pub mod parser_config;
+pub mod relay_config;
+pub mod reload;
+pub mod secrets;
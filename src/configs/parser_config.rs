@@ -1,5 +1,9 @@
 use clap::Parser;
 
+use crate::tokens::prices::PriceProvider;
+
+use super::secrets::{get_replica_urls, get_secret};
+
 #[derive(Parser, Debug)]
 #[command(
     name = "EVM Parser",
@@ -24,15 +28,242 @@ pub struct EVMParserArgs {
         default_value_t = false
     )]
     pub erc20_tokens_parser: bool,
+
+    #[arg(long, help = "OTLP endpoint to export tracing spans to")]
+    pub otlp_endpoint: Option<String>,
+
+    #[arg(
+        long,
+        help = "Publish parsed erc20 transfers for the WebSocket server",
+        default_value_t = false
+    )]
+    pub publish_events: bool,
+
+    #[arg(
+        long,
+        help = "Send a pg_notify event of each parsed erc20 transfer",
+        default_value_t = false
+    )]
+    pub notify: bool,
+
+    #[arg(
+        long,
+        help = "Start the lending events parser",
+        default_value_t = false
+    )]
+    pub lending_parser: bool,
+
+    #[arg(
+        long,
+        help = "JSON file with the lending protocols deployments to parse"
+    )]
+    pub lending_deployments: Option<String>,
+
+    #[arg(long, help = "Start the dex swaps parser", default_value_t = false)]
+    pub dex_swaps_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the uniswap v3 liquidity events parser",
+        default_value_t = false
+    )]
+    pub liquidity_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the token prices parser from dex swaps",
+        default_value_t = false
+    )]
+    pub token_prices_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the sandwich and arbitrage detection parser",
+        default_value_t = false
+    )]
+    pub mev_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the flash loans detection parser",
+        default_value_t = false
+    )]
+    pub flash_loans_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the bridge deposits and withdrawals parser",
+        default_value_t = false
+    )]
+    pub bridge_parser: bool,
+
+    #[arg(
+        long,
+        help = "JSON file with the bridge contracts deployments to parse"
+    )]
+    pub bridge_deployments: Option<String>,
+
+    #[arg(
+        long,
+        help = "Start the governance proposals and votes parser",
+        default_value_t = false
+    )]
+    pub governance_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the staking events and rewards parser",
+        default_value_t = false
+    )]
+    pub staking_parser: bool,
+
+    #[arg(
+        long,
+        help = "JSON file with the staking contracts deployments to parse"
+    )]
+    pub staking_deployments: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with protocol deployments to add to the registry read by the lending, bridge and staking parsers and the API"
+    )]
+    pub protocol_deployments: Option<String>,
+
+    #[arg(
+        long,
+        help = "JSON file with the policies of the protocols for identical events emitted twice, e.g. by proxies"
+    )]
+    pub dedup_policies: Option<String>,
+
+    #[arg(
+        long,
+        help = "Start the ENS registrations and records parser",
+        default_value_t = false
+    )]
+    pub ens_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the permit approvals parser",
+        default_value_t = false
+    )]
+    pub permits_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the spam tokens scoring parser",
+        default_value_t = false
+    )]
+    pub spam_tokens_parser: bool,
+
+    #[arg(
+        long,
+        help = "Start the parser storing the logs of contracts with a known ABI as JSON",
+        default_value_t = false
+    )]
+    pub decoded_logs_parser: bool,
+
+    #[arg(
+        long,
+        help = "Parse again the logs quarantined by a parser, or by all of them with `all`, after fixing it"
+    )]
+    pub redrive_failures: Option<String>,
+
+    #[arg(
+        long,
+        help = "Amount of chunks of each batch of logs parsed in parallel, the order dependent governance, ENS and liquidity parsers stay sequential",
+        default_value_t = 1
+    )]
+    pub parser_workers: usize,
+
+    #[arg(
+        long,
+        help = "Start the worker fetching the USD prices of the indexed tokens from an external provider, used when the dex swaps can't price a token",
+        value_enum
+    )]
+    pub external_prices: Option<PriceProvider>,
+
+    #[arg(
+        long,
+        help = "Days of daily prices fetched from the external provider for each new token",
+        default_value_t = 365
+    )]
+    pub external_prices_days: i64,
+
+    #[arg(
+        long,
+        help = "Seconds between the updates of the external prices",
+        default_value_t = 3600
+    )]
+    pub external_prices_interval: u64,
+
+    #[arg(
+        long,
+        help = "Start the worker refreshing the materialized views read by the dashboards",
+        default_value_t = false
+    )]
+    pub refresh_views: bool,
+
+    #[arg(
+        long,
+        help = "Comma separated list of materialized views to refresh, defaults to all of them"
+    )]
+    pub views: Option<String>,
+
+    #[arg(
+        long,
+        help = "Seconds between the refreshes of each materialized view",
+        default_value_t = 600
+    )]
+    pub views_interval: u64,
+
+    #[arg(
+        long,
+        help = "Comma separated list of reducers to keep the derived state of, e.g. erc20_balances,uniswap_v2_reserves"
+    )]
+    pub reducers: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct EVMParserConfig {
     pub db_url: String,
+    pub db_replica_urls: Vec<String>,
+    pub db_schema: Option<String>,
     pub redis_url: String,
     pub debug: bool,
     pub llamafolio_adapter: bool,
     pub erc20_tokens_parser: bool,
+    pub otlp_endpoint: Option<String>,
+    pub publish_events: bool,
+    pub notify: bool,
+    pub lending_parser: bool,
+    pub lending_deployments: Option<String>,
+    pub dex_swaps_parser: bool,
+    pub liquidity_parser: bool,
+    pub token_prices_parser: bool,
+    pub mev_parser: bool,
+    pub flash_loans_parser: bool,
+    pub bridge_parser: bool,
+    pub bridge_deployments: Option<String>,
+    pub governance_parser: bool,
+    pub staking_parser: bool,
+    pub staking_deployments: Option<String>,
+    pub protocol_deployments: Option<String>,
+    pub dedup_policies: Option<String>,
+    pub ens_parser: bool,
+    pub permits_parser: bool,
+    pub spam_tokens_parser: bool,
+    pub decoded_logs_parser: bool,
+    pub redrive_failures: Option<String>,
+    pub parser_workers: usize,
+    pub external_prices: Option<PriceProvider>,
+    pub external_prices_api_key: Option<String>,
+    pub external_prices_days: i64,
+    pub external_prices_interval: u64,
+    pub refresh_views: bool,
+    pub views: Option<Vec<String>>,
+    pub views_interval: u64,
+    pub reducers: Vec<String>,
 }
 
 impl EVMParserConfig {
@@ -40,11 +271,57 @@ impl EVMParserConfig {
         let args = EVMParserArgs::parse();
 
         Self {
-            db_url: std::env::var("DATABASE_URL").expect("DATABASE_URL must be set."),
-            redis_url: std::env::var("REDIS_URL").expect("REDIS_URL must be set."),
+            db_url: get_secret("DATABASE_URL").expect("DATABASE_URL must be set."),
+            db_replica_urls: get_replica_urls(),
+            db_schema: get_secret("DATABASE_SCHEMA"),
+            redis_url: get_secret("REDIS_URL").expect("REDIS_URL must be set."),
             debug: args.debug,
             llamafolio_adapter: args.llamafolio_adapters,
             erc20_tokens_parser: args.erc20_tokens_parser,
+            otlp_endpoint: args.otlp_endpoint,
+            publish_events: args.publish_events,
+            notify: args.notify,
+            lending_parser: args.lending_parser,
+            lending_deployments: args.lending_deployments,
+            dex_swaps_parser: args.dex_swaps_parser,
+            liquidity_parser: args.liquidity_parser,
+            token_prices_parser: args.token_prices_parser,
+            mev_parser: args.mev_parser,
+            flash_loans_parser: args.flash_loans_parser,
+            bridge_parser: args.bridge_parser,
+            bridge_deployments: args.bridge_deployments,
+            governance_parser: args.governance_parser,
+            staking_parser: args.staking_parser,
+            staking_deployments: args.staking_deployments,
+            protocol_deployments: args.protocol_deployments,
+            dedup_policies: args.dedup_policies,
+            ens_parser: args.ens_parser,
+            permits_parser: args.permits_parser,
+            spam_tokens_parser: args.spam_tokens_parser,
+            decoded_logs_parser: args.decoded_logs_parser,
+            redrive_failures: args.redrive_failures,
+            parser_workers: args.parser_workers.max(1),
+            external_prices: args.external_prices,
+            external_prices_api_key: get_secret("COINGECKO_API_KEY"),
+            external_prices_days: args.external_prices_days,
+            external_prices_interval: args.external_prices_interval,
+            refresh_views: args.refresh_views,
+            views: args.views.map(|views| {
+                views
+                    .split(",")
+                    .map(|view| view.trim().to_string())
+                    .filter(|view| !view.is_empty())
+                    .collect()
+            }),
+            views_interval: args.views_interval,
+            reducers: match args.reducers {
+                Some(reducers) => reducers
+                    .split(",")
+                    .map(|reducer| reducer.trim().to_string())
+                    .filter(|reducer| !reducer.is_empty())
+                    .collect(),
+                None => Vec::new(),
+            },
         }
     }
 }
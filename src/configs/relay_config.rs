@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use clap::Parser;
+
+use crate::sinks::nats::get_nats_subjects;
+
+use super::secrets::get_secret;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "EVM Relay",
+    about = "Outbox relay to deliver indexed events to external sinks."
+)]
+pub struct EVMRelayArgs {
+    #[arg(short, long, help = "Start log with debug", default_value_t = false)]
+    pub debug: bool,
+
+    #[arg(long, help = "Webhook to deliver the outbox events")]
+    pub webhook: Option<String>,
+
+    #[arg(
+        long,
+        help = "BigQuery project to stream the outbox events instead of a webhook"
+    )]
+    pub bigquery_project: Option<String>,
+
+    #[arg(long, help = "BigQuery dataset of the outbox events tables")]
+    pub bigquery_dataset: Option<String>,
+
+    #[arg(
+        long,
+        help = "Prefix of the BigQuery tables, followed by the event topic",
+        default_value_t = String::from("evm_")
+    )]
+    pub bigquery_table_prefix: String,
+
+    #[arg(
+        long,
+        help = "NATS server to publish the outbox events to JetStream instead of a webhook"
+    )]
+    pub nats_url: Option<String>,
+
+    #[arg(
+        long,
+        help = "Prefix of the NATS subjects, followed by the chain and the event topic",
+        default_value_t = String::from("evm")
+    )]
+    pub nats_subject_prefix: String,
+
+    #[arg(
+        long,
+        help = "Comma separated NATS subjects per topic, e.g. block=evm.blocks,log=evm.logs"
+    )]
+    pub nats_subjects: Option<String>,
+
+    #[arg(
+        long,
+        help = "Name of the sink to track the delivered offset, webhook, bigquery or nats by default"
+    )]
+    pub sink_name: Option<String>,
+
+    #[arg(long, help = "Outbox id to replay the events from")]
+    pub replay_from: Option<i64>,
+
+    #[arg(
+        long,
+        help = "Amount of events to deliver at the same time",
+        default_value_t = 1000
+    )]
+    pub batch_size: i64,
+}
+
+#[derive(Debug, Clone)]
+pub enum EVMRelaySink {
+    Webhook(String),
+    BigQuery {
+        project: String,
+        dataset: String,
+        table_prefix: String,
+    },
+    Nats {
+        url: String,
+        subject_prefix: String,
+        subjects: HashMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct EVMRelayConfig {
+    pub db_url: String,
+    pub db_schema: Option<String>,
+    pub redis_url: String,
+    pub debug: bool,
+    pub sink: EVMRelaySink,
+    pub sink_name: String,
+    pub replay_from: Option<i64>,
+    pub batch_size: i64,
+}
+
+impl EVMRelayConfig {
+    pub fn new() -> Self {
+        let args = EVMRelayArgs::parse();
+
+        let sink = match (
+            args.webhook,
+            args.bigquery_project,
+            args.bigquery_dataset,
+            args.nats_url,
+        ) {
+            (None, Some(project), Some(dataset), None) => EVMRelaySink::BigQuery {
+                project,
+                dataset,
+                table_prefix: args.bigquery_table_prefix,
+            },
+            (None, Some(_), None, None) => panic!("--bigquery-dataset must be set."),
+            (None, None, _, Some(url)) => EVMRelaySink::Nats {
+                url,
+                subject_prefix: args.nats_subject_prefix,
+                subjects: get_nats_subjects(&args.nats_subjects),
+            },
+            (Some(webhook), None, _, None) => EVMRelaySink::Webhook(webhook),
+            _ => panic!("Only one of --webhook, --bigquery-project or --nats-url must be set."),
+        };
+
+        let sink_name = match (args.sink_name, &sink) {
+            (Some(sink_name), _) => sink_name,
+            (None, EVMRelaySink::Webhook(_)) => String::from("webhook"),
+            (None, EVMRelaySink::BigQuery { .. }) => String::from("bigquery"),
+            (None, EVMRelaySink::Nats { .. }) => String::from("nats"),
+        };
+
+        Self {
+            db_url: get_secret("DATABASE_URL").expect("DATABASE_URL must be set."),
+            db_schema: get_secret("DATABASE_SCHEMA"),
+            redis_url: get_secret("REDIS_URL").expect("REDIS_URL must be set."),
+            debug: args.debug,
+            sink,
+            sink_name,
+            replay_from: args.replay_from,
+            batch_size: args.batch_size,
+        }
+    }
+}
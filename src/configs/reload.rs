@@ -0,0 +1,54 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use log::*;
+
+/// Tracks the modification time of configuration files to reload them without restarting.
+/// Files that can't be read, e.g. while an editor replaces them, are not reported until they are
+/// readable again.
+#[derive(Debug, Clone, Default)]
+pub struct FileWatcher {
+    pub files: HashMap<String, Option<SystemTime>>,
+}
+
+impl FileWatcher {
+    pub fn new(paths: Vec<String>) -> Self {
+        let files: HashMap<String, Option<SystemTime>> = paths
+            .into_iter()
+            .map(|path| {
+                let modified = get_modified(&path);
+
+                (path, modified)
+            })
+            .collect();
+
+        info!("Watching {} configuration files for changes.", files.len());
+
+        Self { files }
+    }
+
+    pub fn get_changed_files(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = match get_modified(path) {
+                Some(modified) => modified,
+                None => continue,
+            };
+
+            if *last_modified != Some(modified) {
+                *last_modified = Some(modified);
+
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+}
+
+fn get_modified(path: &String) -> Option<SystemTime> {
+    match std::fs::metadata(path) {
+        Ok(metadata) => metadata.modified().ok(),
+        Err(_) => None,
+    }
+}
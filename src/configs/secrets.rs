@@ -0,0 +1,128 @@
+/// Reads a secret from the environment. Besides the variable itself, the value can be read from
+/// the file at `<NAME>_FILE`, as mounted by Docker and Kubernetes secrets, or fetched from Vault
+/// with `<NAME>_VAULT=<path>#<field>` and AWS Secrets Manager with
+/// `<NAME>_AWS_SECRET=<secret id>[#<json field>]` when built with those features.
+pub fn get_secret(name: &str) -> Option<String> {
+    match std::env::var(name) {
+        Ok(value) => return Some(value),
+        Err(_) => (),
+    }
+
+    match std::env::var(format!("{}_FILE", name)) {
+        Ok(path) => {
+            let value = std::fs::read_to_string(&path)
+                .unwrap_or_else(|_| panic!("Unable to read {}_FILE {}", name, path));
+
+            return Some(value.trim().to_string());
+        }
+        Err(_) => (),
+    }
+
+    #[cfg(feature = "vault")]
+    match std::env::var(format!("{}_VAULT", name)) {
+        Ok(reference) => return Some(get_vault_secret(name, &reference)),
+        Err(_) => (),
+    }
+
+    #[cfg(feature = "aws-secrets")]
+    match std::env::var(format!("{}_AWS_SECRET", name)) {
+        Ok(reference) => return Some(get_aws_secret(name, &reference)),
+        Err(_) => (),
+    }
+
+    None
+}
+
+/// Comma separated urls of the database read replicas at `DATABASE_REPLICA_URLS`.
+pub fn get_replica_urls() -> Vec<String> {
+    match get_secret("DATABASE_REPLICA_URLS") {
+        Some(urls) => urls
+            .split(",")
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Reads `<path>#<field>` from the Vault KV engine at `VAULT_ADDR` with `VAULT_TOKEN`.
+#[cfg(feature = "vault")]
+fn get_vault_secret(name: &str, reference: &String) -> String {
+    let (path, field) = match reference.split_once('#') {
+        Some((path, field)) => (path.to_string(), field.to_string()),
+        None => panic!("{}_VAULT must be formatted as <path>#<field>", name),
+    };
+
+    let address = std::env::var("VAULT_ADDR").expect("VAULT_ADDR must be set.");
+    let token = std::env::var("VAULT_TOKEN").expect("VAULT_TOKEN must be set.");
+
+    // Configs are loaded inside the async runtime, so the blocking client runs on its own thread.
+    let response = std::thread::spawn(move || {
+        reqwest::blocking::Client::new()
+            .get(format!("{}/v1/{}", address.trim_end_matches('/'), path))
+            .header("X-Vault-Token", token)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json::<serde_json::Value>())
+    })
+    .join()
+    .unwrap()
+    .unwrap_or_else(|err| panic!("Unable to read {} from Vault: {}", name, err));
+
+    // KV version 2 nests the secret data inside another data object.
+    let data = match response["data"]["data"].is_object() {
+        true => &response["data"]["data"],
+        false => &response["data"],
+    };
+
+    match data[field.as_str()].as_str() {
+        Some(value) => value.to_string(),
+        None => panic!("Vault secret for {} has no field {}", name, field),
+    }
+}
+
+/// Reads `<secret id>[#<json field>]` from AWS Secrets Manager with the default credentials.
+#[cfg(feature = "aws-secrets")]
+fn get_aws_secret(name: &str, reference: &String) -> String {
+    let (secret_id, field) = match reference.split_once('#') {
+        Some((secret_id, field)) => (secret_id.to_string(), Some(field.to_string())),
+        None => (reference.clone(), None),
+    };
+
+    let secret = std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().expect("Unable to start secrets runtime");
+
+        runtime.block_on(async move {
+            let config = aws_config::load_from_env().await;
+
+            aws_sdk_secretsmanager::Client::new(&config)
+                .get_secret_value()
+                .secret_id(secret_id)
+                .send()
+                .await
+                .map(|output| output.secret_string().map(|secret| secret.to_string()))
+        })
+    })
+    .join()
+    .unwrap()
+    .unwrap_or_else(|err| panic!("Unable to read {} from AWS Secrets Manager: {}", name, err));
+
+    let secret = match secret {
+        Some(secret) => secret,
+        None => panic!("AWS secret for {} has no string value", name),
+    };
+
+    // Secrets with several values are stored as a JSON object.
+    match field {
+        Some(field) => {
+            let value: serde_json::Value = serde_json::from_str(&secret)
+                .unwrap_or_else(|_| panic!("AWS secret for {} is not a JSON object", name));
+
+            match value[field.as_str()].as_str() {
+                Some(value) => value.to_string(),
+                None => panic!("AWS secret for {} has no field {}", name, field),
+            }
+        }
+        None => secret,
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    io::{self, Stdout},
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::Show,
+    event::{self, Event, KeyCode},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    text::Spans,
+    widgets::{Block, Borders, Gauge, Paragraph},
+    Frame, Terminal,
+};
+
+use crate::{db::db::EVMDatabase, rpc::rpc::EVMRpc};
+
+/// Time between two samples of the parsers backlog, which counts the unparsed rows.
+pub const BACKLOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Last sample of the parsers backlog, the error when it failed.
+type BacklogSample = (Instant, Result<(i64, i64), String>);
+
+#[derive(Debug, Clone)]
+pub struct SyncDashboardState {
+    pub chain: String,
+    pub start_block: i64,
+    pub last_block: i64,
+    pub indexed_blocks: usize,
+    pub blocks_per_second: f64,
+    pub sync_lag: i64,
+    pub rpc_requests: u64,
+    pub rpc_errors: u64,
+    /// Transfers and tokens backlogs, or the error of their last sample.
+    pub parsers_backlog: Result<(i64, i64), String>,
+}
+
+impl SyncDashboardState {
+    pub fn progress(&self) -> f64 {
+        let total_blocks = self.last_block - self.start_block;
+
+        if total_blocks <= 0 {
+            return 0.0;
+        }
+
+        (self.indexed_blocks as f64 / total_blocks as f64).min(1.0)
+    }
+
+    pub fn rpc_error_rate(&self) -> f64 {
+        if self.rpc_requests == 0 {
+            return 0.0;
+        }
+
+        self.rpc_errors as f64 / self.rpc_requests as f64
+    }
+}
+
+/// Restores the terminal when the dashboard stops, also on errors and panics.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+    }
+}
+
+pub struct SyncDashboard {
+    pub rpc: EVMRpc,
+    pub db: EVMDatabase,
+    pub start_block: i64,
+}
+
+impl SyncDashboard {
+    pub fn new(rpc: EVMRpc, db: EVMDatabase, start_block: i64) -> Self {
+        Self {
+            rpc,
+            db,
+            start_block,
+        }
+    }
+
+    /// Draws the dashboard until the user presses `q`.
+    pub async fn run(&self) -> Result<()> {
+        enable_raw_mode()?;
+
+        let _guard = TerminalGuard;
+
+        let mut stdout = io::stdout();
+
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+        let mut previous: Option<(Instant, usize)> = None;
+
+        let mut backlog: Option<BacklogSample> = None;
+
+        loop {
+            let state = self.collect(&mut previous, &mut backlog).await?;
+
+            terminal.draw(|frame| draw(frame, &state))?;
+
+            // Waiting for a key blocks, so it runs on the blocking threads.
+            if tokio::task::spawn_blocking(wait_for_quit).await?? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn collect(
+        &self,
+        previous: &mut Option<(Instant, usize)>,
+        backlog: &mut Option<BacklogSample>,
+    ) -> Result<SyncDashboardState> {
+        let last_block = self.rpc.get_last_block().await?;
+
+        let indexed_blocks = self.db.get_indexed_blocks().await?;
+
+        let last_indexed_block = match indexed_blocks.iter().max() {
+            Some(block) => *block,
+            None => self.start_block,
+        };
+
+        let now = Instant::now();
+
+        let parsers_backlog = match backlog {
+            Some((sampled, parsers_backlog)) if now.duration_since(*sampled) < BACKLOG_INTERVAL => {
+                parsers_backlog.clone()
+            }
+            _ => {
+                let parsers_backlog = self
+                    .db
+                    .get_parsers_backlog()
+                    .await
+                    .map_err(|err| err.to_string());
+
+                *backlog = Some((now, parsers_backlog.clone()));
+
+                parsers_backlog
+            }
+        };
+
+        let blocks_per_second = match previous {
+            Some((time, amount)) => {
+                let elapsed = now.duration_since(*time).as_secs_f64();
+
+                if elapsed > 0.0 {
+                    indexed_blocks.len().saturating_sub(*amount) as f64 / elapsed
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+
+        *previous = Some((now, indexed_blocks.len()));
+
+        Ok(SyncDashboardState {
+            chain: self.db.chain.name.to_string(),
+            start_block: self.start_block,
+            last_block,
+            indexed_blocks: indexed_blocks.len(),
+            blocks_per_second,
+            sync_lag: (last_block - last_indexed_block).max(0),
+            rpc_requests: self.rpc.stats.requests.load(Ordering::Relaxed),
+            rpc_errors: self.rpc.stats.errors.load(Ordering::Relaxed),
+            parsers_backlog,
+        })
+    }
+}
+
+/// Waits up to 2 seconds for a key, returns whether it's `q`.
+fn wait_for_quit() -> Result<bool> {
+    if event::poll(Duration::from_secs(2))? {
+        if let Event::Key(key) = event::read()? {
+            return Ok(key.code == KeyCode::Char('q'));
+        }
+    }
+
+    Ok(false)
+}
+
+fn draw(frame: &mut Frame<CrosstermBackend<Stdout>>, state: &SyncDashboardState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(frame.size());
+
+    let progress = Gauge::default()
+        .block(
+            Block::default()
+                .title(format!(" {} sync progress ", state.chain))
+                .borders(Borders::ALL),
+        )
+        .gauge_style(Style::default().fg(Color::Green))
+        .ratio(state.progress());
+
+    frame.render_widget(progress, chunks[0]);
+
+    let (erc20_transfers_backlog, erc20_tokens_backlog) = match &state.parsers_backlog {
+        Ok((transfers, tokens)) => (transfers.to_string(), tokens.to_string()),
+        Err(err) => (
+            format!("unavailable ({})", err),
+            String::from("unavailable"),
+        ),
+    };
+
+    let stats = Paragraph::new(vec![
+        Spans::from(format!("Chain head:          {}", state.last_block)),
+        Spans::from(format!("Indexed blocks:      {}", state.indexed_blocks)),
+        Spans::from(format!(
+            "Blocks/s:            {:.2}",
+            state.blocks_per_second
+        )),
+        Spans::from(format!("Lag to head:         {}", state.sync_lag)),
+        Spans::from(format!(
+            "RPC errors:          {} / {} ({:.2}%)",
+            state.rpc_errors,
+            state.rpc_requests,
+            state.rpc_error_rate() * 100.0
+        )),
+        Spans::from(format!(
+            "ERC20 transfers backlog: {}",
+            erc20_transfers_backlog
+        )),
+        Spans::from(format!("ERC20 tokens backlog:    {}", erc20_tokens_backlog)),
+        Spans::from(""),
+        Spans::from("Press q to quit."),
+    ])
+    .block(Block::default().title(" Stats ").borders(Borders::ALL));
+
+    frame.render_widget(stats, chunks[1]);
+}
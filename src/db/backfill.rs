@@ -0,0 +1,173 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use diesel::prelude::*;
+use log::*;
+use serde::Serialize;
+
+use super::{db::EVMDatabase, schema::evm_backfill_jobs};
+
+pub const PENDING: &str = "pending";
+
+pub const RUNNING: &str = "running";
+
+pub const DONE: &str = "done";
+
+/// Finished with blocks that couldn't be fetched or stored, they are left to the linear sync.
+pub const FAILED: &str = "failed";
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = evm_backfill_jobs)]
+pub struct DatabaseEVMBackfillJob {
+    pub chain: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub priority: i64,
+    pub status: String,
+    pub created_at: i64,
+}
+
+#[derive(Selectable, Queryable, Debug, Clone, Serialize)]
+#[diesel(table_name = evm_backfill_jobs)]
+pub struct DatabaseEVMStoredBackfillJob {
+    pub id: i64,
+    pub chain: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    pub priority: i64,
+    pub status: String,
+    pub created_at: i64,
+    pub finished_at: Option<i64>,
+    /// Blocks of a failed job that weren't indexed.
+    pub missing_blocks: Option<Vec<i64>>,
+}
+
+/// Queues a range of blocks for the indexer to sync before resuming the linear sync. Jobs with
+/// a higher priority run first, then the oldest ones.
+pub fn enqueue_backfill_job(
+    db: &EVMDatabase,
+    from_block: i64,
+    to_block: i64,
+    priority: i64,
+) -> Result<DatabaseEVMStoredBackfillJob> {
+    if from_block > to_block {
+        bail!(
+            "Invalid backfill range, block {} is after block {}",
+            from_block,
+            to_block
+        )
+    }
+
+    let mut connection = db.establish_connection();
+
+    let job = DatabaseEVMBackfillJob {
+        chain: db.chain.name.to_string(),
+        from_block,
+        to_block,
+        priority,
+        status: PENDING.to_string(),
+        created_at: get_timestamp(),
+    };
+
+    let id = diesel::insert_into(evm_backfill_jobs::dsl::evm_backfill_jobs)
+        .values(&job)
+        .returning(evm_backfill_jobs::id)
+        .get_result::<i64>(&mut connection)?;
+
+    let job = DatabaseEVMStoredBackfillJob {
+        id,
+        chain: job.chain,
+        from_block,
+        to_block,
+        priority,
+        status: job.status,
+        created_at: job.created_at,
+        finished_at: None,
+        missing_blocks: None,
+    };
+
+    info!(
+        "Queued backfill job {} for blocks {} to {} with priority {}.",
+        job.id, from_block, to_block, priority
+    );
+
+    Ok(job)
+}
+
+/// Next job to run, jobs left running by a restart are resumed.
+pub fn get_next_backfill_job(db: &EVMDatabase) -> Result<Option<DatabaseEVMStoredBackfillJob>> {
+    let mut connection = db.establish_connection();
+
+    let job = evm_backfill_jobs::table
+        .select(DatabaseEVMStoredBackfillJob::as_select())
+        .filter(evm_backfill_jobs::chain.eq(db.chain.name))
+        .filter(evm_backfill_jobs::status.eq_any([PENDING, RUNNING]))
+        .order((
+            evm_backfill_jobs::priority.desc(),
+            evm_backfill_jobs::id.asc(),
+        ))
+        .first(&mut connection)
+        .optional()?;
+
+    Ok(job)
+}
+
+pub fn update_backfill_job_status(db: &EVMDatabase, id: i64, status: &str) -> Result<()> {
+    let mut connection = db.establish_connection();
+
+    let finished_at = match status == DONE {
+        true => Some(get_timestamp()),
+        false => None,
+    };
+
+    diesel::update(evm_backfill_jobs::table.filter(evm_backfill_jobs::id.eq(id)))
+        .set((
+            evm_backfill_jobs::status.eq(status),
+            evm_backfill_jobs::finished_at.eq(finished_at),
+        ))
+        .execute(&mut connection)?;
+
+    Ok(())
+}
+
+/// Finishes a job as failed, recording the blocks it couldn't index.
+pub fn fail_backfill_job(db: &EVMDatabase, id: i64, missing_blocks: &Vec<i64>) -> Result<()> {
+    let mut connection = db.establish_connection();
+
+    diesel::update(evm_backfill_jobs::table.filter(evm_backfill_jobs::id.eq(id)))
+        .set((
+            evm_backfill_jobs::status.eq(FAILED),
+            evm_backfill_jobs::finished_at.eq(Some(get_timestamp())),
+            evm_backfill_jobs::missing_blocks.eq(missing_blocks),
+        ))
+        .execute(&mut connection)?;
+
+    Ok(())
+}
+
+/// Jobs of the chain by the order they run in, the finished ones only with `all`.
+pub fn get_backfill_jobs(db: &EVMDatabase, all: bool) -> Result<Vec<DatabaseEVMStoredBackfillJob>> {
+    let mut connection = db.establish_read_connection();
+
+    let mut query = evm_backfill_jobs::table
+        .select(DatabaseEVMStoredBackfillJob::as_select())
+        .filter(evm_backfill_jobs::chain.eq(db.chain.name))
+        .order((
+            evm_backfill_jobs::priority.desc(),
+            evm_backfill_jobs::id.asc(),
+        ))
+        .into_boxed();
+
+    if !all {
+        query = query.filter(evm_backfill_jobs::status.ne(DONE));
+    }
+
+    Ok(query.load(&mut connection)?)
+}
+
+fn get_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_secs() as i64
+}
@@ -0,0 +1,142 @@
+use std::mem::size_of;
+
+use super::models::models::{
+    DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
+    DatabaseEVMTransactionReceipt,
+};
+
+/// Data of a fetched block, as returned by `EVMRpc::fetch_block`.
+pub type FetchedBlock = (
+    DatabaseEVMBlock,
+    Vec<DatabaseEVMTransaction>,
+    Vec<DatabaseEVMTransactionReceipt>,
+    Vec<DatabaseEVMTransactionLog>,
+    Vec<DatabaseEVMContract>,
+);
+
+/// Fetched data waiting to be stored, with an approximation of the memory it holds. The size
+/// counts the structs and the bytes of their strings, allocator overhead and spare capacity are
+/// ignored, so the real usage is somewhat higher.
+#[derive(Debug, Default)]
+pub struct IndexedDataBuffer {
+    pub blocks: Vec<DatabaseEVMBlock>,
+    pub transactions: Vec<DatabaseEVMTransaction>,
+    pub receipts: Vec<DatabaseEVMTransactionReceipt>,
+    pub logs: Vec<DatabaseEVMTransactionLog>,
+    pub contracts: Vec<DatabaseEVMContract>,
+    pub size: usize,
+}
+
+impl IndexedDataBuffer {
+    pub fn push(&mut self, data: FetchedBlock) {
+        let (block, mut transactions, mut receipts, mut logs, mut contracts) = data;
+
+        self.size += get_block_size(&block);
+        self.size += transactions.iter().map(get_transaction_size).sum::<usize>();
+        self.size += receipts.iter().map(get_receipt_size).sum::<usize>();
+        self.size += logs.iter().map(get_log_size).sum::<usize>();
+        self.size += contracts.iter().map(get_contract_size).sum::<usize>();
+
+        self.blocks.push(block);
+        self.transactions.append(&mut transactions);
+        self.receipts.append(&mut receipts);
+        self.logs.append(&mut logs);
+        self.contracts.append(&mut contracts);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.len() == 0
+    }
+
+    /// Whether the buffer exceeds the memory budget in bytes and must be flushed.
+    pub fn is_full(&self, budget: Option<usize>) -> bool {
+        match budget {
+            Some(budget) => self.size >= budget,
+            None => false,
+        }
+    }
+
+    /// Moves the buffered data out, leaving the buffer empty.
+    pub fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+}
+
+fn get_strings_size<'a>(strings: impl IntoIterator<Item = &'a String>) -> usize {
+    strings.into_iter().map(|string| string.len()).sum()
+}
+
+pub fn get_block_size(block: &DatabaseEVMBlock) -> usize {
+    size_of::<DatabaseEVMBlock>()
+        + get_strings_size([
+            &block.base_fee_per_gas,
+            &block.chain,
+            &block.difficulty,
+            &block.extra_data,
+            &block.gas_limit,
+            &block.gas_used,
+            &block.block_hash,
+            &block.logs_bloom,
+            &block.miner,
+            &block.mix_hash,
+            &block.nonce,
+            &block.parent_hash,
+            &block.receipts_root,
+            &block.sha3_uncles,
+            &block.state_root,
+            &block.timestamp,
+            &block.total_difficulty,
+        ])
+        + block.uncles.len() * size_of::<String>()
+        + get_strings_size(&block.uncles)
+}
+
+pub fn get_transaction_size(transaction: &DatabaseEVMTransaction) -> usize {
+    size_of::<DatabaseEVMTransaction>()
+        + get_strings_size([
+            &transaction.block_hash,
+            &transaction.chain,
+            &transaction.from_address,
+            &transaction.gas,
+            &transaction.gas_price,
+            &transaction.max_priority_fee_per_gas,
+            &transaction.max_fee_per_gas,
+            &transaction.hash,
+            &transaction.input,
+            &transaction.method,
+            &transaction.nonce,
+            &transaction.timestamp,
+            &transaction.to_address,
+            &transaction.value,
+        ])
+}
+
+pub fn get_receipt_size(receipt: &DatabaseEVMTransactionReceipt) -> usize {
+    size_of::<DatabaseEVMTransactionReceipt>()
+        + get_strings_size([
+            &receipt.cumulative_gas_used,
+            &receipt.effective_gas_price,
+            &receipt.gas_used,
+            &receipt.hash,
+            &receipt.status,
+        ])
+        + get_strings_size(receipt.contract_address.iter())
+        + get_strings_size(receipt.revert_reason.iter())
+}
+
+pub fn get_log_size(log: &DatabaseEVMTransactionLog) -> usize {
+    size_of::<DatabaseEVMTransactionLog>()
+        + get_strings_size([&log.address, &log.data, &log.hash])
+        + log.topics.len() * size_of::<Option<String>>()
+        + get_strings_size(log.topics.iter().flatten())
+}
+
+pub fn get_contract_size(contract: &DatabaseEVMContract) -> usize {
+    size_of::<DatabaseEVMContract>()
+        + get_strings_size([
+            &contract.chain,
+            &contract.contract,
+            &contract.creator,
+            &contract.hash,
+        ])
+}
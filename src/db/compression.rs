@@ -0,0 +1,159 @@
+use anyhow::{bail, Result};
+use zstd::bulk::{Compressor, Decompressor};
+
+use super::models::models::{DatabaseEVMTransaction, DatabaseEVMTransactionLog};
+
+pub const INPUT_PAYLOAD: &str = "input";
+
+pub const DATA_PAYLOAD: &str = "data";
+
+pub const PAYLOAD_COMPRESSION_LEVEL: i32 = 3;
+
+/// Size in bytes of the trained dictionaries.
+pub const DICTIONARY_SIZE: usize = 64 * 1024;
+
+/// Payloads needed to train a dictionary, the payloads stored before are kept as text.
+pub const MIN_DICTIONARY_SAMPLES: usize = 1000;
+
+/// Payloads shorter than this in bytes are kept as text, they don't get smaller.
+pub const MIN_COMPRESSED_PAYLOAD: usize = 64;
+
+/// Upper bound of a decompressed payload, far above what fits in a block.
+pub const MAX_PAYLOAD_SIZE: usize = 16 * 1024 * 1024;
+
+/// Compresses the transactions `input` and the logs `data` with zstd and a dictionary trained on
+/// the first payloads of the chain, which shares the selectors, addresses and padding that
+/// dominate calldata. Compressed payloads are stored in the `input_zstd` and `data_zstd` bytea
+/// columns and the text columns are left empty, rows with a null bytea column are plain text.
+#[derive(Debug, Clone)]
+pub struct PayloadCodec {
+    pub dictionary: Vec<u8>,
+}
+
+impl PayloadCodec {
+    pub fn new(dictionary: Vec<u8>) -> Self {
+        Self { dictionary }
+    }
+
+    pub fn train(payloads: &Vec<&String>) -> Result<Self> {
+        let samples: Vec<Vec<u8>> = payloads
+            .iter()
+            .filter_map(|payload| get_payload_bytes(payload))
+            .filter(|bytes| bytes.len() >= MIN_COMPRESSED_PAYLOAD)
+            .collect();
+
+        if samples.len() < MIN_DICTIONARY_SAMPLES {
+            bail!(
+                "Not enough payloads to train a dictionary, {} of {}",
+                samples.len(),
+                MIN_DICTIONARY_SAMPLES
+            );
+        }
+
+        match zstd::dict::from_samples(&samples, DICTIONARY_SIZE) {
+            Ok(dictionary) => Ok(Self::new(dictionary)),
+            Err(err) => bail!("Unable to train the compression dictionary: {}", err),
+        }
+    }
+
+    pub fn compress_transactions(
+        &self,
+        transactions: &mut Vec<DatabaseEVMTransaction>,
+    ) -> Result<()> {
+        let mut compressor =
+            Compressor::with_dictionary(PAYLOAD_COMPRESSION_LEVEL, &self.dictionary)?;
+
+        for transaction in transactions {
+            match compress_payload(&mut compressor, &transaction.input)? {
+                Some(compressed) => {
+                    transaction.input = String::new();
+                    transaction.input_zstd = Some(compressed);
+                }
+                None => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn compress_logs(&self, logs: &mut Vec<DatabaseEVMTransactionLog>) -> Result<()> {
+        let mut compressor =
+            Compressor::with_dictionary(PAYLOAD_COMPRESSION_LEVEL, &self.dictionary)?;
+
+        for log in logs {
+            match compress_payload(&mut compressor, &log.data)? {
+                Some(compressed) => {
+                    log.data = String::new();
+                    log.data_zstd = Some(compressed);
+                }
+                None => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn decompress_transactions(
+        &self,
+        transactions: &mut Vec<DatabaseEVMTransaction>,
+    ) -> Result<()> {
+        let mut decompressor = Decompressor::with_dictionary(&self.dictionary)?;
+
+        for transaction in transactions {
+            match transaction.input_zstd.take() {
+                Some(compressed) => {
+                    transaction.input = decompress_payload(&mut decompressor, &compressed)?;
+                }
+                None => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn decompress_logs(&self, logs: &mut Vec<DatabaseEVMTransactionLog>) -> Result<()> {
+        let mut decompressor = Decompressor::with_dictionary(&self.dictionary)?;
+
+        for log in logs {
+            match log.data_zstd.take() {
+                Some(compressed) => {
+                    log.data = decompress_payload(&mut decompressor, &compressed)?;
+                }
+                None => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Text of a payload read without its model, from the text and bytea columns.
+    pub fn decompress(&self, payload: String, compressed: Option<Vec<u8>>) -> Result<String> {
+        match compressed {
+            Some(compressed) => {
+                let mut decompressor = Decompressor::with_dictionary(&self.dictionary)?;
+
+                decompress_payload(&mut decompressor, &compressed)
+            }
+            None => Ok(payload),
+        }
+    }
+}
+
+fn get_payload_bytes(payload: &String) -> Option<Vec<u8>> {
+    hex::decode(payload.trim_start_matches("0x")).ok()
+}
+
+fn compress_payload(compressor: &mut Compressor, payload: &String) -> Result<Option<Vec<u8>>> {
+    let bytes = match get_payload_bytes(payload) {
+        Some(bytes) if bytes.len() >= MIN_COMPRESSED_PAYLOAD => bytes,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(compressor.compress(&bytes)?))
+}
+
+fn decompress_payload(decompressor: &mut Decompressor, compressed: &Vec<u8>) -> Result<String> {
+    let bytes = decompressor.decompress(compressed, MAX_PAYLOAD_SIZE)?;
+
+    Ok(format!("0x{}", hex::encode(bytes)))
+}
@@ -1,38 +1,109 @@
 use std::cmp::min;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use diesel::dsl::sql;
 use diesel::prelude::*;
-use diesel::{Connection, PgConnection};
+use diesel::sql_types::{Array, BigInt, Bool, Double, Nullable, Text};
+use diesel::{sql_query, upsert::excluded, Connection, PgConnection};
 use diesel_migrations::*;
+use ethers::types::{H160, U256};
 use field_count::FieldCount;
 use log::*;
-use redis::Commands;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use tracing::instrument;
 
+use crate::api::events::IndexedEvent;
 use crate::chains::chains::Chain;
+use crate::parsers::erc20_transfers_parser::{
+    store_transfers, DatabaseEVMErc20Transfer, PARSER_NAME as ERC20_TRANSFERS_PARSER,
+};
 
+use super::compression::{PayloadCodec, DATA_PAYLOAD, INPUT_PAYLOAD};
 use super::models::models::{
     DatabaseChainIndexedState, DatabaseEVMAbi, DatabaseEVMBlock, DatabaseEVMContract,
-    DatabaseEVMMethod, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
-    DatabaseEVMTransactionReceipt,
+    DatabaseEVMLogTransaction, DatabaseEVMMethod, DatabaseEVMOutboxEvent, DatabaseEVMOutboxOffset,
+    DatabaseEVMParseFailure, DatabaseEVMParsedLog, DatabaseEVMStoredOutboxEvent,
+    DatabaseEVMTransaction, DatabaseEVMTransactionLog, DatabaseEVMTransactionReceipt,
 };
+use super::redis_connection::SharedRedisConnection;
 use super::schema::*;
+use super::upsert::{is_inserted, merged, UpsertPolicies, UpsertPolicy};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/");
 
 pub const MAX_DIESEL_PARAM_SIZE: u16 = u16::MAX;
 
+/// Blocks subtracted from the totals at the same time when they are deleted.
+const ROLLBACK_CHUNK_SIZE: usize = 1000;
+
+/// Method of the transactions without calldata.
+pub const EMPTY_METHOD: &str = "0x00000000";
+
+/// Writes always go to the primary at `db_url`. Reads that can tolerate replication lag, like the
+/// parsers fetch queries and the API, use `establish_read_connection` to spread over the
+/// `replica_urls` and fall back to the primary without replicas.
+///
+/// With a `schema` every connection only sees the tables of that schema, which is created and
+/// migrated on its own, so chains indexed into the same database don't share tables.
 #[derive(Debug, Clone)]
 pub struct EVMDatabase {
     pub db_url: String,
+    pub replica_urls: Vec<String>,
+    pub schema: Option<String>,
     pub chain: Chain,
     pub redis: redis::Client,
+    redis_connection: SharedRedisConnection,
+    /// Compress the transactions input and logs data when storing them.
+    pub compress_payloads: bool,
+    /// How `store_data` handles the rows already stored.
+    pub upsert_policies: UpsertPolicies,
+    /// Track the first appearance of each address in `evm_new_addresses`.
+    pub track_new_addresses: bool,
+    /// Write an outbox event for each new address, for the relay sinks.
+    pub publish_new_addresses: bool,
+    next_replica: Arc<AtomicUsize>,
+    codecs: Arc<Mutex<HashMap<String, Arc<PayloadCodec>>>>,
 }
 
 impl EVMDatabase {
-    pub async fn new(db_url: String, redis_url: String, chain: Chain) -> Result<Self> {
+    pub async fn new(
+        db_url: String,
+        replica_urls: Vec<String>,
+        schema: Option<String>,
+        redis_url: String,
+        chain: Chain,
+    ) -> Result<Self> {
         info!("Starting EVM database service");
 
+        let schema = schema.filter(|schema| !schema.is_empty());
+
+        let (db_url, replica_urls) = match &schema {
+            Some(schema) => {
+                info!("Using database schema {}", schema);
+
+                let mut connection =
+                    PgConnection::establish(&db_url).expect("Unable to connect to the database");
+
+                sql_query(format!(
+                    "CREATE SCHEMA IF NOT EXISTS \"{}\"",
+                    get_schema_name(schema)?
+                ))
+                .execute(&mut connection)?;
+
+                (
+                    get_schema_url(&db_url, schema)?,
+                    replica_urls
+                        .iter()
+                        .map(|url| get_schema_url(url, schema))
+                        .collect::<Result<Vec<String>>>()?,
+                )
+            }
+            None => (db_url, replica_urls),
+        };
+
         let mut connection =
             PgConnection::establish(&db_url).expect("Unable to connect to the database");
 
@@ -40,13 +111,45 @@ impl EVMDatabase {
 
         let redis = redis::Client::open(redis_url).expect("Unable to connect with Redis server");
 
+        if !replica_urls.is_empty() {
+            info!("Using {} database read replicas", replica_urls.len());
+        }
+
         Ok(Self {
             db_url,
+            replica_urls,
+            schema,
             chain,
             redis,
+            redis_connection: SharedRedisConnection::default(),
+            compress_payloads: false,
+            upsert_policies: UpsertPolicies::default(),
+            track_new_addresses: false,
+            publish_new_addresses: false,
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            codecs: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    pub fn with_payload_compression(mut self, compress_payloads: bool) -> Self {
+        self.compress_payloads = compress_payloads;
+
+        self
+    }
+
+    pub fn with_upsert_policies(mut self, upsert_policies: UpsertPolicies) -> Self {
+        self.upsert_policies = upsert_policies;
+
+        self
+    }
+
+    pub fn with_new_addresses(mut self, track: bool, publish: bool) -> Self {
+        self.track_new_addresses = track;
+        self.publish_new_addresses = track && publish;
+
+        self
+    }
+
     pub fn establish_connection(&self) -> PgConnection {
         let connection =
             PgConnection::establish(&self.db_url).expect("Unable to connect to the database");
@@ -54,8 +157,23 @@ impl EVMDatabase {
         return connection;
     }
 
+    /// Connects to the replicas in turn. Rows written to the primary can take a moment to show
+    /// up, so parsers may fetch rows they just processed again and rely on idempotent writes.
+    pub fn establish_read_connection(&self) -> PgConnection {
+        if self.replica_urls.is_empty() {
+            return self.establish_connection();
+        }
+
+        let replica = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replica_urls.len();
+
+        let connection = PgConnection::establish(&self.replica_urls[replica])
+            .expect("Unable to connect to the database replica");
+
+        return connection;
+    }
+
     pub async fn get_contracts(&self) -> Result<Vec<DatabaseEVMContract>> {
-        let mut connection = self.establish_connection();
+        let mut connection = self.establish_read_connection();
 
         let contracts = evm_contracts::dsl::evm_contracts
             .select(evm_contracts::all_columns)
@@ -69,135 +187,1737 @@ impl EVMDatabase {
         }
     }
 
-    pub async fn get_indexed_blocks(&self) -> Result<HashSet<i64>> {
-        let mut connection = self.redis.get_connection().unwrap();
+    /// Redis connection shared by the clones of the database.
+    pub async fn get_redis_connection(&self) -> Result<ConnectionManager> {
+        Ok(self.redis_connection.get(&self.redis).await?)
+    }
+
+    pub async fn get_indexed_blocks(&self) -> Result<HashSet<i64>> {
+        let mut connection = self.get_redis_connection().await?;
+
+        let blocks: HashSet<i64> = match connection
+            .get::<String, String>(self.chain.name.to_string())
+            .await
+        {
+            Ok(blocks) => match serde_json::from_str(&blocks) {
+                Ok(deserialized) => deserialized,
+                Err(_) => HashSet::new(),
+            },
+            Err(_) => HashSet::new(),
+        };
+
+        Ok(blocks)
+    }
+
+    /// First indexed block with a timestamp at or after `timestamp`, or the block after the
+    /// last indexed one when there is none.
+    pub async fn get_first_block_at(&self, timestamp: i64) -> Result<i64> {
+        let mut connection = self.establish_read_connection();
+
+        let number = evm_blocks::table
+            .select(evm_blocks::number)
+            .filter(evm_blocks::chain.eq(self.chain.name))
+            .filter(sql::<Bool>("evm_blocks.timestamp::BIGINT >= ").bind::<BigInt, _>(timestamp))
+            .order(evm_blocks::number.asc())
+            .first::<i64>(&mut connection)
+            .optional()?;
+
+        match number {
+            Some(number) => Ok(number),
+            None => {
+                let last_block = evm_blocks::table
+                    .select(diesel::dsl::max(evm_blocks::number))
+                    .filter(evm_blocks::chain.eq(self.chain.name))
+                    .first::<Option<i64>>(&mut connection)?;
+
+                Ok(last_block.map_or(0, |last_block| last_block + 1))
+            }
+        }
+    }
+
+    /// Returns logs with any of the given `topics0` that the parser didn't process yet,
+    /// optionally restricted to the emitting `addresses`.
+    pub async fn get_unparsed_logs(
+        &self,
+        parser: &str,
+        topics0: &Vec<String>,
+        addresses: Option<&Vec<String>>,
+        limit: i64,
+    ) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut connection = self.establish_read_connection();
+
+        let mut logs = sql_query(
+            "SELECT l.* FROM evm_transactions_logs l \
+            WHERE l.topics[1] = ANY($1) \
+            AND ($2::TEXT[] IS NULL OR l.address = ANY($2)) \
+            AND NOT EXISTS (SELECT 1 FROM evm_parsed_logs p \
+            WHERE p.parser = $3 AND p.hash = l.hash AND p.log_index = l.log_index) \
+            LIMIT $4",
+        )
+        .bind::<Array<Text>, _>(topics0)
+        .bind::<Nullable<Array<Text>>, _>(addresses)
+        .bind::<Text, _>(parser)
+        .bind::<BigInt, _>(limit)
+        .load::<DatabaseEVMTransactionLog>(&mut connection)?;
+
+        self.decompress_logs(&mut logs)?;
+
+        Ok(logs)
+    }
+
+    pub async fn store_parsed_logs(
+        &self,
+        parser: &str,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let keys: Vec<(String, i64)> = logs
+            .iter()
+            .map(|log| (log.hash.clone(), log.log_index))
+            .collect();
+
+        self.store_parsed_keys(parser, &keys).await
+    }
+
+    /// Marks the `(hash, log_index)` keys as processed by the parser, parsers working on derived
+    /// tables keyed by log (like the dex swaps) use it to track their progress.
+    pub async fn store_parsed_keys(&self, parser: &str, keys: &Vec<(String, i64)>) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        let parsed_logs: Vec<DatabaseEVMParsedLog> = keys
+            .iter()
+            .map(|(hash, log_index)| DatabaseEVMParsedLog {
+                parser: parser.to_string(),
+                hash: hash.clone(),
+                log_index: *log_index,
+            })
+            .collect();
+
+        let chunks = get_chunks(parsed_logs.len(), DatabaseEVMParsedLog::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_parsed_logs::dsl::evm_parsed_logs)
+                .values(&parsed_logs[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store parsed logs into database");
+        }
+
+        Ok(())
+    }
+
+    /// Quarantines the logs the parser matched but couldn't decode, with the reason, so they
+    /// can be inspected instead of being skipped silently. A log failing again keeps the last
+    /// reason.
+    pub async fn store_parse_failures(
+        &self,
+        failures: &Vec<DatabaseEVMParseFailure>,
+    ) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        let chunks = get_chunks(failures.len(), DatabaseEVMParseFailure::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_parse_failures::dsl::evm_parse_failures)
+                .values(&failures[start..end])
+                .on_conflict((
+                    evm_parse_failures::parser,
+                    evm_parse_failures::hash,
+                    evm_parse_failures::log_index,
+                ))
+                .do_update()
+                .set(evm_parse_failures::reason.eq(excluded(evm_parse_failures::reason)))
+                .execute(&mut connection)
+                .expect("Unable to store parse failures into database");
+        }
+
+        if failures.len() > 0 {
+            warn!("Quarantined {} logs that failed to parse.", failures.len());
+        }
+
+        Ok(())
+    }
+
+    /// Releases the logs quarantined by the `parser`, or by every parser without it, so the
+    /// parsers fetch and decode them again, e.g. after fixing a decoding bug. Logs failing again
+    /// go back to the quarantine. Returns the amount of released logs.
+    pub async fn redrive_parse_failures(&self, parser: Option<&str>) -> Result<usize> {
+        let mut connection = self.establish_connection();
+
+        let released = connection.transaction::<usize, diesel::result::Error, _>(|connection| {
+            let keys = (
+                evm_parse_failures::parser,
+                evm_parse_failures::hash,
+                evm_parse_failures::log_index,
+            );
+
+            let failures = match parser {
+                Some(parser) => diesel::delete(
+                    evm_parse_failures::table.filter(evm_parse_failures::parser.eq(parser)),
+                )
+                .returning(keys)
+                .get_results::<(String, String, i64)>(connection)?,
+                None => diesel::delete(evm_parse_failures::table)
+                    .returning(keys)
+                    .get_results::<(String, String, i64)>(connection)?,
+            };
+
+            for (failure_parser, hash, log_index) in failures.iter() {
+                // The erc20 transfers parser tracks its progress on the logs themselves.
+                if failure_parser == ERC20_TRANSFERS_PARSER {
+                    diesel::update(
+                        evm_transactions_logs::table
+                            .filter(evm_transactions_logs::hash.eq(hash))
+                            .filter(evm_transactions_logs::log_index.eq(log_index)),
+                    )
+                    .set(evm_transactions_logs::erc20_transfers_parsed.eq(false))
+                    .execute(connection)?;
+                } else {
+                    diesel::delete(
+                        evm_parsed_logs::table
+                            .filter(evm_parsed_logs::parser.eq(failure_parser))
+                            .filter(evm_parsed_logs::hash.eq(hash))
+                            .filter(evm_parsed_logs::log_index.eq(log_index)),
+                    )
+                    .execute(connection)?;
+                }
+            }
+
+            Ok(failures.len())
+        });
+
+        match released {
+            Ok(released) => Ok(released),
+            Err(err) => bail!("Unable to redrive the parse failures: {}", err),
+        }
+    }
+
+    /// Resets the progress of the `parser` on the logs of the chain between two blocks, so it
+    /// parses them again. Backfilled logs are located through the log transactions. Returns the
+    /// amount of flushed logs.
+    pub async fn flush_parser_cursors(
+        &self,
+        parser: &str,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<usize> {
+        let mut connection = self.establish_connection();
+
+        // The erc20 transfers parser tracks its progress on the logs themselves.
+        let flushed = match parser == ERC20_TRANSFERS_PARSER {
+            true => sql_query(format!(
+                "UPDATE evm_transactions_logs SET erc20_transfers_parsed = false \
+                WHERE erc20_transfers_parsed AND hash IN ({})",
+                CHAIN_RANGE_HASHES
+            ))
+            .bind::<Text, _>(self.chain.name)
+            .bind::<BigInt, _>(from_block)
+            .bind::<BigInt, _>(to_block)
+            .execute(&mut connection),
+            false => sql_query(format!(
+                "DELETE FROM evm_parsed_logs WHERE parser = $4 AND hash IN ({})",
+                CHAIN_RANGE_HASHES
+            ))
+            .bind::<Text, _>(self.chain.name)
+            .bind::<BigInt, _>(from_block)
+            .bind::<BigInt, _>(to_block)
+            .bind::<Text, _>(parser)
+            .execute(&mut connection),
+        };
+
+        match flushed {
+            Ok(flushed) => {
+                info!(
+                    "Flushed the progress of parser {} on {} logs.",
+                    parser, flushed
+                );
+
+                Ok(flushed)
+            }
+            Err(err) => bail!("Unable to flush the parser cursors: {}", err),
+        }
+    }
+
+    /// Logs don't store the chain, it is resolved through their transaction.
+    pub async fn get_transactions_chains(
+        &self,
+        hashes: &Vec<String>,
+    ) -> Result<HashMap<String, String>> {
+        let mut connection = self.establish_connection();
+
+        let mut chains: HashMap<String, String> = evm_transactions::table
+            .select((evm_transactions::hash, evm_transactions::chain))
+            .filter(evm_transactions::hash.eq_any(hashes))
+            .load::<(String, String)>(&mut connection)?
+            .into_iter()
+            .collect();
+
+        // Logs backfilled below the start block only have their transaction location.
+        let missing: Vec<&String> = hashes
+            .iter()
+            .filter(|hash| !chains.contains_key(*hash))
+            .collect();
+
+        if missing.len() > 0 {
+            let log_chains = evm_log_transactions::table
+                .select((evm_log_transactions::hash, evm_log_transactions::chain))
+                .filter(evm_log_transactions::hash.eq_any(missing))
+                .load::<(String, String)>(&mut connection)?;
+
+            chains.extend(log_chains);
+        }
+
+        Ok(chains)
+    }
+
+    pub async fn get_transactions_blocks(
+        &self,
+        hashes: &Vec<String>,
+    ) -> Result<HashMap<String, i64>> {
+        let mut connection = self.establish_connection();
+
+        let mut blocks: HashMap<String, i64> = evm_transactions::table
+            .select((evm_transactions::hash, evm_transactions::block_number))
+            .filter(evm_transactions::hash.eq_any(hashes))
+            .load::<(String, i64)>(&mut connection)?
+            .into_iter()
+            .collect();
+
+        let missing: Vec<&String> = hashes
+            .iter()
+            .filter(|hash| !blocks.contains_key(*hash))
+            .collect();
+
+        if missing.len() > 0 {
+            let log_blocks = evm_log_transactions::table
+                .select((
+                    evm_log_transactions::hash,
+                    evm_log_transactions::block_number,
+                ))
+                .filter(evm_log_transactions::hash.eq_any(missing))
+                .load::<(String, i64)>(&mut connection)?;
+
+            blocks.extend(log_blocks);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Logs not parsed for transfers and transfers not parsed for tokens. Both are full counts,
+    /// so they run on a replica and callers should sample them sparingly.
+    pub async fn get_parsers_backlog(&self) -> Result<(i64, i64)> {
+        let mut connection = self.establish_read_connection();
+
+        let erc20_transfers_backlog = evm_transactions_logs::table
+            .filter(
+                evm_transactions_logs::erc20_transfers_parsed
+                    .is_null()
+                    .or(evm_transactions_logs::erc20_transfers_parsed.eq(false)),
+            )
+            .count()
+            .get_result::<i64>(&mut connection)?;
+
+        let erc20_tokens_backlog = evm_erc20_transfers::table
+            .filter(
+                evm_erc20_transfers::erc20_tokens_parced
+                    .is_null()
+                    .or(evm_erc20_transfers::erc20_tokens_parced.eq(false)),
+            )
+            .count()
+            .get_result::<i64>(&mut connection)?;
+
+        Ok((erc20_transfers_backlog, erc20_tokens_backlog))
+    }
+
+    #[instrument(skip_all, fields(chain = self.chain.name, blocks = blocks.len()))]
+    pub async fn store_data(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+        outbox: &Vec<DatabaseEVMOutboxEvent>,
+        erc20_transfers: &Vec<DatabaseEVMErc20Transfer>,
+    ) {
+        self.store_data_replacing(
+            &Vec::new(),
+            &Vec::new(),
+            blocks,
+            transactions,
+            receipts,
+            logs,
+            contracts,
+            outbox,
+            erc20_transfers,
+        );
+    }
+
+    /// Stores the new heads, deleting the blocks stored before at their numbers with another
+    /// hash in the same transaction, so a reorged block isn't served next to the one that
+    /// replaced it.
+    #[instrument(skip_all, fields(chain = self.chain.name, blocks = blocks.len()))]
+    pub async fn store_head_data(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+        outbox: &Vec<DatabaseEVMOutboxEvent>,
+        erc20_transfers: &Vec<DatabaseEVMErc20Transfer>,
+    ) {
+        let canonical_blocks = blocks
+            .iter()
+            .map(|block| (block.number, block.block_hash.clone()))
+            .collect();
+
+        self.store_data_replacing(
+            &Vec::new(),
+            &canonical_blocks,
+            blocks,
+            transactions,
+            receipts,
+            logs,
+            contracts,
+            outbox,
+            erc20_transfers,
+        );
+    }
+
+    /// Replaces the stored rows of the blocks with the given data, deleting them in the same
+    /// transaction, so the blocks are never missing from the database.
+    pub async fn replace_data(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) {
+        let numbers = blocks.iter().map(|block| block.number).collect();
+
+        self.store_data_replacing(
+            &numbers,
+            &Vec::new(),
+            blocks,
+            transactions,
+            receipts,
+            logs,
+            contracts,
+            &Vec::new(),
+            &Vec::new(),
+        );
+    }
+
+    fn store_data_replacing(
+        &self,
+        replaced_blocks: &Vec<i64>,
+        canonical_blocks: &Vec<(i64, String)>,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+        outbox: &Vec<DatabaseEVMOutboxEvent>,
+        erc20_transfers: &Vec<DatabaseEVMErc20Transfer>,
+    ) {
+        let compressed_transactions;
+        let compressed_logs;
+
+        let (transactions, logs) = match self.compress_payloads {
+            true => {
+                (compressed_transactions, compressed_logs) =
+                    self.get_compressed_payloads(transactions, logs);
+
+                (&compressed_transactions, &compressed_logs)
+            }
+            false => (transactions, logs),
+        };
+
+        let mut connection = self.establish_connection();
+
+        // Everything is stored in a single transaction so the outbox events are only visible
+        // to the relay once the indexed data is committed.
+        connection
+            .transaction::<_, diesel::result::Error, _>(|connection| {
+                if replaced_blocks.len() > 0 {
+                    self.delete_blocks_rows(connection, replaced_blocks)?;
+                }
+
+                if canonical_blocks.len() > 0 {
+                    self.delete_stale_rows(connection, canonical_blocks)?;
+                }
+
+                let mut new_contracts = HashSet::new();
+
+                if contracts.len() > 0 {
+                    new_contracts = self.store_contracts(connection, &contracts)?;
+                }
+
+                if transactions.len() > 0 {
+                    self.store_transactions(connection, &transactions)?;
+                }
+
+                // Transactions are counted once, even when they are deleted and stored again
+                // after a reorg or a reset, so indexing a block twice doesn't count them twice.
+                let counted_transactions =
+                    self.store_aggregated_transactions(connection, &transactions)?;
+
+                let address_stats = get_address_stats(
+                    transactions,
+                    receipts,
+                    contracts,
+                    &counted_transactions,
+                    &new_contracts,
+                );
+
+                if address_stats.len() > 0 {
+                    self.store_address_stats(connection, &address_stats)?;
+                }
+
+                let contract_calls = get_contract_calls(transactions, &counted_transactions);
+
+                if contract_calls.len() > 0 {
+                    self.store_contract_activity(connection, &contract_calls)?;
+                }
+
+                if self.track_new_addresses {
+                    let new_addresses = get_new_addresses(transactions, contracts);
+
+                    if new_addresses.len() > 0 {
+                        self.store_new_addresses(connection, &new_addresses)?;
+                    }
+                }
+
+                if receipts.len() > 0 {
+                    self.store_transactions_receipts(connection, &receipts)?;
+                }
+
+                let block_fees = get_block_fees(self.chain.name, blocks, transactions, receipts);
+
+                if block_fees.len() > 0 {
+                    self.store_block_fees(connection, &block_fees)?;
+                }
+
+                if logs.len() > 0 {
+                    self.store_transactions_logs(connection, &logs)?;
+                }
+
+                // Transfers parsed inline are committed with their logs, which are stored
+                // already marked as parsed.
+                if erc20_transfers.len() > 0 {
+                    store_transfers(connection, erc20_transfers)?;
+                }
+
+                if outbox.len() > 0 {
+                    self.store_outbox_events(connection, &outbox)?;
+                }
+
+                if blocks.len() > 0 {
+                    self.store_blocks(connection, &blocks)?;
+                }
+
+                // Blocks are counted once for the same reason as the transactions above.
+                let counted_blocks = self.store_aggregated_blocks(connection, &blocks)?;
+
+                let producer_stats = get_producer_stats(blocks, &block_fees, &counted_blocks);
+
+                if producer_stats.len() > 0 {
+                    self.store_producer_stats(connection, &producer_stats)?;
+                }
+
+                Ok(())
+            })
+            .expect("Unable to store data into database");
+
+        info!(
+            "Inserted: blocks ({}) transactions ({}) receipts ({}) logs ({}) contracts ({}) outbox events ({}) for chain {}",
+            blocks.len(),
+            transactions.len(),
+            receipts.len(),
+            logs.len(),
+            contracts.len(),
+            outbox.len(),
+            self.chain.name.clone()
+        );
+    }
+
+    /// Stores the logs fetched by the logs backfill with the location of their transactions,
+    /// and the transfers parsed inline, in a single transaction.
+    #[instrument(skip_all, fields(chain = self.chain.name, logs = logs.len()))]
+    pub async fn store_logs_backfill(
+        &self,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        log_transactions: &Vec<DatabaseEVMLogTransaction>,
+        erc20_transfers: &Vec<DatabaseEVMErc20Transfer>,
+    ) {
+        let compressed_logs;
+
+        let logs = match self.compress_payloads {
+            true => {
+                (_, compressed_logs) = self.get_compressed_payloads(&Vec::new(), logs);
+
+                &compressed_logs
+            }
+            false => logs,
+        };
+
+        let mut connection = self.establish_connection();
+
+        connection
+            .transaction::<_, diesel::result::Error, _>(|connection| {
+                if log_transactions.len() > 0 {
+                    self.store_log_transactions(connection, &log_transactions)?;
+                }
+
+                if logs.len() > 0 {
+                    self.store_transactions_logs(connection, &logs)?;
+                }
+
+                if erc20_transfers.len() > 0 {
+                    store_transfers(connection, erc20_transfers)?;
+                }
+
+                Ok(())
+            })
+            .expect("Unable to store backfilled logs into database");
+
+        info!(
+            "Inserted: backfilled logs ({}) transactions ({}) for chain {}",
+            logs.len(),
+            log_transactions.len(),
+            self.chain.name.clone()
+        );
+    }
+
+    fn store_log_transactions(
+        &self,
+        connection: &mut PgConnection,
+        log_transactions: &Vec<DatabaseEVMLogTransaction>,
+    ) -> QueryResult<()> {
+        let chunks = get_chunks(
+            log_transactions.len(),
+            DatabaseEVMLogTransaction::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_log_transactions::dsl::evm_log_transactions)
+                .values(&log_transactions[start..end])
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies of the transactions and logs with their payloads compressed. Payloads are kept as
+    /// text until there are enough of them to train the dictionaries, or when compressing fails.
+    fn get_compressed_payloads(
+        &self,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> (Vec<DatabaseEVMTransaction>, Vec<DatabaseEVMTransactionLog>) {
+        let mut transactions = transactions.clone();
+        let mut logs = logs.clone();
+
+        let inputs = transactions
+            .iter()
+            .map(|transaction| &transaction.input)
+            .collect();
+
+        match self.get_codec(INPUT_PAYLOAD, Some(inputs)) {
+            Ok(Some(codec)) => match codec.compress_transactions(&mut transactions) {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to compress the transactions input: {}", err),
+            },
+            Ok(None) => (),
+            Err(err) => warn!("Unable to get the transactions input dictionary: {}", err),
+        }
+
+        let data = logs.iter().map(|log| &log.data).collect();
+
+        match self.get_codec(DATA_PAYLOAD, Some(data)) {
+            Ok(Some(codec)) => match codec.compress_logs(&mut logs) {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to compress the logs data: {}", err),
+            },
+            Ok(None) => (),
+            Err(err) => warn!("Unable to get the logs data dictionary: {}", err),
+        }
+
+        (transactions, logs)
+    }
+
+    /// Dictionary of the chain payloads, trained from the `samples` and stored when there isn't
+    /// one yet. Concurrent writers keep the first stored dictionary.
+    fn get_codec(
+        &self,
+        payload: &str,
+        samples: Option<Vec<&String>>,
+    ) -> Result<Option<Arc<PayloadCodec>>> {
+        match self.codecs.lock().unwrap().get(payload) {
+            Some(codec) => return Ok(Some(codec.clone())),
+            None => (),
+        }
+
+        let mut connection = self.establish_connection();
+
+        let mut dictionary = evm_compression_dictionaries::table
+            .select(evm_compression_dictionaries::dictionary)
+            .filter(evm_compression_dictionaries::chain.eq(self.chain.name))
+            .filter(evm_compression_dictionaries::payload.eq(payload))
+            .first::<Vec<u8>>(&mut connection)
+            .optional()?;
+
+        match (&dictionary, samples) {
+            (None, Some(samples)) => {
+                let codec = match PayloadCodec::train(&samples) {
+                    Ok(codec) => codec,
+                    Err(err) => {
+                        debug!("Keeping the {} payloads as text: {}", payload, err);
+
+                        return Ok(None);
+                    }
+                };
+
+                diesel::insert_into(evm_compression_dictionaries::table)
+                    .values((
+                        evm_compression_dictionaries::chain.eq(self.chain.name),
+                        evm_compression_dictionaries::payload.eq(payload),
+                        evm_compression_dictionaries::dictionary.eq(&codec.dictionary),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(&mut connection)?;
+
+                dictionary = evm_compression_dictionaries::table
+                    .select(evm_compression_dictionaries::dictionary)
+                    .filter(evm_compression_dictionaries::chain.eq(self.chain.name))
+                    .filter(evm_compression_dictionaries::payload.eq(payload))
+                    .first::<Vec<u8>>(&mut connection)
+                    .optional()?;
+
+                info!(
+                    "Compressing the {} payloads of chain {}.",
+                    payload, self.chain.name
+                );
+            }
+            _ => (),
+        }
+
+        match dictionary {
+            Some(dictionary) => {
+                let codec = Arc::new(PayloadCodec::new(dictionary));
+
+                self.codecs
+                    .lock()
+                    .unwrap()
+                    .insert(payload.to_string(), codec.clone());
+
+                Ok(Some(codec))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_stored_codec(&self, payload: &str) -> Result<Arc<PayloadCodec>> {
+        match self.get_codec(payload, None)? {
+            Some(codec) => Ok(codec),
+            None => bail!(
+                "Missing the compression dictionary of the {} payloads",
+                payload
+            ),
+        }
+    }
+
+    /// Restores the `input` of the transactions stored compressed.
+    pub fn decompress_transactions(
+        &self,
+        transactions: &mut Vec<DatabaseEVMTransaction>,
+    ) -> Result<()> {
+        if transactions
+            .iter()
+            .all(|transaction| transaction.input_zstd.is_none())
+        {
+            return Ok(());
+        }
+
+        self.get_stored_codec(INPUT_PAYLOAD)?
+            .decompress_transactions(transactions)
+    }
+
+    /// Restores the `data` of the logs stored compressed.
+    pub fn decompress_logs(&self, logs: &mut Vec<DatabaseEVMTransactionLog>) -> Result<()> {
+        if logs.iter().all(|log| log.data_zstd.is_none()) {
+            return Ok(());
+        }
+
+        self.get_stored_codec(DATA_PAYLOAD)?.decompress_logs(logs)
+    }
+
+    /// Text of a payload selected with its bytea column, e.g. `input` and `input_zstd`.
+    pub fn decompress_payload(
+        &self,
+        payload: &str,
+        text: String,
+        compressed: Option<Vec<u8>>,
+    ) -> Result<String> {
+        match compressed {
+            Some(compressed) => self
+                .get_stored_codec(payload)?
+                .decompress(text, Some(compressed)),
+            None => Ok(text),
+        }
+    }
+
+    /// Returns the hashes of the blocks that were not stored before.
+    fn store_blocks(
+        &self,
+        connection: &mut PgConnection,
+        blocks: &Vec<DatabaseEVMBlock>,
+    ) -> QueryResult<HashSet<String>> {
+        let chunks = get_chunks(blocks.len(), DatabaseEVMBlock::field_count());
+
+        let mut inserted = HashSet::new();
+
+        for (start, end) in chunks {
+            let query =
+                diesel::insert_into(evm_blocks::dsl::evm_blocks).values(&blocks[start..end]);
+
+            let rows = match self.upsert_policies.blocks {
+                UpsertPolicy::Ignore => query
+                    .on_conflict_do_nothing()
+                    .returning((evm_blocks::block_hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+                UpsertPolicy::Replace | UpsertPolicy::Merge => query
+                    .on_conflict(evm_blocks::block_hash)
+                    .do_update()
+                    .set((
+                        evm_blocks::base_fee_per_gas.eq(excluded(evm_blocks::base_fee_per_gas)),
+                        evm_blocks::chain.eq(excluded(evm_blocks::chain)),
+                        evm_blocks::difficulty.eq(excluded(evm_blocks::difficulty)),
+                        evm_blocks::extra_data.eq(excluded(evm_blocks::extra_data)),
+                        evm_blocks::gas_limit.eq(excluded(evm_blocks::gas_limit)),
+                        evm_blocks::gas_used.eq(excluded(evm_blocks::gas_used)),
+                        evm_blocks::logs_bloom.eq(excluded(evm_blocks::logs_bloom)),
+                        evm_blocks::miner.eq(excluded(evm_blocks::miner)),
+                        evm_blocks::mix_hash.eq(excluded(evm_blocks::mix_hash)),
+                        evm_blocks::nonce.eq(excluded(evm_blocks::nonce)),
+                        evm_blocks::number.eq(excluded(evm_blocks::number)),
+                        evm_blocks::parent_hash.eq(excluded(evm_blocks::parent_hash)),
+                        evm_blocks::receipts_root.eq(excluded(evm_blocks::receipts_root)),
+                        evm_blocks::sha3_uncles.eq(excluded(evm_blocks::sha3_uncles)),
+                        evm_blocks::size.eq(excluded(evm_blocks::size)),
+                        evm_blocks::state_root.eq(excluded(evm_blocks::state_root)),
+                        evm_blocks::timestamp.eq(excluded(evm_blocks::timestamp)),
+                        evm_blocks::total_difficulty.eq(excluded(evm_blocks::total_difficulty)),
+                        evm_blocks::transactions.eq(excluded(evm_blocks::transactions)),
+                        evm_blocks::uncles.eq(excluded(evm_blocks::uncles)),
+                    ))
+                    .returning((evm_blocks::block_hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+            };
+
+            inserted.extend(get_inserted_keys(rows));
+        }
+
+        Ok(inserted)
+    }
+
+    /// Returns the hashes of the transactions that were not stored before.
+    fn store_transactions(
+        &self,
+        connection: &mut PgConnection,
+        transactions: &Vec<DatabaseEVMTransaction>,
+    ) -> QueryResult<HashSet<String>> {
+        let chunks = get_chunks(transactions.len(), DatabaseEVMTransaction::field_count());
+
+        let mut inserted = HashSet::new();
+
+        for (start, end) in chunks {
+            let query = diesel::insert_into(evm_transactions::dsl::evm_transactions)
+                .values(&transactions[start..end]);
+
+            let rows = match self.upsert_policies.transactions {
+                UpsertPolicy::Ignore => query
+                    .on_conflict_do_nothing()
+                    .returning((evm_transactions::hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+                UpsertPolicy::Replace => query
+                    .on_conflict(evm_transactions::hash)
+                    .do_update()
+                    .set((
+                        evm_transactions::block_hash.eq(excluded(evm_transactions::block_hash)),
+                        evm_transactions::block_number.eq(excluded(evm_transactions::block_number)),
+                        evm_transactions::chain.eq(excluded(evm_transactions::chain)),
+                        evm_transactions::from_address.eq(excluded(evm_transactions::from_address)),
+                        evm_transactions::gas.eq(excluded(evm_transactions::gas)),
+                        evm_transactions::gas_price.eq(excluded(evm_transactions::gas_price)),
+                        evm_transactions::max_priority_fee_per_gas
+                            .eq(excluded(evm_transactions::max_priority_fee_per_gas)),
+                        evm_transactions::max_fee_per_gas
+                            .eq(excluded(evm_transactions::max_fee_per_gas)),
+                        evm_transactions::input.eq(excluded(evm_transactions::input)),
+                        evm_transactions::method.eq(excluded(evm_transactions::method)),
+                        evm_transactions::nonce.eq(excluded(evm_transactions::nonce)),
+                        evm_transactions::timestamp.eq(excluded(evm_transactions::timestamp)),
+                        evm_transactions::to_address.eq(excluded(evm_transactions::to_address)),
+                        evm_transactions::transaction_index
+                            .eq(excluded(evm_transactions::transaction_index)),
+                        evm_transactions::transaction_type
+                            .eq(excluded(evm_transactions::transaction_type)),
+                        evm_transactions::value.eq(excluded(evm_transactions::value)),
+                        evm_transactions::input_zstd.eq(excluded(evm_transactions::input_zstd)),
+                        evm_transactions::gas_efficiency
+                            .eq(excluded(evm_transactions::gas_efficiency)),
+                    ))
+                    .returning((evm_transactions::hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+                UpsertPolicy::Merge => query
+                    .on_conflict(evm_transactions::hash)
+                    .do_update()
+                    .set((
+                        evm_transactions::block_hash.eq(excluded(evm_transactions::block_hash)),
+                        evm_transactions::block_number.eq(excluded(evm_transactions::block_number)),
+                        evm_transactions::chain.eq(excluded(evm_transactions::chain)),
+                        evm_transactions::from_address.eq(excluded(evm_transactions::from_address)),
+                        evm_transactions::gas.eq(excluded(evm_transactions::gas)),
+                        evm_transactions::gas_price.eq(excluded(evm_transactions::gas_price)),
+                        evm_transactions::max_priority_fee_per_gas.eq(merged::<Nullable<Text>>(
+                            "evm_transactions",
+                            "max_priority_fee_per_gas",
+                        )),
+                        evm_transactions::max_fee_per_gas.eq(merged::<Nullable<Text>>(
+                            "evm_transactions",
+                            "max_fee_per_gas",
+                        )),
+                        evm_transactions::input.eq(excluded(evm_transactions::input)),
+                        evm_transactions::method.eq(excluded(evm_transactions::method)),
+                        evm_transactions::nonce.eq(excluded(evm_transactions::nonce)),
+                        evm_transactions::timestamp.eq(excluded(evm_transactions::timestamp)),
+                        evm_transactions::to_address.eq(excluded(evm_transactions::to_address)),
+                        evm_transactions::transaction_index
+                            .eq(excluded(evm_transactions::transaction_index)),
+                        evm_transactions::transaction_type.eq(merged::<Nullable<BigInt>>(
+                            "evm_transactions",
+                            "transaction_type",
+                        )),
+                        evm_transactions::value.eq(excluded(evm_transactions::value)),
+                        // The text and compressed input are written together, a null `input_zstd`
+                        // means the new input is plain text.
+                        evm_transactions::input_zstd.eq(excluded(evm_transactions::input_zstd)),
+                        evm_transactions::gas_efficiency.eq(merged::<Nullable<Double>>(
+                            "evm_transactions",
+                            "gas_efficiency",
+                        )),
+                    ))
+                    .returning((evm_transactions::hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+            };
+
+            inserted.extend(get_inserted_keys(rows));
+        }
+
+        Ok(inserted)
+    }
+
+    fn store_transactions_receipts(
+        &self,
+        connection: &mut PgConnection,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+    ) -> QueryResult<()> {
+        let chunks = get_chunks(receipts.len(), DatabaseEVMTransactionReceipt::field_count());
+
+        for (start, end) in chunks {
+            let query =
+                diesel::insert_into(evm_transactions_receipts::dsl::evm_transactions_receipts)
+                    .values(&receipts[start..end]);
+
+            match self.upsert_policies.receipts {
+                UpsertPolicy::Ignore => query.on_conflict_do_nothing().execute(connection)?,
+                UpsertPolicy::Replace => query
+                    .on_conflict(evm_transactions_receipts::hash)
+                    .do_update()
+                    .set((
+                        evm_transactions_receipts::contract_address
+                            .eq(excluded(evm_transactions_receipts::contract_address)),
+                        evm_transactions_receipts::cumulative_gas_used
+                            .eq(excluded(evm_transactions_receipts::cumulative_gas_used)),
+                        evm_transactions_receipts::effective_gas_price
+                            .eq(excluded(evm_transactions_receipts::effective_gas_price)),
+                        evm_transactions_receipts::gas_used
+                            .eq(excluded(evm_transactions_receipts::gas_used)),
+                        evm_transactions_receipts::status
+                            .eq(excluded(evm_transactions_receipts::status)),
+                        evm_transactions_receipts::revert_reason
+                            .eq(excluded(evm_transactions_receipts::revert_reason)),
+                    ))
+                    .execute(connection)?,
+                UpsertPolicy::Merge => query
+                    .on_conflict(evm_transactions_receipts::hash)
+                    .do_update()
+                    .set((
+                        evm_transactions_receipts::contract_address.eq(merged::<Nullable<Text>>(
+                            "evm_transactions_receipts",
+                            "contract_address",
+                        )),
+                        evm_transactions_receipts::cumulative_gas_used
+                            .eq(excluded(evm_transactions_receipts::cumulative_gas_used)),
+                        evm_transactions_receipts::effective_gas_price
+                            .eq(excluded(evm_transactions_receipts::effective_gas_price)),
+                        evm_transactions_receipts::gas_used
+                            .eq(excluded(evm_transactions_receipts::gas_used)),
+                        evm_transactions_receipts::status
+                            .eq(excluded(evm_transactions_receipts::status)),
+                        evm_transactions_receipts::revert_reason.eq(merged::<Nullable<Text>>(
+                            "evm_transactions_receipts",
+                            "revert_reason",
+                        )),
+                    ))
+                    .execute(connection)?,
+            };
+        }
+
+        Ok(())
+    }
+
+    fn store_transactions_logs(
+        &self,
+        connection: &mut PgConnection,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> QueryResult<()> {
+        let chunks = get_chunks(logs.len(), DatabaseEVMTransactionLog::field_count());
+
+        for (start, end) in chunks {
+            let query = diesel::insert_into(evm_transactions_logs::dsl::evm_transactions_logs)
+                .values(&logs[start..end]);
+
+            // The payload columns are written together, see `store_transactions`.
+            match self.upsert_policies.logs {
+                UpsertPolicy::Ignore => query.on_conflict_do_nothing().execute(connection)?,
+                UpsertPolicy::Replace | UpsertPolicy::Merge => query
+                    .on_conflict((
+                        evm_transactions_logs::hash,
+                        evm_transactions_logs::log_index,
+                    ))
+                    .do_update()
+                    .set((
+                        evm_transactions_logs::address.eq(excluded(evm_transactions_logs::address)),
+                        evm_transactions_logs::topics.eq(excluded(evm_transactions_logs::topics)),
+                        evm_transactions_logs::data.eq(excluded(evm_transactions_logs::data)),
+                        evm_transactions_logs::removed.eq(excluded(evm_transactions_logs::removed)),
+                        evm_transactions_logs::data_zstd
+                            .eq(excluded(evm_transactions_logs::data_zstd)),
+                        evm_transactions_logs::block_number
+                            .eq(excluded(evm_transactions_logs::block_number)),
+                    ))
+                    .execute(connection)?,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Returns the creation hashes of the contracts that were not stored before.
+    fn store_contracts(
+        &self,
+        connection: &mut PgConnection,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) -> QueryResult<HashSet<String>> {
+        let chunks = get_chunks(contracts.len(), DatabaseEVMContract::field_count());
+
+        let mut inserted = HashSet::new();
+
+        for (start, end) in chunks {
+            let query = diesel::insert_into(evm_contracts::dsl::evm_contracts)
+                .values(&contracts[start..end]);
+
+            let rows = match self.upsert_policies.contracts {
+                UpsertPolicy::Ignore => query
+                    .on_conflict_do_nothing()
+                    .returning((evm_contracts::hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+                UpsertPolicy::Replace | UpsertPolicy::Merge => query
+                    .on_conflict(evm_contracts::hash)
+                    .do_update()
+                    .set((
+                        evm_contracts::block.eq(excluded(evm_contracts::block)),
+                        evm_contracts::chain.eq(excluded(evm_contracts::chain)),
+                        evm_contracts::contract.eq(excluded(evm_contracts::contract)),
+                        evm_contracts::creator.eq(excluded(evm_contracts::creator)),
+                    ))
+                    .returning((evm_contracts::hash, is_inserted()))
+                    .get_results::<(String, bool)>(connection)?,
+            };
+
+            inserted.extend(get_inserted_keys(rows));
+        }
+
+        Ok(inserted)
+    }
+
+    /// Adds the stats of a batch to the stored ones with a single upsert over column arrays.
+    fn store_block_fees(
+        &self,
+        connection: &mut PgConnection,
+        fees: &Vec<BlockFees>,
+    ) -> QueryResult<()> {
+        let chains: Vec<String> = vec![self.chain.name.to_string(); fees.len()];
+
+        let numbers: Vec<i64> = fees.iter().map(|fee| fee.number).collect();
+
+        let block_hashes: Vec<String> = fees.iter().map(|fee| fee.block_hash.clone()).collect();
+
+        let base_fees_per_gas: Vec<String> = fees
+            .iter()
+            .map(|fee| fee.base_fee_per_gas.to_string())
+            .collect();
+
+        let gas_used: Vec<String> = fees.iter().map(|fee| fee.gas_used.to_string()).collect();
+
+        let burned: Vec<String> = fees.iter().map(|fee| fee.burned.to_string()).collect();
+
+        let priority_fees: Vec<String> = fees
+            .iter()
+            .map(|fee| fee.priority_fees.to_string())
+            .collect();
+
+        let block_rewards: Vec<String> = fees
+            .iter()
+            .map(|fee| fee.block_reward.to_string())
+            .collect();
+
+        sql_query(
+            "INSERT INTO evm_block_fees (chain, number, block_hash, base_fee_per_gas, gas_used, \
+            burned, priority_fees, block_reward) \
+            SELECT * FROM UNNEST($1::text[], $2::bigint[], $3::text[], $4::text[]::numeric[], \
+            $5::text[]::numeric[], $6::text[]::numeric[], $7::text[]::numeric[], $8::text[]::numeric[]) \
+            ON CONFLICT (chain, number) DO NOTHING",
+        )
+        .bind::<Array<Text>, _>(chains)
+        .bind::<Array<BigInt>, _>(numbers)
+        .bind::<Array<Text>, _>(block_hashes)
+        .bind::<Array<Text>, _>(base_fees_per_gas)
+        .bind::<Array<Text>, _>(gas_used)
+        .bind::<Array<Text>, _>(burned)
+        .bind::<Array<Text>, _>(priority_fees)
+        .bind::<Array<Text>, _>(block_rewards)
+        .execute(connection)?;
+
+        Ok(())
+    }
+
+    fn store_producer_stats(
+        &self,
+        connection: &mut PgConnection,
+        stats: &Vec<ProducerStats>,
+    ) -> QueryResult<()> {
+        let miners: Vec<String> = stats.iter().map(|stat| stat.miner.clone()).collect();
+
+        let chains: Vec<String> = vec![self.chain.name.to_string(); stats.len()];
+
+        let first_blocks: Vec<i64> = stats.iter().map(|stat| stat.first_block).collect();
+
+        let last_blocks: Vec<i64> = stats.iter().map(|stat| stat.last_block).collect();
+
+        let blocks_produced: Vec<i64> = stats.iter().map(|stat| stat.blocks_produced).collect();
+
+        let empty_blocks: Vec<i64> = stats.iter().map(|stat| stat.empty_blocks).collect();
+
+        let fees_earned: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.fees_earned.to_string())
+            .collect();
+
+        let rewards_earned: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.rewards_earned.to_string())
+            .collect();
+
+        let gas_used: Vec<String> = stats.iter().map(|stat| stat.gas_used.to_string()).collect();
+
+        sql_query(
+            "INSERT INTO evm_producer_stats (miner, chain, first_block, last_block, \
+            blocks_produced, empty_blocks, fees_earned, rewards_earned, gas_used, average_gas_used) \
+            SELECT *, gas_used / blocks_produced FROM UNNEST($1::text[], $2::text[], $3::bigint[], \
+            $4::bigint[], $5::bigint[], $6::bigint[], $7::text[]::numeric[], $8::text[]::numeric[], \
+            $9::text[]::numeric[]) AS s (miner, chain, first_block, last_block, blocks_produced, \
+            empty_blocks, fees_earned, rewards_earned, gas_used) ORDER BY miner \
+            ON CONFLICT (miner, chain) DO UPDATE SET \
+            first_block = LEAST(evm_producer_stats.first_block, EXCLUDED.first_block), \
+            last_block = GREATEST(evm_producer_stats.last_block, EXCLUDED.last_block), \
+            blocks_produced = evm_producer_stats.blocks_produced + EXCLUDED.blocks_produced, \
+            empty_blocks = evm_producer_stats.empty_blocks + EXCLUDED.empty_blocks, \
+            fees_earned = evm_producer_stats.fees_earned + EXCLUDED.fees_earned, \
+            rewards_earned = evm_producer_stats.rewards_earned + EXCLUDED.rewards_earned, \
+            gas_used = evm_producer_stats.gas_used + EXCLUDED.gas_used, \
+            average_gas_used = (evm_producer_stats.gas_used + EXCLUDED.gas_used) \
+            / (evm_producer_stats.blocks_produced + EXCLUDED.blocks_produced)",
+        )
+        .bind::<Array<Text>, _>(miners)
+        .bind::<Array<Text>, _>(chains)
+        .bind::<Array<BigInt>, _>(first_blocks)
+        .bind::<Array<BigInt>, _>(last_blocks)
+        .bind::<Array<BigInt>, _>(blocks_produced)
+        .bind::<Array<BigInt>, _>(empty_blocks)
+        .bind::<Array<Text>, _>(fees_earned)
+        .bind::<Array<Text>, _>(rewards_earned)
+        .bind::<Array<Text>, _>(gas_used)
+        .execute(connection)?;
+
+        Ok(())
+    }
+
+    fn store_address_stats(
+        &self,
+        connection: &mut PgConnection,
+        stats: &Vec<AddressStats>,
+    ) -> QueryResult<()> {
+        let addresses: Vec<String> = stats.iter().map(|stat| stat.address.clone()).collect();
+
+        let chains: Vec<String> = vec![self.chain.name.to_string(); stats.len()];
+
+        let first_seen_blocks: Vec<i64> = stats.iter().map(|stat| stat.first_seen_block).collect();
+
+        let last_active_blocks: Vec<i64> =
+            stats.iter().map(|stat| stat.last_active_block).collect();
+
+        let transactions_sent: Vec<i64> = stats.iter().map(|stat| stat.transactions_sent).collect();
+
+        let transactions_received: Vec<i64> = stats
+            .iter()
+            .map(|stat| stat.transactions_received)
+            .collect();
+
+        let gas_spent: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.gas_spent.to_string())
+            .collect();
+
+        let contracts_deployed: Vec<i64> =
+            stats.iter().map(|stat| stat.contracts_deployed).collect();
+
+        sql_query(
+            "INSERT INTO evm_address_stats (address, chain, first_seen_block, last_active_block, \
+            transactions_sent, transactions_received, gas_spent, contracts_deployed) \
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::bigint[], $4::bigint[], $5::bigint[], \
+            $6::bigint[], $7::text[]::numeric[], $8::bigint[]) ORDER BY 1 \
+            ON CONFLICT (address, chain) DO UPDATE SET \
+            first_seen_block = LEAST(evm_address_stats.first_seen_block, EXCLUDED.first_seen_block), \
+            last_active_block = GREATEST(evm_address_stats.last_active_block, EXCLUDED.last_active_block), \
+            transactions_sent = evm_address_stats.transactions_sent + EXCLUDED.transactions_sent, \
+            transactions_received = evm_address_stats.transactions_received + EXCLUDED.transactions_received, \
+            gas_spent = evm_address_stats.gas_spent + EXCLUDED.gas_spent, \
+            contracts_deployed = evm_address_stats.contracts_deployed + EXCLUDED.contracts_deployed",
+        )
+        .bind::<Array<Text>, _>(addresses)
+        .bind::<Array<Text>, _>(chains)
+        .bind::<Array<BigInt>, _>(first_seen_blocks)
+        .bind::<Array<BigInt>, _>(last_active_blocks)
+        .bind::<Array<BigInt>, _>(transactions_sent)
+        .bind::<Array<BigInt>, _>(transactions_received)
+        .bind::<Array<Text>, _>(gas_spent)
+        .bind::<Array<BigInt>, _>(contracts_deployed)
+        .execute(connection)?;
+
+        Ok(())
+    }
+
+    /// Records the transactions counted in the totals, returns the ones not counted before.
+    fn store_aggregated_transactions(
+        &self,
+        connection: &mut PgConnection,
+        transactions: &Vec<DatabaseEVMTransaction>,
+    ) -> QueryResult<HashSet<String>> {
+        if transactions.len() == 0 {
+            return Ok(HashSet::new());
+        }
+
+        let mut hashes: Vec<String> = transactions
+            .iter()
+            .map(|transaction| transaction.hash.clone())
+            .collect();
+
+        hashes.sort();
+
+        let inserted = sql_query(
+            "INSERT INTO evm_aggregated_transactions (chain, hash) \
+            SELECT $1, hash FROM UNNEST($2::text[]) AS hash ORDER BY hash \
+            ON CONFLICT DO NOTHING RETURNING hash",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(hashes)
+        .load::<AggregatedTransaction>(connection)?;
+
+        Ok(inserted
+            .into_iter()
+            .map(|transaction| transaction.hash)
+            .collect())
+    }
+
+    /// Records the blocks counted by the producer stats and returns the ones not counted before.
+    fn store_aggregated_blocks(
+        &self,
+        connection: &mut PgConnection,
+        blocks: &Vec<DatabaseEVMBlock>,
+    ) -> QueryResult<HashSet<String>> {
+        if blocks.len() == 0 {
+            return Ok(HashSet::new());
+        }
 
-        let blocks: HashSet<i64> =
-            match connection.get::<String, String>(self.chain.name.to_string()) {
-                Ok(blocks) => match serde_json::from_str(&blocks) {
-                    Ok(deserialized) => deserialized,
-                    Err(_) => HashSet::new(),
-                },
-                Err(_) => HashSet::new(),
-            };
+        let mut hashes: Vec<String> = blocks
+            .iter()
+            .map(|block| block.block_hash.clone())
+            .collect();
 
-        Ok(blocks)
+        hashes.sort();
+
+        let inserted = sql_query(
+            "INSERT INTO evm_aggregated_blocks (chain, block_hash) \
+            SELECT $1, block_hash FROM UNNEST($2::text[]) AS block_hash ORDER BY block_hash \
+            ON CONFLICT DO NOTHING RETURNING block_hash",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(hashes)
+        .load::<AggregatedBlock>(connection)?;
+
+        Ok(inserted.into_iter().map(|block| block.block_hash).collect())
     }
 
-    pub async fn store_data(
+    /// Adds the calls to the daily totals of `evm_contract_activity`. The callers are kept per
+    /// contract and day in `evm_contract_callers`, so only the ones not seen that day before are
+    /// added to the unique callers. The rows are written in order so concurrent batches don't
+    /// deadlock.
+    fn store_contract_activity(
         &self,
-        blocks: &Vec<DatabaseEVMBlock>,
-        transactions: &Vec<DatabaseEVMTransaction>,
-        receipts: &Vec<DatabaseEVMTransactionReceipt>,
-        logs: &Vec<DatabaseEVMTransactionLog>,
-        contracts: &Vec<DatabaseEVMContract>,
-    ) {
-        if contracts.len() > 0 {
-            self.store_contracts(&contracts).await.unwrap();
-        }
+        connection: &mut PgConnection,
+        calls: &Vec<ContractCall>,
+    ) -> QueryResult<()> {
+        let mut calls = calls.clone();
+
+        calls.sort_by(|a, b| (&a.contract, a.day, &a.caller).cmp(&(&b.contract, b.day, &b.caller)));
+
+        let contracts: Vec<String> = calls.iter().map(|call| call.contract.clone()).collect();
+
+        let days: Vec<i64> = calls.iter().map(|call| call.day).collect();
+
+        let callers: Vec<String> = calls.iter().map(|call| call.caller.clone()).collect();
+
+        sql_query(
+            "WITH calls AS (SELECT * FROM UNNEST($2::text[], $3::bigint[], $4::text[]) AS c(contract, day, caller)), \
+            new_callers AS (INSERT INTO evm_contract_callers (chain, contract, day, caller) \
+            SELECT DISTINCT $1, contract, day, caller FROM calls ORDER BY 2, 3, 4 ON CONFLICT DO NOTHING RETURNING contract, day), \
+            callers AS (SELECT contract, day, COUNT(*) AS unique_callers FROM new_callers GROUP BY contract, day) \
+            INSERT INTO evm_contract_activity (chain, contract, day, transactions, unique_callers) \
+            SELECT $1, c.contract, c.day, COUNT(*), COALESCE(MAX(n.unique_callers), 0) FROM calls c \
+            LEFT JOIN callers n ON n.contract = c.contract AND n.day = c.day GROUP BY c.contract, c.day \
+            ORDER BY c.contract, c.day \
+            ON CONFLICT (chain, contract, day) DO UPDATE SET \
+            transactions = evm_contract_activity.transactions + EXCLUDED.transactions, \
+            unique_callers = evm_contract_activity.unique_callers + EXCLUDED.unique_callers",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(contracts)
+        .bind::<Array<BigInt>, _>(days)
+        .bind::<Array<Text>, _>(callers)
+        .execute(connection)?;
 
-        if transactions.len() > 0 {
-            self.store_transactions(&transactions).await.unwrap();
+        Ok(())
+    }
+
+    /// Subtracts the blocks about to be deleted, and their transactions, from the address,
+    /// contract activity and producer totals, and forgets that they were counted, so the blocks
+    /// that replace them after a reorg or a reset are counted instead of both. The first and
+    /// last blocks of the totals are kept. Must run before the rows are deleted.
+    fn rollback_aggregates(
+        &self,
+        connection: &mut PgConnection,
+        block_hashes: &Vec<String>,
+    ) -> QueryResult<()> {
+        // Large resets are rolled back in chunks to bound the loaded rows.
+        for chunk in block_hashes.chunks(ROLLBACK_CHUNK_SIZE) {
+            self.rollback_blocks(connection, &chunk.to_vec())?;
         }
 
-        if receipts.len() > 0 {
-            self.store_transactions_receipts(&receipts).await.unwrap();
+        Ok(())
+    }
+
+    fn rollback_blocks(
+        &self,
+        connection: &mut PgConnection,
+        block_hashes: &Vec<String>,
+    ) -> QueryResult<()> {
+        let blocks = evm_blocks::table
+            .select(DatabaseEVMBlock::as_select())
+            .filter(evm_blocks::chain.eq(self.chain.name))
+            .filter(evm_blocks::block_hash.eq_any(block_hashes))
+            .load::<DatabaseEVMBlock>(connection)?;
+
+        let transactions = evm_transactions::table
+            .select(DatabaseEVMTransaction::as_select())
+            .filter(evm_transactions::chain.eq(self.chain.name))
+            .filter(evm_transactions::block_hash.eq_any(block_hashes))
+            .load::<DatabaseEVMTransaction>(connection)?;
+
+        let mut hashes: Vec<String> = transactions
+            .iter()
+            .map(|transaction| transaction.hash.clone())
+            .collect();
+
+        hashes.sort();
+
+        let receipts = evm_transactions_receipts::table
+            .select(DatabaseEVMTransactionReceipt::as_select())
+            .filter(evm_transactions_receipts::hash.eq_any(&hashes))
+            .load::<DatabaseEVMTransactionReceipt>(connection)?;
+
+        let counted_transactions: HashSet<String> = sql_query(
+            "DELETE FROM evm_aggregated_transactions WHERE chain = $1 AND hash = ANY($2) \
+            RETURNING hash",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(&hashes)
+        .load::<AggregatedTransaction>(connection)?
+        .into_iter()
+        .map(|transaction| transaction.hash)
+        .collect();
+
+        let counted_blocks: HashSet<String> = sql_query(
+            "DELETE FROM evm_aggregated_blocks WHERE chain = $1 AND block_hash = ANY($2) \
+            RETURNING block_hash",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(block_hashes)
+        .load::<AggregatedBlock>(connection)?
+        .into_iter()
+        .map(|block| block.block_hash)
+        .collect();
+
+        // Contracts aren't deleted with their blocks, so their deployments stay counted.
+        let address_stats = get_address_stats(
+            &transactions,
+            &receipts,
+            &Vec::new(),
+            &counted_transactions,
+            &HashSet::new(),
+        );
+
+        if address_stats.len() > 0 {
+            self.subtract_address_stats(connection, &address_stats)?;
         }
 
-        if logs.len() > 0 {
-            self.store_transactions_logs(&logs).await.unwrap();
+        let contract_calls = get_contract_calls(&transactions, &counted_transactions);
+
+        if contract_calls.len() > 0 {
+            self.subtract_contract_activity(connection, &contract_calls)?;
         }
 
-        if blocks.len() > 0 {
-            self.store_blocks(&blocks).await.unwrap();
+        let block_fees = get_block_fees(self.chain.name, &blocks, &transactions, &receipts);
+
+        let producer_stats = get_producer_stats(&blocks, &block_fees, &counted_blocks);
+
+        if producer_stats.len() > 0 {
+            self.subtract_producer_stats(connection, &producer_stats)?;
         }
 
-        info!(
-            "Inserted: blocks ({}) transactions ({}) receipts ({}) logs ({}) contracts ({}) for chain {}",
-            blocks.len(),
-            transactions.len(),
-            receipts.len(),
-            logs.len(),
-            contracts.len(),
-            self.chain.name.clone()
-        );
+        Ok(())
     }
 
-    async fn store_blocks(&self, blocks: &Vec<DatabaseEVMBlock>) -> Result<()> {
-        let mut connection = self.establish_connection();
+    /// Locks the rows of the totals in key order before they are updated, so concurrent
+    /// batches don't deadlock.
+    fn lock_totals(
+        &self,
+        connection: &mut PgConnection,
+        table: &str,
+        key: &str,
+        keys: &Vec<String>,
+    ) -> QueryResult<()> {
+        sql_query(format!(
+            "SELECT 1 FROM {} WHERE chain = $1 AND {} = ANY($2) ORDER BY {} FOR UPDATE",
+            table, key, key
+        ))
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(keys)
+        .execute(connection)?;
 
-        diesel::insert_into(evm_blocks::dsl::evm_blocks)
-            .values(blocks)
-            .on_conflict_do_nothing()
-            .execute(&mut connection)
-            .expect("Unable to store blocks into database");
+        Ok(())
+    }
+
+    fn subtract_address_stats(
+        &self,
+        connection: &mut PgConnection,
+        stats: &Vec<AddressStats>,
+    ) -> QueryResult<()> {
+        let addresses: Vec<String> = stats.iter().map(|stat| stat.address.clone()).collect();
+
+        let transactions_sent: Vec<i64> = stats.iter().map(|stat| stat.transactions_sent).collect();
+
+        let transactions_received: Vec<i64> = stats
+            .iter()
+            .map(|stat| stat.transactions_received)
+            .collect();
+
+        let gas_spent: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.gas_spent.to_string())
+            .collect();
+
+        self.lock_totals(connection, "evm_address_stats", "address", &addresses)?;
+
+        sql_query(
+            "UPDATE evm_address_stats s SET \
+            transactions_sent = s.transactions_sent - d.transactions_sent, \
+            transactions_received = s.transactions_received - d.transactions_received, \
+            gas_spent = s.gas_spent - d.gas_spent \
+            FROM UNNEST($2::text[], $3::bigint[], $4::bigint[], $5::text[]::numeric[]) \
+            AS d (address, transactions_sent, transactions_received, gas_spent) \
+            WHERE s.chain = $1 AND s.address = d.address",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(addresses)
+        .bind::<Array<BigInt>, _>(transactions_sent)
+        .bind::<Array<BigInt>, _>(transactions_received)
+        .bind::<Array<Text>, _>(gas_spent)
+        .execute(connection)?;
 
         Ok(())
     }
 
-    async fn store_transactions(&self, transactions: &Vec<DatabaseEVMTransaction>) -> Result<()> {
-        let mut connection = self.establish_connection();
+    fn subtract_producer_stats(
+        &self,
+        connection: &mut PgConnection,
+        stats: &Vec<ProducerStats>,
+    ) -> QueryResult<()> {
+        let miners: Vec<String> = stats.iter().map(|stat| stat.miner.clone()).collect();
+
+        let blocks_produced: Vec<i64> = stats.iter().map(|stat| stat.blocks_produced).collect();
+
+        let empty_blocks: Vec<i64> = stats.iter().map(|stat| stat.empty_blocks).collect();
+
+        let fees_earned: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.fees_earned.to_string())
+            .collect();
+
+        let rewards_earned: Vec<String> = stats
+            .iter()
+            .map(|stat| stat.rewards_earned.to_string())
+            .collect();
+
+        let gas_used: Vec<String> = stats.iter().map(|stat| stat.gas_used.to_string()).collect();
+
+        self.lock_totals(connection, "evm_producer_stats", "miner", &miners)?;
+
+        sql_query(
+            "UPDATE evm_producer_stats s SET \
+            blocks_produced = s.blocks_produced - d.blocks_produced, \
+            empty_blocks = s.empty_blocks - d.empty_blocks, \
+            fees_earned = s.fees_earned - d.fees_earned, \
+            rewards_earned = s.rewards_earned - d.rewards_earned, \
+            gas_used = s.gas_used - d.gas_used, \
+            average_gas_used = COALESCE((s.gas_used - d.gas_used) \
+            / NULLIF(s.blocks_produced - d.blocks_produced, 0), 0) \
+            FROM UNNEST($2::text[], $3::bigint[], $4::bigint[], $5::text[]::numeric[], \
+            $6::text[]::numeric[], $7::text[]::numeric[]) AS d (miner, blocks_produced, \
+            empty_blocks, fees_earned, rewards_earned, gas_used) \
+            WHERE s.chain = $1 AND s.miner = d.miner",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(miners)
+        .bind::<Array<BigInt>, _>(blocks_produced)
+        .bind::<Array<BigInt>, _>(empty_blocks)
+        .bind::<Array<Text>, _>(fees_earned)
+        .bind::<Array<Text>, _>(rewards_earned)
+        .bind::<Array<Text>, _>(gas_used)
+        .execute(connection)?;
 
-        let chunks = get_chunks(transactions.len(), DatabaseEVMTransaction::field_count());
+        Ok(())
+    }
 
-        for (start, end) in chunks {
-            diesel::insert_into(evm_transactions::dsl::evm_transactions)
-                .values(&transactions[start..end])
-                .on_conflict_do_nothing()
-                .execute(&mut connection)
-                .expect("Unable to store transactions into database");
-        }
+    /// Subtracts the calls from the daily totals of `evm_contract_activity`. A caller is only
+    /// removed from the unique callers of a day when none of its other counted transactions
+    /// called the contract that day.
+    fn subtract_contract_activity(
+        &self,
+        connection: &mut PgConnection,
+        calls: &Vec<ContractCall>,
+    ) -> QueryResult<()> {
+        let mut calls = calls.clone();
+
+        calls.sort_by(|a, b| (&a.contract, a.day, &a.caller).cmp(&(&b.contract, b.day, &b.caller)));
+
+        let contracts: Vec<String> = calls.iter().map(|call| call.contract.clone()).collect();
+
+        let days: Vec<i64> = calls.iter().map(|call| call.day).collect();
+
+        let callers: Vec<String> = calls.iter().map(|call| call.caller.clone()).collect();
+
+        sql_query(
+            "SELECT 1 FROM evm_contract_activity WHERE chain = $1 \
+            AND (contract, day) IN (SELECT * FROM UNNEST($2::text[], $3::bigint[])) \
+            ORDER BY contract, day FOR UPDATE",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(&contracts)
+        .bind::<Array<BigInt>, _>(&days)
+        .execute(connection)?;
+
+        sql_query(
+            "WITH calls AS (SELECT * FROM UNNEST($2::text[], $3::bigint[], $4::text[]) AS c(contract, day, caller)), \
+            removed_callers AS (DELETE FROM evm_contract_callers cc USING (SELECT DISTINCT * FROM calls) c \
+            WHERE cc.chain = $1 AND cc.contract = c.contract AND cc.day = c.day AND cc.caller = c.caller \
+            AND NOT EXISTS (SELECT 1 FROM evm_transactions t INNER JOIN evm_aggregated_transactions a \
+            ON a.chain = t.chain AND a.hash = t.hash WHERE t.chain = $1 AND t.to_address = c.contract \
+            AND t.from_address = c.caller AND t.method <> $5 \
+            AND t.timestamp::BIGINT >= c.day AND t.timestamp::BIGINT < c.day + 86400) \
+            RETURNING cc.contract, cc.day), \
+            callers AS (SELECT contract, day, COUNT(*) AS unique_callers FROM removed_callers GROUP BY contract, day), \
+            totals AS (SELECT contract, day, COUNT(*) AS transactions FROM calls GROUP BY contract, day) \
+            UPDATE evm_contract_activity a SET \
+            transactions = a.transactions - t.transactions, \
+            unique_callers = a.unique_callers - COALESCE(n.unique_callers, 0) \
+            FROM totals t LEFT JOIN callers n ON n.contract = t.contract AND n.day = t.day \
+            WHERE a.chain = $1 AND a.contract = t.contract AND a.day = t.day",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<Text>, _>(contracts)
+        .bind::<Array<BigInt>, _>(days)
+        .bind::<Array<Text>, _>(callers)
+        .bind::<Text, _>(EMPTY_METHOD)
+        .execute(connection)?;
 
         Ok(())
     }
 
-    async fn store_transactions_receipts(
+    /// Stores the addresses not seen before, or seen first at a later block when backfilling,
+    /// and writes an outbox event for the ones seen for the first time.
+    fn store_new_addresses(
         &self,
-        receipts: &Vec<DatabaseEVMTransactionReceipt>,
-    ) -> Result<()> {
-        let mut connection = self.establish_connection();
+        connection: &mut PgConnection,
+        new_addresses: &Vec<NewAddress>,
+    ) -> QueryResult<()> {
+        let addresses: Vec<String> = new_addresses
+            .iter()
+            .map(|new_address| new_address.address.clone())
+            .collect();
+
+        let chains: Vec<String> = vec![self.chain.name.to_string(); new_addresses.len()];
+
+        let block_numbers: Vec<i64> = new_addresses
+            .iter()
+            .map(|new_address| new_address.block_number)
+            .collect();
+
+        let hashes: Vec<String> = new_addresses
+            .iter()
+            .map(|new_address| new_address.hash.clone())
+            .collect();
+
+        let roles: Vec<String> = new_addresses
+            .iter()
+            .map(|new_address| new_address.role.to_string())
+            .collect();
+
+        let rows = sql_query(
+            "INSERT INTO evm_new_addresses (address, chain, block_number, hash, role) \
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::bigint[], $4::text[], $5::text[]) \
+            ORDER BY 1 \
+            ON CONFLICT (address, chain) DO UPDATE SET \
+            block_number = EXCLUDED.block_number, hash = EXCLUDED.hash, role = EXCLUDED.role \
+            WHERE EXCLUDED.block_number < evm_new_addresses.block_number \
+            RETURNING address, (xmax = 0) AS inserted",
+        )
+        .bind::<Array<Text>, _>(addresses)
+        .bind::<Array<Text>, _>(chains)
+        .bind::<Array<BigInt>, _>(block_numbers)
+        .bind::<Array<Text>, _>(hashes)
+        .bind::<Array<Text>, _>(roles)
+        .load::<StoredAddress>(connection)?;
+
+        let inserted: HashSet<String> = rows
+            .into_iter()
+            .filter(|row| row.inserted)
+            .map(|row| row.address)
+            .collect();
+
+        if !self.publish_new_addresses || inserted.len() == 0 {
+            return Ok(());
+        }
 
-        let chunks = get_chunks(receipts.len(), DatabaseEVMTransactionReceipt::field_count());
+        let events: Vec<DatabaseEVMOutboxEvent> = new_addresses
+            .iter()
+            .filter(|new_address| inserted.contains(&new_address.address))
+            .map(|new_address| {
+                IndexedEvent::NewAddress {
+                    chain: self.chain.name.to_string(),
+                    address: new_address.address.clone(),
+                    block_number: new_address.block_number,
+                    hash: new_address.hash.clone(),
+                    role: new_address.role.to_string(),
+                }
+                .to_outbox_event(self.chain.name)
+            })
+            .collect();
+
+        self.store_outbox_events(connection, &events)
+    }
+
+    fn store_outbox_events(
+        &self,
+        connection: &mut PgConnection,
+        events: &Vec<DatabaseEVMOutboxEvent>,
+    ) -> QueryResult<()> {
+        let chunks = get_chunks(events.len(), DatabaseEVMOutboxEvent::field_count());
 
         for (start, end) in chunks {
-            diesel::insert_into(evm_transactions_receipts::dsl::evm_transactions_receipts)
-                .values(&receipts[start..end])
-                .on_conflict_do_nothing()
-                .execute(&mut connection)
-                .expect("Unable to store receipts into database");
+            diesel::insert_into(evm_outbox::dsl::evm_outbox)
+                .values(&events[start..end])
+                .execute(connection)?;
         }
 
         Ok(())
     }
 
-    async fn store_transactions_logs(&self, logs: &Vec<DatabaseEVMTransactionLog>) -> Result<()> {
+    pub async fn get_outbox_events(
+        &self,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<DatabaseEVMStoredOutboxEvent>> {
         let mut connection = self.establish_connection();
 
-        let chunks = get_chunks(logs.len(), DatabaseEVMTransactionLog::field_count());
-
-        for (start, end) in chunks {
-            diesel::insert_into(evm_transactions_logs::dsl::evm_transactions_logs)
-                .values(&logs[start..end])
-                .on_conflict_do_nothing()
-                .execute(&mut connection)
-                .expect("Unable to store logs into database");
-        }
+        let events = evm_outbox::table
+            .select(evm_outbox::all_columns)
+            .filter(evm_outbox::id.gt(after_id))
+            .order(evm_outbox::id.asc())
+            .limit(limit)
+            .load::<DatabaseEVMStoredOutboxEvent>(&mut connection)?;
 
-        Ok(())
+        Ok(events)
     }
 
-    async fn store_contracts(&self, contracts: &Vec<DatabaseEVMContract>) -> Result<()> {
+    pub async fn get_outbox_offset(&self, sink: &String) -> Result<i64> {
         let mut connection = self.establish_connection();
 
-        let chunks = get_chunks(contracts.len(), DatabaseEVMContract::field_count());
+        let offset = evm_outbox_offsets::table
+            .select(evm_outbox_offsets::last_id)
+            .filter(evm_outbox_offsets::sink.eq(sink))
+            .first::<i64>(&mut connection)
+            .optional()?;
 
-        for (start, end) in chunks {
-            diesel::insert_into(evm_contracts::dsl::evm_contracts)
-                .values(&contracts[start..end])
-                .on_conflict_do_nothing()
-                .execute(&mut connection)
-                .expect("Unable to store contracts into database");
-        }
+        Ok(offset.unwrap_or(0))
+    }
+
+    pub async fn update_outbox_offset(&self, sink: &String, last_id: i64) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        diesel::insert_into(evm_outbox_offsets::table)
+            .values(&DatabaseEVMOutboxOffset {
+                sink: sink.clone(),
+                last_id,
+            })
+            .on_conflict(evm_outbox_offsets::sink)
+            .do_update()
+            .set(evm_outbox_offsets::last_id.eq(last_id))
+            .execute(&mut connection)?;
 
         Ok(())
     }
@@ -234,14 +1954,108 @@ impl EVMDatabase {
         Ok(())
     }
 
+    pub async fn get_last_fee_history_block(&self) -> Result<Option<i64>> {
+        let mut connection = self.establish_connection();
+
+        let last_block = evm_fee_history::table
+            .select(diesel::dsl::max(evm_fee_history::number))
+            .filter(evm_fee_history::chain.eq(self.chain.name))
+            .first::<Option<i64>>(&mut connection)?;
+
+        Ok(last_block)
+    }
+
+    pub async fn store_fee_history(
+        &self,
+        fee_history: &Vec<FeeHistoryBlock>,
+        percentiles: &Vec<f64>,
+    ) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        let chains: Vec<String> = vec![self.chain.name.to_string(); fee_history.len()];
+
+        let numbers: Vec<i64> = fee_history.iter().map(|block| block.number).collect();
+
+        let base_fees_per_gas: Vec<String> = fee_history
+            .iter()
+            .map(|block| block.base_fee_per_gas.to_string())
+            .collect();
+
+        let gas_used_ratios: Vec<f64> = fee_history
+            .iter()
+            .map(|block| block.gas_used_ratio)
+            .collect();
+
+        // Each block has an array of rewards, which can't be unnested, so they are sent as array
+        // literals.
+        let rewards: Vec<String> = fee_history
+            .iter()
+            .map(|block| {
+                let rewards: Vec<String> = block
+                    .rewards
+                    .iter()
+                    .map(|reward| reward.to_string())
+                    .collect();
+
+                format!("{{{}}}", rewards.join(","))
+            })
+            .collect();
+
+        sql_query(
+            "INSERT INTO evm_fee_history (chain, number, base_fee_per_gas, gas_used_ratio, \
+            reward_percentiles, rewards) \
+            SELECT f.chain, f.number, f.base_fee_per_gas::numeric, f.gas_used_ratio, $5, \
+            f.rewards::numeric[] FROM UNNEST($1::text[], $2::bigint[], $3::text[], $4::float8[], \
+            $6::text[]) AS f (chain, number, base_fee_per_gas, gas_used_ratio, rewards) \
+            ON CONFLICT (chain, number) DO NOTHING",
+        )
+        .bind::<Array<Text>, _>(chains)
+        .bind::<Array<BigInt>, _>(numbers)
+        .bind::<Array<Text>, _>(base_fees_per_gas)
+        .bind::<Array<Double>, _>(gas_used_ratios)
+        .bind::<Array<Double>, _>(percentiles)
+        .bind::<Array<Text>, _>(rewards)
+        .execute(&mut connection)?;
+
+        Ok(())
+    }
+
+    /// Next block of the logs backfill, stored apart from the indexed blocks since the backfill
+    /// moves forward by ranges.
+    pub async fn get_logs_cursor(&self) -> Result<Option<i64>> {
+        let mut connection = self.get_redis_connection().await?;
+
+        let cursor: Option<i64> = match connection
+            .get::<String, i64>(self.get_logs_cursor_key())
+            .await
+        {
+            Ok(cursor) => Some(cursor),
+            Err(_) => None,
+        };
+
+        Ok(cursor)
+    }
+
+    pub async fn store_logs_cursor(&self, cursor: i64) -> Result<()> {
+        let mut connection = self.get_redis_connection().await?;
+
+        let _: () = connection.set(self.get_logs_cursor_key(), cursor).await?;
+
+        Ok(())
+    }
+
+    fn get_logs_cursor_key(&self) -> String {
+        format!("{}-logs", self.chain.name)
+    }
+
     pub async fn store_indexed_blocks(&self, blocks: &HashSet<i64>) -> Result<()> {
-        let mut connection = self.redis.get_connection().unwrap();
+        let mut connection = self.get_redis_connection().await?;
 
         let serialized = serde_json::to_string(blocks).unwrap();
 
         let _: () = connection
             .set(self.chain.name.to_string(), serialized)
-            .unwrap();
+            .await?;
 
         self.update_indexed_blocks_number(&DatabaseChainIndexedState {
             chain: self.chain.name.to_string(),
@@ -270,6 +2084,18 @@ impl EVMDatabase {
         Ok(())
     }
 
+    pub async fn update_sync_lag(&self, sync_lag: i64) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        diesel::update(chains_indexed_state::table)
+            .filter(chains_indexed_state::chain.eq(self.chain.name.to_string()))
+            .set(chains_indexed_state::sync_lag.eq(sync_lag))
+            .execute(&mut connection)
+            .expect("Unable to update sync lag");
+
+        Ok(())
+    }
+
     pub async fn update_contracts(&self, contracts: &Vec<DatabaseEVMContract>) -> Result<()> {
         let mut connection = self.establish_connection();
 
@@ -288,15 +2114,609 @@ impl EVMDatabase {
         Ok(())
     }
 
+    /// Removes the blocks stored with a different hash than the canonical one, e.g. after a
+    /// reorg, together with their transactions, receipts, logs and fees, and subtracts them
+    /// from the totals.
+    pub async fn delete_stale_blocks(&self, blocks: &Vec<(i64, String)>) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            self.delete_stale_rows(connection, blocks)
+        })?;
+
+        Ok(())
+    }
+
+    fn delete_stale_rows(
+        &self,
+        connection: &mut PgConnection,
+        blocks: &Vec<(i64, String)>,
+    ) -> Result<(), diesel::result::Error> {
+        let statements = [
+            "DELETE FROM evm_transactions_logs WHERE hash IN (SELECT hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number = $2 AND block_hash <> $3)",
+            "DELETE FROM evm_transactions_receipts WHERE hash IN (SELECT hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number = $2 AND block_hash <> $3)",
+            "DELETE FROM evm_transactions WHERE chain = $1 AND block_number = $2 AND block_hash <> $3",
+            "DELETE FROM evm_blocks WHERE chain = $1 AND number = $2 AND block_hash <> $3",
+            "DELETE FROM evm_block_fees WHERE chain = $1 AND number = $2 AND block_hash <> $3",
+        ];
+
+        for (block_number, block_hash) in blocks {
+            let stale_hashes = sql_query(
+                "SELECT block_hash FROM evm_blocks \
+                WHERE chain = $1 AND number = $2 AND block_hash <> $3 \
+                UNION SELECT block_hash FROM evm_transactions \
+                WHERE chain = $1 AND block_number = $2 AND block_hash <> $3",
+            )
+            .bind::<Text, _>(self.chain.name)
+            .bind::<BigInt, _>(block_number)
+            .bind::<Text, _>(block_hash)
+            .load::<AggregatedBlock>(connection)?;
+
+            if stale_hashes.len() == 0 {
+                continue;
+            }
+
+            self.rollback_aggregates(connection, &get_block_hashes(stale_hashes))?;
+
+            for statement in statements {
+                sql_query(statement)
+                    .bind::<Text, _>(self.chain.name)
+                    .bind::<BigInt, _>(block_number)
+                    .bind::<Text, _>(block_hash)
+                    .execute(connection)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the blocks of a range with their transactions, receipts, logs and fees, and
+    /// subtracts them from the totals.
+    pub async fn delete_blocks_range(&self, from_block: i64, to_block: i64) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        let statements = [
+            "DELETE FROM evm_transactions_logs WHERE hash IN (SELECT hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number BETWEEN $2 AND $3)",
+            "DELETE FROM evm_transactions_receipts WHERE hash IN (SELECT hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number BETWEEN $2 AND $3)",
+            "DELETE FROM evm_transactions WHERE chain = $1 AND block_number BETWEEN $2 AND $3",
+            "DELETE FROM evm_blocks WHERE chain = $1 AND number BETWEEN $2 AND $3",
+            "DELETE FROM evm_block_fees WHERE chain = $1 AND number BETWEEN $2 AND $3",
+        ];
+
+        connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            let deleted_hashes = sql_query(
+                "SELECT block_hash FROM evm_blocks WHERE chain = $1 AND number BETWEEN $2 AND $3 \
+                UNION SELECT block_hash FROM evm_transactions \
+                WHERE chain = $1 AND block_number BETWEEN $2 AND $3",
+            )
+            .bind::<Text, _>(self.chain.name)
+            .bind::<BigInt, _>(from_block)
+            .bind::<BigInt, _>(to_block)
+            .load::<AggregatedBlock>(connection)?;
+
+            self.rollback_aggregates(connection, &get_block_hashes(deleted_hashes))?;
+
+            for statement in statements {
+                sql_query(statement)
+                    .bind::<Text, _>(self.chain.name)
+                    .bind::<BigInt, _>(from_block)
+                    .bind::<BigInt, _>(to_block)
+                    .execute(connection)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn delete_blocks_rows(
+        &self,
+        connection: &mut PgConnection,
+        numbers: &Vec<i64>,
+    ) -> Result<(), diesel::result::Error> {
+        let statements = [
+            "DELETE FROM evm_transactions_logs WHERE hash IN (SELECT hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number = ANY($2))",
+            "DELETE FROM evm_transactions_receipts WHERE hash IN (SELECT hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number = ANY($2))",
+            "DELETE FROM evm_transactions WHERE chain = $1 AND block_number = ANY($2)",
+            "DELETE FROM evm_blocks WHERE chain = $1 AND number = ANY($2)",
+            "DELETE FROM evm_block_fees WHERE chain = $1 AND number = ANY($2)",
+        ];
+
+        let deleted_hashes = sql_query(
+            "SELECT block_hash FROM evm_blocks WHERE chain = $1 AND number = ANY($2) \
+            UNION SELECT block_hash FROM evm_transactions \
+            WHERE chain = $1 AND block_number = ANY($2)",
+        )
+        .bind::<Text, _>(self.chain.name)
+        .bind::<Array<BigInt>, _>(numbers)
+        .load::<AggregatedBlock>(connection)?;
+
+        self.rollback_aggregates(connection, &get_block_hashes(deleted_hashes))?;
+
+        for statement in statements {
+            sql_query(statement)
+                .bind::<Text, _>(self.chain.name)
+                .bind::<Array<BigInt>, _>(numbers)
+                .execute(connection)?;
+        }
+
+        Ok(())
+    }
+
     pub async fn delete_indexed_blocks(&self) -> Result<()> {
-        let mut connection = self.redis.get_connection().unwrap();
+        let mut connection = self.get_redis_connection().await?;
 
-        let _: () = connection.del(self.chain.name.to_string()).unwrap();
+        let _: () = connection.del(self.chain.name.to_string()).await?;
 
         Ok(())
     }
 }
 
+/// Transactions of the chain `$1` between the blocks `$2` and `$3`, including the ones of the
+/// backfilled logs.
+pub const CHAIN_RANGE_HASHES: &str = "SELECT hash FROM evm_transactions \
+    WHERE chain = $1 AND block_number BETWEEN $2 AND $3 \
+    UNION SELECT hash FROM evm_log_transactions \
+    WHERE chain = $1 AND block_number BETWEEN $2 AND $3";
+
+/// Static block rewards of Ethereum before the merge, by the first block they apply to.
+pub const ETHEREUM_BLOCK_REWARDS: [(i64, u64); 3] = [
+    (0, 5_000_000_000_000_000_000),
+    (4_370_000, 3_000_000_000_000_000_000),
+    (7_280_000, 2_000_000_000_000_000_000),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct BlockFees {
+    pub number: i64,
+    pub block_hash: String,
+    pub base_fee_per_gas: U256,
+    pub gas_used: U256,
+    pub burned: U256,
+    pub priority_fees: U256,
+    pub block_reward: U256,
+}
+
+/// Reward issued to the miner of a proof of work block, including the reward for each uncle it
+/// references. Blocks after the merge have no difficulty and no reward. The rewards of the uncle
+/// miners depend on the uncle numbers, which aren't stored, so they aren't counted.
+pub fn get_block_reward(chain: &str, block: &DatabaseEVMBlock) -> U256 {
+    if chain != "ethereum" || block.difficulty == "0" {
+        return U256::zero();
+    }
+
+    let base_reward = ETHEREUM_BLOCK_REWARDS
+        .iter()
+        .filter(|(first_block, _)| block.number >= *first_block)
+        .map(|(_, reward)| U256::from(*reward))
+        .last()
+        .unwrap_or_default();
+
+    base_reward + base_reward / 32 * block.uncles.len()
+}
+
+/// Splits the fees paid in each block between the base fee burned by EIP-1559 and the priority
+/// fees paid to the proposer. Before London the base fee is zero, so all fees go to the miner.
+pub fn get_block_fees(
+    chain: &str,
+    blocks: &Vec<DatabaseEVMBlock>,
+    transactions: &Vec<DatabaseEVMTransaction>,
+    receipts: &Vec<DatabaseEVMTransactionReceipt>,
+) -> Vec<BlockFees> {
+    let mut fees: HashMap<i64, BlockFees> = blocks
+        .iter()
+        .map(|block| {
+            let base_fee_per_gas = U256::from_dec_str(&block.base_fee_per_gas).unwrap_or_default();
+
+            let gas_used = U256::from_dec_str(&block.gas_used).unwrap_or_default();
+
+            (
+                block.number,
+                BlockFees {
+                    number: block.number,
+                    block_hash: block.block_hash.clone(),
+                    base_fee_per_gas,
+                    gas_used,
+                    burned: base_fee_per_gas.saturating_mul(gas_used),
+                    priority_fees: U256::zero(),
+                    block_reward: get_block_reward(chain, block),
+                },
+            )
+        })
+        .collect();
+
+    let transactions_blocks: HashMap<&String, i64> = transactions
+        .iter()
+        .map(|transaction| (&transaction.hash, transaction.block_number))
+        .collect();
+
+    for receipt in receipts {
+        let block_fees = match transactions_blocks.get(&receipt.hash) {
+            Some(block_number) => match fees.get_mut(block_number) {
+                Some(block_fees) => block_fees,
+                None => continue,
+            },
+            None => continue,
+        };
+
+        let gas_used = U256::from_dec_str(&receipt.gas_used).unwrap_or_default();
+
+        let gas_price = U256::from_dec_str(&receipt.effective_gas_price).unwrap_or_default();
+
+        let priority_fee = gas_price.saturating_sub(block_fees.base_fee_per_gas);
+
+        block_fees.priority_fees = block_fees
+            .priority_fees
+            .saturating_add(gas_used.saturating_mul(priority_fee));
+    }
+
+    fees.into_values().collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FeeHistoryBlock {
+    pub number: i64,
+    pub base_fee_per_gas: U256,
+    pub gas_used_ratio: f64,
+    pub rewards: Vec<U256>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProducerStats {
+    pub miner: String,
+    pub first_block: i64,
+    pub last_block: i64,
+    pub blocks_produced: i64,
+    pub empty_blocks: i64,
+    pub fees_earned: U256,
+    pub rewards_earned: U256,
+    pub gas_used: U256,
+}
+
+/// Aggregates the blocks produced by each coinbase for the blocks of a batch not counted before,
+/// sorted by coinbase. The fees earned are the priority fees of the blocks.
+pub fn get_producer_stats(
+    blocks: &Vec<DatabaseEVMBlock>,
+    block_fees: &Vec<BlockFees>,
+    counted_blocks: &HashSet<String>,
+) -> Vec<ProducerStats> {
+    let block_fees: HashMap<&String, &BlockFees> = block_fees
+        .iter()
+        .map(|fees| (&fees.block_hash, fees))
+        .collect();
+
+    let mut stats: HashMap<String, ProducerStats> = HashMap::new();
+
+    for block in blocks {
+        if !counted_blocks.contains(&block.block_hash) {
+            continue;
+        }
+
+        let producer = stats
+            .entry(block.miner.clone())
+            .or_insert_with(|| ProducerStats {
+                miner: block.miner.clone(),
+                first_block: block.number,
+                last_block: block.number,
+                ..Default::default()
+            });
+
+        producer.first_block = producer.first_block.min(block.number);
+        producer.last_block = producer.last_block.max(block.number);
+        producer.blocks_produced += 1;
+
+        if block.transactions == 0 {
+            producer.empty_blocks += 1;
+        }
+
+        producer.gas_used = producer
+            .gas_used
+            .saturating_add(U256::from_dec_str(&block.gas_used).unwrap_or_default());
+
+        match block_fees.get(&block.block_hash) {
+            Some(fees) => {
+                producer.fees_earned = producer.fees_earned.saturating_add(fees.priority_fees);
+                producer.rewards_earned = producer.rewards_earned.saturating_add(fees.block_reward);
+            }
+            None => (),
+        }
+    }
+
+    let mut stats: Vec<ProducerStats> = stats.into_values().collect();
+
+    stats.sort_by(|a, b| a.miner.cmp(&b.miner));
+
+    stats
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AddressStats {
+    pub address: String,
+    pub first_seen_block: i64,
+    pub last_active_block: i64,
+    pub transactions_sent: i64,
+    pub transactions_received: i64,
+    pub gas_spent: U256,
+    pub contracts_deployed: i64,
+}
+
+impl AddressStats {
+    fn seen(&mut self, block_number: i64) {
+        if self.first_seen_block == 0 || block_number < self.first_seen_block {
+            self.first_seen_block = block_number;
+        }
+
+        self.last_active_block = self.last_active_block.max(block_number);
+    }
+}
+
+/// Aggregates the activity of each address for the transactions not counted before and the new
+/// contracts of a batch, sorted by address.
+pub fn get_address_stats(
+    transactions: &Vec<DatabaseEVMTransaction>,
+    receipts: &Vec<DatabaseEVMTransactionReceipt>,
+    contracts: &Vec<DatabaseEVMContract>,
+    counted_transactions: &HashSet<String>,
+    new_contracts: &HashSet<String>,
+) -> Vec<AddressStats> {
+    let zero_address = format!("{:?}", H160::zero());
+
+    let receipts: HashMap<&String, &DatabaseEVMTransactionReceipt> = receipts
+        .iter()
+        .map(|receipt| (&receipt.hash, receipt))
+        .collect();
+
+    let mut stats: HashMap<String, AddressStats> = HashMap::new();
+
+    for transaction in transactions {
+        if !counted_transactions.contains(&transaction.hash) {
+            continue;
+        }
+
+        let gas_spent = match receipts.get(&transaction.hash) {
+            Some(receipt) => {
+                let gas_used = U256::from_dec_str(&receipt.gas_used).unwrap_or_default();
+
+                let gas_price =
+                    U256::from_dec_str(&receipt.effective_gas_price).unwrap_or_default();
+
+                gas_used.saturating_mul(gas_price)
+            }
+            None => U256::zero(),
+        };
+
+        let sender = stats
+            .entry(transaction.from_address.clone())
+            .or_insert_with(|| AddressStats {
+                address: transaction.from_address.clone(),
+                ..Default::default()
+            });
+
+        sender.seen(transaction.block_number);
+        sender.transactions_sent += 1;
+        sender.gas_spent = sender.gas_spent.saturating_add(gas_spent);
+
+        // Contract creations are sent to the zero address.
+        if transaction.to_address == zero_address {
+            continue;
+        }
+
+        let recipient = stats
+            .entry(transaction.to_address.clone())
+            .or_insert_with(|| AddressStats {
+                address: transaction.to_address.clone(),
+                ..Default::default()
+            });
+
+        recipient.seen(transaction.block_number);
+        recipient.transactions_received += 1;
+    }
+
+    for contract in contracts {
+        if !new_contracts.contains(&contract.hash) {
+            continue;
+        }
+
+        let creator = stats
+            .entry(contract.creator.clone())
+            .or_insert_with(|| AddressStats {
+                address: contract.creator.clone(),
+                ..Default::default()
+            });
+
+        creator.seen(contract.block);
+        creator.contracts_deployed += 1;
+    }
+
+    let mut stats: Vec<AddressStats> = stats.into_values().collect();
+
+    stats.sort_by(|a, b| a.address.cmp(&b.address));
+
+    stats
+}
+
+#[derive(Debug, Clone)]
+pub struct ContractCall {
+    pub contract: String,
+    /// Timestamp of the start of the UTC day of the call.
+    pub day: i64,
+    pub caller: String,
+}
+
+/// Contract, day and caller of each transaction not counted before calling a contract method. Transactions
+/// without a method are left out, as they are mostly plain transfers between accounts.
+pub fn get_contract_calls(
+    transactions: &Vec<DatabaseEVMTransaction>,
+    counted_transactions: &HashSet<String>,
+) -> Vec<ContractCall> {
+    let zero_address = format!("{:?}", H160::zero());
+
+    transactions
+        .iter()
+        .filter(|transaction| {
+            counted_transactions.contains(&transaction.hash)
+                && transaction.to_address != zero_address
+                && transaction.method != EMPTY_METHOD
+        })
+        .map(|transaction| {
+            let timestamp = transaction.timestamp.parse::<i64>().unwrap_or(0);
+
+            ContractCall {
+                contract: transaction.to_address.clone(),
+                day: timestamp - timestamp % 86400,
+                caller: transaction.from_address.clone(),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAddress {
+    pub address: String,
+    pub block_number: i64,
+    pub hash: String,
+    /// How the address first appeared: `sender`, `recipient` or `contract`.
+    pub role: &'static str,
+}
+
+#[derive(QueryableByName, Debug)]
+struct AggregatedBlock {
+    #[diesel(sql_type = Text)]
+    block_hash: String,
+}
+
+fn get_block_hashes(blocks: Vec<AggregatedBlock>) -> Vec<String> {
+    let mut hashes: Vec<String> = blocks.into_iter().map(|block| block.block_hash).collect();
+
+    hashes.sort();
+
+    hashes
+}
+
+#[derive(QueryableByName, Debug)]
+struct AggregatedTransaction {
+    #[diesel(sql_type = Text)]
+    hash: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct StoredAddress {
+    #[diesel(sql_type = Text)]
+    address: String,
+    #[diesel(sql_type = Bool)]
+    inserted: bool,
+}
+
+/// First appearance in a batch of each sender, recipient and deployed contract, by address.
+pub fn get_new_addresses(
+    transactions: &Vec<DatabaseEVMTransaction>,
+    contracts: &Vec<DatabaseEVMContract>,
+) -> Vec<NewAddress> {
+    let zero_address = format!("{:?}", H160::zero());
+
+    let mut addresses: HashMap<String, NewAddress> = HashMap::new();
+
+    let mut seen = |address: &String, block_number: i64, hash: &String, role: &'static str| {
+        // Contract creations are sent to the zero address.
+        if address == &zero_address {
+            return;
+        }
+
+        match addresses.get(address) {
+            Some(seen) if seen.block_number <= block_number => (),
+            _ => {
+                addresses.insert(
+                    address.clone(),
+                    NewAddress {
+                        address: address.clone(),
+                        block_number,
+                        hash: hash.clone(),
+                        role,
+                    },
+                );
+            }
+        }
+    };
+
+    for transaction in transactions {
+        seen(
+            &transaction.from_address,
+            transaction.block_number,
+            &transaction.hash,
+            "sender",
+        );
+
+        seen(
+            &transaction.to_address,
+            transaction.block_number,
+            &transaction.hash,
+            "recipient",
+        );
+    }
+
+    for contract in contracts {
+        seen(
+            &contract.contract,
+            contract.block,
+            &contract.hash,
+            "contract",
+        );
+    }
+
+    addresses.into_values().collect()
+}
+
+/// Schema names are used unquoted in the connection options, so they are restricted to lowercase
+/// letters, digits and underscores. Dashes are mapped to underscores so chain names like
+/// `arbitrum-nova` can be used as schemas.
+pub fn get_schema_name(schema: &str) -> Result<String> {
+    let schema = schema.replace("-", "_");
+
+    let valid = schema.len() > 0
+        && schema
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if !valid {
+        bail!("Invalid database schema name {}", schema);
+    }
+
+    Ok(schema)
+}
+
+/// Sets the `search_path` of the connections through the libpq `options` parameter, so the
+/// unqualified table names of every query resolve to the schema.
+pub fn get_schema_url(db_url: &str, schema: &str) -> Result<String> {
+    let separator = match db_url.contains("?") {
+        true => "&",
+        false => "?",
+    };
+
+    Ok(format!(
+        "{}{}options=-c%20search_path%3D{}",
+        db_url,
+        separator,
+        get_schema_name(schema)?
+    ))
+}
+
+/// SQLite databases are selected with a `sqlite://` URL, see `db::sqlite::SqliteDatabase`.
+pub fn is_sqlite_url(db_url: &str) -> bool {
+    db_url.starts_with("sqlite:")
+}
+
+/// Key-value databases are selected with a `sled://` URL, see `db::kv::KVDatabase`.
+pub fn is_kv_url(db_url: &str) -> bool {
+    db_url.starts_with("sled://")
+}
+
 /// Ref: https://github.com/aptos-labs/aptos-core/blob/main/crates/indexer/src/database.rs#L32
 /// Given diesel has a limit of how many parameters can be inserted in a single operation (u16::MAX)
 /// we may need to chunk an array of items based on how many columns are in the table.
@@ -314,3 +2734,11 @@ pub fn get_chunks(num_items_to_insert: usize, column_count: usize) -> Vec<(usize
     }
     chunks
 }
+
+/// Keys of the upserted rows that were inserted, from the rows returned with `is_inserted`.
+pub fn get_inserted_keys(rows: Vec<(String, bool)>) -> Vec<String> {
+    rows.into_iter()
+        .filter(|(_, inserted)| *inserted)
+        .map(|(key, _)| key)
+        .collect()
+}
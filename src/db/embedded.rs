@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+
+use crate::chains::chains::Chain;
+
+use super::{
+    db::{is_kv_url, is_sqlite_url},
+    models::models::{
+        DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
+        DatabaseEVMTransactionReceipt,
+    },
+};
+
+/// Storage of the indexed data without Postgres and Redis, to index small chains or ranges
+/// locally. Only the blocks, transactions, receipts, logs and contracts are stored.
+pub trait EmbeddedDatabase {
+    fn get_indexed_blocks(&self) -> Result<HashSet<i64>>;
+
+    fn store_data(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) -> Result<()>;
+}
+
+pub fn is_embedded_url(db_url: &str) -> bool {
+    is_sqlite_url(db_url) || is_kv_url(db_url)
+}
+
+/// Opens the embedded database of a `sqlite://` or `sled://` URL, the backend must be enabled
+/// with its feature.
+pub fn open_embedded_database(db_url: &str, chain: Chain) -> Result<Box<dyn EmbeddedDatabase>> {
+    if is_sqlite_url(db_url) {
+        return open_sqlite(db_url, chain);
+    }
+
+    if is_kv_url(db_url) {
+        return open_kv(db_url, chain);
+    }
+
+    bail!("Unsupported embedded database URL {}", db_url)
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(db_url: &str, chain: Chain) -> Result<Box<dyn EmbeddedDatabase>> {
+    Ok(Box::new(super::sqlite::SqliteDatabase::new(
+        db_url.to_string(),
+        chain,
+    )?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(_db_url: &str, _chain: Chain) -> Result<Box<dyn EmbeddedDatabase>> {
+    bail!("The indexer must be built with the sqlite feature to use a SQLite database")
+}
+
+#[cfg(feature = "kv")]
+fn open_kv(db_url: &str, chain: Chain) -> Result<Box<dyn EmbeddedDatabase>> {
+    Ok(Box::new(super::kv::KVDatabase::new(
+        db_url.to_string(),
+        chain,
+    )?))
+}
+
+#[cfg(not(feature = "kv"))]
+fn open_kv(_db_url: &str, _chain: Chain) -> Result<Box<dyn EmbeddedDatabase>> {
+    bail!("The indexer must be built with the kv feature to use a key-value database")
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use log::*;
+use serde::Serialize;
+
+use crate::chains::chains::Chain;
+
+use super::{
+    embedded::EmbeddedDatabase,
+    models::models::{
+        DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
+        DatabaseEVMTransactionReceipt,
+    },
+};
+
+/// Embedded key-value storage for firehose-style consumers that don't need SQL. Each data type
+/// is a sled tree of JSON serialized models keyed by chain, block number and index: the
+/// transaction index for transactions, receipts and contracts and the log index for logs. Keys
+/// are big endian, so a prefix scan of a chain returns its data in block order.
+#[derive(Debug, Clone)]
+pub struct KVDatabase {
+    pub db: sled::Db,
+    pub chain: Chain,
+}
+
+impl KVDatabase {
+    pub fn new(db_url: String, chain: Chain) -> Result<Self> {
+        let db_path = get_kv_path(&db_url);
+
+        let db = sled::open(&db_path)?;
+
+        info!("Using key-value database {}", db_path);
+
+        Ok(Self { db, chain })
+    }
+
+    fn get_key(&self, block_number: i64, index: i64) -> Vec<u8> {
+        let mut key = self.get_chain_prefix();
+
+        key.extend_from_slice(&(block_number as u64).to_be_bytes());
+        key.extend_from_slice(&(index as u64).to_be_bytes());
+
+        key
+    }
+
+    fn get_chain_prefix(&self) -> Vec<u8> {
+        let mut prefix = self.chain.name.as_bytes().to_vec();
+
+        prefix.push(0);
+
+        prefix
+    }
+
+    fn store_values<T: Serialize>(&self, tree: &str, values: Vec<(Vec<u8>, &T)>) -> Result<()> {
+        let mut batch = sled::Batch::default();
+
+        for (key, value) in values {
+            batch.insert(key, serde_json::to_vec(value)?);
+        }
+
+        self.db.open_tree(tree)?.apply_batch(batch)?;
+
+        Ok(())
+    }
+}
+
+impl EmbeddedDatabase for KVDatabase {
+    fn get_indexed_blocks(&self) -> Result<HashSet<i64>> {
+        let prefix = self.get_chain_prefix();
+
+        let mut blocks = HashSet::new();
+
+        for entry in self.db.open_tree("blocks")?.scan_prefix(&prefix) {
+            let (key, _) = entry?;
+
+            let mut number = [0; 8];
+
+            number.copy_from_slice(&key[prefix.len()..prefix.len() + 8]);
+
+            blocks.insert(u64::from_be_bytes(number) as i64);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Blocks are stored last and flushed, so a block is only indexed once all its data is on
+    /// disk.
+    fn store_data(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) -> Result<()> {
+        // Receipts, logs and contracts only include their transaction hash.
+        let positions: HashMap<&String, (i64, i64)> = transactions
+            .iter()
+            .map(|transaction| {
+                (
+                    &transaction.hash,
+                    (transaction.block_number, transaction.transaction_index),
+                )
+            })
+            .collect();
+
+        self.store_values(
+            "transactions",
+            transactions
+                .iter()
+                .map(|transaction| {
+                    (
+                        self.get_key(transaction.block_number, transaction.transaction_index),
+                        transaction,
+                    )
+                })
+                .collect(),
+        )?;
+
+        self.store_values(
+            "receipts",
+            receipts
+                .iter()
+                .filter_map(|receipt| {
+                    let (block_number, transaction_index) = positions.get(&receipt.hash)?;
+
+                    Some((self.get_key(*block_number, *transaction_index), receipt))
+                })
+                .collect(),
+        )?;
+
+        self.store_values(
+            "logs",
+            logs.iter()
+                .filter_map(|log| {
+                    let (block_number, _) = positions.get(&log.hash)?;
+
+                    Some((self.get_key(*block_number, log.log_index), log))
+                })
+                .collect(),
+        )?;
+
+        self.store_values(
+            "contracts",
+            contracts
+                .iter()
+                .filter_map(|contract| {
+                    let (_, transaction_index) = positions.get(&contract.hash)?;
+
+                    Some((self.get_key(contract.block, *transaction_index), contract))
+                })
+                .collect(),
+        )?;
+
+        self.store_values(
+            "blocks",
+            blocks
+                .iter()
+                .map(|block| (self.get_key(block.number, 0), block))
+                .collect(),
+        )?;
+
+        self.db.flush()?;
+
+        info!(
+            "Inserted: blocks ({}) transactions ({}) receipts ({}) logs ({}) contracts ({}) for chain {}",
+            blocks.len(),
+            transactions.len(),
+            receipts.len(),
+            logs.len(),
+            contracts.len(),
+            self.chain.name
+        );
+
+        Ok(())
+    }
+}
+
+/// Path of the database directory from a `sled://` URL.
+pub fn get_kv_path(db_url: &str) -> String {
+    db_url.trim_start_matches("sled://").to_string()
+}
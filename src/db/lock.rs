@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use ethers::utils::keccak256;
+use log::*;
+use redis::Script;
+use tokio::time::sleep;
+
+use super::db::EVMDatabase;
+
+/// Seconds before the lease of a stopped indexer expires.
+pub const LOCK_TTL: u64 = 30;
+
+/// Lease in Redis on the chain and database, so two indexers pointed at the same chain and
+/// database don't both write its blocks and indexed blocks. The lease is renewed while the
+/// indexer runs and expires `LOCK_TTL` seconds after it stops, e.g. after a crash.
+#[derive(Debug, Clone)]
+pub struct ChainLock {
+    pub db: EVMDatabase,
+    pub key: String,
+    /// Host, process and a random suffix, so a restarted process doesn't renew the old lease.
+    pub owner: String,
+}
+
+impl ChainLock {
+    pub fn new(db: EVMDatabase) -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or(String::from("unknown"));
+
+        let owner = format!(
+            "{}-{}-{:08x}",
+            host,
+            std::process::id(),
+            rand::random::<u32>()
+        );
+
+        // Hash of the database, so indexers of the same chain into different databases or
+        // schemas don't share the lease, without writing the credentials of the url to Redis.
+        let database = keccak256(format!(
+            "{}/{}",
+            db.db_url,
+            db.schema.clone().unwrap_or_default()
+        ));
+
+        let key = format!("{}-{}-lock", db.chain.name, hex::encode(&database[..8]));
+
+        Self { db, key, owner }
+    }
+
+    /// Takes the lease, waiting for the lease of a stopped owner to expire. Fails when another
+    /// owner still renews it.
+    pub async fn acquire(&self) -> Result<()> {
+        let mut connection = self.db.get_redis_connection().await?;
+
+        for _ in 0..=LOCK_TTL {
+            let acquired: Option<String> = redis::cmd("SET")
+                .arg(&self.key)
+                .arg(&self.owner)
+                .arg("NX")
+                .arg("EX")
+                .arg(LOCK_TTL)
+                .query_async(&mut connection)
+                .await?;
+
+            if acquired.is_some() {
+                info!(
+                    "Acquired the lock of chain {} as {}.",
+                    self.db.chain.name, self.owner
+                );
+
+                return Ok(());
+            }
+
+            sleep(Duration::from_secs(1)).await;
+        }
+
+        let owner: Option<String> = redis::cmd("GET")
+            .arg(&self.key)
+            .query_async(&mut connection)
+            .await?;
+
+        bail!(
+            "Chain {} is already indexed by {}",
+            self.db.chain.name,
+            owner.unwrap_or_default()
+        )
+    }
+
+    /// Extends the lease, returns false when it expired and is no longer owned.
+    pub async fn renew(&self) -> Result<bool> {
+        let mut connection = self.db.get_redis_connection().await?;
+
+        let renewed: i64 = Script::new(
+            "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                return redis.call('EXPIRE', KEYS[1], ARGV[2]) \
+            else \
+                return 0 \
+            end",
+        )
+        .key(&self.key)
+        .arg(&self.owner)
+        .arg(LOCK_TTL)
+        .invoke_async(&mut connection)
+        .await?;
+
+        Ok(renewed == 1)
+    }
+
+    /// Deletes the lease when still owned, so another indexer can take it without waiting for
+    /// it to expire.
+    pub async fn release(&self) -> Result<()> {
+        let mut connection = self.db.get_redis_connection().await?;
+
+        Script::new(
+            "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                return redis.call('DEL', KEYS[1]) \
+            else \
+                return 0 \
+            end",
+        )
+        .key(&self.key)
+        .arg(&self.owner)
+        .invoke_async::<_, i64>(&mut connection)
+        .await?;
+
+        Ok(())
+    }
+}
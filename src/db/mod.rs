@@ -1,3 +1,15 @@
+pub mod backfill;
+pub mod buffer;
+pub mod compression;
 pub mod db;
+pub mod embedded;
+#[cfg(feature = "kv")]
+pub mod kv;
+pub mod lock;
 pub mod models;
+pub mod redis_connection;
+pub mod reset;
 pub mod schema;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod upsert;
@@ -1,11 +1,13 @@
 use diesel::prelude::*;
 use ethers::types::{Block, Log, Transaction, TransactionReceipt, H160};
 use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     db::schema::{
-        chains_indexed_state, evm_abis, evm_blocks, evm_contracts, evm_methods, evm_transactions,
-        evm_transactions_logs, evm_transactions_receipts,
+        chains_indexed_state, evm_abis, evm_blocks, evm_contracts, evm_log_transactions,
+        evm_methods, evm_outbox, evm_outbox_offsets, evm_parse_failures, evm_parsed_logs,
+        evm_transactions, evm_transactions_logs, evm_transactions_receipts,
     },
     utils::{
         format_address, format_bytes, format_bytes_slice, format_hash, format_nonce, format_number,
@@ -13,7 +15,7 @@ use crate::{
     },
 };
 
-#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[derive(Selectable, Queryable, Insertable, Serialize, Deserialize, Debug, Clone, FieldCount)]
 #[diesel(table_name = evm_blocks)]
 pub struct DatabaseEVMBlock {
     pub base_fee_per_gas: String,
@@ -137,7 +139,7 @@ pub fn byte4_from_input(input: &String) -> [u8; 4] {
     return byte4;
 }
 
-#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[derive(Selectable, Queryable, Insertable, Serialize, Deserialize, Debug, Clone, FieldCount)]
 #[diesel(table_name = evm_transactions)]
 pub struct DatabaseEVMTransaction {
     pub block_hash: String,
@@ -157,6 +159,11 @@ pub struct DatabaseEVMTransaction {
     pub transaction_index: i64,
     pub transaction_type: i64,
     pub value: String,
+    /// Compressed `input`, which is then left empty. See `PayloadCodec`.
+    #[serde(skip)]
+    pub input_zstd: Option<Vec<u8>>,
+    /// Share of the gas limit used, from the receipt.
+    pub gas_efficiency: Option<f64>,
 }
 
 impl DatabaseEVMTransaction {
@@ -221,11 +228,25 @@ impl DatabaseEVMTransaction {
             transaction_index,
             transaction_type,
             value: format_number(transaction.value),
+            input_zstd: None,
+            gas_efficiency: None,
         }
     }
+
+    /// Sets the share of the gas limit used by the transaction, left empty without a gas limit.
+    pub fn set_gas_efficiency(&mut self, receipt: &DatabaseEVMTransactionReceipt) {
+        let gas = self.gas.parse::<f64>().unwrap_or(0.0);
+
+        let gas_used = receipt.gas_used.parse::<f64>().unwrap_or(0.0);
+
+        self.gas_efficiency = match gas > 0.0 {
+            true => Some(gas_used / gas),
+            false => None,
+        };
+    }
 }
 
-#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[derive(Selectable, Queryable, Insertable, Serialize, Deserialize, Debug, Clone, FieldCount)]
 #[diesel(table_name = evm_transactions_receipts)]
 pub struct DatabaseEVMTransactionReceipt {
     pub contract_address: Option<String>,
@@ -234,6 +255,7 @@ pub struct DatabaseEVMTransactionReceipt {
     pub gas_used: String,
     pub hash: String,
     pub status: String,
+    pub revert_reason: Option<String>,
 }
 
 impl DatabaseEVMTransactionReceipt {
@@ -265,11 +287,22 @@ impl DatabaseEVMTransactionReceipt {
             gas_used,
             hash: format_hash(receipt.transaction_hash),
             status,
+            revert_reason: None,
         }
     }
 }
 
-#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[derive(
+    Selectable,
+    Queryable,
+    QueryableByName,
+    Insertable,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    FieldCount,
+)]
 #[diesel(table_name = evm_transactions_logs)]
 pub struct DatabaseEVMTransactionLog {
     pub address: String,
@@ -279,6 +312,14 @@ pub struct DatabaseEVMTransactionLog {
     pub log_index: i64,
     pub removed: bool,
     pub erc20_transfers_parsed: Option<bool>,
+    /// Compressed `data`, which is then left empty. See `PayloadCodec`.
+    #[serde(skip)]
+    pub data_zstd: Option<Vec<u8>>,
+    /// Event name and named arguments of the logs of contracts with a known ABI, written by the
+    /// `DecodedLogsParser`.
+    pub decoded: Option<serde_json::Value>,
+    /// Block of the transaction, to page through the logs of an address in order.
+    pub block_number: Option<i64>,
 }
 
 impl DatabaseEVMTransactionLog {
@@ -311,6 +352,47 @@ impl DatabaseEVMTransactionLog {
             log_index,
             removed,
             erc20_transfers_parsed: Some(false),
+            data_zstd: None,
+            decoded: None,
+            block_number: log
+                .block_number
+                .map(|block_number| block_number.as_u64() as i64),
+        }
+    }
+}
+
+/// Location of a transaction only known from its logs, stored by the logs backfill below the
+/// start block of the full blocks so the parsers can resolve the chain and block of the logs.
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_log_transactions)]
+pub struct DatabaseEVMLogTransaction {
+    pub hash: String,
+    pub chain: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub transaction_index: i64,
+}
+
+impl DatabaseEVMLogTransaction {
+    pub fn from_rpc(log: &Log, chain: &'static str) -> Self {
+        Self {
+            hash: match log.transaction_hash {
+                None => String::from("0"),
+                Some(hash) => format_hash(hash),
+            },
+            chain: chain.to_string(),
+            block_number: match log.block_number {
+                None => 0,
+                Some(block_number) => block_number.as_u64() as i64,
+            },
+            block_hash: match log.block_hash {
+                None => String::from("0"),
+                Some(block_hash) => format_hash(block_hash),
+            },
+            transaction_index: match log.transaction_index {
+                None => 0,
+                Some(transaction_index) => transaction_index.as_u64() as i64,
+            },
         }
     }
 }
@@ -331,7 +413,7 @@ pub struct DatabaseEVMAbi {
     pub verified: bool,
 }
 
-#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[derive(Selectable, Queryable, Insertable, Serialize, Deserialize, Debug, Clone, FieldCount)]
 #[diesel(table_name = evm_contracts)]
 pub struct DatabaseEVMContract {
     pub block: i64,
@@ -373,3 +455,44 @@ pub struct DatabaseChainIndexedState {
     pub chain: String,
     pub indexed_blocks_amount: i64,
 }
+
+#[derive(Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_outbox)]
+pub struct DatabaseEVMOutboxEvent {
+    pub chain: String,
+    pub topic: String,
+    pub payload: String,
+}
+
+#[derive(Selectable, Queryable, Debug, Clone, Serialize)]
+#[diesel(table_name = evm_outbox)]
+pub struct DatabaseEVMStoredOutboxEvent {
+    pub id: i64,
+    pub chain: String,
+    pub topic: String,
+    pub payload: String,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_outbox_offsets)]
+pub struct DatabaseEVMOutboxOffset {
+    pub sink: String,
+    pub last_id: i64,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_parsed_logs)]
+pub struct DatabaseEVMParsedLog {
+    pub parser: String,
+    pub hash: String,
+    pub log_index: i64,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_parse_failures)]
+pub struct DatabaseEVMParseFailure {
+    pub parser: String,
+    pub hash: String,
+    pub log_index: i64,
+    pub reason: String,
+}
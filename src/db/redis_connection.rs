@@ -0,0 +1,28 @@
+use std::{fmt, sync::Arc};
+
+use redis::{aio::ConnectionManager, Client, RedisResult};
+use tokio::sync::OnceCell;
+
+/// Async Redis connection shared by the clones of its owner. It's opened on first use and
+/// multiplexes the commands of every task, the manager reconnects it when it drops.
+#[derive(Clone, Default)]
+pub struct SharedRedisConnection {
+    connection: Arc<OnceCell<ConnectionManager>>,
+}
+
+impl SharedRedisConnection {
+    pub async fn get(&self, client: &Client) -> RedisResult<ConnectionManager> {
+        self.connection
+            .get_or_try_init(|| ConnectionManager::new(client.clone()))
+            .await
+            .cloned()
+    }
+}
+
+impl fmt::Debug for SharedRedisConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedRedisConnection")
+            .field("connected", &self.connection.initialized())
+            .finish()
+    }
+}
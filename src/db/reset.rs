@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Text},
+};
+use log::*;
+use serde::Serialize;
+
+use crate::parsers::erc20_transfers_parser::PARSER_NAME as ERC20_TRANSFERS_PARSER;
+
+use super::db::{EVMDatabase, CHAIN_RANGE_HASHES};
+
+/// Tables each parser writes from the logs of a transaction, found by their `hash` column. Rows
+/// keyed by other columns, like the ENS reverse nodes, DEX pools, governance proposals and MEV
+/// events, are not deleted and get updated when the logs are parsed again.
+pub const PARSER_TABLES: [(&str, &[&str]); 10] = [
+    ("bridge", &["evm_bridge_transfers"]),
+    ("dex_swaps", &["evm_dex_swaps"]),
+    (
+        "ens",
+        &[
+            "evm_ens_records",
+            "evm_ens_registrations",
+            "evm_ens_resolvers",
+        ],
+    ),
+    (
+        ERC20_TRANSFERS_PARSER,
+        &["evm_erc20_transfers", SUPPLY_CHANGES_TABLE],
+    ),
+    ("flash_loans", &["evm_flash_loans"]),
+    ("governance", &["evm_governance_votes"]),
+    ("lending", &["evm_lending_events", "evm_liquidations"]),
+    ("liquidity", &["evm_liquidity_events"]),
+    ("permits", &["evm_permits"]),
+    ("staking", &["evm_staking_events", "evm_staking_rewards"]),
+];
+
+const SUPPLY_CHANGES_TABLE: &str = "evm_erc20_supply_changes";
+
+#[derive(QueryableByName, Debug)]
+struct DeletedRows {
+    #[diesel(sql_type = BigInt)]
+    deleted_rows: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResetSummary {
+    pub chain: String,
+    pub from_block: i64,
+    pub to_block: i64,
+    /// Blocks removed from the indexed blocks, fetched again by the next sync.
+    pub blocks: usize,
+    /// Deleted rows of each table.
+    pub rows: BTreeMap<String, usize>,
+    /// Logs the parser parses again.
+    pub flushed_logs: usize,
+}
+
+/// Resets part of the indexed data of a chain instead of the whole chain, e.g. to recover from a
+/// bad parser run. Each reset is scoped to a range of blocks, every block without one.
+#[derive(Debug, Clone)]
+pub struct ChainReset {
+    pub db: EVMDatabase,
+    pub from_block: i64,
+    pub to_block: i64,
+}
+
+impl ChainReset {
+    pub fn new(db: EVMDatabase, range: Option<(i64, i64)>) -> Self {
+        let (from_block, to_block) = range.unwrap_or((0, i64::MAX));
+
+        Self {
+            db,
+            from_block,
+            to_block,
+        }
+    }
+
+    fn get_summary(&self) -> ResetSummary {
+        ResetSummary {
+            chain: self.db.chain.name.to_string(),
+            from_block: self.from_block,
+            to_block: self.to_block,
+            ..Default::default()
+        }
+    }
+
+    /// Deletes the blocks of the range with their transactions, receipts and logs, and removes
+    /// them from the indexed blocks so the indexer fetches them again.
+    pub async fn reset_blocks(&self) -> Result<ResetSummary> {
+        let mut summary = self.get_summary();
+
+        self.db
+            .delete_blocks_range(self.from_block, self.to_block)
+            .await?;
+
+        let mut indexed_blocks = self.db.get_indexed_blocks().await?;
+
+        let total_blocks = indexed_blocks.len();
+
+        indexed_blocks.retain(|block| *block < self.from_block || *block > self.to_block);
+
+        summary.blocks = total_blocks - indexed_blocks.len();
+
+        self.db.store_indexed_blocks(&indexed_blocks).await?;
+
+        info!(
+            "Reset {} blocks of chain {}.",
+            summary.blocks, self.db.chain.name
+        );
+
+        Ok(summary)
+    }
+
+    /// Deletes the rows of every table of the parser and flushes its progress so it parses the
+    /// logs again. Parsers without known tables only get their progress flushed.
+    pub async fn reset_parser(&self, parser: &str) -> Result<ResetSummary> {
+        let tables = match get_parser_tables(parser) {
+            Some(tables) => tables.to_vec(),
+            None => {
+                warn!(
+                    "Parser {} has no known tables, only its progress is flushed.",
+                    parser
+                );
+
+                Vec::new()
+            }
+        };
+
+        self.reset_tables(parser, &tables).await
+    }
+
+    /// Deletes the rows of a parser table, e.g. `erc20_transfers`, and flushes the progress of
+    /// its parser so the rows are parsed again.
+    pub async fn reset_table(&self, table: &str) -> Result<ResetSummary> {
+        let table = match table.starts_with("evm_") {
+            true => table.to_string(),
+            false => format!("evm_{}", table),
+        };
+
+        let parser = match get_table_parser(&table) {
+            Some(parser) => parser,
+            None => bail!(
+                "Table {} is not written by a parser, the tables are {}",
+                table,
+                PARSER_TABLES
+                    .iter()
+                    .flat_map(|(_, tables)| tables.iter())
+                    .cloned()
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        };
+
+        self.reset_tables(parser, &vec![table.as_str()]).await
+    }
+
+    /// Deletes the rows of the tables in a single transaction. Deleted supply changes are
+    /// subtracted from the cumulative supply, which counts them once when they are inserted, so
+    /// parsing the logs again restores it.
+    async fn reset_tables(&self, parser: &str, tables: &Vec<&str>) -> Result<ResetSummary> {
+        let mut summary = self.get_summary();
+
+        let mut connection = self.db.establish_connection();
+
+        let deleted = connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            let mut deleted = Vec::new();
+
+            for table in tables {
+                let rows = match *table {
+                    SUPPLY_CHANGES_TABLE => {
+                        sql_query(format!(
+                            "WITH deleted AS ( \
+                                DELETE FROM {} WHERE hash IN ({}) RETURNING token, kind, amount \
+                            ), deltas AS ( \
+                                SELECT token, SUM(CASE WHEN kind = 'mint' THEN amount::NUMERIC \
+                                ELSE -amount::NUMERIC END) AS delta \
+                                FROM deleted GROUP BY token \
+                            ), updated AS ( \
+                                UPDATE evm_erc20_supply s SET supply = s.supply - d.delta \
+                                FROM deltas d WHERE s.token = d.token RETURNING s.token \
+                            ) \
+                            SELECT COUNT(*) AS deleted_rows FROM deleted",
+                            table, CHAIN_RANGE_HASHES
+                        ))
+                        .bind::<Text, _>(self.db.chain.name)
+                        .bind::<BigInt, _>(self.from_block)
+                        .bind::<BigInt, _>(self.to_block)
+                        .get_result::<DeletedRows>(connection)?
+                        .deleted_rows as usize
+                    }
+                    _ => sql_query(format!(
+                        "DELETE FROM {} WHERE hash IN ({})",
+                        table, CHAIN_RANGE_HASHES
+                    ))
+                    .bind::<Text, _>(self.db.chain.name)
+                    .bind::<BigInt, _>(self.from_block)
+                    .bind::<BigInt, _>(self.to_block)
+                    .execute(connection)?,
+                };
+
+                deleted.push((table.to_string(), rows));
+            }
+
+            Ok(deleted)
+        });
+
+        match deleted {
+            Ok(deleted) => {
+                for (table, rows) in deleted {
+                    info!("Deleted {} rows of {}.", rows, table);
+
+                    summary.rows.insert(table, rows);
+                }
+            }
+            Err(err) => bail!("Unable to reset the tables {}: {}", tables.join(", "), err),
+        }
+
+        summary.flushed_logs = self
+            .db
+            .flush_parser_cursors(parser, self.from_block, self.to_block)
+            .await?;
+
+        Ok(summary)
+    }
+}
+
+pub fn get_parser_tables(parser: &str) -> Option<&'static [&'static str]> {
+    PARSER_TABLES
+        .iter()
+        .find(|(name, _)| *name == parser)
+        .map(|(_, tables)| *tables)
+}
+
+pub fn get_table_parser(table: &str) -> Option<&'static str> {
+    PARSER_TABLES
+        .iter()
+        .find(|(_, tables)| tables.contains(&table))
+        .map(|(parser, _)| *parser)
+}
+
+/// Parses a `<from>..<to>` range of blocks, both included.
+pub fn parse_block_range(range: &str) -> Result<(i64, i64)> {
+    let (from_block, to_block) = match range.split_once("..") {
+        Some((from_block, to_block)) => (from_block.trim(), to_block.trim()),
+        None => bail!("Invalid block range {}, expected <from>..<to>", range),
+    };
+
+    match (from_block.parse::<i64>(), to_block.parse::<i64>()) {
+        (Ok(from_block), Ok(to_block)) if from_block <= to_block => Ok((from_block, to_block)),
+        _ => bail!("Invalid block range {}, expected <from>..<to>", range),
+    }
+}
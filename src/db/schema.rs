@@ -4,6 +4,7 @@ diesel::table! {
     chains_indexed_state (chain) {
         chain -> Text,
         indexed_blocks_amount -> Int8,
+        sync_lag -> Nullable<Int8>,
     }
 }
 
@@ -24,6 +25,99 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    evm_address_clusters (address, chain) {
+        address -> Text,
+        chain -> Text,
+        cluster -> Text,
+        heuristics -> Array<Nullable<Text>>,
+    }
+}
+
+diesel::table! {
+    evm_address_labels (address, chain) {
+        address -> Text,
+        chain -> Text,
+        label -> Text,
+        kind -> Text,
+    }
+}
+
+diesel::table! {
+    evm_address_stats (address, chain) {
+        address -> Text,
+        chain -> Text,
+        first_seen_block -> Int8,
+        last_active_block -> Int8,
+        transactions_sent -> Int8,
+        transactions_received -> Int8,
+        gas_spent -> Numeric,
+        contracts_deployed -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_aggregated_blocks (chain, block_hash) {
+        chain -> Text,
+        block_hash -> Text,
+    }
+}
+
+diesel::table! {
+    evm_aggregated_transactions (chain, hash) {
+        chain -> Text,
+        hash -> Text,
+    }
+}
+
+diesel::table! {
+    evm_api_keys (key) {
+        key -> Text,
+        name -> Text,
+        rate_limit -> Nullable<Int8>,
+        requests -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_backfill_jobs (id) {
+        id -> Int8,
+        chain -> Text,
+        from_block -> Int8,
+        to_block -> Int8,
+        priority -> Int8,
+        status -> Text,
+        created_at -> Int8,
+        finished_at -> Nullable<Int8>,
+        missing_blocks -> Nullable<Array<Int8>>,
+    }
+}
+
+diesel::table! {
+    evm_block_fees (chain, number) {
+        chain -> Text,
+        number -> Int8,
+        block_hash -> Text,
+        base_fee_per_gas -> Numeric,
+        gas_used -> Numeric,
+        burned -> Numeric,
+        priority_fees -> Numeric,
+        block_reward -> Numeric,
+    }
+}
+
+diesel::table! {
+    evm_block_times (chain, block_hash) {
+        chain -> Text,
+        number -> Int8,
+        block_hash -> Text,
+        parent_hash -> Text,
+        timestamp -> Int8,
+        received_at -> Int8,
+        delay_ms -> Int8,
+    }
+}
+
 diesel::table! {
     evm_blocks (block_hash) {
         base_fee_per_gas -> Text,
@@ -50,6 +144,78 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    evm_bridge_transfers (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        protocol -> Text,
+        direction -> Text,
+        status -> Text,
+        user_address -> Text,
+        recipient -> Nullable<Text>,
+        token -> Nullable<Text>,
+        amount -> Text,
+        message_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_call_frames (hash, trace_address) {
+        hash -> Text,
+        trace_address -> Text,
+        chain -> Text,
+        block_number -> Int8,
+        depth -> Int8,
+        call_type -> Text,
+        from_address -> Text,
+        to_address -> Text,
+        value -> Text,
+        selector -> Text,
+        gas -> Text,
+        gas_used -> Text,
+        error -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_call_samples (chain, name, block_number) {
+        chain -> Text,
+        name -> Text,
+        block_number -> Int8,
+        contract -> Text,
+        function -> Text,
+        result -> Text,
+    }
+}
+
+diesel::table! {
+    evm_compression_dictionaries (chain, payload) {
+        chain -> Text,
+        payload -> Text,
+        dictionary -> Bytea,
+    }
+}
+
+diesel::table! {
+    evm_contract_activity (chain, contract, day) {
+        chain -> Text,
+        contract -> Text,
+        day -> Int8,
+        transactions -> Int8,
+        unique_callers -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_contract_callers (chain, contract, day, caller) {
+        chain -> Text,
+        contract -> Text,
+        day -> Int8,
+        caller -> Text,
+    }
+}
+
 diesel::table! {
     evm_contracts (hash) {
         block -> Int8,
@@ -72,6 +238,103 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    evm_dex_pools (pool, chain) {
+        pool -> Text,
+        chain -> Text,
+        tokens -> Array<Nullable<Text>>,
+    }
+}
+
+diesel::table! {
+    evm_dex_swaps (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        dex -> Text,
+        pool -> Text,
+        sender -> Text,
+        recipient -> Nullable<Text>,
+        token_in -> Nullable<Text>,
+        token_out -> Nullable<Text>,
+        token_in_index -> Nullable<Int8>,
+        token_out_index -> Nullable<Int8>,
+        amount_in -> Text,
+        amount_out -> Text,
+    }
+}
+
+diesel::table! {
+    evm_duplicate_logs (hash, log_index, parser) {
+        hash -> Text,
+        log_index -> Int8,
+        parser -> Text,
+        protocol -> Text,
+        policy -> Text,
+        duplicate_of -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_ens_records (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        block_number -> Int8,
+        resolver -> Text,
+        node -> Text,
+        kind -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    evm_ens_registrations (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        block_number -> Int8,
+        action -> Text,
+        name -> Text,
+        label -> Text,
+        node -> Text,
+        owner -> Nullable<Text>,
+        expires -> Text,
+    }
+}
+
+diesel::table! {
+    evm_ens_resolvers (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        block_number -> Int8,
+        node -> Text,
+        resolver -> Text,
+    }
+}
+
+diesel::table! {
+    evm_ens_reverse_nodes (node) {
+        node -> Text,
+        address -> Text,
+    }
+}
+
+diesel::table! {
+    evm_erc20_supply (token) {
+        token -> Text,
+        supply -> Numeric,
+    }
+}
+
+diesel::table! {
+    evm_erc20_supply_changes (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        token -> Text,
+        kind -> Text,
+        amount -> Text,
+    }
+}
+
 diesel::table! {
     evm_erc20_tokens (address, chain) {
         address -> Text,
@@ -79,6 +342,9 @@ diesel::table! {
         name -> Nullable<Text>,
         decimals -> Nullable<Int8>,
         symbol -> Nullable<Text>,
+        spam_score -> Nullable<Int8>,
+        spam_reasons -> Nullable<Array<Nullable<Text>>>,
+        logo -> Nullable<Text>,
     }
 }
 
@@ -91,6 +357,165 @@ diesel::table! {
         to_address -> Text,
         value -> Text,
         erc20_tokens_parced -> Nullable<Bool>,
+        value_decimal -> Nullable<Numeric>,
+        block_number -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    evm_exchange_deposits (address, chain) {
+        address -> Text,
+        chain -> Text,
+        exchange -> Text,
+        hot_wallet -> Text,
+        forwards -> Int8,
+        transfers_out -> Int8,
+        senders -> Int8,
+        confidence -> Float8,
+    }
+}
+
+diesel::table! {
+    evm_excluded_tokens (address, chain) {
+        address -> Text,
+        chain -> Text,
+        reason -> Text,
+        attempts -> Int8,
+        retry_after -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    evm_fee_history (chain, number) {
+        chain -> Text,
+        number -> Int8,
+        base_fee_per_gas -> Numeric,
+        gas_used_ratio -> Float8,
+        reward_percentiles -> Array<Float8>,
+        rewards -> Array<Numeric>,
+    }
+}
+
+diesel::table! {
+    evm_flagged_activity (hash, log_index, address) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        block_number -> Int8,
+        kind -> Text,
+        address -> Text,
+        counterparty -> Text,
+        list -> Text,
+    }
+}
+
+diesel::table! {
+    evm_flash_loans (hash, log_index, token_index) {
+        hash -> Text,
+        log_index -> Int8,
+        token_index -> Int8,
+        chain -> Text,
+        protocol -> Text,
+        lender -> Text,
+        initiator -> Nullable<Text>,
+        receiver -> Nullable<Text>,
+        token -> Nullable<Text>,
+        amount -> Text,
+        fee -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_governance_proposals (chain, governor, proposal_id) {
+        chain -> Text,
+        governor -> Text,
+        proposal_id -> Text,
+        hash -> Text,
+        proposer -> Text,
+        targets -> Array<Nullable<Text>>,
+        call_values -> Array<Nullable<Text>>,
+        signatures -> Array<Nullable<Text>>,
+        calldatas -> Array<Nullable<Text>>,
+        decoded_calls -> Nullable<Text>,
+        vote_start -> Text,
+        vote_end -> Text,
+        description -> Text,
+        status -> Text,
+    }
+}
+
+diesel::table! {
+    evm_governance_votes (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        governor -> Text,
+        proposal_id -> Text,
+        voter -> Text,
+        support -> Int8,
+        weight -> Text,
+        reason -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_lending_events (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        protocol -> Text,
+        market -> Text,
+        action -> Text,
+        user_address -> Text,
+        amount -> Text,
+        liquidator -> Nullable<Text>,
+        collateral -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_liquidations (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        block_number -> Int8,
+        protocol -> Text,
+        liquidator -> Nullable<Text>,
+        borrower -> Text,
+        collateral_token -> Nullable<Text>,
+        collateral_amount -> Nullable<Text>,
+        debt_token -> Text,
+        debt_amount -> Text,
+    }
+}
+
+diesel::table! {
+    evm_liquidity_events (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        dex -> Text,
+        contract -> Text,
+        pool -> Nullable<Text>,
+        event -> Text,
+        position_id -> Nullable<Text>,
+        owner -> Nullable<Text>,
+        recipient -> Nullable<Text>,
+        tick_lower -> Nullable<Int8>,
+        tick_upper -> Nullable<Int8>,
+        liquidity -> Nullable<Text>,
+        amount0 -> Text,
+        amount1 -> Text,
+    }
+}
+
+diesel::table! {
+    evm_log_transactions (hash) {
+        hash -> Text,
+        chain -> Text,
+        block_number -> Int8,
+        block_hash -> Text,
+        transaction_index -> Int8,
     }
 }
 
@@ -101,6 +526,300 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    evm_mev_events (chain, block_number, kind, transactions) {
+        chain -> Text,
+        block_number -> Int8,
+        kind -> Text,
+        attacker -> Text,
+        victim -> Nullable<Text>,
+        pools -> Array<Nullable<Text>>,
+        transactions -> Array<Nullable<Text>>,
+        profit -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_native_transfers (chain, hash, trace_address) {
+        hash -> Text,
+        trace_address -> Text,
+        chain -> Text,
+        block_number -> Int8,
+        transaction_index -> Int8,
+        kind -> Text,
+        from_address -> Text,
+        to_address -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    evm_new_addresses (address, chain) {
+        address -> Text,
+        chain -> Text,
+        block_number -> Int8,
+        hash -> Text,
+        role -> Text,
+    }
+}
+
+diesel::table! {
+    evm_nonstandard_tokens (chain, address) {
+        chain -> Text,
+        address -> Text,
+        kind -> Text,
+        source -> Text,
+        marked_at -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_outbox (id) {
+        id -> Int8,
+        chain -> Text,
+        topic -> Text,
+        payload -> Text,
+    }
+}
+
+diesel::table! {
+    evm_outbox_offsets (sink) {
+        sink -> Text,
+        last_id -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_parse_failures (parser, hash, log_index) {
+        parser -> Text,
+        hash -> Text,
+        log_index -> Int8,
+        reason -> Text,
+    }
+}
+
+diesel::table! {
+    evm_parsed_logs (parser, hash, log_index) {
+        parser -> Text,
+        hash -> Text,
+        log_index -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_permits (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        token -> Text,
+        owner -> Text,
+        spender -> Text,
+        value -> Text,
+        deadline -> Nullable<Text>,
+        kind -> Text,
+        nested -> Bool,
+    }
+}
+
+diesel::table! {
+    evm_producer_stats (miner, chain) {
+        miner -> Text,
+        chain -> Text,
+        first_block -> Int8,
+        last_block -> Int8,
+        blocks_produced -> Int8,
+        empty_blocks -> Int8,
+        fees_earned -> Numeric,
+        rewards_earned -> Numeric,
+        gas_used -> Numeric,
+        average_gas_used -> Numeric,
+    }
+}
+
+diesel::table! {
+    evm_protocol_deployments (chain, address) {
+        chain -> Text,
+        protocol -> Text,
+        address -> Text,
+        contract -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_reducer_checkpoints (chain, reducer) {
+        chain -> Text,
+        reducer -> Text,
+        block_number -> Int8,
+        block_hash -> Text,
+        updated_at -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_reducer_states (chain, reducer, key, block_number) {
+        chain -> Text,
+        reducer -> Text,
+        key -> Text,
+        block_number -> Int8,
+        value -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    evm_rpc_usage (chain, provider, method) {
+        chain -> Text,
+        provider -> Text,
+        method -> Text,
+        requests -> Int8,
+        errors -> Int8,
+        compute_units -> Int8,
+        credits -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_sampled_balances (chain, token, holder, block_number) {
+        chain -> Text,
+        token -> Text,
+        holder -> Text,
+        block_number -> Int8,
+        balance -> Text,
+    }
+}
+
+diesel::table! {
+    evm_signatures (hash, signature) {
+        hash -> Text,
+        kind -> Text,
+        signature -> Text,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    evm_stablecoin_blacklist (chain, token, account) {
+        chain -> Text,
+        token -> Text,
+        account -> Text,
+        blacklisted -> Bool,
+        block_number -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_stablecoin_events (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        block_number -> Int8,
+        token -> Text,
+        symbol -> Text,
+        kind -> Text,
+        account -> Text,
+        counterparty -> Nullable<Text>,
+        amount -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_staking_events (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        protocol -> Text,
+        action -> Text,
+        user_address -> Text,
+        amount -> Text,
+        shares -> Nullable<Text>,
+        request_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_staking_rewards (hash, log_index) {
+        hash -> Text,
+        log_index -> Int8,
+        chain -> Text,
+        protocol -> Text,
+        total_pooled -> Text,
+        total_shares -> Text,
+        rewards -> Nullable<Text>,
+        report_timestamp -> Text,
+    }
+}
+
+diesel::table! {
+    evm_state_diffs (hash, address, kind, slot) {
+        hash -> Text,
+        chain -> Text,
+        block_number -> Int8,
+        transaction_index -> Int8,
+        address -> Text,
+        kind -> Text,
+        slot -> Text,
+        before -> Nullable<Text>,
+        after -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_state_proofs (chain, account, block_number) {
+        chain -> Text,
+        account -> Text,
+        block_number -> Int8,
+        name -> Text,
+        balance -> Text,
+        nonce -> Int8,
+        code_hash -> Text,
+        storage_hash -> Text,
+        account_proof -> Array<Nullable<Text>>,
+        storage_proofs -> Jsonb,
+    }
+}
+
+diesel::table! {
+    evm_storage_values (chain, contract, slot, block_number) {
+        chain -> Text,
+        contract -> Text,
+        slot -> Text,
+        block_number -> Int8,
+        name -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    evm_table_stats (table_name, sampled_at) {
+        sampled_at -> Int8,
+        table_name -> Text,
+        inserted_rows -> Int8,
+        live_rows -> Int8,
+        table_bytes -> Int8,
+        index_bytes -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_token_prices (chain, token, block_number) {
+        chain -> Text,
+        token -> Text,
+        block_number -> Int8,
+        price_native -> Nullable<Float8>,
+        price_usd -> Nullable<Float8>,
+        swaps -> Int8,
+    }
+}
+
+diesel::table! {
+    evm_token_prices_external (chain, token, source, timestamp) {
+        chain -> Text,
+        token -> Text,
+        source -> Text,
+        timestamp -> Int8,
+        price_usd -> Float8,
+    }
+}
+
 diesel::table! {
     evm_transactions (hash) {
         block_hash -> Text,
@@ -120,6 +839,8 @@ diesel::table! {
         transaction_index -> Int8,
         transaction_type -> Nullable<Int8>,
         value -> Text,
+        input_zstd -> Nullable<Bytea>,
+        gas_efficiency -> Nullable<Float8>,
     }
 }
 
@@ -132,6 +853,9 @@ diesel::table! {
         log_index -> Int8,
         removed -> Bool,
         erc20_transfers_parsed -> Nullable<Bool>,
+        data_zstd -> Nullable<Bytea>,
+        decoded -> Nullable<Jsonb>,
+        block_number -> Nullable<Int8>,
     }
 }
 
@@ -143,6 +867,19 @@ diesel::table! {
         gas_used -> Text,
         hash -> Text,
         status -> Text,
+        revert_reason -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    evm_view_refreshes (name) {
+        name -> Text,
+        refreshed_at -> Nullable<Int8>,
+        duration_ms -> Nullable<Int8>,
+        row_count -> Nullable<Int8>,
+        refreshes -> Int8,
+        failures -> Int8,
+        error -> Nullable<Text>,
     }
 }
 
@@ -150,13 +887,75 @@ diesel::allow_tables_to_appear_in_same_query!(
     chains_indexed_state,
     contracts_adapters,
     evm_abis,
+    evm_address_clusters,
+    evm_address_labels,
+    evm_address_stats,
+    evm_aggregated_blocks,
+    evm_aggregated_transactions,
+    evm_api_keys,
+    evm_backfill_jobs,
+    evm_block_fees,
+    evm_block_times,
     evm_blocks,
+    evm_bridge_transfers,
+    evm_call_frames,
+    evm_call_samples,
+    evm_compression_dictionaries,
+    evm_contract_activity,
+    evm_contract_callers,
     evm_contracts,
     evm_contracts_interactions,
+    evm_dex_pools,
+    evm_dex_swaps,
+    evm_duplicate_logs,
+    evm_ens_records,
+    evm_ens_registrations,
+    evm_ens_resolvers,
+    evm_ens_reverse_nodes,
+    evm_erc20_supply,
+    evm_erc20_supply_changes,
     evm_erc20_tokens,
     evm_erc20_transfers,
+    evm_exchange_deposits,
+    evm_excluded_tokens,
+    evm_fee_history,
+    evm_flagged_activity,
+    evm_flash_loans,
+    evm_governance_proposals,
+    evm_governance_votes,
+    evm_lending_events,
+    evm_liquidations,
+    evm_liquidity_events,
+    evm_log_transactions,
     evm_methods,
+    evm_mev_events,
+    evm_native_transfers,
+    evm_new_addresses,
+    evm_nonstandard_tokens,
+    evm_outbox,
+    evm_outbox_offsets,
+    evm_parse_failures,
+    evm_parsed_logs,
+    evm_permits,
+    evm_producer_stats,
+    evm_protocol_deployments,
+    evm_reducer_checkpoints,
+    evm_reducer_states,
+    evm_rpc_usage,
+    evm_sampled_balances,
+    evm_signatures,
+    evm_stablecoin_blacklist,
+    evm_stablecoin_events,
+    evm_staking_events,
+    evm_staking_rewards,
+    evm_state_diffs,
+    evm_state_proofs,
+    evm_storage_values,
+    evm_table_stats,
+    evm_token_prices,
+    evm_token_prices_external,
     evm_transactions,
     evm_transactions_logs,
     evm_transactions_receipts,
+    evm_view_refreshes,
 );
@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Bool, Nullable, Text},
+    SqliteConnection,
+};
+use diesel_migrations::*;
+use log::*;
+
+use crate::chains::chains::Chain;
+
+use super::{
+    embedded::EmbeddedDatabase,
+    models::models::{
+        DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
+        DatabaseEVMTransactionReceipt,
+    },
+};
+
+pub const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite/");
+
+#[derive(QueryableByName, Debug)]
+struct BlockNumber {
+    #[diesel(sql_type = BigInt)]
+    number: i64,
+}
+
+/// Local storage for small chains or short ranges, e.g. tests and research notebooks. Only the
+/// blocks, transactions, receipts, logs and contracts are stored, with the same model structs
+/// used for Postgres. Arrays are stored as JSON text and the indexed blocks are read from
+/// `evm_blocks`, so no Redis server is needed.
+#[derive(Debug, Clone)]
+pub struct SqliteDatabase {
+    pub db_path: String,
+    pub chain: Chain,
+}
+
+impl SqliteDatabase {
+    pub fn new(db_url: String, chain: Chain) -> Result<Self> {
+        let db_path = get_sqlite_path(&db_url);
+
+        let mut connection =
+            SqliteConnection::establish(&db_path).expect("Unable to open the SQLite database");
+
+        connection
+            .run_pending_migrations(SQLITE_MIGRATIONS)
+            .expect("Unable to run the SQLite migrations");
+
+        info!("Using SQLite database {}", db_path);
+
+        Ok(Self { db_path, chain })
+    }
+
+    pub fn establish_connection(&self) -> SqliteConnection {
+        SqliteConnection::establish(&self.db_path).expect("Unable to open the SQLite database")
+    }
+}
+
+impl EmbeddedDatabase for SqliteDatabase {
+    fn get_indexed_blocks(&self) -> Result<HashSet<i64>> {
+        let mut connection = self.establish_connection();
+
+        let blocks = sql_query("SELECT number FROM evm_blocks WHERE chain = ?")
+            .bind::<Text, _>(self.chain.name)
+            .load::<BlockNumber>(&mut connection)?;
+
+        Ok(blocks.into_iter().map(|block| block.number).collect())
+    }
+
+    /// Stores the data of a batch of blocks in a single transaction, so a block is never
+    /// partially stored.
+    fn store_data(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        contracts: &Vec<DatabaseEVMContract>,
+    ) -> Result<()> {
+        let mut connection = self.establish_connection();
+
+        connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            for transaction in transactions {
+                store_transaction(connection, transaction)?;
+            }
+
+            for receipt in receipts {
+                store_receipt(connection, receipt)?;
+            }
+
+            for log in logs {
+                store_log(connection, log)?;
+            }
+
+            for contract in contracts {
+                store_contract(connection, contract)?;
+            }
+
+            // Blocks go last, they mark the range as indexed.
+            for block in blocks {
+                store_block(connection, block)?;
+            }
+
+            Ok(())
+        })?;
+
+        info!(
+            "Inserted: blocks ({}) transactions ({}) receipts ({}) logs ({}) contracts ({}) for chain {}",
+            blocks.len(),
+            transactions.len(),
+            receipts.len(),
+            logs.len(),
+            contracts.len(),
+            self.chain.name
+        );
+
+        Ok(())
+    }
+}
+
+fn store_block(connection: &mut SqliteConnection, block: &DatabaseEVMBlock) -> QueryResult<usize> {
+    sql_query(
+        "INSERT OR IGNORE INTO evm_blocks (base_fee_per_gas, chain, difficulty, extra_data, \
+        gas_limit, gas_used, block_hash, logs_bloom, miner, mix_hash, nonce, number, parent_hash, \
+        receipts_root, sha3_uncles, size, state_root, timestamp, total_difficulty, transactions, \
+        uncles) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind::<Text, _>(&block.base_fee_per_gas)
+    .bind::<Text, _>(&block.chain)
+    .bind::<Text, _>(&block.difficulty)
+    .bind::<Text, _>(&block.extra_data)
+    .bind::<Text, _>(&block.gas_limit)
+    .bind::<Text, _>(&block.gas_used)
+    .bind::<Text, _>(&block.block_hash)
+    .bind::<Text, _>(&block.logs_bloom)
+    .bind::<Text, _>(&block.miner)
+    .bind::<Text, _>(&block.mix_hash)
+    .bind::<Text, _>(&block.nonce)
+    .bind::<BigInt, _>(block.number)
+    .bind::<Text, _>(&block.parent_hash)
+    .bind::<Text, _>(&block.receipts_root)
+    .bind::<Text, _>(&block.sha3_uncles)
+    .bind::<BigInt, _>(block.size)
+    .bind::<Text, _>(&block.state_root)
+    .bind::<Text, _>(&block.timestamp)
+    .bind::<Text, _>(&block.total_difficulty)
+    .bind::<BigInt, _>(block.transactions)
+    .bind::<Text, _>(serde_json::to_string(&block.uncles).unwrap_or_default())
+    .execute(connection)
+}
+
+fn store_transaction(
+    connection: &mut SqliteConnection,
+    transaction: &DatabaseEVMTransaction,
+) -> QueryResult<usize> {
+    sql_query(
+        "INSERT OR IGNORE INTO evm_transactions (block_hash, block_number, chain, from_address, \
+        gas, gas_price, max_priority_fee_per_gas, max_fee_per_gas, hash, input, method, nonce, \
+        timestamp, to_address, transaction_index, transaction_type, value) \
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind::<Text, _>(&transaction.block_hash)
+    .bind::<BigInt, _>(transaction.block_number)
+    .bind::<Text, _>(&transaction.chain)
+    .bind::<Text, _>(&transaction.from_address)
+    .bind::<Text, _>(&transaction.gas)
+    .bind::<Text, _>(&transaction.gas_price)
+    .bind::<Text, _>(&transaction.max_priority_fee_per_gas)
+    .bind::<Text, _>(&transaction.max_fee_per_gas)
+    .bind::<Text, _>(&transaction.hash)
+    .bind::<Text, _>(&transaction.input)
+    .bind::<Text, _>(&transaction.method)
+    .bind::<Text, _>(&transaction.nonce)
+    .bind::<Text, _>(&transaction.timestamp)
+    .bind::<Text, _>(&transaction.to_address)
+    .bind::<BigInt, _>(transaction.transaction_index)
+    .bind::<BigInt, _>(transaction.transaction_type)
+    .bind::<Text, _>(&transaction.value)
+    .execute(connection)
+}
+
+fn store_receipt(
+    connection: &mut SqliteConnection,
+    receipt: &DatabaseEVMTransactionReceipt,
+) -> QueryResult<usize> {
+    sql_query(
+        "INSERT OR IGNORE INTO evm_transactions_receipts (contract_address, cumulative_gas_used, \
+        effective_gas_price, gas_used, hash, status, revert_reason) \
+        VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind::<Nullable<Text>, _>(&receipt.contract_address)
+    .bind::<Text, _>(&receipt.cumulative_gas_used)
+    .bind::<Text, _>(&receipt.effective_gas_price)
+    .bind::<Text, _>(&receipt.gas_used)
+    .bind::<Text, _>(&receipt.hash)
+    .bind::<Text, _>(&receipt.status)
+    .bind::<Nullable<Text>, _>(&receipt.revert_reason)
+    .execute(connection)
+}
+
+fn store_log(
+    connection: &mut SqliteConnection,
+    log: &DatabaseEVMTransactionLog,
+) -> QueryResult<usize> {
+    sql_query(
+        "INSERT OR IGNORE INTO evm_transactions_logs (address, topics, data, hash, log_index, \
+        removed) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind::<Text, _>(&log.address)
+    .bind::<Text, _>(serde_json::to_string(&log.topics).unwrap_or_default())
+    .bind::<Text, _>(&log.data)
+    .bind::<Text, _>(&log.hash)
+    .bind::<BigInt, _>(log.log_index)
+    .bind::<Bool, _>(log.removed)
+    .execute(connection)
+}
+
+fn store_contract(
+    connection: &mut SqliteConnection,
+    contract: &DatabaseEVMContract,
+) -> QueryResult<usize> {
+    sql_query(
+        "INSERT OR IGNORE INTO evm_contracts (block, chain, contract, creator, hash, parsed, \
+        verified) VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind::<BigInt, _>(contract.block)
+    .bind::<Text, _>(&contract.chain)
+    .bind::<Text, _>(&contract.contract)
+    .bind::<Text, _>(&contract.creator)
+    .bind::<Text, _>(&contract.hash)
+    .bind::<Bool, _>(contract.parsed)
+    .bind::<Bool, _>(contract.verified)
+    .execute(connection)
+}
+
+/// Path of the database file from a `sqlite://` or `sqlite:` URL.
+pub fn get_sqlite_path(db_url: &str) -> String {
+    db_url
+        .trim_start_matches("sqlite://")
+        .trim_start_matches("sqlite:")
+        .to_string()
+}
@@ -0,0 +1,98 @@
+use anyhow::{bail, Result};
+use diesel::{
+    dsl::sql,
+    expression::{SqlLiteral, TypedExpressionType},
+    sql_types::Bool,
+};
+
+/// How a store path handles the rows already stored, e.g. when a range is indexed again.
+/// Columns owned by the parsers, like `erc20_transfers_parsed`, are never overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertPolicy {
+    /// Keep the stored row.
+    Ignore,
+    /// Overwrite the stored row with the new one.
+    Replace,
+    /// Overwrite the stored row except with null values, so fields filled later, like the
+    /// revert reasons, aren't erased by a new fetch without them.
+    Merge,
+}
+
+impl UpsertPolicy {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "ignore" => Ok(Self::Ignore),
+            "replace" => Ok(Self::Replace),
+            "merge" => Ok(Self::Merge),
+            _ => bail!("Unknown upsert policy {}", name),
+        }
+    }
+}
+
+/// Upsert policy of each table written by `store_data`. Indexing a block again overwrites its
+/// data by default, so repairs and re-runs converge on the last fetched version.
+#[derive(Debug, Clone)]
+pub struct UpsertPolicies {
+    pub blocks: UpsertPolicy,
+    pub transactions: UpsertPolicy,
+    pub receipts: UpsertPolicy,
+    pub logs: UpsertPolicy,
+    pub contracts: UpsertPolicy,
+}
+
+impl Default for UpsertPolicies {
+    fn default() -> Self {
+        Self {
+            blocks: UpsertPolicy::Replace,
+            transactions: UpsertPolicy::Replace,
+            receipts: UpsertPolicy::Merge,
+            logs: UpsertPolicy::Replace,
+            contracts: UpsertPolicy::Ignore,
+        }
+    }
+}
+
+/// Policies from a comma separated list over the defaults, e.g. `blocks=ignore,receipts=replace`.
+pub fn get_upsert_policies(policies: &Option<String>) -> Result<UpsertPolicies> {
+    let mut upsert_policies = UpsertPolicies::default();
+
+    let policies = match policies {
+        Some(policies) => policies,
+        None => return Ok(upsert_policies),
+    };
+
+    for policy in policies
+        .split(",")
+        .filter(|policy| !policy.trim().is_empty())
+    {
+        let (table, table_policy) = match policy.split_once("=") {
+            Some((table, name)) => (table.trim(), UpsertPolicy::from_name(name.trim())?),
+            None => bail!("Invalid upsert policy {}, expected table=policy", policy),
+        };
+
+        match table {
+            "blocks" => upsert_policies.blocks = table_policy,
+            "transactions" => upsert_policies.transactions = table_policy,
+            "receipts" => upsert_policies.receipts = table_policy,
+            "logs" => upsert_policies.logs = table_policy,
+            "contracts" => upsert_policies.contracts = table_policy,
+            _ => bail!("Unknown upsert policy table {}", table),
+        }
+    }
+
+    Ok(upsert_policies)
+}
+
+/// New value of a nullable column that keeps the stored one when the new value is null.
+pub fn merged<ST: TypedExpressionType>(table: &str, column: &str) -> SqlLiteral<ST> {
+    sql::<ST>(&format!(
+        "COALESCE(EXCLUDED.{}, {}.{})",
+        column, table, column
+    ))
+}
+
+/// Whether an upserted row was inserted rather than updated, Postgres only sets `xmax` on the
+/// rows updated by the statement.
+pub fn is_inserted() -> SqlLiteral<Bool> {
+    sql::<Bool>("(xmax = 0)")
+}
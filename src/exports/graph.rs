@@ -0,0 +1,268 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Result};
+use diesel::prelude::*;
+use log::*;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    configs::graph_export_config::EVMGraphExportConfig,
+    db::{
+        db::EVMDatabase,
+        schema::{evm_erc20_transfers, evm_transactions},
+    },
+};
+
+/// Token of the native currency transfers in the edges.
+pub const NATIVE_TOKEN: &str = "native";
+
+/// Log index of the native transfers, they are the value of the transaction itself.
+pub const NATIVE_LOG_INDEX: i64 = -1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferEdge {
+    pub from_address: String,
+    pub to_address: String,
+    pub hash: String,
+    pub log_index: i64,
+    pub block_number: i64,
+    pub token: String,
+    pub value: String,
+}
+
+/// Exports the ERC-20 and native transfers of a block range as a graph of addresses. The graph
+/// is written as node and edge CSV files, with headers compatible with `neo4j-admin import` and
+/// Memgraph `LOAD CSV`, or ingested directly into Neo4j through its HTTP API.
+pub struct TransferGraphExporter {
+    pub db: EVMDatabase,
+    pub config: EVMGraphExportConfig,
+    pub client: Client,
+}
+
+impl TransferGraphExporter {
+    pub fn new(db: EVMDatabase, config: EVMGraphExportConfig) -> Self {
+        Self {
+            db,
+            config,
+            client: Client::new(),
+        }
+    }
+
+    pub async fn export(&self) -> Result<()> {
+        let mut nodes: HashSet<String> = HashSet::new();
+
+        let mut edges_writer = match &self.config.neo4j_url {
+            Some(_) => None,
+            None => {
+                std::fs::create_dir_all(&self.config.output)?;
+
+                let mut writer = BufWriter::new(File::create(
+                    Path::new(&self.config.output).join("edges.csv"),
+                )?);
+
+                writeln!(
+                    writer,
+                    ":START_ID,:END_ID,hash,log_index:long,block_number:long,token,value,:TYPE"
+                )?;
+
+                Some(writer)
+            }
+        };
+
+        let mut total_edges = 0;
+
+        let mut from_block = self.config.from_block;
+
+        while from_block <= self.config.to_block {
+            let to_block = (from_block + self.config.batch_size - 1).min(self.config.to_block);
+
+            let edges = self.get_transfer_edges(from_block, to_block)?;
+
+            for edge in edges.iter() {
+                nodes.insert(edge.from_address.clone());
+                nodes.insert(edge.to_address.clone());
+            }
+
+            match edges_writer.as_mut() {
+                Some(writer) => {
+                    for edge in edges.iter() {
+                        writeln!(
+                            writer,
+                            "{},{},{},{},{},{},{},TRANSFER",
+                            edge.from_address,
+                            edge.to_address,
+                            edge.hash,
+                            edge.log_index,
+                            edge.block_number,
+                            edge.token,
+                            edge.value
+                        )?;
+                    }
+                }
+                None => self.ingest_neo4j(&edges).await?,
+            }
+
+            total_edges += edges.len();
+
+            info!(
+                "Exported {} transfers for blocks {} to {} of chain {}.",
+                edges.len(),
+                from_block,
+                to_block,
+                self.config.chain.name
+            );
+
+            from_block = to_block + 1;
+        }
+
+        match edges_writer.as_mut() {
+            Some(writer) => {
+                writer.flush()?;
+
+                let mut nodes_writer = BufWriter::new(File::create(
+                    Path::new(&self.config.output).join("nodes.csv"),
+                )?);
+
+                writeln!(nodes_writer, "address:ID,:LABEL")?;
+
+                for node in nodes.iter() {
+                    writeln!(nodes_writer, "{},Address", node)?;
+                }
+
+                nodes_writer.flush()?;
+            }
+            None => (),
+        }
+
+        info!(
+            "Exported {} addresses and {} transfers for chain {}.",
+            nodes.len(),
+            total_edges,
+            self.config.chain.name
+        );
+
+        Ok(())
+    }
+
+    /// Native transfers come from the transactions with value, ERC-20 transfers are resolved to
+    /// the range through their transaction.
+    fn get_transfer_edges(&self, from_block: i64, to_block: i64) -> Result<Vec<TransferEdge>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let transactions = evm_transactions::table
+            .select((
+                evm_transactions::hash,
+                evm_transactions::block_number,
+                evm_transactions::from_address,
+                evm_transactions::to_address,
+                evm_transactions::value,
+            ))
+            .filter(evm_transactions::chain.eq(self.config.chain.name))
+            .filter(evm_transactions::block_number.ge(from_block))
+            .filter(evm_transactions::block_number.le(to_block))
+            .load::<(String, i64, String, String, String)>(&mut connection)?;
+
+        let mut edges = Vec::new();
+
+        let mut blocks = HashMap::new();
+
+        for (hash, block_number, from_address, to_address, value) in transactions {
+            blocks.insert(hash.clone(), block_number);
+
+            if !self.config.native || value == "0" {
+                continue;
+            }
+
+            edges.push(TransferEdge {
+                from_address,
+                to_address,
+                hash,
+                log_index: NATIVE_LOG_INDEX,
+                block_number,
+                token: NATIVE_TOKEN.to_string(),
+                value,
+            });
+        }
+
+        let hashes: Vec<String> = blocks.keys().cloned().collect();
+
+        let transfers = evm_erc20_transfers::table
+            .select((
+                evm_erc20_transfers::hash,
+                evm_erc20_transfers::log_index,
+                evm_erc20_transfers::token,
+                evm_erc20_transfers::from_address,
+                evm_erc20_transfers::to_address,
+                evm_erc20_transfers::value,
+            ))
+            .filter(evm_erc20_transfers::hash.eq_any(hashes))
+            .load::<(String, i64, String, String, String, String)>(&mut connection)?;
+
+        for (hash, log_index, token, from_address, to_address, value) in transfers {
+            edges.push(TransferEdge {
+                from_address,
+                to_address,
+                block_number: blocks[&hash],
+                hash,
+                log_index,
+                token,
+                value,
+            });
+        }
+
+        edges.sort_by_key(|edge| (edge.block_number, edge.log_index));
+
+        Ok(edges)
+    }
+
+    /// Merges the edges by hash and log index, so a range can be ingested more than once.
+    async fn ingest_neo4j(&self, edges: &Vec<TransferEdge>) -> Result<()> {
+        let url = match &self.config.neo4j_url {
+            Some(url) => url,
+            None => return Ok(()),
+        };
+
+        if edges.len() == 0 {
+            return Ok(());
+        }
+
+        let statement = json!({
+            "statements": [{
+                "statement": "UNWIND $edges AS edge \
+                    MERGE (from:Address {address: edge.from_address}) \
+                    MERGE (to:Address {address: edge.to_address}) \
+                    MERGE (from)-[transfer:TRANSFER {hash: edge.hash, log_index: edge.log_index}]->(to) \
+                    SET transfer.block_number = edge.block_number, \
+                    transfer.token = edge.token, transfer.value = edge.value",
+                "parameters": { "edges": edges },
+            }]
+        });
+
+        let mut request = self.client.post(url).json(&statement);
+
+        match &self.config.neo4j_user {
+            Some(user) => request = request.basic_auth(user, self.config.neo4j_password.clone()),
+            None => (),
+        }
+
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            bail!("Neo4j responded with status {}", response.status());
+        }
+
+        let body: serde_json::Value = response.json().await?;
+
+        match body["errors"].as_array() {
+            Some(errors) if errors.len() > 0 => bail!("Neo4j returned errors: {:?}", errors),
+            _ => Ok(()),
+        }
+    }
+}
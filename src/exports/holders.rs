@@ -0,0 +1,297 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Nullable, Text},
+};
+use ethers::types::{H160, U256};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::db::EVMDatabase, query::coverage::TRANSFER_TOPIC, tokens::nonstandard::get_nonstandard_kind,
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TokenStandard {
+    Erc20,
+    Erc721,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotFormat {
+    Csv,
+    Json,
+}
+
+#[derive(QueryableByName, Debug)]
+struct Erc20Balance {
+    #[diesel(sql_type = Text)]
+    address: String,
+    #[diesel(sql_type = Text)]
+    balance: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    balance_decimal: Option<String>,
+}
+
+#[derive(QueryableByName, Debug)]
+struct Erc721Owner {
+    #[diesel(sql_type = Text)]
+    token_id: String,
+    #[diesel(sql_type = Text)]
+    owner: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenHolder {
+    pub address: String,
+    /// Raw balance for ERC-20 tokens, amount of owned tokens for ERC-721 collections.
+    pub balance: String,
+    /// Balance with the token decimals, when they are known.
+    pub balance_decimal: Option<String>,
+    /// Owned tokens of ERC-721 collections.
+    pub token_ids: Option<Vec<String>>,
+}
+
+/// Balances of the holders of a token as of a block, rebuilt from the indexed transfers. ERC-20
+/// balances sum the parsed transfers, while ERC-721 owners are the recipients of the last
+/// `Transfer` log of each token, read from the raw logs since the transfers parser skips them.
+/// The snapshot is only complete when the transfers are indexed from the token deployment.
+/// Rebasing and fee on transfer tokens use the latest sampled balances of the watched holders
+/// instead, since their transfers don't add up to the balances.
+pub struct HolderSnapshotter {
+    pub db: EVMDatabase,
+}
+
+impl HolderSnapshotter {
+    pub fn new(db: EVMDatabase) -> Self {
+        Self { db }
+    }
+
+    pub fn snapshot(
+        &self,
+        token: &String,
+        standard: TokenStandard,
+        block: i64,
+    ) -> Result<Vec<TokenHolder>> {
+        let token = token.to_lowercase();
+
+        let holders = match standard {
+            TokenStandard::Erc20 => match get_nonstandard_kind(&self.db, &token)? {
+                Some(kind) => {
+                    warn!(
+                        "Token {} is marked as {}, using the sampled balances of the watched holders.",
+                        token, kind
+                    );
+
+                    self.get_sampled_holders(&token, block)?
+                }
+                None => self.get_erc20_holders(&token, block)?,
+            },
+            TokenStandard::Erc721 => self.get_erc721_holders(&token, block)?,
+        };
+
+        info!(
+            "Found {} holders of token {} at block {} of chain {}.",
+            holders.len(),
+            token,
+            block,
+            self.db.chain.name
+        );
+
+        Ok(holders)
+    }
+
+    fn get_erc20_holders(&self, token: &String, block: i64) -> Result<Vec<TokenHolder>> {
+        let mut connection = self.db.establish_read_connection();
+
+        // Transfers of backfilled logs are located through the log transactions.
+        let balances = sql_query(
+            "WITH transfers AS ( \
+                SELECT tr.from_address, tr.to_address, tr.value::NUMERIC AS value \
+                FROM evm_erc20_transfers tr \
+                LEFT JOIN evm_transactions t ON t.hash = tr.hash \
+                LEFT JOIN evm_log_transactions b ON b.hash = tr.hash \
+                WHERE tr.token = $2 AND COALESCE(t.chain, b.chain) = $1 \
+                AND COALESCE(t.block_number, b.block_number) <= $3 \
+            ), balances AS ( \
+                SELECT address, SUM(amount) AS balance FROM ( \
+                    SELECT to_address AS address, value AS amount FROM transfers \
+                    UNION ALL \
+                    SELECT from_address AS address, -value AS amount FROM transfers \
+                ) movements \
+                WHERE address <> $4 \
+                GROUP BY address \
+                HAVING SUM(amount) > 0 \
+            ) \
+            SELECT bl.address, bl.balance::TEXT AS balance, \
+            CASE WHEN k.decimals BETWEEN 0 AND 255 \
+            THEN ((bl.balance::TEXT || 'e-' || k.decimals)::NUMERIC)::TEXT END AS balance_decimal \
+            FROM balances bl \
+            LEFT JOIN evm_erc20_tokens k ON k.chain = $1 AND k.address = $2 \
+            ORDER BY bl.balance DESC, bl.address",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(token)
+        .bind::<BigInt, _>(block)
+        .bind::<Text, _>(format!("{:?}", H160::zero()))
+        .load::<Erc20Balance>(&mut connection)?;
+
+        let holders = balances
+            .into_iter()
+            .map(|balance| TokenHolder {
+                address: balance.address,
+                balance: balance.balance,
+                balance_decimal: balance.balance_decimal,
+                token_ids: None,
+            })
+            .collect();
+
+        Ok(holders)
+    }
+
+    /// Latest `balanceOf` sample of each watched holder up to the block.
+    fn get_sampled_holders(&self, token: &String, block: i64) -> Result<Vec<TokenHolder>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let balances = sql_query(
+            "WITH balances AS ( \
+                SELECT DISTINCT ON (holder) holder AS address, balance::NUMERIC AS balance \
+                FROM evm_sampled_balances \
+                WHERE chain = $1 AND token = $2 AND block_number <= $3 \
+                ORDER BY holder, block_number DESC \
+            ) \
+            SELECT bl.address, bl.balance::TEXT AS balance, \
+            CASE WHEN k.decimals BETWEEN 0 AND 255 \
+            THEN ((bl.balance::TEXT || 'e-' || k.decimals)::NUMERIC)::TEXT END AS balance_decimal \
+            FROM balances bl \
+            LEFT JOIN evm_erc20_tokens k ON k.chain = $1 AND k.address = $2 \
+            WHERE bl.balance > 0 \
+            ORDER BY bl.balance DESC, bl.address",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(token)
+        .bind::<BigInt, _>(block)
+        .load::<Erc20Balance>(&mut connection)?;
+
+        let holders = balances
+            .into_iter()
+            .map(|balance| TokenHolder {
+                address: balance.address,
+                balance: balance.balance,
+                balance_decimal: balance.balance_decimal,
+                token_ids: None,
+            })
+            .collect();
+
+        Ok(holders)
+    }
+
+    fn get_erc721_holders(&self, token: &String, block: i64) -> Result<Vec<TokenHolder>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let owners = sql_query(
+            "SELECT DISTINCT ON (l.topics[4]) l.topics[4] AS token_id, \
+            '0x' || RIGHT(l.topics[3], 40) AS owner \
+            FROM evm_transactions_logs l \
+            LEFT JOIN evm_transactions t ON t.hash = l.hash \
+            LEFT JOIN evm_log_transactions b ON b.hash = l.hash \
+            WHERE l.address = $2 AND l.topics[1] = $4 AND array_length(l.topics, 1) = 4 \
+            AND NOT l.removed AND COALESCE(t.chain, b.chain) = $1 \
+            AND COALESCE(t.block_number, b.block_number) <= $3 \
+            ORDER BY l.topics[4], COALESCE(t.block_number, b.block_number) DESC, \
+            COALESCE(t.transaction_index, b.transaction_index) DESC, l.log_index DESC",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(token)
+        .bind::<BigInt, _>(block)
+        .bind::<Text, _>(TRANSFER_TOPIC)
+        .load::<Erc721Owner>(&mut connection)?;
+
+        let zero_address = format!("{:?}", H160::zero());
+
+        let mut tokens: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for owner in owners {
+            // Burned tokens are owned by the zero address.
+            if owner.owner == zero_address {
+                continue;
+            }
+
+            let token_id = match U256::from_str_radix(owner.token_id.trim_start_matches("0x"), 16) {
+                Ok(token_id) => token_id.to_string(),
+                Err(_) => owner.token_id,
+            };
+
+            tokens.entry(owner.owner).or_default().push(token_id);
+        }
+
+        let mut holders: Vec<TokenHolder> = tokens
+            .into_iter()
+            .map(|(address, token_ids)| TokenHolder {
+                address,
+                balance: token_ids.len().to_string(),
+                balance_decimal: None,
+                token_ids: Some(token_ids),
+            })
+            .collect();
+
+        holders.sort_by_key(|holder| std::cmp::Reverse(holder.token_ids.as_ref().unwrap().len()));
+
+        Ok(holders)
+    }
+}
+
+/// Writes the holders to the `output` file, or prints them when there is none.
+pub fn write_holders(
+    holders: &Vec<TokenHolder>,
+    format: SnapshotFormat,
+    output: &Option<String>,
+) -> Result<()> {
+    let mut writer: BufWriter<Box<dyn Write>> = match output {
+        Some(path) => BufWriter::new(Box::new(File::create(path)?)),
+        None => BufWriter::new(Box::new(std::io::stdout())),
+    };
+
+    match format {
+        SnapshotFormat::Json => {
+            writeln!(writer, "{}", serde_json::to_string_pretty(holders)?)?;
+        }
+        SnapshotFormat::Csv => {
+            writeln!(writer, "address,balance,balance_decimal,token_ids")?;
+
+            for holder in holders {
+                writeln!(
+                    writer,
+                    "{},{},{},{}",
+                    holder.address,
+                    holder.balance,
+                    holder.balance_decimal.clone().unwrap_or_default(),
+                    holder.token_ids.clone().unwrap_or_default().join(" ")
+                )?;
+            }
+        }
+    }
+
+    match writer.flush() {
+        Ok(_) => Ok(()),
+        Err(err) => bail!("Unable to write the holders: {}", err),
+    }
+}
+
+/// Reads the holders of a snapshot written in JSON.
+pub fn read_holders(path: &String) -> Result<Vec<TokenHolder>> {
+    let file = std::fs::read_to_string(path)?;
+
+    match serde_json::from_str(&file) {
+        Ok(holders) => Ok(holders),
+        Err(err) => bail!("Unable to parse the holder snapshot: {}", err),
+    }
+}
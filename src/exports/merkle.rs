@@ -0,0 +1,287 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use anyhow::{bail, Result};
+use ethers::{
+    types::{H160, U256},
+    utils::{keccak256, to_checksum},
+};
+use log::*;
+use serde::Serialize;
+
+use super::holders::TokenHolder;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AirdropClaim {
+    pub index: u64,
+    /// Hex encoded amount, as the distributor contracts read it.
+    pub amount: String,
+    pub proof: Vec<String>,
+}
+
+/// Merkle tree of an airdrop in the format of the Uniswap merkle distributor, which most
+/// distributor contracts follow. Each leaf is `keccak256(abi.encodePacked(uint256 index,
+/// address account, uint256 amount))` and pairs are hashed sorted, so the proofs verify with
+/// OpenZeppelin `MerkleProof`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AirdropTree {
+    pub merkle_root: String,
+    pub token_total: String,
+    /// Claims by checksummed address.
+    pub claims: BTreeMap<String, AirdropClaim>,
+}
+
+/// Builds the airdrop tree of the holders of a snapshot, each one can claim its balance.
+pub fn get_airdrop_tree(holders: &Vec<TokenHolder>) -> Result<AirdropTree> {
+    let mut balances: BTreeMap<H160, U256> = BTreeMap::new();
+
+    for holder in holders {
+        let address = match H160::from_str(&holder.address) {
+            Ok(address) => address,
+            Err(err) => bail!("Invalid holder address {}: {}", holder.address, err),
+        };
+
+        let amount = match U256::from_dec_str(&holder.balance) {
+            Ok(amount) => amount,
+            Err(err) => bail!("Invalid balance {}: {:?}", holder.balance, err),
+        };
+
+        if amount.is_zero() {
+            continue;
+        }
+
+        let balance = balances.entry(address).or_default();
+
+        *balance = balance.saturating_add(amount);
+    }
+
+    if balances.is_empty() {
+        bail!("The snapshot has no holders to build the airdrop from");
+    }
+
+    let mut token_total = U256::zero();
+
+    let mut leaves = Vec::new();
+
+    for (index, (address, amount)) in balances.iter().enumerate() {
+        token_total = token_total.saturating_add(*amount);
+
+        leaves.push(get_leaf(index as u64, address, amount));
+    }
+
+    let tree = MerkleTree::new(leaves.clone());
+
+    let mut claims = BTreeMap::new();
+
+    for (index, (address, amount)) in balances.iter().enumerate() {
+        claims.insert(
+            to_checksum(address, None),
+            AirdropClaim {
+                index: index as u64,
+                amount: format!("{:#x}", amount),
+                proof: tree
+                    .get_proof(&leaves[index])
+                    .iter()
+                    .map(|hash| format!("0x{}", hex::encode(hash)))
+                    .collect(),
+            },
+        );
+    }
+
+    info!(
+        "Built the airdrop tree of {} claims with root 0x{}.",
+        claims.len(),
+        hex::encode(tree.get_root())
+    );
+
+    Ok(AirdropTree {
+        merkle_root: format!("0x{}", hex::encode(tree.get_root())),
+        token_total: format!("{:#x}", token_total),
+        claims,
+    })
+}
+
+/// Packs the leaf as `abi.encodePacked` does: the index and the amount as 32 bytes big endian
+/// words around the 20 bytes of the address.
+fn get_leaf(index: u64, address: &H160, amount: &U256) -> [u8; 32] {
+    let mut packed = [0u8; 84];
+
+    U256::from(index).to_big_endian(&mut packed[..32]);
+
+    packed[32..52].copy_from_slice(address.as_bytes());
+
+    amount.to_big_endian(&mut packed[52..]);
+
+    keccak256(packed)
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    match a <= b {
+        true => keccak256([a.as_slice(), b.as_slice()].concat()),
+        false => keccak256([b.as_slice(), a.as_slice()].concat()),
+    }
+}
+
+/// Tree of sorted leaves, an element without a sibling moves up to the next layer as it is.
+struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    fn new(mut leaves: Vec<[u8; 32]>) -> Self {
+        leaves.sort();
+        leaves.dedup();
+
+        let mut layers = vec![leaves];
+
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+
+            layers.push(next);
+        }
+
+        Self { layers }
+    }
+
+    fn get_root(&self) -> [u8; 32] {
+        self.layers.last().unwrap()[0]
+    }
+
+    fn get_proof(&self, leaf: &[u8; 32]) -> Vec<[u8; 32]> {
+        let mut index = match self.layers[0].binary_search(leaf) {
+            Ok(index) => index,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut proof = Vec::new();
+
+        for layer in self.layers.iter().take(self.layers.len() - 1) {
+            let sibling = index ^ 1;
+
+            if sibling < layer.len() {
+                proof.push(layer[sibling]);
+            }
+
+            index /= 2;
+        }
+
+        proof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Leaves, proofs and root of the `parseBalanceMap` test of the Uniswap merkle distributor,
+    /// the first wallet claims 200 with index 0.
+    const UNISWAP_WALLET: &str = "0x17ec8597ff92C3F44523bDc65BF0f1bE632917ff";
+
+    const UNISWAP_LEAVES: [&str; 3] = [
+        "0xd31de46890d4a77baeebddbd77bf73b5c626397b73ee8c69b51efe4c9a5a72fa",
+        "0xbfeb956a3b705056020a3b64c540bff700c0f6c96c55c0a5fcab57124cb36f7b",
+        "0xceaacce7533111e902cc548e961d77b23a4d8cd073c6b68ccf55c62bd47fc36b",
+    ];
+
+    const UNISWAP_ROOT: &str = "0x2ec9c2fc2a55df417ba88ecd833f165fa3c5941772ebaf8c5f4debe33f4d1b12";
+
+    fn to_bytes(hash: &str) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+
+        bytes.copy_from_slice(&hex::decode(hash.trim_start_matches("0x")).unwrap());
+
+        bytes
+    }
+
+    fn to_hex(hash: &[u8; 32]) -> String {
+        format!("0x{}", hex::encode(hash))
+    }
+
+    /// Same as OpenZeppelin `MerkleProof.verify`.
+    fn verify(proof: &Vec<[u8; 32]>, root: &[u8; 32], leaf: &[u8; 32]) -> bool {
+        let computed = proof
+            .iter()
+            .fold(*leaf, |computed, sibling| hash_pair(&computed, sibling));
+
+        computed == *root
+    }
+
+    #[test]
+    fn leaf_matches_uniswap_distributor() {
+        let address = H160::from_str(UNISWAP_WALLET).unwrap();
+
+        let leaf = get_leaf(0, &address, &U256::from(200));
+
+        assert_eq!(to_hex(&leaf), UNISWAP_LEAVES[0]);
+    }
+
+    #[test]
+    fn tree_matches_uniswap_distributor() {
+        let leaves: Vec<[u8; 32]> = UNISWAP_LEAVES.iter().map(|leaf| to_bytes(leaf)).collect();
+
+        let tree = MerkleTree::new(leaves.clone());
+
+        assert_eq!(to_hex(&tree.get_root()), UNISWAP_ROOT);
+
+        let proofs: Vec<Vec<String>> = leaves
+            .iter()
+            .map(|leaf| tree.get_proof(leaf).iter().map(to_hex).collect())
+            .collect();
+
+        assert_eq!(
+            proofs,
+            vec![
+                vec!["0x2a411ed78501edb696adca9e41e78d8256b61cfac45612fa0434d7cf87d916c6"],
+                vec![UNISWAP_LEAVES[2], UNISWAP_LEAVES[0]],
+                vec![UNISWAP_LEAVES[1], UNISWAP_LEAVES[0]],
+            ]
+        );
+    }
+
+    #[test]
+    fn airdrop_proofs_verify() {
+        let holders: Vec<TokenHolder> = [
+            (UNISWAP_WALLET, "200"),
+            ("0x63fc2ad3d021a4af7d8d4d9a4d6c1b8ba1e2d1b1", "300"),
+            ("0xd5c6ed7f7b9a8a1e5ea2f0a1a9d5b4c3e2f1a0b9", "250"),
+            ("0x0000000000000000000000000000000000000001", "0"),
+        ]
+        .iter()
+        .map(|(address, balance)| TokenHolder {
+            address: address.to_string(),
+            balance: balance.to_string(),
+            balance_decimal: None,
+            token_ids: None,
+        })
+        .collect();
+
+        let tree = get_airdrop_tree(&holders).unwrap();
+
+        assert_eq!(tree.claims.len(), 3);
+
+        assert_eq!(tree.token_total, "0x2ee");
+
+        let root = to_bytes(&tree.merkle_root);
+
+        for (address, claim) in tree.claims.iter() {
+            let address = H160::from_str(address).unwrap();
+
+            let amount = U256::from_str_radix(&claim.amount, 16).unwrap();
+
+            let leaf = get_leaf(claim.index, &address, &amount);
+
+            let proof = claim.proof.iter().map(|hash| to_bytes(hash)).collect();
+
+            assert!(verify(&proof, &root, &leaf));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore};
+use serde_json::{json, Value};
+
+/// Column of a lake table, the type is the Delta Lake primitive type name or `array` for an
+/// array of strings.
+pub type LakeColumn = (&'static str, &'static str, bool);
+
+/// File of a table written in a commit, the path is relative to the table.
+#[derive(Debug, Clone)]
+pub struct DeltaFile {
+    pub path: String,
+    pub partition: i64,
+    pub size: usize,
+    pub rows: usize,
+}
+
+/// Delta Lake transaction log of a table, stored in its `_delta_log` directory. Each commit is
+/// a new JSON version adding the written Parquet files, the first one also creates the table
+/// with its schema. Versions are not written atomically, so a table must have a single writer.
+#[derive(Debug, Clone)]
+pub struct DeltaTable {
+    pub store: Arc<dyn ObjectStore>,
+    pub path: String,
+    pub columns: &'static [LakeColumn],
+    pub partition_column: &'static str,
+}
+
+impl DeltaTable {
+    pub fn new(
+        store: Arc<dyn ObjectStore>,
+        path: String,
+        columns: &'static [LakeColumn],
+        partition_column: &'static str,
+    ) -> Self {
+        Self {
+            store,
+            path,
+            columns,
+            partition_column,
+        }
+    }
+
+    /// Last committed version, `None` when the table doesn't exist yet.
+    pub async fn get_version(&self) -> Result<Option<i64>> {
+        let log_path = Path::from(format!("{}/_delta_log", self.path));
+
+        let objects: Vec<_> = self
+            .store
+            .list(Some(&log_path))
+            .await?
+            .try_collect()
+            .await?;
+
+        let version = objects
+            .iter()
+            .filter_map(|object| object.location.filename())
+            .filter_map(|name| name.strip_suffix(".json"))
+            .filter_map(|version| version.parse::<i64>().ok())
+            .max();
+
+        Ok(version)
+    }
+
+    pub async fn commit(&self, files: &Vec<DeltaFile>) -> Result<i64> {
+        let now = get_timestamp();
+
+        let version = match self.get_version().await? {
+            Some(version) => version + 1,
+            None => 0,
+        };
+
+        let mut actions: Vec<Value> = Vec::new();
+
+        if version == 0 {
+            actions.push(json!({
+                "protocol": { "minReaderVersion": 1, "minWriterVersion": 2 }
+            }));
+
+            actions.push(json!({
+                "metaData": {
+                    "id": get_uuid(),
+                    "format": { "provider": "parquet", "options": {} },
+                    "schemaString": self.get_schema().to_string(),
+                    "partitionColumns": [self.partition_column],
+                    "configuration": {},
+                    "createdTime": now,
+                }
+            }));
+        }
+
+        for file in files {
+            actions.push(json!({
+                "add": {
+                    "path": file.path,
+                    "partitionValues": { self.partition_column: file.partition.to_string() },
+                    "size": file.size,
+                    "modificationTime": now,
+                    "dataChange": true,
+                    "stats": json!({ "numRecords": file.rows }).to_string(),
+                }
+            }));
+        }
+
+        actions.push(json!({
+            "commitInfo": { "timestamp": now, "operation": "WRITE" }
+        }));
+
+        let commit: Vec<String> = actions.iter().map(|action| action.to_string()).collect();
+
+        let path = Path::from(format!("{}/_delta_log/{:020}.json", self.path, version));
+
+        self.store
+            .put(&path, Bytes::from(commit.join("\n")))
+            .await?;
+
+        Ok(version)
+    }
+
+    /// Table schema in the Delta Lake JSON format, with the partition column last.
+    fn get_schema(&self) -> Value {
+        let mut fields: Vec<Value> = self
+            .columns
+            .iter()
+            .map(|(name, kind, nullable)| {
+                let kind = match *kind {
+                    "array" => json!({
+                        "type": "array",
+                        "elementType": "string",
+                        "containsNull": true
+                    }),
+                    kind => json!(kind),
+                };
+
+                json!({ "name": name, "type": kind, "nullable": nullable, "metadata": {} })
+            })
+            .collect();
+
+        fields.push(json!({
+            "name": self.partition_column,
+            "type": "long",
+            "nullable": false,
+            "metadata": {}
+        }));
+
+        json!({ "type": "struct", "fields": fields })
+    }
+}
+
+pub fn get_timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Random version 4 UUID.
+pub fn get_uuid() -> String {
+    let bytes = (rand::random::<u128>() & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+
+    let hex = format!("{:032x}", bytes);
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use arrow::{
+    datatypes::{DataType, Field, Schema},
+    json::reader::{Decoder, DecoderOptions},
+    record_batch::RecordBatch,
+};
+use bytes::Bytes;
+use log::*;
+use object_store::{path::Path, ObjectStore};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    archive::archive::get_object_store,
+    db::models::models::{
+        DatabaseEVMBlock, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
+        DatabaseEVMTransactionReceipt,
+    },
+};
+
+use super::delta::{get_uuid, DeltaFile, DeltaTable, LakeColumn};
+
+/// Blocks of each partition of the lake tables.
+pub const LAKE_PARTITION_SIZE: i64 = 100_000;
+
+pub const LAKE_PARTITION_COLUMN: &str = "block_group";
+
+pub const BLOCKS_COLUMNS: &[LakeColumn] = &[
+    ("base_fee_per_gas", "string", false),
+    ("chain", "string", false),
+    ("difficulty", "string", false),
+    ("extra_data", "string", false),
+    ("gas_limit", "string", false),
+    ("gas_used", "string", false),
+    ("block_hash", "string", false),
+    ("logs_bloom", "string", false),
+    ("miner", "string", false),
+    ("mix_hash", "string", false),
+    ("nonce", "string", false),
+    ("number", "long", false),
+    ("parent_hash", "string", false),
+    ("receipts_root", "string", false),
+    ("sha3_uncles", "string", false),
+    ("size", "long", false),
+    ("state_root", "string", false),
+    ("timestamp", "string", false),
+    ("total_difficulty", "string", false),
+    ("transactions", "long", false),
+    ("uncles", "array", false),
+];
+
+pub const TRANSACTIONS_COLUMNS: &[LakeColumn] = &[
+    ("block_hash", "string", false),
+    ("block_number", "long", false),
+    ("chain", "string", false),
+    ("from_address", "string", false),
+    ("gas", "string", false),
+    ("gas_price", "string", false),
+    ("max_priority_fee_per_gas", "string", false),
+    ("max_fee_per_gas", "string", false),
+    ("hash", "string", false),
+    ("input", "string", false),
+    ("method", "string", false),
+    ("nonce", "string", false),
+    ("timestamp", "string", false),
+    ("to_address", "string", false),
+    ("transaction_index", "long", false),
+    ("transaction_type", "long", false),
+    ("value", "string", false),
+];
+
+pub const RECEIPTS_COLUMNS: &[LakeColumn] = &[
+    ("block_number", "long", false),
+    ("contract_address", "string", true),
+    ("cumulative_gas_used", "string", false),
+    ("effective_gas_price", "string", false),
+    ("gas_used", "string", false),
+    ("hash", "string", false),
+    ("status", "string", false),
+    ("revert_reason", "string", true),
+];
+
+pub const LOGS_COLUMNS: &[LakeColumn] = &[
+    ("block_number", "long", false),
+    ("address", "string", false),
+    ("topics", "array", false),
+    ("data", "string", false),
+    ("hash", "string", false),
+    ("log_index", "long", false),
+    ("removed", "boolean", false),
+];
+
+pub const LAKE_TABLES: &[(&str, &[LakeColumn])] = &[
+    ("blocks", BLOCKS_COLUMNS),
+    ("transactions", TRANSACTIONS_COLUMNS),
+    ("receipts", RECEIPTS_COLUMNS),
+    ("logs", LOGS_COLUMNS),
+];
+
+/// Writes the indexed blocks, transactions, receipts and logs as Delta Lake tables of Parquet
+/// files, at `<lake url>/<chain>/<table>`, so query engines like Spark, Trino or DuckDB read them
+/// without a separate ETL. Tables are partitioned by `block_group`, the block number divided by
+/// `LAKE_PARTITION_SIZE`. Rows are kept in memory until `range_size` blocks are indexed, so
+/// files aren't too small, and the ones of a partial range are written with `flush` when the
+/// indexer stops.
+#[derive(Debug, Clone)]
+pub struct LakeWriter {
+    pub store: Arc<dyn ObjectStore>,
+    pub prefix: String,
+    pub chain: &'static str,
+    pub range_size: usize,
+    pending: Arc<tokio::sync::Mutex<(usize, HashMap<&'static str, Vec<Value>>)>>,
+}
+
+impl LakeWriter {
+    pub fn new(url: &String, chain: &'static str, range_size: usize) -> Result<Self> {
+        let (store, prefix) = get_object_store(url)?;
+
+        info!("Writing the lake tables to {}.", url);
+
+        Ok(Self {
+            store,
+            prefix,
+            chain,
+            range_size: range_size.max(1),
+            pending: Arc::new(tokio::sync::Mutex::new((0, HashMap::new()))),
+        })
+    }
+
+    pub async fn write(
+        &self,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        // Receipts and logs only include their transaction hash.
+        let block_numbers: HashMap<&String, i64> = transactions
+            .iter()
+            .map(|transaction| (&transaction.hash, transaction.block_number))
+            .collect();
+
+        let mut pending = self.pending.lock().await;
+
+        pending.0 += blocks.len();
+
+        let rows = &mut pending.1;
+
+        rows.entry("blocks")
+            .or_default()
+            .extend(get_rows(blocks, |_| None)?);
+
+        rows.entry("transactions")
+            .or_default()
+            .extend(get_rows(transactions, |_| None)?);
+
+        rows.entry("receipts")
+            .or_default()
+            .extend(get_rows(receipts, |receipt| {
+                block_numbers.get(&receipt.hash).cloned()
+            })?);
+
+        rows.entry("logs")
+            .or_default()
+            .extend(get_rows(logs, |log| block_numbers.get(&log.hash).cloned())?);
+
+        if pending.0 < self.range_size {
+            return Ok(());
+        }
+
+        self.write_pending(&mut pending).await
+    }
+
+    /// Writes the rows that don't fill a range yet, so they aren't lost when the indexer stops.
+    pub async fn flush(&self) -> Result<()> {
+        let mut pending = self.pending.lock().await;
+
+        if pending.0 == 0 {
+            return Ok(());
+        }
+
+        self.write_pending(&mut pending).await
+    }
+
+    async fn write_pending(
+        &self,
+        pending: &mut (usize, HashMap<&'static str, Vec<Value>>),
+    ) -> Result<()> {
+        for (table, columns) in LAKE_TABLES {
+            let table_rows = match pending.1.remove(table) {
+                Some(table_rows) if table_rows.len() > 0 => table_rows,
+                _ => continue,
+            };
+
+            match self.write_table(table, columns, &table_rows).await {
+                Ok(_) => (),
+                Err(err) => {
+                    // Keep the rows to retry with the next range.
+                    pending.1.insert(*table, table_rows);
+
+                    return Err(err);
+                }
+            }
+        }
+
+        pending.0 = 0;
+
+        Ok(())
+    }
+
+    async fn write_table(
+        &self,
+        table: &str,
+        columns: &'static [LakeColumn],
+        rows: &Vec<Value>,
+    ) -> Result<()> {
+        let path = match self.prefix.is_empty() {
+            true => format!("{}/{}", self.chain, table),
+            false => format!("{}/{}/{}", self.prefix, self.chain, table),
+        };
+
+        let number_column = match table {
+            "blocks" => "number",
+            _ => "block_number",
+        };
+
+        let mut partitions: BTreeMap<i64, Vec<Value>> = BTreeMap::new();
+
+        for row in rows {
+            let block_number = row[number_column].as_i64().unwrap_or_default();
+
+            partitions
+                .entry(block_number / LAKE_PARTITION_SIZE)
+                .or_default()
+                .push(row.clone());
+        }
+
+        let schema = Arc::new(get_arrow_schema(columns));
+
+        let mut files = Vec::new();
+
+        for (partition, partition_rows) in partitions {
+            let parquet = get_parquet(schema.clone(), partition_rows.clone())?;
+
+            let file = format!(
+                "{}={}/part-{}.snappy.parquet",
+                LAKE_PARTITION_COLUMN,
+                partition,
+                get_uuid()
+            );
+
+            self.store
+                .put(
+                    &Path::from(format!("{}/{}", path, file)),
+                    Bytes::from(parquet.clone()),
+                )
+                .await?;
+
+            files.push(DeltaFile {
+                path: file,
+                partition,
+                size: parquet.len(),
+                rows: partition_rows.len(),
+            });
+        }
+
+        let delta = DeltaTable::new(
+            self.store.clone(),
+            path.clone(),
+            columns,
+            LAKE_PARTITION_COLUMN,
+        );
+
+        let version = delta.commit(&files).await?;
+
+        info!(
+            "Wrote {} rows in {} files to the lake table {} version {}.",
+            rows.len(),
+            files.len(),
+            path,
+            version
+        );
+
+        Ok(())
+    }
+}
+
+/// JSON rows of the models, with the block number of the rows that don't include it.
+fn get_rows<T: Serialize>(
+    models: &Vec<T>,
+    get_block_number: impl Fn(&T) -> Option<i64>,
+) -> Result<Vec<Value>> {
+    let mut rows = Vec::new();
+
+    for model in models {
+        let mut row = serde_json::to_value(model)?;
+
+        match (get_block_number(model), row.as_object_mut()) {
+            (Some(block_number), Some(row)) => {
+                row.insert("block_number".to_string(), json!(block_number));
+            }
+            _ => (),
+        }
+
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Parquet schema of the columns, the partition column is only stored in the Delta log.
+fn get_arrow_schema(columns: &[LakeColumn]) -> Schema {
+    let fields = columns
+        .iter()
+        .map(|(name, kind, nullable)| {
+            let data_type = match *kind {
+                "long" => DataType::Int64,
+                "boolean" => DataType::Boolean,
+                "array" => DataType::List(Box::new(Field::new("item", DataType::Utf8, true))),
+                _ => DataType::Utf8,
+            };
+
+            Field::new(*name, data_type, *nullable)
+        })
+        .collect();
+
+    Schema::new(fields)
+}
+
+fn get_parquet(schema: Arc<Schema>, rows: Vec<Value>) -> Result<Vec<u8>> {
+    let decoder = Decoder::new(
+        schema.clone(),
+        DecoderOptions::new().with_batch_size(rows.len().max(1)),
+    );
+
+    let mut values = rows.into_iter().map(Ok);
+
+    let batch: RecordBatch = match decoder.next_batch(&mut values)? {
+        Some(batch) => batch,
+        None => bail!("Unable to decode the lake rows"),
+    };
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let mut buffer = Vec::new();
+
+    let mut writer = ArrowWriter::try_new(&mut buffer, schema, Some(properties))?;
+
+    writer.write(&batch)?;
+
+    writer.close()?;
+
+    Ok(buffer)
+}
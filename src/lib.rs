@@ -1,6 +1,27 @@
+pub mod admin;
+pub mod alerts;
+pub mod api;
+pub mod archive;
+pub mod audit;
+pub mod calls;
 pub mod chains;
+pub mod clustering;
 pub mod configs;
+pub mod dashboard;
 pub mod db;
+pub mod exports;
+pub mod lake;
+pub mod metrics;
 pub mod parsers;
+pub mod query;
 pub mod rpc;
+pub mod screening;
+pub mod signatures;
+pub mod sinks;
+pub mod stablecoins;
+pub mod state;
+pub mod storage;
+pub mod tokens;
+#[cfg(feature = "traces")]
+pub mod traces;
 pub mod utils;
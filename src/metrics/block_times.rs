@@ -0,0 +1,127 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Double, Nullable, Text},
+};
+use serde::Serialize;
+
+use crate::db::{db::EVMDatabase, schema::evm_block_times};
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = evm_block_times)]
+pub struct DatabaseEVMBlockTime {
+    pub chain: String,
+    pub number: i64,
+    pub block_hash: String,
+    pub parent_hash: String,
+    pub timestamp: i64,
+    /// Milliseconds since the epoch when the head was received from the subscription.
+    pub received_at: i64,
+    /// Time between the block timestamp and its reception, negative when the clocks drift.
+    pub delay_ms: i64,
+}
+
+impl DatabaseEVMBlockTime {
+    pub fn new(
+        chain: &str,
+        number: i64,
+        block_hash: String,
+        parent_hash: String,
+        timestamp: i64,
+        received_at: i64,
+    ) -> Self {
+        Self {
+            chain: chain.to_string(),
+            number,
+            block_hash,
+            parent_hash,
+            timestamp,
+            received_at,
+            delay_ms: received_at - timestamp * 1000,
+        }
+    }
+}
+
+#[derive(QueryableByName, Debug, Clone, Serialize)]
+pub struct BlockTimeStats {
+    #[diesel(sql_type = Text)]
+    pub chain: String,
+    #[diesel(sql_type = BigInt)]
+    pub blocks: i64,
+    /// Heads received for a height that already had another head, i.e. reorged blocks.
+    #[diesel(sql_type = BigInt)]
+    pub replaced_heads: i64,
+    /// Seconds between the timestamps of each block and its parent.
+    #[diesel(sql_type = Nullable<Double>)]
+    pub avg_interval: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub p50_interval: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub p95_interval: Option<f64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub max_interval: Option<i64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub avg_delay_ms: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub p50_delay_ms: Option<f64>,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub p95_delay_ms: Option<f64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub max_delay_ms: Option<i64>,
+}
+
+/// Stores the time at which a new head is received. The delays are only meaningful with a
+/// synced clock, and include the latency of the provider.
+pub fn store_block_time(db: &EVMDatabase, block_time: &DatabaseEVMBlockTime) -> Result<()> {
+    let mut connection = db.establish_connection();
+
+    diesel::insert_into(evm_block_times::dsl::evm_block_times)
+        .values(block_time)
+        .on_conflict_do_nothing()
+        .execute(&mut connection)?;
+
+    Ok(())
+}
+
+/// Block intervals and propagation delays of the heads received for the last `blocks` heights.
+/// The intervals are measured against the parent of each block, so the reorged heads don't skew
+/// them. Long intervals and replaced heads suggest a deeper confirmation depth.
+pub fn get_block_time_stats(db: &EVMDatabase, blocks: i64) -> Result<BlockTimeStats> {
+    let mut connection = db.establish_read_connection();
+
+    let stats = sql_query(
+        "WITH heads AS ( \
+            SELECT * FROM evm_block_times \
+            WHERE chain = $1 AND number > (SELECT MAX(number) FROM evm_block_times WHERE chain = $1) - $2 \
+        ), intervals AS ( \
+            SELECT h.timestamp - p.timestamp AS interval FROM heads h \
+            JOIN evm_block_times p ON p.chain = h.chain AND p.block_hash = h.parent_hash \
+        ) \
+        SELECT $1 AS chain, \
+        (SELECT COUNT(DISTINCT number) FROM heads) AS blocks, \
+        (SELECT COUNT(*) - COUNT(DISTINCT number) FROM heads) AS replaced_heads, \
+        (SELECT AVG(interval)::FLOAT8 FROM intervals) AS avg_interval, \
+        (SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY interval) FROM intervals) AS p50_interval, \
+        (SELECT percentile_cont(0.95) WITHIN GROUP (ORDER BY interval) FROM intervals) AS p95_interval, \
+        (SELECT MAX(interval) FROM intervals) AS max_interval, \
+        (SELECT AVG(delay_ms)::FLOAT8 FROM heads) AS avg_delay_ms, \
+        (SELECT percentile_cont(0.5) WITHIN GROUP (ORDER BY delay_ms) FROM heads) AS p50_delay_ms, \
+        (SELECT percentile_cont(0.95) WITHIN GROUP (ORDER BY delay_ms) FROM heads) AS p95_delay_ms, \
+        (SELECT MAX(delay_ms) FROM heads) AS max_delay_ms",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<BigInt, _>(blocks.max(1))
+    .get_result::<BlockTimeStats>(&mut connection)?;
+
+    Ok(stats)
+}
+
+pub fn get_timestamp_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_millis() as i64
+}
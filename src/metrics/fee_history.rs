@@ -0,0 +1,87 @@
+use anyhow::Result;
+use ethers::types::U256;
+use log::*;
+
+use crate::{
+    configs::indexer_config::EVMIndexerConfig,
+    db::db::{EVMDatabase, FeeHistoryBlock},
+    rpc::rpc::EVMRpc,
+};
+
+/// Maximum amount of blocks requested in a single `eth_feeHistory` call.
+pub const MAX_FEE_HISTORY_BLOCKS: i64 = 1024;
+
+/// Polls `eth_feeHistory` and stores the base fee and reward percentiles of each block as a gas
+/// price oracle dataset. It only needs the headers, so it doesn't depend on the blocks sync and
+/// starts from the last `MAX_FEE_HISTORY_BLOCKS` blocks on an empty table.
+#[derive(Debug, Clone)]
+pub struct FeeHistoryWorker {
+    pub percentiles: Vec<f64>,
+}
+
+impl FeeHistoryWorker {
+    pub fn new(config: &EVMIndexerConfig) -> Self {
+        Self {
+            percentiles: config.fee_history_percentiles.clone(),
+        }
+    }
+
+    /// Stores the fee history of the blocks since the last stored one, returns the amount of
+    /// blocks stored.
+    pub async fn poll(&self, rpc: &EVMRpc, db: &EVMDatabase) -> Result<usize> {
+        let last_block = rpc.get_last_block().await?;
+
+        let from_block = match db.get_last_fee_history_block().await? {
+            Some(block) => block + 1,
+            None => last_block - MAX_FEE_HISTORY_BLOCKS + 1,
+        }
+        .max(0);
+
+        if from_block > last_block {
+            return Ok(0);
+        }
+
+        let newest_block = last_block.min(from_block + MAX_FEE_HISTORY_BLOCKS - 1);
+
+        let block_count = newest_block - from_block + 1;
+
+        let fee_history = match rpc
+            .get_fee_history(block_count, newest_block, &self.percentiles)
+            .await?
+        {
+            Some(fee_history) => fee_history,
+            None => return Ok(0),
+        };
+
+        let oldest_block = fee_history.oldest_block.as_u64() as i64;
+
+        // The base fees include the one of the next block, which isn't stored until it's mined.
+        let blocks: Vec<FeeHistoryBlock> = fee_history
+            .gas_used_ratio
+            .iter()
+            .enumerate()
+            .map(|(index, gas_used_ratio)| FeeHistoryBlock {
+                number: oldest_block + index as i64,
+                base_fee_per_gas: match fee_history.base_fee_per_gas.get(index) {
+                    Some(base_fee_per_gas) => *base_fee_per_gas,
+                    None => U256::zero(),
+                },
+                gas_used_ratio: *gas_used_ratio,
+                rewards: match fee_history.reward.get(index) {
+                    Some(rewards) => rewards.clone(),
+                    None => Vec::new(),
+                },
+            })
+            .collect();
+
+        db.store_fee_history(&blocks, &self.percentiles).await?;
+
+        info!(
+            "Stored the fee history of {} blocks for chain {}.",
+            blocks.len(),
+            db.chain.name
+        );
+
+        Ok(blocks.len())
+    }
+}
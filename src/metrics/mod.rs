@@ -0,0 +1,7 @@
+pub mod block_times;
+pub mod fee_history;
+pub mod rpc_usage;
+pub mod sync_lag;
+pub mod table_stats;
+pub mod telemetry;
+pub mod views;
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Text},
+};
+
+use crate::{db::db::EVMDatabase, rpc::rpc::EVMRpc};
+
+/// Adds the RPC requests since the last call to the totals of `evm_rpc_usage`, per provider and
+/// method, to follow the provider bills across restarts. Returns the amount of stored rows.
+pub fn store_rpc_usage(rpc: &EVMRpc, db: &EVMDatabase) -> Result<usize> {
+    let usage = rpc.stats.take_unflushed_usage();
+
+    if usage.len() == 0 {
+        return Ok(0);
+    }
+
+    let mut connection = db.establish_connection();
+
+    let stored = sql_query(
+        "INSERT INTO evm_rpc_usage (chain, provider, method, requests, errors, compute_units, credits) \
+        SELECT $1, * FROM UNNEST($2::text[], $3::text[], $4::bigint[], $5::bigint[], $6::bigint[], $7::bigint[]) \
+        ON CONFLICT (chain, provider, method) DO UPDATE SET \
+        requests = evm_rpc_usage.requests + EXCLUDED.requests, \
+        errors = evm_rpc_usage.errors + EXCLUDED.errors, \
+        compute_units = evm_rpc_usage.compute_units + EXCLUDED.compute_units, \
+        credits = evm_rpc_usage.credits + EXCLUDED.credits",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<Array<Text>, _>(usage.iter().map(|usage| usage.provider.clone()).collect::<Vec<String>>())
+    .bind::<Array<Text>, _>(usage.iter().map(|usage| usage.method.clone()).collect::<Vec<String>>())
+    .bind::<Array<BigInt>, _>(usage.iter().map(|usage| usage.requests).collect::<Vec<i64>>())
+    .bind::<Array<BigInt>, _>(usage.iter().map(|usage| usage.errors).collect::<Vec<i64>>())
+    .bind::<Array<BigInt>, _>(usage.iter().map(|usage| usage.compute_units).collect::<Vec<i64>>())
+    .bind::<Array<BigInt>, _>(usage.iter().map(|usage| usage.credits).collect::<Vec<i64>>())
+    .execute(&mut connection)?;
+
+    Ok(stored)
+}
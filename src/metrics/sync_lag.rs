@@ -0,0 +1,141 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::*;
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::{configs::indexer_config::EVMIndexerConfig, db::db::EVMDatabase, rpc::rpc::EVMRpc};
+
+/// Minimum time between two notifications while the sync lag stays above the threshold.
+pub const LAG_ALERT_INTERVAL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncLagAlert {
+    pub chain: String,
+    pub last_block: i64,
+    pub last_indexed_block: i64,
+    pub sync_lag: i64,
+    pub threshold: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncLagMonitor {
+    pub start_block: i64,
+    pub threshold: Option<i64>,
+    pub webhook: Option<String>,
+    pub exit_on_lag: bool,
+    /// Set once the lag is first within the threshold, the initial sync doesn't alert.
+    pub caught_up: bool,
+    pub last_alert: Option<Instant>,
+}
+
+impl SyncLagMonitor {
+    pub fn new(config: &EVMIndexerConfig) -> Self {
+        Self {
+            start_block: config.start_block,
+            threshold: config.lag_threshold,
+            webhook: config.lag_webhook.clone(),
+            exit_on_lag: config.exit_on_lag,
+            caught_up: false,
+            last_alert: None,
+        }
+    }
+
+    /// Computes `chain_head - last_contiguous_block`, so a gap left by a failed backfill counts
+    /// as lag, stores it and alerts when it exceeds the threshold after the initial sync.
+    pub async fn check(&mut self, rpc: &EVMRpc, db: &EVMDatabase) -> Result<i64> {
+        let last_block = rpc.get_last_block().await?;
+
+        let indexed_blocks = db.get_indexed_blocks().await?;
+
+        let last_indexed_block = get_last_contiguous_block(self.start_block, &indexed_blocks);
+
+        let sync_lag = (last_block - last_indexed_block).max(0);
+
+        db.update_sync_lag(sync_lag).await?;
+
+        info!(
+            "Sync lag for chain {} is {} blocks (head {} indexed {}).",
+            db.chain.name, sync_lag, last_block, last_indexed_block
+        );
+
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return Ok(sync_lag),
+        };
+
+        if sync_lag <= threshold {
+            self.caught_up = true;
+            self.last_alert = None;
+
+            return Ok(sync_lag);
+        }
+
+        if !self.caught_up {
+            return Ok(sync_lag);
+        }
+
+        warn!(
+            "Sync lag for chain {} of {} blocks exceeds the threshold of {} blocks.",
+            db.chain.name, sync_lag, threshold
+        );
+
+        let alerted = self.last_alert.map_or(false, |last_alert| {
+            last_alert.elapsed() < LAG_ALERT_INTERVAL
+        });
+
+        if !alerted {
+            let alert = SyncLagAlert {
+                chain: db.chain.name.to_string(),
+                last_block,
+                last_indexed_block,
+                sync_lag,
+                threshold,
+            };
+
+            self.notify(&alert).await;
+
+            self.last_alert = Some(Instant::now());
+        }
+
+        Ok(sync_lag)
+    }
+
+    /// Whether the indexer must stop for a lag returned by `check`, only once it caught up.
+    pub fn must_exit(&self, sync_lag: i64) -> bool {
+        self.exit_on_lag
+            && self.caught_up
+            && self
+                .threshold
+                .map_or(false, |threshold| sync_lag > threshold)
+    }
+
+    async fn notify(&self, alert: &SyncLagAlert) {
+        match &self.webhook {
+            Some(webhook) => {
+                let client = Client::new();
+
+                match client.post(webhook).json(alert).send().await {
+                    Ok(_) => (),
+                    Err(err) => warn!("Unable to send sync lag alert: {}", err),
+                }
+            }
+            None => (),
+        }
+    }
+}
+
+/// Last block of the indexed blocks contiguous from the start block.
+fn get_last_contiguous_block(start_block: i64, indexed_blocks: &HashSet<i64>) -> i64 {
+    let mut block = start_block;
+
+    while indexed_blocks.contains(&block) {
+        block += 1;
+    }
+
+    block - 1
+}
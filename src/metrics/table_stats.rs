@@ -0,0 +1,88 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Nullable, Text},
+};
+use serde::Serialize;
+
+use crate::db::db::EVMDatabase;
+
+/// Seconds the samples are kept for, enough to compare the growth of the last weeks.
+const SAMPLES_RETENTION: i64 = 30 * 24 * 60 * 60;
+
+#[derive(QueryableByName, Debug, Clone, Serialize)]
+pub struct TableStats {
+    #[diesel(sql_type = Text)]
+    pub table_name: String,
+    #[diesel(sql_type = BigInt)]
+    pub sampled_at: i64,
+    #[diesel(sql_type = BigInt)]
+    pub live_rows: i64,
+    #[diesel(sql_type = BigInt)]
+    pub table_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub index_bytes: i64,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub inserted_rows_per_hour: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub bytes_per_hour: Option<i64>,
+}
+
+/// Samples the inserted rows, live rows and table and index sizes of the tables of the schema
+/// from `pg_stat_user_tables` into `evm_table_stats`, and drops the samples past the retention.
+/// The inserted rows are the totals of Postgres, so the rows inserted per interval are the
+/// difference between samples. Returns the amount of tables sampled.
+pub fn sample_table_stats(db: &EVMDatabase) -> Result<usize> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_secs() as i64;
+
+    let mut connection = db.establish_connection();
+
+    let sampled = sql_query(
+        "INSERT INTO evm_table_stats (sampled_at, table_name, inserted_rows, live_rows, table_bytes, index_bytes) \
+        SELECT $1, relname, n_tup_ins, n_live_tup, pg_table_size(relid), pg_indexes_size(relid) \
+        FROM pg_stat_user_tables \
+        WHERE schemaname = current_schema() AND relname <> 'evm_table_stats' \
+        ON CONFLICT DO NOTHING",
+    )
+    .bind::<BigInt, _>(now)
+    .execute(&mut connection)?;
+
+    sql_query("DELETE FROM evm_table_stats WHERE sampled_at < $1")
+        .bind::<BigInt, _>(now - SAMPLES_RETENTION)
+        .execute(&mut connection)?;
+
+    Ok(sampled)
+}
+
+/// Latest sample of each table with its insert and growth rates since the sample `hours` before
+/// it, largest tables first. The rates are empty until there are two samples far enough apart,
+/// and inserts don't go negative after the Postgres stats are reset.
+pub fn get_table_stats(db: &EVMDatabase, hours: i64) -> Result<Vec<TableStats>> {
+    let mut connection = db.establish_read_connection();
+
+    let stats = sql_query(
+        "WITH latest AS ( \
+            SELECT DISTINCT ON (table_name) * FROM evm_table_stats \
+            ORDER BY table_name, sampled_at DESC \
+        ), previous AS ( \
+            SELECT DISTINCT ON (s.table_name) s.* FROM evm_table_stats s \
+            JOIN latest l ON l.table_name = s.table_name AND s.sampled_at <= l.sampled_at - $1 \
+            ORDER BY s.table_name, s.sampled_at DESC \
+        ) \
+        SELECT l.table_name, l.sampled_at, l.live_rows, l.table_bytes, l.index_bytes, \
+        (GREATEST(l.inserted_rows - p.inserted_rows, 0) * 3600 / NULLIF(l.sampled_at - p.sampled_at, 0))::BIGINT AS inserted_rows_per_hour, \
+        ((l.table_bytes + l.index_bytes - p.table_bytes - p.index_bytes) * 3600 / NULLIF(l.sampled_at - p.sampled_at, 0))::BIGINT AS bytes_per_hour \
+        FROM latest l LEFT JOIN previous p ON p.table_name = l.table_name \
+        ORDER BY l.table_bytes + l.index_bytes DESC",
+    )
+    .bind::<BigInt, _>(hours * 3600)
+    .load::<TableStats>(&mut connection)?;
+
+    Ok(stats)
+}
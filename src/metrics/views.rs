@@ -0,0 +1,177 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Nullable, Text},
+};
+use log::*;
+use serde::Serialize;
+
+use crate::db::{db::EVMDatabase, schema::evm_view_refreshes};
+
+/// Materialized views created by the migrations, each one over the last day of every chain.
+pub const MATERIALIZED_VIEWS: [&str; 3] = [
+    "evm_top_tokens_by_volume",
+    "evm_busiest_contracts",
+    "evm_gas_leaders",
+];
+
+#[derive(QueryableByName, Debug)]
+struct RowCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(Queryable, Debug, Clone, Serialize)]
+pub struct ViewRefresh {
+    pub name: String,
+    pub refreshed_at: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub row_count: Option<i64>,
+    pub refreshes: i64,
+    pub failures: i64,
+    pub error: Option<String>,
+}
+
+/// Refreshes the materialized views read by the dashboards once their interval elapsed. The
+/// views are refreshed concurrently so they stay readable, and the outcome of each refresh is
+/// kept in `evm_view_refreshes` for monitoring, like the sync lag and RPC usage.
+#[derive(Debug, Clone)]
+pub struct ViewRefresher {
+    pub views: Vec<String>,
+    pub interval: i64,
+}
+
+impl ViewRefresher {
+    pub fn new(views: Option<Vec<String>>, interval: u64) -> Result<Self> {
+        let views = match views {
+            Some(views) => views,
+            None => MATERIALIZED_VIEWS
+                .iter()
+                .map(|view| view.to_string())
+                .collect(),
+        };
+
+        for view in views.iter() {
+            if !MATERIALIZED_VIEWS.contains(&view.as_str()) {
+                bail!("Unknown materialized view {}", view);
+            }
+        }
+
+        Ok(Self {
+            views,
+            interval: interval as i64,
+        })
+    }
+
+    /// Refreshes the views whose last refresh is older than the interval, returns the amount of
+    /// views refreshed.
+    pub fn refresh_due(&self, db: &EVMDatabase) -> Result<usize> {
+        let now = get_timestamp();
+
+        let statuses = get_view_refreshes(db)?;
+
+        let mut refreshed = 0;
+
+        for view in self.views.iter() {
+            let refreshed_at = statuses
+                .iter()
+                .find(|status| &status.name == view)
+                .and_then(|status| status.refreshed_at);
+
+            match refreshed_at {
+                Some(refreshed_at) if refreshed_at + self.interval > now => continue,
+                _ => (),
+            }
+
+            match self.refresh(db, view) {
+                Ok(_) => refreshed += 1,
+                Err(err) => warn!("Unable to refresh the materialized view {}: {}", view, err),
+            }
+        }
+
+        Ok(refreshed)
+    }
+
+    fn refresh(&self, db: &EVMDatabase, view: &String) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let started = Instant::now();
+
+        // The views are checked against the known ones, so their names can be formatted in.
+        let refreshed = sql_query(format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {}", view))
+            .execute(&mut connection)
+            .and_then(|_| {
+                sql_query(format!("SELECT COUNT(*) AS count FROM {}", view))
+                    .get_result::<RowCount>(&mut connection)
+            });
+
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        let (row_count, error) = match &refreshed {
+            Ok(rows) => (Some(rows.count), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        sql_query(
+            "INSERT INTO evm_view_refreshes (name, refreshed_at, duration_ms, row_count, refreshes, \
+            failures, error) VALUES ($1, $2, $3, $4, 1, $5, $6) \
+            ON CONFLICT (name) DO UPDATE SET \
+            refreshed_at = EXCLUDED.refreshed_at, \
+            duration_ms = EXCLUDED.duration_ms, \
+            row_count = COALESCE(EXCLUDED.row_count, evm_view_refreshes.row_count), \
+            refreshes = evm_view_refreshes.refreshes + 1, \
+            failures = evm_view_refreshes.failures + EXCLUDED.failures, \
+            error = EXCLUDED.error",
+        )
+        .bind::<Text, _>(view)
+        .bind::<BigInt, _>(get_timestamp())
+        .bind::<BigInt, _>(duration_ms)
+        .bind::<Nullable<BigInt>, _>(row_count)
+        .bind::<BigInt, _>(error.is_some() as i64)
+        .bind::<Nullable<Text>, _>(&error)
+        .execute(&mut connection)?;
+
+        match error {
+            Some(error) => bail!("{}", error),
+            None => {
+                info!(
+                    "Refreshed the materialized view {} with {} rows in {} ms.",
+                    view,
+                    row_count.unwrap_or_default(),
+                    duration_ms
+                );
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Outcome of the last refresh of each materialized view.
+pub fn get_view_refreshes(db: &EVMDatabase) -> Result<Vec<ViewRefresh>> {
+    let mut connection = db.establish_read_connection();
+
+    let refreshes = evm_view_refreshes::table
+        .select((
+            evm_view_refreshes::name,
+            evm_view_refreshes::refreshed_at,
+            evm_view_refreshes::duration_ms,
+            evm_view_refreshes::row_count,
+            evm_view_refreshes::refreshes,
+            evm_view_refreshes::failures,
+            evm_view_refreshes::error,
+        ))
+        .load::<ViewRefresh>(&mut connection)?;
+
+    Ok(refreshes)
+}
+
+fn get_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_secs() as i64
+}
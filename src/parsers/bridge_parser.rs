@@ -0,0 +1,401 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::evm_bridge_transfers,
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder};
+use super::dedup::{get_duplicate_keys, store_duplicate_logs, DuplicateFilter};
+use super::deployments::ProtocolDeployment;
+
+pub const ARBITRUM_GATEWAY: &str = "arbitrum-gateway";
+
+pub const OPTIMISM_BRIDGE: &str = "optimism-bridge";
+
+pub const POLYGON_POS_BRIDGE: &str = "polygon-pos-bridge";
+
+/// Bridges whose registered deployments are parsed.
+pub const BRIDGE_PROTOCOLS: [&str; 3] = [ARBITRUM_GATEWAY, OPTIMISM_BRIDGE, POLYGON_POS_BRIDGE];
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_bridge_transfers)]
+pub struct DatabaseEVMBridgeTransfer {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub protocol: String,
+    pub direction: String,
+    pub status: String,
+    pub user_address: String,
+    pub recipient: Option<String>,
+    pub token: Option<String>,
+    pub amount: String,
+    pub message_id: Option<String>,
+}
+
+pub type BridgeDeployment = ProtocolDeployment;
+
+/// Canonical bridge contracts on both sides of each bridge, the events are emitted by these
+/// contracts.
+pub fn get_default_bridge_deployments() -> Vec<BridgeDeployment> {
+    let deployments = [
+        // Arbitrum L1 and L2 standard ERC20 gateways.
+        (
+            "ethereum",
+            ARBITRUM_GATEWAY,
+            "0xa3a7b6f88361f48403514059f1f16c8e78d60eec",
+        ),
+        (
+            "arbitrum",
+            ARBITRUM_GATEWAY,
+            "0x09e9222e96e7b4ae2a407b98d48e330053351eee",
+        ),
+        // Optimism L1 and L2 standard bridges.
+        (
+            "ethereum",
+            OPTIMISM_BRIDGE,
+            "0x99c9fc46f92e8a1c0dec1b1747d010903e884be1",
+        ),
+        (
+            "optimism",
+            OPTIMISM_BRIDGE,
+            "0x4200000000000000000000000000000000000010",
+        ),
+        // Polygon PoS ERC20 and Ether predicates.
+        (
+            "ethereum",
+            POLYGON_POS_BRIDGE,
+            "0x40ec5b33f54e0e8a33a975908c5ba1c14e5bbbdf",
+        ),
+        (
+            "ethereum",
+            POLYGON_POS_BRIDGE,
+            "0x8484ef722627bf18ca5ae6bcf031c23e6e922b30",
+        ),
+    ];
+
+    deployments
+        .into_iter()
+        .map(|(chain, protocol, address)| BridgeDeployment {
+            chain: chain.to_string(),
+            protocol: protocol.to_string(),
+            address: address.to_string(),
+            contract: None,
+        })
+        .collect()
+}
+
+pub fn load_bridge_deployments(path: &Option<String>) -> Vec<BridgeDeployment> {
+    match path {
+        Some(path) => {
+            let file = std::fs::read_to_string(path).expect("Unable to read bridge deployments");
+
+            serde_json::from_str(&file).expect("Unable to parse bridge deployments")
+        }
+        None => get_default_bridge_deployments(),
+    }
+}
+
+pub struct BridgeParser {
+    pub deployments: HashMap<(String, String), BridgeDeployment>,
+    pub dedup: DuplicateFilter,
+    pub arbitrum_gateway: EventDecoder,
+    pub optimism_bridge: EventDecoder,
+    pub polygon_pos_bridge: EventDecoder,
+}
+
+impl BridgeParser {
+    pub fn new(deployments: Vec<BridgeDeployment>, dedup: DuplicateFilter) -> Self {
+        let arbitrum_gateway = EventDecoder::new(&[
+            "event DepositInitiated(address l1Token, address indexed _from, address indexed _to, uint256 indexed _sequenceNumber, uint256 _amount)",
+            "event DepositFinalized(address indexed l1Token, address indexed _from, address indexed _to, uint256 _amount)",
+            "event WithdrawalInitiated(address l1Token, address indexed _from, address indexed _to, uint256 indexed _l2ToL1Id, uint256 _exitNum, uint256 _amount)",
+            "event WithdrawalFinalized(address l1Token, address indexed _from, address indexed _to, uint256 indexed _exitNum, uint256 _amount)",
+        ]);
+
+        let optimism_bridge = EventDecoder::new(&[
+            "event ETHDepositInitiated(address indexed _from, address indexed _to, uint256 _amount, bytes _data)",
+            "event ERC20DepositInitiated(address indexed _l1Token, address indexed _l2Token, address indexed _from, address _to, uint256 _amount, bytes _data)",
+            "event ETHWithdrawalFinalized(address indexed _from, address indexed _to, uint256 _amount, bytes _data)",
+            "event ERC20WithdrawalFinalized(address indexed _l1Token, address indexed _l2Token, address indexed _from, address _to, uint256 _amount, bytes _data)",
+            "event DepositFinalized(address indexed _l1Token, address indexed _l2Token, address indexed _from, address _to, uint256 _amount, bytes _data)",
+            "event WithdrawalInitiated(address indexed _l1Token, address indexed _l2Token, address indexed _from, address _to, uint256 _amount, bytes _data)",
+        ]);
+
+        let polygon_pos_bridge = EventDecoder::new(&[
+            "event LockedERC20(address indexed depositor, address indexed depositReceiver, address indexed rootToken, uint256 amount)",
+            "event LockedEther(address indexed depositor, address indexed depositReceiver, uint256 amount)",
+            "event ExitedERC20(address indexed exitor, address indexed rootToken, uint256 amount)",
+            "event ExitedEther(address indexed exitor, uint256 amount)",
+        ]);
+
+        Self {
+            deployments: deployments
+                .into_iter()
+                .map(|deployment| {
+                    (
+                        (deployment.chain.clone(), deployment.address.to_lowercase()),
+                        deployment,
+                    )
+                })
+                .collect(),
+            dedup,
+            arbitrum_gateway,
+            optimism_bridge,
+            polygon_pos_bridge,
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut topics = self.arbitrum_gateway.topics();
+
+        topics.append(&mut self.optimism_bridge.topics());
+        topics.append(&mut self.polygon_pos_bridge.topics());
+
+        let addresses: Vec<String> = self
+            .deployments
+            .keys()
+            .map(|(_, address)| address.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        db.get_unparsed_logs("bridge", &topics, Some(&addresses), 10000)
+            .await
+    }
+
+    #[instrument(name = "bridge_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let mut db_bridge_transfers = Vec::new();
+
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let duplicates = self.dedup.find_duplicates("bridge", logs, |log| {
+            let chain = chains.get(&log.hash)?;
+
+            self.deployments
+                .get(&(chain.clone(), log.address.clone()))
+                .map(|deployment| deployment.protocol.clone())
+        });
+
+        let duplicate_keys = get_duplicate_keys(&duplicates);
+
+        for log in logs {
+            if duplicate_keys.contains(&(log.hash.clone(), log.log_index)) {
+                continue;
+            }
+
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let deployment = match self.deployments.get(&(chain, log.address.clone())) {
+                Some(deployment) => deployment,
+                None => continue,
+            };
+
+            let transfer = match deployment.protocol.as_str() {
+                ARBITRUM_GATEWAY => match self.arbitrum_gateway.decode(log) {
+                    Some(decoded) => get_arbitrum_gateway_transfer(&decoded),
+                    None => None,
+                },
+                OPTIMISM_BRIDGE => match self.optimism_bridge.decode(log) {
+                    Some(decoded) => get_optimism_bridge_transfer(&decoded),
+                    None => None,
+                },
+                POLYGON_POS_BRIDGE => match self.polygon_pos_bridge.decode(log) {
+                    Some(decoded) => get_polygon_pos_bridge_transfer(&decoded),
+                    None => None,
+                },
+                _ => None,
+            };
+
+            match transfer {
+                Some((direction, status, user_address, recipient, token, amount, message_id)) => {
+                    db_bridge_transfers.push(DatabaseEVMBridgeTransfer {
+                        hash: log.hash.clone(),
+                        log_index: log.log_index,
+                        chain: deployment.chain.clone(),
+                        protocol: deployment.protocol.clone(),
+                        direction: direction.to_string(),
+                        status: status.to_string(),
+                        user_address,
+                        recipient,
+                        token,
+                        amount,
+                        message_id,
+                    })
+                }
+                None => continue,
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(
+            db_bridge_transfers.len(),
+            DatabaseEVMBridgeTransfer::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_bridge_transfers::dsl::evm_bridge_transfers)
+                .values(&db_bridge_transfers[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store bridge transfers into database");
+        }
+
+        info!(
+            "Inserted {} bridge transfers to the database.",
+            db_bridge_transfers.len()
+        );
+
+        let failures = get_parse_failures(
+            "bridge",
+            &[
+                &self.arbitrum_gateway,
+                &self.optimism_bridge,
+                &self.polygon_pos_bridge,
+            ],
+            logs,
+        );
+
+        db.store_parse_failures(&failures).await?;
+
+        store_duplicate_logs(db, &duplicates)?;
+
+        db.store_parsed_logs("bridge", logs).await
+    }
+}
+
+/// Direction, status, user, recipient, token (`None` for the native token), amount and message id.
+type BridgeTransfer = (
+    &'static str,
+    &'static str,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+    Option<String>,
+);
+
+fn get_arbitrum_gateway_transfer(decoded: &DecodedLog) -> Option<BridgeTransfer> {
+    match decoded.name.as_str() {
+        "DepositInitiated" => Some((
+            "deposit",
+            "initiated",
+            decoded.address("_from")?,
+            decoded.address("_to"),
+            decoded.address("l1Token"),
+            decoded.uint("_amount")?,
+            decoded.uint("_sequenceNumber"),
+        )),
+        "DepositFinalized" => Some((
+            "deposit",
+            "finalized",
+            decoded.address("_from")?,
+            decoded.address("_to"),
+            decoded.address("l1Token"),
+            decoded.uint("_amount")?,
+            None,
+        )),
+        "WithdrawalInitiated" => Some((
+            "withdrawal",
+            "initiated",
+            decoded.address("_from")?,
+            decoded.address("_to"),
+            decoded.address("l1Token"),
+            decoded.uint("_amount")?,
+            decoded.uint("_exitNum"),
+        )),
+        "WithdrawalFinalized" => Some((
+            "withdrawal",
+            "finalized",
+            decoded.address("_from")?,
+            decoded.address("_to"),
+            decoded.address("l1Token"),
+            decoded.uint("_amount")?,
+            decoded.uint("_exitNum"),
+        )),
+        _ => None,
+    }
+}
+
+fn get_optimism_bridge_transfer(decoded: &DecodedLog) -> Option<BridgeTransfer> {
+    let (direction, status, token) = match decoded.name.as_str() {
+        "ETHDepositInitiated" => ("deposit", "initiated", None),
+        "ERC20DepositInitiated" => ("deposit", "initiated", decoded.address("_l1Token")),
+        "DepositFinalized" => ("deposit", "finalized", decoded.address("_l1Token")),
+        "WithdrawalInitiated" => ("withdrawal", "initiated", decoded.address("_l1Token")),
+        "ETHWithdrawalFinalized" => ("withdrawal", "finalized", None),
+        "ERC20WithdrawalFinalized" => ("withdrawal", "finalized", decoded.address("_l1Token")),
+        _ => return None,
+    };
+
+    Some((
+        direction,
+        status,
+        decoded.address("_from")?,
+        decoded.address("_to"),
+        token,
+        decoded.uint("_amount")?,
+        None,
+    ))
+}
+
+fn get_polygon_pos_bridge_transfer(decoded: &DecodedLog) -> Option<BridgeTransfer> {
+    match decoded.name.as_str() {
+        "LockedERC20" => Some((
+            "deposit",
+            "initiated",
+            decoded.address("depositor")?,
+            decoded.address("depositReceiver"),
+            decoded.address("rootToken"),
+            decoded.uint("amount")?,
+            None,
+        )),
+        "LockedEther" => Some((
+            "deposit",
+            "initiated",
+            decoded.address("depositor")?,
+            decoded.address("depositReceiver"),
+            None,
+            decoded.uint("amount")?,
+            None,
+        )),
+        "ExitedERC20" => Some((
+            "withdrawal",
+            "finalized",
+            decoded.address("exitor")?,
+            decoded.address("exitor"),
+            decoded.address("rootToken"),
+            decoded.uint("amount")?,
+            None,
+        )),
+        "ExitedEther" => Some((
+            "withdrawal",
+            "finalized",
+            decoded.address("exitor")?,
+            decoded.address("exitor"),
+            None,
+            decoded.uint("amount")?,
+            None,
+        )),
+        _ => None,
+    }
+}
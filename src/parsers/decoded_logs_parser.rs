@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Text},
+};
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::EVMDatabase,
+    models::models::{DatabaseEVMParseFailure, DatabaseEVMTransactionLog},
+    schema::evm_abis,
+};
+
+use super::decoder::EventDecoder;
+
+pub const PARSER_NAME: &str = "decoded";
+
+/// Stores the event name and named arguments of the logs emitted by contracts with a known ABI
+/// in the `decoded` JSONB column of the logs, so the arguments of any event can be queried
+/// without a protocol specific table, e.g. `decoded->'args'->>'owner'`. Logs of events missing
+/// from the ABI, like the events of a proxy implementation, are left undecoded.
+pub struct DecodedLogsParser {}
+
+impl DecodedLogsParser {
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut connection = db.establish_read_connection();
+
+        let mut logs = sql_query(
+            "SELECT l.* FROM evm_transactions_logs l \
+            WHERE EXISTS (SELECT 1 FROM evm_abis a \
+            WHERE a.contract = l.address AND a.abi IS NOT NULL) \
+            AND NOT EXISTS (SELECT 1 FROM evm_parsed_logs p \
+            WHERE p.parser = $1 AND p.hash = l.hash AND p.log_index = l.log_index) \
+            LIMIT 10000",
+        )
+        .bind::<Text, _>(PARSER_NAME)
+        .load::<DatabaseEVMTransactionLog>(&mut connection)?;
+
+        db.decompress_logs(&mut logs)?;
+
+        Ok(logs)
+    }
+
+    #[instrument(name = "decoded_logs_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let decoders = self.get_decoders(db, logs)?;
+
+        let mut decoded_hashes = Vec::new();
+        let mut decoded_log_indexes = Vec::new();
+        let mut decoded_values = Vec::new();
+
+        let mut failures = Vec::new();
+
+        for log in logs {
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let decoder = match decoders.get(&(chain, log.address.clone())) {
+                Some(decoder) => decoder,
+                None => continue,
+            };
+
+            match decoder.try_decode(log) {
+                Ok(Some(decoded)) => {
+                    decoded_hashes.push(log.hash.clone());
+                    decoded_log_indexes.push(log.log_index);
+                    decoded_values.push(decoded.to_json().to_string());
+                }
+                Ok(None) => (),
+                Err(reason) => failures.push(DatabaseEVMParseFailure {
+                    parser: PARSER_NAME.to_string(),
+                    hash: log.hash.clone(),
+                    log_index: log.log_index,
+                    reason,
+                }),
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        sql_query(
+            "UPDATE evm_transactions_logs l SET decoded = d.decoded::JSONB \
+            FROM UNNEST($1::TEXT[], $2::BIGINT[], $3::TEXT[]) AS d(hash, log_index, decoded) \
+            WHERE l.hash = d.hash AND l.log_index = d.log_index",
+        )
+        .bind::<Array<Text>, _>(&decoded_hashes)
+        .bind::<Array<BigInt>, _>(&decoded_log_indexes)
+        .bind::<Array<Text>, _>(&decoded_values)
+        .execute(&mut connection)
+        .expect("Unable to store decoded logs into database");
+
+        info!("Decoded {} logs with known ABIs.", decoded_values.len());
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs(PARSER_NAME, logs).await
+    }
+
+    /// Event decoders of the ABIs of the contracts emitting the logs, by chain and contract.
+    fn get_decoders(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<HashMap<(String, String), EventDecoder>> {
+        let mut addresses: Vec<String> = logs.iter().map(|log| log.address.clone()).collect();
+
+        addresses.sort();
+        addresses.dedup();
+
+        let mut connection = db.establish_read_connection();
+
+        let abis = evm_abis::table
+            .select((evm_abis::chain, evm_abis::contract, evm_abis::abi))
+            .filter(evm_abis::contract.eq_any(&addresses))
+            .filter(evm_abis::abi.is_not_null())
+            .load::<(String, String, Option<String>)>(&mut connection)?;
+
+        let mut decoders = HashMap::new();
+
+        for (chain, contract, abi) in abis {
+            let decoder = match abi.as_deref().and_then(EventDecoder::from_abi) {
+                Some(decoder) => decoder,
+                None => continue,
+            };
+
+            decoders.insert((chain, contract), decoder);
+        }
+
+        Ok(decoders)
+    }
+}
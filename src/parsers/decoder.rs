@@ -0,0 +1,325 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ethers::abi::{parse_abi, Abi, Event, Function, RawLog, Token};
+use ethers::types::{H256, I256};
+use serde_json::{json, Map, Value};
+
+use crate::db::models::models::{DatabaseEVMParseFailure, DatabaseEVMTransactionLog};
+
+/// Decoded parameters of a log, or of a call for the `FunctionDecoder`.
+#[derive(Debug, Clone)]
+pub struct DecodedLog {
+    pub name: String,
+    pub params: HashMap<String, Token>,
+}
+
+impl DecodedLog {
+    pub fn address(&self, name: &str) -> Option<String> {
+        match self.params.get(name) {
+            Some(token) => token
+                .clone()
+                .into_address()
+                .map(|address| format!("{:?}", address)),
+            None => None,
+        }
+    }
+
+    pub fn uint(&self, name: &str) -> Option<String> {
+        match self.params.get(name) {
+            Some(token) => token.clone().into_uint().map(|value| value.to_string()),
+            None => None,
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Option<I256> {
+        match self.params.get(name) {
+            Some(token) => token.clone().into_int().map(|value| I256::from_raw(value)),
+            None => None,
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.params.get(name) {
+            Some(token) => token.clone().into_bool(),
+            None => None,
+        }
+    }
+
+    pub fn bytes(&self, name: &str) -> Option<String> {
+        match self.params.get(name) {
+            Some(Token::Bytes(bytes)) | Some(Token::FixedBytes(bytes)) => {
+                Some(format!("0x{}", hex::encode(bytes)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Option<String> {
+        match self.params.get(name) {
+            Some(token) => token.clone().into_string(),
+            None => None,
+        }
+    }
+
+    pub fn array(&self, name: &str) -> Option<Vec<Token>> {
+        match self.params.get(name) {
+            Some(token) => token.clone().into_array(),
+            None => None,
+        }
+    }
+
+    pub fn addresses(&self, name: &str) -> Option<Vec<String>> {
+        self.array(name)?
+            .into_iter()
+            .map(|token| token.into_address().map(|address| format!("{:?}", address)))
+            .collect()
+    }
+
+    pub fn uints(&self, name: &str) -> Option<Vec<String>> {
+        self.array(name)?
+            .into_iter()
+            .map(|token| token.into_uint().map(|value| value.to_string()))
+            .collect()
+    }
+
+    pub fn strings(&self, name: &str) -> Option<Vec<String>> {
+        self.array(name)?
+            .into_iter()
+            .map(|token| token.into_string())
+            .collect()
+    }
+
+    /// Event name and arguments as JSON, with the numbers as decimal strings since they
+    /// overflow the JSON numbers.
+    pub fn to_json(&self) -> Value {
+        let args: Map<String, Value> = self
+            .params
+            .iter()
+            .map(|(name, token)| (name.clone(), get_token_json(token)))
+            .collect();
+
+        json!({ "event": self.name, "args": args })
+    }
+
+    pub fn bytes_array(&self, name: &str) -> Option<Vec<String>> {
+        self.array(name)?
+            .into_iter()
+            .map(|token| {
+                token
+                    .into_bytes()
+                    .map(|bytes| format!("0x{}", hex::encode(bytes)))
+            })
+            .collect()
+    }
+}
+
+/// Decodes logs for a set of events declared with human readable signatures,
+/// e.g. `event Transfer(address indexed from, address indexed to, uint256 value)`.
+#[derive(Debug, Clone)]
+pub struct EventDecoder {
+    pub events: HashMap<String, Event>,
+}
+
+impl EventDecoder {
+    pub fn new(signatures: &[&str]) -> Self {
+        let abi = parse_abi(signatures).expect("Unable to parse event signatures");
+
+        let mut events = HashMap::new();
+
+        for event in abi.events() {
+            events.insert(format!("{:?}", event.signature()), event.clone());
+        }
+
+        Self { events }
+    }
+
+    /// Decoder of the events of a contract JSON ABI. Unnamed params are named after their
+    /// position, e.g. `arg0`.
+    pub fn from_abi(abi: &str) -> Option<Self> {
+        let abi: Abi = serde_json::from_str(abi).ok()?;
+
+        let mut events = HashMap::new();
+
+        for event in abi.events() {
+            let mut event = event.clone();
+
+            for (index, input) in event.inputs.iter_mut().enumerate() {
+                if input.name.is_empty() {
+                    input.name = format!("arg{}", index);
+                }
+            }
+
+            events.insert(format!("{:?}", event.signature()), event);
+        }
+
+        Some(Self { events })
+    }
+
+    pub fn topics(&self) -> Vec<String> {
+        self.events.keys().cloned().collect()
+    }
+
+    pub fn decode(&self, log: &DatabaseEVMTransactionLog) -> Option<DecodedLog> {
+        match self.try_decode(log) {
+            Ok(decoded) => decoded,
+            Err(_) => None,
+        }
+    }
+
+    /// Like `decode`, but tells apart the logs of other events, which return `None`, from the
+    /// logs of a known event that fail to decode, which return the reason.
+    pub fn try_decode(
+        &self,
+        log: &DatabaseEVMTransactionLog,
+    ) -> Result<Option<DecodedLog>, String> {
+        let topic0 = match log.topics.first() {
+            Some(Some(topic0)) => topic0,
+            _ => return Ok(None),
+        };
+
+        let event = match self.events.get(topic0) {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+
+        let mut topics = Vec::new();
+
+        for topic in log.topics.iter() {
+            match topic {
+                Some(topic) => match H256::from_str(topic) {
+                    Ok(topic) => topics.push(topic),
+                    Err(_) => return Err(format!("Invalid topic {}", topic)),
+                },
+                None => return Err("Missing topic".to_owned()),
+            }
+        }
+
+        let data = match hex::decode(log.data.trim_start_matches("0x")) {
+            Ok(data) => data,
+            Err(_) => return Err(format!("Invalid data {}", log.data)),
+        };
+
+        let parsed = match event.parse_log(RawLog { topics, data }) {
+            Ok(parsed) => parsed,
+            Err(err) => return Err(format!("Unable to decode {}: {}", event.name, err)),
+        };
+
+        Ok(Some(DecodedLog {
+            name: event.name.clone(),
+            params: parsed
+                .params
+                .into_iter()
+                .map(|param| (param.name, param.value))
+                .collect(),
+        }))
+    }
+}
+
+fn get_token_json(token: &Token) -> Value {
+    match token {
+        Token::Address(address) => Value::String(format!("{:?}", address)),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => {
+            Value::String(format!("0x{}", hex::encode(bytes)))
+        }
+        Token::Int(value) => Value::String(I256::from_raw(*value).to_string()),
+        Token::Uint(value) => Value::String(value.to_string()),
+        Token::Bool(value) => Value::Bool(*value),
+        Token::String(value) => Value::String(value.clone()),
+        Token::FixedArray(tokens) | Token::Array(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.iter().map(get_token_json).collect())
+        }
+    }
+}
+
+/// Quarantine records of the logs that match an event of the decoders but fail to decode, so
+/// the parser stores them with `EVMDatabase::store_parse_failures` instead of skipping them.
+pub fn get_parse_failures(
+    parser: &str,
+    decoders: &[&EventDecoder],
+    logs: &Vec<DatabaseEVMTransactionLog>,
+) -> Vec<DatabaseEVMParseFailure> {
+    let mut failures = Vec::new();
+
+    for log in logs {
+        for decoder in decoders {
+            match decoder.try_decode(log) {
+                Ok(Some(_)) => break,
+                Ok(None) => (),
+                Err(reason) => {
+                    failures.push(DatabaseEVMParseFailure {
+                        parser: parser.to_string(),
+                        hash: log.hash.clone(),
+                        log_index: log.log_index,
+                        reason,
+                    });
+
+                    break;
+                }
+            }
+        }
+    }
+
+    failures
+}
+
+/// Decodes calldata for a set of functions declared with human readable signatures,
+/// e.g. `function approve(address spender, uint256 value)`.
+#[derive(Debug, Clone)]
+pub struct FunctionDecoder {
+    pub functions: HashMap<[u8; 4], Function>,
+}
+
+impl FunctionDecoder {
+    pub fn new(signatures: &[&str]) -> Self {
+        let abi = parse_abi(signatures).expect("Unable to parse function signatures");
+
+        let mut functions = HashMap::new();
+
+        for function in abi.functions() {
+            functions.insert(function.short_signature(), function.clone());
+        }
+
+        Self { functions }
+    }
+
+    pub fn decode(&self, input: &[u8]) -> Option<DecodedLog> {
+        if input.len() < 4 {
+            return None;
+        }
+
+        let selector: [u8; 4] = input[..4].try_into().ok()?;
+
+        let function = self.functions.get(&selector)?;
+
+        let tokens = function.decode_input(&input[4..]).ok()?;
+
+        Some(DecodedLog {
+            name: function.name.clone(),
+            params: function
+                .inputs
+                .iter()
+                .map(|param| param.name.clone())
+                .zip(tokens)
+                .collect(),
+        })
+    }
+
+    /// Decodes the calls nested in the calldata of another call, like the calls of a multicall.
+    /// Nested calldata is always encoded at a word boundary after the outer selector.
+    pub fn decode_nested(&self, input: &[u8]) -> Vec<DecodedLog> {
+        let mut calls = Vec::new();
+
+        let mut position = 4;
+
+        while position + 4 <= input.len() {
+            match self.decode(&input[position..]) {
+                Some(call) => calls.push(call),
+                None => (),
+            }
+
+            position += 32;
+        }
+
+        calls
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::evm_duplicate_logs,
+};
+
+/// Protocol of the ERC-20 transfers parser in the policies.
+pub const ERC20_PROTOCOL: &str = "erc20";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupPolicy {
+    /// Every log is parsed, identical events are distinct actions.
+    Keep,
+    /// Only the first log of identical events is parsed, for proxies emitting the events of
+    /// their implementation again.
+    First,
+}
+
+impl DedupPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DedupPolicy::Keep => "keep",
+            DedupPolicy::First => "first",
+        }
+    }
+}
+
+/// Policy of a protocol, e.g. `{ "protocol": "lido", "policy": "first" }`, optionally restricted
+/// to some of its contracts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupRule {
+    pub protocol: String,
+    pub policy: DedupPolicy,
+    pub addresses: Option<Vec<String>>,
+}
+
+pub fn load_dedup_rules(path: &Option<String>) -> Vec<DedupRule> {
+    match path {
+        Some(path) => {
+            let file = std::fs::read_to_string(path).expect("Unable to read dedup policies");
+
+            serde_json::from_str(&file).expect("Unable to parse dedup policies")
+        }
+        None => Vec::new(),
+    }
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_duplicate_logs)]
+pub struct DatabaseEVMDuplicateLog {
+    pub hash: String,
+    pub log_index: i64,
+    pub parser: String,
+    pub protocol: String,
+    pub policy: String,
+    /// Log index of the first log of the identical events, which is the one parsed.
+    pub duplicate_of: i64,
+}
+
+/// Finds the logs of identical events, same transaction, contract, topics and data with a
+/// different log index, which some upgradeable and proxy patterns emit twice. The parsers skip
+/// them according to the policy of their protocol so the derived tables, like balances, don't
+/// count them twice, and the skipped logs are kept in `evm_duplicate_logs` to audit the policy.
+/// Only the logs of the same batch are compared, and the transfers parsed inline by the indexer
+/// keep every log.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateFilter {
+    pub rules: HashMap<String, Vec<DedupRule>>,
+}
+
+impl DuplicateFilter {
+    pub fn new(rules: Vec<DedupRule>) -> Self {
+        let mut protocols: HashMap<String, Vec<DedupRule>> = HashMap::new();
+
+        for rule in rules {
+            let rule = DedupRule {
+                addresses: rule.addresses.map(|addresses| {
+                    addresses
+                        .iter()
+                        .map(|address| address.to_lowercase())
+                        .collect()
+                }),
+                ..rule
+            };
+
+            protocols
+                .entry(rule.protocol.clone())
+                .or_default()
+                .push(rule);
+        }
+
+        Self { rules: protocols }
+    }
+
+    /// Policy of a contract of a protocol, the rules restricted to the contract go first.
+    pub fn get_policy(&self, protocol: &str, address: &String) -> DedupPolicy {
+        let rules = match self.rules.get(protocol) {
+            Some(rules) => rules,
+            None => return DedupPolicy::Keep,
+        };
+
+        let rule = rules
+            .iter()
+            .find(|rule| match &rule.addresses {
+                Some(addresses) => addresses.contains(address),
+                None => false,
+            })
+            .or_else(|| rules.iter().find(|rule| rule.addresses.is_none()));
+
+        match rule {
+            Some(rule) => rule.policy,
+            None => DedupPolicy::Keep,
+        }
+    }
+
+    /// Duplicate logs of a batch, `get_protocol` gives the protocol of a log to parse, or `None`
+    /// when the parser skips it.
+    pub fn find_duplicates<F>(
+        &self,
+        parser: &str,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+        get_protocol: F,
+    ) -> Vec<DatabaseEVMDuplicateLog>
+    where
+        F: Fn(&DatabaseEVMTransactionLog) -> Option<String>,
+    {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+
+        let mut sorted: Vec<&DatabaseEVMTransactionLog> = logs.iter().collect();
+
+        sorted.sort_by_key(|log| (log.hash.clone(), log.log_index));
+
+        let mut first_logs: HashMap<(&String, &String, &Vec<Option<String>>, &String), i64> =
+            HashMap::new();
+
+        let mut duplicates = Vec::new();
+
+        for log in sorted {
+            let protocol = match get_protocol(log) {
+                Some(protocol) => protocol,
+                None => continue,
+            };
+
+            let policy = self.get_policy(&protocol, &log.address);
+
+            if policy == DedupPolicy::Keep {
+                continue;
+            }
+
+            let key = (&log.hash, &log.address, &log.topics, &log.data);
+
+            match first_logs.get(&key) {
+                Some(first_log_index) => duplicates.push(DatabaseEVMDuplicateLog {
+                    hash: log.hash.clone(),
+                    log_index: log.log_index,
+                    parser: parser.to_string(),
+                    protocol,
+                    policy: policy.name().to_string(),
+                    duplicate_of: *first_log_index,
+                }),
+                None => {
+                    first_logs.insert(key, log.log_index);
+                }
+            }
+        }
+
+        duplicates
+    }
+}
+
+/// Logs to skip, by hash and log index.
+pub fn get_duplicate_keys(duplicates: &Vec<DatabaseEVMDuplicateLog>) -> HashSet<(String, i64)> {
+    duplicates
+        .iter()
+        .map(|duplicate| (duplicate.hash.clone(), duplicate.log_index))
+        .collect()
+}
+
+pub fn store_duplicate_logs(
+    db: &EVMDatabase,
+    duplicates: &Vec<DatabaseEVMDuplicateLog>,
+) -> Result<()> {
+    if duplicates.len() == 0 {
+        return Ok(());
+    }
+
+    let mut connection = db.establish_connection();
+
+    let chunks = get_chunks(duplicates.len(), DatabaseEVMDuplicateLog::field_count());
+
+    for (start, end) in chunks {
+        diesel::insert_into(evm_duplicate_logs::dsl::evm_duplicate_logs)
+            .values(&duplicates[start..end])
+            .on_conflict_do_nothing()
+            .execute(&mut connection)?;
+    }
+
+    info!("Skipped {} duplicate logs.", duplicates.len());
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use diesel::{prelude::*, upsert::excluded};
+use field_count::FieldCount;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    schema::evm_protocol_deployments,
+};
+
+/// Contract of a protocol on a chain, e.g. the Aave v3 pool on Polygon. The contract names the
+/// role of the address within the protocol, like `pool` or `factory`, when known.
+#[derive(
+    Selectable,
+    Queryable,
+    Insertable,
+    Default,
+    Debug,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    FieldCount,
+)]
+#[diesel(table_name = evm_protocol_deployments)]
+pub struct ProtocolDeployment {
+    pub chain: String,
+    pub protocol: String,
+    pub address: String,
+    #[serde(default)]
+    pub contract: Option<String>,
+}
+
+/// Filters of the deployments listed by the API, e.g. `{ "chain": "ethereum", "protocol": "aave-v3" }`.
+#[derive(Default, Debug, Clone, Deserialize)]
+pub struct DeploymentsParams {
+    pub chain: Option<String>,
+    pub protocol: Option<String>,
+}
+
+pub fn load_protocol_deployments(path: &String) -> Vec<ProtocolDeployment> {
+    let file = std::fs::read_to_string(path).expect("Unable to read protocol deployments");
+
+    serde_json::from_str(&file).expect("Unable to parse protocol deployments")
+}
+
+/// Adds the deployments to the registry, an address already registered takes the new protocol
+/// and contract. Returns the amount of deployments stored.
+pub fn store_protocol_deployments(
+    db: &EVMDatabase,
+    deployments: &Vec<ProtocolDeployment>,
+) -> Result<usize> {
+    let deployments: Vec<ProtocolDeployment> = deployments
+        .iter()
+        .map(|deployment| ProtocolDeployment {
+            address: deployment.address.to_lowercase(),
+            ..deployment.clone()
+        })
+        .collect();
+
+    let mut connection = db.establish_connection();
+
+    let chunks = get_chunks(deployments.len(), ProtocolDeployment::field_count());
+
+    for (start, end) in chunks {
+        diesel::insert_into(evm_protocol_deployments::dsl::evm_protocol_deployments)
+            .values(&deployments[start..end])
+            .on_conflict((
+                evm_protocol_deployments::chain,
+                evm_protocol_deployments::address,
+            ))
+            .do_update()
+            .set((
+                evm_protocol_deployments::protocol.eq(excluded(evm_protocol_deployments::protocol)),
+                evm_protocol_deployments::contract.eq(excluded(evm_protocol_deployments::contract)),
+            ))
+            .execute(&mut connection)?;
+    }
+
+    Ok(deployments.len())
+}
+
+/// Registered deployments of every chain, optionally of a single chain or protocol.
+pub fn get_protocol_deployments(
+    db: &EVMDatabase,
+    params: &DeploymentsParams,
+) -> Result<Vec<ProtocolDeployment>> {
+    let mut connection = db.establish_read_connection();
+
+    let mut query = evm_protocol_deployments::table
+        .select(ProtocolDeployment::as_select())
+        .order((
+            evm_protocol_deployments::chain,
+            evm_protocol_deployments::protocol,
+            evm_protocol_deployments::address,
+        ))
+        .into_boxed();
+
+    match &params.chain {
+        Some(chain) => query = query.filter(evm_protocol_deployments::chain.eq(chain)),
+        None => (),
+    }
+
+    match &params.protocol {
+        Some(protocol) => query = query.filter(evm_protocol_deployments::protocol.eq(protocol)),
+        None => (),
+    }
+
+    Ok(query.load::<ProtocolDeployment>(&mut connection)?)
+}
+
+/// Registers the deployments a parser starts with, its defaults or file, and returns every
+/// registered deployment of the protocols it parses, so the deployments added to the registry
+/// are parsed without changing the parser.
+pub fn register_deployments(
+    db: &EVMDatabase,
+    deployments: Vec<ProtocolDeployment>,
+    protocols: &[&str],
+) -> Result<Vec<ProtocolDeployment>> {
+    store_protocol_deployments(db, &deployments)?;
+
+    let mut connection = db.establish_read_connection();
+
+    let registered = evm_protocol_deployments::table
+        .select(ProtocolDeployment::as_select())
+        .filter(evm_protocol_deployments::protocol.eq_any(protocols))
+        .load::<ProtocolDeployment>(&mut connection)?;
+
+    let protocols: HashSet<&String> = registered
+        .iter()
+        .map(|deployment| &deployment.protocol)
+        .collect();
+
+    info!(
+        "Loaded {} registered deployments of {} protocols.",
+        registered.len(),
+        protocols.len()
+    );
+
+    Ok(registered)
+}
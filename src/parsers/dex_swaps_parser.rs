@@ -0,0 +1,249 @@
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::evm_dex_swaps,
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder};
+
+#[derive(Selectable, Queryable, QueryableByName, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_dex_swaps)]
+pub struct DatabaseEVMDexSwap {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub dex: String,
+    pub pool: String,
+    pub sender: String,
+    pub recipient: Option<String>,
+    pub token_in: Option<String>,
+    pub token_out: Option<String>,
+    pub token_in_index: Option<i64>,
+    pub token_out_index: Option<i64>,
+    pub amount_in: String,
+    pub amount_out: String,
+}
+
+/// Normalizes the swaps of the supported DEXes into `evm_dex_swaps`. Uniswap and Curve pools only
+/// emit the index of the pool tokens, Balancer emits the token addresses.
+pub struct DexSwapsParser {
+    pub uniswap_v2: EventDecoder,
+    pub uniswap_v3: EventDecoder,
+    pub curve: EventDecoder,
+    pub balancer: EventDecoder,
+}
+
+impl DexSwapsParser {
+    pub fn new() -> Self {
+        Self {
+            uniswap_v2: EventDecoder::new(&[
+                "event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+            ]),
+            uniswap_v3: EventDecoder::new(&[
+                "event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)",
+            ]),
+            curve: EventDecoder::new(&[
+                "event TokenExchange(address indexed buyer, int128 sold_id, uint256 tokens_sold, int128 bought_id, uint256 tokens_bought)",
+                "event TokenExchangeUnderlying(address indexed buyer, int128 sold_id, uint256 tokens_sold, int128 bought_id, uint256 tokens_bought)",
+            ]),
+            balancer: EventDecoder::new(&[
+                "event Swap(bytes32 indexed poolId, address indexed tokenIn, address indexed tokenOut, uint256 amountIn, uint256 amountOut)",
+            ]),
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut topics = self.uniswap_v2.topics();
+
+        topics.append(&mut self.uniswap_v3.topics());
+        topics.append(&mut self.curve.topics());
+        topics.append(&mut self.balancer.topics());
+
+        db.get_unparsed_logs("dex_swaps", &topics, None, 10000)
+            .await
+    }
+
+    #[instrument(name = "dex_swaps_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let mut db_dex_swaps = Vec::new();
+
+        for log in logs {
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let swap = if let Some(decoded) = self.uniswap_v2.decode(log) {
+                get_uniswap_v2_swap(&decoded, log, chain)
+            } else if let Some(decoded) = self.uniswap_v3.decode(log) {
+                get_uniswap_v3_swap(&decoded, log, chain)
+            } else if let Some(decoded) = self.curve.decode(log) {
+                get_curve_swap(&decoded, log, chain)
+            } else if let Some(decoded) = self.balancer.decode(log) {
+                get_balancer_swap(&decoded, log, chain)
+            } else {
+                None
+            };
+
+            match swap {
+                Some(swap) => db_dex_swaps.push(swap),
+                None => continue,
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(db_dex_swaps.len(), DatabaseEVMDexSwap::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_dex_swaps::dsl::evm_dex_swaps)
+                .values(&db_dex_swaps[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store dex swaps into database");
+        }
+
+        info!("Inserted {} dex swaps to the database.", db_dex_swaps.len());
+
+        let failures = get_parse_failures(
+            "dex_swaps",
+            &[
+                &self.uniswap_v2,
+                &self.uniswap_v3,
+                &self.curve,
+                &self.balancer,
+            ],
+            logs,
+        );
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs("dex_swaps", logs).await
+    }
+}
+
+fn get_uniswap_v2_swap(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMDexSwap> {
+    let amount0_in = decoded.uint("amount0In")?;
+    let amount1_in = decoded.uint("amount1In")?;
+    let amount0_out = decoded.uint("amount0Out")?;
+    let amount1_out = decoded.uint("amount1Out")?;
+
+    let (token_in_index, token_out_index, amount_in, amount_out) = if amount0_in != "0" {
+        (0, 1, amount0_in, amount1_out)
+    } else {
+        (1, 0, amount1_in, amount0_out)
+    };
+
+    Some(DatabaseEVMDexSwap {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        dex: "uniswap-v2".to_string(),
+        pool: log.address.clone(),
+        sender: decoded.address("sender")?,
+        recipient: decoded.address("to"),
+        token_in: None,
+        token_out: None,
+        token_in_index: Some(token_in_index),
+        token_out_index: Some(token_out_index),
+        amount_in,
+        amount_out,
+    })
+}
+
+fn get_uniswap_v3_swap(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMDexSwap> {
+    let amount0 = decoded.int("amount0")?;
+    let amount1 = decoded.int("amount1")?;
+
+    // Positive amounts are received by the pool, negative amounts are sent out of it.
+    let (token_in_index, token_out_index, amount_in, amount_out) = if amount0.is_positive() {
+        (0, 1, amount0, amount1.abs())
+    } else {
+        (1, 0, amount1, amount0.abs())
+    };
+
+    Some(DatabaseEVMDexSwap {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        dex: "uniswap-v3".to_string(),
+        pool: log.address.clone(),
+        sender: decoded.address("sender")?,
+        recipient: decoded.address("recipient"),
+        token_in: None,
+        token_out: None,
+        token_in_index: Some(token_in_index),
+        token_out_index: Some(token_out_index),
+        amount_in: amount_in.to_string(),
+        amount_out: amount_out.to_string(),
+    })
+}
+
+fn get_curve_swap(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMDexSwap> {
+    Some(DatabaseEVMDexSwap {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        dex: "curve".to_string(),
+        pool: log.address.clone(),
+        sender: decoded.address("buyer")?,
+        recipient: decoded.address("buyer"),
+        token_in: None,
+        token_out: None,
+        token_in_index: i64::try_from(decoded.int("sold_id")?).ok(),
+        token_out_index: i64::try_from(decoded.int("bought_id")?).ok(),
+        amount_in: decoded.uint("tokens_sold")?,
+        amount_out: decoded.uint("tokens_bought")?,
+    })
+}
+
+fn get_balancer_swap(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMDexSwap> {
+    // The first 20 bytes of a Balancer pool id are the pool address.
+    let pool_id = decoded.bytes("poolId")?;
+
+    Some(DatabaseEVMDexSwap {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        dex: "balancer".to_string(),
+        pool: pool_id[..42].to_string(),
+        sender: log.address.clone(),
+        recipient: None,
+        token_in: decoded.address("tokenIn"),
+        token_out: decoded.address("tokenOut"),
+        token_in_index: None,
+        token_out_index: None,
+        amount_in: decoded.uint("amountIn")?,
+        amount_out: decoded.uint("amountOut")?,
+    })
+}
@@ -0,0 +1,391 @@
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Text},
+};
+use ethers::providers::ens::namehash;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::{evm_ens_records, evm_ens_registrations, evm_ens_resolvers, evm_ens_reverse_nodes},
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder};
+
+/// ENS is only deployed on Ethereum, logs from other chains are ignored.
+pub const ENS_CHAIN: &str = "ethereum";
+
+/// ENS registry and the registry it falls back to, the resolvers of the nodes are only trusted
+/// from these contracts.
+pub const ENS_REGISTRIES: [&str; 2] = [
+    "0x00000000000c2e074ec69a0dfb2997ba6c7d2e1e",
+    "0x314159265dd8dbb310642f98f50c066173c1259b",
+];
+
+/// `.eth` registrar controllers and reverse registrars, the registrations and reverse claims are
+/// only trusted from these contracts. Resolver records are parsed from any resolver, and only
+/// read from the resolver set for the node in the registry.
+pub const ENS_REGISTRARS: [&str; 4] = [
+    "0x283af0b28c62c092c9727f1ee09c02ca627eb7f5",
+    "0x253553366da8546fc250f225fe3d25d0c782303b",
+    "0x084b1c3c81545d370f3634392de611caabff8148",
+    "0xa58e81fe9b61b5c3fe2afd33cf304c454abfc7cb",
+];
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_ens_registrations)]
+pub struct DatabaseEVMEnsRegistration {
+    pub hash: String,
+    pub log_index: i64,
+    pub block_number: i64,
+    pub action: String,
+    pub name: String,
+    pub label: String,
+    pub node: String,
+    pub owner: Option<String>,
+    pub expires: String,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_ens_records)]
+pub struct DatabaseEVMEnsRecord {
+    pub hash: String,
+    pub log_index: i64,
+    pub block_number: i64,
+    pub resolver: String,
+    pub node: String,
+    pub kind: String,
+    pub value: String,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_ens_resolvers)]
+pub struct DatabaseEVMEnsResolver {
+    pub hash: String,
+    pub log_index: i64,
+    pub block_number: i64,
+    pub node: String,
+    pub resolver: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct RecordValue {
+    #[diesel(sql_type = Text)]
+    value: String,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_ens_reverse_nodes)]
+pub struct DatabaseEVMEnsReverseNode {
+    pub node: String,
+    pub address: String,
+}
+
+pub struct ENSParser {
+    pub registry: EventDecoder,
+    pub registrar: EventDecoder,
+    pub resolver: EventDecoder,
+}
+
+impl ENSParser {
+    pub fn new() -> Self {
+        Self {
+            registry: EventDecoder::new(&[
+                "event NewResolver(bytes32 indexed node, address resolver)",
+            ]),
+            registrar: EventDecoder::new(&[
+                "event NameRegistered(string name, bytes32 indexed label, address indexed owner, uint256 cost, uint256 expires)",
+                "event NameRegistered(string name, bytes32 indexed label, address indexed owner, uint256 baseCost, uint256 premium, uint256 expires)",
+                "event NameRenewed(string name, bytes32 indexed label, uint256 cost, uint256 expires)",
+                "event ReverseClaimed(address indexed addr, bytes32 indexed node)",
+            ]),
+            resolver: EventDecoder::new(&[
+                "event AddrChanged(bytes32 indexed node, address a)",
+                "event NameChanged(bytes32 indexed node, string name)",
+            ]),
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let registrars: Vec<String> = ENS_REGISTRARS
+            .iter()
+            .map(|address| address.to_string())
+            .collect();
+
+        let registries: Vec<String> = ENS_REGISTRIES
+            .iter()
+            .map(|address| address.to_string())
+            .collect();
+
+        let mut logs = db
+            .get_unparsed_logs("ens", &self.registrar.topics(), Some(&registrars), 5000)
+            .await?;
+
+        let mut registry_logs = db
+            .get_unparsed_logs("ens", &self.registry.topics(), Some(&registries), 5000)
+            .await?;
+
+        logs.append(&mut registry_logs);
+
+        let mut resolver_logs = db
+            .get_unparsed_logs("ens", &self.resolver.topics(), None, 5000)
+            .await?;
+
+        logs.append(&mut resolver_logs);
+
+        Ok(logs)
+    }
+
+    #[instrument(name = "ens_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let blocks = db.get_transactions_blocks(&hashes).await?;
+
+        let mut db_registrations = Vec::new();
+
+        let mut db_records = Vec::new();
+
+        let mut db_reverse_nodes = Vec::new();
+
+        let mut db_resolvers = Vec::new();
+
+        for log in logs {
+            match chains.get(&log.hash) {
+                Some(chain) if chain == ENS_CHAIN => (),
+                _ => continue,
+            }
+
+            let block_number = match blocks.get(&log.hash) {
+                Some(block_number) => *block_number,
+                None => continue,
+            };
+
+            if ENS_REGISTRIES.contains(&log.address.as_str()) {
+                match self.registry.decode(log) {
+                    Some(decoded) => match get_resolver(&decoded, log, block_number) {
+                        Some(resolver) => db_resolvers.push(resolver),
+                        None => continue,
+                    },
+                    None => continue,
+                }
+            } else if let Some(decoded) = self.registrar.decode(log) {
+                if decoded.name == "ReverseClaimed" {
+                    match get_reverse_node(&decoded) {
+                        Some(reverse_node) => db_reverse_nodes.push(reverse_node),
+                        None => continue,
+                    }
+                } else {
+                    match get_registration(&decoded, log, block_number) {
+                        Some(registration) => db_registrations.push(registration),
+                        None => continue,
+                    }
+                }
+            } else if let Some(decoded) = self.resolver.decode(log) {
+                match get_record(&decoded, log, block_number) {
+                    Some(record) => db_records.push(record),
+                    None => continue,
+                }
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(
+            db_registrations.len(),
+            DatabaseEVMEnsRegistration::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_ens_registrations::dsl::evm_ens_registrations)
+                .values(&db_registrations[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store ens registrations into database");
+        }
+
+        let chunks = get_chunks(db_records.len(), DatabaseEVMEnsRecord::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_ens_records::dsl::evm_ens_records)
+                .values(&db_records[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store ens records into database");
+        }
+
+        let chunks = get_chunks(
+            db_reverse_nodes.len(),
+            DatabaseEVMEnsReverseNode::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_ens_reverse_nodes::dsl::evm_ens_reverse_nodes)
+                .values(&db_reverse_nodes[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store ens reverse nodes into database");
+        }
+
+        let chunks = get_chunks(db_resolvers.len(), DatabaseEVMEnsResolver::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_ens_resolvers::dsl::evm_ens_resolvers)
+                .values(&db_resolvers[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store ens resolvers into database");
+        }
+
+        info!(
+            "Inserted {} ens registrations, {} ens records and {} ens resolvers to the database.",
+            db_registrations.len(),
+            db_records.len(),
+            db_resolvers.len()
+        );
+
+        let failures = get_parse_failures(
+            "ens",
+            &[&self.registry, &self.registrar, &self.resolver],
+            logs,
+        );
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs("ens", logs).await
+    }
+}
+
+fn get_registration(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    block_number: i64,
+) -> Option<DatabaseEVMEnsRegistration> {
+    let action = match decoded.name.as_str() {
+        "NameRegistered" => "register",
+        "NameRenewed" => "renew",
+        _ => return None,
+    };
+
+    let name = format!("{}.eth", decoded.string("name")?);
+
+    Some(DatabaseEVMEnsRegistration {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        block_number,
+        action: action.to_string(),
+        node: format!("{:?}", namehash(&name)),
+        name,
+        label: decoded.bytes("label")?,
+        owner: decoded.address("owner"),
+        expires: decoded.uint("expires")?,
+    })
+}
+
+fn get_record(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    block_number: i64,
+) -> Option<DatabaseEVMEnsRecord> {
+    let (kind, value) = match decoded.name.as_str() {
+        "AddrChanged" => ("addr", decoded.address("a")?),
+        "NameChanged" => ("name", decoded.string("name")?),
+        _ => return None,
+    };
+
+    Some(DatabaseEVMEnsRecord {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        block_number,
+        resolver: log.address.clone(),
+        node: decoded.bytes("node")?,
+        kind: kind.to_string(),
+        value,
+    })
+}
+
+fn get_resolver(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    block_number: i64,
+) -> Option<DatabaseEVMEnsResolver> {
+    Some(DatabaseEVMEnsResolver {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        block_number,
+        node: decoded.bytes("node")?,
+        resolver: decoded.address("resolver")?,
+    })
+}
+
+fn get_reverse_node(decoded: &DecodedLog) -> Option<DatabaseEVMEnsReverseNode> {
+    Some(DatabaseEVMEnsReverseNode {
+        node: decoded.bytes("node")?,
+        address: decoded.address("addr")?,
+    })
+}
+
+/// Latest value of a record of the node at or before the block, set by the resolver of the node
+/// in the registry at that block. Records of other resolvers are ignored, anyone can emit them.
+fn get_record_at(
+    connection: &mut PgConnection,
+    nodes: Vec<String>,
+    kind: &str,
+    block_number: i64,
+) -> Option<String> {
+    sql_query(
+        "SELECT r.value FROM evm_ens_records r \
+        WHERE r.node = ANY($1) AND r.kind = $2 AND r.block_number <= $3 \
+        AND r.resolver = (SELECT s.resolver FROM evm_ens_resolvers s \
+        WHERE s.node = r.node AND s.block_number <= $3 \
+        ORDER BY s.block_number DESC, s.log_index DESC LIMIT 1) \
+        ORDER BY r.block_number DESC, r.log_index DESC LIMIT 1",
+    )
+    .bind::<Array<Text>, _>(nodes)
+    .bind::<Text, _>(kind)
+    .bind::<BigInt, _>(block_number)
+    .get_result::<RecordValue>(connection)
+    .ok()
+    .map(|record| record.value)
+}
+
+/// Primary ENS name of the address at the block. The name set on the reverse record is only
+/// returned when the name also resolved to the address at that block.
+pub fn get_ens_name(
+    connection: &mut PgConnection,
+    address: &String,
+    block_number: i64,
+) -> Option<String> {
+    let reverse_nodes: Vec<String> = evm_ens_reverse_nodes::table
+        .select(evm_ens_reverse_nodes::node)
+        .filter(evm_ens_reverse_nodes::address.eq(address.to_lowercase()))
+        .load::<String>(connection)
+        .ok()?;
+
+    if reverse_nodes.len() == 0 {
+        return None;
+    }
+
+    let name = get_record_at(connection, reverse_nodes, "name", block_number)?;
+
+    let node = format!("{:?}", namehash(&name));
+
+    let resolved = get_record_at(connection, vec![node], "addr", block_number)?;
+
+    if resolved == address.to_lowercase() {
+        Some(name)
+    } else {
+        None
+    }
+}
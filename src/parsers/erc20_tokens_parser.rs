@@ -1,24 +1,36 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     chains::chains::get_chain,
     db::{
         db::{get_chunks, EVMDatabase},
-        schema::{evm_erc20_tokens, evm_erc20_transfers, evm_transactions},
+        schema::{evm_erc20_tokens, evm_erc20_transfers, evm_excluded_tokens},
     },
 };
 use anyhow::Result;
-use diesel::{prelude::*, result::Error};
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Nullable, Text},
+    upsert::excluded,
+};
 use ethabi::Address;
 use ethers::{
+    contract::ContractError,
     prelude::abigen,
     providers::{Http, Provider},
 };
 use field_count::FieldCount;
 use futures::future::join_all;
-use log::info;
+use log::{info, warn};
+use tracing::instrument;
 
-use super::erc20_transfers_parser::DatabaseEVMErc20Transfer;
+use super::erc20_transfers_parser::{store_decimal_values, DatabaseEVMErc20Transfer};
 
 #[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
 #[diesel(table_name = evm_erc20_tokens)]
@@ -28,8 +40,72 @@ pub struct DatabaseEVMErc20Token {
     pub name: Option<String>,
     pub decimals: Option<i64>,
     pub symbol: Option<String>,
+    pub spam_score: Option<i64>,
+    pub spam_reasons: Option<Vec<Option<String>>>,
+    /// Logo URL from the imported token lists.
+    pub logo: Option<String>,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_excluded_tokens)]
+pub struct DatabaseEVMExcludedToken {
+    pub address: String,
+    pub chain: String,
+    pub reason: String,
+    pub attempts: i64,
+    /// Unix time after which the metadata is fetched again, never for non-conforming tokens.
+    pub retry_after: Option<i64>,
+}
+
+/// Reason a token metadata fetch failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenExclusion {
+    /// The token reverted or returned undecodable data, it doesn't implement the erc20 metadata.
+    Revert,
+    /// The RPC failed to answer, the fetch is retried with backoff.
+    RpcError,
+    /// The token returned an empty name and symbol, which can be a proxy not initialized yet, so
+    /// it's retried a few times.
+    Empty,
+}
+
+impl TokenExclusion {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenExclusion::Revert => "revert",
+            TokenExclusion::RpcError => "rpc_error",
+            TokenExclusion::Empty => "empty",
+        }
+    }
+
+    /// Unix time of the next fetch after the given failed attempts, none when the token stays
+    /// excluded.
+    fn get_retry_after(&self, attempts: i64, now: i64) -> Option<i64> {
+        let backoff =
+            (RETRY_BACKOFF * 2i64.pow((attempts - 1).clamp(0, 16) as u32)).min(MAX_RETRY_BACKOFF);
+
+        match self {
+            TokenExclusion::Revert => None,
+            TokenExclusion::RpcError => Some(now + backoff),
+            TokenExclusion::Empty if attempts < MAX_EMPTY_ATTEMPTS => Some(now + backoff),
+            TokenExclusion::Empty => None,
+        }
+    }
 }
 
+/// Seconds before the first retry of a failed token, doubled on each failed attempt.
+const RETRY_BACKOFF: i64 = 60;
+
+const MAX_RETRY_BACKOFF: i64 = 86400;
+
+const MAX_EMPTY_ATTEMPTS: i64 = 5;
+
+/// Excluded tokens due for a retry fetched on each batch, even without new transfers.
+const RETRIES_PER_BATCH: i64 = 100;
+
+/// Fetches the name, symbol and decimals of the tokens of the parsed transfers. Tokens failing
+/// the fetch go to `evm_excluded_tokens` with the reason of the failure: RPC errors and empty
+/// metadata are retried with an exponential backoff, reverting tokens stay excluded.
 pub struct ERC20TokensParser {}
 
 abigen!(
@@ -43,10 +119,10 @@ abigen!(
 
 impl ERC20TokensParser {
     pub fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMErc20Transfer>> {
-        let mut connection = db.establish_connection();
+        let mut connection = db.establish_read_connection();
 
         let transfers: Result<Vec<DatabaseEVMErc20Transfer>, Error> = evm_erc20_transfers::table
-            .select(evm_erc20_transfers::all_columns)
+            .select(DatabaseEVMErc20Transfer::as_select())
             .filter(
                 evm_erc20_transfers::erc20_tokens_parced
                     .is_null()
@@ -61,6 +137,7 @@ impl ERC20TokensParser {
         }
     }
 
+    #[instrument(name = "erc20_tokens_parser", skip_all, fields(transfers = transfers.len()))]
     pub async fn parse(
         &self,
         db: &EVMDatabase,
@@ -68,46 +145,129 @@ impl ERC20TokensParser {
     ) -> Result<()> {
         let mut connection = db.establish_connection();
 
+        let hashes: Vec<String> = transfers
+            .iter()
+            .map(|transfer| transfer.hash.clone())
+            .collect();
+
+        // Transfers of backfilled logs only have their chain in the log transactions.
+        let chains = db
+            .get_transactions_chains(&hashes)
+            .await
+            .expect("Unable to load the transfers chains from database");
+
         let unique_tokens: Vec<String> = transfers
             .into_iter()
-            .map(|transfer| {
-                let chain: String = evm_transactions::table
-                    .select(evm_transactions::chain)
-                    .filter(evm_transactions::hash.eq(transfer.hash.clone()))
-                    .first::<String>(&mut connection)
-                    .unwrap();
-
-                return format!("{}-{}", transfer.token, chain);
+            .filter_map(|transfer| {
+                let chain = chains.get(&transfer.hash)?;
+
+                Some(format!("{}-{}", transfer.token, chain))
             })
             .collect::<HashSet<_>>()
             .into_iter()
             .collect();
 
+        // Tokens seeded from the token lists already have their metadata.
+        let addresses: Vec<String> = transfers
+            .iter()
+            .map(|transfer| transfer.token.clone())
+            .collect();
+
+        let known_tokens: HashSet<String> = evm_erc20_tokens::table
+            .select((evm_erc20_tokens::address, evm_erc20_tokens::chain))
+            .filter(evm_erc20_tokens::address.eq_any(&addresses))
+            .filter(evm_erc20_tokens::decimals.is_not_null())
+            .load::<(String, String)>(&mut connection)
+            .expect("Unable to load known erc20 tokens from database")
+            .into_iter()
+            .map(|(address, chain)| format!("{}-{}", address, chain))
+            .collect();
+
+        let now = get_timestamp();
+
+        let exclusions = self.get_exclusions(&mut connection, &addresses, now);
+
+        // Excluded tokens are only fetched again once their retry is due.
+        let is_due = |token: &String| match exclusions.get(token) {
+            Some(exclusion) => exclusion.retry_after.map_or(false, |at| at <= now),
+            None => true,
+        };
+
+        let mut pending_tokens: HashSet<String> = unique_tokens
+            .into_iter()
+            .filter(|token| !known_tokens.contains(token))
+            .collect();
+
+        pending_tokens.extend(exclusions.keys().cloned());
+
         let mut tokens_data = vec![];
 
-        for token in unique_tokens {
+        for token in pending_tokens.into_iter().filter(is_due) {
             tokens_data.push(self.get_token_metadata(token))
         }
 
-        let db_tokens: Vec<DatabaseEVMErc20Token> = join_all(tokens_data)
-            .await
-            .into_iter()
-            .filter(|token| token.is_some())
-            .map(|token| token.unwrap())
-            .collect();
+        let mut db_tokens: Vec<DatabaseEVMErc20Token> = Vec::new();
+        let mut db_exclusions: Vec<DatabaseEVMExcludedToken> = Vec::new();
+
+        for (token, metadata) in join_all(tokens_data).await {
+            match metadata {
+                Ok(metadata) => db_tokens.push(metadata),
+                Err(reason) => {
+                    let attempts = match exclusions.get(&token) {
+                        Some(exclusion) => exclusion.attempts + 1,
+                        None => 1,
+                    };
+
+                    let (address, chain) = split_token_id(&token);
 
+                    db_exclusions.push(DatabaseEVMExcludedToken {
+                        address,
+                        chain,
+                        reason: reason.code().to_string(),
+                        attempts,
+                        retry_after: reason.get_retry_after(attempts, now),
+                    })
+                }
+            }
+        }
+
+        // Tokens stored without metadata before being excluded get it filled on a retry.
         let chunks = get_chunks(db_tokens.len(), DatabaseEVMErc20Token::field_count());
 
         for (start, end) in chunks {
             diesel::insert_into(evm_erc20_tokens::dsl::evm_erc20_tokens)
                 .values(&db_tokens[start..end])
-                .on_conflict_do_nothing()
+                .on_conflict((evm_erc20_tokens::address, evm_erc20_tokens::chain))
+                .do_update()
+                .set((
+                    evm_erc20_tokens::name.eq(sql::<Nullable<Text>>(
+                        "COALESCE(evm_erc20_tokens.name, EXCLUDED.name)",
+                    )),
+                    evm_erc20_tokens::decimals.eq(sql::<Nullable<BigInt>>(
+                        "COALESCE(evm_erc20_tokens.decimals, EXCLUDED.decimals)",
+                    )),
+                    evm_erc20_tokens::symbol.eq(sql::<Nullable<Text>>(
+                        "COALESCE(evm_erc20_tokens.symbol, EXCLUDED.symbol)",
+                    )),
+                ))
                 .execute(&mut connection)
                 .expect("Unable to store erc20 tokens into database");
         }
 
+        self.store_exclusions(&mut connection, &db_tokens, &db_exclusions);
+
         info!("Inserted {} erc20 tokens to the database.", db_tokens.len());
 
+        let tokens: Vec<String> = db_tokens
+            .iter()
+            .map(|token| token.address.clone())
+            .collect();
+
+        let decimal_values = store_decimal_values(&mut connection, None, Some(&tokens))
+            .expect("Unable to store erc20 transfers decimal values into database");
+
+        info!("Filled {} erc20 transfers decimal values.", decimal_values);
+
         let transfers_chunks = get_chunks(transfers.len(), DatabaseEVMErc20Transfer::field_count());
 
         for (start, end) in transfers_chunks {
@@ -123,44 +283,182 @@ impl ERC20TokensParser {
         Ok(())
     }
 
-    async fn get_token_metadata(&self, token_id: String) -> Option<DatabaseEVMErc20Token> {
-        let address_chain: Vec<&str> = token_id.split("-").collect();
+    /// Exclusions of the tokens of the batch and the ones due for a retry, by token id.
+    fn get_exclusions(
+        &self,
+        connection: &mut PgConnection,
+        addresses: &Vec<String>,
+        now: i64,
+    ) -> HashMap<String, DatabaseEVMExcludedToken> {
+        let mut exclusions = evm_excluded_tokens::table
+            .select(DatabaseEVMExcludedToken::as_select())
+            .filter(evm_excluded_tokens::address.eq_any(addresses))
+            .load::<DatabaseEVMExcludedToken>(connection)
+            .expect("Unable to load excluded erc20 tokens from database");
+
+        let mut retries = evm_excluded_tokens::table
+            .select(DatabaseEVMExcludedToken::as_select())
+            .filter(evm_excluded_tokens::retry_after.le(now))
+            .order(evm_excluded_tokens::retry_after.asc())
+            .limit(RETRIES_PER_BATCH)
+            .load::<DatabaseEVMExcludedToken>(connection)
+            .expect("Unable to load excluded erc20 tokens from database");
+
+        exclusions.append(&mut retries);
+
+        exclusions
+            .into_iter()
+            .map(|exclusion| {
+                (
+                    format!("{}-{}", exclusion.address, exclusion.chain),
+                    exclusion,
+                )
+            })
+            .collect()
+    }
+
+    fn store_exclusions(
+        &self,
+        connection: &mut PgConnection,
+        tokens: &Vec<DatabaseEVMErc20Token>,
+        exclusions: &Vec<DatabaseEVMExcludedToken>,
+    ) {
+        for token in tokens {
+            diesel::delete(
+                evm_excluded_tokens::table
+                    .filter(evm_excluded_tokens::address.eq(&token.address))
+                    .filter(evm_excluded_tokens::chain.eq(&token.chain)),
+            )
+            .execute(connection)
+            .expect("Unable to delete excluded erc20 tokens from database");
+        }
+
+        let chunks = get_chunks(exclusions.len(), DatabaseEVMExcludedToken::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_excluded_tokens::dsl::evm_excluded_tokens)
+                .values(&exclusions[start..end])
+                .on_conflict((evm_excluded_tokens::address, evm_excluded_tokens::chain))
+                .do_update()
+                .set((
+                    evm_excluded_tokens::reason.eq(excluded(evm_excluded_tokens::reason)),
+                    evm_excluded_tokens::attempts.eq(excluded(evm_excluded_tokens::attempts)),
+                    evm_excluded_tokens::retry_after.eq(excluded(evm_excluded_tokens::retry_after)),
+                ))
+                .execute(connection)
+                .expect("Unable to store excluded erc20 tokens into database");
+        }
+
+        if exclusions.len() > 0 {
+            warn!(
+                "Excluded {} erc20 tokens failing the metadata fetch.",
+                exclusions.len()
+            );
+        }
+    }
 
-        let address = address_chain[0];
-        let chain = address_chain[1];
+    async fn get_token_metadata(
+        &self,
+        token_id: String,
+    ) -> (String, Result<DatabaseEVMErc20Token, TokenExclusion>) {
+        let (address, chain) = split_token_id(&token_id);
 
-        let chain_data = get_chain(chain.to_string());
+        let chain_data = get_chain(chain.clone());
 
         let provider = match Provider::<Http>::try_from(chain_data.public_rpc) {
             Ok(provider) => provider,
-            Err(_) => return None,
+            Err(_) => return (token_id, Err(TokenExclusion::RpcError)),
         };
 
         let client = Arc::new(provider);
 
         let token = ERC20::new(address.parse::<Address>().unwrap(), Arc::clone(&client));
 
-        let name: Option<String> = match token.name().call().await {
-            Ok(name) => Some(format!("{}", name.trim_matches(char::from(0)))),
-            Err(_) => None,
-        };
+        let name = token
+            .name()
+            .call()
+            .await
+            .map(|name| format!("{}", name.trim_matches(char::from(0))));
 
-        let decimals: Option<i64> = match token.decimals().call().await {
-            Ok(decimals) => Some(decimals.into()),
-            Err(_) => None,
-        };
+        let decimals = token
+            .decimals()
+            .call()
+            .await
+            .map(|decimals| i64::from(decimals));
+
+        let symbol = token
+            .symbol()
+            .call()
+            .await
+            .map(|symbol| format!("{}", symbol.trim_matches(char::from(0))));
+
+        // A failing RPC says nothing about the token, the other failures are its own answers.
+        let failures: Vec<TokenExclusion> = [
+            name.as_ref().err(),
+            decimals.as_ref().err(),
+            symbol.as_ref().err(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(get_call_exclusion)
+        .collect();
+
+        if failures.contains(&TokenExclusion::RpcError) {
+            return (token_id, Err(TokenExclusion::RpcError));
+        }
 
-        let symbol: Option<String> = match token.symbol().call().await {
-            Ok(symbol) => Some(format!("{}", symbol.trim_matches(char::from(0)))),
-            Err(_) => None,
+        let decimals = match decimals {
+            Ok(decimals) => decimals,
+            Err(_) => return (token_id, Err(TokenExclusion::Revert)),
         };
 
-        return Some(DatabaseEVMErc20Token {
-            address: address_chain[0].to_string(),
-            chain: address_chain[1].to_string(),
+        // Tokens like MKR return their name and symbol as bytes32, they are stored without them.
+        let name = name.ok();
+        let symbol = symbol.ok();
+
+        let is_empty =
+            |value: &Option<String>| value.as_ref().map_or(true, |value| value.trim().is_empty());
+
+        if is_empty(&name) && is_empty(&symbol) && failures.is_empty() {
+            return (token_id, Err(TokenExclusion::Empty));
+        }
+
+        let token = DatabaseEVMErc20Token {
+            address,
+            chain,
             name,
-            decimals,
+            decimals: Some(decimals),
             symbol,
-        });
+            spam_score: None,
+            spam_reasons: None,
+            logo: None,
+        };
+
+        (token_id, Ok(token))
+    }
+}
+
+/// Splits a token id into its address and chain, chain names can contain dashes.
+fn split_token_id(token_id: &str) -> (String, String) {
+    match token_id.split_once("-") {
+        Some((address, chain)) => (address.to_string(), chain.to_string()),
+        None => (token_id.to_string(), String::new()),
+    }
+}
+
+fn get_call_exclusion(err: &ContractError<Provider<Http>>) -> TokenExclusion {
+    match err {
+        ContractError::DecodingError(_)
+        | ContractError::DetokenizationError(_)
+        | ContractError::AbiError(_) => TokenExclusion::Revert,
+        err if err.to_string().to_lowercase().contains("revert") => TokenExclusion::Revert,
+        _ => TokenExclusion::RpcError,
     }
 }
+
+fn get_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_secs() as i64
+}
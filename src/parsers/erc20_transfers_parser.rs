@@ -1,14 +1,26 @@
-use crate::db::{
-    db::{get_chunks, EVMDatabase},
-    models::models::DatabaseEVMTransactionLog,
-    schema::{evm_erc20_transfers, evm_transactions_logs},
+use crate::{
+    api::events::{notify_events, publish_events, IndexedEvent},
+    db::{
+        db::{get_chunks, EVMDatabase},
+        models::models::{DatabaseEVMParseFailure, DatabaseEVMTransactionLog},
+        schema::{evm_erc20_supply_changes, evm_erc20_transfers, evm_transactions_logs},
+    },
 };
 use anyhow::Result;
-use diesel::{prelude::*, result::Error};
-use ethabi::{ethereum_types::H256, ParamType};
-use ethers::types::Bytes;
+use diesel::{
+    prelude::*,
+    result::Error,
+    sql_query,
+    sql_types::{Array, Nullable, Text},
+};
+use ethabi::{ethereum_types::H256, Event, EventParam, ParamType, RawLog};
+use ethers::types::U256;
 use field_count::FieldCount;
-use log::info;
+use log::{info, warn};
+use std::collections::{BTreeMap, HashSet};
+use tracing::instrument;
+
+use super::dedup::{get_duplicate_keys, store_duplicate_logs, DuplicateFilter, ERC20_PROTOCOL};
 
 #[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
 #[diesel(table_name = evm_erc20_transfers)]
@@ -20,13 +32,53 @@ pub struct DatabaseEVMErc20Transfer {
     pub to_address: String,
     pub value: String,
     pub erc20_tokens_parced: Option<bool>,
+    pub block_number: Option<i64>,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_erc20_supply_changes)]
+pub struct DatabaseEVMErc20SupplyChange {
+    pub hash: String,
+    pub log_index: i64,
+    pub token: String,
+    pub kind: String,
+    pub amount: String,
+}
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+pub const PARSER_NAME: &str = "erc20_transfers";
+
+impl DatabaseEVMErc20SupplyChange {
+    /// Transfers from the zero address are mints and transfers to it are burns.
+    pub fn from_transfer(transfer: &DatabaseEVMErc20Transfer) -> Option<Self> {
+        let kind = if transfer.from_address == ZERO_ADDRESS {
+            "mint"
+        } else if transfer.to_address == ZERO_ADDRESS {
+            "burn"
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            hash: transfer.hash.clone(),
+            log_index: transfer.log_index,
+            token: transfer.token.clone(),
+            kind: kind.to_string(),
+            amount: transfer.value.clone(),
+        })
+    }
 }
 
-pub struct ERC20TransfersParser {}
+pub struct ERC20TransfersParser {
+    pub publish_events: bool,
+    pub notify: bool,
+    pub dedup: DuplicateFilter,
+}
 
 impl ERC20TransfersParser {
     pub fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
-        let mut connection = db.establish_connection();
+        let mut connection = db.establish_read_connection();
 
         let logs: Result<Vec<DatabaseEVMTransactionLog>, Error> = evm_transactions_logs::table
             .select(evm_transactions_logs::all_columns)
@@ -39,17 +91,40 @@ impl ERC20TransfersParser {
             .load::<DatabaseEVMTransactionLog>(&mut connection);
 
         match logs {
-            Ok(logs) => Ok(logs),
+            Ok(mut logs) => {
+                db.decompress_logs(&mut logs)?;
+
+                Ok(logs)
+            }
             Err(_) => Ok(Vec::new()),
         }
     }
 
+    #[instrument(name = "erc20_transfers_parser", skip_all, fields(logs = logs.len()))]
     pub async fn parse(
         &self,
         db: &EVMDatabase,
         logs: &Vec<DatabaseEVMTransactionLog>,
     ) -> Result<()> {
-        let mut db_erc20_transfers = Vec::new();
+        let (mut db_erc20_transfers, db_parse_failures) = get_transfers(logs);
+
+        let transfer_keys: HashSet<(String, i64)> = db_erc20_transfers
+            .iter()
+            .map(|transfer| (transfer.hash.clone(), transfer.log_index))
+            .collect();
+
+        let duplicates = self.dedup.find_duplicates(PARSER_NAME, logs, |log| {
+            match transfer_keys.contains(&(log.hash.clone(), log.log_index)) {
+                true => Some(ERC20_PROTOCOL.to_string()),
+                false => None,
+            }
+        });
+
+        let duplicate_keys = get_duplicate_keys(&duplicates);
+
+        db_erc20_transfers.retain(|transfer| {
+            !duplicate_keys.contains(&(transfer.hash.clone(), transfer.log_index))
+        });
 
         let mut db_parsed_logs = Vec::new();
 
@@ -59,116 +134,19 @@ impl ERC20TransfersParser {
             parsed_log.erc20_transfers_parsed = Some(true);
 
             db_parsed_logs.push(parsed_log);
-
-            if log.topics.len() != 3 {
-                continue;
-            }
-
-            let event = ethabi::Event {
-                name: "Transfer".to_owned(),
-                inputs: vec![
-                    ethabi::EventParam {
-                        name: "from".to_owned(),
-                        kind: ParamType::Address,
-                        indexed: false,
-                    },
-                    ethabi::EventParam {
-                        name: "to".to_owned(),
-                        kind: ParamType::Address,
-                        indexed: false,
-                    },
-                    ethabi::EventParam {
-                        name: "amount".to_owned(),
-                        kind: ParamType::Uint(256),
-                        indexed: false,
-                    },
-                ],
-                anonymous: false,
-            };
-
-            let topic_1 = log.topics[0].clone().unwrap();
-
-            // Check the first topic against keccak256(Transfer(address,address,uint256))
-            if topic_1 != format!("{:?}", event.signature()) {
-                continue;
-            }
-
-            let topic_2 = log.topics[1].clone().unwrap();
-            let topic_3 = log.topics[2].clone().unwrap();
-
-            let topic_2_hash: H256 = array_bytes::hex_n_into::<String, H256, 32>(topic_2).unwrap();
-
-            let topic_3_hash: H256 = array_bytes::hex_n_into::<String, H256, 32>(topic_3).unwrap();
-
-            let data_bytes: Bytes =
-                array_bytes::hex_n_into::<String, Bytes, 32>(log.data.clone()).unwrap();
-
-            let from_address: String =
-                match ethabi::decode(&[ParamType::Address], topic_2_hash.as_bytes()) {
-                    Ok(address) => {
-                        if address.len() == 0 {
-                            continue;
-                        } else {
-                            format!("{:?}", address[0].clone().into_address().unwrap())
-                        }
-                    }
-                    Err(_) => continue,
-                };
-
-            let to_address = match ethabi::decode(&[ParamType::Address], topic_3_hash.as_bytes()) {
-                Ok(address) => {
-                    if address.len() == 0 {
-                        continue;
-                    } else {
-                        format!("{:?}", address[0].clone().into_address().unwrap())
-                    }
-                }
-                Err(_) => continue,
-            };
-
-            let value = match ethabi::decode(&[ParamType::Uint(256)], &data_bytes.0[..]) {
-                Ok(value) => {
-                    if value.len() == 0 {
-                        continue;
-                    } else {
-                        format!("{:?}", value[0].clone().into_uint().unwrap())
-                    }
-                }
-                Err(_) => continue,
-            };
-
-            let db_transfers = DatabaseEVMErc20Transfer {
-                hash: log.hash.clone(),
-                log_index: log.log_index,
-                token: log.address.clone(),
-                from_address,
-                to_address,
-                value,
-                erc20_tokens_parced: Some(false),
-            };
-
-            db_erc20_transfers.push(db_transfers)
         }
 
         let mut connection = db.establish_connection();
 
-        let chunks = get_chunks(
-            db_erc20_transfers.len(),
-            DatabaseEVMErc20Transfer::field_count(),
-        );
+        connection
+            .transaction::<_, Error, _>(|connection| {
+                store_transfers(connection, &db_erc20_transfers)
+            })
+            .expect("Unable to store erc20 transfers into database");
 
-        for (start, end) in chunks {
-            diesel::insert_into(evm_erc20_transfers::dsl::evm_erc20_transfers)
-                .values(&db_erc20_transfers[start..end])
-                .on_conflict_do_nothing()
-                .execute(&mut connection)
-                .expect("Unable to store erc20 transfers into database");
-        }
+        db.store_parse_failures(&db_parse_failures).await?;
 
-        info!(
-            "Inserted {} erc20 transfers to the database.",
-            db_erc20_transfers.len()
-        );
+        store_duplicate_logs(db, &duplicates)?;
 
         let log_chunks = get_chunks(
             db_parsed_logs.len(),
@@ -188,6 +166,309 @@ impl ERC20TransfersParser {
                 .expect("Unable to update parsed logs into database");
         }
 
+        // The transfers are only published once committed, so subscribers can read them back.
+        emit_transfer_events(db, &db_erc20_transfers, self.publish_events, self.notify);
+
         Ok(())
     }
 }
+
+/// Decodes the ERC-20 transfers of the logs, with the logs of a Transfer event that can't be
+/// decoded to quarantine.
+pub fn get_transfers(
+    logs: &Vec<DatabaseEVMTransactionLog>,
+) -> (Vec<DatabaseEVMErc20Transfer>, Vec<DatabaseEVMParseFailure>) {
+    let mut transfers = Vec::new();
+
+    let mut failures = Vec::new();
+
+    for log in logs {
+        match decode_transfer(log) {
+            Ok(Some(transfer)) => transfers.push(transfer),
+            Ok(None) => (),
+            Err(reason) => failures.push(DatabaseEVMParseFailure {
+                parser: PARSER_NAME.to_string(),
+                hash: log.hash.clone(),
+                log_index: log.log_index,
+                reason,
+            }),
+        }
+    }
+
+    (transfers, failures)
+}
+
+/// Stores the transfers and their supply changes, the indexer calls it within the transaction
+/// of the raw logs when it parses the transfers inline. The rows are written in the order of
+/// their keys, so the indexer and the parsers storing the same transfers don't deadlock.
+pub fn store_transfers(
+    connection: &mut PgConnection,
+    transfers: &Vec<DatabaseEVMErc20Transfer>,
+) -> Result<(), Error> {
+    let mut transfers = transfers.clone();
+
+    transfers.sort_by(|a, b| (&a.hash, a.log_index).cmp(&(&b.hash, b.log_index)));
+
+    let chunks = get_chunks(transfers.len(), DatabaseEVMErc20Transfer::field_count());
+
+    for (start, end) in chunks {
+        diesel::insert_into(evm_erc20_transfers::dsl::evm_erc20_transfers)
+            .values(&transfers[start..end])
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+
+    info!(
+        "Inserted {} erc20 transfers to the database.",
+        transfers.len()
+    );
+
+    let hashes: Vec<String> = transfers
+        .iter()
+        .map(|transfer| transfer.hash.clone())
+        .collect();
+
+    store_decimal_values(connection, Some(&hashes), None)?;
+
+    store_supply_changes(connection, &transfers)
+}
+
+/// Fills the `value_decimal` of the transfers of tokens with known decimals, optionally
+/// restricted to the transfers of the `hashes` or of the `tokens`. Transfers of tokens parsed
+/// later are filled by the erc20 tokens parser. The value is built with an exponent so no
+/// digits are lost to the rounding of a division.
+pub fn store_decimal_values(
+    connection: &mut PgConnection,
+    hashes: Option<&Vec<String>>,
+    tokens: Option<&Vec<String>>,
+) -> Result<usize, Error> {
+    sql_query(
+        "UPDATE evm_erc20_transfers tr \
+        SET value_decimal = (tr.value || 'e-' || k.decimals)::NUMERIC \
+        FROM evm_erc20_tokens k \
+        WHERE k.chain = COALESCE((SELECT t.chain FROM evm_transactions t WHERE t.hash = tr.hash), \
+        (SELECT b.chain FROM evm_log_transactions b WHERE b.hash = tr.hash)) \
+        AND k.address = tr.token \
+        AND k.decimals BETWEEN 0 AND 255 AND tr.value_decimal IS NULL \
+        AND ($1::TEXT[] IS NULL OR tr.hash = ANY($1)) \
+        AND ($2::TEXT[] IS NULL OR tr.token = ANY($2))",
+    )
+    .bind::<Nullable<Array<Text>>, _>(hashes)
+    .bind::<Nullable<Array<Text>>, _>(tokens)
+    .execute(connection)
+}
+
+pub fn emit_transfer_events(
+    db: &EVMDatabase,
+    transfers: &Vec<DatabaseEVMErc20Transfer>,
+    publish: bool,
+    notify: bool,
+) {
+    if !publish && !notify {
+        return;
+    }
+
+    let events = transfers
+        .iter()
+        .map(|transfer| IndexedEvent::from_erc20_transfer(transfer, db.chain.name))
+        .collect();
+
+    if publish {
+        match publish_events(db, &events) {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to publish erc20 transfers events: {}", err),
+        }
+    }
+
+    if notify {
+        match notify_events(db, &events) {
+            Ok(_) => (),
+            Err(err) => warn!("Unable to notify erc20 transfers events: {}", err),
+        }
+    }
+}
+
+fn store_supply_changes(
+    connection: &mut PgConnection,
+    transfers: &Vec<DatabaseEVMErc20Transfer>,
+) -> Result<(), Error> {
+    let mut supply_changes: Vec<DatabaseEVMErc20SupplyChange> = transfers
+        .iter()
+        .filter_map(|transfer| DatabaseEVMErc20SupplyChange::from_transfer(transfer))
+        .collect();
+
+    supply_changes.sort_by(|a, b| (&a.hash, a.log_index).cmp(&(&b.hash, b.log_index)));
+
+    let mut inserted: Vec<DatabaseEVMErc20SupplyChange> = Vec::new();
+
+    let chunks = get_chunks(
+        supply_changes.len(),
+        DatabaseEVMErc20SupplyChange::field_count(),
+    );
+
+    // Only the changes inserted for the first time count towards the cumulative supply, so
+    // parsing the same logs again doesn't alter it.
+    for (start, end) in chunks {
+        let mut chunk_inserted = diesel::insert_into(evm_erc20_supply_changes::table)
+            .values(&supply_changes[start..end])
+            .on_conflict_do_nothing()
+            .returning(evm_erc20_supply_changes::all_columns)
+            .get_results::<DatabaseEVMErc20SupplyChange>(connection)?;
+
+        inserted.append(&mut chunk_inserted);
+    }
+
+    // Sorted by token so concurrent parsers lock the supply rows in the same order.
+    let mut deltas: BTreeMap<String, (U256, U256)> = BTreeMap::new();
+
+    for change in inserted.iter() {
+        let amount = match U256::from_dec_str(&change.amount) {
+            Ok(amount) => amount,
+            Err(_) => continue,
+        };
+
+        let (minted, burned) = deltas
+            .entry(change.token.clone())
+            .or_insert((U256::zero(), U256::zero()));
+
+        if change.kind == "mint" {
+            *minted = minted.saturating_add(amount);
+        } else {
+            *burned = burned.saturating_add(amount);
+        }
+    }
+
+    if deltas.len() > 0 {
+        let tokens: Vec<String> = deltas.keys().cloned().collect();
+
+        let amounts: Vec<String> = deltas
+            .values()
+            .map(|(minted, burned)| match minted >= burned {
+                true => (*minted - *burned).to_string(),
+                false => format!("-{}", *burned - *minted),
+            })
+            .collect();
+
+        sql_query(
+            "INSERT INTO evm_erc20_supply (token, supply) \
+            SELECT d.token, d.delta::NUMERIC FROM UNNEST($1::text[], $2::text[]) AS d(token, delta) \
+            ORDER BY d.token \
+            ON CONFLICT (token) DO UPDATE SET supply = evm_erc20_supply.supply + EXCLUDED.supply",
+        )
+        .bind::<Array<Text>, _>(&tokens)
+        .bind::<Array<Text>, _>(&amounts)
+        .execute(connection)?;
+    }
+
+    info!(
+        "Inserted {} erc20 supply changes to the database.",
+        inserted.len()
+    );
+
+    Ok(())
+}
+
+/// Transfer event with the `from` and `to` params indexed or not. Most tokens index both, but
+/// some early tokens index only `from` or none of them, which emits the same topic0 with the
+/// remaining params in the data.
+fn get_transfer_event(indexed_params: usize) -> Event {
+    let params = [
+        ("from", ParamType::Address),
+        ("to", ParamType::Address),
+        ("value", ParamType::Uint(256)),
+    ];
+
+    Event {
+        name: "Transfer".to_owned(),
+        inputs: params
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, kind))| EventParam {
+                name: name.to_owned(),
+                kind,
+                indexed: index < indexed_params,
+            })
+            .collect(),
+        anonymous: false,
+    }
+}
+
+/// Decodes a log as an ERC-20 transfer. Logs of other events and ERC-721 transfers, which also
+/// index the token id, return `None`, while Transfer logs that can't be decoded, like tokens
+/// emitting a value shorter than a word, return the reason to quarantine them.
+fn decode_transfer(
+    log: &DatabaseEVMTransactionLog,
+) -> std::result::Result<Option<DatabaseEVMErc20Transfer>, String> {
+    let indexed_params = match log.topics.len() {
+        1..=3 => log.topics.len() - 1,
+        _ => return Ok(None),
+    };
+
+    let event = get_transfer_event(indexed_params);
+
+    // Check the first topic against keccak256(Transfer(address,address,uint256))
+    if log.topics[0] != Some(format!("{:?}", event.signature())) {
+        return Ok(None);
+    }
+
+    let mut topics = Vec::new();
+
+    for topic in log.topics.iter() {
+        match topic {
+            Some(topic) => match array_bytes::hex_n_into::<String, H256, 32>(topic.clone()) {
+                Ok(topic) => topics.push(topic),
+                Err(_) => return Err(format!("Invalid topic {}", topic)),
+            },
+            None => return Err("Missing topic".to_owned()),
+        }
+    }
+
+    let data = match array_bytes::hex2bytes(&log.data) {
+        Ok(data) => data,
+        Err(_) => return Err(format!("Invalid data {}", log.data)),
+    };
+
+    let raw_log = RawLog {
+        topics,
+        data: data.clone(),
+    };
+
+    let parsed_log = match event.parse_log(raw_log) {
+        Ok(parsed_log) => parsed_log,
+        Err(err) => {
+            return Err(format!(
+                "Unable to decode a transfer with {} indexed params and {} bytes of data: {}",
+                indexed_params,
+                data.len(),
+                err
+            ))
+        }
+    };
+
+    let mut from_address = None;
+    let mut to_address = None;
+    let mut value = None;
+
+    for param in parsed_log.params {
+        match param.name.as_str() {
+            "from" => from_address = param.value.into_address(),
+            "to" => to_address = param.value.into_address(),
+            "value" => value = param.value.into_uint(),
+            _ => (),
+        }
+    }
+
+    match (from_address, to_address, value) {
+        (Some(from_address), Some(to_address), Some(value)) => Ok(Some(DatabaseEVMErc20Transfer {
+            hash: log.hash.clone(),
+            log_index: log.log_index,
+            token: log.address.clone(),
+            from_address: format!("{:?}", from_address),
+            to_address: format!("{:?}", to_address),
+            value: format!("{:?}", value),
+            erc20_tokens_parced: Some(false),
+            block_number: log.block_number,
+        })),
+        _ => Err("Missing transfer params".to_owned()),
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use ethers::types::U256;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::{evm_call_frames, evm_dex_pools, evm_flash_loans},
+};
+
+use super::{
+    decoder::{get_parse_failures, DecodedLog, EventDecoder},
+    token_prices_parser::DatabaseEVMDexPool,
+};
+
+/// Selector of `uniswapV2Call(address,uint256,uint256,bytes)`, the callback a Uniswap V2 pair
+/// makes to the receiver of a flash swap before checking the repayment.
+pub const UNISWAP_V2_CALLBACK_SELECTOR: &str = "0x10d1e85c";
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_flash_loans)]
+pub struct DatabaseEVMFlashLoan {
+    pub hash: String,
+    pub log_index: i64,
+    pub token_index: i64,
+    pub chain: String,
+    pub protocol: String,
+    pub lender: String,
+    pub initiator: Option<String>,
+    pub receiver: Option<String>,
+    pub token: Option<String>,
+    pub amount: String,
+    pub fee: Option<String>,
+}
+
+/// Stores the flash loans into `evm_flash_loans`, one row per borrowed token. Aave and Balancer
+/// emit a `FlashLoan` event and Uniswap V3 pools a `Flash` event. Uniswap V2 flash swaps emit a
+/// regular `Swap`, they are told apart by the `uniswapV2Call` callback of the pair in the call
+/// frames of the transaction, so they are only found for the transactions with a stored call
+/// tree.
+pub struct FlashLoansParser {
+    pub aave: EventDecoder,
+    pub balancer: EventDecoder,
+    pub uniswap_v3: EventDecoder,
+    pub uniswap_v2: EventDecoder,
+}
+
+impl FlashLoansParser {
+    pub fn new() -> Self {
+        Self {
+            aave: EventDecoder::new(&[
+                "event FlashLoan(address indexed target, address indexed initiator, address indexed asset, uint256 amount, uint256 premium, uint16 referralCode)",
+                "event FlashLoan(address indexed target, address initiator, address indexed asset, uint256 amount, uint8 interestRateMode, uint256 premium, uint16 indexed referralCode)",
+            ]),
+            balancer: EventDecoder::new(&[
+                "event FlashLoan(address indexed recipient, address indexed token, uint256 amount, uint256 feeAmount)",
+            ]),
+            uniswap_v3: EventDecoder::new(&[
+                "event Flash(address indexed sender, address indexed recipient, uint256 amount0, uint256 amount1, uint256 paid0, uint256 paid1)",
+            ]),
+            uniswap_v2: EventDecoder::new(&[
+                "event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+            ]),
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut topics = self.aave.topics();
+
+        topics.append(&mut self.balancer.topics());
+        topics.append(&mut self.uniswap_v3.topics());
+        topics.append(&mut self.uniswap_v2.topics());
+
+        db.get_unparsed_logs("flash_loans", &topics, None, 10000)
+            .await
+    }
+
+    #[instrument(name = "flash_loans_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let callbacks = self.get_uniswap_v2_callbacks(&mut connection, logs)?;
+
+        let pools = self.get_pools_tokens(&mut connection, logs);
+
+        let mut db_flash_loans = Vec::new();
+
+        for log in logs {
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let tokens = pools.get(&(log.address.clone(), chain.clone()));
+
+            let flash_loans = if let Some(decoded) = self.aave.decode(log) {
+                get_aave_flash_loan(&decoded, log, chain)
+            } else if let Some(decoded) = self.balancer.decode(log) {
+                get_balancer_flash_loan(&decoded, log, chain)
+            } else if let Some(decoded) = self.uniswap_v3.decode(log) {
+                get_uniswap_v3_flash_loans(&decoded, log, chain, tokens)
+            } else if let Some(decoded) = self.uniswap_v2.decode(log) {
+                match callbacks.contains(&(log.hash.clone(), log.address.clone())) {
+                    true => get_uniswap_v2_flash_loans(&decoded, log, chain, tokens),
+                    false => None,
+                }
+            } else {
+                None
+            };
+
+            match flash_loans {
+                Some(mut flash_loans) => db_flash_loans.append(&mut flash_loans),
+                None => continue,
+            }
+        }
+
+        let chunks = get_chunks(db_flash_loans.len(), DatabaseEVMFlashLoan::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_flash_loans::dsl::evm_flash_loans)
+                .values(&db_flash_loans[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store flash loans into database");
+        }
+
+        info!(
+            "Inserted {} flash loans to the database.",
+            db_flash_loans.len()
+        );
+
+        let failures = get_parse_failures(
+            "flash_loans",
+            &[
+                &self.aave,
+                &self.balancer,
+                &self.uniswap_v3,
+                &self.uniswap_v2,
+            ],
+            logs,
+        );
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs("flash_loans", logs).await
+    }
+
+    /// Transactions and pairs with a `uniswapV2Call` callback in their call frames.
+    fn get_uniswap_v2_callbacks(
+        &self,
+        connection: &mut PgConnection,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<HashSet<(String, String)>> {
+        let hashes: Vec<String> = logs
+            .iter()
+            .filter(|log| self.uniswap_v2.decode(log).is_some())
+            .map(|log| log.hash.clone())
+            .collect();
+
+        if hashes.len() == 0 {
+            return Ok(HashSet::new());
+        }
+
+        let callbacks = evm_call_frames::table
+            .select((evm_call_frames::hash, evm_call_frames::from_address))
+            .filter(evm_call_frames::hash.eq_any(hashes))
+            .filter(evm_call_frames::selector.eq(UNISWAP_V2_CALLBACK_SELECTOR))
+            .load::<(String, String)>(connection)?;
+
+        Ok(callbacks.into_iter().collect())
+    }
+
+    /// Uniswap flash events only include the amounts of each pool token, the tokens come from
+    /// the pools already stored by the token prices parser.
+    fn get_pools_tokens(
+        &self,
+        connection: &mut PgConnection,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> HashMap<(String, String), Vec<Option<String>>> {
+        let addresses: Vec<String> = logs
+            .iter()
+            .map(|log| log.address.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        evm_dex_pools::table
+            .select(evm_dex_pools::all_columns)
+            .filter(evm_dex_pools::pool.eq_any(addresses))
+            .load::<DatabaseEVMDexPool>(connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pool| ((pool.pool, pool.chain), pool.tokens))
+            .collect()
+    }
+}
+
+fn get_aave_flash_loan(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<Vec<DatabaseEVMFlashLoan>> {
+    Some(vec![DatabaseEVMFlashLoan {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        token_index: 0,
+        chain,
+        protocol: "aave".to_string(),
+        lender: log.address.clone(),
+        initiator: decoded.address("initiator"),
+        receiver: decoded.address("target"),
+        token: decoded.address("asset"),
+        amount: decoded.uint("amount")?,
+        fee: decoded.uint("premium"),
+    }])
+}
+
+fn get_balancer_flash_loan(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<Vec<DatabaseEVMFlashLoan>> {
+    Some(vec![DatabaseEVMFlashLoan {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        token_index: 0,
+        chain,
+        protocol: "balancer".to_string(),
+        lender: log.address.clone(),
+        initiator: None,
+        receiver: decoded.address("recipient"),
+        token: decoded.address("token"),
+        amount: decoded.uint("amount")?,
+        fee: decoded.uint("feeAmount"),
+    }])
+}
+
+fn get_uniswap_v3_flash_loans(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+    tokens: Option<&Vec<Option<String>>>,
+) -> Option<Vec<DatabaseEVMFlashLoan>> {
+    let mut flash_loans = Vec::new();
+
+    for token_index in 0..2 {
+        let amount = decoded.uint(&format!("amount{}", token_index))?;
+
+        if amount == "0" {
+            continue;
+        }
+
+        flash_loans.push(DatabaseEVMFlashLoan {
+            hash: log.hash.clone(),
+            log_index: log.log_index,
+            token_index,
+            chain: chain.clone(),
+            protocol: "uniswap-v3".to_string(),
+            lender: log.address.clone(),
+            initiator: decoded.address("sender"),
+            receiver: decoded.address("recipient"),
+            token: get_pool_token(tokens, token_index),
+            amount,
+            fee: decoded.uint(&format!("paid{}", token_index)),
+        })
+    }
+
+    Some(flash_loans)
+}
+
+fn get_uniswap_v2_flash_loans(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+    tokens: Option<&Vec<Option<String>>>,
+) -> Option<Vec<DatabaseEVMFlashLoan>> {
+    let mut flash_loans = Vec::new();
+
+    for token_index in 0..2 {
+        let amount_out = decoded.uint(&format!("amount{}Out", token_index))?;
+        let amount_in = decoded.uint(&format!("amount{}In", token_index))?;
+
+        if amount_out == "0" {
+            continue;
+        }
+
+        // The fee is only known when the loan was repaid with the same token, a repayment with
+        // the other token is a regular swap paid after receiving the tokens.
+        let fee = match (
+            U256::from_dec_str(&amount_in),
+            U256::from_dec_str(&amount_out),
+        ) {
+            (Ok(amount_in), Ok(amount_out)) if amount_in > amount_out => {
+                Some((amount_in - amount_out).to_string())
+            }
+            _ => None,
+        };
+
+        flash_loans.push(DatabaseEVMFlashLoan {
+            hash: log.hash.clone(),
+            log_index: log.log_index,
+            token_index,
+            chain: chain.clone(),
+            protocol: "uniswap-v2".to_string(),
+            lender: log.address.clone(),
+            initiator: decoded.address("sender"),
+            receiver: decoded.address("to"),
+            token: get_pool_token(tokens, token_index),
+            amount: amount_out,
+            fee,
+        })
+    }
+
+    Some(flash_loans)
+}
+
+fn get_pool_token(tokens: Option<&Vec<Option<String>>>, token_index: i64) -> Option<String> {
+    tokens?.get(token_index as usize)?.clone()
+}
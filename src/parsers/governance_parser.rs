@@ -0,0 +1,404 @@
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Text},
+};
+use ethabi::Contract;
+use ethers::utils::id;
+use field_count::FieldCount;
+use log::info;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::{DatabaseEVMParseFailure, DatabaseEVMTransactionLog},
+    schema::{evm_abis, evm_governance_proposals, evm_governance_votes, evm_methods},
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder};
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_governance_proposals)]
+pub struct DatabaseEVMGovernanceProposal {
+    pub chain: String,
+    pub governor: String,
+    pub proposal_id: String,
+    pub hash: String,
+    pub proposer: String,
+    pub targets: Vec<Option<String>>,
+    pub call_values: Vec<Option<String>>,
+    pub signatures: Vec<Option<String>>,
+    pub calldatas: Vec<Option<String>>,
+    pub decoded_calls: Option<String>,
+    pub vote_start: String,
+    pub vote_end: String,
+    pub description: String,
+    pub status: String,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_governance_votes)]
+pub struct DatabaseEVMGovernanceVote {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub governor: String,
+    pub proposal_id: String,
+    pub voter: String,
+    pub support: i64,
+    pub weight: String,
+    pub reason: Option<String>,
+}
+
+#[derive(QueryableByName, Debug)]
+struct UnparsedCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Parses the proposals and votes of OpenZeppelin Governor and Compound Governor Bravo
+/// contracts, both share the same event signatures so any emitting contract is parsed.
+pub struct GovernanceParser {
+    pub governor: EventDecoder,
+}
+
+impl GovernanceParser {
+    pub fn new() -> Self {
+        Self {
+            governor: EventDecoder::new(&[
+                "event ProposalCreated(uint256 proposalId, address proposer, address[] targets, uint256[] values, string[] signatures, bytes[] calldatas, uint256 voteStart, uint256 voteEnd, string description)",
+                "event VoteCast(address indexed voter, uint256 proposalId, uint8 support, uint256 weight, string reason)",
+                "event VoteCastWithParams(address indexed voter, uint256 proposalId, uint8 support, uint256 weight, string reason, bytes params)",
+                "event ProposalQueued(uint256 proposalId, uint256 eta)",
+                "event ProposalExecuted(uint256 proposalId)",
+                "event ProposalCanceled(uint256 proposalId)",
+            ]),
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        db.get_unparsed_logs("governance", &self.governor.topics(), None, 10000)
+            .await
+    }
+
+    #[instrument(name = "governance_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let mut connection = db.establish_connection();
+
+        let mut db_proposals = Vec::new();
+
+        let mut db_votes = Vec::new();
+
+        let mut status_updates = Vec::new();
+
+        let mut parsed_logs = Vec::new();
+
+        for log in logs {
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let decoded = match self.governor.decode(log) {
+                Some(decoded) => decoded,
+                None => {
+                    parsed_logs.push(log.clone());
+                    continue;
+                }
+            };
+
+            match decoded.name.as_str() {
+                "ProposalCreated" => {
+                    match get_proposal(&mut connection, &decoded, log, chain) {
+                        Some(proposal) => db_proposals.push(proposal),
+                        None => (),
+                    }
+
+                    parsed_logs.push(log.clone());
+                }
+                "VoteCast" | "VoteCastWithParams" => {
+                    match get_vote(&decoded, log, chain) {
+                        Some(vote) => db_votes.push(vote),
+                        None => (),
+                    }
+
+                    parsed_logs.push(log.clone());
+                }
+                "ProposalQueued" | "ProposalExecuted" | "ProposalCanceled" => {
+                    match decoded.uint("proposalId") {
+                        Some(proposal_id) => {
+                            status_updates.push((log, chain, proposal_id, get_status(&decoded)))
+                        }
+                        None => parsed_logs.push(log.clone()),
+                    }
+                }
+                _ => parsed_logs.push(log.clone()),
+            }
+        }
+
+        let chunks = get_chunks(
+            db_proposals.len(),
+            DatabaseEVMGovernanceProposal::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_governance_proposals::dsl::evm_governance_proposals)
+                .values(&db_proposals[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store governance proposals into database");
+        }
+
+        let chunks = get_chunks(db_votes.len(), DatabaseEVMGovernanceVote::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_governance_votes::dsl::evm_governance_votes)
+                .values(&db_votes[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store governance votes into database");
+        }
+
+        let mut unknown_proposals = Vec::new();
+
+        // Status changes of proposals not stored yet are left unparsed while the governor has
+        // creation logs left to parse, so they are retried once the proposal is stored. The ones
+        // of proposals created before the indexed blocks are recorded as failures and marked as
+        // parsed, so they aren't fetched again on every batch.
+        for (log, chain, proposal_id, status) in status_updates {
+            let proposal = evm_governance_proposals::table
+                .filter(evm_governance_proposals::chain.eq(&chain))
+                .filter(evm_governance_proposals::governor.eq(&log.address))
+                .filter(evm_governance_proposals::proposal_id.eq(&proposal_id));
+
+            let exists = proposal
+                .clone()
+                .count()
+                .get_result::<i64>(&mut connection)
+                .unwrap_or(0)
+                > 0;
+
+            if !exists {
+                if !self.has_unparsed_proposals(&mut connection, &log.address) {
+                    unknown_proposals.push(DatabaseEVMParseFailure {
+                        parser: "governance".to_string(),
+                        hash: log.hash.clone(),
+                        log_index: log.log_index,
+                        reason: format!(
+                            "Proposal {} of governor {} is not indexed",
+                            proposal_id, log.address
+                        ),
+                    });
+
+                    parsed_logs.push(log.clone());
+                }
+
+                continue;
+            }
+
+            // A queued log processed after the execution or cancellation doesn't revert them.
+            if status == "queued" {
+                diesel::update(proposal.filter(evm_governance_proposals::status.eq("created")))
+                    .set(evm_governance_proposals::status.eq(status))
+                    .execute(&mut connection)
+                    .expect("Unable to update governance proposal status");
+            } else {
+                diesel::update(proposal)
+                    .set(evm_governance_proposals::status.eq(status))
+                    .execute(&mut connection)
+                    .expect("Unable to update governance proposal status");
+            }
+
+            parsed_logs.push(log.clone());
+        }
+
+        info!(
+            "Inserted {} governance proposals and {} votes to the database.",
+            db_proposals.len(),
+            db_votes.len()
+        );
+
+        let mut failures = get_parse_failures("governance", &[&self.governor], &parsed_logs);
+
+        failures.append(&mut unknown_proposals);
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs("governance", &parsed_logs).await
+    }
+
+    /// Whether the governor has proposal creation logs not parsed yet.
+    fn has_unparsed_proposals(&self, connection: &mut PgConnection, governor: &String) -> bool {
+        let topics: Vec<String> = self
+            .governor
+            .events
+            .iter()
+            .filter(|(_, event)| event.name == "ProposalCreated")
+            .map(|(topic, _)| topic.clone())
+            .collect();
+
+        let unparsed = sql_query(
+            "SELECT COUNT(*) AS count FROM evm_transactions_logs l \
+            WHERE l.address = $1 AND l.topics[1] = ANY($2) \
+            AND NOT EXISTS (SELECT 1 FROM evm_parsed_logs p \
+            WHERE p.parser = 'governance' AND p.hash = l.hash AND p.log_index = l.log_index)",
+        )
+        .bind::<Text, _>(governor)
+        .bind::<Array<Text>, _>(topics)
+        .get_result::<UnparsedCount>(connection);
+
+        match unparsed {
+            Ok(unparsed) => unparsed.count > 0,
+            Err(_) => true,
+        }
+    }
+}
+
+fn get_status(decoded: &DecodedLog) -> &'static str {
+    match decoded.name.as_str() {
+        "ProposalQueued" => "queued",
+        "ProposalExecuted" => "executed",
+        _ => "canceled",
+    }
+}
+
+fn get_proposal(
+    connection: &mut PgConnection,
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMGovernanceProposal> {
+    let targets = decoded.addresses("targets")?;
+    let values = decoded.uints("values")?;
+    let signatures = decoded.strings("signatures")?;
+    let calldatas = decoded.bytes_array("calldatas")?;
+
+    let decoded_calls =
+        decode_proposal_calls(connection, &chain, &targets, &signatures, &calldatas);
+
+    Some(DatabaseEVMGovernanceProposal {
+        chain,
+        governor: log.address.clone(),
+        proposal_id: decoded.uint("proposalId")?,
+        hash: log.hash.clone(),
+        proposer: decoded.address("proposer")?,
+        targets: targets.into_iter().map(Some).collect(),
+        call_values: values.into_iter().map(Some).collect(),
+        signatures: signatures.into_iter().map(Some).collect(),
+        calldatas: calldatas.into_iter().map(Some).collect(),
+        decoded_calls,
+        vote_start: decoded.uint("voteStart")?,
+        vote_end: decoded.uint("voteEnd")?,
+        description: decoded.string("description")?,
+        status: "created".to_string(),
+    })
+}
+
+fn get_vote(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMGovernanceVote> {
+    let reason = decoded.string("reason").filter(|reason| reason.len() > 0);
+
+    Some(DatabaseEVMGovernanceVote {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        governor: log.address.clone(),
+        proposal_id: decoded.uint("proposalId")?,
+        voter: decoded.address("voter")?,
+        support: decoded.uint("support")?.parse().ok()?,
+        weight: decoded.uint("weight")?,
+        reason,
+    })
+}
+
+/// Decodes the calls of a proposal with the ABIs of the targets from the ABI registry. Governor
+/// Bravo proposals can pass the function signature apart and only the arguments as calldata.
+/// Calls without a known ABI fall back to the method name of the selector.
+fn decode_proposal_calls(
+    connection: &mut PgConnection,
+    chain: &String,
+    targets: &Vec<String>,
+    signatures: &Vec<String>,
+    calldatas: &Vec<String>,
+) -> Option<String> {
+    let mut calls: Vec<Value> = Vec::new();
+
+    for (index, target) in targets.iter().enumerate() {
+        let signature = signatures.get(index).cloned().unwrap_or_default();
+
+        let calldata = match calldatas.get(index) {
+            Some(calldata) => hex::decode(calldata.trim_start_matches("0x")).ok()?,
+            None => Vec::new(),
+        };
+
+        let (selector, arguments) = if signature.len() > 0 {
+            (id(&signature).to_vec(), calldata)
+        } else if calldata.len() >= 4 {
+            (calldata[..4].to_vec(), calldata[4..].to_vec())
+        } else {
+            calls.push(json!({ "target": target }));
+            continue;
+        };
+
+        let abi: Option<String> = evm_abis::table
+            .select(evm_abis::abi)
+            .filter(evm_abis::chain.eq(chain))
+            .filter(evm_abis::contract.eq(target))
+            .first::<Option<String>>(connection)
+            .ok()
+            .flatten();
+
+        let function = abi
+            .and_then(|abi| serde_json::from_str::<Contract>(&abi).ok())
+            .and_then(|contract| {
+                contract
+                    .functions()
+                    .find(|function| function.short_signature().to_vec() == selector)
+                    .cloned()
+            });
+
+        match function {
+            Some(function) => {
+                let params: Vec<String> = match function.decode_input(&arguments) {
+                    Ok(tokens) => tokens.iter().map(|token| token.to_string()).collect(),
+                    Err(_) => Vec::new(),
+                };
+
+                calls.push(json!({
+                    "target": target,
+                    "function": function.name,
+                    "params": params,
+                }));
+            }
+            None => {
+                let method = format!("0x{}", hex::encode(&selector));
+
+                let name: Option<String> = evm_methods::table
+                    .select(evm_methods::name)
+                    .filter(evm_methods::method.eq(&method))
+                    .first::<String>(connection)
+                    .ok();
+
+                calls.push(json!({
+                    "target": target,
+                    "function": name.unwrap_or(method),
+                }));
+            }
+        }
+    }
+
+    serde_json::to_string(&calls).ok()
+}
@@ -0,0 +1,449 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::{evm_lending_events, evm_liquidations},
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder};
+use super::dedup::{get_duplicate_keys, store_duplicate_logs, DuplicateFilter};
+use super::deployments::ProtocolDeployment;
+
+pub const AAVE_V3: &str = "aave-v3";
+
+pub const COMPOUND_V2: &str = "compound-v2";
+
+/// Lending protocols read from the deployments registry.
+pub const LENDING_PROTOCOLS: [&str; 2] = [AAVE_V3, COMPOUND_V2];
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_lending_events)]
+pub struct DatabaseEVMLendingEvent {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub protocol: String,
+    pub market: String,
+    pub action: String,
+    pub user_address: String,
+    pub amount: String,
+    pub liquidator: Option<String>,
+    pub collateral: Option<String>,
+}
+
+/// Liquidations of every lending protocol in the same shape. Compound v2 liquidations use the
+/// markets as collateral and debt tokens, with the seized collateral in cTokens. Liquidations
+/// parsed before the table existed are copied from the lending events without collateral amount.
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_liquidations)]
+pub struct DatabaseEVMLiquidation {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub block_number: i64,
+    pub protocol: String,
+    pub liquidator: Option<String>,
+    pub borrower: String,
+    pub collateral_token: Option<String>,
+    pub collateral_amount: Option<String>,
+    pub debt_token: String,
+    pub debt_amount: String,
+}
+
+pub type LendingDeployment = ProtocolDeployment;
+
+/// Aave v3 pools and Compound v2 markets, the events are emitted by these contracts.
+pub fn get_default_lending_deployments() -> Vec<LendingDeployment> {
+    let deployments = [
+        (
+            "ethereum",
+            AAVE_V3,
+            "0x87870bca3f3fd6335c3f4ce8392d69350b4fa4e2",
+        ),
+        (
+            "polygon",
+            AAVE_V3,
+            "0x794a61358d6845594f94dc1db02a252b5b4814ad",
+        ),
+        (
+            "arbitrum",
+            AAVE_V3,
+            "0x794a61358d6845594f94dc1db02a252b5b4814ad",
+        ),
+        (
+            "optimism",
+            AAVE_V3,
+            "0x794a61358d6845594f94dc1db02a252b5b4814ad",
+        ),
+        (
+            "avalanche",
+            AAVE_V3,
+            "0x794a61358d6845594f94dc1db02a252b5b4814ad",
+        ),
+        (
+            "fantom",
+            AAVE_V3,
+            "0x794a61358d6845594f94dc1db02a252b5b4814ad",
+        ),
+        (
+            "ethereum",
+            COMPOUND_V2,
+            "0x4ddc2d193948926d02f9b1fe9e1daa0718270ed5",
+        ),
+        (
+            "ethereum",
+            COMPOUND_V2,
+            "0x39aa39c021dfbae8fac545936693ac917d5e7563",
+        ),
+        (
+            "ethereum",
+            COMPOUND_V2,
+            "0x5d3a536e4d6dbd6114cc1ead35777bab948e3643",
+        ),
+        (
+            "ethereum",
+            COMPOUND_V2,
+            "0xf650c3d88d12db855b8bf7d11be6c55a4e07dcc9",
+        ),
+        (
+            "ethereum",
+            COMPOUND_V2,
+            "0xccf4429db6322d5c611ee964527d42e5d685dd6a",
+        ),
+    ];
+
+    deployments
+        .into_iter()
+        .map(|(chain, protocol, address)| LendingDeployment {
+            chain: chain.to_string(),
+            protocol: protocol.to_string(),
+            address: address.to_string(),
+            contract: None,
+        })
+        .collect()
+}
+
+pub fn load_lending_deployments(path: &Option<String>) -> Vec<LendingDeployment> {
+    match path {
+        Some(path) => {
+            let file = std::fs::read_to_string(path).expect("Unable to read lending deployments");
+
+            serde_json::from_str(&file).expect("Unable to parse lending deployments")
+        }
+        None => get_default_lending_deployments(),
+    }
+}
+
+pub struct LendingParser {
+    pub deployments: HashMap<(String, String), LendingDeployment>,
+    pub dedup: DuplicateFilter,
+    pub aave_v3: EventDecoder,
+    pub compound_v2: EventDecoder,
+}
+
+impl LendingParser {
+    pub fn new(deployments: Vec<LendingDeployment>, dedup: DuplicateFilter) -> Self {
+        let aave_v3 = EventDecoder::new(&[
+            "event Supply(address indexed reserve, address user, address indexed onBehalfOf, uint256 amount, uint16 indexed referralCode)",
+            "event Withdraw(address indexed reserve, address indexed user, address indexed to, uint256 amount)",
+            "event Borrow(address indexed reserve, address user, address indexed onBehalfOf, uint256 amount, uint8 interestRateMode, uint256 borrowRate, uint16 indexed referralCode)",
+            "event Repay(address indexed reserve, address indexed user, address indexed repayer, uint256 amount, bool useATokens)",
+            "event LiquidationCall(address indexed collateralAsset, address indexed debtAsset, address indexed user, uint256 debtToCover, uint256 liquidatedCollateralAmount, address liquidator, bool receiveAToken)",
+        ]);
+
+        let compound_v2 = EventDecoder::new(&[
+            "event Mint(address minter, uint256 mintAmount, uint256 mintTokens)",
+            "event Redeem(address redeemer, uint256 redeemAmount, uint256 redeemTokens)",
+            "event Borrow(address borrower, uint256 borrowAmount, uint256 accountBorrows, uint256 totalBorrows)",
+            "event RepayBorrow(address payer, address borrower, uint256 repayAmount, uint256 accountBorrows, uint256 totalBorrows)",
+            "event LiquidateBorrow(address liquidator, address borrower, uint256 repayAmount, address cTokenCollateral, uint256 seizeTokens)",
+        ]);
+
+        Self {
+            deployments: deployments
+                .into_iter()
+                .map(|deployment| {
+                    (
+                        (deployment.chain.clone(), deployment.address.to_lowercase()),
+                        deployment,
+                    )
+                })
+                .collect(),
+            dedup,
+            aave_v3,
+            compound_v2,
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut topics = self.aave_v3.topics();
+
+        topics.append(&mut self.compound_v2.topics());
+
+        let addresses: Vec<String> = self
+            .deployments
+            .keys()
+            .map(|(_, address)| address.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        db.get_unparsed_logs("lending", &topics, Some(&addresses), 10000)
+            .await
+    }
+
+    #[instrument(name = "lending_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let mut db_lending_events = Vec::new();
+
+        let mut db_liquidations = Vec::new();
+
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let duplicates = self.dedup.find_duplicates("lending", logs, |log| {
+            let chain = chains.get(&log.hash)?;
+
+            self.deployments
+                .get(&(chain.clone(), log.address.clone()))
+                .map(|deployment| deployment.protocol.clone())
+        });
+
+        let duplicate_keys = get_duplicate_keys(&duplicates);
+
+        let blocks = db.get_transactions_blocks(&hashes).await?;
+
+        for log in logs {
+            if duplicate_keys.contains(&(log.hash.clone(), log.log_index)) {
+                continue;
+            }
+
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let deployment = match self.deployments.get(&(chain, log.address.clone())) {
+                Some(deployment) => deployment,
+                None => continue,
+            };
+
+            let decoded = match deployment.protocol.as_str() {
+                AAVE_V3 => self.aave_v3.decode(log),
+                COMPOUND_V2 => self.compound_v2.decode(log),
+                _ => None,
+            };
+
+            let decoded = match decoded {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+
+            let event = match deployment.protocol.as_str() {
+                AAVE_V3 => get_aave_v3_event(&decoded),
+                _ => get_compound_v2_event(&decoded, &log.address),
+            };
+
+            let (market, action, user_address, amount, liquidator, collateral) = match event {
+                Some(event) => event,
+                None => continue,
+            };
+
+            if action == "liquidation" {
+                match blocks.get(&log.hash) {
+                    Some(block_number) => db_liquidations.push(DatabaseEVMLiquidation {
+                        hash: log.hash.clone(),
+                        log_index: log.log_index,
+                        chain: deployment.chain.clone(),
+                        block_number: *block_number,
+                        protocol: deployment.protocol.clone(),
+                        liquidator: liquidator.clone(),
+                        borrower: user_address.clone(),
+                        collateral_token: collateral.clone(),
+                        collateral_amount: get_collateral_amount(&decoded),
+                        debt_token: market.clone(),
+                        debt_amount: amount.clone(),
+                    }),
+                    None => (),
+                }
+            }
+
+            db_lending_events.push(DatabaseEVMLendingEvent {
+                hash: log.hash.clone(),
+                log_index: log.log_index,
+                chain: deployment.chain.clone(),
+                protocol: deployment.protocol.clone(),
+                market,
+                action: action.to_string(),
+                user_address,
+                amount,
+                liquidator,
+                collateral,
+            })
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(
+            db_lending_events.len(),
+            DatabaseEVMLendingEvent::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_lending_events::dsl::evm_lending_events)
+                .values(&db_lending_events[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store lending events into database");
+        }
+
+        info!(
+            "Inserted {} lending events to the database.",
+            db_lending_events.len()
+        );
+
+        let chunks = get_chunks(db_liquidations.len(), DatabaseEVMLiquidation::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_liquidations::dsl::evm_liquidations)
+                .values(&db_liquidations[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store liquidations into database");
+        }
+
+        info!(
+            "Inserted {} liquidations to the database.",
+            db_liquidations.len()
+        );
+
+        let failures = get_parse_failures("lending", &[&self.aave_v3, &self.compound_v2], logs);
+
+        db.store_parse_failures(&failures).await?;
+
+        store_duplicate_logs(db, &duplicates)?;
+
+        db.store_parsed_logs("lending", logs).await
+    }
+}
+
+type LendingEvent = (
+    String,
+    &'static str,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+);
+
+fn get_aave_v3_event(decoded: &DecodedLog) -> Option<LendingEvent> {
+    match decoded.name.as_str() {
+        "Supply" => Some((
+            decoded.address("reserve")?,
+            "supply",
+            decoded.address("onBehalfOf")?,
+            decoded.uint("amount")?,
+            None,
+            None,
+        )),
+        "Withdraw" => Some((
+            decoded.address("reserve")?,
+            "withdraw",
+            decoded.address("user")?,
+            decoded.uint("amount")?,
+            None,
+            None,
+        )),
+        "Borrow" => Some((
+            decoded.address("reserve")?,
+            "borrow",
+            decoded.address("onBehalfOf")?,
+            decoded.uint("amount")?,
+            None,
+            None,
+        )),
+        "Repay" => Some((
+            decoded.address("reserve")?,
+            "repay",
+            decoded.address("user")?,
+            decoded.uint("amount")?,
+            None,
+            None,
+        )),
+        "LiquidationCall" => Some((
+            decoded.address("debtAsset")?,
+            "liquidation",
+            decoded.address("user")?,
+            decoded.uint("debtToCover")?,
+            decoded.address("liquidator"),
+            decoded.address("collateralAsset"),
+        )),
+        _ => None,
+    }
+}
+
+fn get_compound_v2_event(decoded: &DecodedLog, market: &String) -> Option<LendingEvent> {
+    match decoded.name.as_str() {
+        "Mint" => Some((
+            market.clone(),
+            "supply",
+            decoded.address("minter")?,
+            decoded.uint("mintAmount")?,
+            None,
+            None,
+        )),
+        "Redeem" => Some((
+            market.clone(),
+            "withdraw",
+            decoded.address("redeemer")?,
+            decoded.uint("redeemAmount")?,
+            None,
+            None,
+        )),
+        "Borrow" => Some((
+            market.clone(),
+            "borrow",
+            decoded.address("borrower")?,
+            decoded.uint("borrowAmount")?,
+            None,
+            None,
+        )),
+        "RepayBorrow" => Some((
+            market.clone(),
+            "repay",
+            decoded.address("borrower")?,
+            decoded.uint("repayAmount")?,
+            None,
+            None,
+        )),
+        "LiquidateBorrow" => Some((
+            market.clone(),
+            "liquidation",
+            decoded.address("borrower")?,
+            decoded.uint("repayAmount")?,
+            decoded.address("liquidator"),
+            decoded.address("cTokenCollateral"),
+        )),
+        _ => None,
+    }
+}
+
+fn get_collateral_amount(decoded: &DecodedLog) -> Option<String> {
+    match decoded.name.as_str() {
+        "LiquidationCall" => decoded.uint("liquidatedCollateralAmount"),
+        "LiquidateBorrow" => decoded.uint("seizeTokens"),
+        _ => None,
+    }
+}
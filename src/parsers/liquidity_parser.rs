@@ -0,0 +1,371 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, Text},
+};
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::evm_liquidity_events,
+};
+
+use super::{
+    decoder::{get_parse_failures, DecodedLog, EventDecoder},
+    deployments::{
+        get_protocol_deployments, store_protocol_deployments, DeploymentsParams, ProtocolDeployment,
+    },
+};
+
+pub const UNISWAP_V3: &str = "uniswap-v3";
+
+/// Liquidity protocols read from the deployments registry.
+pub const LIQUIDITY_PROTOCOLS: [&str; 1] = [UNISWAP_V3];
+
+const FACTORY: &str = "factory";
+
+const POSITIONS: &str = "positions";
+
+const POOL: &str = "pool";
+
+/// Progress of the pools registered from the `PoolCreated` events of the factories.
+const POOLS_PARSER: &str = "uniswap_v3_pools";
+
+/// Uniswap v3 factories and position managers, the pools are registered from the factories.
+pub fn get_default_liquidity_deployments() -> Vec<ProtocolDeployment> {
+    let mut deployments = Vec::new();
+
+    for chain in ["ethereum", "polygon", "arbitrum", "optimism"] {
+        deployments.push(ProtocolDeployment {
+            chain: chain.to_string(),
+            protocol: UNISWAP_V3.to_string(),
+            address: "0x1f98431c8ad98523631ae4a59f267346ea31f984".to_string(),
+            contract: Some(FACTORY.to_string()),
+        });
+
+        deployments.push(ProtocolDeployment {
+            chain: chain.to_string(),
+            protocol: UNISWAP_V3.to_string(),
+            address: "0xc36442b4a4522e871399cd717abdd847ab11fe88".to_string(),
+            contract: Some(POSITIONS.to_string()),
+        });
+    }
+
+    deployments
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_liquidity_events)]
+pub struct DatabaseEVMLiquidityEvent {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub dex: String,
+    pub contract: String,
+    pub pool: Option<String>,
+    pub event: String,
+    pub position_id: Option<String>,
+    pub owner: Option<String>,
+    pub recipient: Option<String>,
+    pub tick_lower: Option<i64>,
+    pub tick_upper: Option<i64>,
+    pub liquidity: Option<String>,
+    pub amount0: String,
+    pub amount1: String,
+}
+
+/// Stores the Uniswap V3 liquidity changes into `evm_liquidity_events`, the `Mint`, `Burn` and
+/// `Collect` events of the pools and the `IncreaseLiquidity`, `DecreaseLiquidity` and `Collect`
+/// events of the position manager NFTs. Position manager events don't include the pool, it is
+/// taken from the pool event emitted right before them in the same transaction, whose owner is
+/// the position manager. Only the events of the registered pools and position managers are
+/// parsed, any contract can emit the same events.
+pub struct LiquidityParser {
+    pub factories: Vec<String>,
+    pub pool_created: EventDecoder,
+    pub pools: EventDecoder,
+    pub positions: EventDecoder,
+}
+
+impl LiquidityParser {
+    pub fn new(deployments: Vec<ProtocolDeployment>) -> Self {
+        let factories: HashSet<String> = deployments
+            .iter()
+            .filter(|deployment| deployment.contract.as_deref() == Some(FACTORY))
+            .map(|deployment| deployment.address.to_lowercase())
+            .collect();
+
+        Self {
+            factories: factories.into_iter().collect(),
+            pool_created: EventDecoder::new(&[
+                "event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)",
+            ]),
+            pools: EventDecoder::new(&[
+                "event Mint(address sender, address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1)",
+                "event Burn(address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1)",
+                "event Collect(address indexed owner, address recipient, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount0, uint128 amount1)",
+            ]),
+            positions: EventDecoder::new(&[
+                "event IncreaseLiquidity(uint256 indexed tokenId, uint128 liquidity, uint256 amount0, uint256 amount1)",
+                "event DecreaseLiquidity(uint256 indexed tokenId, uint128 liquidity, uint256 amount0, uint256 amount1)",
+                "event Collect(uint256 indexed tokenId, address recipient, uint256 amount0, uint256 amount1)",
+            ]),
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        self.register_pools(db).await?;
+
+        let addresses: Vec<String> = get_protocol_deployments(
+            db,
+            &DeploymentsParams {
+                chain: None,
+                protocol: Some(UNISWAP_V3.to_string()),
+            },
+        )?
+        .into_iter()
+        .filter(|deployment| deployment.contract.as_deref() != Some(FACTORY))
+        .map(|deployment| deployment.address)
+        .collect();
+
+        let mut topics = self.pools.topics();
+
+        topics.append(&mut self.positions.topics());
+
+        db.get_unparsed_logs("liquidity", &topics, Some(&addresses), 10000)
+            .await
+    }
+
+    /// Registers the pools created by the registered factories since the last run.
+    async fn register_pools(&self, db: &EVMDatabase) -> Result<()> {
+        let logs = db
+            .get_unparsed_logs(
+                POOLS_PARSER,
+                &self.pool_created.topics(),
+                Some(&self.factories),
+                10000,
+            )
+            .await?;
+
+        if logs.len() == 0 {
+            return Ok(());
+        }
+
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let mut pools = Vec::new();
+
+        for log in logs.iter() {
+            let (chain, decoded) = match (chains.get(&log.hash), self.pool_created.decode(log)) {
+                (Some(chain), Some(decoded)) => (chain, decoded),
+                _ => continue,
+            };
+
+            match decoded.address("pool") {
+                Some(pool) => pools.push(ProtocolDeployment {
+                    chain: chain.clone(),
+                    protocol: UNISWAP_V3.to_string(),
+                    address: pool,
+                    contract: Some(POOL.to_string()),
+                }),
+                None => continue,
+            }
+        }
+
+        store_protocol_deployments(db, &pools)?;
+
+        info!("Registered {} uniswap v3 pools.", pools.len());
+
+        db.store_parsed_logs(POOLS_PARSER, &logs).await
+    }
+
+    #[instrument(name = "liquidity_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let pool_logs = self.get_pool_logs(db, logs)?;
+
+        let mut db_liquidity_events = Vec::new();
+
+        for log in logs {
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let event = if let Some(decoded) = self.pools.decode(log) {
+                get_pool_event(&decoded, log, chain)
+            } else if let Some(decoded) = self.positions.decode(log) {
+                let pool = self.get_position_pool(log, &pool_logs);
+
+                get_position_event(&decoded, log, chain, pool)
+            } else {
+                None
+            };
+
+            match event {
+                Some(event) => db_liquidity_events.push(event),
+                None => continue,
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(
+            db_liquidity_events.len(),
+            DatabaseEVMLiquidityEvent::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_liquidity_events::dsl::evm_liquidity_events)
+                .values(&db_liquidity_events[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store liquidity events into database");
+        }
+
+        info!(
+            "Inserted {} liquidity events to the database.",
+            db_liquidity_events.len()
+        );
+
+        let failures = get_parse_failures("liquidity", &[&self.pools, &self.positions], logs);
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs("liquidity", logs).await
+    }
+
+    /// Pool events of the transactions with position manager events, which can be outside of the
+    /// fetched batch.
+    fn get_pool_logs(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<HashMap<String, Vec<DatabaseEVMTransactionLog>>> {
+        let hashes: Vec<String> = logs
+            .iter()
+            .filter(|log| self.positions.decode(log).is_some())
+            .map(|log| log.hash.clone())
+            .collect();
+
+        if hashes.len() == 0 {
+            return Ok(HashMap::new());
+        }
+
+        let mut connection = db.establish_read_connection();
+
+        let mut pool_logs = sql_query(
+            "SELECT * FROM evm_transactions_logs \
+            WHERE hash = ANY($1) AND topics[1] = ANY($2)",
+        )
+        .bind::<Array<Text>, _>(&hashes)
+        .bind::<Array<Text>, _>(self.pools.topics())
+        .load::<DatabaseEVMTransactionLog>(&mut connection)?;
+
+        db.decompress_logs(&mut pool_logs)?;
+
+        let mut transactions_logs: HashMap<String, Vec<DatabaseEVMTransactionLog>> = HashMap::new();
+
+        for log in pool_logs {
+            transactions_logs
+                .entry(log.hash.clone())
+                .or_default()
+                .push(log);
+        }
+
+        Ok(transactions_logs)
+    }
+
+    fn get_position_pool(
+        &self,
+        log: &DatabaseEVMTransactionLog,
+        pool_logs: &HashMap<String, Vec<DatabaseEVMTransactionLog>>,
+    ) -> Option<String> {
+        pool_logs
+            .get(&log.hash)?
+            .iter()
+            .filter(|pool_log| pool_log.log_index < log.log_index)
+            .filter(|pool_log| match self.pools.decode(pool_log) {
+                Some(decoded) => decoded.address("owner") == Some(log.address.clone()),
+                None => false,
+            })
+            .max_by_key(|pool_log| pool_log.log_index)
+            .map(|pool_log| pool_log.address.clone())
+    }
+}
+
+fn get_pool_event(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+) -> Option<DatabaseEVMLiquidityEvent> {
+    // Collect has no liquidity amount, only the collected fees and withdrawn tokens.
+    let liquidity = match decoded.name.as_str() {
+        "Collect" => None,
+        _ => Some(decoded.uint("amount")?),
+    };
+
+    Some(DatabaseEVMLiquidityEvent {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        dex: "uniswap-v3".to_string(),
+        contract: log.address.clone(),
+        pool: Some(log.address.clone()),
+        event: decoded.name.to_lowercase(),
+        position_id: None,
+        owner: decoded.address("owner"),
+        recipient: decoded.address("recipient"),
+        tick_lower: i64::try_from(decoded.int("tickLower")?).ok(),
+        tick_upper: i64::try_from(decoded.int("tickUpper")?).ok(),
+        liquidity,
+        amount0: decoded.uint("amount0")?,
+        amount1: decoded.uint("amount1")?,
+    })
+}
+
+fn get_position_event(
+    decoded: &DecodedLog,
+    log: &DatabaseEVMTransactionLog,
+    chain: String,
+    pool: Option<String>,
+) -> Option<DatabaseEVMLiquidityEvent> {
+    let event = match decoded.name.as_str() {
+        "IncreaseLiquidity" => "increase",
+        "DecreaseLiquidity" => "decrease",
+        _ => "collect",
+    };
+
+    Some(DatabaseEVMLiquidityEvent {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain,
+        dex: "uniswap-v3".to_string(),
+        contract: log.address.clone(),
+        pool,
+        event: event.to_string(),
+        position_id: decoded.uint("tokenId"),
+        owner: None,
+        recipient: decoded.address("recipient"),
+        tick_lower: None,
+        tick_upper: None,
+        liquidity: decoded.uint("liquidity"),
+        amount0: decoded.uint("amount0")?,
+        amount1: decoded.uint("amount1")?,
+    })
+}
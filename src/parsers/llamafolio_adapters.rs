@@ -11,6 +11,7 @@ use field_count::FieldCount;
 use log::info;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
 
 #[derive(Queryable, Insertable, Debug, Clone, FieldCount)]
 #[diesel(table_name = contracts_adapters)]
@@ -81,6 +82,7 @@ impl LlamafolioParser {
         Ok(adapters)
     }
 
+    #[instrument(name = "llamafolio_adapters_parser", skip_all, fields(adapters = adapters.len()))]
     pub async fn parse(
         &self,
         db: &EVMDatabase,
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Text},
+};
+use ethers::types::U256;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    schema::{evm_dex_pools, evm_mev_events},
+};
+
+use super::{
+    dex_swaps_parser::DatabaseEVMDexSwap,
+    token_prices_parser::{get_swap_tokens, DatabaseEVMDexPool},
+};
+
+pub const SANDWICH: &str = "sandwich";
+
+pub const ARBITRAGE: &str = "arbitrage";
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_mev_events)]
+pub struct DatabaseEVMMevEvent {
+    pub chain: String,
+    pub block_number: i64,
+    pub kind: String,
+    pub attacker: String,
+    pub victim: Option<String>,
+    pub pools: Vec<Option<String>>,
+    pub transactions: Vec<Option<String>>,
+    pub profit: Option<String>,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+struct MevBlock {
+    #[diesel(sql_type = Text)]
+    chain: String,
+    #[diesel(sql_type = BigInt)]
+    block_number: i64,
+}
+
+/// A dex swap with the position and origin of its transaction in the block.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct BlockDexSwap {
+    #[diesel(embed)]
+    pub swap: DatabaseEVMDexSwap,
+    #[diesel(sql_type = BigInt)]
+    pub block_number: i64,
+    #[diesel(sql_type = BigInt)]
+    pub transaction_index: i64,
+    #[diesel(sql_type = Text)]
+    pub from_address: String,
+    #[diesel(sql_type = Text)]
+    pub to_address: String,
+}
+
+/// Analyzes the swaps of a block to flag sandwich attacks and atomic arbitrages. The blocks are
+/// picked from the swaps not analyzed yet, but every swap of those blocks is loaded so a sandwich
+/// split across parser batches is still detected.
+pub struct MevParser {}
+
+impl MevParser {
+    /// Reads from the primary, a replica that doesn't have the swaps marked as parsed yet would
+    /// return blocks that were already parsed.
+    pub fn fetch(&self, db: &EVMDatabase) -> Result<Vec<BlockDexSwap>> {
+        let mut connection = db.establish_connection();
+
+        let blocks = sql_query(
+            "SELECT DISTINCT t.chain, t.block_number FROM evm_dex_swaps s \
+            JOIN evm_transactions t ON t.hash = s.hash \
+            WHERE NOT EXISTS (SELECT 1 FROM evm_parsed_logs p \
+            WHERE p.parser = 'mev' AND p.hash = s.hash AND p.log_index = s.log_index) \
+            LIMIT 100",
+        )
+        .load::<MevBlock>(&mut connection)?;
+
+        let mut chains_blocks: HashMap<String, Vec<i64>> = HashMap::new();
+
+        for block in blocks {
+            chains_blocks
+                .entry(block.chain)
+                .or_default()
+                .push(block.block_number);
+        }
+
+        let mut swaps = Vec::new();
+
+        for (chain, blocks) in chains_blocks {
+            let mut chain_swaps = sql_query(
+                "SELECT s.*, t.block_number, t.transaction_index, t.from_address, t.to_address \
+                FROM evm_dex_swaps s JOIN evm_transactions t ON t.hash = s.hash \
+                WHERE t.chain = $1 AND t.block_number = ANY($2)",
+            )
+            .bind::<Text, _>(chain)
+            .bind::<Array<BigInt>, _>(blocks)
+            .load::<BlockDexSwap>(&mut connection)?;
+
+            swaps.append(&mut chain_swaps);
+        }
+
+        Ok(swaps)
+    }
+
+    #[instrument(name = "mev_parser", skip_all, fields(swaps = swaps.len()))]
+    pub async fn parse(&self, db: &EVMDatabase, swaps: &Vec<BlockDexSwap>) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let pools = self.get_pools_tokens(&mut connection, swaps);
+
+        let mut blocks: HashMap<(String, i64), Vec<BlockDexSwap>> = HashMap::new();
+
+        for swap in swaps {
+            blocks
+                .entry((swap.swap.chain.clone(), swap.block_number))
+                .or_default()
+                .push(swap.clone());
+        }
+
+        let mut db_mev_events = Vec::new();
+
+        for ((chain, block_number), mut block_swaps) in blocks {
+            block_swaps.sort_by_key(|swap| (swap.transaction_index, swap.swap.log_index));
+
+            for (attacker, victim, pool, transactions, profit) in get_sandwiches(&block_swaps) {
+                db_mev_events.push(DatabaseEVMMevEvent {
+                    chain: chain.clone(),
+                    block_number,
+                    kind: SANDWICH.to_string(),
+                    attacker,
+                    victim: Some(victim),
+                    pools: vec![Some(pool)],
+                    transactions: transactions.into_iter().map(Some).collect(),
+                    profit,
+                });
+            }
+
+            for (attacker, pools, hash, profit) in get_arbitrages(&block_swaps, &pools) {
+                db_mev_events.push(DatabaseEVMMevEvent {
+                    chain: chain.clone(),
+                    block_number,
+                    kind: ARBITRAGE.to_string(),
+                    attacker,
+                    victim: None,
+                    pools: pools.into_iter().map(Some).collect(),
+                    transactions: vec![Some(hash)],
+                    profit,
+                });
+            }
+        }
+
+        let chunks = get_chunks(db_mev_events.len(), DatabaseEVMMevEvent::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_mev_events::dsl::evm_mev_events)
+                .values(&db_mev_events[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store mev events into database");
+        }
+
+        info!(
+            "Inserted {} mev events to the database.",
+            db_mev_events.len()
+        );
+
+        let keys: Vec<(String, i64)> = swaps
+            .iter()
+            .map(|swap| (swap.swap.hash.clone(), swap.swap.log_index))
+            .collect();
+
+        db.store_parsed_keys("mev", &keys).await
+    }
+
+    /// Pool tokens resolved by the token prices parser, pools not resolved yet are skipped for
+    /// arbitrage detection.
+    fn get_pools_tokens(
+        &self,
+        connection: &mut PgConnection,
+        swaps: &Vec<BlockDexSwap>,
+    ) -> HashMap<(String, String), Vec<Option<String>>> {
+        let addresses: Vec<String> = swaps
+            .iter()
+            .map(|swap| swap.swap.pool.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        evm_dex_pools::table
+            .select(evm_dex_pools::all_columns)
+            .filter(evm_dex_pools::pool.eq_any(addresses))
+            .load::<DatabaseEVMDexPool>(connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pool| ((pool.pool, pool.chain), pool.tokens))
+            .collect()
+    }
+}
+
+/// Identifies the swapped side of a pool, through the token addresses or the token indices.
+fn get_direction(swap: &DatabaseEVMDexSwap) -> Option<(String, String)> {
+    match (&swap.token_in, &swap.token_out) {
+        (Some(token_in), Some(token_out)) => Some((token_in.clone(), token_out.clone())),
+        _ => Some((
+            swap.token_in_index?.to_string(),
+            swap.token_out_index?.to_string(),
+        )),
+    }
+}
+
+fn get_profit(amount_in: &String, amount_out: &String) -> Option<String> {
+    let amount_in = U256::from_dec_str(amount_in).ok()?;
+    let amount_out = U256::from_dec_str(amount_out).ok()?;
+
+    if amount_out > amount_in {
+        Some((amount_out - amount_in).to_string())
+    } else {
+        None
+    }
+}
+
+type Sandwich = (String, String, String, Vec<String>, Option<String>);
+
+/// A sandwich is a swap on a pool followed by one or more swaps of other accounts in the same
+/// direction and closed by a swap in the opposite direction from the same account or contract.
+fn get_sandwiches(swaps: &Vec<BlockDexSwap>) -> Vec<Sandwich> {
+    let mut pools: HashMap<String, Vec<&BlockDexSwap>> = HashMap::new();
+
+    for swap in swaps {
+        pools.entry(swap.swap.pool.clone()).or_default().push(swap);
+    }
+
+    let mut sandwiches = Vec::new();
+
+    for (pool, pool_swaps) in pools {
+        let mut used: HashSet<String> = HashSet::new();
+
+        for (i, front) in pool_swaps.iter().enumerate() {
+            if used.contains(&front.swap.hash) {
+                continue;
+            }
+
+            let front_direction = match get_direction(&front.swap) {
+                Some(direction) => direction,
+                None => continue,
+            };
+
+            for back in pool_swaps.iter().skip(i + 1) {
+                if back.transaction_index <= front.transaction_index + 1 {
+                    continue;
+                }
+
+                let same_attacker =
+                    back.from_address == front.from_address || back.to_address == front.to_address;
+
+                if !same_attacker {
+                    continue;
+                }
+
+                match get_direction(&back.swap) {
+                    Some((token_in, token_out))
+                        if token_in == front_direction.1 && token_out == front_direction.0 => {}
+                    _ => continue,
+                }
+
+                let victims: Vec<&&BlockDexSwap> = pool_swaps
+                    .iter()
+                    .filter(|victim| {
+                        victim.transaction_index > front.transaction_index
+                            && victim.transaction_index < back.transaction_index
+                            && victim.from_address != front.from_address
+                            && get_direction(&victim.swap).as_ref() == Some(&front_direction)
+                    })
+                    .collect();
+
+                if victims.len() == 0 {
+                    continue;
+                }
+
+                let mut transactions = vec![front.swap.hash.clone()];
+
+                for victim in victims.iter() {
+                    if !transactions.contains(&victim.swap.hash) {
+                        transactions.push(victim.swap.hash.clone());
+                    }
+                }
+
+                transactions.push(back.swap.hash.clone());
+
+                used.insert(back.swap.hash.clone());
+
+                sandwiches.push((
+                    front.from_address.clone(),
+                    victims[0].from_address.clone(),
+                    pool.clone(),
+                    transactions,
+                    get_profit(&front.swap.amount_in, &back.swap.amount_out),
+                ));
+
+                break;
+            }
+        }
+    }
+
+    sandwiches
+}
+
+type Arbitrage = (String, Vec<String>, String, Option<String>);
+
+/// An atomic arbitrage is a transaction swapping through two or more pools and ending with more
+/// of the token it started with.
+fn get_arbitrages(
+    swaps: &Vec<BlockDexSwap>,
+    pools: &HashMap<(String, String), Vec<Option<String>>>,
+) -> Vec<Arbitrage> {
+    let mut transactions: Vec<(String, Vec<&BlockDexSwap>)> = Vec::new();
+
+    for swap in swaps {
+        match transactions.last_mut() {
+            Some((hash, transaction_swaps)) if *hash == swap.swap.hash => {
+                transaction_swaps.push(swap)
+            }
+            _ => transactions.push((swap.swap.hash.clone(), vec![swap])),
+        }
+    }
+
+    let mut arbitrages = Vec::new();
+
+    for (hash, transaction_swaps) in transactions {
+        if transaction_swaps.len() < 2 {
+            continue;
+        }
+
+        let unique_pools: Vec<String> = transaction_swaps
+            .iter()
+            .map(|swap| swap.swap.pool.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        if unique_pools.len() < 2 {
+            continue;
+        }
+
+        let first = transaction_swaps.first().unwrap();
+        let last = transaction_swaps.last().unwrap();
+
+        let (first_token_in, last_token_out) = match (
+            get_swap_tokens(&first.swap, pools),
+            get_swap_tokens(&last.swap, pools),
+        ) {
+            (Some((token_in, _)), Some((_, token_out))) => (token_in, token_out),
+            _ => continue,
+        };
+
+        if first_token_in != last_token_out {
+            continue;
+        }
+
+        let profit = match get_profit(&first.swap.amount_in, &last.swap.amount_out) {
+            Some(profit) => profit,
+            None => continue,
+        };
+
+        arbitrages.push((first.from_address.clone(), unique_pools, hash, Some(profit)));
+    }
+
+    arbitrages
+}
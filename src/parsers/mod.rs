@@ -1,3 +1,20 @@
+pub mod bridge_parser;
+pub mod decoded_logs_parser;
+pub mod decoder;
+pub mod dedup;
+pub mod deployments;
+pub mod dex_swaps_parser;
+pub mod ens_parser;
 pub mod erc20_tokens_parser;
 pub mod erc20_transfers_parser;
+pub mod flash_loans_parser;
+pub mod governance_parser;
+pub mod lending_parser;
+pub mod liquidity_parser;
 pub mod llamafolio_adapters;
+pub mod mev_parser;
+pub mod parallel;
+pub mod permits_parser;
+pub mod spam_tokens_parser;
+pub mod staking_parser;
+pub mod token_prices_parser;
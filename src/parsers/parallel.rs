@@ -0,0 +1,79 @@
+use std::{collections::HashMap, future::Future};
+
+use anyhow::{bail, Result};
+
+use crate::db::models::models::DatabaseEVMTransactionLog;
+
+/// Splits a batch of logs in up to `workers` chunks of similar size. The logs of a transaction
+/// stay in the same chunk, since some parsers, like the flash loans one, look at the other logs
+/// of the transaction.
+pub fn get_log_chunks(
+    logs: Vec<DatabaseEVMTransactionLog>,
+    workers: usize,
+) -> Vec<Vec<DatabaseEVMTransactionLog>> {
+    let chunk_size = (logs.len() + workers.max(1) - 1) / workers.max(1);
+
+    let mut transactions: Vec<Vec<DatabaseEVMTransactionLog>> = Vec::new();
+
+    let mut transactions_index: HashMap<String, usize> = HashMap::new();
+
+    for log in logs {
+        match transactions_index.get(&log.hash) {
+            Some(index) => transactions[*index].push(log),
+            None => {
+                transactions_index.insert(log.hash.clone(), transactions.len());
+
+                transactions.push(vec![log]);
+            }
+        }
+    }
+
+    let mut chunks: Vec<Vec<DatabaseEVMTransactionLog>> = Vec::new();
+
+    let mut chunk = Vec::new();
+
+    for mut transaction_logs in transactions {
+        chunk.append(&mut transaction_logs);
+
+        if chunk.len() >= chunk_size {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+    }
+
+    if chunk.len() > 0 {
+        chunks.push(chunk);
+    }
+
+    chunks
+}
+
+/// Parses the chunks of a batch on their own tasks, each one decoding its logs and storing the
+/// results with its own connection, so a batch is parsed on up to `workers` threads. With a
+/// single worker the batch is parsed in place.
+pub async fn parse_in_chunks<F, Fut>(
+    logs: Vec<DatabaseEVMTransactionLog>,
+    workers: usize,
+    parse: F,
+) -> Result<()>
+where
+    F: Fn(Vec<DatabaseEVMTransactionLog>) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    if workers <= 1 {
+        return parse(logs).await;
+    }
+
+    let tasks: Vec<_> = get_log_chunks(logs, workers)
+        .into_iter()
+        .map(|chunk| tokio::spawn(parse(chunk)))
+        .collect();
+
+    for task in tasks {
+        match task.await {
+            Ok(result) => result?,
+            Err(err) => bail!("Unable to join the parser task: {}", err),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    compression::INPUT_PAYLOAD,
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::{evm_permits, evm_transactions},
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder, FunctionDecoder};
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_permits)]
+pub struct DatabaseEVMPermit {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub token: String,
+    pub owner: String,
+    pub spender: String,
+    pub value: String,
+    pub deadline: Option<String>,
+    pub kind: String,
+    pub nested: bool,
+}
+
+/// Transaction fields needed to find the permit behind an approval.
+struct PermitTransaction {
+    chain: String,
+    from_address: String,
+    to_address: String,
+    input: Vec<u8>,
+}
+
+/// Stores the ERC-20 approvals that come from a permit signature instead of an `approve` call.
+/// The permit is found in the transaction calldata, either called directly on the token or
+/// nested in a router call like a multicall with `selfPermit`.
+pub struct PermitsParser {
+    pub approval: EventDecoder,
+    pub permits: FunctionDecoder,
+}
+
+impl PermitsParser {
+    pub fn new() -> Self {
+        Self {
+            approval: EventDecoder::new(&[
+                "event Approval(address indexed owner, address indexed spender, uint256 value)",
+            ]),
+            permits: FunctionDecoder::new(&[
+                "function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s)",
+                "function permit(address holder, address spender, uint256 nonce, uint256 expiry, bool allowed, uint8 v, bytes32 r, bytes32 s)",
+                "function selfPermit(address token, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s)",
+                "function selfPermitIfNecessary(address token, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s)",
+                "function selfPermitAllowed(address token, uint256 nonce, uint256 expiry, uint8 v, bytes32 r, bytes32 s)",
+                "function selfPermitAllowedIfNecessary(address token, uint256 nonce, uint256 expiry, uint8 v, bytes32 r, bytes32 s)",
+            ]),
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        db.get_unparsed_logs("permits", &self.approval.topics(), None, 10000)
+            .await
+    }
+
+    #[instrument(name = "permits_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let transactions: HashMap<String, PermitTransaction> = evm_transactions::table
+            .select((
+                evm_transactions::hash,
+                evm_transactions::chain,
+                evm_transactions::from_address,
+                evm_transactions::to_address,
+                evm_transactions::input,
+                evm_transactions::input_zstd,
+            ))
+            .filter(evm_transactions::hash.eq_any(hashes))
+            .load::<(String, String, String, String, String, Option<Vec<u8>>)>(&mut connection)?
+            .into_iter()
+            .filter_map(
+                |(hash, chain, from_address, to_address, input, input_zstd)| {
+                    let input = db
+                        .decompress_payload(INPUT_PAYLOAD, input, input_zstd)
+                        .ok()?;
+
+                    let input = hex::decode(input.trim_start_matches("0x")).ok()?;
+
+                    Some((
+                        hash,
+                        PermitTransaction {
+                            chain,
+                            from_address,
+                            to_address,
+                            input,
+                        },
+                    ))
+                },
+            )
+            .collect();
+
+        let mut db_permits = Vec::new();
+
+        for log in logs {
+            let transaction = match transactions.get(&log.hash) {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+
+            // ERC-721 approvals share the topic but index the token id.
+            let approval = match self.approval.decode(log) {
+                Some(approval) => approval,
+                None => continue,
+            };
+
+            match self.get_permit(&approval, log, transaction) {
+                Some(permit) => db_permits.push(permit),
+                None => continue,
+            }
+        }
+
+        let chunks = get_chunks(db_permits.len(), DatabaseEVMPermit::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_permits::dsl::evm_permits)
+                .values(&db_permits[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store permits into database");
+        }
+
+        info!("Inserted {} permits to the database.", db_permits.len());
+
+        let failures = get_parse_failures("permits", &[&self.approval], logs);
+
+        db.store_parse_failures(&failures).await?;
+
+        db.store_parsed_logs("permits", logs).await
+    }
+
+    fn get_permit(
+        &self,
+        approval: &DecodedLog,
+        log: &DatabaseEVMTransactionLog,
+        transaction: &PermitTransaction,
+    ) -> Option<DatabaseEVMPermit> {
+        let owner = approval.address("owner")?;
+        let spender = approval.address("spender")?;
+
+        let direct = transaction.to_address == log.address;
+
+        let mut calls = Vec::new();
+
+        if direct {
+            calls.extend(self.permits.decode(&transaction.input));
+        }
+
+        calls.append(&mut self.permits.decode_nested(&transaction.input));
+
+        for call in calls {
+            let (kind, call_owner, call_spender, token, deadline) = match call.name.as_str() {
+                "permit" => match call.address("owner") {
+                    Some(call_owner) => (
+                        "erc2612",
+                        call_owner,
+                        call.address("spender")?,
+                        log.address.clone(),
+                        call.uint("deadline"),
+                    ),
+                    None => (
+                        "dai",
+                        call.address("holder")?,
+                        call.address("spender")?,
+                        log.address.clone(),
+                        call.uint("expiry"),
+                    ),
+                },
+                // Router permits are signed by the sender for the router itself.
+                "selfPermit" | "selfPermitIfNecessary" => (
+                    "erc2612",
+                    transaction.from_address.clone(),
+                    transaction.to_address.clone(),
+                    call.address("token")?,
+                    call.uint("deadline"),
+                ),
+                "selfPermitAllowed" | "selfPermitAllowedIfNecessary" => (
+                    "dai",
+                    transaction.from_address.clone(),
+                    transaction.to_address.clone(),
+                    call.address("token")?,
+                    call.uint("expiry"),
+                ),
+                _ => continue,
+            };
+
+            if call_owner != owner || call_spender != spender || token != log.address {
+                continue;
+            }
+
+            return Some(DatabaseEVMPermit {
+                hash: log.hash.clone(),
+                log_index: log.log_index,
+                chain: transaction.chain.clone(),
+                token,
+                owner,
+                spender,
+                value: approval.uint("value")?,
+                deadline,
+                kind: kind.to_string(),
+                nested: !direct,
+            });
+        }
+
+        None
+    }
+}
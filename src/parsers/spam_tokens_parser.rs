@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use diesel::{
+    dsl::{count_star, exists},
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Text},
+};
+use ethabi::Address;
+use ethers::{
+    prelude::abigen,
+    providers::{Http, Provider},
+    types::U256,
+};
+use futures::future::join_all;
+use log::info;
+use tracing::instrument;
+
+use crate::{
+    chains::chains::get_chain,
+    db::{
+        db::EVMDatabase,
+        schema::{evm_abis, evm_dex_swaps, evm_erc20_tokens, evm_erc20_transfers},
+    },
+};
+
+use super::erc20_tokens_parser::DatabaseEVMErc20Token;
+
+abigen!(
+    SpamERC20,
+    r#"[
+        function balanceOf(address owner) external view returns (uint256)
+        function transfer(address to, uint256 value) external returns (bool)
+    ]"#,
+);
+
+/// Transfers of the token in a single transaction above which it is considered airdropped.
+const AIRDROP_TRANSFERS: i64 = 100;
+
+/// Words usually found in the name or symbol of tokens advertising a scam website.
+const SPAM_WORDS: [&str; 8] = [
+    "http", "www", ".com", ".io", ".org", "visit", "claim", "reward",
+];
+
+#[derive(QueryableByName, Debug)]
+struct TransfersCount {
+    #[diesel(sql_type = BigInt)]
+    transfers: i64,
+}
+
+/// Scores the indexed tokens from 0 to 100 on how likely they are spam. Every heuristic that
+/// matches adds its weight to the score and its reason to `spam_reasons`.
+pub struct SpamTokensParser {}
+
+impl SpamTokensParser {
+    pub fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMErc20Token>> {
+        let mut connection = db.establish_read_connection();
+
+        let tokens = evm_erc20_tokens::table
+            .select(evm_erc20_tokens::all_columns)
+            .filter(evm_erc20_tokens::spam_score.is_null())
+            .limit(100)
+            .load::<DatabaseEVMErc20Token>(&mut connection)?;
+
+        Ok(tokens)
+    }
+
+    #[instrument(name = "spam_tokens_parser", skip_all, fields(tokens = tokens.len()))]
+    pub async fn parse(&self, db: &EVMDatabase, tokens: &Vec<DatabaseEVMErc20Token>) -> Result<()> {
+        let mut scores = vec![];
+
+        for token in tokens {
+            scores.push(self.get_spam_score(db, token));
+        }
+
+        let scores: Vec<(&DatabaseEVMErc20Token, i64, Vec<Option<String>>)> = join_all(scores)
+            .await
+            .into_iter()
+            .zip(tokens.iter())
+            .map(|((score, reasons), token)| (token, score, reasons))
+            .collect();
+
+        let mut connection = db.establish_connection();
+
+        for (token, score, reasons) in scores.iter() {
+            diesel::update(
+                evm_erc20_tokens::table
+                    .filter(evm_erc20_tokens::address.eq(&token.address))
+                    .filter(evm_erc20_tokens::chain.eq(&token.chain)),
+            )
+            .set((
+                evm_erc20_tokens::spam_score.eq(score),
+                evm_erc20_tokens::spam_reasons.eq(reasons),
+            ))
+            .execute(&mut connection)
+            .expect("Unable to update tokens spam score");
+        }
+
+        let spam_tokens = scores.iter().filter(|(_, score, _)| *score >= 50).count();
+
+        info!(
+            "Scored {} tokens, {} of them are likely spam.",
+            scores.len(),
+            spam_tokens
+        );
+
+        Ok(())
+    }
+
+    async fn get_spam_score(
+        &self,
+        db: &EVMDatabase,
+        token: &DatabaseEVMErc20Token,
+    ) -> (i64, Vec<Option<String>>) {
+        let mut connection = db.establish_connection();
+
+        let mut score = 0;
+
+        let mut reasons = Vec::new();
+
+        if is_airdropped(&mut connection, &token.address) {
+            score += 30;
+            reasons.push(Some("airdrop".to_string()));
+        }
+
+        if !has_dex_liquidity(&mut connection, &token.address, &token.chain) {
+            score += 20;
+            reasons.push(Some("no_liquidity".to_string()));
+        }
+
+        if !has_valid_metadata(&mut connection, token) {
+            score += 20;
+            reasons.push(Some("metadata".to_string()));
+        }
+
+        let holder = get_holder(&mut connection, &token.address);
+
+        match holder {
+            Some(holder) => {
+                if self.is_honeypot(token, &holder).await {
+                    score += 40;
+                    reasons.push(Some("honeypot".to_string()));
+                }
+            }
+            None => (),
+        }
+
+        (score.min(100), reasons)
+    }
+
+    /// Simulates a transfer from a holder with `eth_call`, a token that reverts transfers from
+    /// holders with balance doesn't allow to sell it.
+    async fn is_honeypot(&self, token: &DatabaseEVMErc20Token, holder: &String) -> bool {
+        let chain = get_chain(token.chain.clone());
+
+        let provider = match Provider::<Http>::try_from(chain.public_rpc) {
+            Ok(provider) => provider,
+            Err(_) => return false,
+        };
+
+        let client = Arc::new(provider);
+
+        let (address, holder) = match (token.address.parse::<Address>(), holder.parse::<Address>())
+        {
+            (Ok(address), Ok(holder)) => (address, holder),
+            _ => return false,
+        };
+
+        let contract = SpamERC20::new(address, Arc::clone(&client));
+
+        let balance: U256 = match contract.balance_of(holder).call().await {
+            Ok(balance) => balance,
+            Err(_) => return false,
+        };
+
+        if balance.is_zero() {
+            return false;
+        }
+
+        // Any address other than the holder works as recipient for the simulation.
+        let recipient = Address::from_low_u64_be(0xdead);
+
+        match contract
+            .transfer(recipient, balance)
+            .from(holder)
+            .call()
+            .await
+        {
+            Ok(success) => !success,
+            Err(_) => true,
+        }
+    }
+}
+
+fn is_airdropped(connection: &mut PgConnection, token: &String) -> bool {
+    let transfers = sql_query(
+        "SELECT COALESCE(MAX(transfers), 0) AS transfers FROM \
+        (SELECT COUNT(*) AS transfers FROM evm_erc20_transfers WHERE token = $1 GROUP BY hash) t",
+    )
+    .bind::<Text, _>(token)
+    .get_result::<TransfersCount>(connection);
+
+    match transfers {
+        Ok(transfers) => transfers.transfers >= AIRDROP_TRANSFERS,
+        Err(_) => false,
+    }
+}
+
+/// Swaps in Balancer pools store the token address, Uniswap and Curve swaps are found through
+/// the pool tokens resolved by the token prices parser.
+fn has_dex_liquidity(connection: &mut PgConnection, token: &String, chain: &String) -> bool {
+    let swaps = diesel::select(exists(
+        evm_dex_swaps::table.filter(
+            evm_dex_swaps::token_in
+                .eq(token)
+                .or(evm_dex_swaps::token_out.eq(token)),
+        ),
+    ))
+    .get_result::<bool>(connection)
+    .unwrap_or(false);
+
+    if swaps {
+        return true;
+    }
+
+    let pools = sql_query(
+        "SELECT COUNT(*) AS transfers FROM evm_dex_pools WHERE chain = $1 AND $2 = ANY(tokens)",
+    )
+    .bind::<Text, _>(chain)
+    .bind::<Text, _>(token)
+    .get_result::<TransfersCount>(connection);
+
+    match pools {
+        Ok(pools) => pools.transfers > 0,
+        Err(_) => false,
+    }
+}
+
+fn has_valid_metadata(connection: &mut PgConnection, token: &DatabaseEVMErc20Token) -> bool {
+    let (name, symbol) = match (&token.name, &token.symbol, token.decimals) {
+        (Some(name), Some(symbol), Some(_)) => (name.to_lowercase(), symbol.to_lowercase()),
+        _ => return false,
+    };
+
+    for word in SPAM_WORDS {
+        if name.contains(word) || symbol.contains(word) {
+            return false;
+        }
+    }
+
+    // Tokens with a fetched but unverified source are suspicious, tokens not fetched yet aren't.
+    let unverified = evm_abis::table
+        .select(count_star())
+        .filter(evm_abis::contract.eq(&token.address))
+        .filter(evm_abis::chain.eq(&token.chain))
+        .filter(evm_abis::verified.eq(false))
+        .get_result::<i64>(connection)
+        .unwrap_or(0);
+
+    unverified == 0
+}
+
+/// Any recipient of the token, it is used as the sender to simulate a transfer.
+fn get_holder(connection: &mut PgConnection, token: &String) -> Option<String> {
+    evm_erc20_transfers::table
+        .select(evm_erc20_transfers::to_address)
+        .filter(evm_erc20_transfers::token.eq(token))
+        .order(evm_erc20_transfers::hash.desc())
+        .first::<String>(connection)
+        .ok()
+}
@@ -0,0 +1,372 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use ethers::types::U256;
+use field_count::FieldCount;
+use log::info;
+use tracing::instrument;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMTransactionLog,
+    schema::{evm_staking_events, evm_staking_rewards},
+};
+
+use super::decoder::{get_parse_failures, DecodedLog, EventDecoder};
+use super::dedup::{get_duplicate_keys, store_duplicate_logs, DuplicateFilter};
+use super::deployments::ProtocolDeployment;
+
+pub const LIDO: &str = "lido";
+
+pub const ROCKET_POOL: &str = "rocket-pool";
+
+/// Liquid staking protocols, other registered protocols are not parsed.
+pub const STAKING_PROTOCOLS: [&str; 2] = [LIDO, ROCKET_POOL];
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_staking_events)]
+pub struct DatabaseEVMStakingEvent {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub protocol: String,
+    pub action: String,
+    pub user_address: String,
+    pub amount: String,
+    pub shares: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_staking_rewards)]
+pub struct DatabaseEVMStakingReward {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub protocol: String,
+    pub total_pooled: String,
+    pub total_shares: String,
+    pub rewards: Option<String>,
+    pub report_timestamp: String,
+}
+
+pub type StakingDeployment = ProtocolDeployment;
+
+/// Lido stETH and withdrawal queue, Rocket Pool rETH and network balances.
+pub fn get_default_staking_deployments() -> Vec<StakingDeployment> {
+    let deployments = [
+        (
+            "ethereum",
+            LIDO,
+            "0xae7ab96520de3a18e5e111b5eaab095312d7fe84",
+        ),
+        (
+            "ethereum",
+            LIDO,
+            "0x889edc2edab5f40e902b864ad4d7ade8e412f9b1",
+        ),
+        (
+            "ethereum",
+            ROCKET_POOL,
+            "0xae78736cd615f374d3085123a210448e74fc6393",
+        ),
+        (
+            "ethereum",
+            ROCKET_POOL,
+            "0x138313f102ce9a0662f826fca977e3ab4d6e5539",
+        ),
+    ];
+
+    deployments
+        .into_iter()
+        .map(|(chain, protocol, address)| StakingDeployment {
+            chain: chain.to_string(),
+            protocol: protocol.to_string(),
+            address: address.to_string(),
+            contract: None,
+        })
+        .collect()
+}
+
+pub fn load_staking_deployments(path: &Option<String>) -> Vec<StakingDeployment> {
+    match path {
+        Some(path) => {
+            let file = std::fs::read_to_string(path).expect("Unable to read staking deployments");
+
+            serde_json::from_str(&file).expect("Unable to parse staking deployments")
+        }
+        None => get_default_staking_deployments(),
+    }
+}
+
+pub struct StakingParser {
+    pub deployments: HashMap<(String, String), StakingDeployment>,
+    pub dedup: DuplicateFilter,
+    pub lido: EventDecoder,
+    pub rocket_pool: EventDecoder,
+}
+
+impl StakingParser {
+    pub fn new(deployments: Vec<StakingDeployment>, dedup: DuplicateFilter) -> Self {
+        let lido = EventDecoder::new(&[
+            "event Submitted(address indexed sender, uint256 amount, address referral)",
+            "event TokenRebased(uint256 indexed reportTimestamp, uint256 timeElapsed, uint256 preTotalShares, uint256 preTotalEther, uint256 postTotalShares, uint256 postTotalEther, uint256 sharesMintedAsFees)",
+            "event WithdrawalRequested(uint256 indexed requestId, address indexed requestor, address indexed owner, uint256 amountOfStETH, uint256 amountOfShares)",
+            "event WithdrawalClaimed(uint256 indexed requestId, address indexed owner, address indexed receiver, uint256 amountOfETH)",
+        ]);
+
+        let rocket_pool = EventDecoder::new(&[
+            "event TokensMinted(address indexed to, uint256 amount, uint256 ethAmount, uint256 time)",
+            "event TokensBurned(address indexed from, uint256 amount, uint256 ethAmount, uint256 time)",
+            "event BalancesUpdated(uint256 block, uint256 totalEth, uint256 stakingEth, uint256 rethSupply, uint256 time)",
+        ]);
+
+        Self {
+            deployments: deployments
+                .into_iter()
+                .map(|deployment| {
+                    (
+                        (deployment.chain.clone(), deployment.address.to_lowercase()),
+                        deployment,
+                    )
+                })
+                .collect(),
+            dedup,
+            lido,
+            rocket_pool,
+        }
+    }
+
+    pub async fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMTransactionLog>> {
+        let mut topics = self.lido.topics();
+
+        topics.append(&mut self.rocket_pool.topics());
+
+        let addresses: Vec<String> = self
+            .deployments
+            .keys()
+            .map(|(_, address)| address.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        db.get_unparsed_logs("staking", &topics, Some(&addresses), 10000)
+            .await
+    }
+
+    #[instrument(name = "staking_parser", skip_all, fields(logs = logs.len()))]
+    pub async fn parse(
+        &self,
+        db: &EVMDatabase,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let mut db_staking_events = Vec::new();
+
+        let mut db_staking_rewards = Vec::new();
+
+        let hashes: Vec<String> = logs.iter().map(|log| log.hash.clone()).collect();
+
+        let chains = db.get_transactions_chains(&hashes).await?;
+
+        let duplicates = self.dedup.find_duplicates("staking", logs, |log| {
+            let chain = chains.get(&log.hash)?;
+
+            self.deployments
+                .get(&(chain.clone(), log.address.clone()))
+                .map(|deployment| deployment.protocol.clone())
+        });
+
+        let duplicate_keys = get_duplicate_keys(&duplicates);
+
+        for log in logs {
+            if duplicate_keys.contains(&(log.hash.clone(), log.log_index)) {
+                continue;
+            }
+
+            let chain = match chains.get(&log.hash) {
+                Some(chain) => chain.clone(),
+                None => continue,
+            };
+
+            let deployment = match self.deployments.get(&(chain, log.address.clone())) {
+                Some(deployment) => deployment,
+                None => continue,
+            };
+
+            let decoded = match deployment.protocol.as_str() {
+                LIDO => self.lido.decode(log),
+                ROCKET_POOL => self.rocket_pool.decode(log),
+                _ => None,
+            };
+
+            let decoded = match decoded {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+
+            match get_staking_reward(&decoded) {
+                Some((total_pooled, total_shares, rewards, report_timestamp)) => {
+                    db_staking_rewards.push(DatabaseEVMStakingReward {
+                        hash: log.hash.clone(),
+                        log_index: log.log_index,
+                        chain: deployment.chain.clone(),
+                        protocol: deployment.protocol.clone(),
+                        total_pooled,
+                        total_shares,
+                        rewards,
+                        report_timestamp,
+                    });
+
+                    continue;
+                }
+                None => (),
+            }
+
+            match get_staking_event(&decoded) {
+                Some((action, user_address, amount, shares, request_id)) => {
+                    db_staking_events.push(DatabaseEVMStakingEvent {
+                        hash: log.hash.clone(),
+                        log_index: log.log_index,
+                        chain: deployment.chain.clone(),
+                        protocol: deployment.protocol.clone(),
+                        action: action.to_string(),
+                        user_address,
+                        amount,
+                        shares,
+                        request_id,
+                    })
+                }
+                None => continue,
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(
+            db_staking_events.len(),
+            DatabaseEVMStakingEvent::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_staking_events::dsl::evm_staking_events)
+                .values(&db_staking_events[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store staking events into database");
+        }
+
+        let chunks = get_chunks(
+            db_staking_rewards.len(),
+            DatabaseEVMStakingReward::field_count(),
+        );
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_staking_rewards::dsl::evm_staking_rewards)
+                .values(&db_staking_rewards[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store staking rewards into database");
+        }
+
+        info!(
+            "Inserted {} staking events and {} staking rewards to the database.",
+            db_staking_events.len(),
+            db_staking_rewards.len()
+        );
+
+        let failures = get_parse_failures("staking", &[&self.lido, &self.rocket_pool], logs);
+
+        db.store_parse_failures(&failures).await?;
+
+        store_duplicate_logs(db, &duplicates)?;
+
+        db.store_parsed_logs("staking", logs).await
+    }
+}
+
+/// Action, user, amount of the staked asset, amount of shares and withdrawal request id.
+type StakingEvent = (&'static str, String, String, Option<String>, Option<String>);
+
+fn get_staking_event(decoded: &DecodedLog) -> Option<StakingEvent> {
+    match decoded.name.as_str() {
+        "Submitted" => Some((
+            "stake",
+            decoded.address("sender")?,
+            decoded.uint("amount")?,
+            None,
+            None,
+        )),
+        "WithdrawalRequested" => Some((
+            "unstake_request",
+            decoded.address("owner")?,
+            decoded.uint("amountOfStETH")?,
+            decoded.uint("amountOfShares"),
+            decoded.uint("requestId"),
+        )),
+        "WithdrawalClaimed" => Some((
+            "unstake",
+            decoded.address("owner")?,
+            decoded.uint("amountOfETH")?,
+            None,
+            decoded.uint("requestId"),
+        )),
+        "TokensMinted" => Some((
+            "stake",
+            decoded.address("to")?,
+            decoded.uint("ethAmount")?,
+            decoded.uint("amount"),
+            None,
+        )),
+        "TokensBurned" => Some((
+            "unstake",
+            decoded.address("from")?,
+            decoded.uint("ethAmount")?,
+            decoded.uint("amount"),
+            None,
+        )),
+        _ => None,
+    }
+}
+
+/// Total pooled ether, total shares, rewards and report timestamp.
+type StakingReward = (String, String, Option<String>, String);
+
+fn get_staking_reward(decoded: &DecodedLog) -> Option<StakingReward> {
+    match decoded.name.as_str() {
+        "TokenRebased" => {
+            let pre_shares = U256::from_dec_str(&decoded.uint("preTotalShares")?).ok()?;
+            let pre_ether = U256::from_dec_str(&decoded.uint("preTotalEther")?).ok()?;
+            let post_shares = U256::from_dec_str(&decoded.uint("postTotalShares")?).ok()?;
+            let post_ether = U256::from_dec_str(&decoded.uint("postTotalEther")?).ok()?;
+
+            // The rewards are the growth of the share rate applied to the shares after the
+            // report, so deposits and withdrawals in between are not counted.
+            let rewards = if pre_shares.is_zero() {
+                None
+            } else {
+                let expected_ether = pre_ether.checked_mul(post_shares)? / pre_shares;
+
+                if post_ether > expected_ether {
+                    Some((post_ether - expected_ether).to_string())
+                } else {
+                    Some("0".to_string())
+                }
+            };
+
+            Some((
+                post_ether.to_string(),
+                post_shares.to_string(),
+                rewards,
+                decoded.uint("reportTimestamp")?,
+            ))
+        }
+        "BalancesUpdated" => Some((
+            decoded.uint("totalEth")?,
+            decoded.uint("rethSupply")?,
+            None,
+            decoded.uint("time")?,
+        )),
+        _ => None,
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Double, Nullable, Text},
+};
+use ethabi::Address;
+use ethers::{
+    prelude::abigen,
+    providers::{Http, Provider},
+    types::U256,
+};
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::info;
+use tracing::instrument;
+
+use crate::{
+    chains::chains::get_chain,
+    db::{
+        db::{get_chunks, EVMDatabase},
+        schema::{evm_dex_pools, evm_erc20_tokens, evm_token_prices},
+    },
+    tokens::prices::get_external_usd_price,
+};
+
+use super::dex_swaps_parser::DatabaseEVMDexSwap;
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_dex_pools)]
+pub struct DatabaseEVMDexPool {
+    pub pool: String,
+    pub chain: String,
+    pub tokens: Vec<Option<String>>,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_token_prices)]
+pub struct DatabaseEVMTokenPrice {
+    pub chain: String,
+    pub token: String,
+    pub block_number: i64,
+    pub price_native: Option<f64>,
+    pub price_usd: Option<f64>,
+    pub swaps: i64,
+}
+
+abigen!(
+    DexPool,
+    r#"[
+        function token0() external view returns (address)
+        function token1() external view returns (address)
+        function coins(uint256) external view returns (address)
+    ]"#,
+);
+
+abigen!(
+    CurveLegacyPool,
+    r#"[
+        function coins(int128) external view returns (address)
+    ]"#,
+);
+
+/// Curve pools hold at most 8 coins.
+const MAX_CURVE_COINS: u64 = 8;
+
+/// Amounts traded against the quote tokens of a chain for a single token and block.
+#[derive(Default, Debug, Clone)]
+struct PriceVolumes {
+    native_base: f64,
+    native_quote: f64,
+    usd_base: f64,
+    usd_quote: f64,
+    swaps: i64,
+}
+
+/// Derives per-block token prices from the indexed dex swaps. A swap against the wrapped native
+/// token of the chain prices the other token in native terms, a swap against one of the chain USD
+/// stablecoins prices it in USD. Tokens only traded against the native token get their USD price
+/// through the native token USD price.
+pub struct TokenPricesParser {}
+
+impl TokenPricesParser {
+    /// Reads from the primary, the swaps are averaged into the stored prices, so a replica that
+    /// doesn't have them marked as parsed yet would count them twice.
+    pub fn fetch(&self, db: &EVMDatabase) -> Result<Vec<DatabaseEVMDexSwap>> {
+        let mut connection = db.establish_connection();
+
+        let swaps = sql_query(
+            "SELECT s.* FROM evm_dex_swaps s \
+            WHERE NOT EXISTS (SELECT 1 FROM evm_parsed_logs p \
+            WHERE p.parser = 'token_prices' AND p.hash = s.hash AND p.log_index = s.log_index) \
+            LIMIT 5000",
+        )
+        .load::<DatabaseEVMDexSwap>(&mut connection)?;
+
+        Ok(swaps)
+    }
+
+    #[instrument(name = "token_prices_parser", skip_all, fields(swaps = swaps.len()))]
+    pub async fn parse(&self, db: &EVMDatabase, swaps: &Vec<DatabaseEVMDexSwap>) -> Result<()> {
+        let hashes: Vec<String> = swaps.iter().map(|swap| swap.hash.clone()).collect();
+
+        let blocks = db.get_transactions_blocks(&hashes).await?;
+
+        let pools = self.get_pools_tokens(db, swaps).await;
+
+        let decimals = self.get_tokens_decimals(db, swaps, &pools);
+
+        let mut volumes: HashMap<(String, String, i64), PriceVolumes> = HashMap::new();
+
+        for swap in swaps {
+            let block_number = match blocks.get(&swap.hash) {
+                Some(block_number) => *block_number,
+                None => continue,
+            };
+
+            let (token_in, token_out) = match get_swap_tokens(swap, &pools) {
+                Some(tokens) => tokens,
+                None => continue,
+            };
+
+            let amount_in = match get_amount(
+                &swap.amount_in,
+                decimals.get(&(token_in.clone(), swap.chain.clone())),
+            ) {
+                Some(amount) => amount,
+                None => continue,
+            };
+
+            let amount_out = match get_amount(
+                &swap.amount_out,
+                decimals.get(&(token_out.clone(), swap.chain.clone())),
+            ) {
+                Some(amount) => amount,
+                None => continue,
+            };
+
+            let chain = get_chain(swap.chain.clone());
+
+            let is_native = |token: &String| token == chain.wrapped_native_token;
+
+            let is_usd = |token: &String| chain.usd_quote_tokens.contains(&token.as_str());
+
+            let is_quote = |token: &String| is_native(token) || is_usd(token);
+
+            // The priced token is the non quote side of the swap, when both sides are quote
+            // tokens the native token is priced in USD.
+            let (token, base, quote, quote_token) = if !is_quote(&token_in) && is_quote(&token_out)
+            {
+                (token_in, amount_in, amount_out, token_out)
+            } else if is_quote(&token_in) && !is_quote(&token_out) {
+                (token_out, amount_out, amount_in, token_in)
+            } else if is_native(&token_in) && is_usd(&token_out) {
+                (token_in, amount_in, amount_out, token_out)
+            } else if is_usd(&token_in) && is_native(&token_out) {
+                (token_out, amount_out, amount_in, token_in)
+            } else {
+                continue;
+            };
+
+            let entry = volumes
+                .entry((swap.chain.clone(), token, block_number))
+                .or_default();
+
+            if is_native(&quote_token) {
+                entry.native_base += base;
+                entry.native_quote += quote;
+            } else {
+                entry.usd_base += base;
+                entry.usd_quote += quote;
+            }
+
+            entry.swaps += 1;
+        }
+
+        let mut connection = db.establish_connection();
+
+        let native_usd_prices: HashMap<(String, i64), f64> = volumes
+            .iter()
+            .filter(|((chain, token, _), volumes)| {
+                token == get_chain(chain.clone()).wrapped_native_token && volumes.usd_base > 0.0
+            })
+            .map(|((chain, _, block_number), volumes)| {
+                (
+                    (chain.clone(), *block_number),
+                    volumes.usd_quote / volumes.usd_base,
+                )
+            })
+            .collect();
+
+        let mut db_prices: Vec<DatabaseEVMTokenPrice> = Vec::new();
+
+        for ((chain, token, block_number), volumes) in volumes.iter() {
+            let price_native = if token == get_chain(chain.clone()).wrapped_native_token {
+                Some(1.0)
+            } else if volumes.native_base > 0.0 {
+                Some(volumes.native_quote / volumes.native_base)
+            } else {
+                None
+            };
+
+            let price_usd = if volumes.usd_base > 0.0 {
+                Some(volumes.usd_quote / volumes.usd_base)
+            } else {
+                match price_native {
+                    Some(price_native) => {
+                        let native_usd_price =
+                            match native_usd_prices.get(&(chain.clone(), *block_number)) {
+                                Some(price) => Some(*price),
+                                None => get_native_usd_price(&mut connection, chain, *block_number),
+                            };
+
+                        native_usd_price.map(|native_usd_price| price_native * native_usd_price)
+                    }
+                    None => None,
+                }
+            };
+
+            // Tokens without a USD route on the dex swaps use the external prices, when fetched.
+            let price_usd = match price_usd {
+                Some(price_usd) => Some(price_usd),
+                None => get_external_usd_price(&mut connection, chain, token, *block_number),
+            };
+
+            db_prices.push(DatabaseEVMTokenPrice {
+                chain: chain.clone(),
+                token: token.clone(),
+                block_number: *block_number,
+                price_native,
+                price_usd,
+                swaps: volumes.swaps,
+            });
+        }
+
+        // Swaps of the same block can be split across batches, the stored price is averaged
+        // with the new one weighted by the amount of swaps of each.
+        for price in db_prices.iter() {
+            sql_query(
+                "INSERT INTO evm_token_prices (chain, token, block_number, price_native, price_usd, swaps) \
+                VALUES ($1, $2, $3, $4, $5, $6) \
+                ON CONFLICT (chain, token, block_number) DO UPDATE SET \
+                price_native = COALESCE((evm_token_prices.price_native * evm_token_prices.swaps + EXCLUDED.price_native * EXCLUDED.swaps) / (evm_token_prices.swaps + EXCLUDED.swaps), evm_token_prices.price_native, EXCLUDED.price_native), \
+                price_usd = COALESCE((evm_token_prices.price_usd * evm_token_prices.swaps + EXCLUDED.price_usd * EXCLUDED.swaps) / (evm_token_prices.swaps + EXCLUDED.swaps), evm_token_prices.price_usd, EXCLUDED.price_usd), \
+                swaps = evm_token_prices.swaps + EXCLUDED.swaps",
+            )
+            .bind::<Text, _>(&price.chain)
+            .bind::<Text, _>(&price.token)
+            .bind::<BigInt, _>(price.block_number)
+            .bind::<Nullable<Double>, _>(price.price_native)
+            .bind::<Nullable<Double>, _>(price.price_usd)
+            .bind::<BigInt, _>(price.swaps)
+            .execute(&mut connection)
+            .expect("Unable to store token prices into database");
+        }
+
+        info!("Inserted {} token prices to the database.", db_prices.len());
+
+        let keys: Vec<(String, i64)> = swaps
+            .iter()
+            .map(|swap| (swap.hash.clone(), swap.log_index))
+            .collect();
+
+        db.store_parsed_keys("token_prices", &keys).await
+    }
+
+    /// Uniswap and Curve swaps only store the index of the pool tokens, the pool tokens are
+    /// fetched once from the chain and stored in `evm_dex_pools`.
+    async fn get_pools_tokens(
+        &self,
+        db: &EVMDatabase,
+        swaps: &Vec<DatabaseEVMDexSwap>,
+    ) -> HashMap<(String, String), Vec<Option<String>>> {
+        let mut connection = db.establish_connection();
+
+        let unique_pools: HashSet<(String, String, String)> = swaps
+            .iter()
+            .filter(|swap| swap.token_in_index.is_some())
+            .map(|swap| (swap.pool.clone(), swap.chain.clone(), swap.dex.clone()))
+            .collect();
+
+        let addresses: Vec<String> = unique_pools
+            .iter()
+            .map(|(pool, _, _)| pool.clone())
+            .collect();
+
+        let mut pools: HashMap<(String, String), Vec<Option<String>>> = evm_dex_pools::table
+            .select(evm_dex_pools::all_columns)
+            .filter(evm_dex_pools::pool.eq_any(addresses))
+            .load::<DatabaseEVMDexPool>(&mut connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pool| ((pool.pool, pool.chain), pool.tokens))
+            .collect();
+
+        let mut pools_data = vec![];
+
+        for (pool, chain, dex) in unique_pools {
+            if pools.contains_key(&(pool.clone(), chain.clone())) {
+                continue;
+            }
+
+            pools_data.push(self.get_pool_tokens(pool, chain, dex))
+        }
+
+        let db_pools: Vec<DatabaseEVMDexPool> = join_all(pools_data)
+            .await
+            .into_iter()
+            .filter(|pool| pool.is_some())
+            .map(|pool| pool.unwrap())
+            .collect();
+
+        let chunks = get_chunks(db_pools.len(), DatabaseEVMDexPool::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_dex_pools::dsl::evm_dex_pools)
+                .values(&db_pools[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)
+                .expect("Unable to store dex pools into database");
+        }
+
+        for pool in db_pools {
+            pools.insert((pool.pool, pool.chain), pool.tokens);
+        }
+
+        pools
+    }
+
+    async fn get_pool_tokens(
+        &self,
+        pool: String,
+        chain: String,
+        dex: String,
+    ) -> Option<DatabaseEVMDexPool> {
+        let chain_data = get_chain(chain.clone());
+
+        let provider = match Provider::<Http>::try_from(chain_data.public_rpc) {
+            Ok(provider) => provider,
+            Err(_) => return None,
+        };
+
+        let client = Arc::new(provider);
+
+        let address = pool.parse::<Address>().ok()?;
+
+        let dex_pool = DexPool::new(address, Arc::clone(&client));
+
+        let mut tokens: Vec<Option<String>> = Vec::new();
+
+        if dex == "curve" {
+            let legacy_pool = CurveLegacyPool::new(address, Arc::clone(&client));
+
+            for index in 0..MAX_CURVE_COINS {
+                let coin = match dex_pool.coins(U256::from(index)).call().await {
+                    Ok(coin) => coin,
+                    Err(_) => match legacy_pool.coins(index as i128).call().await {
+                        Ok(coin) => coin,
+                        Err(_) => break,
+                    },
+                };
+
+                tokens.push(Some(format!("{:?}", coin)));
+            }
+        } else {
+            let token0 = dex_pool.token_0().call().await.ok()?;
+
+            let token1 = dex_pool.token_1().call().await.ok()?;
+
+            tokens.push(Some(format!("{:?}", token0)));
+            tokens.push(Some(format!("{:?}", token1)));
+        }
+
+        if tokens.len() == 0 {
+            return None;
+        }
+
+        Some(DatabaseEVMDexPool {
+            pool,
+            chain,
+            tokens,
+        })
+    }
+
+    fn get_tokens_decimals(
+        &self,
+        db: &EVMDatabase,
+        swaps: &Vec<DatabaseEVMDexSwap>,
+        pools: &HashMap<(String, String), Vec<Option<String>>>,
+    ) -> HashMap<(String, String), i64> {
+        let mut connection = db.establish_connection();
+
+        let tokens: Vec<String> = swaps
+            .iter()
+            .filter_map(|swap| get_swap_tokens(swap, pools))
+            .flat_map(|(token_in, token_out)| vec![token_in, token_out])
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        let decimals = evm_erc20_tokens::table
+            .select((
+                evm_erc20_tokens::address,
+                evm_erc20_tokens::chain,
+                evm_erc20_tokens::decimals,
+            ))
+            .filter(evm_erc20_tokens::address.eq_any(tokens))
+            .load::<(String, String, Option<i64>)>(&mut connection)
+            .unwrap_or_default();
+
+        decimals
+            .into_iter()
+            .filter_map(|(address, chain, decimals)| {
+                decimals.map(|decimals| ((address, chain), decimals))
+            })
+            .collect()
+    }
+}
+
+pub fn get_swap_tokens(
+    swap: &DatabaseEVMDexSwap,
+    pools: &HashMap<(String, String), Vec<Option<String>>>,
+) -> Option<(String, String)> {
+    match (&swap.token_in, &swap.token_out) {
+        (Some(token_in), Some(token_out)) => Some((token_in.clone(), token_out.clone())),
+        _ => {
+            let tokens = pools.get(&(swap.pool.clone(), swap.chain.clone()))?;
+
+            let token_in = tokens.get(swap.token_in_index? as usize)?.clone()?;
+            let token_out = tokens.get(swap.token_out_index? as usize)?.clone()?;
+
+            Some((token_in, token_out))
+        }
+    }
+}
+
+fn get_amount(amount: &String, decimals: Option<&i64>) -> Option<f64> {
+    let decimals = *decimals?;
+
+    let amount = amount.parse::<f64>().ok()? / 10f64.powi(decimals as i32);
+
+    if amount > 0.0 {
+        Some(amount)
+    } else {
+        None
+    }
+}
+
+/// Latest USD price of the wrapped native token at or before the block.
+fn get_native_usd_price(
+    connection: &mut PgConnection,
+    chain: &String,
+    block_number: i64,
+) -> Option<f64> {
+    let native_token = get_chain(chain.clone()).wrapped_native_token;
+
+    evm_token_prices::table
+        .select(evm_token_prices::price_usd)
+        .filter(evm_token_prices::chain.eq(chain))
+        .filter(evm_token_prices::token.eq(native_token))
+        .filter(evm_token_prices::block_number.le(block_number))
+        .filter(evm_token_prices::price_usd.is_not_null())
+        .order(evm_token_prices::block_number.desc())
+        .first::<Option<f64>>(connection)
+        .ok()
+        .flatten()
+}
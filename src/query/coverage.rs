@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Nullable, Text},
+};
+use ethers::abi::Contract;
+use serde::Serialize;
+
+use crate::{
+    db::{db::EVMDatabase, schema::evm_abis},
+    signatures::importer::get_event_names,
+};
+
+/// Topic of the ERC-20 and ERC-721 `Transfer` event, decoded by the transfers parser for any
+/// contract.
+pub const TRANSFER_TOPIC: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+#[derive(QueryableByName, Debug)]
+struct TopicCount {
+    #[diesel(sql_type = Text)]
+    address: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    topic: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    logs: i64,
+    #[diesel(sql_type = BigInt)]
+    parsed: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractCoverage {
+    pub contract: String,
+    pub logs: i64,
+    pub decoded_logs: i64,
+    pub coverage: f64,
+    pub has_abi: bool,
+    pub top_undecoded_topic: Option<String>,
+    pub top_undecoded_event: Option<String>,
+}
+
+/// Fraction of the logs of each contract with at least `min_logs` logs that are decoded, either
+/// by a parser that stored them in `evm_parsed_logs` or by an event of the contract's ABI. The
+/// contracts with the most undecoded logs come first, as the next ABIs or parsers to add.
+pub fn get_coverage(db: &EVMDatabase, min_logs: i64, limit: i64) -> Result<Vec<ContractCoverage>> {
+    let mut connection = db.establish_read_connection();
+
+    let counts = sql_query(
+        "WITH contracts AS (SELECT l.address FROM evm_transactions_logs l \
+        JOIN evm_transactions t ON t.hash = l.hash WHERE t.chain = $1 \
+        GROUP BY l.address HAVING COUNT(*) >= $2) \
+        SELECT l.address, l.topics[1] AS topic, COUNT(*) AS logs, \
+        COUNT(*) FILTER (WHERE EXISTS (SELECT 1 FROM evm_parsed_logs p \
+        WHERE p.hash = l.hash AND p.log_index = l.log_index)) AS parsed \
+        FROM evm_transactions_logs l JOIN evm_transactions t ON t.hash = l.hash \
+        WHERE t.chain = $1 AND l.address IN (SELECT address FROM contracts) \
+        GROUP BY l.address, l.topics[1]",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<BigInt, _>(min_logs)
+    .load::<TopicCount>(&mut connection)?;
+
+    let contracts: Vec<String> = counts
+        .iter()
+        .map(|count| count.address.clone())
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect();
+
+    let abis = evm_abis::table
+        .select((evm_abis::contract, evm_abis::abi))
+        .filter(evm_abis::chain.eq(db.chain.name))
+        .filter(evm_abis::contract.eq_any(&contracts))
+        .load::<(String, Option<String>)>(&mut connection)?;
+
+    let abi_topics: HashMap<String, HashSet<String>> = abis
+        .into_iter()
+        .filter_map(|(contract, abi)| {
+            let abi: Contract = serde_json::from_str(&abi?).ok()?;
+
+            let topics = abi
+                .events()
+                .map(|event| format!("{:?}", event.signature()))
+                .collect();
+
+            Some((contract, topics))
+        })
+        .collect();
+
+    let mut coverages: HashMap<String, (ContractCoverage, i64)> = HashMap::new();
+
+    for count in counts {
+        let topics = abi_topics.get(&count.address);
+
+        let decodable = match &count.topic {
+            Some(topic) => {
+                topic == TRANSFER_TOPIC
+                    || topics.map(|topics| topics.contains(topic)).unwrap_or(false)
+            }
+            None => false,
+        };
+
+        let decoded_logs = match decodable {
+            true => count.logs,
+            false => count.parsed,
+        };
+
+        let (coverage, top_undecoded_logs) =
+            coverages.entry(count.address.clone()).or_insert_with(|| {
+                (
+                    ContractCoverage {
+                        contract: count.address.clone(),
+                        logs: 0,
+                        decoded_logs: 0,
+                        coverage: 0.0,
+                        has_abi: topics.is_some(),
+                        top_undecoded_topic: None,
+                        top_undecoded_event: None,
+                    },
+                    0,
+                )
+            });
+
+        coverage.logs += count.logs;
+        coverage.decoded_logs += decoded_logs;
+
+        let undecoded_logs = count.logs - decoded_logs;
+
+        if undecoded_logs > *top_undecoded_logs {
+            *top_undecoded_logs = undecoded_logs;
+            coverage.top_undecoded_topic = count.topic.clone();
+        }
+    }
+
+    let mut report: Vec<ContractCoverage> = coverages
+        .into_values()
+        .map(|(mut coverage, _)| {
+            coverage.coverage = coverage.decoded_logs as f64 / coverage.logs as f64;
+            coverage
+        })
+        .collect();
+
+    report.sort_by_key(|coverage| -(coverage.logs - coverage.decoded_logs));
+
+    report.truncate(limit.max(0) as usize);
+
+    let topics: Vec<String> = report
+        .iter()
+        .filter_map(|coverage| coverage.top_undecoded_topic.clone())
+        .collect();
+
+    let names = get_event_names(db, &topics)?;
+
+    for coverage in report.iter_mut() {
+        coverage.top_undecoded_event = match &coverage.top_undecoded_topic {
+            Some(topic) => names.get(topic).cloned(),
+            None => None,
+        };
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,3 @@
+pub mod coverage;
+pub mod query;
+pub mod trending;
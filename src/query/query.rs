@@ -0,0 +1,365 @@
+use anyhow::{bail, Result};
+use clap::{Subcommand, ValueEnum};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Text},
+};
+use serde_json::{json, Value};
+
+use crate::{
+    api::search::search,
+    db::{
+        backfill::get_backfill_jobs,
+        compression::{DATA_PAYLOAD, INPUT_PAYLOAD},
+        db::EVMDatabase,
+    },
+    metrics::{
+        block_times::get_block_time_stats, table_stats::get_table_stats, views::get_view_refreshes,
+    },
+    state::reducer::get_reduced_values,
+};
+
+use super::{coverage::get_coverage, trending::get_trending_contracts};
+
+#[derive(QueryableByName, Debug)]
+struct JsonRow {
+    #[diesel(sql_type = Text)]
+    json: String,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum QueryCommand {
+    /// Transaction with its receipt and logs.
+    Tx { hash: String },
+
+    /// Block of the indexed chain.
+    Block { number: i64 },
+
+    /// Latest ERC-20 transfers from or to an address.
+    Transfers {
+        #[arg(long, help = "Address sending or receiving the transfers.")]
+        address: String,
+
+        #[arg(long, help = "Amount of transfers to show.", default_value_t = 50)]
+        limit: i64,
+    },
+
+    /// Share of the logs of the busiest contracts decoded by the parsers and ABIs.
+    Coverage {
+        #[arg(
+            long,
+            help = "Minimum amount of logs of the contracts to report.",
+            default_value_t = 1000
+        )]
+        min_logs: i64,
+
+        #[arg(long, help = "Amount of contracts to show.", default_value_t = 50)]
+        limit: i64,
+    },
+
+    /// Contracts with the most unique callers over the latest indexed days.
+    TrendingContracts {
+        #[arg(long, help = "Amount of latest days to count.", default_value_t = 1)]
+        days: i64,
+
+        #[arg(long, help = "Amount of contracts to show.", default_value_t = 50)]
+        limit: i64,
+    },
+
+    /// Outcome of the last refresh of each materialized view.
+    Views,
+
+    /// Block intervals and propagation delays of the heads received by the indexer.
+    BlockTimes {
+        #[arg(
+            long,
+            help = "Amount of latest blocks to measure.",
+            default_value_t = 1000
+        )]
+        blocks: i64,
+    },
+
+    /// Rows, sizes and growth of the tables, largest first, to forecast the disk usage.
+    TableStats {
+        #[arg(
+            long,
+            help = "Hours between the samples the insert and growth rates are measured over.",
+            default_value_t = 24
+        )]
+        hours: i64,
+    },
+
+    /// Derived state of a reducer, e.g. erc20_balances.
+    ReducedValues {
+        reducer: String,
+
+        #[arg(long, help = "Prefix of the keys to show, e.g. a token address.")]
+        prefix: Option<String>,
+
+        #[arg(long, help = "Block to read the state at, defaults to the latest one.")]
+        block: Option<i64>,
+
+        #[arg(long, help = "Amount of values to show.", default_value_t = 50)]
+        limit: i64,
+    },
+
+    /// Queued backfill jobs by the order they run in.
+    BackfillJobs {
+        #[arg(long, help = "Include the finished jobs.", default_value_t = false)]
+        all: bool,
+    },
+
+    /// Tokens by name or symbol and addresses by label matching a text, e.g. usdc.
+    Search {
+        text: String,
+
+        #[arg(long, help = "Amount of results to show.", default_value_t = 20)]
+        limit: i64,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum QueryFormat {
+    Json,
+    Table,
+}
+
+/// Reads indexed data straight from the database, so operators can check it without psql.
+pub fn run_query(db: &EVMDatabase, command: &QueryCommand, format: QueryFormat) -> Result<()> {
+    let result = match command {
+        QueryCommand::Tx { hash } => get_transaction(db, hash)?,
+        QueryCommand::Block { number } => get_block(db, *number)?,
+        QueryCommand::Transfers { address, limit } => get_transfers(db, address, *limit)?,
+        QueryCommand::Coverage { min_logs, limit } => {
+            serde_json::to_value(get_coverage(db, *min_logs, *limit)?)?
+        }
+        QueryCommand::TrendingContracts { days, limit } => {
+            serde_json::to_value(get_trending_contracts(db, *days, *limit)?)?
+        }
+        QueryCommand::Views => serde_json::to_value(get_view_refreshes(db)?)?,
+        QueryCommand::BlockTimes { blocks } => {
+            serde_json::to_value(get_block_time_stats(db, *blocks)?)?
+        }
+        QueryCommand::TableStats { hours } => serde_json::to_value(get_table_stats(db, *hours)?)?,
+        QueryCommand::ReducedValues {
+            reducer,
+            prefix,
+            block,
+            limit,
+        } => serde_json::to_value(get_reduced_values(db, reducer, prefix, *block, *limit)?)?,
+        QueryCommand::BackfillJobs { all } => serde_json::to_value(get_backfill_jobs(db, *all)?)?,
+        QueryCommand::Search { text, limit } => {
+            serde_json::to_value(search(db, text, Some(&db.chain.name.to_string()), *limit)?)?
+        }
+    };
+
+    match format {
+        QueryFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+        QueryFormat::Table => print_table(&result),
+    }
+
+    Ok(())
+}
+
+fn parse_rows(rows: Vec<JsonRow>) -> Result<Vec<Value>> {
+    let mut values = Vec::new();
+
+    for row in rows {
+        values.push(serde_json::from_str(&row.json)?);
+    }
+
+    Ok(values)
+}
+
+/// Replaces a column stored as text with its decompressed value when the payload was stored
+/// compressed. Byte columns are converted to `\x` hex strings by `row_to_json`.
+fn decompress_column(
+    db: &EVMDatabase,
+    row: &mut Value,
+    payload: &str,
+    column: &str,
+    compressed_column: &str,
+) -> Result<()> {
+    let row = match row.as_object_mut() {
+        Some(row) => row,
+        None => return Ok(()),
+    };
+
+    let compressed = match row.remove(compressed_column) {
+        Some(Value::String(compressed)) => hex::decode(compressed.trim_start_matches("\\x"))?,
+        _ => return Ok(()),
+    };
+
+    let text = match row.get(column) {
+        Some(Value::String(text)) => text.clone(),
+        _ => String::new(),
+    };
+
+    let decompressed = db.decompress_payload(payload, text, Some(compressed))?;
+
+    row.insert(column.to_string(), Value::String(decompressed));
+
+    Ok(())
+}
+
+/// Rows are converted to JSON by the database, so every column is shown as stored, except the
+/// compressed inputs and data, which are shown decompressed.
+fn get_transaction(db: &EVMDatabase, hash: &String) -> Result<Value> {
+    let mut connection = db.establish_read_connection();
+
+    let hash = hash.to_lowercase();
+
+    let transaction =
+        sql_query("SELECT row_to_json(t)::TEXT AS json FROM evm_transactions t WHERE t.hash = $1")
+            .bind::<Text, _>(&hash)
+            .load::<JsonRow>(&mut connection)?;
+
+    let mut transaction = match parse_rows(transaction)?.pop() {
+        Some(transaction) => transaction,
+        None => bail!("Transaction {} is not indexed", hash),
+    };
+
+    decompress_column(db, &mut transaction, INPUT_PAYLOAD, "input", "input_zstd")?;
+
+    let receipt = sql_query(
+        "SELECT row_to_json(r)::TEXT AS json FROM evm_transactions_receipts r WHERE r.hash = $1",
+    )
+    .bind::<Text, _>(&hash)
+    .load::<JsonRow>(&mut connection)?;
+
+    let logs = sql_query(
+        "SELECT row_to_json(l)::TEXT AS json FROM evm_transactions_logs l WHERE l.hash = $1 \
+        ORDER BY l.log_index",
+    )
+    .bind::<Text, _>(&hash)
+    .load::<JsonRow>(&mut connection)?;
+
+    let mut logs = parse_rows(logs)?;
+
+    for log in logs.iter_mut() {
+        decompress_column(db, log, DATA_PAYLOAD, "data", "data_zstd")?;
+    }
+
+    Ok(json!({
+        "transaction": transaction,
+        "receipt": parse_rows(receipt)?.pop(),
+        "logs": logs,
+    }))
+}
+
+fn get_block(db: &EVMDatabase, number: i64) -> Result<Value> {
+    let mut connection = db.establish_read_connection();
+
+    let block = sql_query(
+        "SELECT row_to_json(b)::TEXT AS json FROM evm_blocks b \
+        WHERE b.chain = $1 AND b.number = $2",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<BigInt, _>(number)
+    .load::<JsonRow>(&mut connection)?;
+
+    match parse_rows(block)?.pop() {
+        Some(block) => Ok(block),
+        None => bail!("Block {} of chain {} is not indexed", number, db.chain.name),
+    }
+}
+
+/// Transfers don't store the block, so the latest ones are the ones of the latest transactions.
+fn get_transfers(db: &EVMDatabase, address: &String, limit: i64) -> Result<Value> {
+    let mut connection = db.establish_read_connection();
+
+    let transfers = sql_query(
+        "SELECT json_build_object('block_number', t.block_number, 'hash', e.hash, \
+        'log_index', e.log_index, 'token', e.token, 'from_address', e.from_address, \
+        'to_address', e.to_address, 'value', e.value)::TEXT AS json \
+        FROM evm_erc20_transfers e JOIN evm_transactions t ON t.hash = e.hash \
+        WHERE t.chain = $1 AND (e.from_address = $2 OR e.to_address = $2) \
+        ORDER BY t.block_number DESC, e.log_index DESC LIMIT $3",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<Text, _>(address.to_lowercase())
+    .bind::<BigInt, _>(limit)
+    .load::<JsonRow>(&mut connection)?;
+
+    Ok(Value::Array(parse_rows(transfers)?))
+}
+
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Objects are printed as field and value rows, arrays of objects as one row per item.
+fn print_table(value: &Value) {
+    match value {
+        Value::Object(object) => {
+            let width = object.keys().map(|key| key.len()).max().unwrap_or(0);
+
+            for (key, value) in object {
+                match value {
+                    Value::Object(_) | Value::Array(_) => {
+                        println!("\n{}:", key);
+                        print_table(value);
+                    }
+                    value => println!("{:width$}  {}", key, format_cell(value), width = width),
+                }
+            }
+        }
+        Value::Array(rows) => {
+            let columns: Vec<String> = match rows.first() {
+                Some(Value::Object(object)) => object.keys().cloned().collect(),
+                _ => {
+                    for row in rows {
+                        println!("{}", format_cell(row));
+                    }
+
+                    return;
+                }
+            };
+
+            let cells: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .map(|column| format_cell(&row[column]))
+                        .collect()
+                })
+                .collect();
+
+            let widths: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .map(|(index, column)| {
+                    cells
+                        .iter()
+                        .map(|row| row[index].len())
+                        .chain([column.len()])
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            let print_row = |row: &Vec<String>| {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(widths.iter())
+                    .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                    .collect();
+
+                println!("{}", line.join("  ").trim_end());
+            };
+
+            print_row(&columns);
+
+            for row in cells.iter() {
+                print_row(row);
+            }
+        }
+        value => println!("{}", format_cell(value)),
+    }
+}
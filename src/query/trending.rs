@@ -0,0 +1,42 @@
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{BigInt, Text},
+};
+use serde::Serialize;
+
+use crate::db::db::EVMDatabase;
+
+#[derive(QueryableByName, Debug, Clone, Serialize)]
+pub struct TrendingContract {
+    #[diesel(sql_type = Text)]
+    pub contract: String,
+    #[diesel(sql_type = BigInt)]
+    pub transactions: i64,
+    #[diesel(sql_type = BigInt)]
+    pub unique_callers: i64,
+}
+
+/// Contracts with the most unique callers over the last `days` days, from the daily totals of
+/// `evm_contract_activity`. A caller active on several days is counted once per day.
+pub fn get_trending_contracts(
+    db: &EVMDatabase,
+    days: i64,
+    limit: i64,
+) -> Result<Vec<TrendingContract>> {
+    let mut connection = db.establish_read_connection();
+
+    let contracts = sql_query(
+        "SELECT contract, SUM(transactions)::BIGINT AS transactions, \
+        SUM(unique_callers)::BIGINT AS unique_callers FROM evm_contract_activity \
+        WHERE chain = $1 AND day >= (SELECT MAX(day) FROM evm_contract_activity WHERE chain = $1) - ($2 - 1) * 86400 \
+        GROUP BY contract ORDER BY unique_callers DESC, transactions DESC LIMIT $3",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<BigInt, _>(days)
+    .bind::<BigInt, _>(limit)
+    .load::<TrendingContract>(&mut connection)?;
+
+    Ok(contracts)
+}
@@ -0,0 +1,83 @@
+use std::sync::atomic::Ordering;
+
+use log::*;
+
+use super::rpc::EVMRpcStats;
+
+/// Error rate of the RPC requests of a batch above which the batches are halved.
+pub const MAX_ERROR_RATE: f64 = 0.05;
+
+/// Error rate of the RPC requests of a batch below which the batches can grow.
+pub const HEALTHY_ERROR_RATE: f64 = 0.01;
+
+/// Growth of the batches while the indexer is far behind and the providers are healthy.
+pub const BATCH_GROWTH: f64 = 1.25;
+
+/// Adjusts the amount of blocks fetched at the same time between the configured bounds. Batches
+/// grow while the indexer is more than a few batches behind the head and the requests succeed,
+/// and are halved when the providers start failing or rate limiting, so the throughput follows
+/// the capacity of the providers without tuning the batch size by hand.
+#[derive(Debug, Clone)]
+pub struct BatchSizeController {
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    pub batch_size: usize,
+    last_requests: u64,
+    last_errors: u64,
+}
+
+impl BatchSizeController {
+    pub fn new(batch_size: usize, min_batch_size: usize, max_batch_size: usize) -> Self {
+        let min_batch_size = min_batch_size.max(1);
+        let max_batch_size = max_batch_size.max(min_batch_size);
+
+        Self {
+            min_batch_size,
+            max_batch_size,
+            batch_size: batch_size.clamp(min_batch_size, max_batch_size),
+            last_requests: 0,
+            last_errors: 0,
+        }
+    }
+
+    /// Next batch size from the blocks left to sync and the requests since the last call.
+    pub fn adjust(&mut self, lag: usize, stats: &EVMRpcStats) -> usize {
+        let requests = stats.requests.load(Ordering::Relaxed);
+        let errors = stats.errors.load(Ordering::Relaxed);
+
+        let batch_requests = requests.saturating_sub(self.last_requests);
+        let batch_errors = errors.saturating_sub(self.last_errors);
+
+        self.last_requests = requests;
+        self.last_errors = errors;
+
+        let error_rate = match batch_requests {
+            0 => 0.0,
+            _ => batch_errors as f64 / batch_requests as f64,
+        };
+
+        let batch_size = if error_rate > MAX_ERROR_RATE {
+            self.batch_size / 2
+        } else if error_rate < HEALTHY_ERROR_RATE && lag > self.batch_size * 4 {
+            ((self.batch_size as f64 * BATCH_GROWTH).ceil() as usize).max(self.batch_size + 1)
+        } else {
+            self.batch_size
+        };
+
+        let batch_size = batch_size.clamp(self.min_batch_size, self.max_batch_size);
+
+        if batch_size != self.batch_size {
+            info!(
+                "Changing the batch size from {} to {} blocks ({} blocks behind, {:.1}% RPC errors).",
+                self.batch_size,
+                batch_size,
+                lag,
+                error_rate * 100.0
+            );
+        }
+
+        self.batch_size = batch_size;
+
+        batch_size
+    }
+}
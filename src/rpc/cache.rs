@@ -0,0 +1,73 @@
+use log::debug;
+use redis::AsyncCommands;
+use serde_json::Value;
+
+use crate::db::redis_connection::SharedRedisConnection;
+
+#[derive(Debug, Clone)]
+pub struct EVMRpcCache {
+    pub redis: redis::Client,
+    pub chain: &'static str,
+    pub finality_depth: i64,
+    connection: SharedRedisConnection,
+}
+
+impl EVMRpcCache {
+    pub fn new(redis_url: String, chain: &'static str, finality_depth: i64) -> Self {
+        let redis = redis::Client::open(redis_url).expect("Unable to connect with Redis server");
+
+        Self {
+            redis,
+            chain,
+            finality_depth,
+            connection: SharedRedisConnection::default(),
+        }
+    }
+
+    /// Returns true when the block is deep enough behind the head to be considered immutable.
+    pub fn is_final(&self, block_number: i64, last_block: i64) -> bool {
+        last_block > 0 && block_number <= last_block - self.finality_depth
+    }
+
+    pub async fn get(&self, method: &str, id: &str) -> Option<Value> {
+        let mut connection = match self.connection.get(&self.redis).await {
+            Ok(connection) => connection,
+            Err(_) => return None,
+        };
+
+        let cached: Option<String> = match connection.get(self.key(method, id)).await {
+            Ok(cached) => cached,
+            Err(_) => return None,
+        };
+
+        match cached {
+            Some(cached) => match serde_json::from_str(&cached) {
+                Ok(value) => {
+                    debug!("Cache hit for {} {} on chain {}", method, id, self.chain);
+                    Some(value)
+                }
+                Err(_) => None,
+            },
+            None => None,
+        }
+    }
+
+    pub async fn set(&self, method: &str, id: &str, value: &Value) {
+        let mut connection = match self.connection.get(&self.redis).await {
+            Ok(connection) => connection,
+            Err(_) => return,
+        };
+
+        let serialized = match serde_json::to_string(value) {
+            Ok(serialized) => serialized,
+            Err(_) => return,
+        };
+
+        let _: Result<(), redis::RedisError> =
+            connection.set(self.key(method, id), serialized).await;
+    }
+
+    fn key(&self, method: &str, id: &str) -> String {
+        format!("rpc-cache:{}:{}:{}", self.chain, method, id)
+    }
+}
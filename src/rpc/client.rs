@@ -0,0 +1,100 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use jsonrpsee::{
+    core::{params::ArrayParams, traits::ToRpcParams, Error},
+    types::error::{CallError, ErrorObject},
+};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{value::RawValue, Value};
+
+#[derive(Serialize)]
+struct RpcRequest<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<Box<RawValue>>,
+}
+
+/// JSON-RPC client of an HTTP endpoint. Its connections are opened with the connect timeout
+/// and kept alive with TCP keep-alive probes, so idle pooled connections dropped by a load
+/// balancer are detected before the next request uses them.
+#[derive(Debug, Clone)]
+pub struct RpcHttpClient {
+    pub url: String,
+    pub client: Client,
+    next_id: Arc<AtomicU64>,
+}
+
+impl RpcHttpClient {
+    /// A zero keep-alive disables the probes.
+    pub fn new(url: String, connect_timeout: Duration, keepalive: Duration) -> Result<Self, Error> {
+        let keepalive = match keepalive.is_zero() {
+            true => None,
+            false => Some(keepalive),
+        };
+
+        let client = Client::builder()
+            .connect_timeout(connect_timeout)
+            .tcp_keepalive(keepalive)
+            .build()
+            .map_err(|err| Error::Transport(err.into()))?;
+
+        Ok(Self {
+            url,
+            client,
+            next_id: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub async fn request(&self, method: &str, params: ArrayParams) -> Result<Value, Error> {
+        let request = RpcRequest {
+            jsonrpc: "2.0",
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            method,
+            params: params.to_rpc_params()?,
+        };
+
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| Error::Transport(err.into()))?;
+
+        let status = response.status();
+
+        let mut body: Value = match response.json().await {
+            Ok(body) => body,
+            Err(err) => {
+                return Err(Error::Transport(anyhow::anyhow!(
+                    "RPC responded with status {}: {}",
+                    status,
+                    err
+                )))
+            }
+        };
+
+        match body.get("error") {
+            Some(error) if !error.is_null() => {
+                let code = error["code"].as_i64().unwrap_or_default() as i32;
+                let message = error["message"].as_str().unwrap_or_default().to_string();
+
+                Err(Error::Call(CallError::Custom(ErrorObject::owned(
+                    code,
+                    message,
+                    error.get("data").cloned(),
+                ))))
+            }
+            _ => Ok(body["result"].take()),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{bail, Result};
+
+use super::rpc::EVMRpc;
+
+/// Seconds since the epoch of a UTC date, e.g. `2023-03-01` or `2023-03-01T12:30:00Z`. Unix
+/// timestamps are accepted as they are.
+pub fn parse_date(date: &str) -> Result<i64> {
+    if let Ok(timestamp) = date.parse::<i64>() {
+        return Ok(timestamp);
+    }
+
+    let date = date.trim_end_matches('Z');
+
+    let (day, time) = match date.split_once(|c| c == 'T' || c == ' ') {
+        Some((day, time)) => (day, Some(time)),
+        None => (date, None),
+    };
+
+    let day: Vec<&str> = day.split('-').collect();
+
+    let (year, month, day) = match day.as_slice() {
+        [year, month, day] => match (
+            year.parse::<i64>(),
+            month.parse::<i64>(),
+            day.parse::<i64>(),
+        ) {
+            (Ok(year), Ok(month), Ok(day)) => (year, month, day),
+            _ => bail!("Invalid date {}, expected YYYY-MM-DD", date),
+        },
+        _ => bail!("Invalid date {}, expected YYYY-MM-DD", date),
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        bail!("Invalid date {}, expected YYYY-MM-DD", date);
+    }
+
+    let mut seconds = 0;
+
+    if let Some(time) = time {
+        let parts: Vec<Result<i64, _>> = time.split(':').map(|part| part.parse::<i64>()).collect();
+
+        seconds = match parts.as_slice() {
+            [Ok(hours), Ok(minutes)] => hours * 3600 + minutes * 60,
+            [Ok(hours), Ok(minutes), Ok(seconds)] => hours * 3600 + minutes * 60 + seconds,
+            _ => bail!("Invalid time {}, expected HH:MM:SS", time),
+        };
+    }
+
+    Ok(get_days_from_civil(year, month, day) * 86400 + seconds)
+}
+
+/// Timestamp right after the `date`, a date without a time covers the whole day.
+pub fn parse_end_date(date: &str) -> Result<i64> {
+    let timestamp = parse_date(date)?;
+
+    match date.len() == 10 && date.contains('-') {
+        true => Ok(timestamp + 86400),
+        false => Ok(timestamp + 1),
+    }
+}
+
+/// Days between the epoch and a date of the proleptic Gregorian calendar.
+fn get_days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+
+    let year_of_era = year - era * 400;
+
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// Block range of the commands, the dates take precedence over the block numbers and are
+/// translated by searching the blocks by timestamp.
+pub async fn get_block_range(
+    rpc: &EVMRpc,
+    from: Option<i64>,
+    to: Option<i64>,
+    from_date: &Option<String>,
+    to_date: &Option<String>,
+) -> Result<(i64, i64)> {
+    let from = match from_date {
+        Some(date) => Some(rpc.get_first_block_at(parse_date(date)?).await?),
+        None => from,
+    };
+
+    let to = match to_date {
+        Some(date) => Some(rpc.get_first_block_at(parse_end_date(date)?).await? - 1),
+        None => to,
+    };
+
+    match (from, to) {
+        (Some(from), Some(to)) if from <= to => Ok((from, to)),
+        (Some(from), Some(to)) => bail!("The range from block {} to block {} is empty", from, to),
+        _ => bail!("The range requires a block or a date to start and end"),
+    }
+}
@@ -1 +1,8 @@
+pub mod autoscale;
+pub mod cache;
+pub mod client;
+pub mod dates;
 pub mod rpc;
+pub mod scheduler;
+pub mod usage;
+pub mod verify;
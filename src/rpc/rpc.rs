@@ -1,44 +1,164 @@
 use crate::{
+    archive::archive::BlockArchive,
     chains::chains::Chain,
     configs::indexer_config::EVMIndexerConfig,
     db::models::models::{
-        DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMTransaction, DatabaseEVMTransactionLog,
-        DatabaseEVMTransactionReceipt,
+        DatabaseEVMBlock, DatabaseEVMContract, DatabaseEVMLogTransaction, DatabaseEVMTransaction,
+        DatabaseEVMTransactionLog, DatabaseEVMTransactionReceipt,
     },
+    utils::format_hash,
 };
-use ethers::types::{Block, Transaction, TransactionReceipt, U256};
+use ethers::{
+    abi::{encode, Token},
+    types::{
+        Block, Bytes, EIP1186ProofResponse, FeeHistory, Log, Transaction, TransactionReceipt, H160,
+        H256, U256,
+    },
+};
+
+use anyhow::{bail, Result};
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use jsonrpsee::core::{params::ArrayParams, rpc_params};
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::{Error, Value};
+use tracing::instrument;
+
+use super::{
+    cache::EVMRpcCache,
+    client::RpcHttpClient,
+    scheduler::ProviderScheduler,
+    usage::{get_provider_name, get_usage, RpcMethodUsage, RpcUsage},
+    verify::{get_receipts_root, get_transactions_root, is_verifiable_type},
+};
+
+/// Selector of `balanceOf(address)`.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+#[derive(Debug, Default)]
+pub struct EVMRpcStats {
+    pub requests: AtomicU64,
+    pub errors: AtomicU64,
+    /// Requests per provider and method since the start.
+    pub methods: Mutex<HashMap<(String, String), RpcMethodUsage>>,
+    /// Requests per provider and method not stored in the database yet.
+    pub unflushed: Mutex<HashMap<(String, String), RpcMethodUsage>>,
+}
+
+/// Timeouts of the RPC requests. Traces of large blocks and transactions can take minutes on
+/// archive nodes, so they get their own.
+#[derive(Debug, Clone, Copy)]
+pub struct RpcTimeouts {
+    pub request: Duration,
+    pub trace: Duration,
+    /// Timeout of the connections and of the first request to each endpoint, so unreachable
+    /// ones are skipped quickly.
+    pub connect: Duration,
+}
+
+impl RpcTimeouts {
+    pub fn get(&self, method: &str) -> Duration {
+        match method.starts_with("debug_") || method.starts_with("trace_") {
+            true => self.trace,
+            false => self.request,
+        }
+    }
+}
+
+impl EVMRpcStats {
+    pub fn record<T, E>(&self, provider: &String, method: &str, response: &Result<T, E>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+
+        if response.is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
 
-use anyhow::Result;
-use jsonrpsee::core::{client::ClientT, rpc_params};
-use jsonrpsee_http_client::{HttpClient, HttpClientBuilder};
-use log::info;
-use rand::seq::SliceRandom;
-use std::time::Duration;
+        let key = (provider.clone(), method.to_string());
 
-use serde_json::Error;
+        self.methods
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .record(response.is_err());
 
-#[derive(Debug, Clone)]
+        self.unflushed
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .record(response.is_err());
+    }
+
+    pub fn get_usage(&self) -> Vec<RpcUsage> {
+        get_usage(&self.methods.lock().unwrap())
+    }
+
+    /// Usage since the last call, to store it in the database.
+    pub fn take_unflushed_usage(&self) -> Vec<RpcUsage> {
+        let unflushed = std::mem::take(&mut *self.unflushed.lock().unwrap());
+
+        get_usage(&unflushed)
+    }
+}
+
+type InFlightRequest = Shared<BoxFuture<'static, Result<Value, String>>>;
+
+#[derive(Clone)]
 pub struct EVMRpc {
-    pub clients: Vec<HttpClient>,
+    pub clients: Vec<RpcHttpClient>,
+    /// Host of each client, to account the requests per provider.
+    pub providers: Vec<String>,
+    /// Latency and errors of each client, to send more requests to the faster ones.
+    pub scheduler: Arc<ProviderScheduler>,
+    pub timeouts: RpcTimeouts,
     pub chain: Chain,
+    pub cache: Option<EVMRpcCache>,
+    pub last_block: Arc<AtomicI64>,
+    pub stats: Arc<EVMRpcStats>,
+    /// Recompute the transactions and receipts roots and skip the blocks that don't match.
+    pub verify_roots: bool,
+    pub archive: Option<BlockArchive>,
+    in_flight: Arc<Mutex<HashMap<String, InFlightRequest>>>,
 }
 
 impl EVMRpc {
     pub async fn new(config: &EVMIndexerConfig) -> Result<Self> {
         info!("Starting EVM rpc service");
 
-        let timeout = Duration::from_secs(60);
+        let timeouts = RpcTimeouts {
+            request: Duration::from_secs(config.rpc_timeout.max(1)),
+            trace: Duration::from_secs(config.rpc_trace_timeout.max(1)),
+            connect: Duration::from_secs(config.rpc_connect_timeout.max(1)),
+        };
 
         let mut clients = Vec::new();
+        let mut providers = Vec::new();
 
         for rpc in config.rpcs.clone() {
-            let client = HttpClientBuilder::default()
-                .max_concurrent_requests(100000)
-                .request_timeout(timeout)
-                .build(rpc)
-                .unwrap();
+            let provider = get_provider_name(&rpc);
+
+            let client = RpcHttpClient::new(
+                rpc,
+                timeouts.connect,
+                Duration::from_secs(config.rpc_keepalive),
+            )
+            .unwrap();
 
-            let client_id = client.request("eth_chainId", rpc_params![]).await;
+            let client_id =
+                send_request(&client, "eth_chainId", rpc_params![], timeouts.connect).await;
 
             match client_id {
                 Ok(value) => {
@@ -52,6 +172,7 @@ impl EVMRpc {
                     }
 
                     clients.push(client);
+                    providers.push(provider);
                 }
                 Err(_) => continue,
             }
@@ -61,62 +182,153 @@ impl EVMRpc {
             panic!("No valid RPC client found");
         }
 
+        let cache = match config.rpc_cache {
+            true => Some(EVMRpcCache::new(
+                config.redis_url.clone(),
+                config.chain.name,
+                config.finality_depth,
+            )),
+            false => None,
+        };
+
+        let archive = match &config.archive_url {
+            Some(url) => Some(BlockArchive::new(
+                url,
+                config.chain.name,
+                config.archive_range,
+            )?),
+            None => None,
+        };
+
         Ok(Self {
             clients,
+            scheduler: Arc::new(ProviderScheduler::new(providers.len())),
+            providers,
+            timeouts,
             chain: config.chain,
+            cache,
+            last_block: Arc::new(AtomicI64::new(0)),
+            stats: Arc::new(EVMRpcStats::default()),
+            verify_roots: config.verify_roots,
+            archive,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
     pub async fn get_last_block(&self) -> Result<i64> {
-        let client = self.get_client();
-
-        let last_block = client.request("eth_blockNumber", rpc_params![]).await;
+        let last_block = self.request("eth_blockNumber", rpc_params![]).await;
 
         match last_block {
             Ok(value) => {
                 let block_number: U256 = serde_json::from_value(value)
                     .expect("Unable to deserialize eth_blockNumber response");
 
-                Ok(block_number.as_u64() as i64)
+                let block_number = block_number.as_u64() as i64;
+
+                self.last_block.store(block_number, Ordering::Relaxed);
+
+                Ok(block_number)
             }
             Err(_) => Ok(0),
         }
     }
 
-    pub async fn get_block(
-        &self,
-        block_number: &i64,
-    ) -> Result<Option<(DatabaseEVMBlock, Vec<DatabaseEVMTransaction>)>> {
-        let client = self.get_client();
-
-        let raw_block = client
+    /// Timestamp of a block, requested without its transactions.
+    pub async fn get_block_timestamp(&self, block_number: i64) -> Result<Option<i64>> {
+        let raw_block = self
             .request(
                 "eth_getBlockByNumber",
-                rpc_params![format!("0x{:x}", block_number), true],
+                rpc_params![format!("0x{:x}", block_number), false],
             )
             .await;
 
         match raw_block {
             Ok(value) => {
-                let block: Result<Block<Transaction>, Error> = serde_json::from_value(value);
+                let block: Result<Block<H256>, Error> = serde_json::from_value(value);
 
                 match block {
-                    Ok(block) => {
-                        let db_block = DatabaseEVMBlock::from_rpc(&block, self.chain.name);
+                    Ok(block) => Ok(Some(block.timestamp.as_u64() as i64)),
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// First block with a timestamp at or after `timestamp`, found by a binary search over the
+    /// chain. Returns the block after the last one when the timestamp is in the future.
+    pub async fn get_first_block_at(&self, timestamp: i64) -> Result<i64> {
+        let last_block = self.get_last_block().await?;
+
+        let mut low = 0;
+        let mut high = last_block + 1;
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+
+            let block_timestamp = match self.get_block_timestamp(middle).await? {
+                Some(block_timestamp) => block_timestamp,
+                None => bail!("Unable to fetch the timestamp of block {}", middle),
+            };
+
+            if block_timestamp >= timestamp {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+
+        Ok(low)
+    }
+
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_block(
+        &self,
+        block_number: &i64,
+    ) -> Result<Option<(DatabaseEVMBlock, Vec<DatabaseEVMTransaction>)>> {
+        let raw_block = match self
+            .get_cached("eth_getBlockByNumber", &block_number.to_string())
+            .await
+        {
+            Some(value) => Ok(value),
+            None => {
+                let raw_block = self
+                    .coalesced_request(
+                        "eth_getBlockByNumber",
+                        block_number.to_string(),
+                        rpc_params![format!("0x{:x}", block_number), true],
+                    )
+                    .await;
+
+                if let Ok(value) = &raw_block {
+                    self.set_cached(
+                        "eth_getBlockByNumber",
+                        &block_number.to_string(),
+                        *block_number,
+                        value,
+                    )
+                    .await;
+                }
 
-                        let mut db_transactions = Vec::new();
+                raw_block
+            }
+        };
+
+        match raw_block {
+            Ok(value) => {
+                if let Some(archive) = &self.archive {
+                    archive.record_block(*block_number, &value);
+                }
 
-                        for transaction in block.transactions {
-                            let db_transaction = DatabaseEVMTransaction::from_rpc(
-                                transaction,
-                                self.chain.name,
-                                db_block.timestamp.clone(),
-                            );
+                let block: Result<Block<Transaction>, Error> = serde_json::from_value(value);
 
-                            db_transactions.push(db_transaction)
+                match block {
+                    Ok(block) => {
+                        if self.verify_roots && !verify_transactions_root(&block) {
+                            return Ok(None);
                         }
 
-                        Ok(Some((db_block, db_transactions)))
+                        Ok(Some(get_block_data(block, self.chain.name)))
                     }
                     Err(_) => Ok(None),
                 }
@@ -128,119 +340,780 @@ impl EVMRpc {
     pub async fn get_transaction_receipt(
         &self,
         transaction: String,
-    ) -> Result<
-        Option<(
-            DatabaseEVMTransactionReceipt,
-            Vec<DatabaseEVMTransactionLog>,
-            Option<DatabaseEVMContract>,
-        )>,
-    > {
-        let client = self.get_client();
+    ) -> Result<Option<TransactionReceipt>> {
+        let raw_receipt = match self
+            .get_cached("eth_getTransactionReceipt", &transaction)
+            .await
+        {
+            Some(value) => Ok(value),
+            None => {
+                let raw_receipt = self
+                    .coalesced_request(
+                        "eth_getTransactionReceipt",
+                        transaction.clone(),
+                        rpc_params![transaction.clone()],
+                    )
+                    .await;
 
-        let raw_receipt = client
-            .request("eth_getTransactionReceipt", rpc_params![transaction])
-            .await;
+                if let Ok(value) = &raw_receipt {
+                    let block_number = value
+                        .get("blockNumber")
+                        .and_then(|number| serde_json::from_value::<U256>(number.clone()).ok());
+
+                    if let Some(block_number) = block_number {
+                        self.set_cached(
+                            "eth_getTransactionReceipt",
+                            &transaction,
+                            block_number.as_u64() as i64,
+                            value,
+                        )
+                        .await;
+                    }
+                }
+
+                raw_receipt
+            }
+        };
 
         match raw_receipt {
             Ok(value) => {
-                let receipt: Result<TransactionReceipt, Error> = serde_json::from_value(value);
+                let receipt: Result<TransactionReceipt, Error> =
+                    serde_json::from_value(value.clone());
+
+                if let (Some(archive), Ok(receipt)) = (&self.archive, &receipt) {
+                    if let Some(block_number) = receipt.block_number {
+                        archive.record_receipts(block_number.as_u64() as i64, &value);
+                    }
+                }
 
                 match receipt {
-                    Ok(receipt) => {
-                        let db_receipt = DatabaseEVMTransactionReceipt::from_rpc(&receipt);
+                    Ok(receipt) => Ok(Some(receipt)),
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Call tree of a transaction traced with the call tracer. Needs a node with the debug
+    /// namespace enabled.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_call_tree(&self, transaction: &String) -> Result<Option<Value>> {
+        let options = serde_json::json!({ "tracer": "callTracer" });
+
+        let raw_trace = self
+            .request("debug_traceTransaction", rpc_params![transaction, options])
+            .await;
 
-                        let mut db_transaction_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
+        match raw_trace {
+            Ok(Value::Object(trace)) => Ok(Some(Value::Object(trace))),
+            _ => Ok(None),
+        }
+    }
 
-                        let db_contract = match receipt.contract_address {
-                            Some(_) => Some(DatabaseEVMContract::from_rpc(
-                                receipt.clone(),
-                                self.chain.name,
-                            )),
-                            None => None,
-                        };
+    /// Traces the transactions of a block with the prestate tracer in diff mode, returning the
+    /// result of each transaction in order. Needs a node with the debug namespace enabled.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_block_state_diffs(&self, block_number: &i64) -> Result<Option<Vec<Value>>> {
+        let options = serde_json::json!({
+            "tracer": "prestateTracer",
+            "tracerConfig": { "diffMode": true }
+        });
 
-                        for log in receipt.logs {
-                            let db_log = DatabaseEVMTransactionLog::from_rpc(log);
+        self.trace_block(block_number, options).await
+    }
 
-                            db_transaction_logs.push(db_log)
-                        }
+    /// Call trees of the transactions of a block traced with the call tracer, in order.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_block_call_trees(&self, block_number: &i64) -> Result<Option<Vec<Value>>> {
+        let options = serde_json::json!({ "tracer": "callTracer" });
 
-                        return Ok(Some((db_receipt, db_transaction_logs, db_contract)));
-                    }
-                    Err(_) => return Ok(None),
+        self.trace_block(block_number, options).await
+    }
+
+    /// Some clients wrap the result of each transaction with its hash, only the results are
+    /// returned.
+    async fn trace_block(&self, block_number: &i64, options: Value) -> Result<Option<Vec<Value>>> {
+        let raw_traces = self
+            .request(
+                "debug_traceBlockByNumber",
+                rpc_params![format!("0x{:x}", block_number), options],
+            )
+            .await;
+
+        match raw_traces {
+            Ok(Value::Array(traces)) => Ok(Some(
+                traces
+                    .into_iter()
+                    .map(|trace| match trace.get("result") {
+                        Some(result) => result.clone(),
+                        None => trace,
+                    })
+                    .collect(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// Result of a read only call to the contract at the block, `None` when the call reverts.
+    #[instrument(skip(self, data), fields(chain = self.chain.name))]
+    pub async fn call(
+        &self,
+        contract: &String,
+        data: &Bytes,
+        block_number: i64,
+    ) -> Result<Option<Bytes>> {
+        let call = serde_json::json!({ "to": contract, "data": data });
+
+        let raw_result = self
+            .request(
+                "eth_call",
+                rpc_params![call, format!("0x{:x}", block_number)],
+            )
+            .await;
+
+        match raw_result {
+            Ok(value) => {
+                let result: Result<Bytes, Error> = serde_json::from_value(value);
+
+                match result {
+                    Ok(result) => Ok(Some(result)),
+                    Err(_) => Ok(None),
                 }
             }
-            Err(_) => return Ok(None),
+            Err(_) => Ok(None),
         }
     }
 
-    pub async fn get_block_receipts(
+    /// ERC-20 balance of the holder at the block with `balanceOf`, `None` when the call reverts or
+    /// doesn't return a balance.
+    pub async fn get_erc20_balance(
         &self,
-        block_number: &i64,
-    ) -> Result<
-        Option<(
-            Vec<DatabaseEVMTransactionReceipt>,
-            Vec<DatabaseEVMTransactionLog>,
-            Vec<DatabaseEVMContract>,
-        )>,
-    > {
-        let client = self.get_client();
+        token: &String,
+        holder: &String,
+        block_number: i64,
+    ) -> Result<Option<U256>> {
+        let holder = match H160::from_str(holder) {
+            Ok(holder) => holder,
+            Err(err) => bail!("Invalid holder address {}: {}", holder, err),
+        };
+
+        let data = Bytes::from(
+            [
+                BALANCE_OF_SELECTOR.to_vec(),
+                encode(&[Token::Address(holder)]),
+            ]
+            .concat(),
+        );
+
+        match self.call(token, &data, block_number).await? {
+            Some(result) if result.len() >= 32 => Ok(Some(U256::from_big_endian(&result[..32]))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Replays a transaction with `eth_call` at the block and returns the revert data, `None` when
+    /// the call succeeds or the node doesn't return the data of the error.
+    #[instrument(skip(self, transaction), fields(chain = self.chain.name))]
+    pub async fn get_revert_data(
+        &self,
+        transaction: &DatabaseEVMTransaction,
+        block_number: i64,
+    ) -> Result<Option<Bytes>> {
+        let call = serde_json::json!({
+            "from": transaction.from_address,
+            "to": transaction.to_address,
+            "gas": format!("0x{:x}", U256::from_dec_str(&transaction.gas).unwrap_or_default()),
+            "value": format!("0x{:x}", U256::from_dec_str(&transaction.value).unwrap_or_default()),
+            "data": transaction.input,
+        });
+
+        let raw_result: Result<Value, jsonrpsee::core::Error> = self
+            .request(
+                "eth_call",
+                rpc_params![call, format!("0x{:x}", block_number)],
+            )
+            .await;
+
+        match raw_result {
+            Err(jsonrpsee::core::Error::Call(jsonrpsee::types::error::CallError::Custom(err))) => {
+                match err.data() {
+                    Some(data) => Ok(serde_json::from_str::<Bytes>(data.get()).ok()),
+                    None => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Value of a storage slot of the contract at the block.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_storage_at(
+        &self,
+        contract: &String,
+        slot: &String,
+        block_number: i64,
+    ) -> Result<Option<H256>> {
+        let raw_value = self
+            .request(
+                "eth_getStorageAt",
+                rpc_params![contract, slot, format!("0x{:x}", block_number)],
+            )
+            .await;
+
+        match raw_value {
+            Ok(value) => {
+                let value: Result<H256, Error> = serde_json::from_value(value);
+
+                match value {
+                    Ok(value) => Ok(Some(value)),
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
 
-        let raw_receipts = client
+    /// Account and storage proofs of an account at the block, the node must keep the state of
+    /// the block like for `eth_getStorageAt`.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_proof(
+        &self,
+        account: &String,
+        slots: &Vec<String>,
+        block_number: i64,
+    ) -> Result<Option<EIP1186ProofResponse>> {
+        let raw_proof = self
             .request(
-                "eth_getBlockReceipts",
-                rpc_params![format!("0x{:x}", block_number)],
+                "eth_getProof",
+                rpc_params![account, slots, format!("0x{:x}", block_number)],
             )
             .await;
 
+        match raw_proof {
+            Ok(value) => {
+                let proof: Result<EIP1186ProofResponse, Error> = serde_json::from_value(value);
+
+                match proof {
+                    Ok(proof) => Ok(Some(proof)),
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Base fees and reward percentiles of the `block_count` blocks up to `newest_block`. Most
+    /// nodes serve at most 1024 blocks per request.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_fee_history(
+        &self,
+        block_count: i64,
+        newest_block: i64,
+        percentiles: &Vec<f64>,
+    ) -> Result<Option<FeeHistory>> {
+        let raw_fee_history = self
+            .request(
+                "eth_feeHistory",
+                rpc_params![
+                    format!("0x{:x}", block_count),
+                    format!("0x{:x}", newest_block),
+                    percentiles
+                ],
+            )
+            .await;
+
+        match raw_fee_history {
+            Ok(value) => {
+                let fee_history: Result<FeeHistory, Error> = serde_json::from_value(value);
+
+                match fee_history {
+                    Ok(fee_history) => Ok(Some(fee_history)),
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Receipts of a block requested by its hash, so they belong to the same version of the
+    /// block as its body even if the number was reorged in between.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_block_receipts(
+        &self,
+        block_number: &i64,
+        block_hash: &String,
+    ) -> Result<Option<Vec<TransactionReceipt>>> {
+        let raw_receipts = match self.get_cached("eth_getBlockReceipts", block_hash).await {
+            Some(value) => Ok(value),
+            None => {
+                let raw_receipts = self
+                    .coalesced_request(
+                        "eth_getBlockReceipts",
+                        block_hash.clone(),
+                        rpc_params![block_hash],
+                    )
+                    .await;
+
+                if let Ok(value) = &raw_receipts {
+                    self.set_cached("eth_getBlockReceipts", block_hash, *block_number, value)
+                        .await;
+                }
+
+                raw_receipts
+            }
+        };
+
         match raw_receipts {
             Ok(value) => {
+                if let Some(archive) = &self.archive {
+                    archive.record_receipts(*block_number, &value);
+                }
+
                 let receipts: Result<Vec<TransactionReceipt>, Error> =
                     serde_json::from_value(value);
 
                 match receipts {
-                    Ok(receipts) => {
-                        let mut db_receipts: Vec<DatabaseEVMTransactionReceipt> = Vec::new();
+                    Ok(receipts) => Ok(Some(receipts)),
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
 
-                        let mut db_transaction_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
+    /// Logs of a block range with the transactions they belong to, for the logs backfill.
+    /// Returns `None` when the request fails, e.g. when the provider caps the results.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn get_logs(
+        &self,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<
+        Option<(
+            Vec<DatabaseEVMTransactionLog>,
+            Vec<DatabaseEVMLogTransaction>,
+        )>,
+    > {
+        let filter = serde_json::json!({
+            "fromBlock": format!("0x{:x}", from_block),
+            "toBlock": format!("0x{:x}", to_block),
+        });
 
-                        let mut db_contracts: Vec<DatabaseEVMContract> = Vec::new();
+        let raw_logs = self.request("eth_getLogs", rpc_params![filter]).await;
 
-                        for receipt in receipts {
-                            let db_receipt = DatabaseEVMTransactionReceipt::from_rpc(&receipt);
+        match raw_logs {
+            Ok(value) => {
+                let logs: Result<Vec<Log>, Error> = serde_json::from_value(value);
+
+                match logs {
+                    Ok(logs) => {
+                        let mut db_transactions: HashMap<String, DatabaseEVMLogTransaction> =
+                            HashMap::new();
+
+                        for log in logs.iter().filter(|log| log.removed != Some(true)) {
+                            let transaction =
+                                DatabaseEVMLogTransaction::from_rpc(log, self.chain.name);
+
+                            db_transactions.insert(transaction.hash.clone(), transaction);
+                        }
+
+                        let db_logs = logs
+                            .into_iter()
+                            .filter(|log| log.removed != Some(true))
+                            .map(DatabaseEVMTransactionLog::from_rpc)
+                            .collect();
+
+                        Ok(Some((db_logs, db_transactions.into_values().collect())))
+                    }
+                    Err(_) => Ok(None),
+                }
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Fetches a block with its receipts, logs and contracts. Blocks with missing transactions or
+    /// receipts are skipped so they are retried on the next sync.
+    #[instrument(skip(self), fields(chain = self.chain.name))]
+    pub async fn fetch_block(
+        &self,
+        block_number: &i64,
+    ) -> Option<(
+        DatabaseEVMBlock,
+        Vec<DatabaseEVMTransaction>,
+        Vec<DatabaseEVMTransactionReceipt>,
+        Vec<DatabaseEVMTransactionLog>,
+        Vec<DatabaseEVMContract>,
+    )> {
+        let block_data = self.get_block(block_number).await.unwrap();
 
-                            db_receipts.push(db_receipt);
+        match block_data {
+            Some((db_block, mut db_transactions)) => {
+                let total_block_transactions = db_transactions.len();
 
-                            let db_contract = match receipt.contract_address {
-                                Some(_) => Some(DatabaseEVMContract::from_rpc(
-                                    receipt.clone(),
-                                    self.chain.name,
-                                )),
-                                None => None,
-                            };
+                // Make sure all the transactions are correctly formatted.
+                if db_block.transactions != total_block_transactions as i64 {
+                    warn!(
+                        "Missing {} transactions for block {}.",
+                        db_block.transactions - total_block_transactions as i64,
+                        db_block.number
+                    );
+                    return None;
+                }
 
-                            if db_contract.is_some() {
-                                db_contracts.push(db_contract.unwrap())
-                            }
+                let mut receipts: Vec<TransactionReceipt> = Vec::new();
 
-                            for log in receipt.logs {
-                                let db_log = DatabaseEVMTransactionLog::from_rpc(log);
+                if self.chain.supports_blocks_receipts {
+                    let receipts_data = self
+                        .get_block_receipts(block_number, &db_block.block_hash)
+                        .await
+                        .unwrap();
+                    match receipts_data {
+                        Some(mut block_receipts) => receipts.append(&mut block_receipts),
+                        None => return None,
+                    }
+                } else {
+                    for transaction in db_transactions.iter_mut() {
+                        let receipt_data = self
+                            .get_transaction_receipt(transaction.hash.clone())
+                            .await
+                            .unwrap();
 
-                                db_transaction_logs.push(db_log)
-                            }
+                        match receipt_data {
+                            Some(receipt) => receipts.push(receipt),
+                            None => continue,
                         }
+                    }
+                }
+
+                if total_block_transactions != receipts.len() {
+                    warn!(
+                        "Missing receipts for block {}. Transactions {} receipts {}",
+                        db_block.number,
+                        total_block_transactions,
+                        receipts.len()
+                    );
+                    return None;
+                }
+
+                // Receipts by transaction hash come from whichever block includes the transaction
+                // now, which can be another version of the block after a reorg.
+                let mixed_receipts = receipts.iter().any(|receipt| {
+                    receipt.block_hash.map(format_hash).as_ref() != Some(&db_block.block_hash)
+                });
+
+                if mixed_receipts {
+                    warn!(
+                        "Receipts for block {} belong to another version of the block.",
+                        db_block.number
+                    );
+                    return None;
+                }
+
+                if self.verify_roots && !self.verify_receipts_root(&db_block, &receipts) {
+                    return None;
+                }
+
+                let mut db_receipts: Vec<DatabaseEVMTransactionReceipt> = Vec::new();
+                let mut db_logs: Vec<DatabaseEVMTransactionLog> = Vec::new();
+                let mut db_contracts: Vec<DatabaseEVMContract> = Vec::new();
+
+                for receipt in receipts.iter() {
+                    let (db_receipt, mut logs, contract) =
+                        get_receipt_data(receipt, self.chain.name);
 
-                        return Ok(Some((db_receipts, db_transaction_logs, db_contracts)));
+                    db_receipts.push(db_receipt);
+                    db_logs.append(&mut logs);
+
+                    match contract {
+                        Some(contract) => db_contracts.push(contract),
+                        None => (),
                     }
-                    Err(_) => return Ok(None),
                 }
+
+                set_gas_efficiencies(&mut db_transactions, &db_receipts);
+
+                info!(
+                    "Found transactions {} receipts {} logs {} and contracts {} for block {}.",
+                    total_block_transactions,
+                    db_receipts.len(),
+                    db_logs.len(),
+                    db_contracts.len(),
+                    block_number
+                );
+
+                return Some((
+                    db_block,
+                    db_transactions,
+                    db_receipts,
+                    db_logs,
+                    db_contracts,
+                ));
             }
-            Err(_) => return Ok(None),
+            None => return None,
         }
     }
 
-    fn get_client(&self) -> &HttpClient {
-        let client = self.clients.choose(&mut rand::thread_rng()).unwrap();
-        return client;
+    /// Archives the raw responses of the stored blocks when the archive is enabled.
+    pub async fn archive_blocks(&self, blocks: &Vec<DatabaseEVMBlock>) {
+        match &self.archive {
+            Some(archive) => {
+                let numbers = blocks.iter().map(|block| block.number).collect();
+
+                match archive.archive(&numbers).await {
+                    Ok(_) => (),
+                    Err(err) => warn!("Unable to archive raw blocks: {}", err),
+                }
+            }
+            None => (),
+        }
     }
+
+    /// Writes the archived blocks that don't fill a range yet.
+    pub async fn flush_archive(&self) {
+        match &self.archive {
+            Some(archive) => match archive.flush().await {
+                Ok(_) => (),
+                Err(err) => warn!("Unable to archive raw blocks: {}", err),
+            },
+            None => (),
+        }
+    }
+
+    /// Compares the receipts root of the header against the one of the fetched receipts.
+    fn verify_receipts_root(
+        &self,
+        block: &DatabaseEVMBlock,
+        receipts: &Vec<TransactionReceipt>,
+    ) -> bool {
+        if !receipts
+            .iter()
+            .all(|receipt| is_verifiable_type(receipt.transaction_type))
+        {
+            return true;
+        }
+
+        let receipts_root = format_hash(get_receipts_root(receipts));
+
+        if receipts_root != block.receipts_root {
+            warn!(
+                "Receipts root mismatch for block {}. Header {} receipts {}",
+                block.number, block.receipts_root, receipts_root
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// Shares a single network call between concurrent requests for the same method and id,
+    /// e.g. when the head subscription and the backfill loop fetch the same block.
+    async fn coalesced_request(
+        &self,
+        method: &'static str,
+        id: String,
+        params: ArrayParams,
+    ) -> Result<Value, String> {
+        let key = format!("{}:{}", method, id);
+
+        let request = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+
+            match in_flight.get(&key) {
+                Some(request) => request.clone(),
+                None => {
+                    let (index, client) = self.get_client();
+
+                    let client = client.clone();
+                    let provider = self.providers[index].clone();
+
+                    let stats = self.stats.clone();
+                    let scheduler = self.scheduler.clone();
+
+                    let timeout = self.timeouts.get(method);
+
+                    let request = async move {
+                        let started = Instant::now();
+
+                        let response = send_request(&client, method, params, timeout)
+                            .await
+                            .map_err(|err| err.to_string());
+
+                        scheduler.record(index, started.elapsed(), response.is_err());
+
+                        stats.record(&provider, method, &response);
+
+                        response
+                    }
+                    .boxed()
+                    .shared();
+
+                    in_flight.insert(key.clone(), request.clone());
+
+                    request
+                }
+            }
+        };
+
+        let response = request.clone().await;
+
+        // Only the request that was awaited is removed, a later one for the same key stays
+        // shared with its own waiters.
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        if in_flight
+            .get(&key)
+            .map_or(false, |current| current.ptr_eq(&request))
+        {
+            in_flight.remove(&key);
+        }
+
+        response
+    }
+
+    async fn get_cached(&self, method: &str, id: &str) -> Option<Value> {
+        match &self.cache {
+            Some(cache) => cache.get(method, id).await,
+            None => None,
+        }
+    }
+
+    async fn set_cached(&self, method: &str, id: &str, block_number: i64, value: &Value) {
+        match &self.cache {
+            Some(cache) => {
+                if value.is_null() {
+                    return;
+                }
+
+                if cache.is_final(block_number, self.last_block.load(Ordering::Relaxed)) {
+                    cache.set(method, id, value).await;
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// Sends a request to the provider picked by the scheduler and records its usage and
+    /// latency.
+    async fn request(
+        &self,
+        method: &'static str,
+        params: ArrayParams,
+    ) -> Result<Value, jsonrpsee::core::Error> {
+        let (index, client) = self.get_client();
+
+        let started = Instant::now();
+
+        let response = send_request(client, method, params, self.timeouts.get(method)).await;
+
+        self.scheduler
+            .record(index, started.elapsed(), response.is_err());
+
+        self.stats.record(&self.providers[index], method, &response);
+
+        response
+    }
+
+    fn get_client(&self) -> (usize, &RpcHttpClient) {
+        let index = self.scheduler.choose();
+
+        (index, &self.clients[index])
+    }
+}
+
+/// Sends a request with the timeout of its method.
+async fn send_request(
+    client: &RpcHttpClient,
+    method: &'static str,
+    params: ArrayParams,
+    timeout: Duration,
+) -> Result<Value, jsonrpsee::core::Error> {
+    match tokio::time::timeout(timeout, client.request(method, params)).await {
+        Ok(response) => response,
+        Err(_) => Err(jsonrpsee::core::Error::RequestTimeout),
+    }
+}
+
+/// Compares the transactions root of the header against the one of its transactions.
+fn verify_transactions_root(block: &Block<Transaction>) -> bool {
+    if !block
+        .transactions
+        .iter()
+        .all(|transaction| is_verifiable_type(transaction.transaction_type))
+    {
+        return true;
+    }
+
+    let transactions_root = get_transactions_root(&block.transactions);
+
+    if transactions_root != block.transactions_root {
+        warn!(
+            "Transactions root mismatch for block {:?}. Header {:?} transactions {:?}",
+            block.number, block.transactions_root, transactions_root
+        );
+        return false;
+    }
+
+    true
+}
+
+pub fn get_block_data(
+    block: Block<Transaction>,
+    chain: &'static str,
+) -> (DatabaseEVMBlock, Vec<DatabaseEVMTransaction>) {
+    let db_block = DatabaseEVMBlock::from_rpc(&block, chain);
+
+    let mut db_transactions = Vec::new();
+
+    for transaction in block.transactions {
+        let db_transaction =
+            DatabaseEVMTransaction::from_rpc(transaction, chain, db_block.timestamp.clone());
+
+        db_transactions.push(db_transaction)
+    }
+
+    (db_block, db_transactions)
+}
+
+/// Fills the gas efficiency of the transactions from their receipts.
+pub fn set_gas_efficiencies(
+    transactions: &mut Vec<DatabaseEVMTransaction>,
+    receipts: &Vec<DatabaseEVMTransactionReceipt>,
+) {
+    let receipts: HashMap<&String, &DatabaseEVMTransactionReceipt> = receipts
+        .iter()
+        .map(|receipt| (&receipt.hash, receipt))
+        .collect();
+
+    for transaction in transactions.iter_mut() {
+        match receipts.get(&transaction.hash) {
+            Some(receipt) => transaction.set_gas_efficiency(receipt),
+            None => (),
+        }
+    }
+}
+
+pub fn get_receipt_data(
+    receipt: &TransactionReceipt,
+    chain: &'static str,
+) -> (
+    DatabaseEVMTransactionReceipt,
+    Vec<DatabaseEVMTransactionLog>,
+    Option<DatabaseEVMContract>,
+) {
+    let db_receipt = DatabaseEVMTransactionReceipt::from_rpc(receipt);
+
+    let db_contract = match receipt.contract_address {
+        Some(_) => Some(DatabaseEVMContract::from_rpc(receipt.clone(), chain)),
+        None => None,
+    };
+
+    let db_logs = receipt
+        .logs
+        .iter()
+        .map(|log| DatabaseEVMTransactionLog::from_rpc(log.clone()))
+        .collect();
+
+    (db_receipt, db_logs, db_contract)
 }
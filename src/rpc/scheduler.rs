@@ -0,0 +1,83 @@
+use std::{sync::Mutex, time::Duration};
+
+use rand::Rng;
+
+/// Weight of a new sample in the moving averages of the providers.
+pub const SCORE_SMOOTHING: f64 = 0.1;
+
+/// Latency in milliseconds assumed for the providers without requests yet.
+pub const INITIAL_LATENCY: f64 = 100.0;
+
+/// Minimum share of the weight kept by failing providers, to notice when they recover.
+pub const MIN_SUCCESS_RATE: f64 = 0.05;
+
+#[derive(Debug, Clone)]
+pub struct ProviderScore {
+    pub latency: f64,
+    pub error_rate: f64,
+}
+
+impl ProviderScore {
+    /// Expected successful requests per millisecond.
+    pub fn weight(&self) -> f64 {
+        (1.0 - self.error_rate).max(MIN_SUCCESS_RATE) / self.latency.max(1.0)
+    }
+}
+
+/// Picks the provider of each request with a probability proportional to its recent speed and
+/// success rate. The blocks of a batch are requested concurrently, so faster providers serve
+/// more of them and a slow or failing endpoint only gets a small share instead of an equal one.
+#[derive(Debug)]
+pub struct ProviderScheduler {
+    scores: Mutex<Vec<ProviderScore>>,
+}
+
+impl ProviderScheduler {
+    pub fn new(providers: usize) -> Self {
+        Self {
+            scores: Mutex::new(vec![
+                ProviderScore {
+                    latency: INITIAL_LATENCY,
+                    error_rate: 0.0,
+                };
+                providers
+            ]),
+        }
+    }
+
+    pub fn choose(&self) -> usize {
+        let scores = self.scores.lock().unwrap();
+
+        let weights: Vec<f64> = scores.iter().map(|score| score.weight()).collect();
+
+        let mut target = rand::thread_rng().gen_range(0.0..weights.iter().sum::<f64>());
+
+        for (index, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return index;
+            }
+
+            target -= weight;
+        }
+
+        weights.len() - 1
+    }
+
+    pub fn record(&self, index: usize, latency: Duration, error: bool) {
+        let mut scores = self.scores.lock().unwrap();
+
+        let score = &mut scores[index];
+
+        let error = match error {
+            true => 1.0,
+            false => 0.0,
+        };
+
+        score.latency += SCORE_SMOOTHING * (latency.as_secs_f64() * 1000.0 - score.latency);
+        score.error_rate += SCORE_SMOOTHING * (error - score.error_rate);
+    }
+
+    pub fn get_scores(&self) -> Vec<ProviderScore> {
+        self.scores.lock().unwrap().clone()
+    }
+}
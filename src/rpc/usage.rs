@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use log::*;
+use reqwest::Url;
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone)]
+pub struct RpcMethodUsage {
+    pub requests: u64,
+    pub errors: u64,
+}
+
+impl RpcMethodUsage {
+    pub fn record(&mut self, error: bool) {
+        self.requests += 1;
+
+        if error {
+            self.errors += 1;
+        }
+    }
+}
+
+/// Requests of a method to a provider, with their estimated cost in Alchemy compute units and
+/// Infura credits. Failed requests are billed too.
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcUsage {
+    pub provider: String,
+    pub method: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub compute_units: i64,
+    pub credits: i64,
+}
+
+/// Alchemy compute units of a request, from the public pricing table at the time of writing.
+/// Unknown methods are counted as a simple call.
+pub fn get_alchemy_compute_units(method: &str) -> u64 {
+    match method {
+        "eth_chainId" => 0,
+        "eth_blockNumber" => 10,
+        "eth_feeHistory" => 10,
+        "eth_getTransactionReceipt" => 15,
+        "eth_getBlockByNumber" => 16,
+        "eth_getStorageAt" => 17,
+        "eth_getProof" => 21,
+        "eth_call" => 26,
+        "eth_getLogs" => 75,
+        "debug_traceTransaction" => 309,
+        "debug_traceBlockByNumber" => 497,
+        "eth_getBlockReceipts" => 500,
+        _ => 26,
+    }
+}
+
+/// Infura credits of a request, from the public pricing table at the time of writing. Unknown
+/// methods are counted as a simple call.
+pub fn get_infura_credits(method: &str) -> u64 {
+    match method {
+        "eth_chainId" => 5,
+        "eth_getLogs" => 255,
+        "debug_traceTransaction" | "debug_traceBlockByNumber" => 300,
+        "eth_getBlockReceipts" => 1000,
+        _ => 80,
+    }
+}
+
+pub fn get_usage(methods: &HashMap<(String, String), RpcMethodUsage>) -> Vec<RpcUsage> {
+    let mut usage: Vec<RpcUsage> = methods
+        .iter()
+        .map(|((provider, method), method_usage)| RpcUsage {
+            provider: provider.clone(),
+            method: method.clone(),
+            requests: method_usage.requests as i64,
+            errors: method_usage.errors as i64,
+            compute_units: (method_usage.requests * get_alchemy_compute_units(method)) as i64,
+            credits: (method_usage.requests * get_infura_credits(method)) as i64,
+        })
+        .collect();
+
+    usage.sort_by(|a, b| (&a.provider, &a.method).cmp(&(&b.provider, &b.method)));
+
+    usage
+}
+
+/// Host of a RPC url, so the API keys in the path or query don't end in the logs and metrics.
+pub fn get_provider_name(rpc: &String) -> String {
+    match Url::parse(rpc) {
+        Ok(url) => match url.host_str() {
+            Some(host) => host.to_string(),
+            None => "unknown".to_string(),
+        },
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+pub fn log_usage_summary(usage: &Vec<RpcUsage>) {
+    if usage.len() == 0 {
+        return;
+    }
+
+    info!("RPC usage summary:");
+
+    for method_usage in usage {
+        info!(
+            "{} {}: {} requests ({} errors), {} compute units, {} credits.",
+            method_usage.provider,
+            method_usage.method,
+            method_usage.requests,
+            method_usage.errors,
+            method_usage.compute_units,
+            method_usage.credits
+        );
+    }
+
+    info!(
+        "Total: {} requests, {} compute units, {} credits.",
+        usage.iter().map(|usage| usage.requests).sum::<i64>(),
+        usage.iter().map(|usage| usage.compute_units).sum::<i64>(),
+        usage.iter().map(|usage| usage.credits).sum::<i64>()
+    );
+}
@@ -0,0 +1,187 @@
+use ethers::{
+    types::{Transaction, TransactionReceipt, H256, U64},
+    utils::{
+        keccak256,
+        rlp::{self, RlpStream},
+    },
+};
+
+/// Highest transaction type the encoders know about, blocks with other types (e.g. L2 deposit
+/// transactions) can't be verified.
+pub const MAX_VERIFIABLE_TRANSACTION_TYPE: u64 = 2;
+
+pub fn is_verifiable_type(transaction_type: Option<U64>) -> bool {
+    match transaction_type {
+        Some(transaction_type) => transaction_type.as_u64() <= MAX_VERIFIABLE_TRANSACTION_TYPE,
+        None => true,
+    }
+}
+
+pub fn get_transactions_root(transactions: &Vec<Transaction>) -> H256 {
+    let items = transactions
+        .iter()
+        .map(|transaction| transaction.rlp().to_vec())
+        .collect();
+
+    get_ordered_trie_root(items)
+}
+
+pub fn get_receipts_root(receipts: &Vec<TransactionReceipt>) -> H256 {
+    let items = receipts.iter().map(get_receipt_rlp).collect();
+
+    get_ordered_trie_root(items)
+}
+
+/// Consensus encoding of a receipt. Typed receipts are prefixed with their type and receipts
+/// before Byzantium include the state root instead of the status.
+pub fn get_receipt_rlp(receipt: &TransactionReceipt) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(4);
+
+    match (receipt.root, receipt.status) {
+        (Some(root), _) => stream.append(&root),
+        (None, Some(status)) => stream.append(&status),
+        (None, None) => stream.append_empty_data(),
+    };
+
+    stream.append(&receipt.cumulative_gas_used);
+    stream.append(&receipt.logs_bloom);
+
+    stream.begin_list(receipt.logs.len());
+
+    for log in receipt.logs.iter() {
+        stream.begin_list(3);
+        stream.append(&log.address);
+        stream.append_list(&log.topics);
+        stream.append(&log.data.to_vec());
+    }
+
+    let mut encoded = Vec::new();
+
+    match receipt.transaction_type {
+        Some(transaction_type) if transaction_type.as_u64() > 0 => {
+            encoded.push(transaction_type.as_u64() as u8)
+        }
+        _ => (),
+    }
+
+    encoded.extend_from_slice(&stream.out());
+
+    encoded
+}
+
+/// Root of the trie used by the block headers for transactions and receipts, where each item
+/// is keyed by the RLP encoding of its index.
+pub fn get_ordered_trie_root(items: Vec<Vec<u8>>) -> H256 {
+    let mut entries: Vec<(Vec<u8>, Vec<u8>)> = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| (get_nibbles(&rlp::encode(&(index as u64))), item))
+        .collect();
+
+    entries.sort();
+
+    H256::from(keccak256(encode_node(&entries, 0)))
+}
+
+fn get_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Compact encoding of a path, the first nibble flags leaves and odd lengths.
+fn encode_path(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 2 } else { 0 };
+
+    let mut path = Vec::new();
+
+    let rest = match nibbles.len() % 2 {
+        1 => {
+            path.push(((flag + 1) << 4) | nibbles[0]);
+            &nibbles[1..]
+        }
+        _ => {
+            path.push(flag << 4);
+            nibbles
+        }
+    };
+
+    for pair in rest.chunks(2) {
+        path.push((pair[0] << 4) | pair[1]);
+    }
+
+    path
+}
+
+/// Nodes shorter than a hash are embedded in their parent.
+fn append_child(stream: &mut RlpStream, node: Vec<u8>) {
+    if node.len() < 32 {
+        stream.append_raw(&node, 1);
+    } else {
+        stream.append(&keccak256(&node).to_vec());
+    }
+}
+
+/// Encodes the node for the sorted entries sharing the first `depth` nibbles.
+fn encode_node(entries: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    if entries.len() == 0 {
+        return rlp::NULL_RLP.to_vec();
+    }
+
+    if entries.len() == 1 {
+        let (key, value) = &entries[0];
+
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&encode_path(&key[depth..], true));
+        stream.append(value);
+
+        return stream.out().to_vec();
+    }
+
+    let first = &entries[0].0;
+    let last = &entries[entries.len() - 1].0;
+
+    let shared = first[depth..]
+        .iter()
+        .zip(last[depth..].iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    if shared > 0 {
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&encode_path(&first[depth..depth + shared], false));
+        append_child(&mut stream, encode_node(entries, depth + shared));
+
+        return stream.out().to_vec();
+    }
+
+    let mut stream = RlpStream::new_list(17);
+
+    // A key ending at this depth sorts first and holds the value of the branch.
+    let (value, children) = match first.len() == depth {
+        true => (Some(&entries[0].1), &entries[1..]),
+        false => (None, entries),
+    };
+
+    for nibble in 0..16u8 {
+        let group: Vec<(Vec<u8>, Vec<u8>)> = children
+            .iter()
+            .filter(|(key, _)| key[depth] == nibble)
+            .cloned()
+            .collect();
+
+        match group.len() {
+            0 => {
+                stream.append_empty_data();
+            }
+            _ => append_child(&mut stream, encode_node(&group, depth + 1)),
+        }
+    }
+
+    match value {
+        Some(value) => stream.append(value),
+        None => stream.append_empty_data(),
+    };
+
+    stream.out().to_vec()
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use log::*;
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        models::models::{DatabaseEVMTransaction, DatabaseEVMTransactionLog},
+        schema::evm_flagged_activity,
+    },
+    parsers::decoder::EventDecoder,
+};
+
+/// Log index used for the activity flagged on the transaction itself.
+pub const TRANSACTION_LOG_INDEX: i64 = -1;
+
+#[derive(Selectable, Queryable, Insertable, Serialize, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_flagged_activity)]
+pub struct DatabaseEVMFlaggedActivity {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub block_number: i64,
+    pub kind: String,
+    pub address: String,
+    pub counterparty: String,
+    pub list: String,
+}
+
+/// Screens indexed transactions and ERC-20 transfers against an address denylist. Matches are
+/// stored in `evm_flagged_activity` and sent to the webhook.
+#[derive(Debug, Clone)]
+pub struct AddressScreener {
+    pub list: String,
+    pub denylist: Arc<HashSet<String>>,
+    pub webhook: Option<String>,
+    pub transfer: EventDecoder,
+}
+
+impl AddressScreener {
+    /// Loads the denylist from a file. Every `0x` address found in the file is added, so plain
+    /// lists, CSV files and the addresses extracted from the OFAC SDN list are all supported.
+    pub fn load(path: &String, webhook: Option<String>) -> Self {
+        let file = std::fs::read_to_string(path).expect("Unable to read screening list");
+
+        let denylist: HashSet<String> = file
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| {
+                word.len() == 42
+                    && word.starts_with("0x")
+                    && word[2..].chars().all(|c| c.is_ascii_hexdigit())
+            })
+            .map(|address| address.to_lowercase())
+            .collect();
+
+        let list = std::path::Path::new(path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(path.clone());
+
+        info!(
+            "Loaded {} addresses from the {} screening list.",
+            denylist.len(),
+            list
+        );
+
+        Self {
+            list,
+            denylist: Arc::new(denylist),
+            webhook,
+            transfer: EventDecoder::new(&[
+                "event Transfer(address indexed from, address indexed to, uint256 value)",
+            ]),
+        }
+    }
+
+    pub fn screen(
+        &self,
+        chain: &str,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Vec<DatabaseEVMFlaggedActivity> {
+        let mut flagged = Vec::new();
+
+        let mut blocks = HashMap::new();
+
+        for transaction in transactions {
+            blocks.insert(transaction.hash.clone(), transaction.block_number);
+
+            self.flag(
+                &mut flagged,
+                DatabaseEVMFlaggedActivity {
+                    hash: transaction.hash.clone(),
+                    log_index: TRANSACTION_LOG_INDEX,
+                    chain: chain.to_string(),
+                    block_number: transaction.block_number,
+                    kind: "transaction".to_string(),
+                    address: transaction.from_address.to_lowercase(),
+                    counterparty: transaction.to_address.to_lowercase(),
+                    list: self.list.clone(),
+                },
+            );
+        }
+
+        for log in logs {
+            // ERC-721 transfers share the topic but index the token id.
+            let transfer = match self.transfer.decode(log) {
+                Some(transfer) => transfer,
+                None => continue,
+            };
+
+            let (from_address, to_address) =
+                match (transfer.address("from"), transfer.address("to")) {
+                    (Some(from_address), Some(to_address)) => (from_address, to_address),
+                    _ => continue,
+                };
+
+            let block_number = match blocks.get(&log.hash) {
+                Some(block_number) => *block_number,
+                None => continue,
+            };
+
+            self.flag(
+                &mut flagged,
+                DatabaseEVMFlaggedActivity {
+                    hash: log.hash.clone(),
+                    log_index: log.log_index,
+                    chain: chain.to_string(),
+                    block_number,
+                    kind: "transfer".to_string(),
+                    address: from_address,
+                    counterparty: to_address,
+                    list: self.list.clone(),
+                },
+            );
+        }
+
+        flagged
+    }
+
+    /// Screens the indexed data, stores the flagged activity and notifies the webhook.
+    pub async fn process(
+        &self,
+        db: &EVMDatabase,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<()> {
+        let flagged = self.screen(db.chain.name, transactions, logs);
+
+        if flagged.len() == 0 {
+            return Ok(());
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(flagged.len(), DatabaseEVMFlaggedActivity::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_flagged_activity::dsl::evm_flagged_activity)
+                .values(&flagged[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        warn!(
+            "Flagged {} transactions and transfers with addresses of the {} screening list for chain {}.",
+            flagged.len(),
+            self.list,
+            db.chain.name
+        );
+
+        self.notify(&flagged).await;
+
+        Ok(())
+    }
+
+    /// Flags the activity once for each side found in the denylist, with the other side as
+    /// the counterparty.
+    fn flag(
+        &self,
+        flagged: &mut Vec<DatabaseEVMFlaggedActivity>,
+        activity: DatabaseEVMFlaggedActivity,
+    ) {
+        if self.denylist.contains(&activity.counterparty) {
+            flagged.push(DatabaseEVMFlaggedActivity {
+                address: activity.counterparty.clone(),
+                counterparty: activity.address.clone(),
+                ..activity.clone()
+            });
+        }
+
+        if self.denylist.contains(&activity.address) {
+            flagged.push(activity);
+        }
+    }
+
+    async fn notify(&self, flagged: &Vec<DatabaseEVMFlaggedActivity>) {
+        match &self.webhook {
+            Some(webhook) => {
+                let client = Client::new();
+
+                match client.post(webhook).json(flagged).send().await {
+                    Ok(_) => (),
+                    Err(err) => warn!("Unable to send screening alert: {}", err),
+                }
+            }
+            None => (),
+        }
+    }
+}
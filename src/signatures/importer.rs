@@ -0,0 +1,277 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use ethers::{
+    abi::{Contract, Param},
+    utils::keccak256,
+};
+use field_count::FieldCount;
+use log::*;
+use serde_json::Value;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    models::models::DatabaseEVMMethod,
+    schema::{evm_methods, evm_signatures},
+};
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_signatures)]
+pub struct DatabaseEVMSignature {
+    pub hash: String,
+    pub kind: String,
+    pub signature: String,
+    pub name: String,
+}
+
+/// Loads function and event signature datasets into `evm_signatures`, so selectors and topics
+/// can be named without calling a signature API. Function names are also stored in
+/// `evm_methods`. Each path can be a file or a directory, read recursively:
+///
+/// - JSON files are Sourcify metadata with the ABI in `output.abi`, or a plain ABI.
+/// - Files named after a hash hold its signatures separated by `;`, like the 4byte repository.
+/// - Other files have one signature per line, optionally after its hash and a `,`, `:` or
+///   space, like the openchain exports.
+///
+/// Signatures are hashed again and kept only when they match the given hash. The hash length
+/// tells functions from events, signatures without a hash are stored as both.
+pub struct SignatureImporter {
+    pub signatures: HashMap<(String, String), DatabaseEVMSignature>,
+}
+
+impl SignatureImporter {
+    pub fn new() -> Self {
+        Self {
+            signatures: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect();
+
+            entries.sort();
+
+            for entry in entries {
+                self.load(&entry)?;
+            }
+
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(path)?;
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if file_name.ends_with(".json") {
+            self.load_abi(&content);
+        } else if is_hash(&file_name) {
+            for signature in content.split(";") {
+                self.add(Some(&file_name), signature);
+            }
+        } else {
+            for line in content.lines() {
+                match line
+                    .trim()
+                    .split_once(|c: char| c == ',' || c == ':' || c == ' ')
+                {
+                    Some((hash, signature)) if is_hash(hash.trim()) => {
+                        self.add(Some(hash.trim()), signature)
+                    }
+                    _ => self.add(None, line),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_abi(&mut self, content: &str) {
+        let value: Value = match serde_json::from_str(content) {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+
+        let abi = match value["output"]["abi"].is_array() {
+            true => value["output"]["abi"].clone(),
+            false => value,
+        };
+
+        let contract: Contract = match serde_json::from_value(abi) {
+            Ok(contract) => contract,
+            Err(_) => return,
+        };
+
+        for function in contract.functions() {
+            let signature = get_signature(&function.name, &function.inputs);
+
+            self.add_function(&signature);
+        }
+
+        for event in contract.events() {
+            let types: Vec<String> = event
+                .inputs
+                .iter()
+                .map(|input| input.kind.to_string())
+                .collect();
+
+            self.add_event(&format!("{}({})", event.name, types.join(",")));
+        }
+    }
+
+    fn add(&mut self, hash: Option<&str>, signature: &str) {
+        let signature: String = signature.split_whitespace().collect();
+
+        if signature.len() == 0 || !signature.ends_with(")") {
+            return;
+        }
+
+        let hash = hash.map(|hash| {
+            format!(
+                "0x{}",
+                hash.trim_start_matches("0x").trim_start_matches("0X")
+            )
+            .to_lowercase()
+        });
+
+        match hash {
+            Some(hash) if hash.len() == 10 => {
+                if get_selector(&signature) == hash {
+                    self.add_function(&signature)
+                }
+            }
+            Some(hash) if hash.len() == 66 => {
+                if get_topic(&signature) == hash {
+                    self.add_event(&signature)
+                }
+            }
+            Some(_) => (),
+            None => {
+                self.add_function(&signature);
+                self.add_event(&signature);
+            }
+        }
+    }
+
+    fn add_function(&mut self, signature: &str) {
+        self.insert(get_selector(signature), "function", signature)
+    }
+
+    fn add_event(&mut self, signature: &str) {
+        self.insert(get_topic(signature), "event", signature)
+    }
+
+    fn insert(&mut self, hash: String, kind: &str, signature: &str) {
+        let name = signature.split("(").next().unwrap_or_default().to_string();
+
+        self.signatures.insert(
+            (hash.clone(), signature.to_string()),
+            DatabaseEVMSignature {
+                hash,
+                kind: kind.to_string(),
+                signature: signature.to_string(),
+                name,
+            },
+        );
+    }
+
+    pub fn store(&self, db: &EVMDatabase) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let signatures: Vec<DatabaseEVMSignature> = self.signatures.values().cloned().collect();
+
+        let chunks = get_chunks(signatures.len(), DatabaseEVMSignature::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_signatures::dsl::evm_signatures)
+                .values(&signatures[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        let methods: Vec<DatabaseEVMMethod> = signatures
+            .iter()
+            .filter(|signature| signature.kind == "function")
+            .map(|signature| DatabaseEVMMethod {
+                method: signature.hash.clone(),
+                name: signature.name.clone(),
+            })
+            .collect();
+
+        let chunks = get_chunks(methods.len(), DatabaseEVMMethod::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_methods::dsl::evm_methods)
+                .values(&methods[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn import_signatures(db: &EVMDatabase, paths: &Vec<String>) -> Result<()> {
+    let mut importer = SignatureImporter::new();
+
+    for path in paths {
+        importer.load(Path::new(path))?;
+    }
+
+    importer.store(db)?;
+
+    info!(
+        "Imported {} known signatures from {} paths.",
+        importer.signatures.len(),
+        paths.len()
+    );
+
+    Ok(())
+}
+
+/// Signatures of the known events by topic, the first one is used for colliding topics.
+pub fn get_event_names(db: &EVMDatabase, topics: &Vec<String>) -> Result<HashMap<String, String>> {
+    let mut connection = db.establish_read_connection();
+
+    let signatures = evm_signatures::table
+        .select((evm_signatures::hash, evm_signatures::signature))
+        .filter(evm_signatures::kind.eq("event"))
+        .filter(evm_signatures::hash.eq_any(topics))
+        .order(evm_signatures::signature.asc())
+        .load::<(String, String)>(&mut connection)?;
+
+    let mut names = HashMap::new();
+
+    for (hash, signature) in signatures {
+        names.entry(hash).or_insert(signature);
+    }
+
+    Ok(names)
+}
+
+fn get_signature(name: &str, inputs: &Vec<Param>) -> String {
+    let types: Vec<String> = inputs.iter().map(|input| input.kind.to_string()).collect();
+
+    format!("{}({})", name, types.join(","))
+}
+
+fn get_selector(signature: &str) -> String {
+    format!("0x{}", hex::encode(&keccak256(signature.as_bytes())[..4]))
+}
+
+fn get_topic(signature: &str) -> String {
+    format!("0x{}", hex::encode(keccak256(signature.as_bytes())))
+}
+
+fn is_hash(value: &str) -> bool {
+    let value = value.trim_start_matches("0x");
+
+    (value.len() == 8 || value.len() == 64) && value.chars().all(|c| c.is_ascii_hexdigit())
+}
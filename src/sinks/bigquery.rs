@@ -0,0 +1,465 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use prost::encoding;
+use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tonic::{
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Code, Request, Status,
+};
+
+use crate::{configs::secrets::get_secret, db::models::models::DatabaseEVMStoredOutboxEvent};
+
+use super::sink::Sink;
+
+pub mod proto {
+    tonic::include_proto!("google.cloud.bigquery.storage.v1");
+}
+
+use proto::{
+    append_rows_request, append_rows_response, big_query_write_client::BigQueryWriteClient,
+    write_stream, AppendRowsRequest, CreateWriteStreamRequest, DescriptorProto,
+    FieldDescriptorProto, Int64Value, ProtoData, ProtoRows, ProtoSchema, WriteStream,
+};
+
+pub const BIGQUERY_API_URL: &str = "https://bigquery.googleapis.com/bigquery/v2";
+
+pub const BIGQUERY_STORAGE_URL: &str = "https://bigquerystorage.googleapis.com";
+
+pub const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+// Values of the google.protobuf.FieldDescriptorProto enums.
+const LABEL_OPTIONAL: i32 = 1;
+const LABEL_REPEATED: i32 = 3;
+const TYPE_DOUBLE: i32 = 1;
+const TYPE_INT64: i32 = 3;
+const TYPE_BOOL: i32 = 8;
+const TYPE_STRING: i32 = 9;
+
+/// Column of a table, with its protobuf type and whether it is repeated.
+#[derive(Debug, Clone)]
+struct StreamColumn {
+    name: String,
+    kind: i32,
+    repeated: bool,
+}
+
+/// Committed write stream of a table. `next_offset` is the stream offset that follows the rows
+/// of the outbox ids up to `last_id`, and `in_flight` the last outbox id and amount of rows of a
+/// failed append at `next_offset`, which BigQuery may have written.
+#[derive(Debug, Clone)]
+struct TableStream {
+    name: String,
+    columns: Vec<StreamColumn>,
+    next_offset: i64,
+    last_id: i64,
+    in_flight: Option<(i64, i64)>,
+}
+
+/// Streams the outbox events into BigQuery, one table per topic named `<table_prefix><topic>`,
+/// e.g. `evm_block` and `evm_log`. Rows are the event payloads with their `outbox_id`, appended
+/// through the Storage Write API to a committed stream of each table. Each append sets the
+/// stream offset that follows the last acknowledged outbox id of the table, so a batch
+/// delivered again after a lost response is rejected with ALREADY_EXISTS instead of being
+/// written twice. Streams are created when the relay starts, so a batch already written when
+/// the relay stopped is written again by the next one. The tables must exist with the payload
+/// columns.
+///
+/// The access token is read from `BIGQUERY_ACCESS_TOKEN` or requested to the metadata server
+/// of the GCP instance, which uses its service account.
+pub struct BigQuerySink {
+    pub name: String,
+    pub project: String,
+    pub dataset: String,
+    pub table_prefix: String,
+    pub client: Client,
+    pub write_client: BigQueryWriteClient<Channel>,
+    token: Mutex<Option<(String, Instant)>>,
+    streams: Mutex<HashMap<String, TableStream>>,
+}
+
+impl BigQuerySink {
+    pub fn new(
+        name: String,
+        project: String,
+        dataset: String,
+        table_prefix: String,
+    ) -> Result<Self> {
+        let channel = Endpoint::from_static(BIGQUERY_STORAGE_URL)
+            .tls_config(ClientTlsConfig::new().domain_name("bigquerystorage.googleapis.com"))?
+            .connect_lazy();
+
+        Ok(Self {
+            name,
+            project,
+            dataset,
+            table_prefix,
+            client: Client::new(),
+            write_client: BigQueryWriteClient::new(channel),
+            token: Mutex::new(None),
+            streams: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn get_token(&self) -> Result<String> {
+        match get_secret("BIGQUERY_ACCESS_TOKEN") {
+            Some(token) if token.len() > 0 => return Ok(token),
+            _ => (),
+        }
+
+        let mut token = self.token.lock().await;
+
+        match token.as_ref() {
+            Some((access_token, expires_at)) if *expires_at > Instant::now() => {
+                return Ok(access_token.clone())
+            }
+            _ => (),
+        }
+
+        let response: Value = self
+            .client
+            .get(METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let access_token = match response["access_token"].as_str() {
+            Some(access_token) => access_token.to_string(),
+            None => bail!("Unable to get a BigQuery access token from the metadata server"),
+        };
+
+        // Renew the token a minute before it expires.
+        let expires_in = response["expires_in"].as_u64().unwrap_or(0).max(60) - 60;
+
+        *token = Some((
+            access_token.clone(),
+            Instant::now() + Duration::from_secs(expires_in),
+        ));
+
+        Ok(access_token)
+    }
+
+    /// Columns of the table, in the order of the fields of the appended rows.
+    async fn get_columns(&self, table: &str) -> Result<Vec<StreamColumn>> {
+        let url = format!(
+            "{}/projects/{}/datasets/{}/tables/{}",
+            BIGQUERY_API_URL, self.project, self.dataset, table
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .bearer_auth(self.get_token().await?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "BigQuery responded with status {} for table {}",
+                response.status(),
+                table
+            );
+        }
+
+        let body: Value = response.json().await?;
+
+        let fields = match body["schema"]["fields"].as_array() {
+            Some(fields) => fields,
+            None => bail!("BigQuery table {} has no schema", table),
+        };
+
+        let columns = fields
+            .iter()
+            .map(|field| StreamColumn {
+                name: field["name"].as_str().unwrap_or_default().to_string(),
+                kind: match field["type"].as_str().unwrap_or_default() {
+                    "INTEGER" | "INT64" => TYPE_INT64,
+                    "FLOAT" | "FLOAT64" => TYPE_DOUBLE,
+                    "BOOLEAN" | "BOOL" => TYPE_BOOL,
+                    _ => TYPE_STRING,
+                },
+                repeated: field["mode"].as_str() == Some("REPEATED"),
+            })
+            .collect();
+
+        Ok(columns)
+    }
+
+    async fn create_stream(&self, table: &str) -> Result<TableStream> {
+        let columns = self.get_columns(table).await?;
+
+        let parent = format!(
+            "projects/{}/datasets/{}/tables/{}",
+            self.project, self.dataset, table
+        );
+
+        let mut request = Request::new(CreateWriteStreamRequest {
+            parent: parent.clone(),
+            write_stream: Some(WriteStream {
+                name: String::new(),
+                r#type: write_stream::Type::Committed as i32,
+            }),
+        });
+
+        authorize(
+            &mut request,
+            &self.get_token().await?,
+            format!("parent={}", parent),
+        )?;
+
+        let stream = self
+            .write_client
+            .clone()
+            .create_write_stream(request)
+            .await?
+            .into_inner();
+
+        Ok(TableStream {
+            name: stream.name,
+            columns,
+            next_offset: 0,
+            last_id: 0,
+            in_flight: None,
+        })
+    }
+
+    /// Appends the rows with an outbox id above the last acknowledged one of the table. After a
+    /// failed append, the same rows are sent again at the same offset before the next ones, so
+    /// an ALREADY_EXISTS rejection means they were written.
+    async fn append_rows(&self, table: &str, rows: Vec<(i64, Value)>) -> Result<()> {
+        let mut streams = self.streams.lock().await;
+
+        if !streams.contains_key(table) {
+            let stream = self.create_stream(table).await?;
+
+            streams.insert(table.to_string(), stream);
+        }
+
+        let stream = streams.get_mut(table).unwrap();
+
+        loop {
+            let attempt: Vec<&(i64, Value)> = rows
+                .iter()
+                .filter(|(id, _)| *id > stream.last_id)
+                .filter(|(id, _)| match stream.in_flight {
+                    Some((in_flight_id, _)) => *id <= in_flight_id,
+                    None => true,
+                })
+                .collect();
+
+            let last_id = match attempt.last() {
+                Some((id, _)) => *id,
+                None => return Ok(()),
+            };
+
+            let mut serialized_rows = Vec::new();
+
+            for (_, payload) in attempt.iter() {
+                serialized_rows.push(encode_row(&stream.columns, payload)?);
+            }
+
+            let mut request = Request::new(futures::stream::iter(vec![AppendRowsRequest {
+                write_stream: stream.name.clone(),
+                offset: Some(Int64Value {
+                    value: stream.next_offset,
+                }),
+                rows: Some(append_rows_request::Rows::ProtoRows(ProtoData {
+                    writer_schema: Some(ProtoSchema {
+                        proto_descriptor: Some(get_descriptor(&stream.columns)),
+                    }),
+                    rows: Some(ProtoRows { serialized_rows }),
+                })),
+            }]));
+
+            authorize(
+                &mut request,
+                &self.get_token().await?,
+                format!("write_stream={}", stream.name),
+            )?;
+
+            let retried = stream.in_flight.is_some();
+
+            match self.send_append(request).await {
+                Ok(_) => (),
+                // The rows of the failed append were written.
+                Err(status) if retried && status.code() == Code::AlreadyExists => (),
+                Err(status) => {
+                    stream.in_flight = Some((last_id, attempt.len() as i64));
+
+                    bail!(
+                        "Unable to append {} rows to BigQuery table {}: {}",
+                        attempt.len(),
+                        table,
+                        status
+                    );
+                }
+            }
+
+            stream.next_offset += attempt.len() as i64;
+            stream.last_id = last_id;
+            stream.in_flight = None;
+        }
+    }
+
+    async fn send_append(
+        &self,
+        request: Request<futures::stream::Iter<std::vec::IntoIter<AppendRowsRequest>>>,
+    ) -> Result<(), Status> {
+        let mut responses = self
+            .write_client
+            .clone()
+            .append_rows(request)
+            .await?
+            .into_inner();
+
+        match responses.message().await? {
+            Some(response) => match response.response {
+                Some(append_rows_response::Response::Error(error)) => {
+                    Err(Status::new(Code::from_i32(error.code), error.message))
+                }
+                _ => Ok(()),
+            },
+            None => Err(Status::unknown(
+                "BigQuery closed the append stream without a response",
+            )),
+        }
+    }
+}
+
+fn authorize<T>(request: &mut Request<T>, token: &str, params: String) -> Result<()> {
+    request
+        .metadata_mut()
+        .insert("authorization", format!("Bearer {}", token).parse()?);
+
+    request
+        .metadata_mut()
+        .insert("x-goog-request-params", params.parse()?);
+
+    Ok(())
+}
+
+/// Descriptor of the appended rows, with a field for each column of the table.
+fn get_descriptor(columns: &Vec<StreamColumn>) -> DescriptorProto {
+    DescriptorProto {
+        name: String::from("Row"),
+        field: columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| FieldDescriptorProto {
+                name: column.name.clone(),
+                number: index as i32 + 1,
+                label: match column.repeated {
+                    true => LABEL_REPEATED,
+                    false => LABEL_OPTIONAL,
+                },
+                r#type: column.kind,
+            })
+            .collect(),
+    }
+}
+
+/// Protobuf encoding of a payload, keys without a column are ignored and objects are written
+/// as JSON strings.
+fn encode_row(columns: &Vec<StreamColumn>, payload: &Value) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+
+    for (index, column) in columns.iter().enumerate() {
+        let tag = index as u32 + 1;
+
+        match &payload[&column.name] {
+            Value::Null => (),
+            Value::Array(values) if column.repeated => {
+                for value in values {
+                    encode_value(column.kind, tag, value, &mut buffer)?;
+                }
+            }
+            value => encode_value(column.kind, tag, value, &mut buffer)?,
+        }
+    }
+
+    Ok(buffer)
+}
+
+fn encode_value(kind: i32, tag: u32, value: &Value, buffer: &mut Vec<u8>) -> Result<()> {
+    match kind {
+        TYPE_INT64 => {
+            let number = match value {
+                Value::String(value) => value.parse::<i64>().ok(),
+                value => value.as_i64(),
+            };
+
+            match number {
+                Some(number) => encoding::int64::encode(tag, &number, buffer),
+                None => bail!("Unable to write {} as an integer", value),
+            }
+        }
+        TYPE_DOUBLE => {
+            let number = match value {
+                Value::String(value) => value.parse::<f64>().ok(),
+                value => value.as_f64(),
+            };
+
+            match number {
+                Some(number) => encoding::double::encode(tag, &number, buffer),
+                None => bail!("Unable to write {} as a float", value),
+            }
+        }
+        TYPE_BOOL => match value.as_bool() {
+            Some(value) => encoding::bool::encode(tag, &value, buffer),
+            None => bail!("Unable to write {} as a boolean", value),
+        },
+        _ => {
+            let text = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+
+            encoding::string::encode(tag, &text, buffer)
+        }
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl Sink for BigQuerySink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn publish(&self, events: &Vec<DatabaseEVMStoredOutboxEvent>) -> Result<()> {
+        let mut tables: BTreeMap<String, Vec<(i64, Value)>> = BTreeMap::new();
+
+        for event in events {
+            let mut payload: Value = serde_json::from_str(&event.payload)?;
+
+            match payload.as_object_mut() {
+                Some(payload) => {
+                    payload.remove("type");
+                    payload.insert("outbox_id".to_string(), json!(event.id));
+                }
+                None => bail!("Outbox event {} payload is not an object", event.id),
+            }
+
+            tables
+                .entry(format!("{}{}", self.table_prefix, event.topic))
+                .or_default()
+                .push((event.id, payload));
+        }
+
+        for (table, rows) in tables {
+            self.append_rows(&table, rows).await?;
+        }
+
+        Ok(())
+    }
+}
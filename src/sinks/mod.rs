@@ -0,0 +1,6 @@
+#[cfg(feature = "bigquery")]
+pub mod bigquery;
+pub mod nats;
+pub mod outbox;
+pub mod sink;
+pub mod webhook;
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use async_nats::{jetstream, HeaderMap};
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::db::models::models::DatabaseEVMStoredOutboxEvent;
+
+use super::sink::Sink;
+
+/// Publishes the outbox events to NATS JetStream, on the subject configured for their topic or
+/// `<subject_prefix>.<chain>.<topic>`, e.g. `evm.ethereum.block`. A stream must capture the
+/// subjects. Messages carry the outbox id in the `Nats-Msg-Id` header, so JetStream drops the
+/// messages of a batch delivered again within its duplicate window.
+pub struct NatsSink {
+    pub name: String,
+    pub subject_prefix: String,
+    pub subjects: HashMap<String, String>,
+    pub context: jetstream::Context,
+}
+
+impl NatsSink {
+    pub async fn new(
+        name: String,
+        url: String,
+        subject_prefix: String,
+        subjects: HashMap<String, String>,
+    ) -> Result<Self> {
+        let client = match async_nats::connect(url).await {
+            Ok(client) => client,
+            Err(err) => bail!("Unable to connect to NATS: {}", err),
+        };
+
+        Ok(Self {
+            name,
+            subject_prefix,
+            subjects,
+            context: jetstream::new(client),
+        })
+    }
+
+    pub fn get_subject(&self, event: &DatabaseEVMStoredOutboxEvent) -> String {
+        match self.subjects.get(&event.topic) {
+            Some(subject) => subject.clone(),
+            None => format!("{}.{}.{}", self.subject_prefix, event.chain, event.topic),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for NatsSink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn publish(&self, events: &Vec<DatabaseEVMStoredOutboxEvent>) -> Result<()> {
+        let mut acks = Vec::new();
+
+        for event in events {
+            let mut headers = HeaderMap::new();
+
+            headers.insert(
+                "Nats-Msg-Id",
+                format!("{}-{}", self.name, event.id).as_str(),
+            );
+
+            match self
+                .context
+                .publish_with_headers(
+                    self.get_subject(event),
+                    headers,
+                    Bytes::from(event.payload.clone()),
+                )
+                .await
+            {
+                Ok(ack) => acks.push(ack),
+                Err(err) => bail!("Unable to publish outbox event {}: {}", event.id, err),
+            }
+        }
+
+        // Wait for the acknowledgements after sending the batch.
+        for ack in acks {
+            match ack.await {
+                Ok(_) => (),
+                Err(err) => bail!("JetStream didn't acknowledge an outbox event: {}", err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Subjects per topic from a comma separated list, e.g. `block=evm.blocks,log=evm.logs`.
+pub fn get_nats_subjects(subjects: &Option<String>) -> HashMap<String, String> {
+    match subjects {
+        Some(subjects) => subjects
+            .split(",")
+            .filter_map(|subject| subject.split_once("="))
+            .map(|(topic, subject)| (topic.trim().to_string(), subject.trim().to_string()))
+            .collect(),
+        None => HashMap::new(),
+    }
+}
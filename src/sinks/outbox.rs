@@ -0,0 +1,118 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::*;
+
+use crate::db::db::EVMDatabase;
+
+use super::sink::Sink;
+
+/// Time after which a missing outbox id is considered rolled back rather than not yet committed,
+/// longer than the transactions storing the indexed data take.
+const GAP_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub struct OutboxRelay {
+    pub db: EVMDatabase,
+    pub sink: Box<dyn Sink>,
+    pub batch_size: i64,
+    /// First missing id after the offset and when it was first seen missing.
+    gap: Mutex<Option<(i64, Instant)>>,
+}
+
+impl OutboxRelay {
+    pub fn new(db: EVMDatabase, sink: Box<dyn Sink>, batch_size: i64) -> Self {
+        Self {
+            db,
+            sink,
+            batch_size,
+            gap: Mutex::new(None),
+        }
+    }
+
+    /// Moves the sink offset so the next relay starts after the given outbox id.
+    pub async fn replay_from(&self, id: i64) -> Result<()> {
+        info!(
+            "Replaying outbox for sink {} from id {}.",
+            self.sink.name(),
+            id
+        );
+
+        self.db
+            .update_outbox_offset(&self.sink.name(), (id - 1).max(0))
+            .await
+    }
+
+    /// Publishes the next batch of events and returns how many were delivered. The offset is
+    /// only stored once the sink acknowledges the batch. The ids are taken when the events are
+    /// inserted but become visible when their transaction commits, so the relay stops before a
+    /// missing id until it shows up or `GAP_TIMEOUT` passes, instead of moving the offset past
+    /// events that commit late.
+    pub async fn relay(&self) -> Result<usize> {
+        let sink_name = self.sink.name();
+
+        let offset = self.db.get_outbox_offset(&sink_name).await?;
+
+        let mut events = self.db.get_outbox_events(offset, self.batch_size).await?;
+
+        let mut expected = offset + 1;
+
+        let mut contiguous = 0;
+
+        for event in events.iter() {
+            if event.id != expected {
+                if !self.is_gap_expired(expected) {
+                    break;
+                }
+
+                warn!(
+                    "Skipping the missing outbox ids {} to {} for sink {}.",
+                    expected,
+                    event.id - 1,
+                    sink_name
+                );
+            }
+
+            expected = event.id + 1;
+
+            contiguous += 1;
+        }
+
+        events.truncate(contiguous);
+
+        let last_id = match events.last() {
+            Some(event) => event.id,
+            None => return Ok(0),
+        };
+
+        self.sink.publish(&events).await?;
+
+        self.db.update_outbox_offset(&sink_name, last_id).await?;
+
+        debug!(
+            "Relayed {} outbox events to sink {} up to id {}.",
+            events.len(),
+            sink_name,
+            last_id
+        );
+
+        Ok(events.len())
+    }
+
+    /// Whether the id has been missing for longer than `GAP_TIMEOUT`, starting to track it when
+    /// it is a new gap.
+    fn is_gap_expired(&self, id: i64) -> bool {
+        let mut gap = self.gap.lock().unwrap();
+
+        match *gap {
+            Some((gap_id, since)) if gap_id == id => since.elapsed() >= GAP_TIMEOUT,
+            _ => {
+                *gap = Some((id, Instant::now()));
+
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::db::models::models::DatabaseEVMStoredOutboxEvent;
+
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Unique name used to track the sink offset in the outbox.
+    fn name(&self) -> String;
+
+    /// Publishes a batch of outbox events in order. Events keep their outbox id, so sinks that
+    /// deduplicate on it get exactly-once delivery on top of the relay at-least-once guarantee.
+    async fn publish(&self, events: &Vec<DatabaseEVMStoredOutboxEvent>) -> Result<()>;
+}
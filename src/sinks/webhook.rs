@@ -0,0 +1,54 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::db::models::models::DatabaseEVMStoredOutboxEvent;
+
+use super::sink::Sink;
+
+pub struct WebhookSink {
+    pub name: String,
+    pub url: String,
+    pub client: Client,
+}
+
+impl WebhookSink {
+    pub fn new(name: String, url: String) -> Self {
+        Self {
+            name,
+            url,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for WebhookSink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn publish(&self, events: &Vec<DatabaseEVMStoredOutboxEvent>) -> Result<()> {
+        let (first, last) = match (events.first(), events.last()) {
+            (Some(first), Some(last)) => (first.id, last.id),
+            _ => return Ok(()),
+        };
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(
+                "Idempotency-Key",
+                format!("{}-{}-{}", self.name, first, last),
+            )
+            .json(events)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            bail!("Webhook responded with status {}", response.status());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Bool, Text},
+};
+use ethers::types::{H160, U256};
+use field_count::FieldCount;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        models::models::{DatabaseEVMTransaction, DatabaseEVMTransactionLog},
+        schema::evm_stablecoin_events,
+    },
+    parsers::decoder::{DecodedLog, EventDecoder},
+};
+
+/// Stablecoin to monitor, the large transfer threshold is in raw token units, e.g.
+/// `{ "chain": "ethereum", "symbol": "USDC", "address": "0xa0b8...", "large_transfer": "1000000000000" }`.
+/// Transfers are only flagged as large when the threshold is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stablecoin {
+    pub chain: String,
+    pub symbol: String,
+    pub address: String,
+    pub large_transfer: Option<String>,
+}
+
+/// USDC, USDT and DAI on Ethereum, with transfers of 10M or more flagged as large.
+pub fn get_default_stablecoins() -> Vec<Stablecoin> {
+    let stablecoins = [
+        (
+            "USDC",
+            "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            "10000000000000",
+        ),
+        (
+            "USDT",
+            "0xdac17f958d2ee523a2206206994597c13d831ec7",
+            "10000000000000",
+        ),
+        (
+            "DAI",
+            "0x6b175474e89094c44da98b954eedeac495271d0f",
+            "10000000000000000000000000",
+        ),
+    ];
+
+    stablecoins
+        .into_iter()
+        .map(|(symbol, address, large_transfer)| Stablecoin {
+            chain: "ethereum".to_string(),
+            symbol: symbol.to_string(),
+            address: address.to_string(),
+            large_transfer: Some(large_transfer.to_string()),
+        })
+        .collect()
+}
+
+pub fn load_stablecoins(path: &Option<String>) -> Vec<Stablecoin> {
+    match path {
+        Some(path) => {
+            let file = std::fs::read_to_string(path).expect("Unable to read stablecoins");
+
+            serde_json::from_str(&file).expect("Unable to parse stablecoins")
+        }
+        None => get_default_stablecoins(),
+    }
+}
+
+#[derive(Selectable, Queryable, Insertable, Serialize, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_stablecoin_events)]
+pub struct DatabaseEVMStablecoinEvent {
+    pub hash: String,
+    pub log_index: i64,
+    pub chain: String,
+    pub block_number: i64,
+    pub token: String,
+    pub symbol: String,
+    pub kind: String,
+    pub account: String,
+    pub counterparty: Option<String>,
+    pub amount: Option<String>,
+}
+
+/// Stores the supply and compliance events of the configured stablecoins in
+/// `evm_stablecoin_events`: mints and burns, blacklisting of accounts and transfers above the
+/// large transfer threshold. The current blacklist of each token is kept in
+/// `evm_stablecoin_blacklist`. USDT mints and burns are `Issue` and `Redeem` events without a
+/// `Transfer`, credited to the sender of the transaction.
+#[derive(Debug, Clone)]
+pub struct StablecoinMonitor {
+    pub stablecoins: HashMap<String, Stablecoin>,
+    pub events: EventDecoder,
+}
+
+impl StablecoinMonitor {
+    pub fn new(chain: &str, stablecoins: Vec<Stablecoin>) -> Self {
+        let stablecoins: HashMap<String, Stablecoin> = stablecoins
+            .into_iter()
+            .filter(|stablecoin| stablecoin.chain == chain)
+            .map(|stablecoin| (stablecoin.address.to_lowercase(), stablecoin))
+            .collect();
+
+        info!("Monitoring {} stablecoins.", stablecoins.len());
+
+        Self {
+            stablecoins,
+            events: EventDecoder::new(&[
+                "event Transfer(address indexed from, address indexed to, uint256 value)",
+                "event Blacklisted(address indexed _account)",
+                "event UnBlacklisted(address indexed _account)",
+                "event AddedBlackList(address _user)",
+                "event RemovedBlackList(address _user)",
+                "event DestroyedBlackFunds(address _blackListedUser, uint256 _balance)",
+                "event Issue(uint256 amount)",
+                "event Redeem(uint256 amount)",
+            ]),
+        }
+    }
+
+    pub async fn process(
+        &self,
+        db: &EVMDatabase,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        logs: &Vec<DatabaseEVMTransactionLog>,
+    ) -> Result<Vec<DatabaseEVMStablecoinEvent>> {
+        let transactions: HashMap<&String, &DatabaseEVMTransaction> = transactions
+            .iter()
+            .map(|transaction| (&transaction.hash, transaction))
+            .collect();
+
+        let mut events = Vec::new();
+
+        for log in logs {
+            let stablecoin = match self.stablecoins.get(&log.address) {
+                Some(stablecoin) => stablecoin,
+                None => continue,
+            };
+
+            let transaction = match transactions.get(&log.hash) {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+
+            let decoded = match self.events.decode(log) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+
+            match get_stablecoin_event(&decoded, stablecoin, transaction, log) {
+                Some(event) => events.push(event),
+                None => continue,
+            }
+        }
+
+        if events.len() == 0 {
+            return Ok(events);
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(events.len(), DatabaseEVMStablecoinEvent::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_stablecoin_events::dsl::evm_stablecoin_events)
+                .values(&events[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        self.store_blacklist(&mut connection, &events)?;
+
+        info!(
+            "Inserted: stablecoin events ({}) for chain {}",
+            events.len(),
+            db.chain.name
+        );
+
+        Ok(events)
+    }
+
+    /// Keeps the last blacklisting event of each account, older events from a backfill don't
+    /// override a newer state.
+    fn store_blacklist(
+        &self,
+        connection: &mut PgConnection,
+        events: &Vec<DatabaseEVMStablecoinEvent>,
+    ) -> Result<()> {
+        let mut accounts: HashMap<(String, String, String), (bool, i64, i64)> = HashMap::new();
+
+        for event in events {
+            let blacklisted = match event.kind.as_str() {
+                "blacklist" => true,
+                "unblacklist" => false,
+                _ => continue,
+            };
+
+            let key = (
+                event.chain.clone(),
+                event.token.clone(),
+                event.account.clone(),
+            );
+
+            let position = (event.block_number, event.log_index);
+
+            match accounts.get(&key) {
+                Some((_, block_number, log_index)) if (*block_number, *log_index) > position => (),
+                _ => {
+                    accounts.insert(key, (blacklisted, event.block_number, event.log_index));
+                }
+            }
+        }
+
+        if accounts.len() == 0 {
+            return Ok(());
+        }
+
+        let mut chains = Vec::new();
+        let mut tokens = Vec::new();
+        let mut addresses = Vec::new();
+        let mut blacklisted = Vec::new();
+        let mut block_numbers = Vec::new();
+
+        for ((chain, token, account), (account_blacklisted, block_number, _)) in accounts {
+            chains.push(chain);
+            tokens.push(token);
+            addresses.push(account);
+            blacklisted.push(account_blacklisted);
+            block_numbers.push(block_number);
+        }
+
+        sql_query(
+            "INSERT INTO evm_stablecoin_blacklist (chain, token, account, blacklisted, block_number) \
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::bool[], $5::bigint[]) \
+            ON CONFLICT (chain, token, account) DO UPDATE SET \
+            blacklisted = EXCLUDED.blacklisted, block_number = EXCLUDED.block_number \
+            WHERE evm_stablecoin_blacklist.block_number <= EXCLUDED.block_number",
+        )
+        .bind::<Array<Text>, _>(chains)
+        .bind::<Array<Text>, _>(tokens)
+        .bind::<Array<Text>, _>(addresses)
+        .bind::<Array<Bool>, _>(blacklisted)
+        .bind::<Array<BigInt>, _>(block_numbers)
+        .execute(connection)?;
+
+        Ok(())
+    }
+}
+
+fn get_stablecoin_event(
+    decoded: &DecodedLog,
+    stablecoin: &Stablecoin,
+    transaction: &DatabaseEVMTransaction,
+    log: &DatabaseEVMTransactionLog,
+) -> Option<DatabaseEVMStablecoinEvent> {
+    let zero = format!("{:?}", H160::zero());
+
+    let (kind, account, counterparty, amount) = match decoded.name.as_str() {
+        "Transfer" => {
+            let from = decoded.address("from")?;
+            let to = decoded.address("to")?;
+            let value = decoded.uint("value")?;
+
+            if from == zero {
+                ("mint", to, None, Some(value))
+            } else if to == zero {
+                ("burn", from, None, Some(value))
+            } else if is_large_transfer(stablecoin, &value) {
+                ("large_transfer", from, Some(to), Some(value))
+            } else {
+                return None;
+            }
+        }
+        "Blacklisted" => ("blacklist", decoded.address("_account")?, None, None),
+        "UnBlacklisted" => ("unblacklist", decoded.address("_account")?, None, None),
+        "AddedBlackList" => ("blacklist", decoded.address("_user")?, None, None),
+        "RemovedBlackList" => ("unblacklist", decoded.address("_user")?, None, None),
+        "DestroyedBlackFunds" => (
+            "destroyed_funds",
+            decoded.address("_blackListedUser")?,
+            None,
+            decoded.uint("_balance"),
+        ),
+        "Issue" => (
+            "mint",
+            transaction.from_address.clone(),
+            None,
+            decoded.uint("amount"),
+        ),
+        "Redeem" => (
+            "burn",
+            transaction.from_address.clone(),
+            None,
+            decoded.uint("amount"),
+        ),
+        _ => return None,
+    };
+
+    Some(DatabaseEVMStablecoinEvent {
+        hash: log.hash.clone(),
+        log_index: log.log_index,
+        chain: stablecoin.chain.clone(),
+        block_number: transaction.block_number,
+        token: log.address.clone(),
+        symbol: stablecoin.symbol.clone(),
+        kind: kind.to_string(),
+        account,
+        counterparty,
+        amount,
+    })
+}
+
+fn is_large_transfer(stablecoin: &Stablecoin, value: &String) -> bool {
+    match &stablecoin.large_transfer {
+        Some(threshold) => match (U256::from_dec_str(threshold), U256::from_dec_str(value)) {
+            (Ok(threshold), Ok(value)) => value >= threshold,
+            _ => false,
+        },
+        None => false,
+    }
+}
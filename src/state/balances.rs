@@ -0,0 +1,101 @@
+use anyhow::Result;
+use ethers::types::U256;
+use serde_json::Value;
+
+use super::reducer::{Reducer, ReducerEvent, ReducerState};
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Balances of the ERC-20 holders from the `Transfer` events, keyed by `token:holder`, the same
+/// balances the holder snapshots sum from the parsed transfers but kept up to date per block.
+/// ERC-721 transfers index the token id, so they don't decode as ERC-20 transfers and are skipped.
+/// Empty balances are removed.
+pub struct Erc20BalancesReducer {
+    pub tokens: Option<Vec<String>>,
+}
+
+impl Erc20BalancesReducer {
+    pub fn new(tokens: Option<Vec<String>>) -> Self {
+        Self { tokens }
+    }
+
+    /// Keys of the balances a transfer changes, mints and burns only change one.
+    fn get_keys(&self, event: &ReducerEvent) -> Option<(Option<String>, Option<String>, U256)> {
+        let (from, to, value) = match (
+            event.log.address("from"),
+            event.log.address("to"),
+            event.log.uint("value"),
+        ) {
+            (Some(from), Some(to), Some(value)) => (from, to, value),
+            _ => return None,
+        };
+
+        let get_key = |holder: String| match holder == ZERO_ADDRESS {
+            true => None,
+            false => Some(format!("{}:{}", event.address, holder)),
+        };
+
+        let amount = U256::from_dec_str(&value).unwrap_or_default();
+
+        Some((get_key(from), get_key(to), amount))
+    }
+
+    fn add(&self, state: &mut ReducerState, key: &str, amount: U256, positive: bool) -> Result<()> {
+        let balance = match state.get(key)? {
+            Some(Value::String(balance)) => U256::from_dec_str(&balance).unwrap_or_default(),
+            _ => U256::zero(),
+        };
+
+        let balance = match positive {
+            true => balance.saturating_add(amount),
+            false => balance.saturating_sub(amount),
+        };
+
+        match balance.is_zero() {
+            true => state.remove(key),
+            false => state.set(key, Value::String(balance.to_string())),
+        }
+
+        Ok(())
+    }
+}
+
+impl Reducer for Erc20BalancesReducer {
+    fn name(&self) -> &'static str {
+        "erc20_balances"
+    }
+
+    fn events(&self) -> Vec<&'static str> {
+        vec!["event Transfer(address indexed from, address indexed to, uint256 value)"]
+    }
+
+    fn addresses(&self) -> Option<Vec<String>> {
+        self.tokens.clone()
+    }
+
+    fn keys(&self, event: &ReducerEvent) -> Vec<String> {
+        match self.get_keys(event) {
+            Some((from, to, _)) => from.into_iter().chain(to).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn reduce(&self, event: &ReducerEvent, state: &mut ReducerState) -> Result<()> {
+        let (from, to, amount) = match self.get_keys(event) {
+            Some(keys) => keys,
+            None => return Ok(()),
+        };
+
+        match from {
+            Some(from) => self.add(state, &from, amount, false)?,
+            None => (),
+        }
+
+        match to {
+            Some(to) => self.add(state, &to, amount, true)?,
+            None => (),
+        }
+
+        Ok(())
+    }
+}
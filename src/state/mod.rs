@@ -0,0 +1,3 @@
+pub mod balances;
+pub mod reducer;
+pub mod reserves;
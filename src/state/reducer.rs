@@ -0,0 +1,659 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use diesel::{
+    prelude::*,
+    sql_query,
+    sql_types::{Array, BigInt, Nullable, Text},
+};
+use log::*;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    db::{db::EVMDatabase, models::models::DatabaseEVMTransactionLog},
+    parsers::decoder::{DecodedLog, EventDecoder},
+};
+
+use super::{balances::Erc20BalancesReducer, reserves::UniswapV2ReservesReducer};
+
+/// Blocks reduced at most on each run.
+const BATCH_BLOCKS: i64 = 1000;
+
+/// Decoded log handed to a reducer, in the order of the chain.
+#[derive(Debug, Clone)]
+pub struct ReducerEvent {
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub log_index: i64,
+    pub hash: String,
+    pub address: String,
+    pub log: DecodedLog,
+}
+
+/// Folds the events of a set of signatures into keyed JSON values, e.g. the reserves of each
+/// pool from its `Sync` events or the tally of each proposal from its votes. The runner feeds the
+/// events in order, keeps the history of every value and rolls it back on reorgs, so a reducer
+/// only describes how an event changes the state.
+pub trait Reducer: Send + Sync {
+    /// Name of the state, unique among the reducers.
+    fn name(&self) -> &'static str;
+
+    /// Human readable signatures of the events, e.g. `event Sync(uint112 reserve0, uint112 reserve1)`.
+    fn events(&self) -> Vec<&'static str>;
+
+    /// Contracts whose events are reduced, any contract emitting the events when empty.
+    fn addresses(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Keys of the values the event reads, which are fetched for every event of a range at
+    /// once before reducing it.
+    fn keys(&self, _event: &ReducerEvent) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn reduce(&self, event: &ReducerEvent, state: &mut ReducerState) -> Result<()>;
+}
+
+/// Built-in reducers by name, e.g. to start them from the parser flags.
+pub fn get_reducers(names: &Vec<String>) -> Result<Vec<Box<dyn Reducer>>> {
+    let mut reducers: Vec<Box<dyn Reducer>> = Vec::new();
+
+    for name in names.iter() {
+        let reducer: Box<dyn Reducer> = match name.as_str() {
+            "erc20_balances" => Box::new(Erc20BalancesReducer::new(None)),
+            "uniswap_v2_reserves" => Box::new(UniswapV2ReservesReducer::new(None)),
+            _ => bail!("Unknown reducer {}", name),
+        };
+
+        reducers.push(reducer);
+    }
+
+    Ok(reducers)
+}
+
+#[derive(QueryableByName, Debug)]
+struct ReducerLog {
+    #[diesel(embed)]
+    log: DatabaseEVMTransactionLog,
+    #[diesel(sql_type = BigInt)]
+    block_number: i64,
+    #[diesel(sql_type = BigInt)]
+    transaction_index: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+struct StateValue {
+    #[diesel(sql_type = Text)]
+    key: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    value: Option<String>,
+}
+
+#[derive(QueryableByName, Debug)]
+struct Checkpoint {
+    #[diesel(sql_type = BigInt)]
+    block_number: i64,
+    #[diesel(sql_type = Text)]
+    block_hash: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct BlockHash {
+    #[diesel(sql_type = Text)]
+    block_hash: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct StoredBlock {
+    #[diesel(sql_type = BigInt)]
+    number: i64,
+    #[diesel(sql_type = Text)]
+    block_hash: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct BlockNumber {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    number: Option<i64>,
+}
+
+/// Values of a reducer read and written while reducing a range. The values of the keys of the
+/// events are fetched from the stored state as of the checkpoint before reducing, and a removed
+/// value is stored as null.
+pub struct ReducerState {
+    reducer: &'static str,
+    block_number: i64,
+    values: HashMap<String, Option<Value>>,
+    changes: BTreeMap<(i64, String), Option<Value>>,
+}
+
+impl ReducerState {
+    fn new(reducer: &'static str, values: HashMap<String, Option<Value>>) -> Self {
+        Self {
+            reducer,
+            block_number: 0,
+            values,
+            changes: BTreeMap::new(),
+        }
+    }
+
+    /// Fails for the keys not returned by `Reducer::keys`, which were not fetched.
+    pub fn get(&mut self, key: &str) -> Result<Option<Value>> {
+        match self.values.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => bail!("Key {} of reducer {} was not fetched", key, self.reducer),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.write(key, Some(value));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.write(key, None);
+    }
+
+    /// Only the last value of a key in each block is stored.
+    fn write(&mut self, key: &str, value: Option<Value>) {
+        self.values.insert(key.to_string(), value.clone());
+
+        self.changes
+            .insert((self.block_number, key.to_string()), value);
+    }
+}
+
+/// Runs a reducer over the indexed logs of the chain of the database, one range of blocks at a
+/// time from its checkpoint. The state is only complete when the blocks are indexed without gaps
+/// since the deployment of the contracts, like the holder snapshots.
+pub struct ReducerRunner {
+    pub db: EVMDatabase,
+    pub reducer: Box<dyn Reducer>,
+    pub decoder: EventDecoder,
+}
+
+impl ReducerRunner {
+    pub fn new(db: EVMDatabase, reducer: Box<dyn Reducer>) -> Self {
+        let decoder = EventDecoder::new(&reducer.events());
+
+        Self {
+            db,
+            reducer,
+            decoder,
+        }
+    }
+
+    /// Reduces the next range of blocks and stores the changed values with the new checkpoint in
+    /// a single transaction. When the block of the checkpoint was replaced by a reorg or a reset,
+    /// the state is rolled back by the finality depth of the chain instead. Returns the last block
+    /// reduced.
+    pub fn run(&self) -> Result<i64> {
+        let name = self.reducer.name();
+
+        let checkpoint = self.get_checkpoint()?;
+
+        let from_block = match &checkpoint {
+            Some(checkpoint) => {
+                let canonical = self.get_block_hash(checkpoint.block_number)?;
+
+                match get_rollback_block(checkpoint, &canonical, self.db.chain.finality_depth) {
+                    Some(to_block) => {
+                        warn!(
+                            "Block {} of reducer {} was replaced, rolling back to block {}.",
+                            checkpoint.block_number, name, to_block
+                        );
+
+                        self.rollback(to_block)?;
+
+                        return Ok(to_block);
+                    }
+                    None => checkpoint.block_number + 1,
+                }
+            }
+            None => match self.get_first_block()? {
+                Some(first_block) => first_block,
+                None => return Ok(0),
+            },
+        };
+
+        // Only the blocks indexed without gaps after the checkpoint are reduced, a block indexed
+        // later would otherwise be skipped.
+        let blocks = self.get_contiguous_blocks(from_block, from_block + BATCH_BLOCKS - 1)?;
+
+        let (to_block, block_hash) = match blocks.last() {
+            Some(block) => block.clone(),
+            None => return Ok(from_block - 1),
+        };
+
+        let events = self.get_events(from_block, to_block)?;
+
+        let mut state = ReducerState::new(name, self.get_values(&events)?);
+
+        for event in events.iter() {
+            state.block_number = event.block_number;
+
+            self.reducer.reduce(event, &mut state)?;
+        }
+
+        self.store(&state, to_block, &block_hash)?;
+
+        info!(
+            "Reduced {} events into {} values for reducer {} up to block {}.",
+            events.len(),
+            state.changes.len(),
+            name,
+            to_block
+        );
+
+        Ok(to_block)
+    }
+
+    /// Latest stored values of the keys of the events, in a single query. Keys without a stored
+    /// value are empty.
+    fn get_values(&self, events: &Vec<ReducerEvent>) -> Result<HashMap<String, Option<Value>>> {
+        let mut keys: Vec<String> = events
+            .iter()
+            .flat_map(|event| self.reducer.keys(event))
+            .collect();
+
+        keys.sort();
+        keys.dedup();
+
+        let mut values: HashMap<String, Option<Value>> =
+            keys.iter().map(|key| (key.clone(), None)).collect();
+
+        if keys.len() == 0 {
+            return Ok(values);
+        }
+
+        // Read from the primary, a lagging replica would miss the last values stored.
+        let mut connection = self.db.establish_connection();
+
+        let stored = sql_query(
+            "SELECT DISTINCT ON (key) key, value::TEXT AS value FROM evm_reducer_states \
+            WHERE chain = $1 AND reducer = $2 AND key = ANY($3) \
+            ORDER BY key, block_number DESC",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(self.reducer.name())
+        .bind::<Array<Text>, _>(&keys)
+        .load::<StateValue>(&mut connection)?;
+
+        for stored in stored {
+            let value = match stored.value {
+                Some(value) => Some(serde_json::from_str(&value)?),
+                None => None,
+            };
+
+            values.insert(stored.key, value);
+        }
+
+        Ok(values)
+    }
+
+    fn get_events(&self, from_block: i64, to_block: i64) -> Result<Vec<ReducerEvent>> {
+        let mut connection = self.db.establish_read_connection();
+
+        let addresses: Vec<String> = self
+            .reducer
+            .addresses()
+            .unwrap_or_default()
+            .iter()
+            .map(|address| address.to_lowercase())
+            .collect();
+
+        // Logs of backfilled transactions are located through the log transactions.
+        let rows = sql_query(
+            "SELECT l.*, COALESCE(t.block_number, b.block_number) AS block_number, \
+            COALESCE(t.transaction_index, b.transaction_index) AS transaction_index \
+            FROM evm_transactions_logs l \
+            LEFT JOIN evm_transactions t ON t.hash = l.hash \
+            LEFT JOIN evm_log_transactions b ON b.hash = l.hash \
+            WHERE COALESCE(t.chain, b.chain) = $1 \
+            AND COALESCE(t.block_number, b.block_number) BETWEEN $2 AND $3 \
+            AND l.topics[1] = ANY($4) AND NOT l.removed \
+            AND (CARDINALITY($5) = 0 OR l.address = ANY($5)) \
+            ORDER BY block_number, transaction_index, l.log_index",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<BigInt, _>(from_block)
+        .bind::<BigInt, _>(to_block)
+        .bind::<Array<Text>, _>(self.decoder.topics())
+        .bind::<Array<Text>, _>(addresses)
+        .load::<ReducerLog>(&mut connection)?;
+
+        let positions: Vec<(i64, i64)> = rows
+            .iter()
+            .map(|row| (row.block_number, row.transaction_index))
+            .collect();
+
+        let mut logs: Vec<DatabaseEVMTransactionLog> =
+            rows.into_iter().map(|row| row.log).collect();
+
+        self.db.decompress_logs(&mut logs)?;
+
+        let mut events = Vec::new();
+
+        for (log, (block_number, transaction_index)) in logs.into_iter().zip(positions) {
+            let decoded = match self.decoder.decode(&log) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+
+            events.push(ReducerEvent {
+                block_number,
+                transaction_index,
+                log_index: log.log_index,
+                hash: log.hash,
+                address: log.address,
+                log: decoded,
+            });
+        }
+
+        Ok(events)
+    }
+
+    fn store(&self, state: &ReducerState, block_number: i64, block_hash: &String) -> Result<()> {
+        let keys: Vec<String> = state.changes.keys().map(|(_, key)| key.clone()).collect();
+
+        let block_numbers: Vec<i64> = state.changes.keys().map(|(number, _)| *number).collect();
+
+        let values: Vec<Option<String>> = state
+            .changes
+            .values()
+            .map(|value| value.as_ref().map(|value| value.to_string()))
+            .collect();
+
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Unable to get the current time")
+            .as_secs() as i64;
+
+        let mut connection = self.db.establish_connection();
+
+        connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            if keys.len() > 0 {
+                sql_query(
+                    "INSERT INTO evm_reducer_states (chain, reducer, key, block_number, value) \
+                    SELECT $1, $2, s.key, s.block_number, s.value::JSONB \
+                    FROM UNNEST($3::text[], $4::bigint[], $5::text[]) AS s(key, block_number, value) \
+                    ON CONFLICT (chain, reducer, key, block_number) DO UPDATE SET value = EXCLUDED.value",
+                )
+                .bind::<Text, _>(self.db.chain.name)
+                .bind::<Text, _>(self.reducer.name())
+                .bind::<Array<Text>, _>(&keys)
+                .bind::<Array<BigInt>, _>(&block_numbers)
+                .bind::<Array<Nullable<Text>>, _>(&values)
+                .execute(connection)?;
+            }
+
+            self.store_checkpoint(connection, block_number, block_hash, updated_at)?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Deletes the values changed after the block and moves the checkpoint back to it.
+    fn rollback(&self, block_number: i64) -> Result<()> {
+        let block_hash = self.get_block_hash(block_number)?.unwrap_or_default();
+
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Unable to get the current time")
+            .as_secs() as i64;
+
+        let mut connection = self.db.establish_connection();
+
+        connection.transaction::<_, diesel::result::Error, _>(|connection| {
+            sql_query(
+                "DELETE FROM evm_reducer_states \
+                WHERE chain = $1 AND reducer = $2 AND block_number > $3",
+            )
+            .bind::<Text, _>(self.db.chain.name)
+            .bind::<Text, _>(self.reducer.name())
+            .bind::<BigInt, _>(block_number)
+            .execute(connection)?;
+
+            self.store_checkpoint(connection, block_number, &block_hash, updated_at)?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    fn store_checkpoint(
+        &self,
+        connection: &mut PgConnection,
+        block_number: i64,
+        block_hash: &String,
+        updated_at: i64,
+    ) -> QueryResult<usize> {
+        sql_query(
+            "INSERT INTO evm_reducer_checkpoints (chain, reducer, block_number, block_hash, updated_at) \
+            VALUES ($1, $2, $3, $4, $5) \
+            ON CONFLICT (chain, reducer) DO UPDATE SET block_number = EXCLUDED.block_number, \
+            block_hash = EXCLUDED.block_hash, updated_at = EXCLUDED.updated_at",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(self.reducer.name())
+        .bind::<BigInt, _>(block_number)
+        .bind::<Text, _>(block_hash)
+        .bind::<BigInt, _>(updated_at)
+        .execute(connection)
+    }
+
+    fn get_checkpoint(&self) -> Result<Option<Checkpoint>> {
+        let mut connection = self.db.establish_connection();
+
+        let checkpoint = sql_query(
+            "SELECT block_number, block_hash FROM evm_reducer_checkpoints \
+            WHERE chain = $1 AND reducer = $2",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<Text, _>(self.reducer.name())
+        .load::<Checkpoint>(&mut connection)?;
+
+        Ok(checkpoint.into_iter().next())
+    }
+
+    fn get_block_hash(&self, block_number: i64) -> Result<Option<String>> {
+        let mut connection = self.db.establish_connection();
+
+        let block = sql_query("SELECT block_hash FROM evm_blocks WHERE chain = $1 AND number = $2")
+            .bind::<Text, _>(self.db.chain.name)
+            .bind::<BigInt, _>(block_number)
+            .load::<BlockHash>(&mut connection)?;
+
+        Ok(block.into_iter().next().map(|block| block.block_hash))
+    }
+
+    fn get_first_block(&self) -> Result<Option<i64>> {
+        self.get_block_number("SELECT MIN(number) AS number FROM evm_blocks WHERE chain = $1")
+    }
+
+    fn get_contiguous_blocks(&self, from_block: i64, to_block: i64) -> Result<Vec<(i64, String)>> {
+        let mut connection = self.db.establish_connection();
+
+        let blocks = sql_query(
+            "SELECT number, block_hash FROM evm_blocks \
+            WHERE chain = $1 AND number BETWEEN $2 AND $3 ORDER BY number",
+        )
+        .bind::<Text, _>(self.db.chain.name)
+        .bind::<BigInt, _>(from_block)
+        .bind::<BigInt, _>(to_block)
+        .load::<StoredBlock>(&mut connection)?;
+
+        Ok(get_contiguous_blocks(
+            from_block,
+            blocks
+                .into_iter()
+                .map(|block| (block.number, block.block_hash))
+                .collect(),
+        ))
+    }
+
+    fn get_block_number(&self, query: &str) -> Result<Option<i64>> {
+        let mut connection = self.db.establish_connection();
+
+        let block = sql_query(query)
+            .bind::<Text, _>(self.db.chain.name)
+            .load::<BlockNumber>(&mut connection)?;
+
+        Ok(block.into_iter().next().and_then(|block| block.number))
+    }
+}
+
+/// Block to roll the state back to when the block of the checkpoint is no longer the canonical
+/// one, by the finality depth of the chain. Checkpoints without a hash are never rolled back.
+fn get_rollback_block(
+    checkpoint: &Checkpoint,
+    canonical: &Option<String>,
+    finality_depth: i64,
+) -> Option<i64> {
+    if checkpoint.block_hash.is_empty() || canonical.as_ref() == Some(&checkpoint.block_hash) {
+        return None;
+    }
+
+    Some((checkpoint.block_number - finality_depth).max(0))
+}
+
+/// Blocks of a range up to the first missing block, like the gRPC streams.
+fn get_contiguous_blocks(from_block: i64, blocks: Vec<(i64, String)>) -> Vec<(i64, String)> {
+    let mut contiguous = Vec::new();
+
+    for (number, block_hash) in blocks {
+        if number != from_block + contiguous.len() as i64 {
+            break;
+        }
+
+        contiguous.push((number, block_hash));
+    }
+
+    contiguous
+}
+
+#[derive(QueryableByName, Debug, Clone, Serialize)]
+pub struct ReducedValue {
+    #[diesel(sql_type = Text)]
+    pub key: String,
+    #[diesel(sql_type = BigInt)]
+    pub block_number: i64,
+    #[diesel(sql_type = Text)]
+    pub value: String,
+}
+
+/// Values of a reducer as of a block, the latest ones without a block, optionally of the keys
+/// starting with a prefix. Removed values are left out.
+pub fn get_reduced_values(
+    db: &EVMDatabase,
+    reducer: &String,
+    prefix: &Option<String>,
+    block: Option<i64>,
+    limit: i64,
+) -> Result<Vec<ReducedValue>> {
+    let mut connection = db.establish_read_connection();
+
+    let values = sql_query(
+        "SELECT key, block_number, value::TEXT AS value FROM ( \
+            SELECT DISTINCT ON (key) key, block_number, value FROM evm_reducer_states \
+            WHERE chain = $1 AND reducer = $2 AND key LIKE $3 AND block_number <= $4 \
+            ORDER BY key, block_number DESC \
+        ) s WHERE value IS NOT NULL ORDER BY key LIMIT $5",
+    )
+    .bind::<Text, _>(db.chain.name)
+    .bind::<Text, _>(reducer)
+    .bind::<Text, _>(format!("{}%", prefix.clone().unwrap_or_default()))
+    .bind::<BigInt, _>(block.unwrap_or(i64::MAX))
+    .bind::<BigInt, _>(limit)
+    .load::<ReducedValue>(&mut connection)?;
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(block_number: i64, block_hash: &str) -> Checkpoint {
+        Checkpoint {
+            block_number,
+            block_hash: block_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn rolls_back_replaced_checkpoints() {
+        let stored = checkpoint(100, "0xaa");
+
+        assert_eq!(
+            get_rollback_block(&stored, &Some("0xaa".to_string()), 64),
+            None
+        );
+
+        assert_eq!(
+            get_rollback_block(&stored, &Some("0xbb".to_string()), 64),
+            Some(36)
+        );
+
+        // The block was deleted by a reset.
+        assert_eq!(get_rollback_block(&stored, &None, 64), Some(36));
+
+        assert_eq!(
+            get_rollback_block(&checkpoint(10, "0xaa"), &Some("0xbb".to_string()), 64),
+            Some(0)
+        );
+
+        assert_eq!(get_rollback_block(&checkpoint(100, ""), &None, 64), None);
+    }
+
+    #[test]
+    fn stops_at_the_first_missing_block() {
+        let blocks = vec![
+            (10, "0x0a".to_string()),
+            (11, "0x0b".to_string()),
+            (13, "0x0d".to_string()),
+        ];
+
+        assert_eq!(
+            get_contiguous_blocks(10, blocks.clone()),
+            vec![(10, "0x0a".to_string()), (11, "0x0b".to_string())]
+        );
+
+        assert_eq!(get_contiguous_blocks(9, blocks), Vec::new());
+    }
+
+    #[test]
+    fn keeps_the_last_change_of_each_block() {
+        let values = HashMap::from([("a".to_string(), Some(Value::from(1)))]);
+
+        let mut state = ReducerState::new("test", values);
+
+        assert_eq!(state.get("a").unwrap(), Some(Value::from(1)));
+
+        assert!(state.get("b").is_err());
+
+        state.block_number = 5;
+        state.set("a", Value::from(2));
+        state.set("a", Value::from(3));
+
+        state.block_number = 6;
+        state.remove("a");
+
+        assert_eq!(state.get("a").unwrap(), None);
+
+        assert_eq!(
+            state.changes.into_iter().collect::<Vec<_>>(),
+            vec![
+                ((5, "a".to_string()), Some(Value::from(3))),
+                ((6, "a".to_string()), None),
+            ]
+        );
+    }
+}
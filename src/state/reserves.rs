@@ -0,0 +1,42 @@
+use anyhow::Result;
+use serde_json::json;
+
+use super::reducer::{Reducer, ReducerEvent, ReducerState};
+
+/// Reserves of the Uniswap v2 pools and forks keyed by pool, from the `Sync` event each pool
+/// emits after every change of its balances.
+pub struct UniswapV2ReservesReducer {
+    pub pools: Option<Vec<String>>,
+}
+
+impl UniswapV2ReservesReducer {
+    pub fn new(pools: Option<Vec<String>>) -> Self {
+        Self { pools }
+    }
+}
+
+impl Reducer for UniswapV2ReservesReducer {
+    fn name(&self) -> &'static str {
+        "uniswap_v2_reserves"
+    }
+
+    fn events(&self) -> Vec<&'static str> {
+        vec!["event Sync(uint112 reserve0, uint112 reserve1)"]
+    }
+
+    fn addresses(&self) -> Option<Vec<String>> {
+        self.pools.clone()
+    }
+
+    fn reduce(&self, event: &ReducerEvent, state: &mut ReducerState) -> Result<()> {
+        match (event.log.uint("reserve0"), event.log.uint("reserve1")) {
+            (Some(reserve0), Some(reserve1)) => state.set(
+                &event.address,
+                json!({ "reserve0": reserve0, "reserve1": reserve1 }),
+            ),
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod proofs;
+pub mod watcher;
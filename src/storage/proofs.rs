@@ -0,0 +1,170 @@
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        schema::evm_state_proofs,
+    },
+    rpc::rpc::EVMRpc,
+    utils::{format_bytes, format_hash, format_number},
+};
+
+/// Account to prove with its storage slots, e.g. a bridge contract
+/// `{ "name": "bridge", "account": "0x8315...", "slots": ["0x0"], "interval": 100 }`.
+/// The proofs are fetched every `interval` blocks, or at each new block when unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofAccount {
+    pub name: String,
+    pub chain: Option<String>,
+    pub account: String,
+    #[serde(default)]
+    pub slots: Vec<String>,
+    pub interval: Option<i64>,
+}
+
+pub fn load_proof_accounts(path: &String) -> Vec<ProofAccount> {
+    let file = std::fs::read_to_string(path).expect("Unable to read proof accounts");
+
+    serde_json::from_str(&file).expect("Unable to parse proof accounts")
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_state_proofs)]
+pub struct DatabaseEVMStateProof {
+    pub chain: String,
+    pub account: String,
+    pub block_number: i64,
+    pub name: String,
+    pub balance: String,
+    pub nonce: i64,
+    pub code_hash: String,
+    pub storage_hash: String,
+    /// RLP encoded nodes from the state root to the account.
+    pub account_proof: Vec<Option<String>>,
+    /// Key, value and RLP encoded nodes from the storage root of each slot, e.g.
+    /// `[{ "key": "0x0", "value": "1", "proof": ["0xf8..."] }]`.
+    pub storage_proofs: serde_json::Value,
+}
+
+/// Archives the `eth_getProof` results of the configured accounts as new blocks arrive in
+/// `evm_state_proofs`, so light clients and cross-chain verifiers can check the state of the
+/// accounts against the state root of the stored blocks.
+#[derive(Debug, Clone)]
+pub struct ProofArchiver {
+    pub accounts: Vec<ProofAccount>,
+}
+
+impl ProofArchiver {
+    pub fn new(chain: &str, accounts: Vec<ProofAccount>) -> Self {
+        let accounts: Vec<ProofAccount> = accounts
+            .into_iter()
+            .filter(|account| match &account.chain {
+                Some(account_chain) => account_chain == chain,
+                None => true,
+            })
+            .map(|account| ProofAccount {
+                account: account.account.to_lowercase(),
+                slots: account
+                    .slots
+                    .iter()
+                    .map(|slot| slot.to_lowercase())
+                    .collect(),
+                ..account
+            })
+            .collect();
+
+        info!(
+            "Archiving the proofs of {} accounts for chain {}.",
+            accounts.len(),
+            chain
+        );
+
+        Self { accounts }
+    }
+
+    pub fn get_due_accounts(&self, block_number: i64) -> Vec<&ProofAccount> {
+        self.accounts
+            .iter()
+            .filter(|account| match account.interval {
+                Some(interval) if interval > 1 => block_number % interval == 0,
+                _ => true,
+            })
+            .collect()
+    }
+
+    pub async fn process(&self, rpc: &EVMRpc, db: &EVMDatabase, block_number: i64) -> Result<()> {
+        let accounts = self.get_due_accounts(block_number);
+
+        if accounts.len() == 0 {
+            return Ok(());
+        }
+
+        let mut work = vec![];
+
+        for account in accounts.iter() {
+            work.push(rpc.get_proof(&account.account, &account.slots, block_number))
+        }
+
+        let results = join_all(work).await;
+
+        let mut proofs = Vec::new();
+
+        for (account, result) in accounts.into_iter().zip(results) {
+            match result? {
+                Some(proof) => proofs.push(DatabaseEVMStateProof {
+                    chain: db.chain.name.to_string(),
+                    account: account.account.clone(),
+                    block_number,
+                    name: account.name.clone(),
+                    balance: format_number(proof.balance),
+                    nonce: proof.nonce.as_u64() as i64,
+                    code_hash: format_hash(proof.code_hash),
+                    storage_hash: format_hash(proof.storage_hash),
+                    account_proof: proof
+                        .account_proof
+                        .iter()
+                        .map(|node| Some(format_bytes(node)))
+                        .collect(),
+                    storage_proofs: proof
+                        .storage_proof
+                        .iter()
+                        .map(|storage| {
+                            json!({
+                                "key": format_hash(storage.key),
+                                "value": format_number(storage.value),
+                                "proof": storage
+                                    .proof
+                                    .iter()
+                                    .map(format_bytes)
+                                    .collect::<Vec<String>>(),
+                            })
+                        })
+                        .collect(),
+                }),
+                None => warn!(
+                    "Unable to fetch the proof of {} at block {}.",
+                    account.name, block_number
+                ),
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(proofs.len(), DatabaseEVMStateProof::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_state_proofs::dsl::evm_state_proofs)
+                .values(&proofs[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        schema::evm_storage_values,
+    },
+    rpc::rpc::EVMRpc,
+    utils::format_hash,
+};
+
+/// Storage slot to read from a contract, e.g. the EIP-1967 implementation of a proxy
+/// `{ "name": "usdc_implementation", "contract": "0xa0b8...", "slot": "0x3608...", "interval": 1 }`.
+/// The slot is read every `interval` blocks, or at each new block when unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageSlot {
+    pub name: String,
+    pub chain: Option<String>,
+    pub contract: String,
+    pub slot: String,
+    pub interval: Option<i64>,
+}
+
+pub fn load_storage_slots(path: &String) -> Vec<StorageSlot> {
+    let file = std::fs::read_to_string(path).expect("Unable to read storage slots");
+
+    serde_json::from_str(&file).expect("Unable to parse storage slots")
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_storage_values)]
+pub struct DatabaseEVMStorageValue {
+    pub chain: String,
+    pub contract: String,
+    pub slot: String,
+    pub block_number: i64,
+    pub name: String,
+    pub value: String,
+}
+
+/// Reads the configured storage slots with `eth_getStorageAt` as new blocks arrive and stores
+/// their values in `evm_storage_values`, for state that isn't emitted as events.
+#[derive(Debug, Clone)]
+pub struct StorageWatcher {
+    pub slots: Vec<StorageSlot>,
+}
+
+impl StorageWatcher {
+    pub fn new(chain: &str, slots: Vec<StorageSlot>) -> Self {
+        let slots: Vec<StorageSlot> = slots
+            .into_iter()
+            .filter(|slot| match &slot.chain {
+                Some(slot_chain) => slot_chain == chain,
+                None => true,
+            })
+            .map(|slot| StorageSlot {
+                contract: slot.contract.to_lowercase(),
+                slot: slot.slot.to_lowercase(),
+                ..slot
+            })
+            .collect();
+
+        info!(
+            "Watching {} storage slots for chain {}.",
+            slots.len(),
+            chain
+        );
+
+        Self { slots }
+    }
+
+    pub fn get_due_slots(&self, block_number: i64) -> Vec<&StorageSlot> {
+        self.slots
+            .iter()
+            .filter(|slot| match slot.interval {
+                Some(interval) if interval > 1 => block_number % interval == 0,
+                _ => true,
+            })
+            .collect()
+    }
+
+    pub async fn process(&self, rpc: &EVMRpc, db: &EVMDatabase, block_number: i64) -> Result<()> {
+        let slots = self.get_due_slots(block_number);
+
+        if slots.len() == 0 {
+            return Ok(());
+        }
+
+        let mut work = vec![];
+
+        for slot in slots.iter() {
+            work.push(rpc.get_storage_at(&slot.contract, &slot.slot, block_number))
+        }
+
+        let results = join_all(work).await;
+
+        let mut values = Vec::new();
+
+        for (slot, result) in slots.into_iter().zip(results) {
+            match result? {
+                Some(value) => values.push(DatabaseEVMStorageValue {
+                    chain: db.chain.name.to_string(),
+                    contract: slot.contract.clone(),
+                    slot: slot.slot.clone(),
+                    block_number,
+                    name: slot.name.clone(),
+                    value: format_hash(value),
+                }),
+                None => warn!(
+                    "Unable to read storage slot {} of {} at block {}.",
+                    slot.name, slot.contract, block_number
+                ),
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(values.len(), DatabaseEVMStorageValue::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_storage_values::dsl::evm_storage_values)
+                .values(&values[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+}
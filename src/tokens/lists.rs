@@ -0,0 +1,263 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, Result};
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{BigInt, Nullable, Text},
+};
+use field_count::FieldCount;
+use log::*;
+use serde_json::Value;
+
+use crate::{
+    chains::chains::get_chain_by_id,
+    db::{
+        db::{get_chunks, EVMDatabase},
+        schema::evm_erc20_tokens,
+        upsert::merged,
+    },
+    parsers::{
+        erc20_tokens_parser::DatabaseEVMErc20Token, erc20_transfers_parser::store_decimal_values,
+    },
+};
+
+/// CoinGecko platform ids of the supported chains.
+pub const COINGECKO_PLATFORMS: [(&str, &str); 12] = [
+    ("ethereum", "ethereum"),
+    ("polygon-pos", "polygon"),
+    ("fantom", "fantom"),
+    ("binance-smart-chain", "bsc"),
+    ("xdai", "gnosis"),
+    ("optimistic-ethereum", "optimism"),
+    ("arbitrum-one", "arbitrum"),
+    ("arbitrum-nova", "arbitrum-nova"),
+    ("moonbeam", "moonbeam"),
+    ("avalanche", "avalanche"),
+    ("bittorrent", "bittorrent"),
+    ("celo", "celo"),
+];
+
+/// Loads token lists into `evm_erc20_tokens`, so the erc20 tokens parser doesn't call the
+/// tokens already listed and the API gets their logos. Each source can be a URL, a file or a
+/// directory, read recursively:
+///
+/// - Objects with a `tokens` array are Uniswap token lists, which also covers the lists
+///   published by CoinGecko.
+/// - Arrays are CoinGecko coins exports with their `platforms` or `detail_platforms`, only the
+///   latter have the decimals.
+///
+/// Tokens of unsupported chains are skipped. The name, symbol and decimals already read from
+/// the chain are kept, while the logos are replaced by the imported ones.
+pub struct TokenListImporter {
+    pub tokens: HashMap<(String, String), DatabaseEVMErc20Token>,
+}
+
+impl TokenListImporter {
+    pub fn new() -> Self {
+        Self {
+            tokens: HashMap::new(),
+        }
+    }
+
+    pub async fn load(&mut self, source: &str) -> Result<()> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            let content = reqwest::get(source).await?.text().await?;
+
+            return self.load_list(&content);
+        }
+
+        self.load_path(Path::new(source))
+    }
+
+    fn load_path(&mut self, path: &Path) -> Result<()> {
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect();
+
+            entries.sort();
+
+            for entry in entries {
+                self.load_path(&entry)?;
+            }
+
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(path)?;
+
+        self.load_list(&content)
+    }
+
+    fn load_list(&mut self, content: &str) -> Result<()> {
+        let value: Value = match serde_json::from_str(content) {
+            Ok(value) => value,
+            Err(err) => bail!("Unable to parse the token list: {}", err),
+        };
+
+        match &value {
+            Value::Object(list) => match list.get("tokens") {
+                Some(Value::Array(tokens)) => {
+                    for token in tokens {
+                        self.add_listed_token(token);
+                    }
+                }
+                _ => bail!("Unknown token list format"),
+            },
+            Value::Array(coins) => {
+                for coin in coins {
+                    self.add_coin(coin);
+                }
+            }
+            _ => bail!("Unknown token list format"),
+        }
+
+        Ok(())
+    }
+
+    fn add_listed_token(&mut self, token: &Value) {
+        let chain = match token["chainId"].as_i64().and_then(get_chain_by_id) {
+            Some(chain) => chain,
+            None => return,
+        };
+
+        self.add(
+            chain.name,
+            &token["address"],
+            &token["name"],
+            &token["symbol"],
+            token["decimals"].as_i64(),
+            &token["logoURI"],
+        );
+    }
+
+    fn add_coin(&mut self, coin: &Value) {
+        let logo = match &coin["image"] {
+            Value::Object(images) => images
+                .get("large")
+                .or(images.get("small"))
+                .or(images.get("thumb"))
+                .cloned()
+                .unwrap_or_default(),
+            image => image.clone(),
+        };
+
+        let symbol = match coin["symbol"].as_str() {
+            Some(symbol) => Value::String(symbol.to_uppercase()),
+            None => Value::Null,
+        };
+
+        for (platform, chain) in COINGECKO_PLATFORMS {
+            let details = &coin["detail_platforms"][platform];
+
+            let (address, decimals) = match details.is_object() {
+                true => (
+                    &details["contract_address"],
+                    details["decimal_place"].as_i64(),
+                ),
+                false => (&coin["platforms"][platform], None),
+            };
+
+            self.add(chain, address, &coin["name"], &symbol, decimals, &logo);
+        }
+    }
+
+    fn add(
+        &mut self,
+        chain: &str,
+        address: &Value,
+        name: &Value,
+        symbol: &Value,
+        decimals: Option<i64>,
+        logo: &Value,
+    ) {
+        let address = match address.as_str() {
+            Some(address) if address.starts_with("0x") && address.len() == 42 => {
+                address.to_lowercase()
+            }
+            _ => return,
+        };
+
+        let token = DatabaseEVMErc20Token {
+            address: address.clone(),
+            chain: chain.to_string(),
+            name: name.as_str().map(|name| name.to_string()),
+            decimals,
+            symbol: symbol.as_str().map(|symbol| symbol.to_string()),
+            spam_score: None,
+            spam_reasons: None,
+            logo: logo
+                .as_str()
+                .filter(|logo| !logo.is_empty())
+                .map(|logo| logo.to_string()),
+        };
+
+        // The first list with a token wins, the next ones only fill its missing fields.
+        match self.tokens.get_mut(&(chain.to_string(), address.clone())) {
+            Some(listed) => {
+                listed.name = listed.name.take().or(token.name);
+                listed.decimals = listed.decimals.or(token.decimals);
+                listed.symbol = listed.symbol.take().or(token.symbol);
+                listed.logo = listed.logo.take().or(token.logo);
+            }
+            None => {
+                self.tokens.insert((chain.to_string(), address), token);
+            }
+        }
+    }
+
+    pub fn store(&self, db: &EVMDatabase) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let tokens: Vec<DatabaseEVMErc20Token> = self.tokens.values().cloned().collect();
+
+        let chunks = get_chunks(tokens.len(), DatabaseEVMErc20Token::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_erc20_tokens::dsl::evm_erc20_tokens)
+                .values(&tokens[start..end])
+                .on_conflict((evm_erc20_tokens::address, evm_erc20_tokens::chain))
+                .do_update()
+                .set((
+                    evm_erc20_tokens::name.eq(sql::<Nullable<Text>>(
+                        "COALESCE(evm_erc20_tokens.name, EXCLUDED.name)",
+                    )),
+                    evm_erc20_tokens::decimals.eq(sql::<Nullable<BigInt>>(
+                        "COALESCE(evm_erc20_tokens.decimals, EXCLUDED.decimals)",
+                    )),
+                    evm_erc20_tokens::symbol.eq(sql::<Nullable<Text>>(
+                        "COALESCE(evm_erc20_tokens.symbol, EXCLUDED.symbol)",
+                    )),
+                    evm_erc20_tokens::logo.eq(merged::<Nullable<Text>>("evm_erc20_tokens", "logo")),
+                ))
+                .execute(&mut connection)?;
+        }
+
+        // The transfers parsed before the import get their decimal values now.
+        let addresses: Vec<String> = tokens.iter().map(|token| token.address.clone()).collect();
+
+        store_decimal_values(&mut connection, None, Some(&addresses))?;
+
+        Ok(())
+    }
+}
+
+pub async fn import_token_lists(db: &EVMDatabase, sources: &Vec<String>) -> Result<()> {
+    let mut importer = TokenListImporter::new();
+
+    for source in sources {
+        importer.load(source).await?;
+    }
+
+    importer.store(db)?;
+
+    info!(
+        "Imported {} listed tokens from {} token lists.",
+        importer.tokens.len(),
+        sources.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+pub mod lists;
+pub mod nonstandard;
+pub mod prices;
@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use diesel::{prelude::*, upsert::excluded};
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::balances::BalanceAuditReport,
+    db::{
+        db::{get_chunks, EVMDatabase},
+        schema::{evm_nonstandard_tokens, evm_sampled_balances},
+    },
+    rpc::rpc::EVMRpc,
+};
+
+/// Balances change without transfers, e.g. stETH or AMPL.
+pub const REBASING: &str = "rebasing";
+
+/// Transfers move more than the recipient receives, e.g. PAXG.
+pub const FEE_ON_TRANSFER: &str = "fee_on_transfer";
+
+/// Marked by the balances audit.
+pub const AUDIT_SOURCE: &str = "audit";
+
+/// Marked from the known tokens list.
+pub const LIST_SOURCE: &str = "list";
+
+/// Least holders of a token to audit before marking it, so a single missed transfer doesn't.
+const MIN_AUDITED_HOLDERS: i64 = 3;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NonstandardToken {
+    pub chain: String,
+    pub address: String,
+    pub kind: String,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_nonstandard_tokens)]
+pub struct DatabaseEVMNonstandardToken {
+    pub chain: String,
+    pub address: String,
+    pub kind: String,
+    pub source: String,
+    pub marked_at: i64,
+}
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_sampled_balances)]
+pub struct DatabaseEVMSampledBalance {
+    pub chain: String,
+    pub token: String,
+    pub holder: String,
+    pub block_number: i64,
+    pub balance: String,
+}
+
+/// Lido stETH, Ampleforth, Aave v2 aUSDC and Paxos Gold.
+pub fn get_default_nonstandard_tokens() -> Vec<NonstandardToken> {
+    let tokens = [
+        (
+            "ethereum",
+            "0xae7ab96520de3a18e5e111b5eaab095312d7fe84",
+            REBASING,
+        ),
+        (
+            "ethereum",
+            "0xd46ba6d942050d489dbd938a2c909a5d5039a161",
+            REBASING,
+        ),
+        (
+            "ethereum",
+            "0xbcca60bb61934080951369a648fb03df4f96263c",
+            REBASING,
+        ),
+        (
+            "ethereum",
+            "0x45804880de22913dafe09f4980848ece6ecbaf78",
+            FEE_ON_TRANSFER,
+        ),
+    ];
+
+    tokens
+        .into_iter()
+        .map(|(chain, address, kind)| NonstandardToken {
+            chain: chain.to_string(),
+            address: address.to_string(),
+            kind: kind.to_string(),
+        })
+        .collect()
+}
+
+pub fn load_nonstandard_tokens(path: &Option<String>) -> Vec<NonstandardToken> {
+    match path {
+        Some(path) => {
+            let file = std::fs::read_to_string(path).expect("Unable to read nonstandard tokens");
+
+            serde_json::from_str(&file).expect("Unable to parse nonstandard tokens")
+        }
+        None => get_default_nonstandard_tokens(),
+    }
+}
+
+/// Holders whose balances of the nonstandard tokens are sampled, as a JSON list of addresses.
+pub fn load_watched_holders(path: &String) -> Vec<String> {
+    let file = std::fs::read_to_string(path).expect("Unable to read watched holders");
+
+    let holders: Vec<String> =
+        serde_json::from_str(&file).expect("Unable to parse watched holders");
+
+    holders.iter().map(|holder| holder.to_lowercase()).collect()
+}
+
+/// Tokens of an audit whose every sampled holder has a discrepancy in the same direction, more
+/// than the transfers report for rebasing tokens and less for fee on transfer ones. Mixed
+/// discrepancies point to missed transfers instead, so those tokens are not marked.
+pub fn get_audited_tokens(report: &BalanceAuditReport) -> Vec<NonstandardToken> {
+    let mut discrepancies: HashMap<&String, (i64, i64)> = HashMap::new();
+
+    for discrepancy in report.discrepancies.iter() {
+        let (more, less) = discrepancies.entry(&discrepancy.token).or_default();
+
+        match discrepancy.difference.starts_with('-') {
+            true => *less += 1,
+            false => *more += 1,
+        }
+    }
+
+    let mut tokens = Vec::new();
+
+    for (token, checked) in report.tokens_checked.iter() {
+        if *checked < MIN_AUDITED_HOLDERS {
+            continue;
+        }
+
+        let kind = match discrepancies.get(token) {
+            Some((more, 0)) if more == checked => REBASING,
+            Some((0, less)) if less == checked => FEE_ON_TRANSFER,
+            _ => continue,
+        };
+
+        tokens.push(NonstandardToken {
+            chain: report.chain.clone(),
+            address: token.clone(),
+            kind: kind.to_string(),
+        });
+    }
+
+    tokens
+}
+
+/// Marks the tokens of the chain of the database, returns the amount of tokens marked.
+pub fn store_nonstandard_tokens(
+    db: &EVMDatabase,
+    tokens: &Vec<NonstandardToken>,
+    source: &str,
+) -> Result<usize> {
+    let marked_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_secs() as i64;
+
+    let tokens: Vec<DatabaseEVMNonstandardToken> = tokens
+        .iter()
+        .filter(|token| token.chain == db.chain.name)
+        .map(|token| DatabaseEVMNonstandardToken {
+            chain: token.chain.clone(),
+            address: token.address.to_lowercase(),
+            kind: token.kind.clone(),
+            source: source.to_string(),
+            marked_at,
+        })
+        .collect();
+
+    let mut connection = db.establish_connection();
+
+    let chunks = get_chunks(tokens.len(), DatabaseEVMNonstandardToken::field_count());
+
+    for (start, end) in chunks {
+        diesel::insert_into(evm_nonstandard_tokens::dsl::evm_nonstandard_tokens)
+            .values(&tokens[start..end])
+            .on_conflict((
+                evm_nonstandard_tokens::chain,
+                evm_nonstandard_tokens::address,
+            ))
+            .do_update()
+            .set((
+                evm_nonstandard_tokens::kind.eq(excluded(evm_nonstandard_tokens::kind)),
+                evm_nonstandard_tokens::source.eq(excluded(evm_nonstandard_tokens::source)),
+                evm_nonstandard_tokens::marked_at.eq(excluded(evm_nonstandard_tokens::marked_at)),
+            ))
+            .execute(&mut connection)?;
+    }
+
+    Ok(tokens.len())
+}
+
+/// Kind of the token when its balances can't be derived from its transfers.
+pub fn get_nonstandard_kind(db: &EVMDatabase, token: &String) -> Result<Option<String>> {
+    let mut connection = db.establish_read_connection();
+
+    let kind = evm_nonstandard_tokens::table
+        .select(evm_nonstandard_tokens::kind)
+        .filter(evm_nonstandard_tokens::chain.eq(db.chain.name))
+        .filter(evm_nonstandard_tokens::address.eq(token.to_lowercase()))
+        .first::<String>(&mut connection)
+        .optional()?;
+
+    Ok(kind)
+}
+
+/// Samples the `balanceOf` of the watched holders for the nonstandard tokens every `interval`
+/// blocks, since accumulating their transfers gives wrong balances. The holder snapshots of
+/// those tokens are built from the samples.
+#[derive(Debug, Clone)]
+pub struct BalanceSampler {
+    pub holders: Vec<String>,
+    pub interval: i64,
+}
+
+impl BalanceSampler {
+    pub fn new(holders: Vec<String>, interval: i64) -> Self {
+        info!(
+            "Sampling the balances of {} holders every {} blocks.",
+            holders.len(),
+            interval
+        );
+
+        Self { holders, interval }
+    }
+
+    pub async fn process(&self, rpc: &EVMRpc, db: &EVMDatabase, block_number: i64) -> Result<()> {
+        if self.interval > 1 && block_number % self.interval != 0 {
+            return Ok(());
+        }
+
+        let mut connection = db.establish_read_connection();
+
+        let tokens = evm_nonstandard_tokens::table
+            .select(evm_nonstandard_tokens::address)
+            .filter(evm_nonstandard_tokens::chain.eq(db.chain.name))
+            .load::<String>(&mut connection)?;
+
+        let mut pairs = Vec::new();
+
+        for token in tokens.iter() {
+            for holder in self.holders.iter() {
+                pairs.push((token, holder));
+            }
+        }
+
+        let results = join_all(
+            pairs
+                .iter()
+                .map(|(token, holder)| rpc.get_erc20_balance(token, holder, block_number)),
+        )
+        .await;
+
+        let mut balances = Vec::new();
+
+        for ((token, holder), result) in pairs.into_iter().zip(results) {
+            match result? {
+                Some(balance) => balances.push(DatabaseEVMSampledBalance {
+                    chain: db.chain.name.to_string(),
+                    token: token.clone(),
+                    holder: holder.clone(),
+                    block_number,
+                    balance: balance.to_string(),
+                }),
+                None => warn!(
+                    "Unable to sample the balance of {} for holder {}.",
+                    token, holder
+                ),
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(balances.len(), DatabaseEVMSampledBalance::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_sampled_balances::dsl::evm_sampled_balances)
+                .values(&balances[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+}
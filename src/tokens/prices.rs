@@ -0,0 +1,465 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use diesel::{prelude::*, upsert::excluded};
+use field_count::FieldCount;
+use log::*;
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::db::{
+    db::{get_chunks, EVMDatabase},
+    schema::{evm_blocks, evm_erc20_tokens, evm_token_prices_external},
+};
+
+use super::lists::COINGECKO_PLATFORMS;
+
+/// DefiLlama chain prefixes of the supported chains.
+const DEFILLAMA_CHAINS: [(&str, &str); 12] = [
+    ("ethereum", "ethereum"),
+    ("polygon", "polygon"),
+    ("fantom", "fantom"),
+    ("bsc", "bsc"),
+    ("xdai", "gnosis"),
+    ("optimism", "optimism"),
+    ("arbitrum", "arbitrum"),
+    ("arbitrum_nova", "arbitrum-nova"),
+    ("moonbeam", "moonbeam"),
+    ("avax", "avalanche"),
+    ("bittorrent", "bittorrent"),
+    ("celo", "celo"),
+];
+
+/// Tokens priced by a single request, the CoinGecko charts are requested one token at a time.
+const TOKENS_PER_REQUEST: usize = 50;
+
+/// External prices older than a day before the block are too stale to price it.
+const MAX_PRICE_AGE: i64 = 86400;
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_token_prices_external)]
+pub struct DatabaseEVMTokenPriceExternal {
+    pub chain: String,
+    pub token: String,
+    pub source: String,
+    pub timestamp: i64,
+    pub price_usd: f64,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum PriceProvider {
+    Coingecko,
+    Defillama,
+}
+
+impl PriceProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceProvider::Coingecko => "coingecko",
+            PriceProvider::Defillama => "defillama",
+        }
+    }
+
+    /// Id of the chain on the provider API.
+    fn get_chain_id(&self, chain: &str) -> Option<&'static str> {
+        let ids: &[(&str, &str)] = match self {
+            PriceProvider::Coingecko => &COINGECKO_PLATFORMS,
+            PriceProvider::Defillama => &DEFILLAMA_CHAINS,
+        };
+
+        ids.iter()
+            .find(|(_, name)| *name == chain)
+            .map(|(id, _)| *id)
+    }
+
+    /// Pause between requests to stay under the rate limits of the public APIs.
+    fn get_request_interval(&self, api_key: &Option<String>) -> Duration {
+        match (self, api_key) {
+            (PriceProvider::Coingecko, None) => Duration::from_millis(6000),
+            (PriceProvider::Coingecko, Some(_)) => Duration::from_millis(200),
+            (PriceProvider::Defillama, _) => Duration::from_millis(200),
+        }
+    }
+}
+
+/// Fetches the USD prices of the indexed tokens from CoinGecko or DefiLlama into
+/// `evm_token_prices_external`. Each token gets its daily prices of the last `history_days` the
+/// first time it's seen and its current price on every run. The token prices parser falls back to
+/// these prices for the tokens it can't price from the dex swaps.
+///
+/// The tokens unknown to the provider are remembered until the worker restarts, so they aren't
+/// requested on every run.
+pub struct ExternalPricesFetcher {
+    pub provider: PriceProvider,
+    pub api_key: Option<String>,
+    pub history_days: i64,
+    client: Client,
+    unknown_tokens: HashSet<(String, String)>,
+}
+
+impl ExternalPricesFetcher {
+    pub fn new(provider: PriceProvider, api_key: Option<String>, history_days: i64) -> Self {
+        Self {
+            provider,
+            api_key,
+            history_days,
+            client: Client::new(),
+            unknown_tokens: HashSet::new(),
+        }
+    }
+
+    pub async fn run(&mut self, db: &EVMDatabase) -> Result<()> {
+        let (new_tokens, priced_tokens) = self.get_tokens(db)?;
+
+        info!(
+            "Fetching {} prices of {} new and {} priced tokens.",
+            self.provider.name(),
+            new_tokens.len(),
+            priced_tokens.len()
+        );
+
+        for chunk in new_tokens.chunks(TOKENS_PER_REQUEST) {
+            let prices = self.fetch_history(chunk).await?;
+
+            for token in chunk {
+                if !prices
+                    .iter()
+                    .any(|price| price.chain == token.0 && price.token == token.1)
+                {
+                    self.unknown_tokens.insert(token.clone());
+                }
+            }
+
+            self.store(db, &prices)?;
+        }
+
+        for chunk in priced_tokens.chunks(TOKENS_PER_REQUEST) {
+            let prices = self.fetch_current(chunk).await?;
+
+            self.store(db, &prices)?;
+        }
+
+        Ok(())
+    }
+
+    /// Chain and address of the tokens without prices of the provider and of the tokens with them.
+    fn get_tokens(
+        &self,
+        db: &EVMDatabase,
+    ) -> Result<(Vec<(String, String)>, Vec<(String, String)>)> {
+        let mut connection = db.establish_read_connection();
+
+        let tokens = evm_erc20_tokens::table
+            .select((evm_erc20_tokens::chain, evm_erc20_tokens::address))
+            .filter(evm_erc20_tokens::decimals.is_not_null())
+            .load::<(String, String)>(&mut connection)?;
+
+        let priced: HashSet<(String, String)> = evm_token_prices_external::table
+            .select((
+                evm_token_prices_external::chain,
+                evm_token_prices_external::token,
+            ))
+            .filter(evm_token_prices_external::source.eq(self.provider.name()))
+            .distinct()
+            .load::<(String, String)>(&mut connection)?
+            .into_iter()
+            .collect();
+
+        let (priced_tokens, new_tokens): (Vec<(String, String)>, Vec<(String, String)>) = tokens
+            .into_iter()
+            .filter(|(chain, _)| self.provider.get_chain_id(chain).is_some())
+            .filter(|token| !self.unknown_tokens.contains(token))
+            .partition(|token| priced.contains(token));
+
+        Ok((new_tokens, priced_tokens))
+    }
+
+    async fn fetch_current(
+        &self,
+        tokens: &[(String, String)],
+    ) -> Result<Vec<DatabaseEVMTokenPriceExternal>> {
+        let mut prices = Vec::new();
+
+        match self.provider {
+            PriceProvider::Coingecko => {
+                for (chain, addresses) in group_by_chain(tokens) {
+                    let platform = self.provider.get_chain_id(&chain).unwrap();
+
+                    let response = self
+                        .get(&format!(
+                            "/simple/token_price/{}?contract_addresses={}&vs_currencies=usd&include_last_updated_at=true",
+                            platform,
+                            addresses.join(",")
+                        ))
+                        .await?;
+
+                    for address in addresses {
+                        let price = &response[&address];
+
+                        match (price["usd"].as_f64(), price["last_updated_at"].as_i64()) {
+                            (Some(price_usd), Some(timestamp)) => {
+                                prices.push(self.get_price(&chain, &address, timestamp, price_usd))
+                            }
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+            PriceProvider::Defillama => {
+                let response = self
+                    .get(&format!(
+                        "/prices/current/{}",
+                        self.get_defillama_coins(tokens).join(",")
+                    ))
+                    .await?;
+
+                for (chain, address) in tokens {
+                    let coin =
+                        format!("{}:{}", self.provider.get_chain_id(chain).unwrap(), address);
+
+                    let price = &response["coins"][&coin];
+
+                    match (price["price"].as_f64(), price["timestamp"].as_i64()) {
+                        (Some(price_usd), Some(timestamp)) => {
+                            prices.push(self.get_price(chain, address, timestamp, price_usd))
+                        }
+                        _ => continue,
+                    }
+                }
+            }
+        }
+
+        Ok(prices)
+    }
+
+    async fn fetch_history(
+        &self,
+        tokens: &[(String, String)],
+    ) -> Result<Vec<DatabaseEVMTokenPriceExternal>> {
+        let mut prices = Vec::new();
+
+        match self.provider {
+            PriceProvider::Coingecko => {
+                for (chain, address) in tokens {
+                    let platform = self.provider.get_chain_id(chain).unwrap();
+
+                    // Ranges longer than 90 days return a price per day.
+                    let response = match self
+                        .get(&format!(
+                            "/coins/{}/contract/{}/market_chart?vs_currency=usd&days={}",
+                            platform,
+                            address,
+                            self.history_days.max(91)
+                        ))
+                        .await
+                    {
+                        Ok(response) => response,
+                        // Unknown contracts are answered with a 404.
+                        Err(_) => continue,
+                    };
+
+                    let points = match response["prices"].as_array() {
+                        Some(points) => points,
+                        None => continue,
+                    };
+
+                    for point in points {
+                        match (point[0].as_i64(), point[1].as_f64()) {
+                            (Some(timestamp), Some(price_usd)) => prices.push(self.get_price(
+                                chain,
+                                address,
+                                timestamp / 1000,
+                                price_usd,
+                            )),
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+            PriceProvider::Defillama => {
+                let start = get_timestamp() - self.history_days * 86400;
+
+                let response = self
+                    .get(&format!(
+                        "/chart/{}?start={}&span={}&period=1d",
+                        self.get_defillama_coins(tokens).join(","),
+                        start,
+                        self.history_days + 1
+                    ))
+                    .await?;
+
+                for (chain, address) in tokens {
+                    let coin =
+                        format!("{}:{}", self.provider.get_chain_id(chain).unwrap(), address);
+
+                    let points = match response["coins"][&coin]["prices"].as_array() {
+                        Some(points) => points,
+                        None => continue,
+                    };
+
+                    for point in points {
+                        match (point["timestamp"].as_i64(), point["price"].as_f64()) {
+                            (Some(timestamp), Some(price_usd)) => {
+                                prices.push(self.get_price(chain, address, timestamp, price_usd))
+                            }
+                            _ => continue,
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(prices)
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        let url = match (self.provider, &self.api_key) {
+            (PriceProvider::Coingecko, None) => {
+                format!("https://api.coingecko.com/api/v3{}", path)
+            }
+            (PriceProvider::Coingecko, Some(_)) => {
+                format!("https://pro-api.coingecko.com/api/v3{}", path)
+            }
+            (PriceProvider::Defillama, _) => format!("https://coins.llama.fi{}", path),
+        };
+
+        let mut request = self.client.get(url);
+
+        match (self.provider, &self.api_key) {
+            (PriceProvider::Coingecko, Some(api_key)) => {
+                request = request.header("x-cg-pro-api-key", api_key)
+            }
+            _ => (),
+        }
+
+        let response = request.send().await;
+
+        tokio::time::sleep(self.provider.get_request_interval(&self.api_key)).await;
+
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => bail!(
+                "Unable to fetch the {} prices: {}",
+                self.provider.name(),
+                err
+            ),
+        };
+
+        if !response.status().is_success() {
+            bail!(
+                "Unable to fetch the {} prices: {}",
+                self.provider.name(),
+                response.status()
+            );
+        }
+
+        Ok(response.json::<Value>().await?)
+    }
+
+    fn get_defillama_coins(&self, tokens: &[(String, String)]) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|(chain, address)| {
+                format!("{}:{}", self.provider.get_chain_id(chain).unwrap(), address)
+            })
+            .collect()
+    }
+
+    fn get_price(
+        &self,
+        chain: &str,
+        address: &str,
+        timestamp: i64,
+        price_usd: f64,
+    ) -> DatabaseEVMTokenPriceExternal {
+        DatabaseEVMTokenPriceExternal {
+            chain: chain.to_string(),
+            token: address.to_string(),
+            source: self.provider.name().to_string(),
+            timestamp,
+            price_usd,
+        }
+    }
+
+    fn store(&self, db: &EVMDatabase, prices: &Vec<DatabaseEVMTokenPriceExternal>) -> Result<()> {
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(prices.len(), DatabaseEVMTokenPriceExternal::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_token_prices_external::dsl::evm_token_prices_external)
+                .values(&prices[start..end])
+                .on_conflict((
+                    evm_token_prices_external::chain,
+                    evm_token_prices_external::token,
+                    evm_token_prices_external::source,
+                    evm_token_prices_external::timestamp,
+                ))
+                .do_update()
+                .set(
+                    evm_token_prices_external::price_usd
+                        .eq(excluded(evm_token_prices_external::price_usd)),
+                )
+                .execute(&mut connection)?;
+        }
+
+        info!(
+            "Inserted {} {} token prices to the database.",
+            prices.len(),
+            self.provider.name()
+        );
+
+        Ok(())
+    }
+}
+
+/// Latest external USD price of a token at most a day older than the block, from any provider.
+pub fn get_external_usd_price(
+    connection: &mut PgConnection,
+    chain: &String,
+    token: &String,
+    block_number: i64,
+) -> Option<f64> {
+    let timestamp = evm_blocks::table
+        .select(evm_blocks::timestamp)
+        .filter(evm_blocks::chain.eq(chain))
+        .filter(evm_blocks::number.eq(block_number))
+        .first::<String>(connection)
+        .ok()?
+        .parse::<i64>()
+        .ok()?;
+
+    evm_token_prices_external::table
+        .select(evm_token_prices_external::price_usd)
+        .filter(evm_token_prices_external::chain.eq(chain))
+        .filter(evm_token_prices_external::token.eq(token))
+        .filter(evm_token_prices_external::timestamp.le(timestamp))
+        .filter(evm_token_prices_external::timestamp.gt(timestamp - MAX_PRICE_AGE))
+        .order(evm_token_prices_external::timestamp.desc())
+        .first::<f64>(connection)
+        .ok()
+}
+
+fn group_by_chain(tokens: &[(String, String)]) -> HashMap<String, Vec<String>> {
+    let mut chains: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (chain, address) in tokens {
+        chains
+            .entry(chain.clone())
+            .or_default()
+            .push(address.clone());
+    }
+
+    chains
+}
+
+fn get_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get the current time")
+        .as_secs() as i64
+}
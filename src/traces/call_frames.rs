@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde_json::Value;
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        models::models::DatabaseEVMTransaction,
+        schema::{evm_call_frames, evm_flagged_activity},
+    },
+    rpc::rpc::EVMRpc,
+};
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_call_frames)]
+pub struct DatabaseEVMCallFrame {
+    pub hash: String,
+    pub trace_address: String,
+    pub chain: String,
+    pub block_number: i64,
+    pub depth: i64,
+    pub call_type: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub value: String,
+    pub selector: String,
+    pub gas: String,
+    pub gas_used: String,
+    pub error: Option<String>,
+}
+
+/// Stores the call tree of the transactions sent to the configured contracts, and of the ones
+/// flagged by the screening, as one row per frame in `evm_call_frames`. Frames are keyed by
+/// their trace address, the position of each call within its parent joined by dots, with the
+/// top level call as an empty trace address.
+#[derive(Debug, Clone)]
+pub struct CallTreeIndexer {
+    pub contracts: HashSet<String>,
+    pub flagged: bool,
+}
+
+impl CallTreeIndexer {
+    pub fn new(contracts: &Vec<String>, flagged: bool) -> Self {
+        Self {
+            contracts: contracts
+                .iter()
+                .map(|contract| contract.to_lowercase())
+                .collect(),
+            flagged,
+        }
+    }
+
+    pub async fn process(
+        &self,
+        rpc: &EVMRpc,
+        db: &EVMDatabase,
+        transactions: &Vec<DatabaseEVMTransaction>,
+    ) -> Result<()> {
+        let mut hashes: HashSet<String> = transactions
+            .iter()
+            .filter(|transaction| self.contracts.contains(&transaction.to_address))
+            .map(|transaction| transaction.hash.clone())
+            .collect();
+
+        if self.flagged {
+            hashes.extend(self.get_flagged_transactions(db, transactions)?);
+        }
+
+        let transactions: Vec<&DatabaseEVMTransaction> = transactions
+            .iter()
+            .filter(|transaction| hashes.contains(&transaction.hash))
+            .collect();
+
+        if transactions.len() == 0 {
+            return Ok(());
+        }
+
+        let mut work = vec![];
+
+        for transaction in transactions.iter() {
+            work.push(rpc.get_call_tree(&transaction.hash))
+        }
+
+        let results = join_all(work).await;
+
+        let mut frames = Vec::new();
+
+        for (transaction, result) in transactions.into_iter().zip(results) {
+            match result? {
+                Some(trace) => get_call_frames(
+                    db.chain.name,
+                    transaction,
+                    &trace,
+                    String::new(),
+                    0,
+                    &mut frames,
+                ),
+                None => warn!("Unable to trace the call tree of {}.", transaction.hash),
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(frames.len(), DatabaseEVMCallFrame::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_call_frames::dsl::evm_call_frames)
+                .values(&frames[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        info!(
+            "Inserted: call frames ({}) for chain {}",
+            frames.len(),
+            db.chain.name
+        );
+
+        Ok(())
+    }
+
+    fn get_flagged_transactions(
+        &self,
+        db: &EVMDatabase,
+        transactions: &Vec<DatabaseEVMTransaction>,
+    ) -> Result<Vec<String>> {
+        let mut connection = db.establish_connection();
+
+        let hashes: Vec<String> = transactions
+            .iter()
+            .map(|transaction| transaction.hash.clone())
+            .collect();
+
+        let flagged = evm_flagged_activity::table
+            .select(evm_flagged_activity::hash)
+            .filter(evm_flagged_activity::hash.eq_any(hashes))
+            .distinct()
+            .load::<String>(&mut connection)?;
+
+        Ok(flagged)
+    }
+}
+
+/// Flattens a call tracer frame and its nested calls depth first.
+pub fn get_call_frames(
+    chain: &str,
+    transaction: &DatabaseEVMTransaction,
+    frame: &Value,
+    trace_address: String,
+    depth: i64,
+    frames: &mut Vec<DatabaseEVMCallFrame>,
+) {
+    let input = frame["input"].as_str().unwrap_or("0x");
+
+    let selector = match input.len() >= 10 {
+        true => input[..10].to_lowercase(),
+        false => String::new(),
+    };
+
+    frames.push(DatabaseEVMCallFrame {
+        hash: transaction.hash.clone(),
+        trace_address: trace_address.clone(),
+        chain: chain.to_string(),
+        block_number: transaction.block_number,
+        depth,
+        call_type: frame["type"].as_str().unwrap_or_default().to_lowercase(),
+        from_address: frame["from"].as_str().unwrap_or_default().to_lowercase(),
+        to_address: frame["to"].as_str().unwrap_or_default().to_lowercase(),
+        value: frame["value"].as_str().unwrap_or("0x0").to_string(),
+        selector,
+        gas: frame["gas"].as_str().unwrap_or("0x0").to_string(),
+        gas_used: frame["gasUsed"].as_str().unwrap_or("0x0").to_string(),
+        error: frame["error"].as_str().map(|error| error.to_string()),
+    });
+
+    let calls = match frame["calls"].as_array() {
+        Some(calls) => calls,
+        None => return,
+    };
+
+    for (index, call) in calls.iter().enumerate() {
+        let child_address = match trace_address.is_empty() {
+            true => index.to_string(),
+            false => format!("{}.{}", trace_address, index),
+        };
+
+        get_call_frames(chain, transaction, call, child_address, depth + 1, frames);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod call_frames;
+pub mod native_transfers;
+pub mod revert_reasons;
+pub mod state_diffs;
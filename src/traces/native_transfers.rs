@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use diesel::prelude::*;
+use ethers::types::{H160, U256};
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde_json::Value;
+
+use crate::{
+    db::{
+        db::{get_block_reward, get_chunks, EVMDatabase},
+        models::models::{DatabaseEVMBlock, DatabaseEVMTransaction},
+        schema::evm_native_transfers,
+    },
+    rpc::rpc::EVMRpc,
+};
+
+/// Trace address of the block reward, stored with the block hash as its hash.
+pub const BLOCK_REWARD_TRACE_ADDRESS: &str = "reward";
+
+/// Transaction index of the block reward, which isn't part of any transaction.
+pub const BLOCK_REWARD_TRANSACTION_INDEX: i64 = -1;
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_native_transfers)]
+pub struct DatabaseEVMNativeTransfer {
+    pub hash: String,
+    pub trace_address: String,
+    pub chain: String,
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub kind: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub value: String,
+}
+
+/// Stores every movement of the native token in `evm_native_transfers`, the value of the
+/// transactions and of their internal calls, creations and selfdestructs from the call tracer,
+/// and the proof of work block rewards. Values moved by calls that reverted are skipped.
+#[derive(Debug, Clone)]
+pub struct NativeTransferIndexer {}
+
+impl NativeTransferIndexer {
+    pub async fn process(
+        &self,
+        rpc: &EVMRpc,
+        db: &EVMDatabase,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+    ) -> Result<()> {
+        let mut blocks_transactions: HashMap<i64, Vec<&DatabaseEVMTransaction>> = HashMap::new();
+
+        for transaction in transactions {
+            blocks_transactions
+                .entry(transaction.block_number)
+                .or_default()
+                .push(transaction);
+        }
+
+        let mut transfers: Vec<DatabaseEVMNativeTransfer> = blocks
+            .iter()
+            .filter_map(|block| get_block_reward_transfer(db.chain.name, block))
+            .collect();
+
+        let traced_blocks: Vec<i64> = blocks
+            .iter()
+            .filter(|block| block.transactions > 0)
+            .map(|block| block.number)
+            .collect();
+
+        let mut work = vec![];
+
+        for block_number in traced_blocks.iter() {
+            work.push(rpc.get_block_call_trees(block_number))
+        }
+
+        let results = join_all(work).await;
+
+        for (block_number, result) in traced_blocks.into_iter().zip(results) {
+            let traces = match result? {
+                Some(traces) => traces,
+                None => {
+                    warn!("Unable to trace the call trees of block {}.", block_number);
+                    continue;
+                }
+            };
+
+            let mut block_transactions = match blocks_transactions.remove(&block_number) {
+                Some(block_transactions) => block_transactions,
+                None => continue,
+            };
+
+            block_transactions.sort_by_key(|transaction| transaction.transaction_index);
+
+            if traces.len() != block_transactions.len() {
+                warn!(
+                    "Got {} traces for the {} transactions of block {}.",
+                    traces.len(),
+                    block_transactions.len(),
+                    block_number
+                );
+                continue;
+            }
+
+            for (transaction, trace) in block_transactions.into_iter().zip(traces) {
+                get_native_transfers(
+                    db.chain.name,
+                    transaction,
+                    &trace,
+                    String::new(),
+                    &mut transfers,
+                );
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(transfers.len(), DatabaseEVMNativeTransfer::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_native_transfers::dsl::evm_native_transfers)
+                .values(&transfers[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        info!(
+            "Inserted: native transfers ({}) for chain {}",
+            transfers.len(),
+            db.chain.name
+        );
+
+        Ok(())
+    }
+}
+
+pub fn get_block_reward_transfer(
+    chain: &str,
+    block: &DatabaseEVMBlock,
+) -> Option<DatabaseEVMNativeTransfer> {
+    let reward = get_block_reward(chain, block);
+
+    if reward.is_zero() {
+        return None;
+    }
+
+    Some(DatabaseEVMNativeTransfer {
+        hash: block.block_hash.clone(),
+        trace_address: BLOCK_REWARD_TRACE_ADDRESS.to_string(),
+        chain: chain.to_string(),
+        block_number: block.number,
+        transaction_index: BLOCK_REWARD_TRANSACTION_INDEX,
+        kind: "block_reward".to_string(),
+        from_address: format!("{:?}", H160::zero()),
+        to_address: block.miner.clone(),
+        value: reward.to_string(),
+    })
+}
+
+/// Walks a call tracer frame depth first. A frame with an error reverted together with all its
+/// nested calls, so none of their values moved.
+pub fn get_native_transfers(
+    chain: &str,
+    transaction: &DatabaseEVMTransaction,
+    frame: &Value,
+    trace_address: String,
+    transfers: &mut Vec<DatabaseEVMNativeTransfer>,
+) {
+    if frame.get("error").is_some() {
+        return;
+    }
+
+    let call_type = frame["type"].as_str().unwrap_or_default().to_lowercase();
+
+    let value = match frame["value"].as_str() {
+        Some(value) => U256::from_str_radix(value.trim_start_matches("0x"), 16).unwrap_or_default(),
+        None => U256::zero(),
+    };
+
+    // Delegate and static calls can't move value, the value of a delegate call is the one of
+    // its parent.
+    if !value.is_zero() && call_type != "delegatecall" && call_type != "staticcall" {
+        let kind = match trace_address.is_empty() {
+            true => "transaction".to_string(),
+            false => call_type,
+        };
+
+        transfers.push(DatabaseEVMNativeTransfer {
+            hash: transaction.hash.clone(),
+            trace_address: trace_address.clone(),
+            chain: chain.to_string(),
+            block_number: transaction.block_number,
+            transaction_index: transaction.transaction_index,
+            kind,
+            from_address: frame["from"].as_str().unwrap_or_default().to_lowercase(),
+            to_address: frame["to"].as_str().unwrap_or_default().to_lowercase(),
+            value: value.to_string(),
+        });
+    }
+
+    let calls = match frame["calls"].as_array() {
+        Some(calls) => calls,
+        None => return,
+    };
+
+    for (index, call) in calls.iter().enumerate() {
+        let child_address = match trace_address.is_empty() {
+            true => index.to_string(),
+            false => format!("{}.{}", trace_address, index),
+        };
+
+        get_native_transfers(chain, transaction, call, child_address, transfers);
+    }
+}
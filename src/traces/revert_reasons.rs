@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use diesel::prelude::*;
+use ethers::{
+    abi::{decode, Contract, ParamType},
+    types::Bytes,
+};
+use futures::future::join_all;
+
+use crate::{
+    calls::sampler::get_token_json,
+    db::{
+        db::EVMDatabase,
+        models::models::{DatabaseEVMTransaction, DatabaseEVMTransactionReceipt},
+        schema::{evm_abis, evm_transactions_receipts},
+    },
+    rpc::rpc::EVMRpc,
+};
+
+/// Selector of `Error(string)`, used by `require` and `revert` with a message.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of `Panic(uint256)`, used by failed assertions and arithmetic errors.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Reason stored for the transactions that reverted without data.
+pub const EMPTY_REVERT_REASON: &str = "execution reverted";
+
+/// Extracts the revert reason of the failed transactions and stores it on their receipt. The
+/// transactions are traced with the call tracer, which returns the exact revert data, or replayed
+/// with `eth_call` at their parent block when the node doesn't support tracing. Custom errors are
+/// decoded with the ABIs of the fetched contracts.
+#[derive(Debug, Clone)]
+pub struct RevertReasonIndexer {}
+
+impl RevertReasonIndexer {
+    pub async fn process(
+        &self,
+        rpc: &EVMRpc,
+        db: &EVMDatabase,
+        transactions: &Vec<DatabaseEVMTransaction>,
+        receipts: &Vec<DatabaseEVMTransactionReceipt>,
+    ) -> Result<()> {
+        let transactions: HashMap<&String, &DatabaseEVMTransaction> = transactions
+            .iter()
+            .map(|transaction| (&transaction.hash, transaction))
+            .collect();
+
+        let failed: Vec<&DatabaseEVMTransaction> = receipts
+            .iter()
+            .filter(|receipt| receipt.status == "0")
+            .filter_map(|receipt| transactions.get(&receipt.hash).copied())
+            .collect();
+
+        if failed.len() == 0 {
+            return Ok(());
+        }
+
+        let mut work = vec![];
+
+        for transaction in failed.iter() {
+            work.push(self.get_revert(rpc, transaction))
+        }
+
+        let results = join_all(work).await;
+
+        let abis = self.get_abis(db, &failed)?;
+
+        let mut connection = db.establish_connection();
+
+        for (transaction, result) in failed.into_iter().zip(results) {
+            let (data, error) = result?;
+
+            let reason = match &data {
+                Some(data) if data.len() > 0 => {
+                    get_revert_reason(data, abis.get(&transaction.to_address))
+                }
+                _ => error.unwrap_or(EMPTY_REVERT_REASON.to_string()),
+            };
+
+            diesel::update(evm_transactions_receipts::table.find(&transaction.hash))
+                .set(evm_transactions_receipts::revert_reason.eq(reason))
+                .execute(&mut connection)?;
+        }
+
+        Ok(())
+    }
+
+    /// Revert data of the transaction, with the error of the trace when it has no data.
+    async fn get_revert(
+        &self,
+        rpc: &EVMRpc,
+        transaction: &DatabaseEVMTransaction,
+    ) -> Result<(Option<Bytes>, Option<String>)> {
+        match rpc.get_call_tree(&transaction.hash).await? {
+            Some(trace) => {
+                let data = match trace["output"].as_str() {
+                    Some(output) => hex::decode(output.trim_start_matches("0x"))
+                        .ok()
+                        .map(Bytes::from),
+                    None => None,
+                };
+
+                let error = trace["error"].as_str().map(|error| error.to_string());
+
+                Ok((data, error))
+            }
+            None => {
+                let data = rpc
+                    .get_revert_data(transaction, transaction.block_number - 1)
+                    .await?;
+
+                Ok((data, None))
+            }
+        }
+    }
+
+    fn get_abis(
+        &self,
+        db: &EVMDatabase,
+        transactions: &Vec<&DatabaseEVMTransaction>,
+    ) -> Result<HashMap<String, Contract>> {
+        let mut connection = db.establish_connection();
+
+        let contracts: Vec<String> = transactions
+            .iter()
+            .map(|transaction| transaction.to_address.clone())
+            .collect();
+
+        let abis = evm_abis::table
+            .select((evm_abis::contract, evm_abis::abi))
+            .filter(evm_abis::chain.eq(db.chain.name))
+            .filter(evm_abis::contract.eq_any(contracts))
+            .load::<(String, Option<String>)>(&mut connection)?;
+
+        Ok(abis
+            .into_iter()
+            .filter_map(|(contract, abi)| {
+                let abi: Contract = serde_json::from_str(&abi?).ok()?;
+
+                Some((contract, abi))
+            })
+            .collect())
+    }
+}
+
+/// Decodes `Error(string)` into its message, `Panic(uint256)` into `Panic(<code>)` and custom
+/// errors of the ABI into their name and arguments. Unknown errors are kept as hex.
+pub fn get_revert_reason(data: &Bytes, abi: Option<&Contract>) -> String {
+    let raw = format!("0x{}", hex::encode(data));
+
+    if data.len() < 4 {
+        return raw;
+    }
+
+    let (selector, params) = data.split_at(4);
+
+    if selector == ERROR_SELECTOR {
+        return match decode(&[ParamType::String], params) {
+            Ok(tokens) => match tokens[0].clone().into_string() {
+                Some(message) => message,
+                None => raw,
+            },
+            Err(_) => raw,
+        };
+    }
+
+    if selector == PANIC_SELECTOR {
+        return match decode(&[ParamType::Uint(256)], params) {
+            Ok(tokens) => match tokens[0].clone().into_uint() {
+                Some(code) => format!("Panic(0x{:x})", code),
+                None => raw,
+            },
+            Err(_) => raw,
+        };
+    }
+
+    let abi = match abi {
+        Some(abi) => abi,
+        None => return raw,
+    };
+
+    for error in abi.errors() {
+        if error.signature().as_bytes()[..4] != *selector {
+            continue;
+        }
+
+        match error.decode(params) {
+            Ok(tokens) => {
+                let args: Vec<String> = tokens
+                    .into_iter()
+                    .map(|token| get_token_json(token).to_string())
+                    .collect();
+
+                return format!("{}({})", error.name, args.join(", "));
+            }
+            Err(_) => continue,
+        }
+    }
+
+    raw
+}
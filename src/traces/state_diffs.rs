@@ -0,0 +1,227 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::Result;
+use diesel::prelude::*;
+use field_count::FieldCount;
+use futures::future::join_all;
+use log::*;
+use serde_json::Value;
+
+use crate::{
+    db::{
+        db::{get_chunks, EVMDatabase},
+        models::models::{DatabaseEVMBlock, DatabaseEVMTransaction},
+        schema::evm_state_diffs,
+    },
+    rpc::rpc::EVMRpc,
+};
+
+/// Account fields tracked by the prestate tracer, storage slots are handled separately.
+pub const ACCOUNT_FIELDS: [&str; 3] = ["balance", "nonce", "code"];
+
+/// Value of a storage slot omitted from the post state because it was cleared.
+pub const EMPTY_SLOT: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Selectable, Queryable, Insertable, Debug, Clone, FieldCount)]
+#[diesel(table_name = evm_state_diffs)]
+pub struct DatabaseEVMStateDiff {
+    pub hash: String,
+    pub chain: String,
+    pub block_number: i64,
+    pub transaction_index: i64,
+    pub address: String,
+    pub kind: String,
+    pub slot: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Captures the balance, nonce, code and storage changes of every indexed transaction with
+/// `debug_traceBlockByNumber` and the prestate tracer in diff mode, so the history of an account
+/// can be rebuilt from `evm_state_diffs` without an archive node. Tracing old blocks still needs
+/// a node that keeps their state.
+#[derive(Debug, Clone)]
+pub struct StateDiffIndexer {}
+
+impl StateDiffIndexer {
+    pub async fn process(
+        &self,
+        rpc: &EVMRpc,
+        db: &EVMDatabase,
+        blocks: &Vec<DatabaseEVMBlock>,
+        transactions: &Vec<DatabaseEVMTransaction>,
+    ) -> Result<()> {
+        let mut blocks_transactions: HashMap<i64, Vec<&DatabaseEVMTransaction>> = HashMap::new();
+
+        for transaction in transactions {
+            blocks_transactions
+                .entry(transaction.block_number)
+                .or_default()
+                .push(transaction);
+        }
+
+        let blocks: Vec<i64> = blocks
+            .iter()
+            .filter(|block| block.transactions > 0)
+            .map(|block| block.number)
+            .collect();
+
+        let mut work = vec![];
+
+        for block_number in blocks.iter() {
+            work.push(rpc.get_block_state_diffs(block_number))
+        }
+
+        let results = join_all(work).await;
+
+        let mut diffs = Vec::new();
+
+        for (block_number, result) in blocks.into_iter().zip(results) {
+            let traces = match result? {
+                Some(traces) => traces,
+                None => {
+                    warn!("Unable to trace the state diffs of block {}.", block_number);
+                    continue;
+                }
+            };
+
+            let mut block_transactions = match blocks_transactions.remove(&block_number) {
+                Some(block_transactions) => block_transactions,
+                None => continue,
+            };
+
+            block_transactions.sort_by_key(|transaction| transaction.transaction_index);
+
+            if traces.len() != block_transactions.len() {
+                warn!(
+                    "Got {} traces for the {} transactions of block {}.",
+                    traces.len(),
+                    block_transactions.len(),
+                    block_number
+                );
+                continue;
+            }
+
+            for (transaction, trace) in block_transactions.into_iter().zip(traces) {
+                diffs.append(&mut get_state_diffs(db.chain.name, transaction, &trace));
+            }
+        }
+
+        let mut connection = db.establish_connection();
+
+        let chunks = get_chunks(diffs.len(), DatabaseEVMStateDiff::field_count());
+
+        for (start, end) in chunks {
+            diesel::insert_into(evm_state_diffs::dsl::evm_state_diffs)
+                .values(&diffs[start..end])
+                .on_conflict_do_nothing()
+                .execute(&mut connection)?;
+        }
+
+        info!(
+            "Inserted: state diffs ({}) for chain {}",
+            diffs.len(),
+            db.chain.name
+        );
+
+        Ok(())
+    }
+}
+
+/// Compares the `pre` and `post` states of a diff mode trace. Accounts only in `pre` were
+/// destroyed and storage slots missing from the `post` state of an account were cleared.
+pub fn get_state_diffs(
+    chain: &str,
+    transaction: &DatabaseEVMTransaction,
+    trace: &Value,
+) -> Vec<DatabaseEVMStateDiff> {
+    let empty = serde_json::Map::new();
+
+    let pre = trace["pre"].as_object().unwrap_or(&empty);
+    let post = trace["post"].as_object().unwrap_or(&empty);
+
+    let addresses: BTreeSet<&String> = pre.keys().chain(post.keys()).collect();
+
+    let mut diffs = Vec::new();
+
+    for address in addresses {
+        let before = pre.get(address).cloned().unwrap_or(Value::Null);
+
+        let after = post.get(address);
+
+        let mut push = |kind: &str, slot: String, old: Option<String>, new: Option<String>| {
+            if old == new {
+                return;
+            }
+
+            diffs.push(DatabaseEVMStateDiff {
+                hash: transaction.hash.clone(),
+                chain: chain.to_string(),
+                block_number: transaction.block_number,
+                transaction_index: transaction.transaction_index,
+                address: address.to_lowercase(),
+                kind: kind.to_string(),
+                slot,
+                before: old,
+                after: new,
+            })
+        };
+
+        for field in ACCOUNT_FIELDS {
+            let old = get_string(&before[field]);
+
+            // Fields are only in the post state when they changed.
+            let new = match after {
+                Some(after) => match get_string(&after[field]) {
+                    Some(new) => Some(new),
+                    None => continue,
+                },
+                None => None,
+            };
+
+            push(field, String::new(), old, new);
+        }
+
+        let empty_storage = serde_json::Map::new();
+
+        let old_storage = before["storage"].as_object().unwrap_or(&empty_storage);
+
+        let new_storage = match after {
+            Some(after) => after["storage"].as_object(),
+            None => None,
+        };
+
+        let slots: BTreeSet<&String> = old_storage
+            .keys()
+            .chain(new_storage.iter().flat_map(|storage| storage.keys()))
+            .collect();
+
+        for slot in slots {
+            let old = match old_storage.get(slot) {
+                Some(value) => get_string(value),
+                None => None,
+            };
+
+            let new = match after {
+                Some(_) => match new_storage.and_then(|storage| storage.get(slot)) {
+                    Some(value) => get_string(value),
+                    None => Some(EMPTY_SLOT.to_string()),
+                },
+                None => None,
+            };
+
+            push("storage", slot.to_lowercase(), old, new);
+        }
+    }
+
+    diffs
+}
+
+/// Balances are returned as hex or decimal depending on the client, values are kept as returned.
+fn get_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}